@@ -0,0 +1,294 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
+use serde_json::{Map, Value};
+
+use super::report::{REPORT_KEY_PHASES, REPORT_KEY_TOTAL, REPORT_QUANTILE_P99, REPORT_TOTAL_RPS};
+
+pub const COMMAND: &str = "compare";
+
+const COMPARE_ARG_BASELINE: &str = "baseline";
+const COMPARE_ARG_CURRENT: &str = "current";
+const COMPARE_ARG_MAX_P99_INCREASE: &str = "max-p99-increase";
+const COMPARE_ARG_MAX_RPS_DECREASE: &str = "max-rps-decrease";
+const COMPARE_ARG_PHASE: &str = "phase";
+
+struct CompareArgs {
+    baseline: PathBuf,
+    current: PathBuf,
+    max_p99_increase: Option<f64>,
+    max_rps_decrease: Option<f64>,
+    phases: Vec<String>,
+}
+
+impl CompareArgs {
+    fn parse(args: &ArgMatches) -> anyhow::Result<Self> {
+        let baseline = args.get_one::<PathBuf>(COMPARE_ARG_BASELINE).unwrap();
+        let current = args.get_one::<PathBuf>(COMPARE_ARG_CURRENT).unwrap();
+
+        let max_p99_increase = match args.get_one::<String>(COMPARE_ARG_MAX_P99_INCREASE) {
+            Some(v) => Some(
+                parse_percentage(v)
+                    .context(format!("invalid {COMPARE_ARG_MAX_P99_INCREASE} value {v}"))?,
+            ),
+            None => None,
+        };
+        let max_rps_decrease = match args.get_one::<String>(COMPARE_ARG_MAX_RPS_DECREASE) {
+            Some(v) => Some(
+                parse_percentage(v)
+                    .context(format!("invalid {COMPARE_ARG_MAX_RPS_DECREASE} value {v}"))?,
+            ),
+            None => None,
+        };
+
+        let phases = args
+            .get_many::<String>(COMPARE_ARG_PHASE)
+            .map(|v| v.cloned().collect())
+            .unwrap_or_default();
+
+        Ok(CompareArgs {
+            baseline: baseline.clone(),
+            current: current.clone(),
+            max_p99_increase,
+            max_rps_decrease,
+            phases,
+        })
+    }
+}
+
+/// Parse percentage value like "10" or "10%"
+fn parse_percentage(s: &str) -> anyhow::Result<f64> {
+    let s = s.trim();
+    let s = s.strip_suffix('%').unwrap_or(s);
+    let v = f64::from_str(s.trim()).map_err(|e| anyhow!("invalid float value: {e}"))?;
+    if !v.is_finite() || v < 0.0 {
+        return Err(anyhow!("the percentage should be a non-negative value"));
+    }
+    Ok(v)
+}
+
+fn load_report(path: &Path) -> anyhow::Result<Map<String, Value>> {
+    let file = File::open(path)
+        .map_err(|e| anyhow!("failed to open report file {}: {e}", path.display()))?;
+    let value: Value = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| anyhow!("invalid json report file {}: {e}", path.display()))?;
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => Err(anyhow!(
+            "invalid report file {}: root value should be a map",
+            path.display()
+        )),
+    }
+}
+
+fn get_total_rps(report: &Map<String, Value>) -> Option<f64> {
+    report
+        .get(REPORT_KEY_TOTAL)
+        .and_then(|v| v.get(REPORT_TOTAL_RPS))
+        .and_then(|v| v.as_f64())
+}
+
+fn get_phases(report: &Map<String, Value>) -> Option<&Map<String, Value>> {
+    report.get(REPORT_KEY_PHASES).and_then(|v| v.as_object())
+}
+
+fn get_phase_p99(phase: &Value) -> Option<u64> {
+    phase.get(REPORT_QUANTILE_P99).and_then(|v| v.as_u64())
+}
+
+/// Get the change percentage from the baseline value to the current value,
+/// or None if the baseline value is zero
+fn change_percentage(baseline: f64, current: f64) -> Option<f64> {
+    if baseline == 0.0 {
+        None
+    } else {
+        Some((current - baseline) * 100.0 / baseline)
+    }
+}
+
+fn change_percentage_str(change: Option<f64>) -> String {
+    match change {
+        Some(v) => format!("{v:+.2}%"),
+        None => "n/a".to_string(),
+    }
+}
+
+pub fn command() -> Command {
+    Command::new(COMMAND)
+        .about("Compare two json reports and check for regressions")
+        .arg(
+            Arg::new(COMPARE_ARG_BASELINE)
+                .help("The baseline report file")
+                .value_name("BASELINE REPORT")
+                .required(true)
+                .num_args(1)
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath),
+        )
+        .arg(
+            Arg::new(COMPARE_ARG_CURRENT)
+                .help("The current report file")
+                .value_name("CURRENT REPORT")
+                .required(true)
+                .num_args(1)
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath),
+        )
+        .arg(
+            Arg::new(COMPARE_ARG_MAX_P99_INCREASE)
+                .help("Fail if the p99 time of any checked phase increases more than this percentage")
+                .value_name("PERCENTAGE")
+                .long(COMPARE_ARG_MAX_P99_INCREASE)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new(COMPARE_ARG_MAX_RPS_DECREASE)
+                .help("Fail if the requests per second decreases more than this percentage")
+                .value_name("PERCENTAGE")
+                .long(COMPARE_ARG_MAX_RPS_DECREASE)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new(COMPARE_ARG_PHASE)
+                .help("Only check the p99 time of this phase. All common phases will be checked if not set")
+                .value_name("PHASE NAME")
+                .long(COMPARE_ARG_PHASE)
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+}
+
+pub fn run(args: &ArgMatches) -> anyhow::Result<()> {
+    let compare_args = CompareArgs::parse(args)?;
+
+    let baseline = load_report(&compare_args.baseline)?;
+    let current = load_report(&compare_args.current)?;
+
+    let mut breaches = Vec::new();
+
+    let baseline_rps = get_total_rps(&baseline)
+        .ok_or_else(|| anyhow!("no {REPORT_TOTAL_RPS} found in baseline report"))?;
+    let current_rps = get_total_rps(&current)
+        .ok_or_else(|| anyhow!("no {REPORT_TOTAL_RPS} found in current report"))?;
+    let rps_change = change_percentage(baseline_rps, current_rps);
+    println!(
+        "Requests per second: {baseline_rps:.3} -> {current_rps:.3} ({})",
+        change_percentage_str(rps_change)
+    );
+    if let (Some(max_decrease), Some(rps_change)) = (compare_args.max_rps_decrease, rps_change) {
+        if -rps_change > max_decrease {
+            breaches.push(format!(
+                "requests per second decreased {:.2}%, exceeds {max_decrease}%",
+                -rps_change
+            ));
+        }
+    }
+
+    let baseline_phases = get_phases(&baseline)
+        .ok_or_else(|| anyhow!("no {REPORT_KEY_PHASES} found in baseline report"))?;
+    let current_phases = get_phases(&current)
+        .ok_or_else(|| anyhow!("no {REPORT_KEY_PHASES} found in current report"))?;
+    let check_phases: Vec<&str> = if compare_args.phases.is_empty() {
+        baseline_phases
+            .keys()
+            .filter(|k| current_phases.contains_key(k.as_str()))
+            .map(|k| k.as_str())
+            .collect()
+    } else {
+        compare_args.phases.iter().map(|s| s.as_str()).collect()
+    };
+
+    println!("P99 time:");
+    for name in check_phases {
+        let Some(baseline_p99) = baseline_phases.get(name).and_then(get_phase_p99) else {
+            return Err(anyhow!(
+                "no p99 value found for phase {name} in baseline report"
+            ));
+        };
+        let Some(current_p99) = current_phases.get(name).and_then(get_phase_p99) else {
+            return Err(anyhow!(
+                "no p99 value found for phase {name} in current report"
+            ));
+        };
+        let p99_change = change_percentage(baseline_p99 as f64, current_p99 as f64);
+        println!(
+            "  {name:<20} {:>12.3?} -> {:>12.3?} ({})",
+            Duration::from_nanos(baseline_p99),
+            Duration::from_nanos(current_p99),
+            change_percentage_str(p99_change)
+        );
+        // a zero baseline has no meaningful change percentage, skip the check for it
+        if let (Some(max_increase), Some(p99_change)) = (compare_args.max_p99_increase, p99_change)
+        {
+            if p99_change > max_increase {
+                breaches.push(format!(
+                    "p99 time of phase {name} increased {p99_change:.2}%, exceeds {max_increase}%"
+                ));
+            }
+        }
+    }
+
+    if breaches.is_empty() {
+        Ok(())
+    } else {
+        for s in &breaches {
+            eprintln!("! {s}");
+        }
+        Err(anyhow!("{} threshold(s) breached", breaches.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_percentage_valid() {
+        assert_eq!(parse_percentage("10").unwrap(), 10.0);
+        assert_eq!(parse_percentage("10%").unwrap(), 10.0);
+        assert_eq!(parse_percentage(" 2.5 % ").unwrap(), 2.5);
+        assert_eq!(parse_percentage("0").unwrap(), 0.0);
+    }
+
+    #[test]
+    fn parse_percentage_invalid() {
+        assert!(parse_percentage("").is_err());
+        assert!(parse_percentage("%").is_err());
+        assert!(parse_percentage("abc").is_err());
+        assert!(parse_percentage("-1").is_err());
+        assert!(parse_percentage("inf").is_err());
+        assert!(parse_percentage("NaN").is_err());
+    }
+
+    #[test]
+    fn change_percentage_value() {
+        assert_eq!(change_percentage(100.0, 110.0), Some(10.0));
+        assert_eq!(change_percentage(100.0, 90.0), Some(-10.0));
+        assert_eq!(change_percentage(200.0, 200.0), Some(0.0));
+        assert_eq!(change_percentage(0.0, 0.0), None);
+        assert_eq!(change_percentage(0.0, 1.0), None);
+        assert_eq!(change_percentage_str(Some(10.0)), "+10.00%");
+        assert_eq!(change_percentage_str(None), "n/a");
+    }
+}
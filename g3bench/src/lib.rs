@@ -16,8 +16,11 @@
 
 mod opts;
 mod progress;
+mod rate;
+mod report;
 
 pub mod build;
+pub mod compare;
 pub mod target;
 pub mod worker;
 
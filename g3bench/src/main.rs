@@ -38,12 +38,16 @@ fn build_cli_args() -> Command {
                     .value_parser(value_parser!(Shell)),
             ),
         )
+        .subcommand(g3bench::compare::command())
         .subcommand(g3bench::target::h1::command())
         .subcommand(g3bench::target::h2::command())
         .subcommand(g3bench::target::h3::command())
         .subcommand(g3bench::target::ssl::command())
         .subcommand(g3bench::target::dns::command())
         .subcommand(g3bench::target::keyless::command())
+        .subcommand(g3bench::target::socks5_udp::command())
+        .subcommand(g3bench::target::icap::command())
+        .subcommand(g3bench::target::websocket::command())
 }
 
 fn main() -> anyhow::Result<()> {
@@ -68,10 +72,13 @@ fn main() -> anyhow::Result<()> {
             generate_completion(sub_args);
             return Ok(());
         }
+        g3bench::compare::COMMAND => return g3bench::compare::run(sub_args),
         _ => {}
     }
 
-    proc_args.summary();
+    if proc_args.print_summary() {
+        proc_args.summary();
+    }
 
     let rt = proc_args
         .main_runtime()
@@ -96,6 +103,15 @@ fn main() -> anyhow::Result<()> {
             g3bench::target::keyless::COMMAND => {
                 g3bench::target::keyless::run(&proc_args, sub_args).await
             }
+            g3bench::target::socks5_udp::COMMAND => {
+                g3bench::target::socks5_udp::run(&proc_args, sub_args).await
+            }
+            g3bench::target::icap::COMMAND => {
+                g3bench::target::icap::run(&proc_args, sub_args).await
+            }
+            g3bench::target::websocket::COMMAND => {
+                g3bench::target::websocket::run(&proc_args, sub_args).await
+            }
             cmd => Err(anyhow!("invalid subcommand {}", cmd)),
         }
     })
@@ -32,6 +32,7 @@ use g3_types::metrics::MetricsName;
 use g3_types::net::{TcpSockSpeedLimitConfig, UdpSockSpeedLimitConfig, UpstreamAddr};
 
 use super::progress::BenchProgress;
+use super::rate::RateSchedule;
 
 const GLOBAL_ARG_UNAIDED: &str = "unaided";
 const GLOBAL_ARG_UNCONSTRAINED: &str = "unconstrained";
@@ -40,6 +41,8 @@ const GLOBAL_ARG_THREAD_STACK_SIZE: &str = "thread-stack-size";
 const GLOBAL_ARG_OPENSSL_ASYNC_JOBS: &str = "openssl-async-jobs";
 const GLOBAL_ARG_CONCURRENCY: &str = "concurrency";
 const GLOBAL_ARG_LATENCY: &str = "latency";
+const GLOBAL_ARG_RATE: &str = "rate";
+const GLOBAL_ARG_RATE_RAMP: &str = "rate-ramp";
 const GLOBAL_ARG_TIME_LIMIT: &str = "time-limit";
 const GLOBAL_ARG_REQUESTS: &str = "requests";
 const GLOBAL_ARG_RESOLVE: &str = "resolve";
@@ -49,6 +52,8 @@ const GLOBAL_ARG_EMIT_METRICS: &str = "emit-metrics";
 const GLOBAL_ARG_STATSD_TARGET_UDP: &str = "statsd-target-udp";
 const GLOBAL_ARG_STATSD_TARGET_UNIX: &str = "statsd-target-unix";
 const GLOBAL_ARG_NO_PROGRESS_BAR: &str = "no-progress-bar";
+const GLOBAL_ARG_REPORT: &str = "report";
+const GLOBAL_ARG_REPORT_FILE: &str = "report-file";
 
 const GLOBAL_ARG_PEER_PICK_POLICY: &str = "peer-pick-policy";
 const GLOBAL_ARG_TCP_LIMIT_SHIFT: &str = "tcp-limit-shift";
@@ -60,6 +65,7 @@ const GLOBAL_ARG_UDP_LIMIT_PACKETS: &str = "udp-limit-packets";
 pub struct ProcArgs {
     pub(super) concurrency: usize,
     pub(super) latency: Option<Duration>,
+    pub(super) rate: Option<RateSchedule>,
     pub(super) requests: Option<usize>,
     pub(super) time_limit: Option<Duration>,
     pub(super) log_error_count: usize,
@@ -74,6 +80,8 @@ pub struct ProcArgs {
 
     statsd_client_config: Option<StatsdClientConfig>,
     no_progress_bar: bool,
+    pub(super) report_json: bool,
+    pub(super) report_file: Option<PathBuf>,
 
     peer_pick_policy: SelectivePickPolicy,
     pub(super) tcp_sock_speed_limit: TcpSockSpeedLimitConfig,
@@ -85,6 +93,7 @@ impl Default for ProcArgs {
         ProcArgs {
             concurrency: 1,
             latency: None,
+            rate: None,
             requests: None,
             time_limit: None,
             log_error_count: 0,
@@ -98,6 +107,8 @@ impl Default for ProcArgs {
             openssl_async_job_size: 0,
             statsd_client_config: None,
             no_progress_bar: false,
+            report_json: false,
+            report_file: None,
             peer_pick_policy: SelectivePickPolicy::RoundRobin,
             tcp_sock_speed_limit: TcpSockSpeedLimitConfig::default(),
             udp_sock_speed_limit: UdpSockSpeedLimitConfig::default(),
@@ -106,8 +117,19 @@ impl Default for ProcArgs {
 }
 
 impl ProcArgs {
+    /// The human-readable summary should not be printed if the json report goes to stdout
+    pub fn print_summary(&self) -> bool {
+        !self.report_json || self.report_file.is_some()
+    }
+
     pub fn summary(&self) {
         println!("Concurrency Level: {}", self.concurrency);
+        if let Some(rate) = &self.rate {
+            println!("Request Rate:      {:.3} [#/sec]", rate.rate());
+            if let Some(ramp) = rate.ramp() {
+                println!("Rate Ramp Time:    {ramp:?}");
+            }
+        }
         println!();
     }
 
@@ -242,6 +264,27 @@ pub fn add_global_args(app: Command) -> Command {
             .num_args(1)
             .value_parser(value_parser!(usize)),
     )
+    .arg(
+        Arg::new(GLOBAL_ARG_RATE)
+            .help(
+                "Send requests at a fixed rate (open-loop mode), \
+                the concurrency will be the max number of in-flight requests",
+            )
+            .value_name("COUNT[/s|/m|/h]")
+            .long(GLOBAL_ARG_RATE)
+            .global(true)
+            .num_args(1)
+            .conflicts_with(GLOBAL_ARG_LATENCY),
+    )
+    .arg(
+        Arg::new(GLOBAL_ARG_RATE_RAMP)
+            .help("Time to linearly increase the request rate from 0 to the target value")
+            .value_name("RAMP TIME")
+            .long(GLOBAL_ARG_RATE_RAMP)
+            .global(true)
+            .num_args(1)
+            .requires(GLOBAL_ARG_RATE),
+    )
     .arg(
         Arg::new(GLOBAL_ARG_TIME_LIMIT)
             .help("Maximum time to spend for benchmarking")
@@ -359,6 +402,26 @@ pub fn add_global_args(app: Command) -> Command {
             .long(GLOBAL_ARG_NO_PROGRESS_BAR)
             .global(true),
     )
+    .arg(
+        Arg::new(GLOBAL_ARG_REPORT)
+            .help("Generate a machine-readable report at the end of the test")
+            .value_name("FORMAT")
+            .long(GLOBAL_ARG_REPORT)
+            .global(true)
+            .num_args(1)
+            .value_parser(["json"]),
+    )
+    .arg(
+        Arg::new(GLOBAL_ARG_REPORT_FILE)
+            .help("Write the report to this file instead of stdout, where the summary will be suppressed")
+            .value_name("FILE PATH")
+            .long(GLOBAL_ARG_REPORT_FILE)
+            .global(true)
+            .num_args(1)
+            .value_hint(ValueHint::FilePath)
+            .value_parser(value_parser!(PathBuf))
+            .requires(GLOBAL_ARG_REPORT),
+    )
     .arg(
         Arg::new(GLOBAL_ARG_PEER_PICK_POLICY)
             .help("Set the pick policy for selecting peers")
@@ -429,6 +492,15 @@ pub fn parse_global_args(args: &ArgMatches) -> anyhow::Result<ProcArgs> {
         proc_args.latency = Some(Duration::from_millis(*n as u64));
     }
 
+    if let Some(v) = args.get_one::<String>(GLOBAL_ARG_RATE) {
+        let mut rate =
+            RateSchedule::from_str(v).context(format!("invalid {GLOBAL_ARG_RATE} value {v}"))?;
+        if let Some(ramp) = g3_clap::humanize::get_duration(args, GLOBAL_ARG_RATE_RAMP)? {
+            rate.set_ramp(ramp);
+        }
+        proc_args.rate = Some(rate);
+    }
+
     if let Some(n) = args.get_one::<usize>(GLOBAL_ARG_REQUESTS) {
         proc_args.requests = Some(*n);
     }
@@ -490,6 +562,14 @@ pub fn parse_global_args(args: &ArgMatches) -> anyhow::Result<ProcArgs> {
         proc_args.no_progress_bar = true;
     }
 
+    if let Some(format) = args.get_one::<String>(GLOBAL_ARG_REPORT) {
+        match format.as_str() {
+            "json" => proc_args.report_json = true,
+            _ => return Err(anyhow!("unsupported report format {format}")),
+        }
+        proc_args.report_file = args.get_one::<PathBuf>(GLOBAL_ARG_REPORT_FILE).cloned();
+    }
+
     if let Some(s) = args.get_one::<String>(GLOBAL_ARG_PEER_PICK_POLICY) {
         proc_args.peer_pick_policy = SelectivePickPolicy::from_str(s).unwrap();
     }
@@ -0,0 +1,182 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+/// The send schedule for the open-loop mode.
+///
+/// The n-th request is scheduled at a fixed offset from the start time, which is independent
+/// of the response time of the previous requests. If a ramp time is set, the rate will increase
+/// linearly from 0 to the target rate during that time.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RateSchedule {
+    rate: f64,
+    ramp: Option<Duration>,
+}
+
+impl RateSchedule {
+    pub(crate) fn set_ramp(&mut self, ramp: Duration) {
+        if ramp.is_zero() {
+            self.ramp = None;
+        } else {
+            self.ramp = Some(ramp);
+        }
+    }
+
+    #[inline]
+    pub(crate) fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    #[inline]
+    pub(crate) fn ramp(&self) -> Option<Duration> {
+        self.ramp
+    }
+
+    /// Get the intended send time of the request with the given index
+    pub(crate) fn offset(&self, index: usize) -> Duration {
+        let index = index as f64;
+        let secs = match self.ramp {
+            Some(ramp) => {
+                let ramp = ramp.as_secs_f64();
+                // the count of requests sent in the ramp stage
+                let ramp_count = self.rate * ramp / 2.0;
+                if index < ramp_count {
+                    (2.0 * ramp * index / self.rate).sqrt()
+                } else {
+                    ramp + (index - ramp_count) / self.rate
+                }
+            }
+            None => index / self.rate,
+        };
+        Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+    }
+}
+
+impl FromStr for RateSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (v, unit_secs) = match s.split_once('/') {
+            Some((v, unit)) => {
+                let unit_secs = match unit.trim() {
+                    "s" | "sec" | "second" => 1.0,
+                    "m" | "min" | "minute" => 60.0,
+                    "h" | "hour" => 3600.0,
+                    _ => return Err(anyhow!("unsupported rate unit {unit}")),
+                };
+                (v.trim(), unit_secs)
+            }
+            None => (s.trim(), 1.0),
+        };
+        let count = f64::from_str(v).map_err(|e| anyhow!("invalid rate count {v}: {e}"))?;
+        if !count.is_finite() || count <= 0.0 {
+            return Err(anyhow!("the rate count should be a positive number"));
+        }
+        Ok(RateSchedule {
+            rate: count / unit_secs,
+            ramp: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_offset(schedule: &RateSchedule, index: usize, secs: f64) {
+        let offset = schedule.offset(index).as_secs_f64();
+        assert!(
+            (offset - secs).abs() < 1e-6,
+            "offset of #{index} is {offset}, expected {secs}"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(RateSchedule::from_str("100").unwrap().rate(), 100.0);
+        assert_eq!(RateSchedule::from_str("100/s").unwrap().rate(), 100.0);
+        assert_eq!(RateSchedule::from_str(" 0.5 / sec ").unwrap().rate(), 0.5);
+        assert_eq!(RateSchedule::from_str("60/m").unwrap().rate(), 1.0);
+        assert_eq!(RateSchedule::from_str("120/minute").unwrap().rate(), 2.0);
+        assert_eq!(RateSchedule::from_str("7200/h").unwrap().rate(), 2.0);
+        assert!(RateSchedule::from_str("100").unwrap().ramp().is_none());
+
+        assert!(RateSchedule::from_str("").is_err());
+        assert!(RateSchedule::from_str("abc").is_err());
+        assert!(RateSchedule::from_str("0").is_err());
+        assert!(RateSchedule::from_str("-1/s").is_err());
+        assert!(RateSchedule::from_str("inf").is_err());
+        assert!(RateSchedule::from_str("NaN").is_err());
+        assert!(RateSchedule::from_str("10/d").is_err());
+    }
+
+    #[test]
+    fn set_ramp() {
+        let mut schedule = RateSchedule::from_str("10/s").unwrap();
+        schedule.set_ramp(Duration::from_secs(2));
+        assert_eq!(schedule.ramp(), Some(Duration::from_secs(2)));
+        schedule.set_ramp(Duration::ZERO);
+        assert!(schedule.ramp().is_none());
+    }
+
+    #[test]
+    fn offset_constant() {
+        let schedule = RateSchedule::from_str("10/s").unwrap();
+        assert_offset(&schedule, 0, 0.0);
+        assert_offset(&schedule, 1, 0.1);
+        assert_offset(&schedule, 5, 0.5);
+        assert_offset(&schedule, 100, 10.0);
+    }
+
+    #[test]
+    fn offset_ramp() {
+        let mut schedule = RateSchedule::from_str("10/s").unwrap();
+        schedule.set_ramp(Duration::from_secs(2));
+
+        // 10 requests will be sent during the 2s ramp stage, as the rate increases
+        // linearly, the n-th request is sent at sqrt(2 * ramp * n / rate)
+        assert_offset(&schedule, 0, 0.0);
+        assert_offset(&schedule, 1, 0.4f64.sqrt());
+        assert_offset(&schedule, 5, 2.0f64.sqrt());
+        assert_offset(&schedule, 9, 3.6f64.sqrt());
+        // the full rate is used after the ramp stage
+        assert_offset(&schedule, 10, 2.0);
+        assert_offset(&schedule, 11, 2.1);
+        assert_offset(&schedule, 20, 3.0);
+
+        // the offset should be increasing, and the interval should be decreasing during ramp
+        let mut last_offset = Duration::ZERO;
+        let mut last_interval = Duration::MAX;
+        for i in 1..=10 {
+            let offset = schedule.offset(i);
+            let interval = offset - last_offset;
+            assert!(offset > last_offset);
+            assert!(interval < last_interval);
+            last_offset = offset;
+            last_interval = interval;
+        }
+    }
+
+    #[test]
+    fn offset_overflow() {
+        let schedule = RateSchedule::from_str("1/h").unwrap();
+        assert_eq!(schedule.offset(usize::MAX), Duration::MAX);
+    }
+}
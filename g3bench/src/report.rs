@@ -0,0 +1,143 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use hdrhistogram::Histogram;
+use serde_json::{Map, Number, Value};
+
+pub(crate) const REPORT_KEY_TOTAL: &str = "total";
+pub(crate) const REPORT_KEY_ERRORS: &str = "errors";
+pub(crate) const REPORT_KEY_PHASES: &str = "phases";
+pub(crate) const REPORT_KEY_DISTRIBUTIONS: &str = "distributions";
+
+pub(crate) const REPORT_TOTAL_RPS: &str = "rps";
+pub(crate) const REPORT_QUANTILE_P99: &str = "p99";
+
+/// Machine-readable report of a bench run.
+///
+/// All time values in the phases map are in nanoseconds.
+pub(crate) struct BenchReport {
+    total: Map<String, Value>,
+    errors: Map<String, Value>,
+    phases: Map<String, Value>,
+    distributions: Map<String, Value>,
+}
+
+impl BenchReport {
+    pub(crate) fn new() -> Self {
+        BenchReport {
+            total: Map::new(),
+            errors: Map::new(),
+            phases: Map::new(),
+            distributions: Map::new(),
+        }
+    }
+
+    pub(crate) fn set_total_time(&mut self, total_time: Duration) {
+        self.set_total_float("time_taken", total_time.as_secs_f64());
+    }
+
+    pub(crate) fn set_total_count(&mut self, name: &str, count: usize) {
+        self.total
+            .insert(name.to_string(), Value::Number(Number::from(count)));
+    }
+
+    pub(crate) fn set_total_float(&mut self, name: &str, value: f64) {
+        if let Some(n) = Number::from_f64(value) {
+            self.total.insert(name.to_string(), Value::Number(n));
+        }
+    }
+
+    pub(crate) fn set_error_count(&mut self, name: &str, count: usize) {
+        self.errors
+            .insert(name.to_string(), Value::Number(Number::from(count)));
+    }
+
+    pub(crate) fn add_phase(&mut self, name: &str, h: &Histogram<u64>) {
+        self.phases.insert(name.to_string(), quantiles_value(h));
+    }
+
+    pub(crate) fn add_distribution(&mut self, name: &str, h: &Histogram<u64>) {
+        self.distributions
+            .insert(name.to_string(), quantiles_value(h));
+    }
+
+    fn into_value(self) -> Value {
+        let mut map = Map::new();
+        map.insert(REPORT_KEY_TOTAL.to_string(), Value::Object(self.total));
+        map.insert(REPORT_KEY_ERRORS.to_string(), Value::Object(self.errors));
+        map.insert(REPORT_KEY_PHASES.to_string(), Value::Object(self.phases));
+        map.insert(
+            REPORT_KEY_DISTRIBUTIONS.to_string(),
+            Value::Object(self.distributions),
+        );
+        Value::Object(map)
+    }
+
+    /// Write the json report to the file, or stdout if no file path is set
+    pub(crate) fn write(self, path: Option<&Path>) -> anyhow::Result<()> {
+        let value = self.into_value();
+        match path {
+            Some(path) => {
+                let file = File::create(path)
+                    .map_err(|e| anyhow!("failed to create report file {}: {e}", path.display()))?;
+                let mut writer = BufWriter::new(file);
+                serde_json::to_writer_pretty(&mut writer, &value)
+                    .map_err(|e| anyhow!("failed to write report: {e}"))?;
+                writer
+                    .flush()
+                    .map_err(|e| anyhow!("failed to flush report file: {e}"))
+            }
+            None => {
+                let s = serde_json::to_string_pretty(&value)
+                    .map_err(|e| anyhow!("failed to serialize report: {e}"))?;
+                println!("{s}");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn quantiles_value(h: &Histogram<u64>) -> Value {
+    let mut map = Map::new();
+    map.insert("count".to_string(), Value::Number(Number::from(h.len())));
+    map.insert("min".to_string(), Value::Number(Number::from(h.min())));
+    if let Some(n) = Number::from_f64(h.mean()) {
+        map.insert("mean".to_string(), Value::Number(n));
+    }
+    if let Some(n) = Number::from_f64(h.stdev()) {
+        map.insert("stdev".to_string(), Value::Number(n));
+    }
+    for (name, q) in [
+        ("p50", 0.50),
+        ("p90", 0.90),
+        ("p95", 0.95),
+        (REPORT_QUANTILE_P99, 0.99),
+        ("p999", 0.999),
+    ] {
+        map.insert(
+            name.to_string(),
+            Value::Number(Number::from(h.value_at_quantile(q))),
+        );
+    }
+    map.insert("max".to_string(), Value::Number(Number::from(h.max())));
+    Value::Object(map)
+}
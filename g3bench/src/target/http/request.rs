@@ -0,0 +1,531 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{anyhow, Context};
+use bytes::Bytes;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command, ValueHint};
+use http::uri::PathAndQuery;
+use http::{header, HeaderName, HeaderValue, Method};
+use url::Url;
+use yaml_rust::{Yaml, YamlLoader};
+
+const HTTP_ARG_METHOD: &str = "method";
+const HTTP_ARG_HEADER: &str = "header";
+const HTTP_ARG_BODY_FILE: &str = "body-file";
+const HTTP_ARG_BODY_SIZE: &str = "body-size";
+const HTTP_ARG_INPUT: &str = "input";
+const HTTP_ARG_ITER_GLOBAL: &str = "iter-global";
+
+const HTTP_METHODS: [&str; 7] = ["GET", "HEAD", "POST", "PUT", "DELETE", "OPTIONS", "PATCH"];
+
+pub(crate) struct HttpRequestTemplate {
+    pub(crate) method: Method,
+    pub(crate) path_and_query: PathAndQuery,
+    pub(crate) headers: Vec<(HeaderName, HeaderValue)>,
+    pub(crate) body: Bytes,
+}
+
+impl HttpRequestTemplate {
+    fn new(method: Method, url: &Url) -> anyhow::Result<Self> {
+        let path_and_query = match url.query() {
+            Some(q) => format!("{}?{q}", url.path()),
+            None => url.path().to_string(),
+        };
+        let path_and_query = PathAndQuery::from_str(&path_and_query)
+            .map_err(|e| anyhow!("invalid path in url {url}: {e}"))?;
+        Ok(HttpRequestTemplate {
+            method,
+            path_and_query,
+            headers: Vec::new(),
+            body: Bytes::new(),
+        })
+    }
+
+    fn parse_yaml(
+        value: &Yaml,
+        target_url: &Url,
+        lookup_dir: &Path,
+    ) -> anyhow::Result<(Self, usize)> {
+        match value {
+            Yaml::String(s) => {
+                let url = parse_template_url(target_url, s)?;
+                let template = HttpRequestTemplate::new(Method::GET, &url)?;
+                Ok((template, 1))
+            }
+            Yaml::Hash(map) => {
+                let v = g3_yaml::hash_get_required(map, "url")?;
+                let url = g3_yaml::value::as_string(v)?;
+                let url = parse_template_url(target_url, &url)?;
+                let mut template = HttpRequestTemplate::new(Method::GET, &url)?;
+                let mut weight = 1;
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "url" => Ok(()),
+                    "method" => {
+                        let method = g3_yaml::value::as_string(v)?;
+                        template.method = Method::from_str(&method.to_uppercase())
+                            .map_err(|e| anyhow!("invalid method {method}: {e}"))?;
+                        Ok(())
+                    }
+                    "weight" => {
+                        weight = g3_yaml::value::as_usize(v)?;
+                        Ok(())
+                    }
+                    "headers" => {
+                        let Yaml::Hash(map) = v else {
+                            return Err(anyhow!("invalid map value for key {k}"));
+                        };
+                        for (name, value) in map {
+                            let name = g3_yaml::value::as_string(name)?;
+                            let value = g3_yaml::value::as_string(value)?;
+                            let header = parse_header(&name, &value)?;
+                            template.headers.push(header);
+                        }
+                        Ok(())
+                    }
+                    "body_file" => {
+                        let path = g3_yaml::value::as_file_path(v, lookup_dir, false)?;
+                        template.body = read_body_file(&path)?;
+                        Ok(())
+                    }
+                    "body_size" => {
+                        let size = g3_yaml::humanize::as_usize(v)?;
+                        template.body = generate_body(size)?;
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+
+                Ok((template, weight))
+            }
+            _ => Err(anyhow!("invalid yaml value type for request template")),
+        }
+    }
+}
+
+/// The url of each request template should point to the same target
+fn parse_template_url(target_url: &Url, s: &str) -> anyhow::Result<Url> {
+    let url = target_url
+        .join(s)
+        .map_err(|e| anyhow!("invalid url {s}: {e}"))?;
+    if url.scheme() != target_url.scheme()
+        || url.host_str() != target_url.host_str()
+        || url.port_or_known_default() != target_url.port_or_known_default()
+    {
+        return Err(anyhow!("url {s} does not match target {target_url}"));
+    }
+    Ok(url)
+}
+
+fn parse_header(name: &str, value: &str) -> anyhow::Result<(HeaderName, HeaderValue)> {
+    let name = HeaderName::from_str(name.trim())
+        .map_err(|e| anyhow!("invalid header name {name}: {e}"))?;
+    match name {
+        header::HOST | header::CONTENT_LENGTH | header::TRANSFER_ENCODING | header::CONNECTION => {
+            return Err(anyhow!("setting header {name} is not allowed"));
+        }
+        _ => {}
+    }
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|e| anyhow!("invalid header value {value}: {e}"))?;
+    Ok((name, value))
+}
+
+fn read_body_file(path: &Path) -> anyhow::Result<Bytes> {
+    let data = std::fs::read(path)
+        .map_err(|e| anyhow!("failed to read body file {}: {e}", path.display()))?;
+    Ok(Bytes::from(data))
+}
+
+fn generate_body(size: usize) -> anyhow::Result<Bytes> {
+    let mut data = vec![0u8; size];
+    openssl::rand::rand_bytes(&mut data)
+        .map_err(|e| anyhow!("failed to generate body data: {e}"))?;
+    Ok(Bytes::from(data))
+}
+
+pub(crate) struct HttpRequestArgs {
+    templates: Vec<HttpRequestTemplate>,
+    /// the accumulated weight till each template
+    weight_ends: Vec<usize>,
+    iter_global: bool,
+    global_index: AtomicUsize,
+}
+
+impl HttpRequestArgs {
+    fn new() -> Self {
+        HttpRequestArgs {
+            templates: Vec::new(),
+            weight_ends: Vec::new(),
+            iter_global: false,
+            global_index: AtomicUsize::new(0),
+        }
+    }
+
+    fn push_template(&mut self, template: HttpRequestTemplate, weight: usize) {
+        if weight == 0 {
+            return;
+        }
+        let end = self.weight_ends.last().copied().unwrap_or_default() + weight;
+        self.templates.push(template);
+        self.weight_ends.push(end);
+    }
+
+    /// Fetch the next request template, the local index will be used if not iter globally.
+    ///
+    /// The templates are picked in a deterministic weighted round robin order, in which each
+    /// template will be picked `weight` times in a row in each round.
+    pub(crate) fn fetch_template(&self, local_index: &mut usize) -> &HttpRequestTemplate {
+        if self.templates.len() == 1 {
+            return &self.templates[0];
+        }
+
+        let total = self.weight_ends.last().copied().unwrap_or_default();
+        let index = if self.iter_global {
+            self.global_index.fetch_add(1, Ordering::Relaxed)
+        } else {
+            let i = *local_index;
+            *local_index = i.wrapping_add(1);
+            i
+        };
+        let slot = index % total;
+        let id = self.weight_ends.partition_point(|end| *end <= slot);
+        &self.templates[id]
+    }
+
+    pub(crate) fn parse_args(args: &ArgMatches, target_url: &Url) -> anyhow::Result<Self> {
+        let mut request_args = HttpRequestArgs::new();
+
+        let mut common_headers = Vec::new();
+        if let Some(headers) = args.get_many::<String>(HTTP_ARG_HEADER) {
+            for h in headers {
+                let Some((name, value)) = h.split_once(':') else {
+                    return Err(anyhow!("invalid {HTTP_ARG_HEADER} value {h}"));
+                };
+                let header =
+                    parse_header(name, value).context(format!("invalid {HTTP_ARG_HEADER}"))?;
+                common_headers.push(header);
+            }
+        }
+
+        if let Some(p) = args.get_one::<PathBuf>(HTTP_ARG_INPUT) {
+            let lookup_dir = p
+                .parent()
+                .ok_or_else(|| anyhow!("invalid input file path {}", p.display()))?
+                .to_path_buf();
+            let content = std::fs::read_to_string(p)
+                .map_err(|e| anyhow!("failed to read input file {}: {e}", p.display()))?;
+            let docs = YamlLoader::load_from_str(&content)
+                .map_err(|e| anyhow!("invalid yaml input file {}: {e}", p.display()))?;
+            for doc in &docs {
+                let Yaml::Array(seq) = doc else {
+                    return Err(anyhow!("the input doc should be an array"));
+                };
+                for (i, v) in seq.iter().enumerate() {
+                    let (template, weight) =
+                        HttpRequestTemplate::parse_yaml(v, target_url, &lookup_dir)
+                            .context(format!("invalid request template #{i}"))?;
+                    request_args.push_template(template, weight);
+                }
+            }
+            if request_args.templates.is_empty() {
+                return Err(anyhow!("no request template found in input file"));
+            }
+        } else {
+            let method = if let Some(v) = args.get_one::<String>(HTTP_ARG_METHOD) {
+                Method::from_str(v).context(format!("invalid {HTTP_ARG_METHOD} value"))?
+            } else {
+                Method::GET
+            };
+            let mut template = HttpRequestTemplate::new(method, target_url)?;
+            if let Some(p) = args.get_one::<PathBuf>(HTTP_ARG_BODY_FILE) {
+                template.body = read_body_file(p)?;
+            } else if let Some(size) = g3_clap::humanize::get_usize(args, HTTP_ARG_BODY_SIZE)? {
+                template.body = generate_body(size)?;
+            }
+            request_args.push_template(template, 1);
+        }
+
+        if !common_headers.is_empty() {
+            for template in &mut request_args.templates {
+                let mut headers = common_headers.clone();
+                headers.append(&mut template.headers);
+                template.headers = headers;
+            }
+        }
+
+        if args.get_flag(HTTP_ARG_ITER_GLOBAL) {
+            request_args.iter_global = true;
+        }
+
+        Ok(request_args)
+    }
+}
+
+pub(crate) trait AppendHttpRequestArgs {
+    fn append_http_request_args(self) -> Self;
+}
+
+impl AppendHttpRequestArgs for Command {
+    fn append_http_request_args(self) -> Self {
+        self.arg(
+            Arg::new(HTTP_ARG_METHOD)
+                .value_name("METHOD")
+                .short('m')
+                .long(HTTP_ARG_METHOD)
+                .num_args(1)
+                .value_parser(HTTP_METHODS)
+                .default_value("GET"),
+        )
+        .arg(
+            Arg::new(HTTP_ARG_HEADER)
+                .help("Add request header, in 'Name: Value' format")
+                .value_name("HEADER")
+                .short('H')
+                .long(HTTP_ARG_HEADER)
+                .action(ArgAction::Append)
+                .num_args(1),
+        )
+        .arg(
+            Arg::new(HTTP_ARG_BODY_FILE)
+                .help("Send the content of this file as request body")
+                .value_name("FILE PATH")
+                .long(HTTP_ARG_BODY_FILE)
+                .num_args(1)
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath)
+                .conflicts_with_all([HTTP_ARG_BODY_SIZE, HTTP_ARG_INPUT]),
+        )
+        .arg(
+            Arg::new(HTTP_ARG_BODY_SIZE)
+                .help("Send generated request body of this size")
+                .value_name("SIZE")
+                .long(HTTP_ARG_BODY_SIZE)
+                .num_args(1)
+                .conflicts_with_all([HTTP_ARG_BODY_FILE, HTTP_ARG_INPUT]),
+        )
+        .arg(
+            Arg::new(HTTP_ARG_INPUT)
+                .help(
+                    "Input yaml file that contains weighted request templates.\n\
+                        The templates will be picked in weighted round robin order.\n\
+                        The method arg will be ignored if set",
+                )
+                .value_name("FILE PATH")
+                .long(HTTP_ARG_INPUT)
+                .num_args(1)
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(ValueHint::FilePath),
+        )
+        .arg(
+            Arg::new(HTTP_ARG_ITER_GLOBAL)
+                .help("Iter request templates globally")
+                .action(ArgAction::SetTrue)
+                .long(HTTP_ARG_ITER_GLOBAL),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target_url() -> Url {
+        Url::parse("https://example.net/index.html").unwrap()
+    }
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().pop().unwrap()
+    }
+
+    #[test]
+    fn template_url() {
+        let target = target_url();
+        let url = parse_template_url(&target, "/api/search?q=g3").unwrap();
+        assert_eq!(url.as_str(), "https://example.net/api/search?q=g3");
+        let url = parse_template_url(&target, "style.css").unwrap();
+        assert_eq!(url.path(), "/style.css");
+        let url = parse_template_url(&target, "https://example.net:443/a").unwrap();
+        assert_eq!(url.path(), "/a");
+
+        assert!(parse_template_url(&target, "http://example.net/a").is_err());
+        assert!(parse_template_url(&target, "https://www.example.net/a").is_err());
+        assert!(parse_template_url(&target, "https://example.net:8443/a").is_err());
+        assert!(parse_template_url(&target, "//example.org/a").is_err());
+    }
+
+    #[test]
+    fn header() {
+        let (name, value) = parse_header(" X-Test ", " abc ").unwrap();
+        assert_eq!(name.as_str(), "x-test");
+        assert_eq!(value.to_str().unwrap(), "abc");
+
+        assert!(parse_header("Host", "example.org").is_err());
+        assert!(parse_header("content-length", "1").is_err());
+        assert!(parse_header("Transfer-Encoding", "chunked").is_err());
+        assert!(parse_header("Connection", "close").is_err());
+        assert!(parse_header("X Test", "a").is_err());
+        assert!(parse_header("X-Test", "a\nb").is_err());
+    }
+
+    #[test]
+    fn yaml_template_ok() {
+        let target = target_url();
+        let lookup_dir =
+            std::env::temp_dir().join(format!("g3bench-request-{}", std::process::id()));
+        std::fs::create_dir_all(&lookup_dir).unwrap();
+        std::fs::write(lookup_dir.join("body.json"), b"{}").unwrap();
+
+        let (template, weight) =
+            HttpRequestTemplate::parse_yaml(&load_yaml("/a?b=c"), &target, &lookup_dir).unwrap();
+        assert_eq!(template.method, Method::GET);
+        assert_eq!(template.path_and_query.as_str(), "/a?b=c");
+        assert!(template.headers.is_empty());
+        assert!(template.body.is_empty());
+        assert_eq!(weight, 1);
+
+        let v = load_yaml(
+            r#"
+            url: /api/upload
+            method: post
+            weight: 3
+            headers:
+              Content-Type: application/json
+              X-Test: a
+            body_file: body.json
+            "#,
+        );
+        let (template, weight) = HttpRequestTemplate::parse_yaml(&v, &target, &lookup_dir).unwrap();
+        assert_eq!(template.method, Method::POST);
+        assert_eq!(template.path_and_query.as_str(), "/api/upload");
+        assert_eq!(template.headers.len(), 2);
+        assert_eq!(template.headers[0].0, header::CONTENT_TYPE);
+        assert_eq!(template.headers[1].1.to_str().unwrap(), "a");
+        assert_eq!(template.body.as_ref(), b"{}");
+        assert_eq!(weight, 3);
+
+        let v = load_yaml(
+            r#"
+            url: /api/upload
+            method: PUT
+            body_size: 1024
+            "#,
+        );
+        let (template, weight) = HttpRequestTemplate::parse_yaml(&v, &target, &lookup_dir).unwrap();
+        assert_eq!(template.method, Method::PUT);
+        assert_eq!(template.body.len(), 1024);
+        assert_eq!(weight, 1);
+
+        std::fs::remove_dir_all(&lookup_dir).unwrap();
+    }
+
+    #[test]
+    fn yaml_template_err() {
+        let target = target_url();
+        let lookup_dir = std::env::temp_dir();
+
+        for s in [
+            "https://example.org/",
+            "1",
+            "method: GET",
+            "{url: /a, method: 'G ET'}",
+            "{url: /a, weight: -1}",
+            "{url: /a, headers: [a, b]}",
+            "{url: /a, headers: {Host: example.org}}",
+            "{url: /a, body_file: not-existed.file}",
+            "{url: /a, unknown: 1}",
+        ] {
+            let v = load_yaml(s);
+            assert!(
+                HttpRequestTemplate::parse_yaml(&v, &target, &lookup_dir).is_err(),
+                "{s}"
+            );
+        }
+    }
+
+    fn new_request_args(weights: &[usize]) -> HttpRequestArgs {
+        let target = target_url();
+        let mut args = HttpRequestArgs::new();
+        for (i, weight) in weights.iter().enumerate() {
+            let url = parse_template_url(&target, &format!("/{i}")).unwrap();
+            let template = HttpRequestTemplate::new(Method::GET, &url).unwrap();
+            args.push_template(template, *weight);
+        }
+        args
+    }
+
+    fn fetch_paths(args: &HttpRequestArgs, local_index: &mut usize, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| {
+                args.fetch_template(local_index)
+                    .path_and_query
+                    .as_str()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fetch_weighted() {
+        // the template with zero weight is skipped
+        let args = new_request_args(&[1, 2, 0, 3]);
+        assert_eq!(args.templates.len(), 3);
+
+        let mut local_index = 0;
+        let paths = fetch_paths(&args, &mut local_index, 12);
+        assert_eq!(
+            paths,
+            ["/0", "/1", "/1", "/3", "/3", "/3", "/0", "/1", "/1", "/3", "/3", "/3"]
+        );
+        assert_eq!(local_index, 12);
+
+        // each local index iterates on its own
+        let mut other_index = 0;
+        assert_eq!(fetch_paths(&args, &mut other_index, 1), ["/0"]);
+
+        // the local index wraps to zero
+        let mut local_index = usize::MAX;
+        args.fetch_template(&mut local_index);
+        assert_eq!(local_index, 0);
+        assert_eq!(fetch_paths(&args, &mut local_index, 2), ["/0", "/1"]);
+    }
+
+    #[test]
+    fn fetch_global() {
+        let mut args = new_request_args(&[2, 1]);
+        args.iter_global = true;
+
+        let mut index_a = 0;
+        let mut index_b = 0;
+        assert_eq!(fetch_paths(&args, &mut index_a, 1), ["/0"]);
+        assert_eq!(fetch_paths(&args, &mut index_b, 2), ["/0", "/1"]);
+        assert_eq!(fetch_paths(&args, &mut index_a, 1), ["/0"]);
+        // the local index is not used
+        assert_eq!(index_a, 0);
+        assert_eq!(index_b, 0);
+    }
+
+    #[test]
+    fn fetch_single() {
+        let args = new_request_args(&[5]);
+        let mut local_index = 0;
+        assert_eq!(fetch_paths(&args, &mut local_index, 3), ["/0", "/0", "/0"]);
+    }
+}
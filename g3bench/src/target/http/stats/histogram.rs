@@ -0,0 +1,147 @@
+/*
+ * Copyright 2023 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use g3_histogram::{HistogramRecorder, KeepingHistogram};
+use g3_statsd_client::StatsdClient;
+use g3_types::ext::DurationExt;
+
+use crate::report::BenchReport;
+use crate::target::BenchHistogram;
+
+pub(crate) struct HttpHistogram {
+    conn_time: KeepingHistogram<u64>,
+    tls_handshake_time: KeepingHistogram<u64>,
+    send_hdr_time: KeepingHistogram<u64>,
+    recv_hdr_time: KeepingHistogram<u64>,
+    total_time: KeepingHistogram<u64>,
+    conn_reuse_count: KeepingHistogram<u64>,
+}
+
+impl HttpHistogram {
+    pub(crate) fn new() -> (Self, HttpHistogramRecorder) {
+        let (conn_time_h, conn_time_r) = KeepingHistogram::new();
+        let (tls_handshake_time_h, tls_handshake_time_r) = KeepingHistogram::new();
+        let (send_hdr_time_h, send_hdr_time_r) = KeepingHistogram::new();
+        let (recv_hdr_time_h, recv_hdr_time_r) = KeepingHistogram::new();
+        let (total_time_h, total_time_r) = KeepingHistogram::new();
+        let (conn_reuse_count_h, conn_reuse_count_r) = KeepingHistogram::new();
+        let h = HttpHistogram {
+            conn_time: conn_time_h,
+            tls_handshake_time: tls_handshake_time_h,
+            send_hdr_time: send_hdr_time_h,
+            recv_hdr_time: recv_hdr_time_h,
+            total_time: total_time_h,
+            conn_reuse_count: conn_reuse_count_h,
+        };
+        let r = HttpHistogramRecorder {
+            conn_time: conn_time_r,
+            tls_handshake_time: tls_handshake_time_r,
+            send_hdr_time: send_hdr_time_r,
+            recv_hdr_time: recv_hdr_time_r,
+            total_time: total_time_r,
+            conn_reuse_count: conn_reuse_count_r,
+        };
+        (h, r)
+    }
+}
+
+impl BenchHistogram for HttpHistogram {
+    fn refresh(&mut self) {
+        self.conn_time.refresh().unwrap();
+        self.tls_handshake_time.refresh().unwrap();
+        self.send_hdr_time.refresh().unwrap();
+        self.recv_hdr_time.refresh().unwrap();
+        self.total_time.refresh().unwrap();
+        self.conn_reuse_count.refresh().unwrap();
+    }
+
+    fn emit(&self, client: &mut StatsdClient) {
+        self.emit_histogram(client, self.conn_time.inner(), "http.time.connect");
+        let tls_handshake_time = self.tls_handshake_time.inner();
+        if !tls_handshake_time.is_empty() {
+            self.emit_histogram(client, tls_handshake_time, "http.time.tls_handshake");
+        }
+        self.emit_histogram(client, self.send_hdr_time.inner(), "http.time.send_hdr");
+        self.emit_histogram(client, self.recv_hdr_time.inner(), "http.time.recv_hdr");
+        self.emit_histogram(client, self.total_time.inner(), "http.time.total");
+    }
+
+    fn summary(&self) {
+        Self::summary_histogram_title("# Connection Re-Usage:");
+        Self::summary_data_line("Req/Conn:", self.conn_reuse_count.inner());
+        Self::summary_histogram_title("# Duration Times");
+        Self::summary_duration_line("Connect:", self.conn_time.inner());
+        let tls_handshake_time = self.tls_handshake_time.inner();
+        if !tls_handshake_time.is_empty() {
+            Self::summary_duration_line("TlsHandshake:", tls_handshake_time);
+        }
+        Self::summary_duration_line("SendHdr:", self.send_hdr_time.inner());
+        Self::summary_duration_line("RecvHdr:", self.recv_hdr_time.inner());
+        Self::summary_duration_line("Total:", self.total_time.inner());
+        Self::summary_newline();
+        Self::summary_total_percentage(self.total_time.inner());
+    }
+
+    fn report(&self, report: &mut BenchReport) {
+        report.add_distribution("conn_reuse", self.conn_reuse_count.inner());
+        report.add_phase("connect", self.conn_time.inner());
+        let tls_handshake_time = self.tls_handshake_time.inner();
+        if !tls_handshake_time.is_empty() {
+            report.add_phase("tls_handshake", tls_handshake_time);
+        }
+        report.add_phase("send_hdr", self.send_hdr_time.inner());
+        report.add_phase("first_byte", self.recv_hdr_time.inner());
+        report.add_phase("total", self.total_time.inner());
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HttpHistogramRecorder {
+    conn_time: HistogramRecorder<u64>,
+    tls_handshake_time: HistogramRecorder<u64>,
+    send_hdr_time: HistogramRecorder<u64>,
+    recv_hdr_time: HistogramRecorder<u64>,
+    total_time: HistogramRecorder<u64>,
+    conn_reuse_count: HistogramRecorder<u64>,
+}
+
+impl HttpHistogramRecorder {
+    pub(crate) fn record_conn_time(&mut self, dur: Duration) {
+        let _ = self.conn_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_tls_handshake_time(&mut self, dur: Duration) {
+        let _ = self.tls_handshake_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_send_hdr_time(&mut self, dur: Duration) {
+        let _ = self.send_hdr_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_recv_hdr_time(&mut self, dur: Duration) {
+        let _ = self.recv_hdr_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_total_time(&mut self, dur: Duration) {
+        let _ = self.total_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_conn_reuse_count(&mut self, count: u64) {
+        let _ = self.conn_reuse_count.record(count);
+    }
+}
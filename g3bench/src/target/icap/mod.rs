@@ -0,0 +1,88 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use clap::{ArgMatches, Command};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{BenchTarget, BenchTaskContext, ProcArgs};
+
+mod opts;
+use opts::BenchIcapArgs;
+
+mod response;
+use response::IcapResponse;
+
+mod stats;
+pub(crate) use stats::{IcapHistogram, IcapHistogramRecorder, IcapRuntimeStats};
+
+mod task;
+use task::IcapTaskContext;
+
+pub const COMMAND: &str = "icap";
+
+type BoxIcapConnection = (
+    Box<dyn AsyncRead + Send + Unpin>,
+    Box<dyn AsyncWrite + Send + Unpin>,
+);
+
+struct IcapTarget {
+    args: Arc<BenchIcapArgs>,
+    proc_args: Arc<ProcArgs>,
+    stats: Arc<IcapRuntimeStats>,
+    histogram: Option<IcapHistogram>,
+    histogram_recorder: IcapHistogramRecorder,
+}
+
+impl BenchTarget<IcapRuntimeStats, IcapHistogram, IcapTaskContext> for IcapTarget {
+    fn new_context(&self) -> anyhow::Result<IcapTaskContext> {
+        IcapTaskContext::new(
+            &self.args,
+            &self.proc_args,
+            &self.stats,
+            self.histogram_recorder.clone(),
+        )
+    }
+
+    fn fetch_runtime_stats(&self) -> Arc<IcapRuntimeStats> {
+        self.stats.clone()
+    }
+
+    fn take_histogram(&mut self) -> Option<IcapHistogram> {
+        self.histogram.take()
+    }
+}
+
+pub fn command() -> Command {
+    opts::add_icap_args(Command::new(COMMAND))
+}
+
+pub async fn run(proc_args: &Arc<ProcArgs>, cmd_args: &ArgMatches) -> anyhow::Result<()> {
+    let mut icap_args = opts::parse_icap_args(cmd_args)?;
+    icap_args.resolve_target_address(proc_args).await?;
+
+    let (histogram, histogram_recorder) = IcapHistogram::new();
+    let target = IcapTarget {
+        args: Arc::new(icap_args),
+        proc_args: Arc::clone(proc_args),
+        stats: Arc::new(IcapRuntimeStats::default()),
+        histogram: Some(histogram),
+        histogram_recorder,
+    };
+
+    super::run(target, proc_args).await
+}
@@ -0,0 +1,480 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use http::{HeaderName, HeaderValue};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+use g3_icap_client::IcapMethod;
+use g3_types::collection::{SelectiveVec, WeightedValue};
+use g3_types::net::{HttpAuth, OpensslClientConfigBuilder, UpstreamAddr};
+
+use super::{BoxIcapConnection, ProcArgs};
+use crate::target::{
+    AppendOpensslArgs, AppendProxyProtocolArgs, OpensslTlsClientArgs, ProxyProtocolArgs,
+};
+
+const ICAP_ARG_URL: &str = "url";
+const ICAP_ARG_METHOD: &str = "method";
+const ICAP_ARG_LOCAL_ADDRESS: &str = "local-address";
+const ICAP_ARG_NO_KEEPALIVE: &str = "no-keepalive";
+const ICAP_ARG_ALLOW_204: &str = "allow-204";
+const ICAP_ARG_PREVIEW: &str = "preview";
+const ICAP_ARG_HTTP_URL: &str = "http-url";
+const ICAP_ARG_HTTP_HEADER: &str = "http-header";
+const ICAP_ARG_BODY_SIZE: &str = "body-size";
+const ICAP_ARG_TIMEOUT: &str = "timeout";
+const ICAP_ARG_HEADER_SIZE: &str = "header-size";
+const ICAP_ARG_CONNECT_TIMEOUT: &str = "connect-timeout";
+
+pub(super) struct BenchIcapArgs {
+    pub(super) method: IcapMethod,
+    service_url: Url,
+    bind: Option<IpAddr>,
+    pub(super) no_keepalive: bool,
+    allow_204: bool,
+    pub(super) preview_size: Option<usize>,
+    http_url: Url,
+    http_headers: Vec<(HeaderName, HeaderValue)>,
+    pub(super) body_size: usize,
+    pub(super) timeout: Duration,
+    pub(super) max_header_size: usize,
+    pub(super) connect_timeout: Duration,
+
+    target_tls: OpensslTlsClientArgs,
+    proxy_protocol: ProxyProtocolArgs,
+
+    host: UpstreamAddr,
+    auth: HttpAuth,
+    peer_addrs: Option<SelectiveVec<WeightedValue<SocketAddr>>>,
+}
+
+impl BenchIcapArgs {
+    fn new(mut url: Url) -> anyhow::Result<Self> {
+        let mut target_tls = OpensslTlsClientArgs::default();
+        let default_port = match url.scheme() {
+            "icap" => 1344,
+            "icaps" => {
+                target_tls.config = Some(OpensslClientConfigBuilder::with_cache_for_one_site());
+                11344
+            }
+            s => return Err(anyhow!("unsupported icap url scheme {s}")),
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("no host found in url {url}"))?;
+        let upstream =
+            UpstreamAddr::from_host_str_and_port(host, url.port().unwrap_or(default_port))
+                .map_err(|e| anyhow!("invalid host in url {url}: {e}"))?;
+        let auth = HttpAuth::try_from(&url)
+            .map_err(|e| anyhow!("failed to detect upstream auth method: {e}"))?;
+        url.set_username("")
+            .map_err(|_| anyhow!("failed to clear username in url"))?;
+        url.set_password(None)
+            .map_err(|_| anyhow!("failed to clear password in url"))?;
+
+        Ok(BenchIcapArgs {
+            method: IcapMethod::Options,
+            service_url: url,
+            bind: None,
+            no_keepalive: false,
+            allow_204: false,
+            preview_size: None,
+            http_url: Url::parse("http://www.example.net/").unwrap(),
+            http_headers: Vec::new(),
+            body_size: 0,
+            timeout: Duration::from_secs(30),
+            max_header_size: 8192,
+            connect_timeout: Duration::from_secs(15),
+            target_tls,
+            proxy_protocol: ProxyProtocolArgs::default(),
+            host: upstream,
+            auth,
+            peer_addrs: None,
+        })
+    }
+
+    pub(super) async fn resolve_target_address(
+        &mut self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<()> {
+        let addrs = proc_args.resolve(&self.host).await?;
+        self.peer_addrs = Some(addrs);
+        Ok(())
+    }
+
+    async fn new_tcp_connection(&self, proc_args: &ProcArgs) -> anyhow::Result<TcpStream> {
+        let addrs = self
+            .peer_addrs
+            .as_ref()
+            .ok_or_else(|| anyhow!("no peer address set"))?;
+        let peer = *proc_args.select_peer(addrs);
+
+        let socket = g3_socket::tcp::new_socket_to(
+            peer.ip(),
+            self.bind,
+            &Default::default(),
+            &Default::default(),
+            !self.no_keepalive,
+        )
+        .map_err(|e| anyhow!("failed to setup socket to {peer}: {e:?}"))?;
+        let mut stream = socket
+            .connect(peer)
+            .await
+            .map_err(|e| anyhow!("connect to {peer} error: {e:?}"))?;
+
+        if let Some(data) = self.proxy_protocol.data() {
+            stream
+                .write_all(data)
+                .await
+                .map_err(|e| anyhow!("failed to send proxy protocol data: {e:?}"))?;
+        }
+
+        Ok(stream)
+    }
+
+    pub(super) async fn new_icap_connection(
+        &self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<BoxIcapConnection> {
+        let stream = self
+            .new_tcp_connection(proc_args)
+            .await
+            .context(format!("failed to connect to icap server {}", self.host))?;
+
+        if let Some(tls_client) = &self.target_tls.client {
+            let tls_stream = self
+                .target_tls
+                .connect_target(tls_client, stream, &self.host)
+                .await?;
+            let (r, w) = tokio::io::split(tls_stream);
+            Ok((Box::new(r), Box::new(w)))
+        } else {
+            let (r, w) = stream.into_split();
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    fn write_http_request_header<W: Write>(&self, buf: &mut W, with_body: bool) {
+        let _ = if with_body {
+            write!(buf, "POST {} HTTP/1.1\r\n", self.http_url)
+        } else {
+            write!(buf, "GET {} HTTP/1.1\r\n", self.http_url)
+        };
+        if let Some(host) = self.http_url.host_str() {
+            match self.http_url.port() {
+                Some(port) => {
+                    let _ = write!(buf, "Host: {host}:{port}\r\n");
+                }
+                None => {
+                    let _ = write!(buf, "Host: {host}\r\n");
+                }
+            }
+        }
+        if self.method == IcapMethod::Reqmod {
+            self.write_custom_http_headers(buf);
+            if with_body {
+                let _ = write!(buf, "Content-Length: {}\r\n", self.body_size);
+            }
+        }
+        let _ = buf.write_all(b"\r\n");
+    }
+
+    fn write_http_response_header<W: Write>(&self, buf: &mut W) {
+        let _ = buf.write_all(b"HTTP/1.1 200 OK\r\n");
+        self.write_custom_http_headers(buf);
+        let _ = write!(buf, "Content-Length: {}\r\n\r\n", self.body_size);
+    }
+
+    fn write_custom_http_headers<W: Write>(&self, buf: &mut W) {
+        for (name, value) in &self.http_headers {
+            let _ = buf.write_all(name.as_str().as_bytes());
+            let _ = buf.write_all(b": ");
+            let _ = buf.write_all(value.as_bytes());
+            let _ = buf.write_all(b"\r\n");
+        }
+    }
+
+    /// Build the fixed ICAP request header along with the encapsulated HTTP header
+    pub(super) fn build_request_header(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(1024);
+        let _ = write!(
+            buf,
+            "{} {} ICAP/1.0\r\n",
+            self.method.as_str(),
+            self.service_url
+        );
+        let _ = write!(buf, "Host: {}\r\n", self.host.host());
+        let _ = write!(buf, "User-Agent: g3bench/{}\r\n", crate::build::VERSION);
+        if let HttpAuth::Basic(basic) = &self.auth {
+            let _ = write!(buf, "Authorization: Basic {}\r\n", basic.encoded_value());
+        }
+        if self.allow_204 {
+            buf.extend_from_slice(b"Allow: 204\r\n");
+        }
+        if self.no_keepalive {
+            buf.extend_from_slice(b"Connection: close\r\n");
+        }
+
+        let with_body = self.body_size > 0;
+        let mut http_header = Vec::with_capacity(512);
+        match self.method {
+            IcapMethod::Options => {
+                buf.extend_from_slice(b"Encapsulated: null-body=0\r\n\r\n");
+                return buf;
+            }
+            IcapMethod::Reqmod => {
+                self.write_http_request_header(&mut http_header, with_body);
+                if with_body {
+                    let _ = write!(
+                        buf,
+                        "Encapsulated: req-hdr=0, req-body={}\r\n",
+                        http_header.len()
+                    );
+                } else {
+                    let _ = write!(
+                        buf,
+                        "Encapsulated: req-hdr=0, null-body={}\r\n",
+                        http_header.len()
+                    );
+                }
+            }
+            IcapMethod::Respmod => {
+                self.write_http_request_header(&mut http_header, false);
+                let res_hdr = http_header.len();
+                self.write_http_response_header(&mut http_header);
+                let _ = if with_body {
+                    write!(
+                        buf,
+                        "Encapsulated: req-hdr=0, res-hdr={res_hdr}, res-body={}\r\n",
+                        http_header.len()
+                    )
+                } else {
+                    write!(
+                        buf,
+                        "Encapsulated: req-hdr=0, res-hdr={res_hdr}, null-body={}\r\n",
+                        http_header.len()
+                    )
+                };
+            }
+        }
+        if with_body {
+            if let Some(preview_size) = self.preview_size {
+                let _ = write!(buf, "Preview: {}\r\n", preview_size.min(self.body_size));
+            }
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf.extend_from_slice(&http_header);
+        buf
+    }
+}
+
+pub(super) fn add_icap_args(app: Command) -> Command {
+    app.arg(
+        Arg::new(ICAP_ARG_URL)
+            .help("ICAP service url, the scheme should be icap or icaps")
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_METHOD)
+            .value_name("METHOD")
+            .short('m')
+            .long(ICAP_ARG_METHOD)
+            .num_args(1)
+            .value_parser(["OPTIONS", "REQMOD", "RESPMOD"])
+            .ignore_case(true)
+            .default_value("OPTIONS"),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_LOCAL_ADDRESS)
+            .value_name("LOCAL IP ADDRESS")
+            .short('B')
+            .long(ICAP_ARG_LOCAL_ADDRESS)
+            .num_args(1)
+            .value_parser(value_parser!(IpAddr)),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_NO_KEEPALIVE)
+            .help("Disable icap keepalive")
+            .action(ArgAction::SetTrue)
+            .long(ICAP_ARG_NO_KEEPALIVE),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_ALLOW_204)
+            .help("Add 'Allow: 204' header to the request")
+            .action(ArgAction::SetTrue)
+            .long(ICAP_ARG_ALLOW_204),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_PREVIEW)
+            .help("Send preview data of this size")
+            .value_name("SIZE")
+            .long(ICAP_ARG_PREVIEW)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HTTP_URL)
+            .help("Url of the encapsulated http request")
+            .value_name("URL")
+            .long(ICAP_ARG_HTTP_URL)
+            .num_args(1)
+            .default_value("http://www.example.net/"),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HTTP_HEADER)
+            .help(
+                "Add header to the encapsulated http request or response, in 'Name: Value' format",
+            )
+            .value_name("HEADER")
+            .short('H')
+            .long(ICAP_ARG_HTTP_HEADER)
+            .action(ArgAction::Append)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_BODY_SIZE)
+            .help("Size of the encapsulated http body")
+            .value_name("SIZE")
+            .long(ICAP_ARG_BODY_SIZE)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("ICAP response timeout")
+            .default_value("30s")
+            .long(ICAP_ARG_TIMEOUT)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_HEADER_SIZE)
+            .value_name("SIZE")
+            .help("Set max response header size")
+            .long(ICAP_ARG_HEADER_SIZE)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(ICAP_ARG_CONNECT_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for connection to the icap server")
+            .default_value("15s")
+            .long(ICAP_ARG_CONNECT_TIMEOUT)
+            .num_args(1),
+    )
+    .append_openssl_args()
+    .append_proxy_protocol_args()
+}
+
+fn parse_http_header(s: &str) -> anyhow::Result<(HeaderName, HeaderValue)> {
+    let Some((name, value)) = s.split_once(':') else {
+        return Err(anyhow!("no ':' found in header {s}"));
+    };
+    let name = HeaderName::from_str(name.trim())
+        .map_err(|e| anyhow!("invalid header name {name}: {e}"))?;
+    let value = HeaderValue::from_str(value.trim())
+        .map_err(|e| anyhow!("invalid header value {value}: {e}"))?;
+    Ok((name, value))
+}
+
+pub(super) fn parse_icap_args(args: &ArgMatches) -> anyhow::Result<BenchIcapArgs> {
+    let url = if let Some(v) = args.get_one::<String>(ICAP_ARG_URL) {
+        Url::parse(v).context(format!("invalid {ICAP_ARG_URL} value"))?
+    } else {
+        return Err(anyhow!("no icap service url set"));
+    };
+
+    let mut icap_args = BenchIcapArgs::new(url)?;
+
+    if let Some(v) = args.get_one::<String>(ICAP_ARG_METHOD) {
+        icap_args.method = match v.to_ascii_uppercase().as_str() {
+            "OPTIONS" => IcapMethod::Options,
+            "REQMOD" => IcapMethod::Reqmod,
+            "RESPMOD" => IcapMethod::Respmod,
+            _ => return Err(anyhow!("unsupported icap method {v}")),
+        };
+    }
+
+    if let Some(ip) = args.get_one::<IpAddr>(ICAP_ARG_LOCAL_ADDRESS) {
+        icap_args.bind = Some(*ip);
+    }
+
+    if args.get_flag(ICAP_ARG_NO_KEEPALIVE) {
+        icap_args.no_keepalive = true;
+    }
+    if args.get_flag(ICAP_ARG_ALLOW_204) {
+        icap_args.allow_204 = true;
+    }
+
+    if let Some(size) = g3_clap::humanize::get_usize(args, ICAP_ARG_PREVIEW)? {
+        icap_args.preview_size = Some(size);
+    }
+
+    if let Some(v) = args.get_one::<String>(ICAP_ARG_HTTP_URL) {
+        let url = Url::parse(v).context(format!("invalid {ICAP_ARG_HTTP_URL} value"))?;
+        icap_args.http_url = url;
+    }
+
+    if let Some(headers) = args.get_many::<String>(ICAP_ARG_HTTP_HEADER) {
+        for h in headers {
+            let header = parse_http_header(h).context(format!("invalid {ICAP_ARG_HTTP_HEADER}"))?;
+            icap_args.http_headers.push(header);
+        }
+    }
+
+    if let Some(size) = g3_clap::humanize::get_usize(args, ICAP_ARG_BODY_SIZE)? {
+        icap_args.body_size = size;
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, ICAP_ARG_TIMEOUT)? {
+        icap_args.timeout = timeout;
+    }
+    if let Some(header_size) = g3_clap::humanize::get_usize(args, ICAP_ARG_HEADER_SIZE)? {
+        icap_args.max_header_size = header_size;
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, ICAP_ARG_CONNECT_TIMEOUT)? {
+        icap_args.connect_timeout = timeout;
+    }
+
+    icap_args
+        .target_tls
+        .parse_tls_args(args)
+        .context("invalid target tls config")?;
+    icap_args
+        .proxy_protocol
+        .parse_args(args)
+        .context("invalid proxy protocol config")?;
+
+    if icap_args.method == IcapMethod::Options {
+        if icap_args.body_size > 0 {
+            return Err(anyhow!("no body is allowed for OPTIONS method"));
+        }
+        if icap_args.preview_size.is_some() {
+            return Err(anyhow!("preview is not allowed for OPTIONS method"));
+        }
+    }
+
+    Ok(icap_args)
+}
@@ -0,0 +1,290 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use tokio::io::{AsyncBufRead, AsyncReadExt};
+
+use g3_http::{HttpBodyReader, HttpBodyType};
+use g3_io_ext::LimitedBufReadExt;
+
+pub(super) struct IcapResponse {
+    pub(super) code: u16,
+    pub(super) reason: String,
+    pub(super) keep_alive: bool,
+    /// the encapsulated header size and whether there is a chunked body
+    encapsulated: Option<(usize, bool)>,
+}
+
+impl IcapResponse {
+    pub(super) async fn parse<R>(reader: &mut R, max_header_size: usize) -> anyhow::Result<Self>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut line_buf = Vec::<u8>::with_capacity(1024);
+        let mut header_size = 0;
+
+        let (found, nr) = reader
+            .limited_read_until(b'\n', max_header_size, &mut line_buf)
+            .await
+            .map_err(|e| anyhow!("read response status line failed: {e}"))?;
+        if nr == 0 {
+            return Err(anyhow!("connection closed by server"));
+        }
+        if !found {
+            return if nr < max_header_size {
+                Err(anyhow!("connection closed while reading response header"))
+            } else {
+                Err(anyhow!("too large response header"))
+            };
+        }
+        header_size += nr;
+        let mut rsp = Self::parse_status_line(&line_buf)?;
+
+        loop {
+            if header_size >= max_header_size {
+                return Err(anyhow!("too large response header"));
+            }
+            line_buf.clear();
+            let max_len = max_header_size - header_size;
+            let (found, nr) = reader
+                .limited_read_until(b'\n', max_len, &mut line_buf)
+                .await
+                .map_err(|e| anyhow!("read response header failed: {e}"))?;
+            if nr == 0 {
+                return Err(anyhow!("connection closed by server"));
+            }
+            if !found {
+                return if nr < max_len {
+                    Err(anyhow!("connection closed while reading response header"))
+                } else {
+                    Err(anyhow!("too large response header"))
+                };
+            }
+            header_size += nr;
+            if (line_buf.len() == 1 && line_buf[0] == b'\n')
+                || (line_buf.len() == 2 && line_buf[0] == b'\r' && line_buf[1] == b'\n')
+            {
+                // header end line
+                break;
+            }
+            rsp.parse_header_line(&line_buf)?;
+        }
+
+        Ok(rsp)
+    }
+
+    fn parse_status_line(line: &[u8]) -> anyhow::Result<Self> {
+        let line = std::str::from_utf8(line).map_err(|_| anyhow!("invalid status line"))?;
+        let Some(left) = line.strip_prefix("ICAP/1.0 ") else {
+            return Err(anyhow!("invalid icap version in status line"));
+        };
+        let (code, reason) = left
+            .trim_end()
+            .split_once(' ')
+            .unwrap_or((left.trim_end(), ""));
+        let code = u16::from_str(code).map_err(|_| anyhow!("invalid status code {code}"))?;
+        if !(100..600).contains(&code) {
+            return Err(anyhow!("invalid status code {code}"));
+        }
+        Ok(IcapResponse {
+            code,
+            reason: reason.trim().to_string(),
+            keep_alive: true,
+            encapsulated: None,
+        })
+    }
+
+    fn parse_header_line(&mut self, line: &[u8]) -> anyhow::Result<()> {
+        let line = std::str::from_utf8(line).map_err(|_| anyhow!("invalid header line"))?;
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(anyhow!("invalid header line {}", line.trim_end()));
+        };
+        let value = value.trim();
+
+        match name.trim().to_lowercase().as_str() {
+            "connection" => {
+                for v in value.split(',') {
+                    if v.trim().eq_ignore_ascii_case("close") {
+                        self.keep_alive = false;
+                    }
+                }
+            }
+            "encapsulated" => {
+                let mut last: Option<(&str, usize)> = None;
+                for p in value.split(',') {
+                    let Some((name, offset)) = p.trim().split_once('=') else {
+                        return Err(anyhow!("invalid Encapsulated header value {value}"));
+                    };
+                    let offset = usize::from_str(offset.trim())
+                        .map_err(|_| anyhow!("invalid Encapsulated header value {value}"))?;
+                    last = Some((name.trim(), offset));
+                }
+                if let Some((name, offset)) = last {
+                    let has_body = !name.eq_ignore_ascii_case("null-body");
+                    self.encapsulated = Some((offset, has_body));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Read and drop the encapsulated http message
+    pub(super) async fn recv_encapsulated<R>(&self, reader: &mut R) -> anyhow::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let Some((header_size, has_body)) = self.encapsulated else {
+            return Ok(());
+        };
+
+        if header_size > 0 {
+            let nr = tokio::io::copy(&mut reader.take(header_size as u64), &mut tokio::io::sink())
+                .await
+                .map_err(|e| anyhow!("failed to read encapsulated header: {e}"))?;
+            if nr != header_size as u64 {
+                return Err(anyhow!(
+                    "connection closed while reading encapsulated header"
+                ));
+            }
+        }
+
+        if has_body {
+            let mut body_reader =
+                HttpBodyReader::new(reader, HttpBodyType::ChunkedWithoutTrailer, 2048);
+            tokio::io::copy(&mut body_reader, &mut tokio::io::sink())
+                .await
+                .map_err(|e| anyhow!("failed to read encapsulated body: {e}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    #[test]
+    fn status_line() {
+        let rsp = IcapResponse::parse_status_line(b"ICAP/1.0 200 OK\r\n").unwrap();
+        assert_eq!(rsp.code, 200);
+        assert_eq!(rsp.reason, "OK");
+        assert!(rsp.keep_alive);
+        assert!(rsp.encapsulated.is_none());
+
+        let rsp =
+            IcapResponse::parse_status_line(b"ICAP/1.0 404 ICAP Service Not Found\r\n").unwrap();
+        assert_eq!(rsp.code, 404);
+        assert_eq!(rsp.reason, "ICAP Service Not Found");
+
+        let rsp = IcapResponse::parse_status_line(b"ICAP/1.0 204\n").unwrap();
+        assert_eq!(rsp.code, 204);
+        assert_eq!(rsp.reason, "");
+
+        assert!(IcapResponse::parse_status_line(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(IcapResponse::parse_status_line(b"ICAP/1.0 abc OK\r\n").is_err());
+        assert!(IcapResponse::parse_status_line(b"ICAP/1.0 99 OK\r\n").is_err());
+        assert!(IcapResponse::parse_status_line(b"ICAP/1.0 600 OK\r\n").is_err());
+    }
+
+    #[test]
+    fn header_line() {
+        let mut rsp = IcapResponse::parse_status_line(b"ICAP/1.0 200 OK\r\n").unwrap();
+        rsp.parse_header_line(b"ISTag: \"abc\"\r\n").unwrap();
+        assert!(rsp.keep_alive);
+        rsp.parse_header_line(b"Connection: keep-alive, Close\r\n")
+            .unwrap();
+        assert!(!rsp.keep_alive);
+
+        assert!(rsp.parse_header_line(b"invalid header\r\n").is_err());
+    }
+
+    #[test]
+    fn encapsulated() {
+        let mut rsp = IcapResponse::parse_status_line(b"ICAP/1.0 200 OK\r\n").unwrap();
+        rsp.parse_header_line(b"Encapsulated: res-hdr=0, res-body=137\r\n")
+            .unwrap();
+        assert_eq!(rsp.encapsulated, Some((137, true)));
+
+        rsp.parse_header_line(b"encapsulated: req-hdr=0, null-body=75\r\n")
+            .unwrap();
+        assert_eq!(rsp.encapsulated, Some((75, false)));
+
+        rsp.parse_header_line(b"Encapsulated: null-body=0\r\n")
+            .unwrap();
+        assert_eq!(rsp.encapsulated, Some((0, false)));
+
+        assert!(rsp.parse_header_line(b"Encapsulated: res-hdr\r\n").is_err());
+        assert!(rsp
+            .parse_header_line(b"Encapsulated: res-hdr=abc\r\n")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn parse_full() {
+        let data: &[u8] = b"ICAP/1.0 200 OK\r\n\
+            ISTag: \"test\"\r\n\
+            Encapsulated: res-hdr=0, res-body=19\r\n\
+            \r\n\
+            HTTP/1.1 200 OK\r\n\r\n\
+            5\r\nhello\r\n0\r\n\r\n\
+            ICAP/1.0 204 No Content\r\n\
+            Connection: close\r\n\
+            Encapsulated: null-body=0\r\n\
+            \r\n";
+        let mut reader = BufReader::new(data);
+
+        let rsp = IcapResponse::parse(&mut reader, 4096).await.unwrap();
+        assert_eq!(rsp.code, 200);
+        assert!(rsp.keep_alive);
+        assert_eq!(rsp.encapsulated, Some((19, true)));
+        rsp.recv_encapsulated(&mut reader).await.unwrap();
+
+        let rsp = IcapResponse::parse(&mut reader, 4096).await.unwrap();
+        assert_eq!(rsp.code, 204);
+        assert_eq!(rsp.reason, "No Content");
+        assert!(!rsp.keep_alive);
+        assert_eq!(rsp.encapsulated, Some((0, false)));
+        rsp.recv_encapsulated(&mut reader).await.unwrap();
+
+        let mut left = Vec::new();
+        reader.read_to_end(&mut left).await.unwrap();
+        assert!(left.is_empty());
+    }
+
+    #[tokio::test]
+    async fn parse_error() {
+        let mut reader = BufReader::new(&b""[..]);
+        assert!(IcapResponse::parse(&mut reader, 4096).await.is_err());
+
+        let mut reader = BufReader::new(&b"ICAP/1.0 200 OK\r\nISTag: \"test\"\r\n"[..]);
+        assert!(IcapResponse::parse(&mut reader, 4096).await.is_err());
+
+        let data: &[u8] = b"ICAP/1.0 200 OK\r\nISTag: \"test\"\r\n\r\n";
+        let mut reader = BufReader::new(data);
+        assert!(IcapResponse::parse(&mut reader, 24).await.is_err());
+
+        let data: &[u8] = b"ICAP/1.0 200 OK\r\nEncapsulated: res-hdr=0, res-body=64\r\n\r\n";
+        let mut reader = BufReader::new(data);
+        let rsp = IcapResponse::parse(&mut reader, 4096).await.unwrap();
+        assert!(rsp.recv_encapsulated(&mut reader).await.is_err());
+    }
+}
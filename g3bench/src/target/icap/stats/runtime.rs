@@ -0,0 +1,194 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use g3_io_ext::{LimitedReaderStats, LimitedWriterStats};
+use g3_statsd_client::StatsdClient;
+
+use crate::target::BenchRuntimeStats;
+
+#[derive(Default)]
+pub(crate) struct IcapRuntimeStats {
+    task_total: AtomicU64,
+    task_alive: AtomicI64,
+    task_passed: AtomicU64,
+    task_failed: AtomicU64,
+    conn_attempt: AtomicU64,
+    conn_attempt_total: AtomicU64,
+    conn_success: AtomicU64,
+    conn_success_total: AtomicU64,
+    conn_close_error: AtomicU64,
+    conn_close_timeout: AtomicU64,
+
+    rsp_100: AtomicU64,
+    rsp_100_total: AtomicU64,
+    rsp_200: AtomicU64,
+    rsp_200_total: AtomicU64,
+    rsp_204: AtomicU64,
+    rsp_204_total: AtomicU64,
+    rsp_206: AtomicU64,
+    rsp_206_total: AtomicU64,
+    rsp_other: AtomicU64,
+    rsp_other_total: AtomicU64,
+
+    tcp_read: AtomicU64,
+    tcp_write: AtomicU64,
+    tcp_read_total: AtomicU64,
+    tcp_write_total: AtomicU64,
+}
+
+impl IcapRuntimeStats {
+    pub(crate) fn add_task_total(&self) {
+        self.task_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_task_alive(&self) {
+        self.task_alive.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dec_task_alive(&self) {
+        self.task_alive.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task_passed(&self) {
+        self.task_passed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task_failed(&self) {
+        self.task_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_attempt(&self) {
+        self.conn_attempt.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_success(&self) {
+        self.conn_success.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_close_fail(&self) {
+        self.conn_close_error.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_close_timeout(&self) {
+        self.conn_close_timeout.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_rsp_code(&self, code: u16) {
+        match code {
+            100 => self.rsp_100.fetch_add(1, Ordering::Relaxed),
+            200 => self.rsp_200.fetch_add(1, Ordering::Relaxed),
+            204 => self.rsp_204.fetch_add(1, Ordering::Relaxed),
+            206 => self.rsp_206.fetch_add(1, Ordering::Relaxed),
+            _ => self.rsp_other.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+}
+
+impl LimitedReaderStats for IcapRuntimeStats {
+    fn add_read_bytes(&self, size: usize) {
+        self.tcp_read.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+impl LimitedWriterStats for IcapRuntimeStats {
+    fn add_write_bytes(&self, size: usize) {
+        self.tcp_write.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+impl BenchRuntimeStats for IcapRuntimeStats {
+    fn emit(&self, client: &mut StatsdClient) {
+        macro_rules! emit_count {
+            ($field:ident, $name:literal) => {
+                let $field = self.$field.swap(0, Ordering::Relaxed);
+                client.count(concat!("icap.", $name), $field).send();
+            };
+            ($field:ident, $total:ident, $name:literal) => {
+                emit_count!($field, $name);
+                self.$total.fetch_add($field, Ordering::Relaxed);
+            };
+        }
+
+        let task_alive = self.task_alive.load(Ordering::Relaxed);
+        client.gauge("icap.task.alive", task_alive).send();
+
+        emit_count!(task_total, "task.total");
+        emit_count!(task_passed, "task.passed");
+        emit_count!(task_failed, "task.failed");
+        emit_count!(conn_attempt, conn_attempt_total, "connection.attempt");
+        emit_count!(conn_success, conn_success_total, "connection.success");
+        emit_count!(rsp_100, rsp_100_total, "response.100");
+        emit_count!(rsp_200, rsp_200_total, "response.200");
+        emit_count!(rsp_204, rsp_204_total, "response.204");
+        emit_count!(rsp_206, rsp_206_total, "response.206");
+        emit_count!(rsp_other, rsp_other_total, "response.other");
+        emit_count!(tcp_write, tcp_write_total, "io.tcp.write");
+        emit_count!(tcp_read, tcp_read_total, "io.tcp.read");
+    }
+
+    fn summary(&self, total_time: Duration) {
+        macro_rules! load_total {
+            ($field:ident, $total:ident) => {
+                self.$total.load(Ordering::Relaxed) + self.$field.load(Ordering::Relaxed)
+            };
+        }
+
+        let total_secs = total_time.as_secs_f64();
+
+        println!("# Connections");
+        let total_attempt = load_total!(conn_attempt, conn_attempt_total);
+        println!("Attempt count: {total_attempt}");
+        let total_success = load_total!(conn_success, conn_success_total);
+        println!("Success count: {total_success}");
+        println!(
+            "Success ratio: {:.2}%",
+            (total_success as f64 / total_attempt as f64) * 100.0
+        );
+        println!("Success rate:  {:.3}/s", total_success as f64 / total_secs);
+        let close_error = self.conn_close_error.load(Ordering::Relaxed);
+        if close_error > 0 {
+            println!("Close error:   {close_error}");
+        }
+        let close_timeout = self.conn_close_timeout.load(Ordering::Relaxed);
+        if close_timeout > 0 {
+            println!("Close timeout: {close_timeout}");
+        }
+
+        println!("# Result Codes");
+        let total_100 = load_total!(rsp_100, rsp_100_total);
+        if total_100 > 0 {
+            println!("100 Continue:  {total_100}");
+        }
+        println!("200 OK:        {}", load_total!(rsp_200, rsp_200_total));
+        println!("204 No Change: {}", load_total!(rsp_204, rsp_204_total));
+        let total_206 = load_total!(rsp_206, rsp_206_total);
+        if total_206 > 0 {
+            println!("206 Partial:   {total_206}");
+        }
+        println!("Others:        {}", load_total!(rsp_other, rsp_other_total));
+
+        println!("# Traffic");
+        let total_send = load_total!(tcp_write, tcp_write_total);
+        println!("Send bytes:    {total_send}");
+        println!("Send rate:     {:.3}B/s", total_send as f64 / total_secs);
+        let total_recv = load_total!(tcp_read, tcp_read_total);
+        println!("Recv bytes:    {total_recv}");
+        println!("Recv rate:     {:.3}B/s", total_recv as f64 / total_secs);
+    }
+}
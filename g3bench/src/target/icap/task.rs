@@ -0,0 +1,305 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use futures_util::FutureExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::time::Instant;
+
+use g3_io_ext::{LimitedReader, LimitedWriter};
+
+use super::{
+    BenchIcapArgs, BenchTaskContext, IcapHistogramRecorder, IcapResponse, IcapRuntimeStats,
+    ProcArgs,
+};
+use crate::target::{BenchError, BenchErrorType};
+
+struct SavedIcapConnection {
+    reader: BufReader<LimitedReader<Box<dyn AsyncRead + Send + Unpin>>>,
+    writer: LimitedWriter<Box<dyn AsyncWrite + Send + Unpin>>,
+}
+
+pub(super) struct IcapTaskContext {
+    args: Arc<BenchIcapArgs>,
+    proc_args: Arc<ProcArgs>,
+    saved_connection: Option<SavedIcapConnection>,
+    reuse_conn_count: u64,
+
+    runtime_stats: Arc<IcapRuntimeStats>,
+    histogram_recorder: IcapHistogramRecorder,
+
+    req_header: Vec<u8>,
+    body: Vec<u8>,
+}
+
+impl IcapTaskContext {
+    pub(super) fn new(
+        args: &Arc<BenchIcapArgs>,
+        proc_args: &Arc<ProcArgs>,
+        runtime_stats: &Arc<IcapRuntimeStats>,
+        histogram_recorder: IcapHistogramRecorder,
+    ) -> anyhow::Result<Self> {
+        let req_header = args.build_request_header();
+        let mut body = vec![0u8; args.body_size];
+        openssl::rand::rand_bytes(&mut body)
+            .map_err(|e| anyhow!("failed to generate body data: {e}"))?;
+
+        Ok(IcapTaskContext {
+            args: Arc::clone(args),
+            proc_args: Arc::clone(proc_args),
+            saved_connection: None,
+            reuse_conn_count: 0,
+            runtime_stats: Arc::clone(runtime_stats),
+            histogram_recorder,
+            req_header,
+            body,
+        })
+    }
+
+    async fn fetch_connection(&mut self) -> anyhow::Result<SavedIcapConnection> {
+        if let Some(mut c) = self.saved_connection.take() {
+            let mut buf = [0u8; 4];
+            if c.reader.read(&mut buf).now_or_never().is_none() {
+                // no eof, reuse the old connection
+                self.reuse_conn_count += 1;
+                return Ok(c);
+            }
+        }
+
+        self.histogram_recorder
+            .record_conn_reuse_count(self.reuse_conn_count);
+        self.reuse_conn_count = 0;
+
+        self.runtime_stats.add_conn_attempt();
+        let (r, w) = match tokio::time::timeout(
+            self.args.connect_timeout,
+            self.args.new_icap_connection(&self.proc_args),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to get new connection")),
+        };
+        self.runtime_stats.add_conn_success();
+
+        let r = LimitedReader::new(
+            r,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_south,
+            self.runtime_stats.clone() as _,
+        );
+        let w = LimitedWriter::new(
+            w,
+            self.proc_args.tcp_sock_speed_limit.shift_millis,
+            self.proc_args.tcp_sock_speed_limit.max_north,
+            self.runtime_stats.clone() as _,
+        );
+        Ok(SavedIcapConnection {
+            reader: BufReader::new(r),
+            writer: w,
+        })
+    }
+
+    async fn send_chunk<W>(writer: &mut W, data: &[u8]) -> anyhow::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let chunk_header = format!("{:x}\r\n", data.len());
+        writer
+            .write_all(chunk_header.as_bytes())
+            .await
+            .map_err(|e| anyhow!("failed to send chunk header: {e:?}"))?;
+        writer
+            .write_all(data)
+            .await
+            .map_err(|e| anyhow!("failed to send chunk data: {e:?}"))?;
+        writer
+            .write_all(b"\r\n")
+            .await
+            .map_err(|e| anyhow!("failed to send chunk end: {e:?}"))
+    }
+
+    async fn send_request(&self, connection: &mut SavedIcapConnection) -> anyhow::Result<bool> {
+        let writer = &mut connection.writer;
+        writer
+            .write_all(&self.req_header)
+            .await
+            .map_err(|e| anyhow!("failed to send request header: {e:?}"))?;
+
+        let mut wait_continue = false;
+        if !self.body.is_empty() {
+            let preview_size = self
+                .args
+                .preview_size
+                .map(|n| n.min(self.body.len()))
+                .unwrap_or(self.body.len());
+            Self::send_chunk(writer, &self.body[..preview_size]).await?;
+            let body_end: &[u8] = if self.args.preview_size.is_none() {
+                b"0\r\n\r\n"
+            } else if preview_size == self.body.len() {
+                b"0; ieof\r\n\r\n"
+            } else {
+                wait_continue = true;
+                b"0\r\n\r\n"
+            };
+            writer
+                .write_all(body_end)
+                .await
+                .map_err(|e| anyhow!("failed to send body end: {e:?}"))?;
+        }
+
+        writer
+            .flush()
+            .await
+            .map_err(|e| anyhow!("failed to flush request: {e:?}"))?;
+        Ok(wait_continue)
+    }
+
+    async fn send_left_body(&self, connection: &mut SavedIcapConnection) -> anyhow::Result<()> {
+        let preview_size = self.args.preview_size.unwrap_or_default();
+        let writer = &mut connection.writer;
+        Self::send_chunk(writer, &self.body[preview_size..]).await?;
+        writer
+            .write_all(b"0\r\n\r\n")
+            .await
+            .map_err(|e| anyhow!("failed to send body end: {e:?}"))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| anyhow!("failed to flush body: {e:?}"))
+    }
+
+    async fn recv_response(
+        &self,
+        connection: &mut SavedIcapConnection,
+    ) -> anyhow::Result<IcapResponse> {
+        match tokio::time::timeout(
+            self.args.timeout,
+            IcapResponse::parse(&mut connection.reader, self.args.max_header_size),
+        )
+        .await
+        {
+            Ok(Ok(rsp)) => {
+                self.runtime_stats.add_rsp_code(rsp.code);
+                Ok(rsp)
+            }
+            Ok(Err(e)) => Err(anyhow!("failed to read response: {e}")),
+            Err(_) => Err(BenchErrorType::Timeout.error("timeout to read response")),
+        }
+    }
+
+    async fn run_with_connection(
+        &mut self,
+        time_started: Instant,
+        connection: &mut SavedIcapConnection,
+    ) -> anyhow::Result<bool> {
+        let wait_continue = self.send_request(connection).await?;
+        let send_req_time = time_started.elapsed();
+        self.histogram_recorder.record_send_req_time(send_req_time);
+
+        let mut rsp = self.recv_response(connection).await?;
+        if wait_continue && rsp.code == 100 {
+            self.send_left_body(connection).await?;
+            rsp = self.recv_response(connection).await?;
+        }
+
+        let recv_hdr_time = time_started.elapsed();
+        self.histogram_recorder.record_recv_hdr_time(recv_hdr_time);
+        if !(200..300).contains(&rsp.code) {
+            return Err(
+                BenchErrorType::Status.error(format!("Got rsp code {} {}", rsp.code, rsp.reason))
+            );
+        }
+
+        match tokio::time::timeout(
+            self.args.timeout,
+            rsp.recv_encapsulated(&mut connection.reader),
+        )
+        .await
+        {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e),
+            Err(_) => {
+                return Err(BenchErrorType::Timeout.error("timeout to read encapsulated data"))
+            }
+        }
+
+        Ok(!self.args.no_keepalive && rsp.keep_alive)
+    }
+}
+
+impl BenchTaskContext for IcapTaskContext {
+    fn mark_task_start(&self) {
+        self.runtime_stats.add_task_total();
+        self.runtime_stats.inc_task_alive();
+    }
+
+    fn mark_task_passed(&self) {
+        self.runtime_stats.add_task_passed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    fn mark_task_failed(&self) {
+        self.runtime_stats.add_task_failed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    async fn run(&mut self, _task_id: usize, time_started: Instant) -> Result<(), BenchError> {
+        let mut connection = self
+            .fetch_connection()
+            .await
+            .context(BenchErrorType::Connect)
+            .context("connect to icap server failed")
+            .map_err(BenchError::Fatal)?;
+
+        match self
+            .run_with_connection(time_started, &mut connection)
+            .await
+        {
+            Ok(keep_alive) => {
+                let total_time = time_started.elapsed();
+                self.histogram_recorder.record_total_time(total_time);
+
+                if keep_alive {
+                    self.saved_connection = Some(connection);
+                } else {
+                    let runtime_stats = self.runtime_stats.clone();
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(
+                            Duration::from_secs(4),
+                            connection.writer.shutdown(),
+                        )
+                        .await
+                        {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(_e)) => runtime_stats.add_conn_close_fail(),
+                            Err(_) => runtime_stats.add_conn_close_timeout(),
+                        }
+                    });
+                }
+                Ok(())
+            }
+            Err(e) => Err(BenchError::Task(e)),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use g3_histogram::{HistogramRecorder, KeepingHistogram};
+use g3_statsd_client::StatsdClient;
+use g3_types::ext::DurationExt;
+
+use super::BenchHistogram;
+use crate::report::BenchReport;
+
+/// Histogram for the open-loop mode.
+///
+/// The service time is measured from the real send time, and the response time is measured
+/// from the intended send time, which will include the queueing delay at the client side.
+/// The time of failed requests is also measured from the intended send time, but is kept in
+/// a separate histogram, as they may fail much faster or slower than the passed ones.
+pub(super) struct OpenLoopHistogram {
+    service_time: KeepingHistogram<u64>,
+    response_time: KeepingHistogram<u64>,
+    failed_time: KeepingHistogram<u64>,
+}
+
+impl OpenLoopHistogram {
+    pub(super) fn new() -> (Self, OpenLoopHistogramRecorder) {
+        let (service_time_h, service_time_r) = KeepingHistogram::new();
+        let (response_time_h, response_time_r) = KeepingHistogram::new();
+        let (failed_time_h, failed_time_r) = KeepingHistogram::new();
+        let h = OpenLoopHistogram {
+            service_time: service_time_h,
+            response_time: response_time_h,
+            failed_time: failed_time_h,
+        };
+        let r = OpenLoopHistogramRecorder {
+            service_time: service_time_r,
+            response_time: response_time_r,
+            failed_time: failed_time_r,
+        };
+        (h, r)
+    }
+}
+
+impl BenchHistogram for OpenLoopHistogram {
+    fn refresh(&mut self) {
+        self.service_time.refresh().unwrap();
+        self.response_time.refresh().unwrap();
+        self.failed_time.refresh().unwrap();
+    }
+
+    fn emit(&self, client: &mut StatsdClient) {
+        self.emit_histogram(client, self.service_time.inner(), "open_loop.time.service");
+        self.emit_histogram(
+            client,
+            self.response_time.inner(),
+            "open_loop.time.response",
+        );
+        let failed_time = self.failed_time.inner();
+        if !failed_time.is_empty() {
+            self.emit_histogram(client, failed_time, "open_loop.time.failed");
+        }
+    }
+
+    fn summary(&self) {
+        Self::summary_histogram_title("# Open Loop Times");
+        Self::summary_duration_line("Service:", self.service_time.inner());
+        Self::summary_duration_line("Response:", self.response_time.inner());
+        let failed_time = self.failed_time.inner();
+        if !failed_time.is_empty() {
+            Self::summary_duration_line("Failed:", failed_time);
+        }
+        Self::summary_newline();
+        Self::summary_total_percentage(self.response_time.inner());
+    }
+
+    fn report(&self, report: &mut BenchReport) {
+        report.add_phase("open_loop_service", self.service_time.inner());
+        report.add_phase("open_loop_response", self.response_time.inner());
+        let failed_time = self.failed_time.inner();
+        if !failed_time.is_empty() {
+            report.add_phase("open_loop_failed", failed_time);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct OpenLoopHistogramRecorder {
+    service_time: HistogramRecorder<u64>,
+    response_time: HistogramRecorder<u64>,
+    failed_time: HistogramRecorder<u64>,
+}
+
+impl OpenLoopHistogramRecorder {
+    pub(super) fn record_service_time(&mut self, dur: Duration) {
+        let _ = self.service_time.record(dur.as_nanos_u64());
+    }
+
+    pub(super) fn record_response_time(&mut self, dur: Duration) {
+        let _ = self.response_time.record(dur.as_nanos_u64());
+    }
+
+    pub(super) fn record_failed_time(&mut self, dur: Duration) {
+        let _ = self.failed_time.record(dur.as_nanos_u64());
+    }
+}
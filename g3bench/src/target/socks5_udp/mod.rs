@@ -0,0 +1,81 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use clap::{ArgMatches, Command};
+
+use super::{BenchTarget, BenchTaskContext, ProcArgs};
+
+mod opts;
+use opts::BenchSocks5UdpArgs;
+
+mod stats;
+use stats::{Socks5UdpHistogram, Socks5UdpHistogramRecorder, Socks5UdpRuntimeStats};
+
+mod task;
+use task::Socks5UdpTaskContext;
+
+pub const COMMAND: &str = "socks5-udp";
+
+struct Socks5UdpTarget {
+    args: Arc<BenchSocks5UdpArgs>,
+    proc_args: Arc<ProcArgs>,
+    stats: Arc<Socks5UdpRuntimeStats>,
+    histogram: Option<Socks5UdpHistogram>,
+    histogram_recorder: Socks5UdpHistogramRecorder,
+}
+
+impl BenchTarget<Socks5UdpRuntimeStats, Socks5UdpHistogram, Socks5UdpTaskContext>
+    for Socks5UdpTarget
+{
+    fn new_context(&self) -> anyhow::Result<Socks5UdpTaskContext> {
+        Socks5UdpTaskContext::new(
+            &self.args,
+            &self.proc_args,
+            &self.stats,
+            self.histogram_recorder.clone(),
+        )
+    }
+
+    fn fetch_runtime_stats(&self) -> Arc<Socks5UdpRuntimeStats> {
+        self.stats.clone()
+    }
+
+    fn take_histogram(&mut self) -> Option<Socks5UdpHistogram> {
+        self.histogram.take()
+    }
+}
+
+pub fn command() -> Command {
+    opts::add_socks5_udp_args(Command::new(COMMAND))
+}
+
+pub async fn run(proc_args: &Arc<ProcArgs>, cmd_args: &ArgMatches) -> anyhow::Result<()> {
+    let mut udp_args = opts::parse_socks5_udp_args(cmd_args)?;
+    udp_args.resolve_proxy_address(proc_args).await?;
+
+    let (histogram, histogram_recorder) = Socks5UdpHistogram::new();
+    let target = Socks5UdpTarget {
+        args: Arc::new(udp_args),
+        proc_args: Arc::clone(proc_args),
+        stats: Arc::new(Socks5UdpRuntimeStats::default()),
+        histogram: Some(histogram),
+        histogram_recorder,
+    };
+
+    super::run(target, proc_args).await
+}
@@ -0,0 +1,251 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use tokio::net::{TcpStream, UdpSocket};
+use url::Url;
+
+use g3_socks::v5::UdpOutput;
+use g3_types::collection::{SelectiveVec, WeightedValue};
+use g3_types::net::{Proxy, Socks5Proxy, UpstreamAddr};
+
+use super::ProcArgs;
+
+const UDP_ARG_TARGET: &str = "target";
+const UDP_ARG_PROXY: &str = "proxy";
+const UDP_ARG_LOCAL_ADDRESS: &str = "local-address";
+const UDP_ARG_PAYLOAD_SIZE: &str = "payload-size";
+const UDP_ARG_NO_KEEPALIVE: &str = "no-keepalive";
+const UDP_ARG_TIMEOUT: &str = "timeout";
+const UDP_ARG_CONNECT_TIMEOUT: &str = "connect-timeout";
+
+/// the payload should be large enough to contain the sequence number
+const MIN_PAYLOAD_SIZE: usize = 8;
+
+pub(super) struct Socks5UdpAssociation {
+    pub(super) ctl_stream: TcpStream,
+    pub(super) socket: UdpSocket,
+}
+
+pub(super) struct BenchSocks5UdpArgs {
+    target: UpstreamAddr,
+    proxy: Socks5Proxy,
+    bind: Option<IpAddr>,
+    pub(super) payload_size: usize,
+    pub(super) no_keepalive: bool,
+    pub(super) timeout: Duration,
+    pub(super) connect_timeout: Duration,
+
+    proxy_addrs: Option<SelectiveVec<WeightedValue<SocketAddr>>>,
+}
+
+impl BenchSocks5UdpArgs {
+    fn new(target: UpstreamAddr, proxy: Socks5Proxy) -> Self {
+        BenchSocks5UdpArgs {
+            target,
+            proxy,
+            bind: None,
+            payload_size: 64,
+            no_keepalive: false,
+            timeout: Duration::from_secs(5),
+            connect_timeout: Duration::from_secs(10),
+            proxy_addrs: None,
+        }
+    }
+
+    pub(super) async fn resolve_proxy_address(
+        &mut self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<()> {
+        let addrs = proc_args.resolve(self.proxy.peer()).await?;
+        self.proxy_addrs = Some(addrs);
+        Ok(())
+    }
+
+    /// Build the udp packet with the socks5 udp header, the payload will be filled with zero
+    pub(super) fn new_packet(&self) -> (Vec<u8>, usize) {
+        let header_len = UdpOutput::calc_header_len(&self.target);
+        let mut buf = vec![0u8; header_len + self.payload_size];
+        UdpOutput::generate_header(&mut buf[0..header_len], &self.target);
+        (buf, header_len)
+    }
+
+    pub(super) async fn new_udp_association(
+        &self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<Socks5UdpAssociation> {
+        let addrs = self
+            .proxy_addrs
+            .as_ref()
+            .ok_or_else(|| anyhow!("no proxy addr set"))?;
+        let peer = *proc_args.select_peer(addrs);
+
+        let socket = g3_socket::tcp::new_socket_to(
+            peer.ip(),
+            self.bind,
+            &Default::default(),
+            &Default::default(),
+            true,
+        )
+        .map_err(|e| anyhow!("failed to setup socket to peer {peer}: {e:?}"))?;
+        let stream = socket
+            .connect(peer)
+            .await
+            .map_err(|e| anyhow!("connect to {peer} error: {e:?}"))?;
+        let (mut r, mut w) = stream.into_split();
+
+        let socket = g3_socket::udp::new_std_socket_to(
+            peer,
+            self.bind,
+            Default::default(),
+            Default::default(),
+        )
+        .map_err(|e| anyhow!("failed to setup local udp socket: {e}"))?;
+        let local_udp_addr = socket
+            .local_addr()
+            .map_err(|e| anyhow!("failed to get local addr of udp socket: {e}"))?;
+
+        let peer_udp_addr = g3_socks::v5::client::socks5_udp_associate(
+            &mut r,
+            &mut w,
+            &self.proxy.auth,
+            local_udp_addr,
+        )
+        .await
+        .map_err(|e| anyhow!("socks5 udp associate to {} failed: {e}", self.proxy.peer()))?;
+
+        socket
+            .connect(peer_udp_addr)
+            .map_err(|e| anyhow!("failed to connect local udp socket to {peer_udp_addr}: {e}"))?;
+        let socket = UdpSocket::from_std(socket)
+            .map_err(|e| anyhow!("failed to convert to tokio udp socket: {e}"))?;
+
+        Ok(Socks5UdpAssociation {
+            ctl_stream: r.reunite(w).unwrap(),
+            socket,
+        })
+    }
+}
+
+pub(super) fn add_socks5_udp_args(app: Command) -> Command {
+    app.arg(
+        Arg::new(UDP_ARG_TARGET)
+            .help("Target udp echo server address")
+            .required(true)
+            .num_args(1)
+            .value_parser(value_parser!(UpstreamAddr)),
+    )
+    .arg(
+        Arg::new(UDP_ARG_PROXY)
+            .value_name("PROXY URL")
+            .short('x')
+            .help("The socks5 proxy to use")
+            .long(UDP_ARG_PROXY)
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(UDP_ARG_LOCAL_ADDRESS)
+            .value_name("LOCAL IP ADDRESS")
+            .short('B')
+            .long(UDP_ARG_LOCAL_ADDRESS)
+            .num_args(1)
+            .value_parser(value_parser!(IpAddr)),
+    )
+    .arg(
+        Arg::new(UDP_ARG_PAYLOAD_SIZE)
+            .help("Size of the udp payload")
+            .value_name("SIZE")
+            .long(UDP_ARG_PAYLOAD_SIZE)
+            .num_args(1)
+            .value_parser(value_parser!(usize))
+            .default_value("64"),
+    )
+    .arg(
+        Arg::new(UDP_ARG_NO_KEEPALIVE)
+            .help("Use a new udp associate session for each packet")
+            .action(ArgAction::SetTrue)
+            .long(UDP_ARG_NO_KEEPALIVE),
+    )
+    .arg(
+        Arg::new(UDP_ARG_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout to wait for the echo packet")
+            .default_value("5s")
+            .long(UDP_ARG_TIMEOUT)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(UDP_ARG_CONNECT_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for udp associate to the proxy")
+            .default_value("10s")
+            .long(UDP_ARG_CONNECT_TIMEOUT)
+            .num_args(1),
+    )
+}
+
+pub(super) fn parse_socks5_udp_args(args: &ArgMatches) -> anyhow::Result<BenchSocks5UdpArgs> {
+    let target = if let Some(v) = args.get_one::<UpstreamAddr>(UDP_ARG_TARGET) {
+        v.clone()
+    } else {
+        return Err(anyhow!("no target set"));
+    };
+
+    let proxy = if let Some(v) = args.get_one::<String>(UDP_ARG_PROXY) {
+        let url = Url::parse(v).context(format!("invalid {UDP_ARG_PROXY} value"))?;
+        let proxy = Proxy::try_from(&url).map_err(|e| anyhow!("invalid proxy: {e}"))?;
+        let Proxy::Socks5(proxy) = proxy else {
+            return Err(anyhow!("unsupported proxy {v}"));
+        };
+        proxy
+    } else {
+        return Err(anyhow!("no proxy set"));
+    };
+
+    let mut udp_args = BenchSocks5UdpArgs::new(target, proxy);
+
+    if let Some(ip) = args.get_one::<IpAddr>(UDP_ARG_LOCAL_ADDRESS) {
+        udp_args.bind = Some(*ip);
+    }
+
+    if let Some(size) = args.get_one::<usize>(UDP_ARG_PAYLOAD_SIZE) {
+        if *size < MIN_PAYLOAD_SIZE {
+            return Err(anyhow!(
+                "the payload size should be at least {MIN_PAYLOAD_SIZE}"
+            ));
+        }
+        udp_args.payload_size = *size;
+    }
+
+    if args.get_flag(UDP_ARG_NO_KEEPALIVE) {
+        udp_args.no_keepalive = true;
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, UDP_ARG_TIMEOUT)? {
+        udp_args.timeout = timeout;
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, UDP_ARG_CONNECT_TIMEOUT)? {
+        udp_args.connect_timeout = timeout;
+    }
+
+    Ok(udp_args)
+}
@@ -0,0 +1,179 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use g3_statsd_client::StatsdClient;
+
+use crate::target::BenchRuntimeStats;
+
+#[derive(Default)]
+pub(crate) struct Socks5UdpRuntimeStats {
+    task_total: AtomicU64,
+    task_alive: AtomicI64,
+    task_passed: AtomicU64,
+    task_failed: AtomicU64,
+    associate_attempt: AtomicU64,
+    associate_attempt_total: AtomicU64,
+    associate_success: AtomicU64,
+    associate_success_total: AtomicU64,
+
+    packet_send: AtomicU64,
+    packet_send_total: AtomicU64,
+    packet_recv: AtomicU64,
+    packet_recv_total: AtomicU64,
+    packet_lost: AtomicU64,
+    packet_lost_total: AtomicU64,
+
+    udp_send: AtomicU64,
+    udp_recv: AtomicU64,
+    udp_send_total: AtomicU64,
+    udp_recv_total: AtomicU64,
+}
+
+impl Socks5UdpRuntimeStats {
+    pub(crate) fn add_task_total(&self) {
+        self.task_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_task_alive(&self) {
+        self.task_alive.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dec_task_alive(&self) {
+        self.task_alive.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task_passed(&self) {
+        self.task_passed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task_failed(&self) {
+        self.task_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_associate_attempt(&self) {
+        self.associate_attempt.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_associate_success(&self) {
+        self.associate_success.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_packet_send(&self, size: usize) {
+        self.packet_send.fetch_add(1, Ordering::Relaxed);
+        self.udp_send.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_packet_recv(&self, size: usize) {
+        self.packet_recv.fetch_add(1, Ordering::Relaxed);
+        self.udp_recv.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_packet_lost(&self) {
+        self.packet_lost.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// get the count of (send, recv, lost) packets since the last emit
+    #[cfg(test)]
+    pub(crate) fn packet_count(&self) -> (u64, u64, u64) {
+        (
+            self.packet_send.load(Ordering::Relaxed),
+            self.packet_recv.load(Ordering::Relaxed),
+            self.packet_lost.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl BenchRuntimeStats for Socks5UdpRuntimeStats {
+    fn emit(&self, client: &mut StatsdClient) {
+        macro_rules! emit_count {
+            ($field:ident, $name:literal) => {
+                let $field = self.$field.swap(0, Ordering::Relaxed);
+                client.count(concat!("socks5_udp.", $name), $field).send();
+            };
+            ($field:ident, $total:ident, $name:literal) => {
+                emit_count!($field, $name);
+                self.$total.fetch_add($field, Ordering::Relaxed);
+            };
+        }
+
+        let task_alive = self.task_alive.load(Ordering::Relaxed);
+        client.gauge("socks5_udp.task.alive", task_alive).send();
+
+        emit_count!(task_total, "task.total");
+        emit_count!(task_passed, "task.passed");
+        emit_count!(task_failed, "task.failed");
+        emit_count!(
+            associate_attempt,
+            associate_attempt_total,
+            "associate.attempt"
+        );
+        emit_count!(
+            associate_success,
+            associate_success_total,
+            "associate.success"
+        );
+        emit_count!(packet_send, packet_send_total, "packet.send");
+        emit_count!(packet_recv, packet_recv_total, "packet.recv");
+        emit_count!(packet_lost, packet_lost_total, "packet.lost");
+        emit_count!(udp_send, udp_send_total, "io.udp.send");
+        emit_count!(udp_recv, udp_recv_total, "io.udp.recv");
+    }
+
+    fn summary(&self, total_time: Duration) {
+        macro_rules! load_total {
+            ($field:ident, $total:ident) => {
+                self.$total.load(Ordering::Relaxed) + self.$field.load(Ordering::Relaxed)
+            };
+        }
+
+        let total_secs = total_time.as_secs_f64();
+
+        println!("# Associations");
+        let total_attempt = load_total!(associate_attempt, associate_attempt_total);
+        println!("Attempt count: {total_attempt}");
+        let total_success = load_total!(associate_success, associate_success_total);
+        println!("Success count: {total_success}");
+        println!(
+            "Success ratio: {:.2}%",
+            (total_success as f64 / total_attempt as f64) * 100.0
+        );
+
+        println!("# Packets");
+        let total_send = load_total!(packet_send, packet_send_total);
+        println!("Send count:    {total_send}");
+        let total_recv = load_total!(packet_recv, packet_recv_total);
+        println!("Recv count:    {total_recv}");
+        let total_lost = load_total!(packet_lost, packet_lost_total);
+        println!("Lost count:    {total_lost}");
+        if total_send > 0 {
+            println!(
+                "Lost ratio:    {:.2}%",
+                (total_lost as f64 / total_send as f64) * 100.0
+            );
+        }
+
+        println!("# Traffic");
+        let total_send = load_total!(udp_send, udp_send_total);
+        println!("Send bytes:    {total_send}");
+        println!("Send rate:     {:.3}B/s", total_send as f64 / total_secs);
+        let total_recv = load_total!(udp_recv, udp_recv_total);
+        println!("Recv bytes:    {total_recv}");
+        println!("Recv rate:     {:.3}B/s", total_recv as f64 / total_secs);
+    }
+}
@@ -0,0 +1,330 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use tokio::io::AsyncReadExt;
+use tokio::time::Instant;
+
+use g3_socks::v5::UdpInput;
+
+use super::opts::Socks5UdpAssociation;
+use super::{
+    BenchSocks5UdpArgs, BenchTaskContext, ProcArgs, Socks5UdpHistogramRecorder,
+    Socks5UdpRuntimeStats,
+};
+use crate::target::{BenchError, BenchErrorType};
+
+pub(super) struct Socks5UdpTaskContext {
+    args: Arc<BenchSocks5UdpArgs>,
+    proc_args: Arc<ProcArgs>,
+
+    association: Option<Socks5UdpAssociation>,
+    send_buf: Vec<u8>,
+    send_header_len: usize,
+    recv_buf: Vec<u8>,
+
+    runtime_stats: Arc<Socks5UdpRuntimeStats>,
+    histogram_recorder: Socks5UdpHistogramRecorder,
+}
+
+impl Socks5UdpTaskContext {
+    pub(super) fn new(
+        args: &Arc<BenchSocks5UdpArgs>,
+        proc_args: &Arc<ProcArgs>,
+        runtime_stats: &Arc<Socks5UdpRuntimeStats>,
+        histogram_recorder: Socks5UdpHistogramRecorder,
+    ) -> anyhow::Result<Self> {
+        let (send_buf, send_header_len) = args.new_packet();
+        let recv_buf = vec![0u8; send_buf.len() + 512];
+        Ok(Socks5UdpTaskContext {
+            args: Arc::clone(args),
+            proc_args: Arc::clone(proc_args),
+            association: None,
+            send_buf,
+            send_header_len,
+            recv_buf,
+            runtime_stats: Arc::clone(runtime_stats),
+            histogram_recorder,
+        })
+    }
+
+    async fn fetch_association(&mut self) -> anyhow::Result<Socks5UdpAssociation> {
+        if let Some(association) = self.association.take() {
+            return Ok(association);
+        }
+
+        self.runtime_stats.add_associate_attempt();
+        let time_start = Instant::now();
+        let association = match tokio::time::timeout(
+            self.args.connect_timeout,
+            self.args.new_udp_association(&self.proc_args),
+        )
+        .await
+        {
+            Ok(Ok(association)) => association,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to setup udp associate session")),
+        };
+        self.runtime_stats.add_associate_success();
+        self.histogram_recorder
+            .record_associate_time(time_start.elapsed());
+        Ok(association)
+    }
+
+    /// Send one packet and wait for the echo, return false if the packet is lost
+    async fn run_with_association(
+        &mut self,
+        association: &mut Socks5UdpAssociation,
+        seq: u64,
+    ) -> anyhow::Result<bool> {
+        let seq_bytes = seq.to_be_bytes();
+        let payload_start = self.send_header_len;
+        self.send_buf[payload_start..payload_start + seq_bytes.len()].copy_from_slice(&seq_bytes);
+
+        let time_send = Instant::now();
+        let nw = association
+            .socket
+            .send(&self.send_buf)
+            .await
+            .map_err(|e| anyhow!("failed to send udp packet: {e}"))?;
+        self.runtime_stats.add_packet_send(nw);
+
+        let runtime_stats = &self.runtime_stats;
+        let recv_buf = &mut self.recv_buf;
+        let mut ctl_buf = [0u8; 4];
+        let recv_echo = async {
+            loop {
+                tokio::select! {
+                    biased;
+
+                    r = association.ctl_stream.read(&mut ctl_buf) => {
+                        return match r {
+                            Ok(0) => Err(anyhow!("udp associate control connection closed")),
+                            Ok(_) => Err(anyhow!("unexpected data received on udp associate control connection")),
+                            Err(e) => Err(anyhow!("udp associate control connection error: {e}")),
+                        };
+                    }
+                    r = association.socket.recv(recv_buf) => {
+                        let nr = r.map_err(|e| anyhow!("failed to recv udp packet: {e}"))?;
+                        let packet = &recv_buf[..nr];
+                        let (off, _) = UdpInput::parse_header(packet)
+                            .map_err(|e| anyhow!("invalid socks5 udp packet: {e}"))?;
+                        let payload = &packet[off..];
+                        // skip packets for the previous lost requests
+                        if payload.len() >= seq_bytes.len() && payload[..seq_bytes.len()] == seq_bytes {
+                            runtime_stats.add_packet_recv(nr);
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        };
+
+        match tokio::time::timeout(self.args.timeout, recv_echo).await {
+            Ok(Ok(_)) => {
+                self.histogram_recorder.record_rtt(time_send.elapsed());
+                Ok(true)
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => {
+                self.runtime_stats.add_packet_lost();
+                Ok(false)
+            }
+        }
+    }
+}
+
+impl BenchTaskContext for Socks5UdpTaskContext {
+    fn mark_task_start(&self) {
+        self.runtime_stats.add_task_total();
+        self.runtime_stats.inc_task_alive();
+    }
+
+    fn mark_task_passed(&self) {
+        self.runtime_stats.add_task_passed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    fn mark_task_failed(&self) {
+        self.runtime_stats.add_task_failed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    async fn run(&mut self, task_id: usize, _time_started: Instant) -> Result<(), BenchError> {
+        let mut association = self
+            .fetch_association()
+            .await
+            .context(BenchErrorType::Connect)
+            .map_err(BenchError::Fatal)?;
+
+        let r = self
+            .run_with_association(&mut association, task_id as u64)
+            .await;
+        match r {
+            Ok(received) => {
+                // the association can still be used after packet lost
+                if !self.args.no_keepalive {
+                    self.association = Some(association);
+                }
+                if received {
+                    Ok(())
+                } else {
+                    Err(BenchError::Task(
+                        BenchErrorType::Timeout.error("timeout to recv the echo packet"),
+                    ))
+                }
+            }
+            Err(e) => Err(BenchError::Task(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Command;
+    use tokio::net::{TcpListener, TcpStream, UdpSocket};
+
+    use crate::target::socks5_udp::opts::{add_socks5_udp_args, parse_socks5_udp_args};
+    use crate::target::socks5_udp::Socks5UdpHistogram;
+
+    struct TestProxy {
+        socket: UdpSocket,
+        _ctl_stream: TcpStream,
+        buf: Vec<u8>,
+    }
+
+    impl TestProxy {
+        /// receive a packet from the client, and return the packet and its seq number
+        async fn recv(&mut self) -> (Vec<u8>, u64) {
+            let nr = self.socket.recv(&mut self.buf).await.unwrap();
+            let packet = self.buf[..nr].to_vec();
+            let (off, _) = UdpInput::parse_header(&packet).unwrap();
+            let seq = u64::from_be_bytes(packet[off..off + 8].try_into().unwrap());
+            (packet, seq)
+        }
+
+        /// echo the packet back with the given seq number
+        async fn echo(&self, packet: &[u8], seq: u64) {
+            let mut packet = packet.to_vec();
+            let (off, _) = UdpInput::parse_header(&packet).unwrap();
+            packet[off..off + 8].copy_from_slice(&seq.to_be_bytes());
+            self.socket.send(&packet).await.unwrap();
+        }
+    }
+
+    async fn new_context() -> (Socks5UdpTaskContext, TestProxy, Socks5UdpHistogram) {
+        let args = add_socks5_udp_args(Command::new("test"))
+            .try_get_matches_from([
+                "test",
+                "127.0.0.1:7",
+                "-x",
+                "socks5://127.0.0.1:1080",
+                "--timeout",
+                "200ms",
+            ])
+            .unwrap();
+        let args = parse_socks5_udp_args(&args).unwrap();
+        let (histogram, histogram_recorder) = Socks5UdpHistogram::new();
+        let mut context = Socks5UdpTaskContext::new(
+            &Arc::new(args),
+            &Arc::new(ProcArgs::default()),
+            &Arc::new(Socks5UdpRuntimeStats::default()),
+            histogram_recorder,
+        )
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ctl_stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (proxy_ctl_stream, _) = listener.accept().await.unwrap();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let proxy_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket
+            .connect(proxy_socket.local_addr().unwrap())
+            .await
+            .unwrap();
+        proxy_socket
+            .connect(socket.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        context.association = Some(Socks5UdpAssociation { ctl_stream, socket });
+        let proxy = TestProxy {
+            socket: proxy_socket,
+            _ctl_stream: proxy_ctl_stream,
+            buf: vec![0u8; 1024],
+        };
+        (context, proxy, histogram)
+    }
+
+    #[tokio::test]
+    async fn seq_match() {
+        let (mut context, mut proxy, _histogram) = new_context().await;
+
+        let proxy_task = tokio::spawn(async move {
+            let (packet, seq) = proxy.recv().await;
+            // the echo of a previous packet should be skipped
+            proxy.echo(&packet, seq - 1).await;
+            proxy.echo(&packet, seq + 1).await;
+            proxy.echo(&packet, seq).await;
+            // keep the control connection open
+            (seq, proxy)
+        });
+
+        assert!(context.run(10, Instant::now()).await.is_ok());
+        let (seq, _proxy) = proxy_task.await.unwrap();
+        assert_eq!(seq, 10);
+        assert!(context.association.is_some());
+        // only the matched packet is counted
+        assert_eq!(context.runtime_stats.packet_count(), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn lost_packet() {
+        let (mut context, mut proxy, _histogram) = new_context().await;
+
+        let proxy_task = tokio::spawn(async move {
+            // drop the first packet
+            let (_, seq1) = proxy.recv().await;
+            // echo the second one after the late echo of the first one
+            let (packet, seq2) = proxy.recv().await;
+            proxy.echo(&packet, seq1).await;
+            proxy.echo(&packet, seq2).await;
+            // keep the control connection open
+            (seq1, seq2, proxy)
+        });
+
+        match context.run(1, Instant::now()).await {
+            Err(BenchError::Task(e)) => {
+                assert_eq!(BenchErrorType::find(&e), Some(BenchErrorType::Timeout));
+            }
+            _ => panic!("the packet should be lost"),
+        }
+        // the association can still be used after packet lost
+        assert!(context.association.is_some());
+        assert_eq!(context.runtime_stats.packet_count(), (1, 0, 1));
+
+        assert!(context.run(2, Instant::now()).await.is_ok());
+        let (seq1, seq2, _proxy) = proxy_task.await.unwrap();
+        assert_eq!((seq1, seq2), (1, 2));
+        assert_eq!(context.runtime_stats.packet_count(), (2, 1, 1));
+    }
+}
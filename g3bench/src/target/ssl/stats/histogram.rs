@@ -0,0 +1,100 @@
+/*
+ * Copyright 2023 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use g3_histogram::{HistogramRecorder, KeepingHistogram};
+use g3_statsd_client::StatsdClient;
+use g3_types::ext::DurationExt;
+
+use crate::report::BenchReport;
+use crate::target::BenchHistogram;
+
+pub(crate) struct SslHistogram {
+    conn_time: KeepingHistogram<u64>,
+    handshake_time: KeepingHistogram<u64>,
+    total_time: KeepingHistogram<u64>,
+}
+
+impl SslHistogram {
+    pub(crate) fn new() -> (Self, SslHistogramRecorder) {
+        let (conn_time_h, conn_time_r) = KeepingHistogram::new();
+        let (handshake_time_h, handshake_time_r) = KeepingHistogram::new();
+        let (total_time_h, total_time_r) = KeepingHistogram::new();
+        let h = SslHistogram {
+            conn_time: conn_time_h,
+            handshake_time: handshake_time_h,
+            total_time: total_time_h,
+        };
+        let r = SslHistogramRecorder {
+            conn_time: conn_time_r,
+            handshake_time: handshake_time_r,
+            total_time: total_time_r,
+        };
+        (h, r)
+    }
+}
+
+impl BenchHistogram for SslHistogram {
+    fn refresh(&mut self) {
+        self.conn_time.refresh().unwrap();
+        self.handshake_time.refresh().unwrap();
+        self.total_time.refresh().unwrap();
+    }
+
+    fn emit(&self, client: &mut StatsdClient) {
+        self.emit_histogram(client, self.conn_time.inner(), "ssl.time.connect");
+        self.emit_histogram(client, self.handshake_time.inner(), "ssl.time.handshake");
+        self.emit_histogram(client, self.total_time.inner(), "ssl.time.total");
+    }
+
+    fn summary(&self) {
+        Self::summary_histogram_title("# Duration Times");
+        Self::summary_duration_line("Connect:", self.conn_time.inner());
+        Self::summary_duration_line("Handshake:", self.handshake_time.inner());
+        let total_time = self.total_time.inner();
+        Self::summary_duration_line("Total:", total_time);
+        Self::summary_newline();
+        Self::summary_total_percentage(total_time);
+    }
+
+    fn report(&self, report: &mut BenchReport) {
+        report.add_phase("connect", self.conn_time.inner());
+        report.add_phase("tls_handshake", self.handshake_time.inner());
+        report.add_phase("total", self.total_time.inner());
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct SslHistogramRecorder {
+    conn_time: HistogramRecorder<u64>,
+    handshake_time: HistogramRecorder<u64>,
+    total_time: HistogramRecorder<u64>,
+}
+
+impl SslHistogramRecorder {
+    pub(crate) fn record_conn_time(&mut self, dur: Duration) {
+        let _ = self.conn_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_handshake_time(&mut self, dur: Duration) {
+        let _ = self.handshake_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_total_time(&mut self, dur: Duration) {
+        let _ = self.total_time.record(dur.as_nanos_u64());
+    }
+}
@@ -0,0 +1,274 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::anyhow;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub(super) const OPCODE_CONTINUATION: u8 = 0x0;
+pub(super) const OPCODE_TEXT: u8 = 0x1;
+pub(super) const OPCODE_BINARY: u8 = 0x2;
+pub(super) const OPCODE_CLOSE: u8 = 0x8;
+pub(super) const OPCODE_PING: u8 = 0x9;
+pub(super) const OPCODE_PONG: u8 = 0xA;
+
+/// max payload length of control frames, see RFC 6455 Section 5.5
+const MAX_CONTROL_PAYLOAD_LEN: u64 = 125;
+
+pub(super) struct FrameHeader {
+    pub(super) fin: bool,
+    pub(super) opcode: u8,
+    pub(super) payload_len: u64,
+    mask_key: Option<[u8; 4]>,
+}
+
+impl FrameHeader {
+    pub(super) fn is_control(&self) -> bool {
+        self.opcode & 0x08 != 0
+    }
+
+    pub(super) async fn read<R>(reader: &mut R) -> anyhow::Result<Self>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut hdr = [0u8; 2];
+        reader
+            .read_exact(&mut hdr)
+            .await
+            .map_err(|e| anyhow!("failed to read frame header: {e}"))?;
+
+        let fin = hdr[0] & 0x80 != 0;
+        if hdr[0] & 0x70 != 0 {
+            return Err(anyhow!("unsupported frame with rsv bits set"));
+        }
+        let opcode = hdr[0] & 0x0F;
+        let masked = hdr[1] & 0x80 != 0;
+        let payload_len = match hdr[1] & 0x7F {
+            126 => reader
+                .read_u16()
+                .await
+                .map_err(|e| anyhow!("failed to read extended payload length: {e}"))?
+                as u64,
+            127 => reader
+                .read_u64()
+                .await
+                .map_err(|e| anyhow!("failed to read extended payload length: {e}"))?,
+            n => n as u64,
+        };
+        let mask_key = if masked {
+            let mut key = [0u8; 4];
+            reader
+                .read_exact(&mut key)
+                .await
+                .map_err(|e| anyhow!("failed to read mask key: {e}"))?;
+            Some(key)
+        } else {
+            None
+        };
+
+        let header = FrameHeader {
+            fin,
+            opcode,
+            payload_len,
+            mask_key,
+        };
+        if header.is_control() && (!fin || payload_len > MAX_CONTROL_PAYLOAD_LEN) {
+            return Err(anyhow!("invalid control frame"));
+        }
+        Ok(header)
+    }
+
+    /// Read the payload into the buffer, which should be large enough
+    pub(super) async fn read_payload<R>(&self, reader: &mut R, buf: &mut [u8]) -> anyhow::Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        reader
+            .read_exact(buf)
+            .await
+            .map_err(|e| anyhow!("failed to read frame payload: {e}"))?;
+        if let Some(key) = self.mask_key {
+            apply_mask(buf, key);
+        }
+        Ok(())
+    }
+
+    /// Skip the payload, return the bytes read
+    pub(super) async fn skip_payload<R>(&self, reader: &mut R) -> anyhow::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let nr = tokio::io::copy(&mut reader.take(self.payload_len), &mut tokio::io::sink())
+            .await
+            .map_err(|e| anyhow!("failed to read frame payload: {e}"))?;
+        if nr != self.payload_len {
+            return Err(anyhow!("connection closed while reading frame payload"));
+        }
+        Ok(nr)
+    }
+}
+
+fn apply_mask(buf: &mut [u8], key: [u8; 4]) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b ^= key[i & 0x03];
+    }
+}
+
+/// Encode a client frame, the payload will always be masked
+pub(super) fn encode_client_frame(buf: &mut Vec<u8>, opcode: u8, payload: &[u8], key: [u8; 4]) {
+    buf.clear();
+    buf.push(0x80 | opcode);
+    let len = payload.len();
+    if len < 126 {
+        buf.push(0x80 | len as u8);
+    } else if len <= u16::MAX as usize {
+        buf.push(0x80 | 126);
+        buf.extend_from_slice(&(len as u16).to_be_bytes());
+    } else {
+        buf.push(0x80 | 127);
+        buf.extend_from_slice(&(len as u64).to_be_bytes());
+    }
+    buf.extend_from_slice(&key);
+    let offset = buf.len();
+    buf.extend_from_slice(payload);
+    apply_mask(&mut buf[offset..], key);
+}
+
+pub(super) async fn send_client_frame<W>(
+    writer: &mut W,
+    buf: &mut Vec<u8>,
+    opcode: u8,
+    payload: &[u8],
+) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut key = [0u8; 4];
+    openssl::rand::rand_bytes(&mut key).map_err(|e| anyhow!("failed to get mask key: {e}"))?;
+    encode_client_frame(buf, opcode, payload, key);
+    writer
+        .write_all(buf)
+        .await
+        .map_err(|e| anyhow!("failed to write frame: {e}"))?;
+    writer
+        .flush()
+        .await
+        .map_err(|e| anyhow!("failed to flush frame: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 4] = [0x37, 0xfa, 0x21, 0x3d];
+
+    #[test]
+    fn encode_small() {
+        let mut buf = Vec::new();
+        encode_client_frame(&mut buf, OPCODE_TEXT, b"Hello", KEY);
+        // the masked example in RFC 6455 Section 5.7
+        assert_eq!(
+            buf,
+            [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58]
+        );
+    }
+
+    #[test]
+    fn encode_extended_len() {
+        let mut buf = Vec::new();
+        let payload = vec![0u8; 126];
+        encode_client_frame(&mut buf, OPCODE_BINARY, &payload, KEY);
+        assert_eq!(&buf[..4], &[0x82, 0x80 | 126, 0x00, 126]);
+        assert_eq!(buf.len(), 4 + 4 + 126);
+
+        let payload = vec![0u8; 65536];
+        encode_client_frame(&mut buf, OPCODE_BINARY, &payload, KEY);
+        assert_eq!(&buf[..2], &[0x82, 0x80 | 127]);
+        assert_eq!(&buf[2..10], &65536u64.to_be_bytes());
+        assert_eq!(buf.len(), 10 + 4 + 65536);
+    }
+
+    #[tokio::test]
+    async fn round_trip() {
+        for len in [0usize, 125, 126, 65535, 65536] {
+            let payload: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let mut buf = Vec::new();
+            encode_client_frame(&mut buf, OPCODE_BINARY, &payload, KEY);
+
+            let mut reader = buf.as_slice();
+            let header = FrameHeader::read(&mut reader).await.unwrap();
+            assert!(header.fin);
+            assert!(!header.is_control());
+            assert_eq!(header.opcode, OPCODE_BINARY);
+            assert_eq!(header.payload_len, len as u64);
+
+            let mut data = vec![0u8; len];
+            header.read_payload(&mut reader, &mut data).await.unwrap();
+            assert_eq!(data, payload);
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn read_server_frame() {
+        let data = [0x01, 0x03, b'H', b'e', b'l', 0x80, 0x02, b'l', b'o'];
+        let mut reader = data.as_slice();
+
+        let header = FrameHeader::read(&mut reader).await.unwrap();
+        assert!(!header.fin);
+        assert_eq!(header.opcode, OPCODE_TEXT);
+        let mut buf = [0u8; 3];
+        header.read_payload(&mut reader, &mut buf).await.unwrap();
+        assert_eq!(&buf, b"Hel");
+
+        let header = FrameHeader::read(&mut reader).await.unwrap();
+        assert!(header.fin);
+        assert_eq!(header.opcode, OPCODE_CONTINUATION);
+        assert_eq!(header.skip_payload(&mut reader).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn read_control_frame() {
+        let data = [0x89, 0x02, b'h', b'i'];
+        let header = FrameHeader::read(&mut data.as_slice()).await.unwrap();
+        assert!(header.is_control());
+        assert_eq!(header.opcode, OPCODE_PING);
+
+        // fragmented control frame
+        let data = [0x0A, 0x00];
+        assert!(FrameHeader::read(&mut data.as_slice()).await.is_err());
+
+        // control frame payload too long
+        let data = [0x88, 126, 0x00, 126];
+        assert!(FrameHeader::read(&mut data.as_slice()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn read_invalid() {
+        // rsv bits set
+        let data = [0xC1, 0x00];
+        assert!(FrameHeader::read(&mut data.as_slice()).await.is_err());
+
+        // truncated extended payload length
+        let data = [0x82, 127, 0x00];
+        assert!(FrameHeader::read(&mut data.as_slice()).await.is_err());
+
+        // truncated payload
+        let data = [0x82, 0x04, 0x00, 0x00];
+        let mut reader = data.as_slice();
+        let header = FrameHeader::read(&mut reader).await.unwrap();
+        assert!(header.skip_payload(&mut reader).await.is_err());
+    }
+}
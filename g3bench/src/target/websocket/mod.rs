@@ -0,0 +1,89 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use clap::{ArgMatches, Command};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use super::{BenchTarget, BenchTaskContext, ProcArgs};
+
+mod frame;
+
+mod opts;
+use opts::BenchWebsocketArgs;
+
+mod stats;
+pub(crate) use stats::{WebsocketHistogram, WebsocketHistogramRecorder, WebsocketRuntimeStats};
+
+mod task;
+use task::WebsocketTaskContext;
+
+pub const COMMAND: &str = "websocket";
+
+type BoxWebsocketConnection = (
+    Box<dyn AsyncRead + Send + Unpin>,
+    Box<dyn AsyncWrite + Send + Unpin>,
+);
+
+struct WebsocketTarget {
+    args: Arc<BenchWebsocketArgs>,
+    proc_args: Arc<ProcArgs>,
+    stats: Arc<WebsocketRuntimeStats>,
+    histogram: Option<WebsocketHistogram>,
+    histogram_recorder: WebsocketHistogramRecorder,
+}
+
+impl BenchTarget<WebsocketRuntimeStats, WebsocketHistogram, WebsocketTaskContext>
+    for WebsocketTarget
+{
+    fn new_context(&self) -> anyhow::Result<WebsocketTaskContext> {
+        WebsocketTaskContext::new(
+            &self.args,
+            &self.proc_args,
+            &self.stats,
+            self.histogram_recorder.clone(),
+        )
+    }
+
+    fn fetch_runtime_stats(&self) -> Arc<WebsocketRuntimeStats> {
+        self.stats.clone()
+    }
+
+    fn take_histogram(&mut self) -> Option<WebsocketHistogram> {
+        self.histogram.take()
+    }
+}
+
+pub fn command() -> Command {
+    opts::add_websocket_args(Command::new(COMMAND))
+}
+
+pub async fn run(proc_args: &Arc<ProcArgs>, cmd_args: &ArgMatches) -> anyhow::Result<()> {
+    let mut ws_args = opts::parse_websocket_args(cmd_args)?;
+    ws_args.resolve_target_address(proc_args).await?;
+
+    let (histogram, histogram_recorder) = WebsocketHistogram::new();
+    let target = WebsocketTarget {
+        args: Arc::new(ws_args),
+        proc_args: Arc::clone(proc_args),
+        stats: Arc::new(WebsocketRuntimeStats::default()),
+        histogram: Some(histogram),
+        histogram_recorder,
+    };
+
+    super::run(target, proc_args).await
+}
@@ -0,0 +1,560 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use base64::prelude::*;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use http::{HeaderValue, Method, Request, StatusCode, Version};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use url::Url;
+
+use g3_h2::{H2StreamReader, H2StreamWriter};
+use g3_http::client::HttpForwardRemoteResponse;
+use g3_io_ext::AggregatedIo;
+use g3_openssl::SslStream;
+use g3_types::collection::{SelectiveVec, WeightedValue};
+use g3_types::net::{
+    AlpnProtocol, HttpAuth, OpensslClientConfig, OpensslClientConfigBuilder, Proxy, UpstreamAddr,
+};
+
+use super::{BoxWebsocketConnection, ProcArgs};
+use crate::target::{
+    AppendOpensslArgs, AppendProxyProtocolArgs, OpensslTlsClientArgs, ProxyProtocolArgs,
+};
+
+const WS_ARG_URL: &str = "url";
+const WS_ARG_PROXY: &str = "proxy";
+const WS_ARG_LOCAL_ADDRESS: &str = "local-address";
+const WS_ARG_H2: &str = "h2";
+const WS_ARG_FRAME_SIZE: &str = "frame-size";
+const WS_ARG_FRAMES_PER_CONN: &str = "frames-per-conn";
+const WS_ARG_TIMEOUT: &str = "timeout";
+const WS_ARG_CONNECT_TIMEOUT: &str = "connect-timeout";
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_HANDSHAKE_HEADER_SIZE: usize = 4096;
+/// the payload should be large enough to contain the sequence number
+const MIN_FRAME_SIZE: usize = 8;
+
+pub(super) struct BenchWebsocketArgs {
+    target_url: Url,
+    connect_proxy: Option<Proxy>,
+    bind: Option<IpAddr>,
+    use_h2: bool,
+    pub(super) frame_size: usize,
+    pub(super) frames_per_conn: Option<usize>,
+    pub(super) timeout: Duration,
+    pub(super) connect_timeout: Duration,
+
+    target_tls: OpensslTlsClientArgs,
+    proxy_tls: OpensslTlsClientArgs,
+    proxy_protocol: ProxyProtocolArgs,
+
+    host: UpstreamAddr,
+    auth: HttpAuth,
+    peer_addrs: Option<SelectiveVec<WeightedValue<SocketAddr>>>,
+}
+
+impl BenchWebsocketArgs {
+    fn new(url: Url) -> anyhow::Result<Self> {
+        let mut target_tls = OpensslTlsClientArgs::default();
+        let default_port = match url.scheme() {
+            "ws" => 80,
+            "wss" => {
+                target_tls.config = Some(OpensslClientConfigBuilder::with_cache_for_one_site());
+                443
+            }
+            s => return Err(anyhow!("unsupported websocket url scheme {s}")),
+        };
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow!("no host found in url {url}"))?;
+        let upstream =
+            UpstreamAddr::from_host_str_and_port(host, url.port().unwrap_or(default_port))
+                .map_err(|e| anyhow!("invalid host in url {url}: {e}"))?;
+        let auth = HttpAuth::try_from(&url)
+            .map_err(|e| anyhow!("failed to detect upstream auth method: {e}"))?;
+
+        Ok(BenchWebsocketArgs {
+            target_url: url,
+            connect_proxy: None,
+            bind: None,
+            use_h2: false,
+            frame_size: 64,
+            frames_per_conn: None,
+            timeout: Duration::from_secs(30),
+            connect_timeout: Duration::from_secs(15),
+            target_tls,
+            proxy_tls: OpensslTlsClientArgs::default(),
+            proxy_protocol: ProxyProtocolArgs::default(),
+            host: upstream,
+            auth,
+            peer_addrs: None,
+        })
+    }
+
+    pub(super) async fn resolve_target_address(
+        &mut self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<()> {
+        let host = if let Some(proxy) = &self.connect_proxy {
+            proxy.peer()
+        } else {
+            &self.host
+        };
+        let addrs = proc_args.resolve(host).await?;
+        self.peer_addrs = Some(addrs);
+        Ok(())
+    }
+
+    async fn new_tcp_connection(&self, proc_args: &ProcArgs) -> anyhow::Result<TcpStream> {
+        let addrs = self
+            .peer_addrs
+            .as_ref()
+            .ok_or_else(|| anyhow!("no peer address set"))?;
+        let peer = *proc_args.select_peer(addrs);
+
+        let socket = g3_socket::tcp::new_socket_to(
+            peer.ip(),
+            self.bind,
+            &Default::default(),
+            &Default::default(),
+            true,
+        )
+        .map_err(|e| anyhow!("failed to setup socket to {peer}: {e:?}"))?;
+        let mut stream = socket
+            .connect(peer)
+            .await
+            .map_err(|e| anyhow!("connect to {peer} error: {e:?}"))?;
+
+        if let Some(data) = self.proxy_protocol.data() {
+            stream
+                .write_all(data)
+                .await
+                .map_err(|e| anyhow!("failed to send proxy protocol data: {e:?}"))?;
+        }
+
+        Ok(stream)
+    }
+
+    /// Get the stream to the target server, the websocket handshake is not done
+    async fn new_target_stream(
+        &self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<BoxWebsocketConnection> {
+        if let Some(proxy) = &self.connect_proxy {
+            match proxy {
+                Proxy::Http(http_proxy) => {
+                    let stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to http proxy {}",
+                        http_proxy.peer()
+                    ))?;
+
+                    if let Some(tls_config) = &self.proxy_tls.client {
+                        let tls_stream = self
+                            .tls_connect_to_proxy(tls_config, http_proxy.peer(), stream)
+                            .await?;
+
+                        let (r, mut w) = tokio::io::split(tls_stream);
+                        let mut buf_r = BufReader::new(r);
+
+                        g3_http::connect::client::http_connect_to(
+                            &mut buf_r,
+                            &mut w,
+                            &http_proxy.auth,
+                            &self.host,
+                        )
+                        .await
+                        .map_err(|e| {
+                            anyhow!("http connect to {} failed: {e}", http_proxy.peer())
+                        })?;
+
+                        self.connect_to_target(AggregatedIo::new(buf_r.into_inner(), w))
+                            .await
+                    } else {
+                        let (r, mut w) = stream.into_split();
+                        let mut buf_r = BufReader::new(r);
+
+                        g3_http::connect::client::http_connect_to(
+                            &mut buf_r,
+                            &mut w,
+                            &http_proxy.auth,
+                            &self.host,
+                        )
+                        .await
+                        .map_err(|e| {
+                            anyhow!("http connect to {} failed: {e}", http_proxy.peer())
+                        })?;
+
+                        self.connect_to_target(AggregatedIo::new(buf_r.into_inner(), w))
+                            .await
+                    }
+                }
+                Proxy::Socks4(socks4_proxy) => {
+                    let stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to socks4 proxy {}",
+                        socks4_proxy.peer()
+                    ))?;
+                    let (mut r, mut w) = stream.into_split();
+
+                    g3_socks::v4a::client::socks4a_connect_to(&mut r, &mut w, &self.host)
+                        .await
+                        .map_err(|e| {
+                            anyhow!("socks4a connect to {} failed: {e}", socks4_proxy.peer())
+                        })?;
+
+                    self.connect_to_target(AggregatedIo::new(r, w)).await
+                }
+                Proxy::Socks5(socks5_proxy) => {
+                    let stream = self.new_tcp_connection(proc_args).await.context(format!(
+                        "failed to connect to socks5 proxy {}",
+                        socks5_proxy.peer()
+                    ))?;
+                    let (mut r, mut w) = stream.into_split();
+
+                    g3_socks::v5::client::socks5_connect_to(
+                        &mut r,
+                        &mut w,
+                        &socks5_proxy.auth,
+                        &self.host,
+                    )
+                    .await
+                    .map_err(|e| {
+                        anyhow!("socks5 connect to {} failed: {e}", socks5_proxy.peer())
+                    })?;
+
+                    self.connect_to_target(AggregatedIo::new(r, w)).await
+                }
+            }
+        } else {
+            let stream = self
+                .new_tcp_connection(proc_args)
+                .await
+                .context(format!("failed to connect to target host {}", self.host))?;
+            self.connect_to_target(stream).await
+        }
+    }
+
+    async fn connect_to_target<S>(&self, stream: S) -> anyhow::Result<BoxWebsocketConnection>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        if let Some(tls_client) = &self.target_tls.client {
+            let tls_stream = self
+                .target_tls
+                .connect_target(tls_client, stream, &self.host)
+                .await
+                .context("tls connect to target failed")?;
+            let (r, w) = tokio::io::split(tls_stream);
+            Ok((Box::new(r), Box::new(w)))
+        } else {
+            let (r, w) = tokio::io::split(stream);
+            Ok((Box::new(r), Box::new(w)))
+        }
+    }
+
+    async fn tls_connect_to_proxy(
+        &self,
+        tls_client: &OpensslClientConfig,
+        peer: &UpstreamAddr,
+        stream: TcpStream,
+    ) -> anyhow::Result<SslStream<TcpStream>> {
+        self.proxy_tls
+            .connect_target(tls_client, stream, peer)
+            .await
+    }
+
+    /// Get a new websocket connection with the handshake done
+    pub(super) async fn new_websocket_connection(
+        &self,
+        proc_args: &ProcArgs,
+    ) -> anyhow::Result<BoxWebsocketConnection> {
+        let (r, w) = self.new_target_stream(proc_args).await?;
+        if self.use_h2 {
+            self.h2_handshake(r, w).await
+        } else {
+            self.h1_handshake(r, w).await
+        }
+    }
+
+    fn request_path(&self) -> String {
+        match self.target_url.query() {
+            Some(q) => format!("{}?{q}", self.target_url.path()),
+            None => self.target_url.path().to_string(),
+        }
+    }
+
+    async fn h1_handshake(
+        &self,
+        r: Box<dyn AsyncRead + Send + Unpin>,
+        mut w: Box<dyn AsyncWrite + Send + Unpin>,
+    ) -> anyhow::Result<BoxWebsocketConnection> {
+        use std::io::Write;
+
+        let mut key = [0u8; 16];
+        openssl::rand::rand_bytes(&mut key)
+            .map_err(|e| anyhow!("failed to generate websocket key: {e}"))?;
+        let key = BASE64_STANDARD.encode(key);
+
+        let mut req = Vec::with_capacity(512);
+        let _ = write!(req, "GET {} HTTP/1.1\r\n", self.request_path());
+        let _ = write!(req, "Host: {}\r\n", self.host);
+        req.extend_from_slice(b"Connection: Upgrade\r\nUpgrade: websocket\r\n");
+        let _ = write!(req, "Sec-WebSocket-Key: {key}\r\n");
+        req.extend_from_slice(b"Sec-WebSocket-Version: 13\r\n");
+        if let HttpAuth::Basic(basic) = &self.auth {
+            let _ = write!(req, "Authorization: Basic {}\r\n", basic.encoded_value());
+        }
+        req.extend_from_slice(b"\r\n");
+        w.write_all(&req)
+            .await
+            .map_err(|e| anyhow!("failed to send handshake request: {e}"))?;
+        w.flush()
+            .await
+            .map_err(|e| anyhow!("failed to flush handshake request: {e}"))?;
+
+        let mut buf_r = BufReader::new(r);
+        let rsp = HttpForwardRemoteResponse::parse(
+            &mut buf_r,
+            &Method::GET,
+            true,
+            MAX_HANDSHAKE_HEADER_SIZE,
+        )
+        .await
+        .map_err(|e| anyhow!("failed to read handshake response: {e}"))?;
+        if rsp.code != StatusCode::SWITCHING_PROTOCOLS.as_u16() {
+            return Err(anyhow!(
+                "handshake failed with response code {} {}",
+                rsp.code,
+                rsp.reason
+            ));
+        }
+
+        let expected_accept = {
+            let mut data = key.into_bytes();
+            data.extend_from_slice(WEBSOCKET_GUID.as_bytes());
+            BASE64_STANDARD.encode(openssl::sha::sha1(&data))
+        };
+        let Some(accept) = rsp.end_to_end_headers.get("sec-websocket-accept") else {
+            return Err(anyhow!("no Sec-WebSocket-Accept header found in response"));
+        };
+        if accept.to_str() != expected_accept {
+            return Err(anyhow!("invalid Sec-WebSocket-Accept header value"));
+        }
+
+        Ok((Box::new(buf_r), w))
+    }
+
+    async fn h2_handshake(
+        &self,
+        r: Box<dyn AsyncRead + Send + Unpin>,
+        w: Box<dyn AsyncWrite + Send + Unpin>,
+    ) -> anyhow::Result<BoxWebsocketConnection> {
+        let (mut send_request, h2_connection) = h2::client::handshake(AggregatedIo::new(r, w))
+            .await
+            .map_err(|e| anyhow!("h2 handshake failed: {e}"))?;
+        tokio::spawn(async move {
+            let _ = h2_connection.await;
+        });
+
+        let scheme = if self.target_tls.client.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let uri = format!("{scheme}://{}{}", self.host, self.request_path());
+        let mut req = Request::builder()
+            .version(Version::HTTP_2)
+            .method(Method::CONNECT)
+            .uri(uri)
+            .extension(h2::ext::Protocol::from_static("websocket"))
+            .header("sec-websocket-version", HeaderValue::from_static("13"))
+            .body(())
+            .map_err(|e| anyhow!("failed to build extended connect request: {e}"))?;
+        if let HttpAuth::Basic(basic) = &self.auth {
+            let value = HeaderValue::try_from(format!("Basic {}", basic.encoded_value()))
+                .map_err(|e| anyhow!("invalid authorization header value: {e}"))?;
+            req.headers_mut().insert(http::header::AUTHORIZATION, value);
+        }
+
+        send_request = send_request
+            .ready()
+            .await
+            .map_err(|e| anyhow!("h2 connection is not ready: {e}"))?;
+        let (rsp_fut, send_stream) = send_request
+            .send_request(req, false)
+            .map_err(|e| anyhow!("failed to send extended connect request: {e}"))?;
+        let rsp = rsp_fut
+            .await
+            .map_err(|e| anyhow!("failed to recv extended connect response: {e}"))?;
+        if rsp.status() != StatusCode::OK {
+            return Err(anyhow!(
+                "extended connect failed with response code {}",
+                rsp.status()
+            ));
+        }
+
+        let recv_stream = rsp.into_body();
+        Ok((
+            Box::new(H2StreamReader::new(recv_stream)),
+            Box::new(H2StreamWriter::new(send_stream)),
+        ))
+    }
+}
+
+pub(super) fn add_websocket_args(app: Command) -> Command {
+    app.arg(
+        Arg::new(WS_ARG_URL)
+            .help("Target websocket url, the scheme should be ws or wss")
+            .required(true)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(WS_ARG_PROXY)
+            .value_name("PROXY URL")
+            .short('x')
+            .help("Use a proxy, the connection will always be tunneled")
+            .long(WS_ARG_PROXY)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(WS_ARG_LOCAL_ADDRESS)
+            .value_name("LOCAL IP ADDRESS")
+            .short('B')
+            .long(WS_ARG_LOCAL_ADDRESS)
+            .num_args(1)
+            .value_parser(value_parser!(IpAddr)),
+    )
+    .arg(
+        Arg::new(WS_ARG_H2)
+            .help("Use h2 extended CONNECT (RFC 8441) to establish the websocket")
+            .action(ArgAction::SetTrue)
+            .long(WS_ARG_H2),
+    )
+    .arg(
+        Arg::new(WS_ARG_FRAME_SIZE)
+            .help("Payload size of each frame")
+            .value_name("SIZE")
+            .long(WS_ARG_FRAME_SIZE)
+            .num_args(1)
+            .default_value("64"),
+    )
+    .arg(
+        Arg::new(WS_ARG_FRAMES_PER_CONN)
+            .help("Close and reconnect after sending this number of frames")
+            .value_name("COUNT")
+            .long(WS_ARG_FRAMES_PER_CONN)
+            .num_args(1)
+            .value_parser(value_parser!(usize)),
+    )
+    .arg(
+        Arg::new(WS_ARG_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout to wait for the echo frame")
+            .default_value("30s")
+            .long(WS_ARG_TIMEOUT)
+            .num_args(1),
+    )
+    .arg(
+        Arg::new(WS_ARG_CONNECT_TIMEOUT)
+            .value_name("TIMEOUT DURATION")
+            .help("Timeout for connection and websocket handshake")
+            .default_value("15s")
+            .long(WS_ARG_CONNECT_TIMEOUT)
+            .num_args(1),
+    )
+    .append_openssl_args()
+    .append_proxy_openssl_args()
+    .append_proxy_protocol_args()
+}
+
+pub(super) fn parse_websocket_args(args: &ArgMatches) -> anyhow::Result<BenchWebsocketArgs> {
+    let url = if let Some(v) = args.get_one::<String>(WS_ARG_URL) {
+        Url::parse(v).context(format!("invalid {WS_ARG_URL} value"))?
+    } else {
+        return Err(anyhow!("no target url set"));
+    };
+
+    let mut ws_args = BenchWebsocketArgs::new(url)?;
+
+    if let Some(v) = args.get_one::<String>(WS_ARG_PROXY) {
+        let url = Url::parse(v).context(format!("invalid {WS_ARG_PROXY} value"))?;
+        let proxy = Proxy::try_from(&url).map_err(|e| anyhow!("invalid proxy: {e}"))?;
+        if let Proxy::Http(mut http_proxy) = proxy {
+            ws_args.proxy_tls.config = http_proxy.tls_config.take();
+            ws_args.connect_proxy = Some(Proxy::Http(http_proxy));
+        } else {
+            ws_args.connect_proxy = Some(proxy);
+        }
+    }
+
+    if let Some(ip) = args.get_one::<IpAddr>(WS_ARG_LOCAL_ADDRESS) {
+        ws_args.bind = Some(*ip);
+    }
+
+    if args.get_flag(WS_ARG_H2) {
+        ws_args.use_h2 = true;
+    }
+    if ws_args.target_tls.config.is_some() {
+        ws_args.target_tls.alpn_protocol = if ws_args.use_h2 {
+            Some(AlpnProtocol::Http2)
+        } else {
+            Some(AlpnProtocol::Http11)
+        };
+    }
+
+    if let Some(size) = g3_clap::humanize::get_usize(args, WS_ARG_FRAME_SIZE)? {
+        if size < MIN_FRAME_SIZE {
+            return Err(anyhow!(
+                "the frame size should be at least {MIN_FRAME_SIZE}"
+            ));
+        }
+        ws_args.frame_size = size;
+    }
+
+    if let Some(n) = args.get_one::<usize>(WS_ARG_FRAMES_PER_CONN) {
+        if *n > 0 {
+            ws_args.frames_per_conn = Some(*n);
+        }
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, WS_ARG_TIMEOUT)? {
+        ws_args.timeout = timeout;
+    }
+
+    if let Some(timeout) = g3_clap::humanize::get_duration(args, WS_ARG_CONNECT_TIMEOUT)? {
+        ws_args.connect_timeout = timeout;
+    }
+
+    ws_args
+        .target_tls
+        .parse_tls_args(args)
+        .context("invalid target tls config")?;
+    ws_args
+        .proxy_tls
+        .parse_proxy_tls_args(args)
+        .context("invalid proxy tls config")?;
+    ws_args
+        .proxy_protocol
+        .parse_args(args)
+        .context("invalid proxy protocol config")?;
+
+    Ok(ws_args)
+}
@@ -0,0 +1,90 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use g3_histogram::{HistogramRecorder, KeepingHistogram};
+use g3_statsd_client::StatsdClient;
+use g3_types::ext::DurationExt;
+
+use crate::report::BenchReport;
+use crate::target::BenchHistogram;
+
+pub(crate) struct WebsocketHistogram {
+    handshake_time: KeepingHistogram<u64>,
+    rtt: KeepingHistogram<u64>,
+}
+
+impl WebsocketHistogram {
+    pub(crate) fn new() -> (Self, WebsocketHistogramRecorder) {
+        let (handshake_time_h, handshake_time_r) = KeepingHistogram::new();
+        let (rtt_h, rtt_r) = KeepingHistogram::new();
+        let h = WebsocketHistogram {
+            handshake_time: handshake_time_h,
+            rtt: rtt_h,
+        };
+        let r = WebsocketHistogramRecorder {
+            handshake_time: handshake_time_r,
+            rtt: rtt_r,
+        };
+        (h, r)
+    }
+}
+
+impl BenchHistogram for WebsocketHistogram {
+    fn refresh(&mut self) {
+        self.handshake_time.refresh().unwrap();
+        self.rtt.refresh().unwrap();
+    }
+
+    fn emit(&self, client: &mut StatsdClient) {
+        self.emit_histogram(
+            client,
+            self.handshake_time.inner(),
+            "websocket.time.handshake",
+        );
+        self.emit_histogram(client, self.rtt.inner(), "websocket.time.rtt");
+    }
+
+    fn summary(&self) {
+        Self::summary_histogram_title("# Duration Times");
+        Self::summary_duration_line("Handshake:", self.handshake_time.inner());
+        Self::summary_duration_line("RTT:", self.rtt.inner());
+        Self::summary_newline();
+        Self::summary_total_percentage(self.rtt.inner());
+    }
+
+    fn report(&self, report: &mut BenchReport) {
+        report.add_phase("handshake", self.handshake_time.inner());
+        report.add_phase("rtt", self.rtt.inner());
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct WebsocketHistogramRecorder {
+    handshake_time: HistogramRecorder<u64>,
+    rtt: HistogramRecorder<u64>,
+}
+
+impl WebsocketHistogramRecorder {
+    pub(crate) fn record_handshake_time(&mut self, dur: Duration) {
+        let _ = self.handshake_time.record(dur.as_nanos_u64());
+    }
+
+    pub(crate) fn record_rtt(&mut self, dur: Duration) {
+        let _ = self.rtt.record(dur.as_nanos_u64());
+    }
+}
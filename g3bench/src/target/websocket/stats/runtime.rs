@@ -0,0 +1,188 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use g3_io_ext::{LimitedReaderStats, LimitedWriterStats};
+use g3_statsd_client::StatsdClient;
+
+use crate::target::BenchRuntimeStats;
+
+#[derive(Default)]
+pub(crate) struct WebsocketRuntimeStats {
+    task_total: AtomicU64,
+    task_alive: AtomicI64,
+    task_passed: AtomicU64,
+    task_failed: AtomicU64,
+    conn_attempt: AtomicU64,
+    conn_attempt_total: AtomicU64,
+    conn_success: AtomicU64,
+    conn_success_total: AtomicU64,
+    conn_close: AtomicU64,
+    conn_close_total: AtomicU64,
+    conn_close_error: AtomicU64,
+    conn_close_timeout: AtomicU64,
+
+    frame_send: AtomicU64,
+    frame_send_total: AtomicU64,
+    frame_recv: AtomicU64,
+    frame_recv_total: AtomicU64,
+
+    tcp_read: AtomicU64,
+    tcp_write: AtomicU64,
+    tcp_read_total: AtomicU64,
+    tcp_write_total: AtomicU64,
+}
+
+impl WebsocketRuntimeStats {
+    pub(crate) fn add_task_total(&self) {
+        self.task_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn inc_task_alive(&self) {
+        self.task_alive.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dec_task_alive(&self) {
+        self.task_alive.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task_passed(&self) {
+        self.task_passed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_task_failed(&self) {
+        self.task_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_attempt(&self) {
+        self.conn_attempt.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_success(&self) {
+        self.conn_success.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_close(&self) {
+        self.conn_close.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_close_fail(&self) {
+        self.conn_close_error.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_conn_close_timeout(&self) {
+        self.conn_close_timeout.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_frame_send(&self) {
+        self.frame_send.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_frame_recv(&self) {
+        self.frame_recv.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl LimitedReaderStats for WebsocketRuntimeStats {
+    fn add_read_bytes(&self, size: usize) {
+        self.tcp_read.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+impl LimitedWriterStats for WebsocketRuntimeStats {
+    fn add_write_bytes(&self, size: usize) {
+        self.tcp_write.fetch_add(size as u64, Ordering::Relaxed);
+    }
+}
+
+impl BenchRuntimeStats for WebsocketRuntimeStats {
+    fn emit(&self, client: &mut StatsdClient) {
+        macro_rules! emit_count {
+            ($field:ident, $name:literal) => {
+                let $field = self.$field.swap(0, Ordering::Relaxed);
+                client.count(concat!("websocket.", $name), $field).send();
+            };
+            ($field:ident, $total:ident, $name:literal) => {
+                emit_count!($field, $name);
+                self.$total.fetch_add($field, Ordering::Relaxed);
+            };
+        }
+
+        let task_alive = self.task_alive.load(Ordering::Relaxed);
+        client.gauge("websocket.task.alive", task_alive).send();
+
+        emit_count!(task_total, "task.total");
+        emit_count!(task_passed, "task.passed");
+        emit_count!(task_failed, "task.failed");
+        emit_count!(conn_attempt, conn_attempt_total, "connection.attempt");
+        emit_count!(conn_success, conn_success_total, "connection.success");
+        emit_count!(conn_close, conn_close_total, "connection.close");
+        emit_count!(frame_send, frame_send_total, "frame.send");
+        emit_count!(frame_recv, frame_recv_total, "frame.recv");
+        emit_count!(tcp_write, tcp_write_total, "io.tcp.write");
+        emit_count!(tcp_read, tcp_read_total, "io.tcp.read");
+    }
+
+    fn summary(&self, total_time: Duration) {
+        macro_rules! load_total {
+            ($field:ident, $total:ident) => {
+                self.$total.load(Ordering::Relaxed) + self.$field.load(Ordering::Relaxed)
+            };
+        }
+
+        let total_secs = total_time.as_secs_f64();
+
+        println!("# Connections");
+        let total_attempt = load_total!(conn_attempt, conn_attempt_total);
+        println!("Attempt count: {total_attempt}");
+        let total_success = load_total!(conn_success, conn_success_total);
+        println!("Success count: {total_success}");
+        println!(
+            "Success ratio: {:.2}%",
+            (total_success as f64 / total_attempt as f64) * 100.0
+        );
+        println!("Success rate:  {:.3}/s", total_success as f64 / total_secs);
+        let total_close = load_total!(conn_close, conn_close_total);
+        println!("Close count:   {total_close}");
+        println!("Close rate:    {:.3}/s", total_close as f64 / total_secs);
+        let close_error = self.conn_close_error.load(Ordering::Relaxed);
+        if close_error > 0 {
+            println!("Close error:   {close_error}");
+        }
+        let close_timeout = self.conn_close_timeout.load(Ordering::Relaxed);
+        if close_timeout > 0 {
+            println!("Close timeout: {close_timeout}");
+        }
+
+        println!("# Frames");
+        let total_send = load_total!(frame_send, frame_send_total);
+        println!("Send count:    {total_send}");
+        println!("Send rate:     {:.3}/s", total_send as f64 / total_secs);
+        let total_recv = load_total!(frame_recv, frame_recv_total);
+        println!("Recv count:    {total_recv}");
+        println!("Recv rate:     {:.3}/s", total_recv as f64 / total_secs);
+
+        println!("# Traffic");
+        let total_send = load_total!(tcp_write, tcp_write_total);
+        println!("Send bytes:    {total_send}");
+        println!("Send rate:     {:.3}B/s", total_send as f64 / total_secs);
+        let total_recv = load_total!(tcp_read, tcp_read_total);
+        println!("Recv bytes:    {total_recv}");
+        println!("Recv rate:     {:.3}B/s", total_recv as f64 / total_secs);
+    }
+}
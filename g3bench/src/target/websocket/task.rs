@@ -0,0 +1,293 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
+
+use g3_io_ext::{LimitedReader, LimitedWriter};
+
+use super::frame::{self, FrameHeader};
+use super::{
+    BenchTaskContext, BenchWebsocketArgs, ProcArgs, WebsocketHistogramRecorder,
+    WebsocketRuntimeStats,
+};
+use crate::target::{BenchError, BenchErrorType};
+
+struct WebsocketConnection {
+    reader: LimitedReader<Box<dyn AsyncRead + Send + Unpin>>,
+    writer: LimitedWriter<Box<dyn AsyncWrite + Send + Unpin>>,
+    frames_sent: usize,
+}
+
+pub(super) struct WebsocketTaskContext {
+    args: Arc<BenchWebsocketArgs>,
+    proc_args: Arc<ProcArgs>,
+    saved_connection: Option<WebsocketConnection>,
+
+    runtime_stats: Arc<WebsocketRuntimeStats>,
+    histogram_recorder: WebsocketHistogramRecorder,
+
+    payload: Vec<u8>,
+    send_buf: Vec<u8>,
+    recv_buf: Vec<u8>,
+}
+
+impl WebsocketTaskContext {
+    pub(super) fn new(
+        args: &Arc<BenchWebsocketArgs>,
+        proc_args: &Arc<ProcArgs>,
+        runtime_stats: &Arc<WebsocketRuntimeStats>,
+        histogram_recorder: WebsocketHistogramRecorder,
+    ) -> anyhow::Result<Self> {
+        let mut payload = vec![0u8; args.frame_size];
+        openssl::rand::rand_bytes(&mut payload[8..])
+            .map_err(|e| anyhow!("failed to generate frame payload: {e}"))?;
+
+        Ok(WebsocketTaskContext {
+            args: Arc::clone(args),
+            proc_args: Arc::clone(proc_args),
+            saved_connection: None,
+            runtime_stats: Arc::clone(runtime_stats),
+            histogram_recorder,
+            payload,
+            send_buf: Vec::with_capacity(args.frame_size + 14),
+            recv_buf: vec![0u8; args.frame_size],
+        })
+    }
+
+    async fn fetch_connection(&mut self) -> anyhow::Result<WebsocketConnection> {
+        if let Some(c) = self.saved_connection.take() {
+            return Ok(c);
+        }
+
+        self.runtime_stats.add_conn_attempt();
+        let time_started = Instant::now();
+        let (r, w) = match tokio::time::timeout(
+            self.args.connect_timeout,
+            self.args.new_websocket_connection(&self.proc_args),
+        )
+        .await
+        {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Err(anyhow!("timeout to get new websocket connection")),
+        };
+        self.histogram_recorder
+            .record_handshake_time(time_started.elapsed());
+        self.runtime_stats.add_conn_success();
+
+        let speed_limit = &self.proc_args.tcp_sock_speed_limit;
+        let reader = LimitedReader::new(
+            r,
+            speed_limit.shift_millis,
+            speed_limit.max_south,
+            self.runtime_stats.clone() as _,
+        );
+        let writer = LimitedWriter::new(
+            w,
+            speed_limit.shift_millis,
+            speed_limit.max_north,
+            self.runtime_stats.clone() as _,
+        );
+        Ok(WebsocketConnection {
+            reader,
+            writer,
+            frames_sent: 0,
+        })
+    }
+
+    fn close_connection(&self, mut connection: WebsocketConnection) {
+        self.runtime_stats.add_conn_close();
+
+        let runtime_stats = self.runtime_stats.clone();
+        tokio::spawn(async move {
+            let mut buf = Vec::with_capacity(8);
+            // status code 1000 for normal closure
+            let payload = 1000u16.to_be_bytes();
+            match tokio::time::timeout(Duration::from_secs(4), async {
+                frame::send_client_frame(
+                    &mut connection.writer,
+                    &mut buf,
+                    frame::OPCODE_CLOSE,
+                    &payload,
+                )
+                .await?;
+                connection
+                    .writer
+                    .shutdown()
+                    .await
+                    .map_err(|e| anyhow!("failed to shutdown connection: {e}"))
+            })
+            .await
+            {
+                Ok(Ok(_)) => {}
+                Ok(Err(_e)) => runtime_stats.add_conn_close_fail(),
+                Err(_) => runtime_stats.add_conn_close_timeout(),
+            }
+        });
+    }
+
+    async fn recv_echo(
+        &mut self,
+        connection: &mut WebsocketConnection,
+        seq: u64,
+    ) -> anyhow::Result<()> {
+        let mut recv_len = 0usize;
+        let mut control_buf = [0u8; 125];
+
+        loop {
+            let header = FrameHeader::read(&mut connection.reader).await?;
+            match header.opcode {
+                frame::OPCODE_PING => {
+                    let payload = &mut control_buf[..header.payload_len as usize];
+                    header.read_payload(&mut connection.reader, payload).await?;
+                    frame::send_client_frame(
+                        &mut connection.writer,
+                        &mut self.send_buf,
+                        frame::OPCODE_PONG,
+                        payload,
+                    )
+                    .await
+                    .context("failed to reply pong")?;
+                }
+                frame::OPCODE_PONG => {
+                    header.skip_payload(&mut connection.reader).await?;
+                }
+                frame::OPCODE_CLOSE => {
+                    return Err(anyhow!("connection closed by server"));
+                }
+                frame::OPCODE_TEXT | frame::OPCODE_BINARY | frame::OPCODE_CONTINUATION => {
+                    if (header.opcode == frame::OPCODE_CONTINUATION) != (recv_len > 0) {
+                        return Err(anyhow!("unexpected data frame opcode {}", header.opcode));
+                    }
+
+                    let end = recv_len + header.payload_len as usize;
+                    if header.payload_len > self.args.frame_size as u64
+                        || end > self.args.frame_size
+                    {
+                        return Err(anyhow!(
+                            "echo message size exceeds the frame size {}",
+                            self.args.frame_size
+                        ));
+                    }
+                    header
+                        .read_payload(&mut connection.reader, &mut self.recv_buf[recv_len..end])
+                        .await?;
+                    recv_len = end;
+
+                    if header.fin {
+                        self.runtime_stats.add_frame_recv();
+                        if recv_len != self.args.frame_size {
+                            return Err(anyhow!(
+                                "echo message size {recv_len} mismatch with frame size {}",
+                                self.args.frame_size
+                            ));
+                        }
+                        let mut seq_buf = [0u8; 8];
+                        seq_buf.copy_from_slice(&self.recv_buf[..8]);
+                        let echo_seq = u64::from_be_bytes(seq_buf);
+                        if echo_seq != seq {
+                            return Err(anyhow!(
+                                "unexpected echo sequence {echo_seq}, expected {seq}"
+                            ));
+                        }
+                        return Ok(());
+                    }
+                }
+                n => return Err(anyhow!("unsupported frame opcode {n}")),
+            }
+        }
+    }
+
+    async fn run_with_connection(
+        &mut self,
+        time_send: Instant,
+        seq: u64,
+        connection: &mut WebsocketConnection,
+    ) -> anyhow::Result<()> {
+        self.payload[..8].copy_from_slice(&seq.to_be_bytes());
+        frame::send_client_frame(
+            &mut connection.writer,
+            &mut self.send_buf,
+            frame::OPCODE_BINARY,
+            &self.payload,
+        )
+        .await?;
+        connection.frames_sent += 1;
+        self.runtime_stats.add_frame_send();
+
+        match tokio::time::timeout(self.args.timeout, self.recv_echo(connection, seq)).await {
+            Ok(Ok(_)) => {
+                self.histogram_recorder.record_rtt(time_send.elapsed());
+                Ok(())
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(BenchErrorType::Timeout.error("timeout to recv echo frame")),
+        }
+    }
+}
+
+impl BenchTaskContext for WebsocketTaskContext {
+    fn mark_task_start(&self) {
+        self.runtime_stats.add_task_total();
+        self.runtime_stats.inc_task_alive();
+    }
+
+    fn mark_task_passed(&self) {
+        self.runtime_stats.add_task_passed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    fn mark_task_failed(&self) {
+        self.runtime_stats.add_task_failed();
+        self.runtime_stats.dec_task_alive();
+    }
+
+    async fn run(&mut self, task_id: usize, _time_started: Instant) -> Result<(), BenchError> {
+        let mut connection = self
+            .fetch_connection()
+            .await
+            .context(BenchErrorType::Connect)
+            .context("connect to websocket server failed")
+            .map_err(BenchError::Fatal)?;
+
+        // the rtt should not include the handshake time
+        let time_send = Instant::now();
+        match self
+            .run_with_connection(time_send, task_id as u64, &mut connection)
+            .await
+        {
+            Ok(_) => {
+                if self
+                    .args
+                    .frames_per_conn
+                    .map(|n| connection.frames_sent >= n)
+                    .unwrap_or(false)
+                {
+                    self.close_connection(connection);
+                } else {
+                    self.saved_connection = Some(connection);
+                }
+                Ok(())
+            }
+            Err(e) => Err(BenchError::Task(e)),
+        }
+    }
+}
@@ -14,40 +14,51 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
-use flume::{Receiver, Sender};
-use log::{debug, error, warn};
+use flume::{Receiver, TrySendError};
+use log::{debug, warn};
+use openssl::hash::MessageDigest;
+use openssl::x509::X509;
 use tokio::runtime::Handle;
 
-use g3_tls_cert::builder::{ServerCertBuilder, TlsServerCertBuilder};
+use g3_tls_cert::builder::{ServerCertBuilder, TlsServerCertBuilder, TlsServerCertKeyType};
+use g3_tls_cert::cache::{CertCacheEntry, CertDiskCache};
 use g3_types::net::Host;
 
 mod stats;
 pub(crate) use stats::BackendStats;
 
-use super::{BackendRequest, BackendResponse};
+use super::BackendRequest;
 use crate::config::OpensslBackendConfig;
 use crate::frontend::ResponseData;
 
+const RESPONSE_TTL: u32 = 300;
+
 pub(crate) struct OpensslBackend {
     config: Arc<OpensslBackendConfig>,
     builder: ServerCertBuilder,
+    mimic_builders: HashMap<TlsServerCertKeyType, ServerCertBuilder>,
+    cache: Option<Arc<CertDiskCache>>,
     stats: Arc<BackendStats>,
 }
 
 impl OpensslBackend {
     pub(crate) fn new(
         config: &Arc<OpensslBackendConfig>,
+        cache: &Option<Arc<CertDiskCache>>,
         stats: &Arc<BackendStats>,
     ) -> anyhow::Result<Self> {
         let builder = TlsServerCertBuilder::new_ec256()?;
         Ok(OpensslBackend {
             config: Arc::clone(config),
             builder,
+            mimic_builders: HashMap::new(),
+            cache: cache.clone(),
             stats: Arc::clone(stats),
         })
     }
@@ -57,36 +68,143 @@ impl OpensslBackend {
         self.builder.refresh_datetime()?;
         self.builder.refresh_ec256()?;
         self.builder.refresh_serial()?;
+        // the mimic builders will be created again when needed
+        self.mimic_builders.clear();
         self.stats.add_refresh_ok();
         Ok(())
     }
 
-    pub(crate) fn generate(&self, host: &str) -> anyhow::Result<ResponseData> {
+    pub(crate) async fn generate(
+        &mut self,
+        host: &str,
+        mimic_cert: Option<&X509>,
+    ) -> anyhow::Result<ResponseData> {
         self.stats.add_request_total();
         let host = Host::from_str(host)?;
-        let cert =
-            self.builder
-                .build_fake(&host, &self.config.ca_cert, &self.config.ca_key, None)?;
-        let mut cert_pem = cert
+
+        let data = match mimic_cert {
+            Some(mimic_cert) => {
+                let mimic_cert_sha256 = mimic_cert
+                    .digest(MessageDigest::sha256())
+                    .map_err(|e| anyhow!("failed to get digest of the mimic cert: {e}"))?;
+                let key_type = TlsServerCertKeyType::from_cert(mimic_cert);
+                if !self.mimic_builders.contains_key(&key_type) {
+                    // RSA key generation may take a long time, so keep it off the async worker
+                    let builder = tokio::task::spawn_blocking(move || key_type.new_builder())
+                        .await
+                        .map_err(|e| anyhow!("failed to join key generation task: {e}"))??;
+                    self.mimic_builders.insert(key_type, builder);
+                }
+                let builder = &self.mimic_builders[&key_type];
+
+                let cache_name =
+                    g3_tls_cert::cache::mimic_cache_name(&host.to_string(), &mimic_cert_sha256);
+                let mut data = self
+                    .generate_with(builder, &host, cache_name, |builder| {
+                        builder.build_mimic(
+                            &host,
+                            mimic_cert,
+                            &self.config.ca_cert,
+                            &self.config.ca_key,
+                            None,
+                        )
+                    })
+                    .await?;
+                data.mimic_cert_sha256 = Some(mimic_cert_sha256.to_vec());
+                data
+            }
+            None => {
+                self.generate_with(&self.builder, &host, host.to_string(), |builder| {
+                    builder.build_fake(&host, &self.config.ca_cert, &self.config.ca_key, None)
+                })
+                .await?
+            }
+        };
+
+        self.stats.add_request_ok();
+        Ok(data)
+    }
+
+    async fn generate_with<F>(
+        &self,
+        builder: &ServerCertBuilder,
+        host: &Host,
+        cache_name: String,
+        build: F,
+    ) -> anyhow::Result<ResponseData>
+    where
+        F: FnOnce(&ServerCertBuilder) -> anyhow::Result<X509>,
+    {
+        let key_type = g3_tls_cert::cache::key_type_name(builder.pkey());
+
+        if let Some(entry) = self.cache_get(&cache_name, &key_type).await {
+            let ttl = entry.ttl().min(RESPONSE_TTL);
+            if ttl > 0 {
+                self.stats.add_cache_hit();
+                return Ok(self.build_response(host, entry.cert_pem, entry.key_pem, ttl));
+            }
+        }
+
+        let cert = build(builder)?;
+        let cert_pem = cert
             .to_pem()
             .map_err(|e| anyhow!("failed to encode cert: {e}"))?;
-        if !self.config.ca_cert_pem.is_empty() {
-            cert_pem.extend_from_slice(&self.config.ca_cert_pem);
-        }
-        let key_pem = self
-            .builder
+        let key_pem = builder
             .pkey()
             .private_key_to_pem_pkcs8()
             .map_err(|e| anyhow!("failed to encode pkey: {e}"))?;
 
-        let data = ResponseData {
+        if let (Some(cache), Some(config)) = (&self.cache, &self.config.cache) {
+            let entry = CertCacheEntry::new(
+                cache_name,
+                key_type,
+                config.ttl,
+                cert_pem.clone(),
+                key_pem.clone(),
+            );
+            let cache = Arc::clone(cache);
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = cache.put(&entry) {
+                    warn!("failed to save cert for {} to cache: {e:?}", entry.host);
+                }
+            });
+        }
+
+        Ok(self.build_response(host, cert_pem, key_pem, RESPONSE_TTL))
+    }
+
+    async fn cache_get(&self, name: &str, key_type: &str) -> Option<CertCacheEntry> {
+        let cache = self.cache.clone()?;
+        let name = name.to_string();
+        let key_type = key_type.to_string();
+        match tokio::task::spawn_blocking(move || cache.get(&name, &key_type)).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("failed to join cert cache query task: {e}");
+                None
+            }
+        }
+    }
+
+    fn build_response(
+        &self,
+        host: &Host,
+        mut cert_pem: Vec<u8>,
+        key_pem: Vec<u8>,
+        ttl: u32,
+    ) -> ResponseData {
+        // the ca cert is not cached, so we can always use the latest chain
+        if !self.config.ca_cert_pem.is_empty() {
+            cert_pem.extend_from_slice(&self.config.ca_cert_pem);
+        }
+
+        ResponseData {
             host: host.to_string(),
             cert: unsafe { String::from_utf8_unchecked(cert_pem) },
             key: unsafe { String::from_utf8_unchecked(key_pem) },
-            ttl: 300,
-        };
-        self.stats.add_request_ok();
-        Ok(data)
+            ttl,
+            mimic_cert_sha256: None,
+        }
     }
 
     pub(crate) fn spawn(
@@ -94,7 +212,6 @@ impl OpensslBackend {
         handle: &Handle,
         id: usize,
         req_receiver: Receiver<BackendRequest>,
-        rsp_sender: Sender<BackendResponse>,
     ) {
         handle.spawn(async move {
             let mut interval = tokio::time::interval(Duration::from_secs(300));
@@ -111,14 +228,23 @@ impl OpensslBackend {
                             break
                         };
 
-                        match self.generate(&req.host) {
+                        match self.generate(&req.host, req.mimic_cert.as_ref()).await {
                             Ok(data) => {
                                 debug!("Worker#{id} got certificate for host {}", req.host);
-                                if let Err(e) = rsp_sender.send_async(req.response(data)).await {
-                                    error!(
-                                        "Worker#{id} failed to send certificate for host {} to frontend: {e}", req.host
-                                    );
-                                    break;
+                                // never block the worker on a slow frontend connection
+                                match req.rsp_sender.try_send(req.response(data)) {
+                                    Ok(_) => {}
+                                    Err(TrySendError::Full(_)) => {
+                                        warn!(
+                                            "Worker#{id} dropped certificate for host {} as the frontend response queue is full", req.host
+                                        );
+                                    }
+                                    Err(TrySendError::Disconnected(_)) => {
+                                        // the frontend connection may have been closed
+                                        debug!(
+                                            "Worker#{id} failed to send certificate for host {} to frontend: connection closed", req.host
+                                        );
+                                    }
                                 }
                             }
                             Err(e) => {
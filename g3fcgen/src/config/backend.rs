@@ -14,8 +14,10 @@
  * limitations under the License.
  */
 
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::OnceLock;
+use std::time::Duration;
 
 use anyhow::{anyhow, Context};
 use openssl::pkey::{PKey, Private};
@@ -23,6 +25,8 @@ use openssl::x509::X509;
 use yaml_rust::Yaml;
 
 use g3_histogram::HistogramMetricsConfig;
+use g3_tls_cert::cache::CertDiskCache;
+use g3_tls_cert::ext::PublicKeyExt;
 
 static BACKEND_CONFIG_LOCK: OnceLock<Arc<OpensslBackendConfig>> = OnceLock::new();
 
@@ -30,11 +34,93 @@ pub(crate) fn get_config() -> Option<Arc<OpensslBackendConfig>> {
     BACKEND_CONFIG_LOCK.get().cloned()
 }
 
+pub(crate) struct BackendCacheConfig {
+    pub(crate) directory: PathBuf,
+    pub(crate) max_entries: usize,
+    pub(crate) ttl: u32,
+}
+
+impl BackendCacheConfig {
+    fn new(directory: PathBuf) -> Self {
+        BackendCacheConfig {
+            directory,
+            max_entries: 65536,
+            ttl: 7 * 86400,
+        }
+    }
+
+    fn parse(value: &Yaml, lookup_dir: &Path) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                let v = g3_yaml::hash_get_required(map, "directory")?;
+                let dir = g3_yaml::value::as_dir_path(v, lookup_dir, true)
+                    .context("invalid directory path value for key directory")?;
+                let mut config = BackendCacheConfig::new(dir);
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "directory" => Ok(()),
+                    "max_entries" => {
+                        config.max_entries = g3_yaml::value::as_usize(v)?;
+                        Ok(())
+                    }
+                    "ttl" => {
+                        let ttl = g3_yaml::humanize::as_duration(v)
+                            .context(format!("invalid humanize duration value for key {k}"))?;
+                        if ttl < Duration::from_secs(1) {
+                            return Err(anyhow!("too small ttl value for key {k}"));
+                        }
+                        config.ttl = u32::try_from(ttl.as_secs()).unwrap_or(u32::MAX);
+                        Ok(())
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+
+                Ok(config)
+            }
+            Yaml::String(_) => {
+                let dir = g3_yaml::value::as_dir_path(value, lookup_dir, true)
+                    .context("invalid directory path string value")?;
+                Ok(BackendCacheConfig::new(dir))
+            }
+            _ => Err(anyhow!(
+                "yaml value type for the cache config should be 'map' or 'string'"
+            )),
+        }
+    }
+}
+
 pub(crate) struct OpensslBackendConfig {
     pub(crate) ca_cert: X509,
     pub(crate) ca_key: PKey<Private>,
     pub(crate) ca_cert_pem: Vec<u8>,
     pub(crate) duration_stats: HistogramMetricsConfig,
+    pub(crate) cache: Option<BackendCacheConfig>,
+}
+
+impl OpensslBackendConfig {
+    pub(crate) fn open_cache(&self) -> anyhow::Result<Option<Arc<CertDiskCache>>> {
+        let Some(config) = &self.cache else {
+            return Ok(None);
+        };
+
+        // use a separate sub directory for each CA, so we won't use old certs if the CA changed
+        let ca_ski = self
+            .ca_cert
+            .public_key()
+            .and_then(|k| k.ski())
+            .map_err(|e| anyhow!("failed to get ski of the ca cert: {e}"))?;
+        let mut sub_dir = String::with_capacity(ca_ski.len() * 2);
+        for b in ca_ski.iter() {
+            sub_dir.push_str(&format!("{b:02x}"));
+        }
+        let dir = config.directory.join(sub_dir);
+
+        let cache = CertDiskCache::open(&dir, config.max_entries).context(format!(
+            "failed to open cert cache in dir {}",
+            dir.display()
+        ))?;
+        Ok(Some(Arc::new(cache)))
+    }
 }
 
 pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
@@ -44,6 +130,7 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
         let mut ca_cert: Option<X509> = None;
         let mut ca_key: Option<PKey<Private>> = None;
         let mut duration_stats = HistogramMetricsConfig::default();
+        let mut cache: Option<BackendCacheConfig> = None;
         let lookup_dir = g3_daemon::config::get_lookup_dir(None)?;
 
         g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
@@ -80,6 +167,12 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
                 )?;
                 Ok(())
             }
+            "cache" => {
+                let config = BackendCacheConfig::parse(v, lookup_dir)
+                    .context(format!("invalid cache config value for key {k}"))?;
+                cache = Some(config);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         })?;
 
@@ -99,12 +192,13 @@ pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
                 ca_key,
                 ca_cert_pem,
                 duration_stats,
+                cache,
             }))
             .map_err(|_| anyhow!("duplicate backend config"))?;
         Ok(())
     } else {
         Err(anyhow!(
-            "yam value type for the backend config should be 'map'"
+            "yaml value type for the backend config should be 'map'"
         ))
     }
 }
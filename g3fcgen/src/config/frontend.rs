@@ -0,0 +1,75 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+static FRONTEND_CONFIG_LOCK: OnceLock<FrontendConfig> = OnceLock::new();
+
+pub(crate) fn get_config() -> Option<&'static FrontendConfig> {
+    FRONTEND_CONFIG_LOCK.get()
+}
+
+#[derive(Default)]
+pub(crate) struct FrontendConfig {
+    pub(crate) udp_listen: Option<SocketAddr>,
+    pub(crate) tcp_listen: Option<SocketAddr>,
+    #[cfg(unix)]
+    pub(crate) unix_listen: Option<PathBuf>,
+}
+
+pub(super) fn load_config(value: &Yaml) -> anyhow::Result<()> {
+    if let Yaml::Hash(map) = value {
+        let mut config = FrontendConfig::default();
+
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "udp_listen" | "udp" => {
+                let addr = g3_yaml::value::as_env_sockaddr(v)
+                    .context(format!("invalid sockaddr str value for key {k}"))?;
+                config.udp_listen = Some(addr);
+                Ok(())
+            }
+            "tcp_listen" | "tcp" => {
+                let addr = g3_yaml::value::as_env_sockaddr(v)
+                    .context(format!("invalid sockaddr str value for key {k}"))?;
+                config.tcp_listen = Some(addr);
+                Ok(())
+            }
+            #[cfg(unix)]
+            "unix_listen" | "unix" => {
+                let path = g3_yaml::value::as_absolute_path(v)
+                    .context(format!("invalid absolute path value for key {k}"))?;
+                config.unix_listen = Some(path);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        FRONTEND_CONFIG_LOCK
+            .set(config)
+            .map_err(|_| anyhow!("duplicate frontend config"))?;
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "yaml value type for the frontend config should be 'map'"
+        ))
+    }
+}
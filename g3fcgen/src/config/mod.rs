@@ -22,6 +22,9 @@ use yaml_rust::{yaml, Yaml};
 mod backend;
 pub(crate) use backend::{get_config as get_backend_config, OpensslBackendConfig};
 
+mod frontend;
+pub(crate) use frontend::get_config as get_frontend_config;
+
 pub fn load() -> anyhow::Result<&'static Path> {
     let config_file =
         g3_daemon::opts::config_file().ok_or_else(|| anyhow!("no config file set"))?;
@@ -41,6 +44,7 @@ fn load_doc(map: &yaml::Hash) -> anyhow::Result<()> {
         "worker" => g3_daemon::runtime::config::load_worker(v),
         "stat" => g3_daemon::stat::config::load(v, crate::build::PKG_NAME),
         "backend" => backend::load_config(v),
+        "frontend" => frontend::load_config(v),
         _ => Err(anyhow!("invalid key {k} in main conf")),
     })?;
     Ok(())
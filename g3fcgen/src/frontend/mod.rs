@@ -15,6 +15,7 @@
  */
 
 use anyhow::{anyhow, Context};
+use openssl::x509::X509;
 use rmpv::ValueRef;
 
 mod stats;
@@ -23,17 +24,21 @@ pub(crate) use stats::FrontendStats;
 mod udp_dgram;
 pub(crate) use udp_dgram::UdpDgramFrontend;
 
+mod stream;
+pub(crate) use stream::StreamFrontend;
+
 #[derive(Debug)]
 pub(crate) struct ResponseData {
     pub(crate) host: String,
     pub(crate) cert: String,
     pub(crate) key: String,
     pub(crate) ttl: u32,
+    pub(crate) mimic_cert_sha256: Option<Vec<u8>>,
 }
 
 impl ResponseData {
     pub(crate) fn encode(&self) -> anyhow::Result<Vec<u8>> {
-        let map = vec![
+        let mut map = vec![
             (
                 ValueRef::String("host".into()),
                 ValueRef::String(self.host.as_str().into()),
@@ -51,6 +56,12 @@ impl ResponseData {
                 ValueRef::Integer(self.ttl.into()),
             ),
         ];
+        if let Some(digest) = &self.mimic_cert_sha256 {
+            map.push((
+                ValueRef::String("mimic_cert_sha256".into()),
+                ValueRef::Binary(digest.as_slice()),
+            ));
+        }
         let mut buf = Vec::with_capacity(32);
         let v = ValueRef::Map(map);
         rmpv::encode::write_value_ref(&mut buf, &v)
@@ -59,12 +70,18 @@ impl ResponseData {
     }
 }
 
-pub(crate) fn decode_req(mut data: &[u8]) -> anyhow::Result<String> {
+pub(crate) struct RequestData {
+    pub(crate) host: String,
+    pub(crate) mimic_cert: Option<X509>,
+}
+
+pub(crate) fn decode_req(mut data: &[u8]) -> anyhow::Result<RequestData> {
     let v =
         rmpv::decode::read_value_ref(&mut data).map_err(|e| anyhow!("invalid req data: {e}"))?;
 
     if let ValueRef::Map(map) = v {
         let mut host = String::default();
+        let mut mimic_cert = None;
 
         for (k, v) in map {
             let key = g3_msgpack::value::as_string(&k)?;
@@ -73,6 +90,11 @@ pub(crate) fn decode_req(mut data: &[u8]) -> anyhow::Result<String> {
                     host = g3_msgpack::value::as_string(&v)
                         .context(format!("invalid string value for key {key}"))?;
                 }
+                "mimic_cert" => {
+                    let cert = g3_msgpack::value::as_openssl_certificate(&v)
+                        .context(format!("invalid openssl certificate value for key {key}"))?;
+                    mimic_cert = Some(cert);
+                }
                 _ => return Err(anyhow!("invalid key {key}")),
             }
         }
@@ -80,7 +102,7 @@ pub(crate) fn decode_req(mut data: &[u8]) -> anyhow::Result<String> {
         if host.is_empty() {
             Err(anyhow!("invalid host value"))
         } else {
-            Ok(host)
+            Ok(RequestData { host, mimic_cert })
         }
     } else {
         Err(anyhow!("the req root data type should be map"))
@@ -0,0 +1,323 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use flume::{Receiver, Sender};
+use log::{debug, warn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+#[cfg(unix)]
+use tokio::net::UnixListener;
+use tokio::time::Instant;
+
+use g3_histogram::HistogramRecorder;
+
+use super::FrontendStats;
+use crate::{BackendRequest, BackendResponse};
+
+const MAX_REQUEST_SIZE: usize = 65536;
+/// The connection will be closed if no new request is received in this time
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// The max time to wait for the remaining data of a request after the length is received
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Frontend for stream sockets, each message is prefixed with a 4 bytes big endian length.
+/// Requests on the same connection can be pipelined, and the responses may be out of order.
+/// Idle connections will be closed after a while, and the peer should reconnect when needed.
+#[derive(Clone)]
+pub(crate) struct StreamFrontend {
+    req_sender: Sender<BackendRequest>,
+    stats: Arc<FrontendStats>,
+    duration_recorder: HistogramRecorder<u64>,
+}
+
+impl StreamFrontend {
+    pub(crate) fn new(
+        req_sender: Sender<BackendRequest>,
+        stats: Arc<FrontendStats>,
+        duration_recorder: HistogramRecorder<u64>,
+    ) -> Self {
+        StreamFrontend {
+            req_sender,
+            stats,
+            duration_recorder,
+        }
+    }
+
+    pub(crate) async fn run_tcp(self, listener: TcpListener) -> anyhow::Result<()> {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    debug!("new tcp connection from {peer}");
+                    let (r, w) = stream.into_split();
+                    self.clone().spawn_connection(r, w);
+                }
+                Err(e) => warn!("failed to accept tcp connection: {e}"),
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    pub(crate) async fn run_unix(self, listener: UnixListener) -> anyhow::Result<()> {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let (r, w) = stream.into_split();
+                    self.clone().spawn_connection(r, w);
+                }
+                Err(e) => warn!("failed to accept unix connection: {e}"),
+            }
+        }
+    }
+
+    fn spawn_connection<R, W>(self, reader: R, writer: W)
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let (rsp_sender, rsp_receiver) = flume::bounded::<BackendResponse>(1024);
+
+        let stats = self.stats.clone();
+        let duration_recorder = self.duration_recorder.clone();
+        tokio::spawn(async move {
+            if let Err(e) = send_rsp_loop(writer, rsp_receiver, stats, duration_recorder).await {
+                debug!("stream frontend write error: {e:?}");
+            }
+        });
+        tokio::spawn(async move {
+            if let Err(e) = self.recv_req_loop(reader, rsp_sender).await {
+                debug!("stream frontend read error: {e:?}");
+            }
+        });
+    }
+
+    async fn recv_req_loop<R>(
+        &self,
+        mut reader: R,
+        rsp_sender: Sender<BackendResponse>,
+    ) -> anyhow::Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buf = Vec::with_capacity(1024);
+        loop {
+            if !read_req_frame(&mut reader, &mut buf, IDLE_TIMEOUT, READ_TIMEOUT).await? {
+                return Ok(());
+            }
+
+            self.stats.add_request_total();
+            let recv_time = Instant::now();
+            match super::decode_req(&buf) {
+                Ok(data) => {
+                    let req = BackendRequest {
+                        host: data.host,
+                        mimic_cert: data.mimic_cert,
+                        peer: None,
+                        recv_time,
+                        rsp_sender: rsp_sender.clone(),
+                    };
+                    self.req_sender
+                        .send_async(req)
+                        .await
+                        .map_err(|e| anyhow!("failed to send request to backend: {e}"))?;
+                }
+                Err(e) => {
+                    self.stats.add_request_invalid();
+                    warn!("invalid request from stream peer: {e:?}");
+                }
+            }
+        }
+    }
+}
+
+/// Read a length prefixed request frame into `buf`.
+///
+/// Return false if the connection is closed by the peer before a new frame.
+async fn read_req_frame<R>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    idle_timeout: Duration,
+    read_timeout: Duration,
+) -> anyhow::Result<bool>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = match tokio::time::timeout(idle_timeout, reader.read_u32()).await {
+        Ok(Ok(len)) => len as usize,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+        Ok(Err(e)) => return Err(anyhow!("failed to read request length: {e}")),
+        Err(_) => return Err(anyhow!("idle timeout while waiting for new request")),
+    };
+    if len > MAX_REQUEST_SIZE {
+        return Err(anyhow!("too large request size {len}"));
+    }
+    buf.resize(len, 0);
+    match tokio::time::timeout(read_timeout, reader.read_exact(buf)).await {
+        Ok(Ok(_)) => Ok(true),
+        Ok(Err(e)) => Err(anyhow!("failed to read request body: {e}")),
+        Err(_) => Err(anyhow!("timeout to read request body")),
+    }
+}
+
+fn encode_rsp_frame(data: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(data.len() + 4);
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+    buf
+}
+
+async fn send_rsp_loop<W>(
+    mut writer: W,
+    rsp_receiver: Receiver<BackendResponse>,
+    stats: Arc<FrontendStats>,
+    duration_recorder: HistogramRecorder<u64>,
+) -> anyhow::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    // the loop ends when the reader side is closed and all pending responses are sent
+    while let Ok(rsp) = rsp_receiver.recv_async().await {
+        let data = rsp
+            .data
+            .encode()
+            .map_err(|e| anyhow!("response encode error: {e:?}"))?;
+        let buf = encode_rsp_frame(&data);
+
+        stats.add_response_total();
+        if let Err(e) = writer.write_all(&buf).await {
+            stats.add_response_fail();
+            return Err(anyhow!("write response back error: {e}"));
+        }
+        let _ = duration_recorder.record(rsp.duration());
+    }
+    let _ = writer.shutdown().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[tokio::test]
+    async fn read_frames() {
+        let mut data = encode_rsp_frame(b"hello");
+        data.extend_from_slice(&encode_rsp_frame(b""));
+        data.extend_from_slice(&encode_rsp_frame(b"world"));
+        let mut reader = data.as_slice();
+
+        let mut buf = Vec::new();
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .unwrap()
+        );
+        assert_eq!(buf, b"hello");
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .unwrap()
+        );
+        assert!(buf.is_empty());
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .unwrap()
+        );
+        assert_eq!(buf, b"world");
+        assert!(
+            !read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn encode_frame() {
+        assert_eq!(encode_rsp_frame(b""), [0, 0, 0, 0]);
+        assert_eq!(encode_rsp_frame(b"abc"), [0, 0, 0, 3, b'a', b'b', b'c']);
+
+        let data = vec![0x5a; 0x10203];
+        let buf = encode_rsp_frame(&data);
+        assert_eq!(&buf[..4], &[0, 1, 2, 3]);
+        assert_eq!(&buf[4..], data.as_slice());
+    }
+
+    #[tokio::test]
+    async fn max_request_size() {
+        let data = vec![0x5a; MAX_REQUEST_SIZE];
+        let frame = encode_rsp_frame(&data);
+        let mut reader = frame.as_slice();
+        let mut buf = Vec::new();
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .unwrap()
+        );
+        assert_eq!(buf.len(), MAX_REQUEST_SIZE);
+
+        let data = vec![0x5a; MAX_REQUEST_SIZE + 1];
+        let frame = encode_rsp_frame(&data);
+        let mut reader = frame.as_slice();
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn truncated() {
+        let mut buf = Vec::new();
+
+        let mut reader: &[u8] = &[0, 0];
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .is_err()
+        );
+
+        let mut reader: &[u8] = &[0, 0, 0, 5, b'a', b'b'];
+        assert!(
+            read_req_frame(&mut reader, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let mut buf = Vec::new();
+
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        assert!(
+            read_req_frame(&mut server, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .is_err()
+        );
+
+        client.write_all(&[0, 0, 0, 5, b'a']).await.unwrap();
+        assert!(
+            read_req_frame(&mut server, &mut buf, TEST_TIMEOUT, TEST_TIMEOUT)
+                .await
+                .is_err()
+        );
+    }
+}
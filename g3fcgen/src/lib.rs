@@ -19,9 +19,16 @@ use std::sync::Arc;
 
 use ::log::warn;
 use anyhow::{anyhow, Context};
+use flume::Sender;
+use openssl::x509::X509;
+use tokio::net::TcpListener;
 use tokio::runtime::Handle;
+use tokio::task::JoinSet;
 use tokio::time::Instant;
 
+use g3_histogram::HistogramRecorder;
+use g3_types::ext::DurationExt;
+
 pub mod config;
 
 mod build;
@@ -35,18 +42,20 @@ mod backend;
 use backend::{BackendStats, OpensslBackend};
 
 mod frontend;
-use frontend::{FrontendStats, ResponseData, UdpDgramFrontend};
-use g3_types::ext::DurationExt;
+use frontend::{FrontendStats, ResponseData, StreamFrontend, UdpDgramFrontend};
 
 struct BackendRequest {
     host: String,
-    peer: SocketAddr,
+    mimic_cert: Option<X509>,
+    /// the peer address for datagram frontends
+    peer: Option<SocketAddr>,
     recv_time: Instant,
+    rsp_sender: Sender<BackendResponse>,
 }
 
 struct BackendResponse {
     data: ResponseData,
-    peer: SocketAddr,
+    peer: Option<SocketAddr>,
     recv_time: Instant,
 }
 
@@ -68,24 +77,24 @@ impl BackendResponse {
 
 pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
     let (req_sender, req_receiver) = flume::bounded::<BackendRequest>(1024);
-    let (rsp_sender, rsp_receiver) = flume::bounded::<BackendResponse>(1024);
 
     let backend_config =
         config::get_backend_config().ok_or_else(|| anyhow!("no backend config available"))?;
     let backend_stats = Arc::new(BackendStats::default());
+    let cert_cache = backend_config.open_cache()?;
 
     let (duration_recorder, duration_stats) = backend_config.duration_stats.build_spawned(None);
 
     let workers = g3_daemon::runtime::worker::foreach(|h| {
-        let backend = OpensslBackend::new(&backend_config, &backend_stats)
+        let backend = OpensslBackend::new(&backend_config, &cert_cache, &backend_stats)
             .context(format!("failed to build backend for worker {}", h.id))?;
-        backend.spawn(&h.handle, h.id, req_receiver.clone(), rsp_sender.clone());
+        backend.spawn(&h.handle, h.id, req_receiver.clone());
         Ok::<(), anyhow::Error>(())
     })?;
     if workers < 1 {
-        let backend = OpensslBackend::new(&backend_config, &backend_stats)
+        let backend = OpensslBackend::new(&backend_config, &cert_cache, &backend_stats)
             .context("failed to build backend for main runtime")?;
-        backend.spawn(&Handle::current(), 0, req_receiver, rsp_sender);
+        backend.spawn(&Handle::current(), 0, req_receiver);
     }
 
     let frontend_stats = Arc::new(FrontendStats::default());
@@ -98,55 +107,110 @@ pub async fn run(proc_args: &ProcArgs) -> anyhow::Result<()> {
         )?;
     }
 
-    if let Some(addr) = proc_args.udp_addr {
+    let frontend_config = config::get_frontend_config();
+    let mut frontend_tasks = JoinSet::new();
+
+    let stream_frontend = StreamFrontend::new(
+        req_sender.clone(),
+        frontend_stats.clone(),
+        duration_recorder.clone(),
+    );
+    if let Some(addr) = frontend_config.and_then(|c| c.tcp_listen) {
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|e| anyhow!("failed to listen on tcp addr {addr}: {e}"))?;
+        frontend_tasks.spawn(stream_frontend.clone().run_tcp(listener));
+    }
+    #[cfg(unix)]
+    if let Some(path) = frontend_config.and_then(|c| c.unix_listen.as_ref()) {
+        // remove the stale socket file left by the last run
+        let _ = std::fs::remove_file(path);
+        let listener = tokio::net::UnixListener::bind(path)
+            .map_err(|e| anyhow!("failed to listen on unix path {}: {e}", path.display()))?;
+        frontend_tasks.spawn(stream_frontend.clone().run_unix(listener));
+    }
+
+    if let Some(addr) = proc_args
+        .udp_addr
+        .or_else(|| frontend_config.and_then(|c| c.udp_listen))
+    {
         let frontend = UdpDgramFrontend::new(addr).await?;
+        frontend_tasks.spawn(run_udp(
+            frontend,
+            req_sender,
+            frontend_stats,
+            duration_recorder,
+        ));
+    }
 
-        let mut rcv_buf = [0u8; 1024];
-
-        loop {
-            tokio::select! {
-                r = frontend.recv_req(&mut rcv_buf) => {
-                    frontend_stats.add_request_total();
-                    let recv_time = Instant::now();
-                    match r {
-                        Ok((len, peer)) => match crate::frontend::decode_req(&rcv_buf[0..len]) {
-                            Ok(host) => {
-                                let req = BackendRequest {host, peer, recv_time};
-                                if let Err(e) = req_sender.send_async(req).await {
-                                    return Err(anyhow!("failed to send request to backend: {e}"));
-                                }
-                            }
-                            Err(e) => {
-                                frontend_stats.add_request_invalid();
-                                warn!("invalid request from peer {peer}: {e:?}");
+    match frontend_tasks.join_next().await {
+        Some(Ok(r)) => r,
+        Some(Err(e)) => Err(anyhow!("frontend task join error: {e}")),
+        None => Err(anyhow!("no frontend found")),
+    }
+}
+
+async fn run_udp(
+    frontend: UdpDgramFrontend,
+    req_sender: Sender<BackendRequest>,
+    frontend_stats: Arc<FrontendStats>,
+    duration_recorder: HistogramRecorder<u64>,
+) -> anyhow::Result<()> {
+    let (rsp_sender, rsp_receiver) = flume::bounded::<BackendResponse>(1024);
+
+    // the request may contain the upstream cert
+    let mut rcv_buf = vec![0u8; 16384];
+
+    loop {
+        tokio::select! {
+            r = frontend.recv_req(&mut rcv_buf) => {
+                frontend_stats.add_request_total();
+                let recv_time = Instant::now();
+                match r {
+                    Ok((len, peer)) => match crate::frontend::decode_req(&rcv_buf[0..len]) {
+                        Ok(data) => {
+                            let req = BackendRequest {
+                                host: data.host,
+                                mimic_cert: data.mimic_cert,
+                                peer: Some(peer),
+                                recv_time,
+                                rsp_sender: rsp_sender.clone(),
+                            };
+                            if let Err(e) = req_sender.send_async(req).await {
+                                return Err(anyhow!("failed to send request to backend: {e}"));
                             }
                         }
-                        Err(e) => return Err(anyhow!("frontend recv error: {e:?}")),
+                        Err(e) => {
+                            frontend_stats.add_request_invalid();
+                            warn!("invalid request from peer {peer}: {e:?}");
+                        }
                     }
+                    Err(e) => return Err(anyhow!("frontend recv error: {e:?}")),
                 }
-                r = rsp_receiver.recv_async() => {
-                    match r {
-                        Ok(rsp) => match rsp.data.encode() {
-                            Ok(buf) => {
-                                frontend_stats.add_response_total();
-                                match frontend.send_rsp(buf.as_slice(), rsp.peer).await {
-                                    Ok(_) => {
-                                        let _ = duration_recorder.record(rsp.duration());
-                                    }
-                                    Err(e) => {
-                                        frontend_stats.add_response_fail();
-                                        warn!("write response back error: {e:?}");
-                                    }
+            }
+            r = rsp_receiver.recv_async() => {
+                match r {
+                    Ok(rsp) => match rsp.data.encode() {
+                        Ok(buf) => {
+                            let Some(peer) = rsp.peer else {
+                                continue;
+                            };
+                            frontend_stats.add_response_total();
+                            match frontend.send_rsp(buf.as_slice(), peer).await {
+                                Ok(_) => {
+                                    let _ = duration_recorder.record(rsp.duration());
+                                }
+                                Err(e) => {
+                                    frontend_stats.add_response_fail();
+                                    warn!("write response back error: {e:?}");
                                 }
                             }
-                            Err(e) => return Err(anyhow!("response encode error: {e:?}")),
                         }
-                        Err(e) => return Err(anyhow!("recv from backend failed: {e}")),
+                        Err(e) => return Err(anyhow!("response encode error: {e:?}")),
                     }
+                    Err(e) => return Err(anyhow!("recv from backend failed: {e}")),
                 }
             }
         }
-    } else {
-        Err(anyhow!("no frontend found"))
     }
 }
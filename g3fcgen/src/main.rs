@@ -51,6 +51,8 @@ fn main() -> anyhow::Result<()> {
 
     // enter daemon mode after config loaded
     g3_daemon::daemonize::check_enter(&proc_args.daemon_config)?;
+    g3_daemon::stat::prometheus::spawn_working_thread()
+        .context("failed to start prometheus exporter")?;
 
     let ret = tokio_run(&proc_args);
 
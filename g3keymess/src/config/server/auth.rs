@@ -0,0 +1,424 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::x509::X509Ref;
+use yaml_rust::Yaml;
+
+use crate::protocol::{KeylessAction, KeylessErrorResponse, KeylessRequest};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ClientIdentity {
+    /// match SAN dns names, or the subject CN if there is no SAN dns names
+    Name(String),
+    /// match the SHA-256 hash of the DER encoded SubjectPublicKeyInfo
+    SpkiSha256(Vec<u8>),
+}
+
+impl ClientIdentity {
+    fn matches(&self, cert: &X509Ref) -> bool {
+        match self {
+            ClientIdentity::Name(name) => {
+                if let Some(names) = cert.subject_alt_names() {
+                    let mut has_dns_name = false;
+                    for n in names.iter().filter_map(|n| n.dnsname()) {
+                        if n.eq_ignore_ascii_case(name) {
+                            return true;
+                        }
+                        has_dns_name = true;
+                    }
+                    if has_dns_name {
+                        return false;
+                    }
+                }
+                cert.subject_name()
+                    .entries_by_nid(Nid::COMMONNAME)
+                    .filter_map(|e| e.data().as_utf8().ok())
+                    .any(|cn| cn.eq_ignore_ascii_case(name))
+            }
+            ClientIdentity::SpkiSha256(hash) => {
+                let Ok(key) = cert.public_key() else {
+                    return false;
+                };
+                let Ok(der) = key.public_key_to_der() else {
+                    return false;
+                };
+                openssl::hash::hash(MessageDigest::sha256(), &der)
+                    .map(|v| v.as_ref() == hash.as_slice())
+                    .unwrap_or(false)
+            }
+        }
+    }
+}
+
+const ACTION_PING: u8 = 1 << 0;
+const ACTION_RSA_DECRYPT: u8 = 1 << 1;
+const ACTION_RSA_SIGN: u8 = 1 << 2;
+const ACTION_RSA_PSS_SIGN: u8 = 1 << 3;
+const ACTION_ECDSA_SIGN: u8 = 1 << 4;
+const ACTION_ED25519_SIGN: u8 = 1 << 5;
+const ACTION_ALL: u8 = 0x3F;
+
+fn action_bit(action: &KeylessAction) -> u8 {
+    match action {
+        KeylessAction::NotSet => 0,
+        KeylessAction::Ping => ACTION_PING,
+        KeylessAction::RsaDecrypt(_) => ACTION_RSA_DECRYPT,
+        KeylessAction::RsaSign(_) => ACTION_RSA_SIGN,
+        KeylessAction::RsaPssSign(_) => ACTION_RSA_PSS_SIGN,
+        KeylessAction::EcdsaSign(_) => ACTION_ECDSA_SIGN,
+        KeylessAction::Ed25519Sign => ACTION_ED25519_SIGN,
+    }
+}
+
+fn parse_action_bit(v: &Yaml) -> anyhow::Result<u8> {
+    let s = g3_yaml::value::as_string(v)?;
+    match g3_yaml::key::normalize(&s).as_str() {
+        "ping" => Ok(ACTION_PING),
+        "rsa_decrypt" => Ok(ACTION_RSA_DECRYPT),
+        "rsa_sign" => Ok(ACTION_RSA_SIGN),
+        "rsa_pss_sign" => Ok(ACTION_RSA_PSS_SIGN),
+        "ecdsa_sign" => Ok(ACTION_ECDSA_SIGN),
+        "ed25519_sign" => Ok(ACTION_ED25519_SIGN),
+        _ => Err(anyhow!("unsupported keyless action {s}")),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KeyAuthorizationRule {
+    identity: Option<ClientIdentity>,
+    keys: HashSet<Vec<u8>>,
+    actions: u8,
+}
+
+impl KeyAuthorizationRule {
+    /// the rule for clients that match no configured rules
+    pub(crate) fn deny_all() -> Self {
+        KeyAuthorizationRule {
+            identity: None,
+            keys: HashSet::new(),
+            actions: 0,
+        }
+    }
+
+    fn parse(value: &Yaml) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = value else {
+            return Err(anyhow!(
+                "yaml value type for key authorization rule should be 'map'"
+            ));
+        };
+
+        let mut rule = KeyAuthorizationRule {
+            identity: None,
+            keys: HashSet::new(),
+            actions: ACTION_ALL,
+        };
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "name" | "identity" => {
+                let name = g3_yaml::value::as_string(v)?;
+                rule.identity = Some(ClientIdentity::Name(name));
+                Ok(())
+            }
+            "spki_sha256" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let hash = hex::decode(s).map_err(|e| anyhow!("invalid hex string: {e}"))?;
+                if hash.len() != 32 {
+                    return Err(anyhow!("invalid sha256 hash length {}", hash.len()));
+                }
+                rule.identity = Some(ClientIdentity::SpkiSha256(hash));
+                Ok(())
+            }
+            "keys" | "ski" => {
+                let keys = g3_yaml::value::as_list(v, |v| {
+                    let s = g3_yaml::value::as_string(v)?;
+                    hex::decode(s).map_err(|e| anyhow!("invalid hex string: {e}"))
+                })
+                .context(format!("invalid SKI list value for key {k}"))?;
+                rule.keys = keys.into_iter().collect();
+                Ok(())
+            }
+            "actions" | "operations" => {
+                let bits = g3_yaml::value::as_list(v, parse_action_bit)
+                    .context(format!("invalid keyless action list value for key {k}"))?;
+                rule.actions = bits.into_iter().fold(0, |acc, b| acc | b);
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+
+        if rule.identity.is_none() {
+            return Err(anyhow!("no client identity set"));
+        }
+        Ok(rule)
+    }
+
+    pub(crate) fn check(&self, req: &KeylessRequest) -> Result<(), KeylessErrorResponse> {
+        if self.actions & action_bit(&req.action) == 0 {
+            return Err(KeylessErrorResponse::new(req.id)
+                .unexpected_op_code()
+                .unauthorized());
+        }
+        if matches!(req.action, KeylessAction::Ping) {
+            return Ok(());
+        }
+        if !self.keys.is_empty() && !self.keys.contains(&req.ski) {
+            return Err(KeylessErrorResponse::new(req.id)
+                .key_not_found()
+                .unauthorized());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct KeyServerAuthorization {
+    rules: Vec<Arc<KeyAuthorizationRule>>,
+}
+
+impl KeyServerAuthorization {
+    pub(super) fn parse(value: &Yaml) -> anyhow::Result<Self> {
+        let rules =
+            g3_yaml::value::as_list(value, |v| KeyAuthorizationRule::parse(v).map(Arc::new))?;
+        Ok(KeyServerAuthorization { rules })
+    }
+
+    pub(crate) fn find_rule(&self, client_cert: Option<&X509Ref>) -> Arc<KeyAuthorizationRule> {
+        if let Some(cert) = client_cert {
+            for rule in &self.rules {
+                if let Some(identity) = &rule.identity {
+                    if identity.matches(cert) {
+                        return rule.clone();
+                    }
+                }
+            }
+        }
+        Arc::new(KeyAuthorizationRule::deny_all())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::pkey::{PKey, Private};
+    use openssl::rsa::Padding;
+    use openssl::x509::extension::SubjectAlternativeName;
+    use openssl::x509::{X509NameBuilder, X509};
+    use yaml_rust::YamlLoader;
+
+    use crate::protocol::KeylessResponseErrorCode;
+
+    fn build_cert(cn: &str, dns_names: &[&str]) -> (X509, PKey<Private>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        if !dns_names.is_empty() {
+            let mut san = SubjectAlternativeName::new();
+            for n in dns_names {
+                san.dns(n);
+            }
+            let san = san.build(&builder.x509v3_context(None, None)).unwrap();
+            builder.append_extension(san).unwrap();
+        }
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        (builder.build(), pkey)
+    }
+
+    fn spki_sha256(pkey: &PKey<Private>) -> Vec<u8> {
+        let der = pkey.public_key_to_der().unwrap();
+        openssl::hash::hash(MessageDigest::sha256(), &der)
+            .unwrap()
+            .to_vec()
+    }
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().remove(0)
+    }
+
+    fn build_request(id: u32, action: KeylessAction, ski: &[u8]) -> KeylessRequest {
+        KeylessRequest {
+            id,
+            opcode: 0,
+            action,
+            ski: ski.to_vec(),
+            payload: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn identity_name_san() {
+        let (cert, _) = build_cert("client.example.net", &["a.example.net", "B.example.net"]);
+
+        assert!(ClientIdentity::Name("a.example.net".to_string()).matches(&cert));
+        assert!(ClientIdentity::Name("b.example.net".to_string()).matches(&cert));
+        assert!(!ClientIdentity::Name("c.example.net".to_string()).matches(&cert));
+        // the subject CN should not be checked if there are SAN dns names
+        assert!(!ClientIdentity::Name("client.example.net".to_string()).matches(&cert));
+    }
+
+    #[test]
+    fn identity_name_cn() {
+        let (cert, _) = build_cert("Client.Example.Net", &[]);
+
+        assert!(ClientIdentity::Name("client.example.net".to_string()).matches(&cert));
+        assert!(!ClientIdentity::Name("example.net".to_string()).matches(&cert));
+    }
+
+    #[test]
+    fn identity_name_wildcard() {
+        let (cert, _) = build_cert("client.example.net", &["*.example.net"]);
+
+        // wildcard names are compared literally
+        assert!(ClientIdentity::Name("*.example.net".to_string()).matches(&cert));
+        assert!(!ClientIdentity::Name("www.example.net".to_string()).matches(&cert));
+        assert!(!ClientIdentity::Name("example.net".to_string()).matches(&cert));
+    }
+
+    #[test]
+    fn identity_spki() {
+        let (cert1, pkey1) = build_cert("client1.example.net", &[]);
+        let (cert2, _) = build_cert("client2.example.net", &[]);
+
+        let identity = ClientIdentity::SpkiSha256(spki_sha256(&pkey1));
+        assert!(identity.matches(&cert1));
+        assert!(!identity.matches(&cert2));
+    }
+
+    #[test]
+    fn rule_check() {
+        let v = load_yaml(
+            r#"
+            name: client.example.net
+            keys:
+              - "0102030405"
+            actions:
+              - ping
+              - ecdsa_sign
+            "#,
+        );
+        let rule = KeyAuthorizationRule::parse(&v).unwrap();
+        let ski = [0x01, 0x02, 0x03, 0x04, 0x05];
+        let other_ski = [0x05, 0x04, 0x03, 0x02, 0x01];
+
+        let req = build_request(1, KeylessAction::EcdsaSign(Nid::SHA256), &ski);
+        assert!(rule.check(&req).is_ok());
+
+        let req = build_request(2, KeylessAction::Ping, &other_ski);
+        assert!(rule.check(&req).is_ok());
+
+        let req = build_request(3, KeylessAction::EcdsaSign(Nid::SHA256), &other_ski);
+        let rsp = rule.check(&req).unwrap_err();
+        assert!(rsp.unauthorized);
+        assert!(matches!(
+            rsp.error_code(),
+            KeylessResponseErrorCode::KeyNotFound
+        ));
+
+        let req = build_request(4, KeylessAction::RsaDecrypt(Padding::NONE), &ski);
+        let rsp = rule.check(&req).unwrap_err();
+        assert!(rsp.unauthorized);
+        assert!(matches!(
+            rsp.error_code(),
+            KeylessResponseErrorCode::UnexpectedOpCode
+        ));
+    }
+
+    #[test]
+    fn rule_any_key() {
+        let v = load_yaml("name: client.example.net");
+        let rule = KeyAuthorizationRule::parse(&v).unwrap();
+
+        // all keys and all actions are allowed by default
+        let req = build_request(1, KeylessAction::RsaSign(Nid::SHA256), &[0x01; 20]);
+        assert!(rule.check(&req).is_ok());
+        let req = build_request(2, KeylessAction::Ed25519Sign, &[0x02; 20]);
+        assert!(rule.check(&req).is_ok());
+
+        let req = build_request(3, KeylessAction::NotSet, &[0x01; 20]);
+        assert!(rule.check(&req).is_err());
+    }
+
+    #[test]
+    fn rule_deny_all() {
+        let rule = KeyAuthorizationRule::deny_all();
+
+        let req = build_request(1, KeylessAction::Ping, &[]);
+        assert!(rule.check(&req).unwrap_err().unauthorized);
+        let req = build_request(2, KeylessAction::EcdsaSign(Nid::SHA256), &[0x01; 20]);
+        assert!(rule.check(&req).unwrap_err().unauthorized);
+    }
+
+    #[test]
+    fn rule_parse_error() {
+        let v = load_yaml("keys: [\"0102\"]");
+        assert!(KeyAuthorizationRule::parse(&v).is_err());
+
+        let v = load_yaml("spki_sha256: \"0102\"");
+        assert!(KeyAuthorizationRule::parse(&v).is_err());
+
+        let v = load_yaml("name: client.example.net\nactions: [sign]");
+        assert!(KeyAuthorizationRule::parse(&v).is_err());
+    }
+
+    #[test]
+    fn find_rule() {
+        let (cert1, pkey1) = build_cert("client1.example.net", &[]);
+        let (cert2, _) = build_cert("client2.example.net", &["client2.example.net"]);
+        let (cert3, _) = build_cert("client3.example.net", &[]);
+
+        let v = load_yaml(&format!(
+            r#"
+            - spki_sha256: "{}"
+              actions: [ping]
+            - name: client2.example.net
+              keys: ["0102"]
+            "#,
+            hex::encode(spki_sha256(&pkey1))
+        ));
+        let auth = KeyServerAuthorization::parse(&v).unwrap();
+
+        let ping = build_request(1, KeylessAction::Ping, &[]);
+        let sign = build_request(2, KeylessAction::EcdsaSign(Nid::SHA256), &[0x01, 0x02]);
+
+        let rule = auth.find_rule(Some(&cert1));
+        assert!(rule.check(&ping).is_ok());
+        assert!(rule.check(&sign).is_err());
+
+        let rule = auth.find_rule(Some(&cert2));
+        assert!(rule.check(&ping).is_ok());
+        assert!(rule.check(&sign).is_ok());
+
+        let rule = auth.find_rule(Some(&cert3));
+        assert_eq!(*rule, KeyAuthorizationRule::deny_all());
+
+        let rule = auth.find_rule(None);
+        assert_eq!(*rule, KeyAuthorizationRule::deny_all());
+    }
+}
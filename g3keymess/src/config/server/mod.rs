@@ -24,25 +24,33 @@ use slog::Logger;
 use yaml_rust::{yaml, Yaml};
 
 use g3_histogram::HistogramMetricsConfig;
+use g3_types::limit::RateLimitQuotaConfig;
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
-use g3_types::net::TcpListenConfig;
+use g3_types::net::{OpensslServerConfig, TcpListenConfig};
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 mod registry;
 pub(crate) use registry::{clear, get_all};
 
+mod auth;
+pub(crate) use auth::{KeyAuthorizationRule, KeyServerAuthorization};
+
 #[derive(Clone)]
 pub(crate) struct KeyServerConfig {
     name: MetricsName,
-    #[allow(unused)]
     position: Option<YamlDocPosition>,
     pub(crate) shared_logger: Option<AsciiString>,
     pub(crate) listen: TcpListenConfig,
+    pub(crate) tls_server: Option<OpensslServerConfig>,
+    pub(crate) client_authorization: Option<KeyServerAuthorization>,
     #[cfg(feature = "openssl-async-job")]
     pub(crate) multiplex_queue_depth: usize,
     pub(crate) request_read_timeout: Duration,
     pub(crate) duration_stats: HistogramMetricsConfig,
-    #[cfg(feature = "openssl-async-job")]
+    pub(crate) key_stats: bool,
+    pub(crate) key_request_rate_limit: Option<RateLimitQuotaConfig>,
+    pub(crate) client_request_rate_limit: Option<RateLimitQuotaConfig>,
+    #[cfg(any(feature = "openssl-async-job", feature = "pkcs11"))]
     pub(crate) async_op_timeout: Duration,
     pub(crate) concurrency_limit: usize,
     pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
@@ -55,11 +63,16 @@ impl KeyServerConfig {
             position,
             shared_logger: None,
             listen: TcpListenConfig::default(),
+            tls_server: None,
+            client_authorization: None,
             #[cfg(feature = "openssl-async-job")]
             multiplex_queue_depth: 0,
             request_read_timeout: Duration::from_millis(100),
             duration_stats: HistogramMetricsConfig::default(),
-            #[cfg(feature = "openssl-async-job")]
+            key_stats: false,
+            key_request_rate_limit: None,
+            client_request_rate_limit: None,
+            #[cfg(any(feature = "openssl-async-job", feature = "pkcs11"))]
             async_op_timeout: Duration::from_secs(1),
             concurrency_limit: 0,
             extra_metrics_tags: None,
@@ -85,6 +98,11 @@ impl KeyServerConfig {
             return Err(anyhow!("name is not set"));
         }
         self.listen.check().context("invalid listen address")?;
+        if self.client_authorization.is_some() && self.tls_server.is_none() {
+            return Err(anyhow!(
+                "client authorization can only be used with tls server config"
+            ));
+        }
         Ok(())
     }
 
@@ -110,6 +128,25 @@ impl KeyServerConfig {
                     .context(format!("invalid tcp listen config value for key {k}"))?;
                 Ok(())
             }
+            "tls_server" | "tls" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let builder =
+                    g3_yaml::value::as_openssl_tls_server_config_builder(v, Some(lookup_dir))
+                        .context(format!(
+                            "invalid openssl tls server config value for key {k}"
+                        ))?;
+                let tls_server = builder
+                    .build()
+                    .context("failed to build openssl tls server config")?;
+                self.tls_server = Some(tls_server);
+                Ok(())
+            }
+            "client_authorization" | "authorization" => {
+                let auth = KeyServerAuthorization::parse(v)
+                    .context(format!("invalid client authorization value for key {k}"))?;
+                self.client_authorization = Some(auth);
+                Ok(())
+            }
             #[cfg(feature = "openssl-async-job")]
             "multiplex_queue_depth" => {
                 self.multiplex_queue_depth = g3_yaml::value::as_usize(v)?;
@@ -125,7 +162,23 @@ impl KeyServerConfig {
                 )?;
                 Ok(())
             }
-            #[cfg(feature = "openssl-async-job")]
+            "key_stats" | "key_metrics" => {
+                self.key_stats = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "key_request_rate_limit" => {
+                let quota = g3_yaml::value::as_rate_limit_quota(v)
+                    .context(format!("invalid request quota value for key {k}"))?;
+                self.key_request_rate_limit = Some(quota);
+                Ok(())
+            }
+            "client_request_rate_limit" => {
+                let quota = g3_yaml::value::as_rate_limit_quota(v)
+                    .context(format!("invalid request quota value for key {k}"))?;
+                self.client_request_rate_limit = Some(quota);
+                Ok(())
+            }
+            #[cfg(any(feature = "openssl-async-job", feature = "pkcs11"))]
             "async_op_timeout" => {
                 self.async_op_timeout = g3_yaml::humanize::as_duration(v)?;
                 Ok(())
@@ -24,6 +24,8 @@ use g3_types::metrics::MetricsName;
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 mod local;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
 mod redis;
 
 mod registry;
@@ -45,6 +47,8 @@ macro_rules! impl_transparent0 {
             match self {
                 AnyKeyStoreConfig::Local(s) => s.$f(),
                 AnyKeyStoreConfig::Redis(s) => s.$f(),
+                #[cfg(feature = "pkcs11")]
+                AnyKeyStoreConfig::Pkcs11(s) => s.$f(),
             }
         }
     };
@@ -56,6 +60,8 @@ macro_rules! impl_async_transparent0 {
             match self {
                 AnyKeyStoreConfig::Local(s) => s.$f().await,
                 AnyKeyStoreConfig::Redis(s) => s.$f().await,
+                #[cfg(feature = "pkcs11")]
+                AnyKeyStoreConfig::Pkcs11(s) => s.$f().await,
             }
         }
     };
@@ -65,6 +71,8 @@ macro_rules! impl_async_transparent0 {
 pub enum AnyKeyStoreConfig {
     Local(local::LocalKeyStoreConfig),
     Redis(redis::RedisKeyStoreConfig),
+    #[cfg(feature = "pkcs11")]
+    Pkcs11(pkcs11::Pkcs11KeyStoreConfig),
 }
 
 impl AnyKeyStoreConfig {
@@ -106,6 +114,11 @@ fn load_store(
             let config = redis::RedisKeyStoreConfig::parse(map, position)?;
             Ok(AnyKeyStoreConfig::Redis(config))
         }
+        #[cfg(feature = "pkcs11")]
+        "pkcs11" => {
+            let config = pkcs11::Pkcs11KeyStoreConfig::parse(map, position)?;
+            Ok(AnyKeyStoreConfig::Pkcs11(config))
+        }
         _ => Err(anyhow!("unsupported key store type {store_type}")),
     }
 }
@@ -0,0 +1,248 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use log::warn;
+use yaml_rust::{yaml, Yaml};
+
+use g3_types::metrics::MetricsName;
+use g3_yaml::YamlDocPosition;
+
+use super::KeyStoreConfig;
+use crate::store::{Pkcs11SlotSelector, Pkcs11Token};
+
+#[derive(Clone, PartialEq)]
+pub struct Pkcs11KeyStoreConfig {
+    name: MetricsName,
+    position: Option<YamlDocPosition>,
+    module_path: PathBuf,
+    slot_id: Option<u64>,
+    token_label: Option<String>,
+    pin: Option<String>,
+    labels: Vec<String>,
+    ids: Vec<Vec<u8>>,
+}
+
+impl fmt::Debug for Pkcs11KeyStoreConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // never print the user pin
+        f.debug_struct("Pkcs11KeyStoreConfig")
+            .field("name", &self.name)
+            .field("position", &self.position)
+            .field("module_path", &self.module_path)
+            .field("slot_id", &self.slot_id)
+            .field("token_label", &self.token_label)
+            .field("pin", &self.pin.as_ref().map(|_| "<redacted>"))
+            .field("labels", &self.labels)
+            .field("ids", &self.ids)
+            .finish()
+    }
+}
+
+impl Pkcs11KeyStoreConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        Pkcs11KeyStoreConfig {
+            name: MetricsName::default(),
+            position,
+            module_path: PathBuf::new(),
+            slot_id: None,
+            token_label: None,
+            pin: None,
+            labels: Vec::new(),
+            ids: Vec::new(),
+        }
+    }
+
+    pub(super) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut server = Pkcs11KeyStoreConfig::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| server.set(k, v))?;
+
+        server.check()?;
+        Ok(server)
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.module_path.as_os_str().is_empty() {
+            return Err(anyhow!("module path is not set"));
+        }
+        if self.slot_id.is_none() && self.token_label.is_none() {
+            return Err(anyhow!("neither slot id nor token label is set"));
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_STORE_TYPE => Ok(()),
+            "name" => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "module" | "module_path" | "library" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                self.module_path = g3_yaml::value::as_file_path(v, lookup_dir, false)?;
+                Ok(())
+            }
+            "slot" | "slot_id" => {
+                let id = g3_yaml::value::as_u64(v)?;
+                self.slot_id = Some(id);
+                Ok(())
+            }
+            "token" | "token_label" => {
+                let label = g3_yaml::value::as_string(v)?;
+                self.token_label = Some(label);
+                Ok(())
+            }
+            "pin" | "user_pin" => {
+                let pin = g3_yaml::value::as_string(v)?;
+                self.pin = Some(pin);
+                Ok(())
+            }
+            "label" | "labels" => {
+                self.labels = g3_yaml::value::as_list(v, g3_yaml::value::as_string)
+                    .context(format!("invalid string list value for key {k}"))?;
+                Ok(())
+            }
+            "id" | "ids" => {
+                self.ids = g3_yaml::value::as_list(v, |v| {
+                    let s = g3_yaml::value::as_string(v)?;
+                    hex::decode(s).map_err(|e| anyhow!("invalid hex string: {e}"))
+                })
+                .context(format!("invalid hex string list value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+}
+
+impl KeyStoreConfig for Pkcs11KeyStoreConfig {
+    #[inline]
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    async fn load_keys(&self) -> anyhow::Result<()> {
+        let selector = if let Some(id) = self.slot_id {
+            Pkcs11SlotSelector::Id(id)
+        } else if let Some(label) = &self.token_label {
+            Pkcs11SlotSelector::TokenLabel(label.clone())
+        } else {
+            unreachable!()
+        };
+
+        let config = self.clone();
+        let keys = tokio::task::spawn_blocking(move || {
+            let token = Pkcs11Token::open(&config.module_path, &selector, config.pin.as_deref())?;
+            Arc::new(token).find_keys(&config.labels, &config.ids)
+        })
+        .await
+        .map_err(|e| anyhow!("failed to join pkcs11 load task: {e}"))?
+        .context(format!(
+            "failed to load keys from pkcs11 module {}",
+            self.module_path.display()
+        ))?;
+
+        if keys.is_empty() {
+            warn!("no private keys found in pkcs11 key store {}", self.name);
+        }
+        for (ski, key) in keys {
+            crate::store::add_global_pkcs11(ski, key);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debug_redact_pin() {
+        let mut config = Pkcs11KeyStoreConfig::new(None);
+        config.pin = Some("secret-1234".to_string());
+        let s = format!("{config:?}");
+        assert!(!s.contains("secret-1234"));
+        assert!(s.contains("<redacted>"));
+
+        config.pin = None;
+        let s = format!("{config:?}");
+        assert!(s.contains("pin: None"));
+    }
+
+    fn parse_yaml(s: &str) -> anyhow::Result<Pkcs11KeyStoreConfig> {
+        let dir = std::env::temp_dir().join(format!("g3keymess-pkcs11-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("softhsm2.so"), b"").unwrap();
+        let position = YamlDocPosition {
+            path: dir.join("main.yaml"),
+            index: 0,
+        };
+
+        let docs = yaml_rust::YamlLoader::load_from_str(s).unwrap();
+        let Yaml::Hash(map) = &docs[0] else {
+            unreachable!()
+        };
+        Pkcs11KeyStoreConfig::parse(map, Some(position))
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse_yaml(
+            "name: hsm\nmodule: softhsm2.so\ntoken_label: test\npin: \"1234\"\nlabels: [a, b]\nids: [\"0a1B\"]",
+        )
+        .unwrap();
+        assert_eq!(config.name.as_str(), "hsm");
+        assert!(config.module_path.ends_with("softhsm2.so"));
+        assert!(config.module_path.is_absolute());
+        assert_eq!(config.slot_id, None);
+        assert_eq!(config.token_label.as_deref(), Some("test"));
+        assert_eq!(config.pin.as_deref(), Some("1234"));
+        assert_eq!(config.labels, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(config.ids, vec![vec![0x0a, 0x1b]]);
+
+        let config = parse_yaml("name: hsm\nlibrary: softhsm2.so\nslot: 3").unwrap();
+        assert_eq!(config.slot_id, Some(3));
+        assert!(config.token_label.is_none());
+        assert!(config.pin.is_none());
+    }
+
+    #[test]
+    fn parse_err() {
+        // no slot selector
+        assert!(parse_yaml("name: hsm\nmodule: softhsm2.so").is_err());
+        // no name
+        assert!(parse_yaml("module: softhsm2.so\nslot: 0").is_err());
+        // no module
+        assert!(parse_yaml("name: hsm\nslot: 0").is_err());
+        // module not existed
+        assert!(parse_yaml("name: hsm\nmodule: not-existed.so\nslot: 0").is_err());
+        // invalid id
+        assert!(parse_yaml("name: hsm\nmodule: softhsm2.so\nslot: 0\nid: xyz").is_err());
+        assert!(parse_yaml("name: hsm\nmodule: softhsm2.so\nslot: 0\nunknown: 1").is_err());
+    }
+}
@@ -39,9 +39,14 @@ pub(crate) async fn list_keys() -> anyhow::Result<Vec<Vec<u8>>> {
 
 pub(crate) async fn check_key(ski: Vec<u8>) -> anyhow::Result<()> {
     run_in_main_thread(async move {
-        crate::store::get_by_ski(&ski)
-            .map(|_| ())
-            .ok_or_else(|| anyhow!("key not found"))
+        if crate::store::get_by_ski(&ski).is_some() {
+            return Ok(());
+        }
+        #[cfg(feature = "pkcs11")]
+        if crate::store::get_pkcs11_by_ski(&ski).is_some() {
+            return Ok(());
+        }
+        Err(anyhow!("key not found"))
     })
     .await
 }
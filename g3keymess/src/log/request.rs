@@ -55,6 +55,8 @@ impl<'a> RequestErrorLogContext<'a> {
             slog_info!(logger, "{}", e;
                 "task_id" => LtUuid(self.task_id),
                 "msg_id" => r.id,
+                "unauthorized" => r.unauthorized,
+                "throttled" => r.throttled,
             )
         }
     }
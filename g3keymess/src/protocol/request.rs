@@ -15,6 +15,8 @@
  */
 
 use std::io;
+#[cfg(feature = "pkcs11")]
+use std::sync::Arc;
 
 use openssl::encrypt::Decrypter;
 use openssl::hash::MessageDigest;
@@ -30,6 +32,8 @@ use tokio::io::{AsyncRead, AsyncReadExt};
 use g3_types::net::{T1L2BVParse, TlvParse};
 
 use super::{KeylessDataResponse, KeylessErrorResponse, KeylessPongResponse};
+#[cfg(feature = "pkcs11")]
+use crate::store::Pkcs11PrivateKey;
 
 #[derive(Clone, Copy)]
 pub(crate) enum KeylessAction {
@@ -291,6 +295,42 @@ impl KeylessRequest {
         Err(KeylessErrorResponse::new(self.id).key_not_found())
     }
 
+    #[cfg(feature = "pkcs11")]
+    pub(crate) fn find_pkcs11_key(
+        &self,
+    ) -> Result<Option<Arc<Pkcs11PrivateKey>>, KeylessErrorResponse> {
+        if !self.ski.is_empty() {
+            if let Some(k) = crate::store::get_pkcs11_by_ski(&self.ski) {
+                self.check_payload_for_key_size(k.size())?;
+                return Ok(Some(k));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Process the request with the key in the PKCS#11 token, this will block the current thread.
+    #[cfg(feature = "pkcs11")]
+    pub(crate) fn process_by_pkcs11(
+        &self,
+        key: &Pkcs11PrivateKey,
+    ) -> Result<KeylessDataResponse, KeylessErrorResponse> {
+        let err_rsp = KeylessErrorResponse::new(self.id);
+        let r = match self.action {
+            KeylessAction::RsaDecrypt(p) => key.rsa_decrypt(p, &self.payload),
+            KeylessAction::RsaSign(h) => key.rsa_sign(h, &self.payload),
+            KeylessAction::RsaPssSign(h) => key.rsa_pss_sign(h, &self.payload),
+            KeylessAction::EcdsaSign(_) => key.ecdsa_sign(&self.payload),
+            KeylessAction::Ed25519Sign => key.ed25519_sign(&self.payload),
+            KeylessAction::NotSet | KeylessAction::Ping => return Err(err_rsp.unexpected_op_code()),
+        };
+        let data = r.map_err(|_| err_rsp.crypto_fail())?;
+
+        let mut data_rsp = KeylessDataResponse::new(self.id, data.len());
+        data_rsp.payload_data_mut().copy_from_slice(&data);
+        data_rsp.finalize_payload(data.len());
+        Ok(data_rsp)
+    }
+
     pub(crate) fn process(
         &self,
         key: &PKey<Private>,
@@ -121,6 +121,8 @@ pub(crate) enum KeylessResponseErrorCode {
 pub(crate) struct KeylessErrorResponse {
     pub(crate) id: u32,
     pub(crate) buf: [u8; BUF_PREFIX_LEN + 1],
+    pub(crate) unauthorized: bool,
+    pub(crate) throttled: bool,
 }
 
 impl KeylessErrorResponse {
@@ -135,6 +137,8 @@ impl KeylessErrorResponse {
                 0x11, 0x00, 0x01, 0xFF, // OpCode
                 0x12, 0x00, 0x01, 0x00, // Payload
             ],
+            unauthorized: false,
+            throttled: false,
         }
     }
 
@@ -184,6 +188,25 @@ impl KeylessErrorResponse {
     pub(crate) fn format_error(self) -> Self {
         self.set_error_code(KeylessResponseErrorCode::FormatError)
     }
+
+    #[inline]
+    pub(crate) fn internal_error(self) -> Self {
+        self.set_error_code(KeylessResponseErrorCode::InternalError)
+    }
+
+    /// mark this error as caused by client authorization
+    #[inline]
+    pub(crate) fn unauthorized(mut self) -> Self {
+        self.unauthorized = true;
+        self
+    }
+
+    /// mark this error as caused by request rate limit
+    #[inline]
+    pub(crate) fn throttled(mut self) -> Self {
+        self.throttled = true;
+        self
+    }
 }
 
 pub(crate) enum KeylessResponse {
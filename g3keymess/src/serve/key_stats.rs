@@ -0,0 +1,224 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use ahash::AHashMap;
+use arc_swap::ArcSwapOption;
+
+use g3_histogram::{HistogramMetricsConfig, HistogramRecorder, HistogramStats};
+use g3_types::metrics::{MetricsName, StaticMetricsTags};
+use g3_types::stats::StatId;
+
+use super::{KeyServerRequestSnapshot, KeyServerRequestStats, KeyServerStats};
+use crate::protocol::KeylessAction;
+
+pub(crate) struct KeyUsageStats {
+    server: MetricsName,
+    id: StatId,
+    ski: String,
+    extra_metrics_tags: Arc<ArcSwapOption<StaticMetricsTags>>,
+
+    pub(crate) rsa_decrypt: Arc<KeyServerRequestStats>,
+    pub(crate) rsa_sign: Arc<KeyServerRequestStats>,
+    pub(crate) rsa_pss_sign: Arc<KeyServerRequestStats>,
+    pub(crate) ecdsa_sign: Arc<KeyServerRequestStats>,
+    pub(crate) ed25519_sign: Arc<KeyServerRequestStats>,
+}
+
+#[derive(Default)]
+pub(crate) struct KeyUsageSnapshot {
+    pub(crate) rsa_decrypt: KeyServerRequestSnapshot,
+    pub(crate) rsa_sign: KeyServerRequestSnapshot,
+    pub(crate) rsa_pss_sign: KeyServerRequestSnapshot,
+    pub(crate) ecdsa_sign: KeyServerRequestSnapshot,
+    pub(crate) ed25519_sign: KeyServerRequestSnapshot,
+}
+
+pub(crate) struct KeyUsageDurationStats {
+    server: MetricsName,
+    id: StatId,
+    ski: String,
+    extra_metrics_tags: Arc<ArcSwapOption<StaticMetricsTags>>,
+
+    pub(crate) rsa_decrypt: Arc<HistogramStats>,
+    pub(crate) rsa_sign: Arc<HistogramStats>,
+    pub(crate) rsa_pss_sign: Arc<HistogramStats>,
+    pub(crate) ecdsa_sign: Arc<HistogramStats>,
+    pub(crate) ed25519_sign: Arc<HistogramStats>,
+}
+
+macro_rules! impl_key_usage_common {
+    ($t:ty) => {
+        impl $t {
+            #[inline]
+            pub(crate) fn server(&self) -> &MetricsName {
+                &self.server
+            }
+
+            #[inline]
+            pub(crate) fn stat_id(&self) -> StatId {
+                self.id
+            }
+
+            #[inline]
+            pub(crate) fn ski(&self) -> &str {
+                &self.ski
+            }
+
+            #[inline]
+            pub(crate) fn load_extra_tags(&self) -> Option<Arc<StaticMetricsTags>> {
+                self.extra_metrics_tags.load_full()
+            }
+        }
+    };
+}
+
+impl_key_usage_common!(KeyUsageStats);
+impl_key_usage_common!(KeyUsageDurationStats);
+
+/// The usage stats of a single key in a single server.
+pub(crate) struct KeyUsage {
+    pub(crate) stats: Arc<KeyUsageStats>,
+    pub(crate) duration_stats: Arc<KeyUsageDurationStats>,
+
+    rsa_decrypt: Arc<HistogramRecorder<u64>>,
+    rsa_sign: Arc<HistogramRecorder<u64>>,
+    rsa_pss_sign: Arc<HistogramRecorder<u64>>,
+    ecdsa_sign: Arc<HistogramRecorder<u64>>,
+    ed25519_sign: Arc<HistogramRecorder<u64>>,
+}
+
+impl KeyUsage {
+    fn new(
+        server_stats: &KeyServerStats,
+        ski: &[u8],
+        duration_config: &HistogramMetricsConfig,
+    ) -> Self {
+        let ski = hex::encode(ski);
+        let (rsa_decrypt_r, rsa_decrypt_s) = duration_config.build_spawned(None);
+        let (rsa_sign_r, rsa_sign_s) = duration_config.build_spawned(None);
+        let (rsa_pss_sign_r, rsa_pss_sign_s) = duration_config.build_spawned(None);
+        let (ecdsa_sign_r, ecdsa_sign_s) = duration_config.build_spawned(None);
+        let (ed25519_sign_r, ed25519_sign_s) = duration_config.build_spawned(None);
+
+        let stats = KeyUsageStats {
+            server: server_stats.name().clone(),
+            id: StatId::new(),
+            ski: ski.clone(),
+            extra_metrics_tags: server_stats.shared_extra_tags(),
+            rsa_decrypt: Arc::new(KeyServerRequestStats::default()),
+            rsa_sign: Arc::new(KeyServerRequestStats::default()),
+            rsa_pss_sign: Arc::new(KeyServerRequestStats::default()),
+            ecdsa_sign: Arc::new(KeyServerRequestStats::default()),
+            ed25519_sign: Arc::new(KeyServerRequestStats::default()),
+        };
+        let duration_stats = KeyUsageDurationStats {
+            server: server_stats.name().clone(),
+            id: StatId::new(),
+            ski,
+            extra_metrics_tags: server_stats.shared_extra_tags(),
+            rsa_decrypt: rsa_decrypt_s,
+            rsa_sign: rsa_sign_s,
+            rsa_pss_sign: rsa_pss_sign_s,
+            ecdsa_sign: ecdsa_sign_s,
+            ed25519_sign: ed25519_sign_s,
+        };
+
+        KeyUsage {
+            stats: Arc::new(stats),
+            duration_stats: Arc::new(duration_stats),
+            rsa_decrypt: Arc::new(rsa_decrypt_r),
+            rsa_sign: Arc::new(rsa_sign_r),
+            rsa_pss_sign: Arc::new(rsa_pss_sign_r),
+            ecdsa_sign: Arc::new(ecdsa_sign_r),
+            ed25519_sign: Arc::new(ed25519_sign_r),
+        }
+    }
+
+    pub(crate) fn select(
+        &self,
+        action: &KeylessAction,
+    ) -> Option<(Arc<KeyServerRequestStats>, Arc<HistogramRecorder<u64>>)> {
+        match action {
+            KeylessAction::RsaDecrypt(_) => {
+                Some((self.stats.rsa_decrypt.clone(), self.rsa_decrypt.clone()))
+            }
+            KeylessAction::RsaSign(_) => Some((self.stats.rsa_sign.clone(), self.rsa_sign.clone())),
+            KeylessAction::RsaPssSign(_) => {
+                Some((self.stats.rsa_pss_sign.clone(), self.rsa_pss_sign.clone()))
+            }
+            KeylessAction::EcdsaSign(_) => {
+                Some((self.stats.ecdsa_sign.clone(), self.ecdsa_sign.clone()))
+            }
+            KeylessAction::Ed25519Sign => {
+                Some((self.stats.ed25519_sign.clone(), self.ed25519_sign.clone()))
+            }
+            KeylessAction::NotSet | KeylessAction::Ping => None,
+        }
+    }
+}
+
+/// All key usage stats of a single server, indexed by SKI.
+///
+/// Only keys that have been found in the key store will be added.
+pub(crate) struct KeyUsageRegistry {
+    server_stats: Arc<KeyServerStats>,
+    duration_config: HistogramMetricsConfig,
+    keys: Mutex<AHashMap<Vec<u8>, Arc<KeyUsage>>>,
+}
+
+impl KeyUsageRegistry {
+    pub(crate) fn new(
+        server_stats: Arc<KeyServerStats>,
+        duration_config: &HistogramMetricsConfig,
+    ) -> Self {
+        KeyUsageRegistry {
+            server_stats,
+            duration_config: duration_config.clone(),
+            keys: Mutex::new(AHashMap::new()),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn duration_config(&self) -> &HistogramMetricsConfig {
+        &self.duration_config
+    }
+
+    pub(crate) fn get(&self, ski: &[u8]) -> Arc<KeyUsage> {
+        let mut map = self.keys.lock().unwrap();
+        if let Some(usage) = map.get(ski) {
+            return usage.clone();
+        }
+        let usage = Arc::new(KeyUsage::new(
+            &self.server_stats,
+            ski,
+            &self.duration_config,
+        ));
+        map.insert(ski.to_vec(), usage.clone());
+        usage
+    }
+
+    pub(crate) fn foreach<F>(&self, mut f: F)
+    where
+        F: FnMut(&Arc<KeyUsage>),
+    {
+        let map = self.keys.lock().unwrap();
+        for usage in map.values() {
+            f(usage);
+        }
+    }
+}
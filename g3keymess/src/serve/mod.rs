@@ -20,11 +20,17 @@ pub(crate) use stats::{
     KeyServerRequestStats, KeyServerSnapshot, KeyServerStats,
 };
 
+mod key_stats;
+pub(crate) use key_stats::{
+    KeyUsage, KeyUsageDurationStats, KeyUsageRegistry, KeyUsageSnapshot, KeyUsageStats,
+};
+
 mod error;
 pub(crate) use error::ServerTaskError;
 
 mod server;
 pub(crate) use server::KeyServer;
+use server::{ClientRateLimiter, KeyRateLimiter, KeyedRateLimiter};
 
 mod task;
 use task::{KeylessTask, KeylessTaskContext};
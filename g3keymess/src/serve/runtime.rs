@@ -16,6 +16,7 @@
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use log::{info, warn};
 use tokio::net::TcpStream;
@@ -28,6 +29,8 @@ use g3_types::net::TcpListenConfig;
 
 use super::{KeyServer, ServerReloadCommand};
 
+const RATE_LIMIT_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
 pub(super) struct KeyServerRuntime {
     server: Arc<KeyServer>,
     listen_stats: Arc<ListenStats>,
@@ -62,6 +65,9 @@ impl KeyServerRuntime {
     ) {
         use broadcast::error::RecvError;
 
+        let mut prune_interval = tokio::time::interval(RATE_LIMIT_PRUNE_INTERVAL);
+        prune_interval.tick().await;
+
         loop {
             tokio::select! {
                 biased;
@@ -87,6 +93,9 @@ impl KeyServerRuntime {
                         break;
                     }
                 }
+                _ = prune_interval.tick() => {
+                    self.server.prune_rate_limit();
+                }
                 result = listener.accept() => {
                     if listener.accept_current_available(result, |result| {
                         match result {
@@ -14,23 +14,91 @@
  * limitations under the License.
  */
 
-use std::net::SocketAddr;
+use std::hash::Hash;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
 use arc_swap::ArcSwap;
+use governor::clock::DefaultClock;
+use governor::state::keyed::DefaultKeyedStateStore;
+use governor::RateLimiter;
+use log::debug;
+use openssl::ssl::Ssl;
 use slog::Logger;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 use tokio::sync::{broadcast, Semaphore};
 
 use g3_daemon::listen::ListenStats;
 use g3_daemon::server::ServerQuitPolicy;
+use g3_openssl::SslAcceptor;
 use g3_types::metrics::{MetricsName, MetricsTagName, MetricsTagValue, StaticMetricsTags};
+use g3_types::net::OpensslServerConfig;
 
 use super::{
     KeyServerDurationRecorder, KeyServerDurationStats, KeyServerRuntime, KeyServerStats,
-    KeylessTask, KeylessTaskContext, ServerReloadCommand,
+    KeyUsageRegistry, KeylessTask, KeylessTaskContext, ServerReloadCommand,
 };
-use crate::config::server::KeyServerConfig;
+use crate::config::server::{KeyAuthorizationRule, KeyServerConfig};
+
+pub(crate) type KeyedRateLimiter<K> = RateLimiter<K, DefaultKeyedStateStore<K>, DefaultClock>;
+pub(crate) type KeyRateLimiter = KeyedRateLimiter<Vec<u8>>;
+pub(crate) type ClientRateLimiter = KeyedRateLimiter<IpAddr>;
+
+/// Remove the states that have been fully replenished, so the map won't grow without bound
+fn prune_rate_limiter<K: Clone + Hash + Eq>(limiter: &KeyedRateLimiter<K>) {
+    limiter.retain_recent();
+    limiter.shrink_to_fit();
+}
+
+/// The per key and per client state, which should be kept on reload if possible.
+struct KeyServerKeyedState {
+    key_usage: Option<Arc<KeyUsageRegistry>>,
+    key_rate_limit: Option<Arc<KeyRateLimiter>>,
+    client_rate_limit: Option<Arc<ClientRateLimiter>>,
+}
+
+impl KeyServerKeyedState {
+    fn new(config: &KeyServerConfig, server_stats: &Arc<KeyServerStats>) -> Self {
+        let key_usage = if config.key_stats {
+            Some(Arc::new(KeyUsageRegistry::new(
+                server_stats.clone(),
+                &config.duration_stats,
+            )))
+        } else {
+            None
+        };
+        let key_rate_limit = config
+            .key_request_rate_limit
+            .as_ref()
+            .map(|quota| Arc::new(RateLimiter::keyed(quota.get_inner())));
+        let client_rate_limit = config
+            .client_request_rate_limit
+            .as_ref()
+            .map(|quota| Arc::new(RateLimiter::keyed(quota.get_inner())));
+        KeyServerKeyedState {
+            key_usage,
+            key_rate_limit,
+            client_rate_limit,
+        }
+    }
+
+    fn reload(server: &KeyServer, config: &KeyServerConfig) -> Self {
+        let mut new = KeyServerKeyedState::new(config, &server.server_stats);
+        if let Some(old) = &server.key_usage {
+            if config.key_stats && old.duration_config() == &config.duration_stats {
+                new.key_usage = Some(old.clone());
+            }
+        }
+        if server.config.key_request_rate_limit == config.key_request_rate_limit {
+            new.key_rate_limit = server.key_rate_limit.clone();
+        }
+        if server.config.client_request_rate_limit == config.client_request_rate_limit {
+            new.client_rate_limit = server.client_rate_limit.clone();
+        }
+        new
+    }
+}
 
 pub(crate) struct KeyServer {
     config: Arc<KeyServerConfig>,
@@ -41,6 +109,9 @@ pub(crate) struct KeyServer {
     quit_policy: Arc<ServerQuitPolicy>,
     reload_sender: broadcast::Sender<ServerReloadCommand>,
     concurrency_limit: Option<Arc<Semaphore>>,
+    key_usage: Option<Arc<KeyUsageRegistry>>,
+    key_rate_limit: Option<Arc<KeyRateLimiter>>,
+    client_rate_limit: Option<Arc<ClientRateLimiter>>,
     task_logger: Logger,
     request_logger: Logger,
     dynamic_metrics_tags: Arc<ArcSwap<StaticMetricsTags>>,
@@ -54,6 +125,7 @@ impl KeyServer {
         duration_recorder: KeyServerDurationRecorder,
         duration_stats: Arc<KeyServerDurationStats>,
         concurrency_limit: Option<Arc<Semaphore>>,
+        keyed_state: KeyServerKeyedState,
         dynamic_metrics_tags: Arc<ArcSwap<StaticMetricsTags>>,
     ) -> Self {
         let reload_sender = broadcast::Sender::new(16);
@@ -85,6 +157,9 @@ impl KeyServer {
             quit_policy: Arc::new(ServerQuitPolicy::default()),
             reload_sender,
             concurrency_limit,
+            key_usage: keyed_state.key_usage,
+            key_rate_limit: keyed_state.key_rate_limit,
+            client_rate_limit: keyed_state.client_rate_limit,
             task_logger,
             request_logger,
             dynamic_metrics_tags,
@@ -101,13 +176,16 @@ impl KeyServer {
         } else {
             None
         };
+        let server_stats = Arc::new(server_stats);
+        let keyed_state = KeyServerKeyedState::new(&config, &server_stats);
         KeyServer::new(
             config,
-            Arc::new(server_stats),
+            server_stats,
             Arc::new(listen_stats),
             duration_recorder,
             duration_stats,
             concurrency_limit,
+            keyed_state,
             Arc::new(ArcSwap::new(Default::default())),
         )
     }
@@ -124,6 +202,7 @@ impl KeyServer {
             } else {
                 (self.duration_recorder.clone(), self.duration_stats.clone())
             };
+        let keyed_state = KeyServerKeyedState::reload(self, &config);
         KeyServer::new(
             config,
             self.server_stats.clone(),
@@ -131,6 +210,7 @@ impl KeyServer {
             duration_recorder,
             duration_stats,
             concurrency_limit,
+            keyed_state,
             self.dynamic_metrics_tags.clone(),
         )
     }
@@ -196,6 +276,20 @@ impl KeyServer {
         self.duration_stats.clone()
     }
 
+    #[inline]
+    pub(crate) fn get_key_usage(&self) -> Option<Arc<KeyUsageRegistry>> {
+        self.key_usage.clone()
+    }
+
+    pub(super) fn prune_rate_limit(&self) {
+        if let Some(limiter) = &self.key_rate_limit {
+            prune_rate_limiter(limiter);
+        }
+        if let Some(limiter) = &self.client_rate_limit {
+            prune_rate_limiter(limiter);
+        }
+    }
+
     pub(super) fn start_runtime(&self, server: &Arc<KeyServer>) -> anyhow::Result<()> {
         KeyServerRuntime::new(server)
             .into_running(&self.config.listen, &self.reload_sender)
@@ -217,6 +311,62 @@ impl KeyServer {
         peer_addr: SocketAddr,
         local_addr: SocketAddr,
     ) {
+        if let Some(tls_server) = &self.config.tls_server {
+            self.run_tls_task(tls_server, stream, peer_addr, local_addr)
+                .await
+        } else {
+            let (r, w) = stream.into_split();
+            self.run_task(r, w, peer_addr, local_addr, None).await
+        }
+    }
+
+    async fn run_tls_task(
+        &self,
+        tls_server: &OpensslServerConfig,
+        stream: TcpStream,
+        peer_addr: SocketAddr,
+        local_addr: SocketAddr,
+    ) {
+        let Ok(ssl) = Ssl::new(&tls_server.ssl_context) else {
+            self.listen_stats.add_dropped();
+            return;
+        };
+        let Ok(ssl_acceptor) = SslAcceptor::new(ssl, stream) else {
+            self.listen_stats.add_dropped();
+            return;
+        };
+        match tokio::time::timeout(tls_server.accept_timeout, ssl_acceptor.accept()).await {
+            Ok(Ok(ssl_stream)) => {
+                let client_authorization = self.config.client_authorization.as_ref().map(|auth| {
+                    let client_cert = ssl_stream.ssl().peer_certificate();
+                    auth.find_rule(client_cert.as_deref())
+                });
+                let (r, w) = tokio::io::split(ssl_stream);
+                self.run_task(r, w, peer_addr, local_addr, client_authorization)
+                    .await
+            }
+            Ok(Err(e)) => {
+                self.listen_stats.add_failed();
+                debug!("{local_addr} - {peer_addr} tls error: {e:?}");
+            }
+            Err(_) => {
+                self.listen_stats.add_timeout();
+                debug!("{local_addr} - {peer_addr} tls timeout");
+            }
+        }
+    }
+
+    async fn run_task<R, W>(
+        &self,
+        r: R,
+        w: W,
+        peer_addr: SocketAddr,
+        local_addr: SocketAddr,
+        client_authorization: Option<Arc<KeyAuthorizationRule>>,
+    ) where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
         let ctx = KeylessTaskContext {
             server_config: self.config.clone(),
             server_stats: self.server_stats.clone(),
@@ -227,9 +377,12 @@ impl KeyServer {
             request_logger: self.request_logger.clone(),
             reload_notifier: self.reload_sender.subscribe(),
             concurrency_limit: self.concurrency_limit.clone(),
+            key_usage: self.key_usage.clone(),
+            key_rate_limit: self.key_rate_limit.clone(),
+            client_rate_limit: self.client_rate_limit.clone(),
+            client_authorization,
         };
 
-        let (r, w) = stream.into_split();
         let task = KeylessTask::new(ctx);
 
         #[cfg(feature = "openssl-async-job")]
@@ -243,3 +396,38 @@ impl KeyServer {
         task.into_simplex_running(r, w).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU32;
+    use std::time::Duration;
+
+    use governor::Quota;
+
+    #[test]
+    fn prune_replenished() {
+        let limiter: ClientRateLimiter =
+            RateLimiter::keyed(Quota::per_second(NonZeroU32::new(1000).unwrap()));
+        let ip1 = IpAddr::from([127, 0, 0, 1]);
+        let ip2 = IpAddr::from([127, 0, 0, 2]);
+        assert!(limiter.check_key(&ip1).is_ok());
+        assert!(limiter.check_key(&ip2).is_ok());
+        assert_eq!(limiter.len(), 2);
+
+        std::thread::sleep(Duration::from_millis(10));
+        prune_rate_limiter(&limiter);
+        assert!(limiter.is_empty());
+    }
+
+    #[test]
+    fn prune_keep_recent() {
+        let limiter: KeyRateLimiter =
+            RateLimiter::keyed(Quota::per_hour(NonZeroU32::new(1).unwrap()));
+        let ski = b"ski".to_vec();
+        assert!(limiter.check_key(&ski).is_ok());
+        prune_rate_limiter(&limiter);
+        assert_eq!(limiter.len(), 1);
+        assert!(limiter.check_key(&ski).is_err());
+    }
+}
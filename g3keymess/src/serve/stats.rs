@@ -35,6 +35,7 @@ pub(crate) struct KeyServerRequestStats {
     crypto_fail: AtomicU64,
     bad_op_code: AtomicU64,
     format_error: AtomicU64,
+    throttled: AtomicU64,
     other_fail: AtomicU64,
 }
 
@@ -48,6 +49,7 @@ pub(crate) struct KeyServerRequestSnapshot {
     pub(crate) crypto_fail: u64,
     pub(crate) bad_op_code: u64,
     pub(crate) format_error: u64,
+    pub(crate) throttled: u64,
     pub(crate) other_fail: u64,
 }
 
@@ -84,6 +86,10 @@ impl KeyServerRequestStats {
         self.format_error.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub(crate) fn add_throttled(&self) {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+    }
+
     fn add_other_fail(&self) {
         self.other_fail.fetch_add(1, Ordering::Relaxed);
     }
@@ -108,6 +114,7 @@ impl KeyServerRequestStats {
             crypto_fail: self.crypto_fail.load(Ordering::Relaxed),
             bad_op_code: self.bad_op_code.load(Ordering::Relaxed),
             format_error: self.format_error.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
             other_fail: self.other_fail.load(Ordering::Relaxed),
         }
     }
@@ -192,6 +199,11 @@ impl KeyServerStats {
         self.extra_metrics_tags.load_full()
     }
 
+    #[inline]
+    pub(crate) fn shared_extra_tags(&self) -> Arc<ArcSwapOption<StaticMetricsTags>> {
+        self.extra_metrics_tags.clone()
+    }
+
     pub(crate) fn add_task(&self) {
         self.task_total.fetch_add(1, Ordering::Relaxed);
     }
@@ -14,34 +14,110 @@
  * limitations under the License.
  */
 
+use std::hash::Hash;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
 use chrono::{DateTime, Utc};
 use slog::{slog_info, Logger};
 use tokio::io::AsyncRead;
-use tokio::sync::{broadcast, Semaphore};
+use tokio::sync::{broadcast, mpsc, Semaphore};
 use tokio::time::Instant;
 use uuid::Uuid;
 
 use g3_histogram::HistogramRecorder;
 use g3_slog_types::{LtDateTime, LtUuid};
 
-use crate::config::server::KeyServerConfig;
+use crate::config::server::{KeyAuthorizationRule, KeyServerConfig};
 use crate::protocol::{KeylessAction, KeylessErrorResponse, KeylessRequest};
 use crate::serve::{
-    KeyServerDurationRecorder, KeyServerRequestStats, KeyServerStats, ServerReloadCommand,
-    ServerTaskError,
+    ClientRateLimiter, KeyRateLimiter, KeyServerDurationRecorder, KeyServerRequestStats,
+    KeyServerStats, KeyUsageRegistry, KeyedRateLimiter, ServerReloadCommand, ServerTaskError,
 };
 
 #[cfg(feature = "openssl-async-job")]
 mod multiplex;
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
 mod simplex;
 
+/// The request stats of the server, and of the key if key stats is enabled
+#[derive(Clone)]
+struct RequestStats {
+    server: Arc<KeyServerRequestStats>,
+    key: Option<Arc<KeyServerRequestStats>>,
+}
+
+impl RequestStats {
+    fn dec_alive(&self) {
+        self.server.dec_alive();
+        if let Some(key) = &self.key {
+            key.dec_alive();
+        }
+    }
+
+    fn add_passed(&self) {
+        self.server.add_passed();
+        if let Some(key) = &self.key {
+            key.add_passed();
+        }
+    }
+
+    #[cfg(any(feature = "openssl-async-job", feature = "pkcs11"))]
+    fn add_crypto_fail(&self) {
+        self.server.add_crypto_fail();
+        if let Some(key) = &self.key {
+            key.add_crypto_fail();
+        }
+    }
+
+    fn add_by_error_rsp(&self, rsp: &KeylessErrorResponse) {
+        if rsp.throttled {
+            self.server.add_throttled();
+            if let Some(key) = &self.key {
+                key.add_throttled();
+            }
+        } else {
+            let code = rsp.error_code();
+            self.server.add_by_error_code(code);
+            if let Some(key) = &self.key {
+                key.add_by_error_code(code);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct RequestDurationRecorder {
+    server: Arc<HistogramRecorder<u64>>,
+    key: Option<Arc<HistogramRecorder<u64>>>,
+}
+
+impl RequestDurationRecorder {
+    fn record(&self, v: u64) -> Result<(), mpsc::error::SendError<u64>> {
+        if let Some(key) = &self.key {
+            let _ = key.record(v);
+        }
+        self.server.record(v)
+    }
+}
+
+fn check_rate_limit<K: Clone + Hash + Eq>(
+    limit: &KeyedRateLimiter<K>,
+    key: &K,
+    id: u32,
+) -> Result<(), KeylessErrorResponse> {
+    if limit.check_key(key).is_err() {
+        Err(KeylessErrorResponse::new(id).internal_error().throttled())
+    } else {
+        Ok(())
+    }
+}
+
 struct WrappedKeylessRequest {
     inner: KeylessRequest,
-    stats: Arc<KeyServerRequestStats>,
-    duration_recorder: Arc<HistogramRecorder<u64>>,
+    stats: RequestStats,
+    duration_recorder: RequestDurationRecorder,
     create_time: Instant,
     err_rsp: Option<KeylessErrorResponse>,
 }
@@ -87,8 +163,14 @@ impl WrappedKeylessRequest {
         stats.inc_alive();
         WrappedKeylessRequest {
             inner: req,
-            stats,
-            duration_recorder,
+            stats: RequestStats {
+                server: stats,
+                key: None,
+            },
+            duration_recorder: RequestDurationRecorder {
+                server: duration_recorder,
+                key: None,
+            },
             create_time: Instant::now(),
             err_rsp,
         }
@@ -97,6 +179,16 @@ impl WrappedKeylessRequest {
     fn take_err_rsp(&mut self) -> Option<KeylessErrorResponse> {
         self.err_rsp.take()
     }
+
+    fn attach_key_usage(&mut self, registry: &KeyUsageRegistry) {
+        let usage = registry.get(&self.inner.ski);
+        if let Some((stats, duration_recorder)) = usage.select(&self.inner.action) {
+            stats.add_total();
+            stats.inc_alive();
+            self.stats.key = Some(stats);
+            self.duration_recorder.key = Some(duration_recorder);
+        }
+    }
 }
 
 impl Drop for WrappedKeylessRequest {
@@ -115,6 +207,10 @@ pub(crate) struct KeylessTaskContext {
     pub(crate) request_logger: Logger,
     pub(crate) reload_notifier: broadcast::Receiver<ServerReloadCommand>,
     pub(crate) concurrency_limit: Option<Arc<Semaphore>>,
+    pub(crate) key_usage: Option<Arc<KeyUsageRegistry>>,
+    pub(crate) key_rate_limit: Option<Arc<KeyRateLimiter>>,
+    pub(crate) client_rate_limit: Option<Arc<ClientRateLimiter>>,
+    pub(crate) client_authorization: Option<Arc<KeyAuthorizationRule>>,
 }
 
 pub(crate) struct KeylessTask {
@@ -159,16 +255,41 @@ impl KeylessTask {
         )
         .await
         {
-            Ok(Ok(req)) => Ok(WrappedKeylessRequest::new(
-                req,
-                &self.ctx.server_stats,
-                &self.ctx.duration_recorder,
-            )),
+            Ok(Ok(req)) => {
+                let mut req = WrappedKeylessRequest::new(
+                    req,
+                    &self.ctx.server_stats,
+                    &self.ctx.duration_recorder,
+                );
+                if req.err_rsp.is_none() {
+                    if let Some(rule) = &self.ctx.client_authorization {
+                        req.err_rsp = rule.check(&req.inner).err();
+                    }
+                }
+                if req.err_rsp.is_none() {
+                    if let Some(limit) = &self.ctx.client_rate_limit {
+                        req.err_rsp =
+                            check_rate_limit(limit, &self.ctx.peer_addr.ip(), req.inner.id).err();
+                    }
+                }
+                Ok(req)
+            }
             Ok(Err(e)) => Err(e.into()),
             Err(_) => Err(ServerTaskError::ReadTimeout),
         }
     }
 
+    /// Should be called after the key is found, to add key stats and check key rate limit
+    fn check_key_usage(&self, req: &mut WrappedKeylessRequest) -> Result<(), KeylessErrorResponse> {
+        if let Some(registry) = &self.ctx.key_usage {
+            req.attach_key_usage(registry);
+        }
+        if let Some(limit) = &self.ctx.key_rate_limit {
+            check_rate_limit(limit, &req.inner.ski, req.inner.id)?;
+        }
+        Ok(())
+    }
+
     fn log_task_err(&self, e: ServerTaskError) {
         if e.ignore_log() {
             return;
@@ -185,3 +306,47 @@ impl KeylessTask {
         self.log_task_err(ServerTaskError::NoError)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+    use std::num::NonZeroU32;
+
+    use governor::{Quota, RateLimiter};
+
+    use crate::protocol::KeylessResponseErrorCode;
+
+    #[test]
+    fn rate_limit_reject() {
+        let limit: ClientRateLimiter =
+            RateLimiter::keyed(Quota::per_hour(NonZeroU32::new(2).unwrap()));
+        let ip1 = IpAddr::from([127, 0, 0, 1]);
+        let ip2 = IpAddr::from([127, 0, 0, 2]);
+
+        assert!(check_rate_limit(&limit, &ip1, 1).is_ok());
+        assert!(check_rate_limit(&limit, &ip1, 2).is_ok());
+        let rsp = check_rate_limit(&limit, &ip1, 3).unwrap_err();
+        assert!(rsp.throttled);
+        assert_eq!(rsp.id(), 3);
+        assert!(matches!(
+            rsp.error_code(),
+            KeylessResponseErrorCode::InternalError
+        ));
+
+        // the other clients should not be affected
+        assert!(check_rate_limit(&limit, &ip2, 4).is_ok());
+    }
+
+    #[test]
+    fn key_rate_limit_reject() {
+        let limit: KeyRateLimiter =
+            RateLimiter::keyed(Quota::per_hour(NonZeroU32::new(1).unwrap()));
+        let ski1 = vec![0x01; 20];
+        let ski2 = vec![0x02; 20];
+
+        assert!(check_rate_limit(&limit, &ski1, 1).is_ok());
+        assert!(check_rate_limit(&limit, &ski1, 2).unwrap_err().throttled);
+        assert!(check_rate_limit(&limit, &ski2, 3).is_ok());
+    }
+}
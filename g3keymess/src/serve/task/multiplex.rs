@@ -147,7 +147,7 @@ impl KeylessTask {
     {
         let mut req = self.timed_read_request(reader, msg_count).await?;
         if let Some(rsp) = req.take_err_rsp() {
-            req.stats.add_by_error_code(rsp.error_code());
+            req.stats.add_by_error_rsp(&rsp);
             let _ = msg_sender.send(KeylessResponse::Error(rsp)).await;
             return Ok(());
         }
@@ -158,15 +158,53 @@ impl KeylessTask {
             return Ok(());
         }
 
+        #[cfg(feature = "pkcs11")]
+        match req.inner.find_pkcs11_key() {
+            Ok(Some(key)) => {
+                if let Err(rsp) = self.check_key_usage(&mut req) {
+                    req.stats.add_by_error_rsp(&rsp);
+                    let _ = msg_sender.send(KeylessResponse::Error(rsp)).await;
+                    return Ok(());
+                }
+
+                let server_sem = if let Some(sem) = self.ctx.concurrency_limit.clone() {
+                    sem.acquire_owned().await.ok()
+                } else {
+                    None
+                };
+
+                let task = self.build_pkcs11_task(req, key, server_sem);
+                let msg_sender = msg_sender.clone();
+                tokio::spawn(async move {
+                    let rsp = task.await;
+                    // send to writer
+                    let _ = msg_sender.send(rsp).await;
+                });
+                return Ok(());
+            }
+            Ok(None) => {}
+            Err(rsp) => {
+                req.stats.add_by_error_rsp(&rsp);
+                let _ = msg_sender.send(KeylessResponse::Error(rsp)).await;
+                return Ok(());
+            }
+        }
+
         let key = match req.inner.find_key() {
             Ok(key) => key,
             Err(rsp) => {
-                req.stats.add_by_error_code(rsp.error_code());
+                req.stats.add_by_error_rsp(&rsp);
                 let _ = msg_sender.send(KeylessResponse::Error(rsp)).await;
                 return Ok(());
             }
         };
 
+        if let Err(rsp) = self.check_key_usage(&mut req) {
+            req.stats.add_by_error_rsp(&rsp);
+            let _ = msg_sender.send(KeylessResponse::Error(rsp)).await;
+            return Ok(());
+        }
+
         let rsp = KeylessErrorResponse::new(req.inner.id);
         self.async_process_by_openssl(req, rsp, key, msg_sender)
             .await;
@@ -0,0 +1,68 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use tokio::sync::OwnedSemaphorePermit;
+
+use g3_types::ext::DurationExt;
+
+use super::{KeylessTask, WrappedKeylessRequest};
+use crate::protocol::{KeylessErrorResponse, KeylessResponse};
+use crate::store::Pkcs11PrivateKey;
+
+impl KeylessTask {
+    /// Build the future which will run the PKCS#11 operation in the blocking thread pool.
+    ///
+    /// The server semaphore permit will be held until the blocking operation finished, even if
+    /// the response has already been sent out as timed out.
+    pub(super) fn build_pkcs11_task(
+        &self,
+        req: WrappedKeylessRequest,
+        key: Arc<Pkcs11PrivateKey>,
+        server_sem: Option<OwnedSemaphorePermit>,
+    ) -> impl std::future::Future<Output = KeylessResponse> + Send + 'static {
+        let async_op_timeout = self.ctx.server_config.async_op_timeout;
+        async move {
+            let create_time = req.create_time;
+            let duration_recorder = req.duration_recorder.clone();
+            let req_stats = req.stats.clone();
+            let err_rsp = KeylessErrorResponse::new(req.inner.id);
+
+            let task = tokio::task::spawn_blocking(move || {
+                let r = req.inner.process_by_pkcs11(&key);
+                r
+            });
+            let rsp = match tokio::time::timeout(async_op_timeout, task).await {
+                Ok(Ok(Ok(d))) => {
+                    req_stats.add_passed();
+                    KeylessResponse::Data(d)
+                }
+                Ok(Ok(Err(e))) => {
+                    req_stats.add_by_error_rsp(&e);
+                    KeylessResponse::Error(e)
+                }
+                Ok(Err(_)) | Err(_) => {
+                    req_stats.add_crypto_fail();
+                    KeylessResponse::Error(err_rsp.crypto_fail())
+                }
+            };
+            drop(server_sem);
+            let _ = duration_recorder.record(create_time.elapsed().as_nanos_u64());
+            rsp
+        }
+    }
+}
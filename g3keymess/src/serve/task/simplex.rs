@@ -93,7 +93,7 @@ impl KeylessTask {
     {
         let mut req = self.timed_read_request(reader, msg_count).await?;
         if let Some(rsp) = req.take_err_rsp() {
-            req.stats.add_by_error_code(rsp.error_code());
+            req.stats.add_by_error_rsp(&rsp);
             return self
                 .send_response(writer, KeylessResponse::Error(rsp))
                 .await;
@@ -106,16 +106,51 @@ impl KeylessTask {
                 .await;
         }
 
+        #[cfg(feature = "pkcs11")]
+        match req.inner.find_pkcs11_key() {
+            Ok(Some(key)) => {
+                if let Err(rsp) = self.check_key_usage(&mut req) {
+                    req.stats.add_by_error_rsp(&rsp);
+                    return self
+                        .send_response(writer, KeylessResponse::Error(rsp))
+                        .await;
+                }
+
+                let server_sem = if let Some(sem) = self.ctx.concurrency_limit.clone() {
+                    sem.acquire_owned().await.ok()
+                } else {
+                    None
+                };
+
+                let rsp = self.build_pkcs11_task(req, key, server_sem).await;
+                return self.send_response(writer, rsp).await;
+            }
+            Ok(None) => {}
+            Err(rsp) => {
+                req.stats.add_by_error_rsp(&rsp);
+                return self
+                    .send_response(writer, KeylessResponse::Error(rsp))
+                    .await;
+            }
+        }
+
         let key = match req.inner.find_key() {
             Ok(key) => key,
             Err(rsp) => {
-                req.stats.add_by_error_code(rsp.error_code());
+                req.stats.add_by_error_rsp(&rsp);
                 return self
                     .send_response(writer, KeylessResponse::Error(rsp))
                     .await;
             }
         };
 
+        if let Err(rsp) = self.check_key_usage(&mut req) {
+            req.stats.add_by_error_rsp(&rsp);
+            return self
+                .send_response(writer, KeylessResponse::Error(rsp))
+                .await;
+        }
+
         let server_sem = if let Some(sem) = self.ctx.concurrency_limit.clone() {
             sem.acquire_owned().await.ok()
         } else {
@@ -144,7 +179,7 @@ impl KeylessTask {
                 KeylessResponse::Data(d)
             }
             Err(e) => {
-                req.stats.add_by_error_code(e.error_code());
+                req.stats.add_by_error_rsp(&e);
                 KeylessResponse::Error(e)
             }
         }
@@ -0,0 +1,154 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use ahash::AHashMap;
+use once_cell::sync::Lazy;
+
+use g3_daemon::metrics::{TAG_KEY_SERVER, TAG_KEY_STAT_ID};
+use g3_statsd_client::{StatsdClient, StatsdTagGroup};
+use g3_types::stats::StatId;
+
+use super::server::{
+    emit_request_duration_stats, emit_request_stats, RequestMetricNames, REQUEST_TYPE_ECDSA_SIGN,
+    REQUEST_TYPE_ED25519_SIGN, REQUEST_TYPE_RSA_DECRYPT, REQUEST_TYPE_RSA_PSS_SIGN,
+    REQUEST_TYPE_RSA_SIGN,
+};
+use crate::serve::{KeyUsageDurationStats, KeyUsageSnapshot, KeyUsageStats};
+
+const TAG_KEY_SKI: &str = "ski";
+
+const METRIC_NAME_KEY_REQUEST_TOTAL: &str = "key.request.total";
+const METRIC_NAME_KEY_REQUEST_ALIVE: &str = "key.request.alive";
+const METRIC_NAME_KEY_REQUEST_PASSED: &str = "key.request.passed";
+const METRIC_NAME_KEY_REQUEST_FAILED: &str = "key.request.failed";
+const METRIC_NAME_KEY_REQUEST_DURATION: &str = "key.request.duration";
+
+const KEY_REQUEST_METRIC_NAMES: RequestMetricNames = RequestMetricNames {
+    total: METRIC_NAME_KEY_REQUEST_TOTAL,
+    alive: METRIC_NAME_KEY_REQUEST_ALIVE,
+    passed: METRIC_NAME_KEY_REQUEST_PASSED,
+    failed: METRIC_NAME_KEY_REQUEST_FAILED,
+    duration: METRIC_NAME_KEY_REQUEST_DURATION,
+};
+
+type KeyStatsValue = (Arc<KeyUsageStats>, KeyUsageSnapshot);
+
+static KEY_STATS_MAP: Lazy<Mutex<AHashMap<StatId, KeyStatsValue>>> =
+    Lazy::new(|| Mutex::new(AHashMap::new()));
+static KEY_DURATION_STATS_MAP: Lazy<Mutex<AHashMap<StatId, Arc<KeyUsageDurationStats>>>> =
+    Lazy::new(|| Mutex::new(AHashMap::new()));
+
+pub(in crate::stat) fn sync_stats() {
+    let mut key_stats_map = KEY_STATS_MAP.lock().unwrap();
+    let mut key_duration_stats_map = KEY_DURATION_STATS_MAP.lock().unwrap();
+    crate::serve::foreach_server(|_, server| {
+        let Some(registry) = server.get_key_usage() else {
+            return;
+        };
+        registry.foreach(|usage| {
+            let stats = usage.stats.clone();
+            key_stats_map
+                .entry(stats.stat_id())
+                .or_insert_with(|| (stats, KeyUsageSnapshot::default()));
+
+            let stats = usage.duration_stats.clone();
+            key_duration_stats_map
+                .entry(stats.stat_id())
+                .or_insert_with(|| stats);
+        });
+    });
+}
+
+pub(in crate::stat) fn emit_stats(client: &mut StatsdClient) {
+    let mut key_stats_map = KEY_STATS_MAP.lock().unwrap();
+    key_stats_map.retain(|_, (stats, snap)| {
+        emit_key_stats(client, stats, snap);
+        // use Arc instead of Weak here, as we should emit the final metrics before drop it
+        Arc::strong_count(stats) > 1
+    });
+    drop(key_stats_map);
+
+    let mut key_duration_stats_map = KEY_DURATION_STATS_MAP.lock().unwrap();
+    key_duration_stats_map.retain(|_, stats| {
+        emit_key_duration_stats(client, stats);
+        // use Arc instead of Weak here, as we should emit the final metrics before drop it
+        Arc::strong_count(stats) > 1
+    });
+    drop(key_duration_stats_map);
+}
+
+fn emit_key_stats(
+    client: &mut StatsdClient,
+    stats: &Arc<KeyUsageStats>,
+    snap: &mut KeyUsageSnapshot,
+) {
+    let mut common_tags = StatsdTagGroup::default();
+    let mut buffer = itoa::Buffer::new();
+    common_tags.add_tag(TAG_KEY_SERVER, stats.server());
+    common_tags.add_tag(TAG_KEY_STAT_ID, buffer.format(stats.stat_id().as_u64()));
+    common_tags.add_tag(TAG_KEY_SKI, stats.ski());
+    if let Some(tags) = stats.load_extra_tags() {
+        common_tags.add_static_tags(&tags);
+    }
+
+    macro_rules! emit_request_stats_u64 {
+        ($id:ident, $request:expr) => {
+            emit_request_stats(
+                client,
+                &KEY_REQUEST_METRIC_NAMES,
+                $request,
+                stats.$id.snapshot(),
+                &mut snap.$id,
+                &common_tags,
+            );
+        };
+    }
+    emit_request_stats_u64!(rsa_decrypt, REQUEST_TYPE_RSA_DECRYPT);
+    emit_request_stats_u64!(rsa_sign, REQUEST_TYPE_RSA_SIGN);
+    emit_request_stats_u64!(rsa_pss_sign, REQUEST_TYPE_RSA_PSS_SIGN);
+    emit_request_stats_u64!(ecdsa_sign, REQUEST_TYPE_ECDSA_SIGN);
+    emit_request_stats_u64!(ed25519_sign, REQUEST_TYPE_ED25519_SIGN);
+}
+
+fn emit_key_duration_stats(client: &mut StatsdClient, stats: &Arc<KeyUsageDurationStats>) {
+    let mut common_tags = StatsdTagGroup::default();
+    let mut buffer = itoa::Buffer::new();
+    common_tags.add_tag(TAG_KEY_SERVER, stats.server());
+    common_tags.add_tag(TAG_KEY_STAT_ID, buffer.format(stats.stat_id().as_u64()));
+    common_tags.add_tag(TAG_KEY_SKI, stats.ski());
+    if let Some(tags) = stats.load_extra_tags() {
+        common_tags.add_static_tags(&tags);
+    }
+
+    macro_rules! emit_request_stats_u64 {
+        ($id:ident, $request:expr) => {
+            emit_request_duration_stats(
+                client,
+                &KEY_REQUEST_METRIC_NAMES,
+                $request,
+                &stats.$id,
+                &common_tags,
+            );
+        };
+    }
+    emit_request_stats_u64!(rsa_decrypt, REQUEST_TYPE_RSA_DECRYPT);
+    emit_request_stats_u64!(rsa_sign, REQUEST_TYPE_RSA_SIGN);
+    emit_request_stats_u64!(rsa_pss_sign, REQUEST_TYPE_RSA_PSS_SIGN);
+    emit_request_stats_u64!(ecdsa_sign, REQUEST_TYPE_ECDSA_SIGN);
+    emit_request_stats_u64!(ed25519_sign, REQUEST_TYPE_ED25519_SIGN);
+}
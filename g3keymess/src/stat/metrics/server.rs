@@ -40,18 +40,35 @@ const METRIC_NAME_SERVER_REQUEST_PASSED: &str = "server.request.passed";
 const METRIC_NAME_SERVER_REQUEST_FAILED: &str = "server.request.failed";
 const METRIC_NAME_SERVER_REQUEST_DURATION: &str = "server.request.duration";
 
+pub(super) struct RequestMetricNames {
+    pub(super) total: &'static str,
+    pub(super) alive: &'static str,
+    pub(super) passed: &'static str,
+    pub(super) failed: &'static str,
+    pub(super) duration: &'static str,
+}
+
+const SERVER_REQUEST_METRIC_NAMES: RequestMetricNames = RequestMetricNames {
+    total: METRIC_NAME_SERVER_REQUEST_TOTAL,
+    alive: METRIC_NAME_SERVER_REQUEST_ALIVE,
+    passed: METRIC_NAME_SERVER_REQUEST_PASSED,
+    failed: METRIC_NAME_SERVER_REQUEST_FAILED,
+    duration: METRIC_NAME_SERVER_REQUEST_DURATION,
+};
+
 const REQUEST_TYPE_NO_OP: &str = "no_op";
 const REQUEST_TYPE_PING_PONG: &str = "ping_pong";
-const REQUEST_TYPE_RSA_DECRYPT: &str = "rsa_decrypt";
-const REQUEST_TYPE_RSA_SIGN: &str = "rsa_sign";
-const REQUEST_TYPE_RSA_PSS_SIGN: &str = "rsa_pss_sign";
-const REQUEST_TYPE_ECDSA_SIGN: &str = "ecdsa_sign";
-const REQUEST_TYPE_ED25519_SIGN: &str = "ed25519_sign";
+pub(super) const REQUEST_TYPE_RSA_DECRYPT: &str = "rsa_decrypt";
+pub(super) const REQUEST_TYPE_RSA_SIGN: &str = "rsa_sign";
+pub(super) const REQUEST_TYPE_RSA_PSS_SIGN: &str = "rsa_pss_sign";
+pub(super) const REQUEST_TYPE_ECDSA_SIGN: &str = "ecdsa_sign";
+pub(super) const REQUEST_TYPE_ED25519_SIGN: &str = "ed25519_sign";
 
 const FAIL_REASON_KEY_NOT_FOUND: &str = "key_not_found";
 const FAIL_REASON_CRYPTO_FAIL: &str = "crypto_fail";
 const FAIL_REASON_BAD_OP_CODE: &str = "bad_op_code";
 const FAIL_REASON_FORMAT_ERROR: &str = "format_error";
+const FAIL_REASON_THROTTLED: &str = "throttled";
 const FAIL_REASON_OTHER_FAIL: &str = "other_fail";
 
 type ServerStatsValue = (Arc<KeyServerStats>, KeyServerSnapshot);
@@ -148,8 +165,9 @@ fn emit_server_stats(
 
     macro_rules! emit_request_stats_u64 {
         ($id:ident, $request:expr) => {
-            emit_server_request_stats(
+            emit_request_stats(
                 client,
+                &SERVER_REQUEST_METRIC_NAMES,
                 $request,
                 stats.$id.snapshot(),
                 &mut snap.$id,
@@ -166,8 +184,9 @@ fn emit_server_stats(
     emit_request_stats_u64!(ed25519_sign, REQUEST_TYPE_ED25519_SIGN);
 }
 
-fn emit_server_request_stats(
+pub(super) fn emit_request_stats(
     client: &mut StatsdClient,
+    names: &RequestMetricNames,
     request: &str,
     stats: KeyServerRequestSnapshot,
     snap: &mut KeyServerRequestSnapshot,
@@ -179,24 +198,20 @@ fn emit_server_request_stats(
     }
     let diff_value = new_value.wrapping_sub(snap.total);
     client
-        .count_with_tags(METRIC_NAME_SERVER_REQUEST_TOTAL, diff_value, common_tags)
+        .count_with_tags(names.total, diff_value, common_tags)
         .with_tag(TAG_KEY_REQUEST, request)
         .send();
     snap.total = new_value;
 
     client
-        .gauge_with_tags(
-            METRIC_NAME_SERVER_REQUEST_ALIVE,
-            stats.alive_count,
-            common_tags,
-        )
+        .gauge_with_tags(names.alive, stats.alive_count, common_tags)
         .with_tag(TAG_KEY_REQUEST, request)
         .send();
 
     let new_value = stats.passed;
     let diff_value = new_value.wrapping_sub(snap.passed);
     client
-        .count_with_tags(METRIC_NAME_SERVER_REQUEST_PASSED, diff_value, common_tags)
+        .count_with_tags(names.passed, diff_value, common_tags)
         .with_tag(TAG_KEY_REQUEST, request)
         .send();
     snap.passed = new_value;
@@ -207,7 +222,7 @@ fn emit_server_request_stats(
             if new_value != 0 || snap.$id != 0 {
                 let diff_value = new_value.wrapping_sub(snap.$id);
                 client
-                    .count_with_tags(METRIC_NAME_SERVER_REQUEST_FAILED, diff_value, common_tags)
+                    .count_with_tags(names.failed, diff_value, common_tags)
                     .with_tag(TAG_KEY_REQUEST, request)
                     .with_tag(TAG_KEY_REASON, $reason)
                     .send();
@@ -219,6 +234,7 @@ fn emit_server_request_stats(
     emit_failed_stats_u64!(crypto_fail, FAIL_REASON_CRYPTO_FAIL);
     emit_failed_stats_u64!(bad_op_code, FAIL_REASON_BAD_OP_CODE);
     emit_failed_stats_u64!(format_error, FAIL_REASON_FORMAT_ERROR);
+    emit_failed_stats_u64!(throttled, FAIL_REASON_THROTTLED);
     emit_failed_stats_u64!(other_fail, FAIL_REASON_OTHER_FAIL);
 }
 
@@ -231,7 +247,13 @@ fn emit_server_duration_stats(client: &mut StatsdClient, stats: &Arc<KeyServerDu
 
     macro_rules! emit_request_stats_u64 {
         ($id:ident, $request:expr) => {
-            emit_server_request_duration_stats(client, $request, &stats.$id, &common_tags);
+            emit_request_duration_stats(
+                client,
+                &SERVER_REQUEST_METRIC_NAMES,
+                $request,
+                &stats.$id,
+                &common_tags,
+            );
         };
     }
     emit_request_stats_u64!(ping_pong, REQUEST_TYPE_PING_PONG);
@@ -242,8 +264,9 @@ fn emit_server_duration_stats(client: &mut StatsdClient, stats: &Arc<KeyServerDu
     emit_request_stats_u64!(ed25519_sign, REQUEST_TYPE_ED25519_SIGN);
 }
 
-fn emit_server_request_duration_stats(
+pub(super) fn emit_request_duration_stats(
     client: &mut StatsdClient,
+    names: &RequestMetricNames,
     request: &str,
     stats: &HistogramStats,
     common_tags: &StatsdTagGroup,
@@ -251,7 +274,7 @@ fn emit_server_request_duration_stats(
     stats.foreach_stat(|_, qs, v| {
         if v > 0_f64 {
             client
-                .gauge_float_with_tags(METRIC_NAME_SERVER_REQUEST_DURATION, v, common_tags)
+                .gauge_float_with_tags(names.duration, v, common_tags)
                 .with_tag(TAG_KEY_REQUEST, request)
                 .with_tag(TAG_KEY_QUANTILE, qs)
                 .send();
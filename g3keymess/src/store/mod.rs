@@ -15,6 +15,8 @@
  */
 
 use std::cell::RefCell;
+#[cfg(feature = "pkcs11")]
+use std::sync::Arc;
 
 use ahash::AHashMap;
 use anyhow::anyhow;
@@ -27,10 +29,20 @@ pub use ops::{load_all, reload_all};
 
 mod registry;
 
+#[cfg(feature = "pkcs11")]
+mod pkcs11;
+#[cfg(feature = "pkcs11")]
+pub(crate) use pkcs11::{Pkcs11PrivateKey, Pkcs11SlotSelector, Pkcs11Token};
+
 thread_local! {
     static GLOBAL_SKI_MAP: RefCell<AHashMap<Vec<u8>, PKey<Private>>> = RefCell::new(AHashMap::new());
 }
 
+#[cfg(feature = "pkcs11")]
+thread_local! {
+    static GLOBAL_PKCS11_SKI_MAP: RefCell<AHashMap<Vec<u8>, Arc<Pkcs11PrivateKey>>> = RefCell::new(AHashMap::new());
+}
+
 pub(crate) fn add_global(key: PKey<Private>) -> anyhow::Result<()> {
     let ski = key.ski().map_err(|e| anyhow!("failed to get SKI: {e}"))?;
     GLOBAL_SKI_MAP.with_borrow_mut(|map| {
@@ -40,10 +52,27 @@ pub(crate) fn add_global(key: PKey<Private>) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "pkcs11")]
+pub(crate) fn add_global_pkcs11(ski: Vec<u8>, key: Pkcs11PrivateKey) {
+    GLOBAL_PKCS11_SKI_MAP.with_borrow_mut(|map| {
+        map.insert(ski, Arc::new(key));
+    });
+}
+
 pub(crate) fn get_all_ski() -> Vec<Vec<u8>> {
-    GLOBAL_SKI_MAP.with_borrow(|map| map.keys().map(|v| v.to_vec()).collect())
+    #[allow(unused_mut)]
+    let mut all: Vec<Vec<u8>> =
+        GLOBAL_SKI_MAP.with_borrow(|map| map.keys().map(|v| v.to_vec()).collect());
+    #[cfg(feature = "pkcs11")]
+    GLOBAL_PKCS11_SKI_MAP.with_borrow(|map| all.extend(map.keys().map(|v| v.to_vec())));
+    all
 }
 
 pub(crate) fn get_by_ski(ski: &[u8]) -> Option<PKey<Private>> {
     GLOBAL_SKI_MAP.with_borrow(|map| map.get(ski).cloned())
 }
+
+#[cfg(feature = "pkcs11")]
+pub(crate) fn get_pkcs11_by_ski(ski: &[u8]) -> Option<Arc<Pkcs11PrivateKey>> {
+    GLOBAL_PKCS11_SKI_MAP.with_borrow(|map| map.get(ski).cloned())
+}
@@ -0,0 +1,528 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context};
+use cryptoki::context::{CInitializeArgs, Pkcs11};
+use cryptoki::error::RvError;
+use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsPssParams};
+use cryptoki::mechanism::{Mechanism, MechanismType};
+use cryptoki::object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::slot::Slot;
+use cryptoki::types::AuthPin;
+use once_cell::sync::Lazy;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey, EcPoint};
+use openssl::ecdsa::EcdsaSig;
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Public};
+use openssl::rsa::{Padding, Rsa};
+
+use g3_tls_cert::ext::PublicKeyExt;
+
+static PKCS11_MODULES: Lazy<Mutex<HashMap<PathBuf, Pkcs11>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Load and initialize the PKCS#11 module, the module will be initialized only once.
+fn load_module(path: &Path) -> anyhow::Result<Pkcs11> {
+    let mut map = PKCS11_MODULES.lock().unwrap();
+    if let Some(module) = map.get(path) {
+        return Ok(module.clone());
+    }
+
+    let module = Pkcs11::new(path)
+        .map_err(|e| anyhow!("failed to load pkcs11 module {}: {e}", path.display()))?;
+    module
+        .initialize(CInitializeArgs::OsThreads)
+        .map_err(|e| anyhow!("failed to initialize pkcs11 module {}: {e}", path.display()))?;
+    map.insert(path.to_path_buf(), module.clone());
+    Ok(module)
+}
+
+pub(crate) enum Pkcs11SlotSelector {
+    Id(u64),
+    TokenLabel(String),
+}
+
+pub(crate) struct Pkcs11Token {
+    module: Pkcs11,
+    slot: Slot,
+    /// keep a logged in session, so the login state will be kept for all sessions
+    _login_session: Mutex<Session>,
+    idle_sessions: Mutex<Vec<Session>>,
+}
+
+impl Pkcs11Token {
+    pub(crate) fn open(
+        module_path: &Path,
+        selector: &Pkcs11SlotSelector,
+        pin: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let module = load_module(module_path)?;
+
+        let slots = module
+            .get_slots_with_token()
+            .map_err(|e| anyhow!("failed to get slots with token: {e}"))?;
+        let mut found_slot = None;
+        for slot in slots {
+            match selector {
+                Pkcs11SlotSelector::Id(id) => {
+                    if slot.id() == *id {
+                        found_slot = Some(slot);
+                        break;
+                    }
+                }
+                Pkcs11SlotSelector::TokenLabel(label) => {
+                    let info = module
+                        .get_token_info(slot)
+                        .map_err(|e| anyhow!("failed to get token info for slot {slot}: {e}"))?;
+                    if info.label() == label {
+                        found_slot = Some(slot);
+                        break;
+                    }
+                }
+            }
+        }
+        let Some(slot) = found_slot else {
+            return Err(anyhow!("no matched pkcs11 slot found"));
+        };
+
+        let session = module
+            .open_ro_session(slot)
+            .map_err(|e| anyhow!("failed to open session on slot {slot}: {e}"))?;
+        if let Some(pin) = pin {
+            match session.login(UserType::User, Some(&AuthPin::new(pin.to_string()))) {
+                Ok(_) => {}
+                // the login state is shared by all sessions of the same token
+                Err(cryptoki::error::Error::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+                Err(e) => return Err(anyhow!("failed to login to slot {slot}: {e}")),
+            }
+        }
+
+        Ok(Pkcs11Token {
+            module,
+            slot,
+            _login_session: Mutex::new(session),
+            idle_sessions: Mutex::new(Vec::new()),
+        })
+    }
+
+    fn with_session<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: FnOnce(&Session) -> cryptoki::error::Result<T>,
+    {
+        let session = self.idle_sessions.lock().unwrap().pop();
+        let session = match session {
+            Some(s) => s,
+            None => self
+                .module
+                .open_ro_session(self.slot)
+                .map_err(|e| anyhow!("failed to open new session: {e}"))?,
+        };
+        // the session will be closed on error
+        let r = f(&session).map_err(|e| anyhow!("pkcs11 operation failed: {e}"))?;
+        self.idle_sessions.lock().unwrap().push(session);
+        Ok(r)
+    }
+
+    /// Find all private keys on this token, which should match the label and id filters.
+    pub(crate) fn find_keys(
+        self: &Arc<Self>,
+        labels: &[String],
+        ids: &[Vec<u8>],
+    ) -> anyhow::Result<Vec<(Vec<u8>, Pkcs11PrivateKey)>> {
+        let handles =
+            self.with_session(|s| s.find_objects(&[Attribute::Class(ObjectClass::PRIVATE_KEY)]))?;
+
+        let mut keys = Vec::with_capacity(handles.len());
+        for handle in handles {
+            let attrs = self.with_session(|s| {
+                s.get_attributes(
+                    handle,
+                    &[
+                        AttributeType::KeyType,
+                        AttributeType::Label,
+                        AttributeType::Id,
+                    ],
+                )
+            })?;
+
+            let mut key_type = None;
+            let mut label = Vec::new();
+            let mut id = Vec::new();
+            for attr in attrs {
+                match attr {
+                    Attribute::KeyType(t) => key_type = Some(t),
+                    Attribute::Label(v) => label = v,
+                    Attribute::Id(v) => id = v,
+                    _ => {}
+                }
+            }
+
+            if !labels.is_empty() && !labels.iter().any(|l| l.as_bytes() == label.as_slice()) {
+                continue;
+            }
+            if !ids.is_empty() && !ids.iter().any(|i| i == &id) {
+                continue;
+            }
+            let Some(key_type) = key_type else {
+                continue;
+            };
+
+            let public_key = self.get_public_key(handle, key_type, &id).context(format!(
+                "failed to get public key for private key {}",
+                String::from_utf8_lossy(&label)
+            ))?;
+            let ski = public_key
+                .ski()
+                .map_err(|e| anyhow!("failed to get SKI: {e}"))?;
+            keys.push((
+                ski.to_vec(),
+                Pkcs11PrivateKey {
+                    token: self.clone(),
+                    handle,
+                    key_id: public_key.id(),
+                    key_size: public_key.size(),
+                },
+            ));
+        }
+        Ok(keys)
+    }
+
+    fn get_public_key(
+        &self,
+        private_handle: ObjectHandle,
+        key_type: KeyType,
+        id: &[u8],
+    ) -> anyhow::Result<PKey<Public>> {
+        if key_type == KeyType::RSA {
+            let attrs = self.with_session(|s| {
+                s.get_attributes(
+                    private_handle,
+                    &[AttributeType::Modulus, AttributeType::PublicExponent],
+                )
+            })?;
+            let mut n = None;
+            let mut e = None;
+            for attr in attrs {
+                match attr {
+                    Attribute::Modulus(v) => n = Some(v),
+                    Attribute::PublicExponent(v) => e = Some(v),
+                    _ => {}
+                }
+            }
+            let (Some(n), Some(e)) = (n, e) else {
+                return Err(anyhow!("no rsa public components found"));
+            };
+            let n = BigNum::from_slice(&n)?;
+            let e = BigNum::from_slice(&e)?;
+            let rsa = Rsa::from_public_components(n, e)?;
+            return PKey::from_rsa(rsa).map_err(anyhow::Error::from);
+        }
+
+        // the EC_POINT attribute is only available in the public key object
+        let public_handle = self
+            .with_session(|s| {
+                s.find_objects(&[
+                    Attribute::Class(ObjectClass::PUBLIC_KEY),
+                    Attribute::Id(id.to_vec()),
+                ])
+            })?
+            .pop()
+            .ok_or_else(|| anyhow!("no public key object with the same id found"))?;
+        let attrs = self.with_session(|s| {
+            s.get_attributes(
+                public_handle,
+                &[AttributeType::EcParams, AttributeType::EcPoint],
+            )
+        })?;
+        let mut params = None;
+        let mut point = None;
+        for attr in attrs {
+            match attr {
+                Attribute::EcParams(v) => params = Some(v),
+                Attribute::EcPoint(v) => point = Some(v),
+                _ => {}
+            }
+        }
+        let Some(point) = point else {
+            return Err(anyhow!("no ec point found"));
+        };
+        let point = strip_der_octet_string(&point);
+
+        if key_type == KeyType::EC {
+            let Some(params) = params else {
+                return Err(anyhow!("no ec params found"));
+            };
+            let nid = curve_nid_from_params(&params)?;
+            let group = EcGroup::from_curve_name(nid)?;
+            let mut ctx = openssl::bn::BigNumContext::new()?;
+            let point = EcPoint::from_bytes(&group, point, &mut ctx)?;
+            let ec_key = EcKey::from_public_key(&group, &point)?;
+            PKey::from_ec_key(ec_key).map_err(anyhow::Error::from)
+        } else if key_type == KeyType::EC_EDWARDS {
+            PKey::public_key_from_raw_bytes(point, Id::ED25519).map_err(anyhow::Error::from)
+        } else {
+            Err(anyhow!("unsupported key type {key_type}"))
+        }
+    }
+}
+
+fn strip_der_octet_string(v: &[u8]) -> &[u8] {
+    if v.len() < 2 || v[0] != 0x04 {
+        return v;
+    }
+    let len = v[1] as usize;
+    if len < 0x80 {
+        if len + 2 == v.len() {
+            return &v[2..];
+        }
+    } else {
+        let n = len & 0x7F;
+        if v.len() > 2 + n {
+            let len = v[2..2 + n]
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            if len + 2 + n == v.len() {
+                return &v[2 + n..];
+            }
+        }
+    }
+    v
+}
+
+fn curve_nid_from_params(params: &[u8]) -> anyhow::Result<Nid> {
+    const PRIME256V1: &[u8] = &[0x06, 0x08, 0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+    const SECP384R1: &[u8] = &[0x06, 0x05, 0x2B, 0x81, 0x04, 0x00, 0x22];
+    const SECP521R1: &[u8] = &[0x06, 0x05, 0x2B, 0x81, 0x04, 0x00, 0x23];
+
+    match params {
+        PRIME256V1 => Ok(Nid::X9_62_PRIME256V1),
+        SECP384R1 => Ok(Nid::SECP384R1),
+        SECP521R1 => Ok(Nid::SECP521R1),
+        _ => Err(anyhow!("unsupported ec params")),
+    }
+}
+
+fn rsa_digest_info_prefix(nid: Nid) -> Option<&'static [u8]> {
+    match nid {
+        Nid::MD5_SHA1 => Some(&[]),
+        Nid::SHA1 => Some(&[
+            0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2B, 0x0E, 0x03, 0x02, 0x1A, 0x05, 0x00, 0x04,
+            0x14,
+        ]),
+        Nid::SHA224 => Some(&[
+            0x30, 0x2D, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x04, 0x05, 0x00, 0x04, 0x1C,
+        ]),
+        Nid::SHA256 => Some(&[
+            0x30, 0x31, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x01, 0x05, 0x00, 0x04, 0x20,
+        ]),
+        Nid::SHA384 => Some(&[
+            0x30, 0x41, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x02, 0x05, 0x00, 0x04, 0x30,
+        ]),
+        Nid::SHA512 => Some(&[
+            0x30, 0x51, 0x30, 0x0D, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+            0x03, 0x05, 0x00, 0x04, 0x40,
+        ]),
+        _ => None,
+    }
+}
+
+fn rsa_pss_params(nid: Nid) -> Option<PkcsPssParams> {
+    let (hash_alg, mgf, s_len) = match nid {
+        Nid::SHA256 => (MechanismType::SHA256, PkcsMgfType::MGF1_SHA256, 32u64),
+        Nid::SHA384 => (MechanismType::SHA384, PkcsMgfType::MGF1_SHA384, 48u64),
+        Nid::SHA512 => (MechanismType::SHA512, PkcsMgfType::MGF1_SHA512, 64u64),
+        _ => return None,
+    };
+    Some(PkcsPssParams {
+        hash_alg,
+        mgf,
+        s_len: s_len.into(),
+    })
+}
+
+/// A private key which lives in a PKCS#11 token.
+///
+/// All methods will do blocking calls to the PKCS#11 module.
+pub(crate) struct Pkcs11PrivateKey {
+    token: Arc<Pkcs11Token>,
+    handle: ObjectHandle,
+    key_id: Id,
+    key_size: usize,
+}
+
+impl Pkcs11PrivateKey {
+    #[inline]
+    pub(crate) fn size(&self) -> usize {
+        self.key_size
+    }
+
+    fn check_key_id(&self, id: Id) -> anyhow::Result<()> {
+        if self.key_id != id {
+            return Err(anyhow!("unmatched key type {}", self.key_id.as_raw()));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn rsa_decrypt(&self, padding: Padding, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.check_key_id(Id::RSA)?;
+        let mechanism = match padding {
+            Padding::PKCS1 => Mechanism::RsaPkcs,
+            Padding::NONE => Mechanism::RsaX509,
+            _ => return Err(anyhow!("unsupported rsa padding {}", padding.as_raw())),
+        };
+        self.token
+            .with_session(|s| s.decrypt(&mechanism, self.handle, data))
+    }
+
+    pub(crate) fn rsa_sign(&self, digest_nid: Nid, digest: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.check_key_id(Id::RSA)?;
+        let prefix = rsa_digest_info_prefix(digest_nid)
+            .ok_or_else(|| anyhow!("unsupported digest {}", digest_nid.as_raw()))?;
+        let mut data = Vec::with_capacity(prefix.len() + digest.len());
+        data.extend_from_slice(prefix);
+        data.extend_from_slice(digest);
+        self.token
+            .with_session(|s| s.sign(&Mechanism::RsaPkcs, self.handle, &data))
+    }
+
+    pub(crate) fn rsa_pss_sign(&self, digest_nid: Nid, digest: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.check_key_id(Id::RSA)?;
+        let params = rsa_pss_params(digest_nid)
+            .ok_or_else(|| anyhow!("unsupported digest {}", digest_nid.as_raw()))?;
+        self.token
+            .with_session(|s| s.sign(&Mechanism::RsaPkcsPss(params), self.handle, digest))
+    }
+
+    /// the returned signature will be DER encoded
+    pub(crate) fn ecdsa_sign(&self, digest: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.check_key_id(Id::EC)?;
+        let raw = self
+            .token
+            .with_session(|s| s.sign(&Mechanism::Ecdsa, self.handle, digest))?;
+        if raw.is_empty() || raw.len() % 2 != 0 {
+            return Err(anyhow!("invalid ecdsa signature length {}", raw.len()));
+        }
+        let (r, s) = raw.split_at(raw.len() / 2);
+        let r = BigNum::from_slice(r)?;
+        let s = BigNum::from_slice(s)?;
+        let sig = EcdsaSig::from_private_components(r, s)?;
+        sig.to_der().map_err(anyhow::Error::from)
+    }
+
+    pub(crate) fn ed25519_sign(&self, msg: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self.check_key_id(Id::ED25519)?;
+        self.token
+            .with_session(|s| s.sign(&Mechanism::Eddsa, self.handle, msg))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_octet_string() {
+        assert_eq!(
+            strip_der_octet_string(&[0x04, 0x02, 0x01, 0x02]),
+            &[0x01, 0x02]
+        );
+        // raw point without the octet string wrapper
+        assert_eq!(
+            strip_der_octet_string(&[0x04, 0x01, 0x02, 0x03]),
+            &[0x04, 0x01, 0x02, 0x03]
+        );
+
+        // long form length
+        let mut v = vec![0x04, 0x81, 0x85];
+        v.extend_from_slice(&[0x04; 0x85]);
+        assert_eq!(strip_der_octet_string(&v), &[0x04; 0x85]);
+        let mut v = vec![0x04, 0x82, 0x01, 0x00];
+        v.extend_from_slice(&[0x01; 0x100]);
+        assert_eq!(strip_der_octet_string(&v), &[0x01; 0x100]);
+
+        assert_eq!(strip_der_octet_string(&[0x04]), &[0x04]);
+        assert_eq!(
+            strip_der_octet_string(&[0x04, 0x82, 0x01]),
+            &[0x04, 0x82, 0x01]
+        );
+        assert_eq!(
+            strip_der_octet_string(&[0x03, 0x01, 0x00]),
+            &[0x03, 0x01, 0x00]
+        );
+    }
+
+    #[test]
+    fn curve_params() {
+        let params = [0x06, 0x08, 0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x03, 0x01, 0x07];
+        assert_eq!(
+            curve_nid_from_params(&params).unwrap(),
+            Nid::X9_62_PRIME256V1
+        );
+        let params = [0x06, 0x05, 0x2B, 0x81, 0x04, 0x00, 0x22];
+        assert_eq!(curve_nid_from_params(&params).unwrap(), Nid::SECP384R1);
+        let params = [0x06, 0x05, 0x2B, 0x81, 0x04, 0x00, 0x23];
+        assert_eq!(curve_nid_from_params(&params).unwrap(), Nid::SECP521R1);
+
+        // secp256k1
+        let params = [0x06, 0x05, 0x2B, 0x81, 0x04, 0x00, 0x0A];
+        assert!(curve_nid_from_params(&params).is_err());
+        assert!(curve_nid_from_params(&[]).is_err());
+    }
+
+    #[test]
+    fn digest_info_prefix() {
+        for (nid, digest_len) in [
+            (Nid::SHA1, 20),
+            (Nid::SHA224, 28),
+            (Nid::SHA256, 32),
+            (Nid::SHA384, 48),
+            (Nid::SHA512, 64),
+        ] {
+            let prefix = rsa_digest_info_prefix(nid).unwrap();
+            // SEQUENCE length should cover the whole DigestInfo
+            assert_eq!(prefix[0], 0x30);
+            assert_eq!(prefix[1] as usize, prefix.len() - 2 + digest_len);
+            // OCTET STRING length should be the digest length
+            assert_eq!(prefix[prefix.len() - 2], 0x04);
+            assert_eq!(prefix[prefix.len() - 1] as usize, digest_len);
+        }
+
+        // the TLS 1.0/1.1 MD5-SHA1 signature has no DigestInfo
+        assert_eq!(rsa_digest_info_prefix(Nid::MD5_SHA1).unwrap(), &[]);
+        assert!(rsa_digest_info_prefix(Nid::MD5).is_none());
+    }
+
+    #[test]
+    fn pss_params() {
+        let params = rsa_pss_params(Nid::SHA256).unwrap();
+        assert_eq!(params.hash_alg, MechanismType::SHA256);
+        assert_eq!(params.mgf, PkcsMgfType::MGF1_SHA256);
+
+        let params = rsa_pss_params(Nid::SHA512).unwrap();
+        assert_eq!(params.hash_alg, MechanismType::SHA512);
+
+        assert!(rsa_pss_params(Nid::SHA1).is_none());
+    }
+}
@@ -121,9 +121,10 @@ impl AuditorConfig {
                     .context(format!("invalid protocol portmap value for key {k}"))
             }
             "tls_cert_agent" | "tls_cert_generator" => {
-                let agent = g3_yaml::value::as_tls_cert_agent_config(v).context(format!(
-                    "invalid tls cert generator config value for key {k}"
-                ))?;
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let agent = g3_yaml::value::as_tls_cert_agent_config(v, Some(lookup_dir)).context(
+                    format!("invalid tls cert generator config value for key {k}"),
+                )?;
                 self.tls_cert_agent = Some(agent);
                 Ok(())
             }
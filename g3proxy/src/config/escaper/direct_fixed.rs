@@ -23,7 +23,9 @@ use yaml_rust::{yaml, Yaml};
 
 use g3_types::acl::{AclAction, AclNetworkRuleBuilder};
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
-use g3_types::net::{HappyEyeballsConfig, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts};
+use g3_types::net::{
+    HappyEyeballsConfig, Nat64Prefix, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts,
+};
 use g3_types::resolve::{QueryStrategy, ResolveRedirectionBuilder, ResolveStrategy};
 use g3_yaml::YamlDocPosition;
 
@@ -40,6 +42,7 @@ pub(crate) struct DirectFixedEscaperConfig {
     pub(crate) bind6: Vec<IpAddr>,
     pub(crate) no_ipv4: bool,
     pub(crate) no_ipv6: bool,
+    pub(crate) nat64_prefix: Option<Nat64Prefix>,
     pub(crate) resolver: MetricsName,
     pub(crate) resolve_strategy: ResolveStrategy,
     pub(crate) resolve_redirection: Option<ResolveRedirectionBuilder>,
@@ -63,6 +66,7 @@ impl DirectFixedEscaperConfig {
             bind6: Vec::new(),
             no_ipv4: false,
             no_ipv6: false,
+            nat64_prefix: None,
             resolver: MetricsName::default(),
             resolve_strategy: Default::default(),
             resolve_redirection: None,
@@ -172,6 +176,12 @@ impl DirectFixedEscaperConfig {
                 self.no_ipv6 = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
+            "nat64_prefix" | "nat64" => {
+                let prefix = g3_yaml::value::as_nat64_prefix(v)
+                    .context(format!("invalid NAT64 prefix value for key {k}"))?;
+                self.nat64_prefix = Some(prefix);
+                Ok(())
+            }
             "tcp_connect" => {
                 self.general.tcp_connect = g3_yaml::value::as_tcp_connect_config(v)
                     .context(format!("invalid tcp connect value for key {k}"))?;
@@ -207,6 +217,9 @@ impl DirectFixedEscaperConfig {
                 _ => {}
             }
         }
+        if self.nat64_prefix.is_some() && !self.no_ipv4 {
+            return Err(anyhow!("nat64 prefix can only be used if ipv4 is disabled"));
+        }
 
         Ok(())
     }
@@ -24,7 +24,9 @@ use yaml_rust::{yaml, Yaml};
 
 use g3_types::acl::{AclAction, AclNetworkRuleBuilder};
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
-use g3_types::net::{HappyEyeballsConfig, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts};
+use g3_types::net::{
+    HappyEyeballsConfig, Nat64Prefix, TcpKeepAliveConfig, TcpMiscSockOpts, UdpMiscSockOpts,
+};
 use g3_types::resolve::{QueryStrategy, ResolveRedirectionBuilder, ResolveStrategy};
 use g3_yaml::YamlDocPosition;
 
@@ -39,6 +41,7 @@ pub(crate) struct DirectFloatEscaperConfig {
     pub(crate) shared_logger: Option<AsciiString>,
     pub(crate) no_ipv4: bool,
     pub(crate) no_ipv6: bool,
+    pub(crate) nat64_prefix: Option<Nat64Prefix>,
     pub(crate) cache_ipv4: Option<PathBuf>,
     pub(crate) cache_ipv6: Option<PathBuf>,
     pub(crate) resolver: MetricsName,
@@ -61,6 +64,7 @@ impl DirectFloatEscaperConfig {
             shared_logger: None,
             no_ipv4: false,
             no_ipv6: false,
+            nat64_prefix: None,
             cache_ipv4: None,
             cache_ipv6: None,
             resolver: MetricsName::default(),
@@ -143,6 +147,12 @@ impl DirectFloatEscaperConfig {
                 self.no_ipv6 = g3_yaml::value::as_bool(v)?;
                 Ok(())
             }
+            "nat64_prefix" | "nat64" => {
+                let prefix = g3_yaml::value::as_nat64_prefix(v)
+                    .context(format!("invalid NAT64 prefix value for key {k}"))?;
+                self.nat64_prefix = Some(prefix);
+                Ok(())
+            }
             "cache_ipv4" => {
                 let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
                 self.cache_ipv4 = Some(
@@ -209,6 +219,9 @@ impl DirectFloatEscaperConfig {
                 _ => {}
             }
         }
+        if self.nat64_prefix.is_some() && !self.no_ipv4 {
+            return Err(anyhow!("nat64 prefix can only be used if ipv4 is disabled"));
+        }
 
         if !self.no_ipv4 && self.cache_ipv4.is_none() {
             warn!(
@@ -34,7 +34,9 @@ pub(crate) fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
                 #[cfg(target_os = "linux")]
                 "journal" => LogConfig::default_journal(crate::build::PKG_NAME),
                 "syslog" => LogConfig::default_syslog(crate::build::PKG_NAME),
+                "file" => LogConfig::default_file(crate::build::PKG_NAME),
                 "fluentd" => LogConfig::default_fluentd(crate::build::PKG_NAME),
+                "otlp" => LogConfig::default_otlp(crate::build::PKG_NAME),
                 _ => return Err(anyhow!("invalid default log config")),
             };
             unsafe {
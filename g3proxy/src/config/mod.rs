@@ -81,7 +81,7 @@ fn reload_doc(map: &yaml::Hash) -> anyhow::Result<()> {
     let conf_dir =
         g3_daemon::opts::config_dir().ok_or_else(|| anyhow!("no valid config dir has been set"))?;
     g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
-        "runtime" | "worker" | "log" | "stat" | "controller" => Ok(()),
+        "runtime" | "worker" | "log" | "stat" | "trace" | "controller" => Ok(()),
         #[cfg(feature = "geoip")]
         "geoip_db" => geoip::load(v, conf_dir),
         "escaper" => escaper::load_all(v, conf_dir),
@@ -102,6 +102,7 @@ fn load_doc(map: &yaml::Hash) -> anyhow::Result<()> {
         "worker" => g3_daemon::runtime::config::load_worker(v),
         "log" => log::load(v, conf_dir),
         "stat" => g3_daemon::stat::config::load(v, crate::build::PKG_NAME),
+        "trace" => g3_daemon::trace::config::load(v, conf_dir),
         "controller" => g3_daemon::control::config::load(v),
         #[cfg(feature = "geoip")]
         "geoip_db" => geoip::load(v, conf_dir),
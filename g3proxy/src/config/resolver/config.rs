@@ -25,7 +25,9 @@ use super::c_ares;
 use super::hickory;
 
 use super::deny_all;
+use super::domain_route;
 use super::fail_over;
+use super::hosts;
 
 pub(super) const CONFIG_KEY_RESOLVER_TYPE: &str = "type";
 pub(super) const CONFIG_KEY_RESOLVER_NAME: &str = "name";
@@ -52,7 +54,9 @@ pub(crate) enum AnyResolverConfig {
     #[cfg(feature = "hickory")]
     Hickory(hickory::HickoryResolverConfig),
     DenyAll(deny_all::DenyAllResolverConfig),
+    DomainRoute(domain_route::DomainRouteResolverConfig),
     FailOver(fail_over::FailOverResolverConfig),
+    Hosts(hosts::HostsResolverConfig),
 }
 
 macro_rules! impl_transparent0 {
@@ -64,7 +68,9 @@ macro_rules! impl_transparent0 {
                 #[cfg(feature = "hickory")]
                 AnyResolverConfig::Hickory(r) => r.$f(),
                 AnyResolverConfig::DenyAll(r) => r.$f(),
+                AnyResolverConfig::DomainRoute(r) => r.$f(),
                 AnyResolverConfig::FailOver(r) => r.$f(),
+                AnyResolverConfig::Hosts(r) => r.$f(),
             }
         }
     };
@@ -79,7 +85,9 @@ macro_rules! impl_transparent1 {
                 #[cfg(feature = "hickory")]
                 AnyResolverConfig::Hickory(r) => r.$f(p),
                 AnyResolverConfig::DenyAll(r) => r.$f(p),
+                AnyResolverConfig::DomainRoute(r) => r.$f(p),
                 AnyResolverConfig::FailOver(r) => r.$f(p),
+                AnyResolverConfig::Hosts(r) => r.$f(p),
             }
         }
     };
@@ -0,0 +1,352 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
+
+use g3_resolver::driver::domain_route::DomainRouteDriverStaticConfig;
+use g3_resolver::ResolverRuntimeConfig;
+use g3_types::metrics::MetricsName;
+use g3_yaml::YamlDocPosition;
+
+use super::{AnyResolverConfig, ResolverConfig, ResolverConfigDiffAction};
+
+const RESOLVER_CONFIG_TYPE: &str = "domain-route";
+
+#[derive(Clone, Eq, PartialEq)]
+pub(crate) struct DomainRouteResolverConfig {
+    position: Option<YamlDocPosition>,
+    name: MetricsName,
+    pub(crate) runtime: ResolverRuntimeConfig,
+    pub(crate) default_next: MetricsName,
+    next_resolvers: BTreeSet<MetricsName>,
+    pub(crate) static_conf: DomainRouteDriverStaticConfig,
+}
+
+impl DomainRouteResolverConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        DomainRouteResolverConfig {
+            position,
+            name: MetricsName::default(),
+            runtime: Default::default(),
+            default_next: MetricsName::default(),
+            next_resolvers: BTreeSet::new(),
+            static_conf: DomainRouteDriverStaticConfig::default(),
+        }
+    }
+
+    pub(crate) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut resolver = Self::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| resolver.set(k, v))?;
+
+        resolver.check()?;
+        Ok(resolver)
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_RESOLVER_TYPE => Ok(()),
+            super::CONFIG_KEY_RESOLVER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "rules" | "rule" => {
+                if let Yaml::Array(seq) = v {
+                    for (i, v) in seq.iter().enumerate() {
+                        self.add_rule(v)
+                            .context(format!("invalid value for {k}#{i}"))?;
+                    }
+                    Ok(())
+                } else {
+                    self.add_rule(v)
+                        .context(format!("invalid value for key {k}"))
+                }
+            }
+            "default" | "default_next" => {
+                self.default_next = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "negative_ttl" | "protective_cache_ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.static_conf.set_negative_ttl(ttl);
+                Ok(())
+            }
+            "graceful_stop_wait" => {
+                self.runtime.graceful_stop_wait = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "protective_query_timeout" => {
+                self.runtime.protective_query_timeout = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "dns64_prefix" | "dns64" => {
+                let prefix = g3_yaml::value::as_nat64_prefix(v)?;
+                self.runtime.dns64_prefix = Some(prefix);
+                Ok(())
+            }
+            "cache_snapshot_file" | "cache_snapshot" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, true)
+                    .context(format!("invalid file path value for key {k}"))?;
+                self.runtime.cache_snapshot_file = Some(path);
+                Ok(())
+            }
+            "cache_snapshot_interval" => {
+                self.runtime.cache_snapshot_interval = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "serve_stale_period" | "serve_stale" => {
+                self.runtime.serve_stale_period = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn add_rule(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        let Yaml::Hash(map) = v else {
+            return Err(anyhow!(
+                "yaml value type for domain route rule should be 'map'"
+            ));
+        };
+
+        let v = g3_yaml::hash_get_required(map, "next")?;
+        let next = g3_yaml::value::as_metrics_name(v)?;
+
+        let mut exact_match = Vec::new();
+        let mut child_match = Vec::new();
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "next" => Ok(()),
+            "exact_match" | "exact" => {
+                exact_match = g3_yaml::value::as_list(v, g3_yaml::value::as_domain)
+                    .context(format!("invalid domain list value for key {k}"))?;
+                Ok(())
+            }
+            "child_match" | "child" | "suffix_match" => {
+                child_match = g3_yaml::value::as_list(v, g3_yaml::value::as_domain)
+                    .context(format!("invalid domain list value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })?;
+        if exact_match.is_empty() && child_match.is_empty() {
+            return Err(anyhow!("no exact or child domain set"));
+        }
+
+        for domain in exact_match {
+            if let Some(old) = self.static_conf.add_exact_match(&domain, next.to_string()) {
+                return Err(anyhow!(
+                    "exact domain {domain} has already been routed to resolver {old}"
+                ));
+            }
+        }
+        for domain in child_match {
+            if let Some(old) = self.static_conf.add_child_match(&domain, next.to_string()) {
+                return Err(anyhow!(
+                    "child domain {domain} has already been routed to resolver {old}"
+                ));
+            }
+        }
+        self.next_resolvers.insert(next);
+        Ok(())
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.default_next.is_empty() {
+            return Err(anyhow!("no default next resolver set"));
+        }
+        if self.next_resolvers.contains(&self.name) || self.default_next.eq(&self.name) {
+            return Err(anyhow!(
+                "the next resolver should not be this resolver itself"
+            ));
+        }
+        self.runtime.check().context("invalid runtime config")?;
+
+        self.static_conf
+            .set_default_next(self.default_next.to_string());
+        self.next_resolvers.insert(self.default_next.clone());
+        Ok(())
+    }
+}
+
+impl ResolverConfig for DomainRouteResolverConfig {
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn resolver_type(&self) -> &'static str {
+        RESOLVER_CONFIG_TYPE
+    }
+
+    fn diff_action(&self, new: &AnyResolverConfig) -> ResolverConfigDiffAction {
+        let new = match new {
+            AnyResolverConfig::DomainRoute(new) => new,
+            _ => return ResolverConfigDiffAction::SpawnNew,
+        };
+
+        if self.eq(new) {
+            return ResolverConfigDiffAction::NoAction;
+        }
+
+        ResolverConfigDiffAction::Update
+    }
+
+    fn dependent_resolver(&self) -> Option<BTreeSet<MetricsName>> {
+        Some(self.next_resolvers.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse_yaml(s: &str) -> anyhow::Result<DomainRouteResolverConfig> {
+        let docs = YamlLoader::load_from_str(s).unwrap();
+        let Yaml::Hash(map) = &docs[0] else {
+            panic!("not a yaml map");
+        };
+        DomainRouteResolverConfig::parse(map, None)
+    }
+
+    #[test]
+    fn parse_ok() {
+        let config = parse_yaml(
+            r#"
+            name: route
+            type: domain_route
+            rules:
+              - next: internal
+                exact_match: www.corp.example
+                child_match:
+                  - "*.corp.example"
+                  - .lan
+              - next: other
+                exact: api.corp.example
+            default: doh
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.default_next.as_str(), "doh");
+        let next: Vec<&str> = config.next_resolvers.iter().map(|n| n.as_str()).collect();
+        assert_eq!(next, ["doh", "internal", "other"]);
+
+        let conf = &config.static_conf;
+        assert_eq!(conf.select_next("www.corp.example"), "internal");
+        assert_eq!(conf.select_next("api.corp.example"), "other");
+        assert_eq!(conf.select_next("mail.corp.example"), "internal");
+        assert_eq!(conf.select_next("corp.example"), "internal");
+        assert_eq!(conf.select_next("host.lan"), "internal");
+        assert_eq!(conf.select_next("example.net"), "doh");
+    }
+
+    #[test]
+    fn parse_duplicate() {
+        let r = parse_yaml(
+            r#"
+            name: route
+            rules:
+              - next: a
+                exact_match: www.corp.example
+              - next: b
+                exact_match: WWW.corp.example
+            default: doh
+            "#,
+        );
+        assert!(r.is_err());
+
+        let r = parse_yaml(
+            r#"
+            name: route
+            rules:
+              - next: a
+                child_match: corp.example
+              - next: b
+                child_match: "*.corp.example"
+            default: doh
+            "#,
+        );
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn parse_err() {
+        // no domain in rule
+        let r = parse_yaml(
+            r#"
+            name: route
+            rules:
+              - next: a
+            default: doh
+            "#,
+        );
+        assert!(r.is_err());
+
+        // no next in rule
+        let r = parse_yaml(
+            r#"
+            name: route
+            rules:
+              - exact_match: www.corp.example
+            default: doh
+            "#,
+        );
+        assert!(r.is_err());
+
+        // no default
+        let r = parse_yaml(
+            r#"
+            name: route
+            rules:
+              - next: a
+                exact_match: www.corp.example
+            "#,
+        );
+        assert!(r.is_err());
+
+        // route to itself
+        let r = parse_yaml(
+            r#"
+            name: route
+            rules:
+              - next: route
+                exact_match: www.corp.example
+            default: doh
+            "#,
+        );
+        assert!(r.is_err());
+        let r = parse_yaml(
+            r#"
+            name: route
+            default: route
+            "#,
+        );
+        assert!(r.is_err());
+    }
+}
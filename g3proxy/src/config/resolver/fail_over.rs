@@ -16,7 +16,7 @@
 
 use std::collections::BTreeSet;
 
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
 use yaml_rust::{yaml, Yaml};
 
 use g3_resolver::driver::fail_over::FailOverDriverStaticConfig;
@@ -100,6 +100,26 @@ impl FailOverResolverConfig {
                 self.runtime.protective_query_timeout = g3_yaml::humanize::as_duration(v)?;
                 Ok(())
             }
+            "dns64_prefix" | "dns64" => {
+                let prefix = g3_yaml::value::as_nat64_prefix(v)?;
+                self.runtime.dns64_prefix = Some(prefix);
+                Ok(())
+            }
+            "cache_snapshot_file" | "cache_snapshot" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, true)
+                    .context(format!("invalid file path value for key {k}"))?;
+                self.runtime.cache_snapshot_file = Some(path);
+                Ok(())
+            }
+            "cache_snapshot_interval" => {
+                self.runtime.cache_snapshot_interval = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "serve_stale_period" | "serve_stale" => {
+                self.runtime.serve_stale_period = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -119,6 +139,9 @@ impl FailOverResolverConfig {
                 "the primary and standby next resolver should not be the same one"
             ));
         }
+        self.runtime
+            .check()
+            .context("invalid runtime config")?;
 
         Ok(())
     }
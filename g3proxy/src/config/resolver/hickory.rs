@@ -21,7 +21,7 @@ use std::str::FromStr;
 use anyhow::{anyhow, Context};
 use yaml_rust::{yaml, Yaml};
 
-use g3_resolver::driver::hickory::HickoryDriverConfig;
+use g3_resolver::driver::hickory::{DnssecInsecureStrategy, HickoryDriverConfig};
 use g3_resolver::{AnyResolveDriverConfig, ResolverRuntimeConfig};
 use g3_types::metrics::MetricsName;
 use g3_yaml::YamlDocPosition;
@@ -150,6 +150,25 @@ impl HickoryResolverConfig {
                 self.driver.set_negative_max_ttl(ttl);
                 Ok(())
             }
+            "dnssec" => {
+                let enable = g3_yaml::value::as_bool(v)?;
+                self.driver.set_dnssec(enable);
+                Ok(())
+            }
+            "dnssec_trust_anchor" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, false)
+                    .context(format!("invalid file path value for key {k}"))?;
+                self.driver.set_dnssec_trust_anchor(path);
+                Ok(())
+            }
+            "dnssec_insecure" | "dnssec_insecure_strategy" => {
+                let s = g3_yaml::value::as_string(v)?;
+                let strategy = DnssecInsecureStrategy::from_str(&s)
+                    .map_err(|_| anyhow!("invalid dnssec insecure strategy {s}"))?;
+                self.driver.set_dnssec_insecure_strategy(strategy);
+                Ok(())
+            }
             "graceful_stop_wait" => {
                 self.runtime.graceful_stop_wait = g3_yaml::humanize::as_duration(v)?;
                 Ok(())
@@ -158,6 +177,26 @@ impl HickoryResolverConfig {
                 self.runtime.protective_query_timeout = g3_yaml::humanize::as_duration(v)?;
                 Ok(())
             }
+            "dns64_prefix" | "dns64" => {
+                let prefix = g3_yaml::value::as_nat64_prefix(v)?;
+                self.runtime.dns64_prefix = Some(prefix);
+                Ok(())
+            }
+            "cache_snapshot_file" | "cache_snapshot" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, true)
+                    .context(format!("invalid file path value for key {k}"))?;
+                self.runtime.cache_snapshot_file = Some(path);
+                Ok(())
+            }
+            "cache_snapshot_interval" => {
+                self.runtime.cache_snapshot_interval = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "serve_stale_period" | "serve_stale" => {
+                self.runtime.serve_stale_period = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -196,6 +235,9 @@ impl HickoryResolverConfig {
         if self.driver.is_unspecified() {
             return Err(anyhow!("no dns server has been set"));
         }
+        self.runtime
+            .check()
+            .context("invalid runtime config")?;
 
         Ok(())
     }
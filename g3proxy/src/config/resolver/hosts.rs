@@ -0,0 +1,231 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
+
+use g3_resolver::driver::hosts::HostsDriverStaticConfig;
+use g3_resolver::ResolverRuntimeConfig;
+use g3_types::metrics::MetricsName;
+use g3_yaml::YamlDocPosition;
+
+use super::{AnyResolverConfig, ResolverConfig, ResolverConfigDiffAction};
+
+const RESOLVER_CONFIG_TYPE: &str = "hosts";
+
+const DEFAULT_STATIC_RECORD_TTL: u32 = 300;
+
+#[derive(Clone, Eq, PartialEq)]
+pub(crate) struct HostsResolverConfig {
+    position: Option<YamlDocPosition>,
+    name: MetricsName,
+    pub(crate) runtime: ResolverRuntimeConfig,
+    pub(crate) next: Option<MetricsName>,
+    pub(crate) static_conf: HostsDriverStaticConfig,
+}
+
+impl HostsResolverConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        HostsResolverConfig {
+            position,
+            name: MetricsName::default(),
+            runtime: Default::default(),
+            next: None,
+            static_conf: HostsDriverStaticConfig::default(),
+        }
+    }
+
+    pub(crate) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut resolver = Self::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| resolver.set(k, v))?;
+
+        resolver.check()?;
+        Ok(resolver)
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_RESOLVER_TYPE => Ok(()),
+            super::CONFIG_KEY_RESOLVER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "hosts_file" | "file" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, false)
+                    .context(format!("invalid file path value for key {k}"))?;
+                self.static_conf.set_hosts_file(path);
+                Ok(())
+            }
+            "check_interval" => {
+                let interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                if interval.is_zero() {
+                    return Err(anyhow!("the check interval should not be zero"));
+                }
+                self.static_conf.set_check_interval(interval);
+                Ok(())
+            }
+            "hosts_file_ttl" | "ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.static_conf.set_hosts_file_ttl(ttl);
+                Ok(())
+            }
+            "records" | "static_records" => {
+                if let Yaml::Hash(map) = v {
+                    g3_yaml::foreach_kv(map, |domain, v| {
+                        self.add_static_record(domain, v)
+                            .context(format!("invalid static record value for domain {domain}"))
+                    })
+                } else {
+                    Err(anyhow!("invalid map value for key {k}"))
+                }
+            }
+            "next" | "next_resolver" => {
+                self.next = Some(g3_yaml::value::as_metrics_name(v)?);
+                Ok(())
+            }
+            "negative_ttl" | "protective_cache_ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.static_conf.set_negative_ttl(ttl);
+                Ok(())
+            }
+            "graceful_stop_wait" => {
+                self.runtime.graceful_stop_wait = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "protective_query_timeout" => {
+                self.runtime.protective_query_timeout = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "dns64_prefix" | "dns64" => {
+                let prefix = g3_yaml::value::as_nat64_prefix(v)?;
+                self.runtime.dns64_prefix = Some(prefix);
+                Ok(())
+            }
+            "cache_snapshot_file" | "cache_snapshot" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, true)
+                    .context(format!("invalid file path value for key {k}"))?;
+                self.runtime.cache_snapshot_file = Some(path);
+                Ok(())
+            }
+            "cache_snapshot_interval" => {
+                self.runtime.cache_snapshot_interval = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            "serve_stale_period" | "serve_stale" => {
+                self.runtime.serve_stale_period = g3_yaml::humanize::as_duration(v)?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn add_static_record(&mut self, domain: &str, v: &Yaml) -> anyhow::Result<()> {
+        let domain = g3_yaml::value::as_domain(&Yaml::String(domain.to_string()))?;
+
+        let mut ttl = DEFAULT_STATIC_RECORD_TTL;
+        let addrs = if let Yaml::Hash(map) = v {
+            let mut addrs = Vec::new();
+            g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                "addr" | "addrs" | "address" | "addresses" | "ip" | "ips" => {
+                    addrs = g3_yaml::value::as_list(v, g3_yaml::value::as_ipaddr)
+                        .context(format!("invalid ip address list value for key {k}"))?;
+                    Ok(())
+                }
+                "ttl" => {
+                    ttl = g3_yaml::value::as_u32(v)
+                        .context(format!("invalid u32 value for key {k}"))?;
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+            addrs
+        } else {
+            g3_yaml::value::as_list(v, g3_yaml::value::as_ipaddr)?
+        };
+        if addrs.is_empty() {
+            return Err(anyhow!("no ip address set"));
+        }
+
+        if !self.static_conf.add_static_record(&domain, addrs, ttl) {
+            return Err(anyhow!("duplicate static record for domain {domain}"));
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.static_conf.is_empty() {
+            return Err(anyhow!("neither hosts file nor static records is set"));
+        }
+        if let Some(next) = &self.next {
+            if next.eq(&self.name) {
+                return Err(anyhow!(
+                    "the next resolver should not be this resolver itself"
+                ));
+            }
+        }
+        self.runtime
+            .check()
+            .context("invalid runtime config")?;
+
+        Ok(())
+    }
+}
+
+impl ResolverConfig for HostsResolverConfig {
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn resolver_type(&self) -> &'static str {
+        RESOLVER_CONFIG_TYPE
+    }
+
+    fn diff_action(&self, new: &AnyResolverConfig) -> ResolverConfigDiffAction {
+        let new = match new {
+            AnyResolverConfig::Hosts(new) => new,
+            _ => return ResolverConfigDiffAction::SpawnNew,
+        };
+
+        if self.eq(new) {
+            return ResolverConfigDiffAction::NoAction;
+        }
+
+        ResolverConfigDiffAction::Update
+    }
+
+    fn dependent_resolver(&self) -> Option<BTreeSet<MetricsName>> {
+        let next = self.next.as_ref()?;
+        let mut set = BTreeSet::new();
+        set.insert(next.clone());
+        Some(set)
+    }
+}
@@ -32,7 +32,9 @@ pub(crate) mod c_ares;
 pub(crate) mod hickory;
 
 pub(crate) mod deny_all;
+pub(crate) mod domain_route;
 pub(crate) mod fail_over;
+pub(crate) mod hosts;
 
 mod config;
 
@@ -104,11 +106,21 @@ fn load_resolver(
                 .context("failed to load this DenyAll resolver")?;
             Ok(AnyResolverConfig::DenyAll(resolver))
         }
+        "domain_route" | "domainroute" => {
+            let resolver = domain_route::DomainRouteResolverConfig::parse(map, position)
+                .context("failed to load this DomainRoute resolver")?;
+            Ok(AnyResolverConfig::DomainRoute(resolver))
+        }
         "fail_over" | "failover" => {
             let resolver = fail_over::FailOverResolverConfig::parse(map, position)
                 .context("failed to load this FailOver resolver")?;
             Ok(AnyResolverConfig::FailOver(resolver))
         }
+        "hosts" | "hosts_file" => {
+            let resolver = hosts::HostsResolverConfig::parse(map, position)
+                .context("failed to load this Hosts resolver")?;
+            Ok(AnyResolverConfig::Hosts(resolver))
+        }
         _ => Err(anyhow!("unsupported resolver type {resolver_type}")),
     }
 }
@@ -29,8 +29,8 @@ use g3_types::acl::{AclExactPortRule, AclNetworkRuleBuilder};
 use g3_types::acl_set::AclDstHostRuleSetBuilder;
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::net::{
-    HttpKeepAliveConfig, HttpServerId, OpensslClientConfigBuilder, RustlsServerConfigBuilder,
-    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
+    HttpKeepAliveConfig, HttpServerId, HttpTraceContextConfig, OpensslClientConfigBuilder,
+    RustlsServerConfigBuilder, TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
 };
 use g3_yaml::YamlDocPosition;
 
@@ -97,6 +97,7 @@ pub(crate) struct HttpProxyServerConfig {
     pub(crate) untrusted_read_limit: Option<TcpSockSpeedLimitConfig>,
     pub(crate) egress_path_selection_header: Option<HeaderName>,
     pub(crate) steal_forwarded_for: bool,
+    pub(crate) trace_context: Option<HttpTraceContextConfig>,
     pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
 }
 
@@ -139,6 +140,7 @@ impl HttpProxyServerConfig {
             untrusted_read_limit: None,
             egress_path_selection_header: None,
             steal_forwarded_for: false,
+            trace_context: None,
             extra_metrics_tags: None,
         }
     }
@@ -366,6 +368,13 @@ impl HttpProxyServerConfig {
                     .context(format!("invalid boolean value for key {k}"))?;
                 Ok(())
             }
+            "trace_context" => {
+                let config = g3_yaml::value::as_http_trace_context_config(v).context(format!(
+                    "invalid http trace context config value for key {k}"
+                ))?;
+                self.trace_context = Some(config);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
@@ -452,4 +461,8 @@ impl ServerConfig for HttpProxyServerConfig {
     fn task_max_idle_count(&self) -> i32 {
         self.task_idle_max_count
     }
+
+    fn trace_context(&self) -> Option<HttpTraceContextConfig> {
+        self.trace_context
+    }
 }
@@ -25,8 +25,8 @@ use g3_io_ext::LimitedCopyConfig;
 use g3_types::acl::AclNetworkRuleBuilder;
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
 use g3_types::net::{
-    HttpForwardedHeaderType, HttpKeepAliveConfig, HttpServerId, RustlsServerConfigBuilder,
-    TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
+    HttpForwardedHeaderType, HttpKeepAliveConfig, HttpServerId, HttpTraceContextConfig,
+    RustlsServerConfigBuilder, TcpListenConfig, TcpMiscSockOpts, TcpSockSpeedLimitConfig,
 };
 use g3_types::route::HostMatch;
 use g3_yaml::YamlDocPosition;
@@ -87,6 +87,7 @@ pub(crate) struct HttpRProxyServerConfig {
     pub(crate) http_forward_upstream_keepalive: HttpKeepAliveConfig,
     pub(crate) untrusted_read_limit: Option<TcpSockSpeedLimitConfig>,
     pub(crate) append_forwarded_for: HttpForwardedHeaderType,
+    pub(crate) trace_context: Option<HttpTraceContextConfig>,
     pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
     pub(crate) hosts: HostMatch<Arc<HttpHostConfig>>,
     pub(crate) enable_tls_server: bool,
@@ -123,6 +124,7 @@ impl HttpRProxyServerConfig {
             http_forward_upstream_keepalive: Default::default(),
             untrusted_read_limit: None,
             append_forwarded_for: HttpForwardedHeaderType::default(),
+            trace_context: None,
             extra_metrics_tags: None,
             hosts: Default::default(),
             enable_tls_server: false,
@@ -292,6 +294,13 @@ impl HttpRProxyServerConfig {
                     ))?;
                 Ok(())
             }
+            "trace_context" => {
+                let config = g3_yaml::value::as_http_trace_context_config(v).context(format!(
+                    "invalid http trace context config value for key {k}"
+                ))?;
+                self.trace_context = Some(config);
+                Ok(())
+            }
             "hosts" | "sites" => {
                 self.hosts = g3_yaml::value::as_host_matched_obj(v, self.position.as_ref())
                     .context(format!(
@@ -399,4 +408,8 @@ impl ServerConfig for HttpRProxyServerConfig {
     fn task_max_idle_count(&self) -> i32 {
         self.task_idle_max_count
     }
+
+    fn trace_context(&self) -> Option<HttpTraceContextConfig> {
+        self.trace_context
+    }
 }
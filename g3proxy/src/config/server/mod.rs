@@ -27,6 +27,7 @@ use yaml_rust::{yaml, Yaml};
 use g3_daemon::config::sort_nodes_in_dependency_graph;
 use g3_io_ext::LimitedCopyConfig;
 use g3_types::metrics::MetricsName;
+use g3_types::net::HttpTraceContextConfig;
 use g3_yaml::{HybridParser, YamlDocPosition};
 
 use crate::audit::AuditHandle;
@@ -106,6 +107,9 @@ pub(crate) trait ServerConfig {
     fn task_max_idle_count(&self) -> i32 {
         1
     }
+    fn trace_context(&self) -> Option<HttpTraceContextConfig> {
+        None
+    }
 
     fn get_user_group(&self) -> Option<Arc<UserGroup>> {
         if self.user_group().is_empty() {
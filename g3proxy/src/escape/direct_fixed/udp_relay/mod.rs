@@ -14,11 +14,12 @@
  * limitations under the License.
  */
 
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 
 use g3_io_ext::{LimitedUdpRecv, LimitedUdpSend, UdpRecvHalf, UdpSendHalf};
 use g3_socket::util::AddressFamily;
+use g3_types::net::Nat64Prefix;
 
 use tokio::net::UdpSocket;
 
@@ -54,6 +55,10 @@ impl DirectFixedEscaper {
             &self.resolver_handle,
             self.config.resolve_strategy,
         );
+        if let Some(prefix) = self.config.nat64_prefix {
+            recv.set_nat64_prefix(prefix);
+            send.set_nat64_prefix(prefix);
+        }
 
         if !self.config.no_ipv4 {
             let (bind, r, w) =
@@ -123,3 +128,44 @@ impl DirectFixedEscaper {
         Ok((bind_addr, recv, send))
     }
 }
+
+/// map the IPv4 target address into the NAT64 prefix
+fn nat64_embed_addr(prefix: &Nat64Prefix, addr: SocketAddr) -> Option<SocketAddr> {
+    match addr.ip() {
+        IpAddr::V4(ip4) => Some(SocketAddr::new(IpAddr::V6(prefix.embed(ip4)), addr.port())),
+        IpAddr::V6(_) => None,
+    }
+}
+
+/// map the NAT64 peer address back to the IPv4 address that the client sent to
+fn nat64_extract_addr(prefix: &Nat64Prefix, addr: SocketAddr) -> Option<SocketAddr> {
+    match addr.ip() {
+        IpAddr::V4(_) => None,
+        IpAddr::V6(ip6) => {
+            let ip4 = prefix.extract(ip6)?;
+            Some(SocketAddr::new(IpAddr::V4(ip4), addr.port()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn nat64_addr() {
+        let prefix = Nat64Prefix::WELL_KNOWN;
+
+        let ip4_addr = SocketAddr::from_str("192.0.2.33:53").unwrap();
+        let ip6_addr = SocketAddr::from_str("[64:ff9b::c000:221]:53").unwrap();
+        assert_eq!(nat64_embed_addr(&prefix, ip4_addr), Some(ip6_addr));
+        assert_eq!(nat64_extract_addr(&prefix, ip6_addr), Some(ip4_addr));
+
+        // native IPv6 addresses should be kept as is
+        let addr = SocketAddr::from_str("[2001:db8::1]:53").unwrap();
+        assert!(nat64_embed_addr(&prefix, addr).is_none());
+        assert!(nat64_extract_addr(&prefix, addr).is_none());
+        assert!(nat64_extract_addr(&prefix, ip4_addr).is_none());
+    }
+}
@@ -26,13 +26,14 @@ use g3_io_ext::{AsyncUdpRecv, UdpRelayRemoteError, UdpRelayRemoteRecv};
     target_os = "openbsd",
 ))]
 use g3_io_ext::{RecvMsgBuf, RecvMsgHdr, UdpRelayPacket};
-use g3_types::net::UpstreamAddr;
+use g3_types::net::{Nat64Prefix, UpstreamAddr};
 
 pub(crate) struct DirectUdpRelayRemoteRecv<T> {
     inner_v4: Option<T>,
     inner_v6: Option<T>,
     bind_v4: SocketAddr,
     bind_v6: SocketAddr,
+    nat64_prefix: Option<Nat64Prefix>,
 }
 
 impl<T> DirectUdpRelayRemoteRecv<T> {
@@ -42,6 +43,7 @@ impl<T> DirectUdpRelayRemoteRecv<T> {
             inner_v6: None,
             bind_v4: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
             bind_v6: SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
+            nat64_prefix: None,
         }
     }
 }
@@ -60,6 +62,18 @@ where
         self.bind_v6 = bind;
     }
 
+    /// report packets from the NAT64 addresses as from the original IPv4 addresses
+    pub(crate) fn set_nat64_prefix(&mut self, prefix: Nat64Prefix) {
+        self.nat64_prefix = Some(prefix);
+    }
+
+    fn nat64_extract(&self, addr: SocketAddr) -> SocketAddr {
+        self.nat64_prefix
+            .as_ref()
+            .and_then(|prefix| super::nat64_extract_addr(prefix, addr))
+            .unwrap_or(addr)
+    }
+
     fn poll_recv_packet(
         &mut self,
         cx: &mut Context<'_>,
@@ -145,6 +159,7 @@ where
         buf: &mut [u8],
     ) -> Poll<Result<(usize, usize, UpstreamAddr), UdpRelayRemoteError>> {
         let (off, nr, addr) = ready!(self.poll_recv_packet(cx, buf))?;
+        let addr = self.nat64_extract(addr);
         Poll::Ready(Ok((off, nr, UpstreamAddr::from(addr))))
     }
 
@@ -159,6 +174,34 @@ where
         &mut self,
         cx: &mut Context<'_>,
         packets: &mut [UdpRelayPacket],
+    ) -> Poll<Result<usize, UdpRelayRemoteError>> {
+        let count = ready!(self.poll_recv_packets_inner(cx, packets))?;
+        if self.nat64_prefix.is_some() {
+            for p in packets.iter_mut().take(count) {
+                if let Ok(addr) = SocketAddr::try_from(p.upstream()) {
+                    p.set_upstream(UpstreamAddr::from(self.nat64_extract(addr)));
+                }
+            }
+        }
+        Poll::Ready(Ok(count))
+    }
+}
+
+impl<T> DirectUdpRelayRemoteRecv<T>
+where
+    T: AsyncUdpRecv + Send,
+{
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd",
+    ))]
+    fn poll_recv_packets_inner(
+        &mut self,
+        cx: &mut Context<'_>,
+        packets: &mut [UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
         match (&mut self.inner_v4, &mut self.inner_v6) {
             (Some(inner_v4), Some(inner_v6)) => {
@@ -30,7 +30,7 @@ use g3_io_ext::{AsyncUdpSend, UdpRelayRemoteError, UdpRelayRemoteSend};
 use g3_io_ext::{SendMsgHdr, UdpRelayPacket};
 use g3_resolver::{ResolveError, ResolveLocalError};
 use g3_types::acl::{AclAction, AclNetworkRule};
-use g3_types::net::{Host, UpstreamAddr};
+use g3_types::net::{Host, Nat64Prefix, UpstreamAddr};
 use g3_types::resolve::ResolveStrategy;
 
 use super::DirectFixedEscaperStats;
@@ -52,6 +52,7 @@ pub(crate) struct DirectUdpRelayRemoteSend<T> {
     resolve_retry_domain: Option<String>,
     resolved_port: u16,
     resolved_ip: Option<IpAddr>,
+    nat64_prefix: Option<Nat64Prefix>,
 }
 
 impl<T> DirectUdpRelayRemoteSend<T> {
@@ -77,6 +78,7 @@ impl<T> DirectUdpRelayRemoteSend<T> {
             resolve_retry_domain: None,
             resolved_port: 0,
             resolved_ip: None,
+            nat64_prefix: None,
         }
     }
 }
@@ -95,6 +97,11 @@ where
         self.bind_v6 = bind;
     }
 
+    /// send IPv4 packets through the IPv6 socket with the NAT64 prefix
+    pub(crate) fn set_nat64_prefix(&mut self, prefix: Nat64Prefix) {
+        self.nat64_prefix = Some(prefix);
+    }
+
     fn poll_send_packet(
         &mut self,
         cx: &mut Context<'_>,
@@ -218,13 +225,41 @@ where
         Ok(())
     }
 
+    /// check the IPv4 target address, and return the NAT64 translated address if needed
+    fn check_egress_ipv4(
+        &mut self,
+        to_addr: SocketAddr,
+    ) -> Result<Option<SocketAddr>, UdpRelayRemoteError> {
+        let Some(to6_addr) = self
+            .nat64_prefix
+            .as_ref()
+            .and_then(|prefix| super::nat64_embed_addr(prefix, to_addr))
+        else {
+            self.check_egress_ip(to_addr)?;
+            return Ok(None);
+        };
+
+        let to_ip = to_addr.ip();
+        if self.checked_egress_ip != Some(to_ip) {
+            // both the original and the translated address should be checked
+            let (_, action) = self.egress_net_filter.check(to_ip);
+            self.handle_udp_target_ip_acl_action(action, to_addr)?;
+            let (_, action) = self.egress_net_filter.check(to6_addr.ip());
+            self.handle_udp_target_ip_acl_action(action, to6_addr)?;
+            self.checked_egress_ip = Some(to_ip);
+        }
+        Ok(Some(to6_addr))
+    }
+
     fn poll_send_v4_packet(
         &mut self,
         cx: &mut Context<'_>,
         buf: &[u8],
         to: SocketAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
-        self.check_egress_ip(to)?;
+        if let Some(to6) = self.check_egress_ipv4(to)? {
+            return self.poll_send_v6_checked(cx, buf, to6);
+        }
         if let Some(inner) = &mut self.inner_v4 {
             let nw = ready!(inner.poll_send_to(cx, buf, to))
                 .map_err(|e| UdpRelayRemoteError::SendFailed(self.bind_v4, to, e))?;
@@ -249,6 +284,15 @@ where
         to: SocketAddr,
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
         self.check_egress_ip(to)?;
+        self.poll_send_v6_checked(cx, buf, to)
+    }
+
+    fn poll_send_v6_checked(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+        to: SocketAddr,
+    ) -> Poll<Result<usize, UdpRelayRemoteError>> {
         if let Some(inner) = &mut self.inner_v6 {
             let nw = ready!(inner.poll_send_to(cx, buf, to))
                 .map_err(|e| UdpRelayRemoteError::SendFailed(self.bind_v6, to, e))?;
@@ -276,6 +320,7 @@ where
     fn poll_send_packets(
         inner: &mut T,
         bind_addr: SocketAddr,
+        nat64_prefix: Option<&Nat64Prefix>,
         cx: &mut Context<'_>,
         packets: &[UdpRelayPacket],
     ) -> Poll<Result<usize, UdpRelayRemoteError>> {
@@ -284,7 +329,12 @@ where
         let msgs: Vec<SendMsgHdr<1>> = packets
             .iter()
             .map(|p| {
-                let addr = SocketAddr::try_from(p.upstream()).unwrap();
+                let mut addr = SocketAddr::try_from(p.upstream()).unwrap();
+                if let Some(prefix) = nat64_prefix {
+                    if let Some(addr6) = super::nat64_embed_addr(prefix, addr) {
+                        addr = addr6;
+                    }
+                }
                 SendMsgHdr {
                     iov: [IoSlice::new(p.payload())],
                     addr: Some(addr),
@@ -347,7 +397,7 @@ where
                     };
 
                     if let Err(e) =
-                        self.check_egress_ip(SocketAddr::new(IpAddr::V4(*ip4), ups.port()))
+                        self.check_egress_ipv4(SocketAddr::new(IpAddr::V4(*ip4), ups.port()))
                     {
                         if count == 0 {
                             return Poll::Ready(Err(e));
@@ -359,8 +409,20 @@ where
                     count += 1;
                 }
 
-                if let Some(inner) = &mut self.inner_v4 {
-                    Self::poll_send_packets(inner, self.bind_v4, cx, &packets[0..count])
+                if self.nat64_prefix.is_some() {
+                    if let Some(inner) = &mut self.inner_v6 {
+                        Self::poll_send_packets(
+                            inner,
+                            self.bind_v6,
+                            self.nat64_prefix.as_ref(),
+                            cx,
+                            &packets[0..count],
+                        )
+                    } else {
+                        Poll::Ready(Err(UdpRelayRemoteError::AddressNotSupported))
+                    }
+                } else if let Some(inner) = &mut self.inner_v4 {
+                    Self::poll_send_packets(inner, self.bind_v4, None, cx, &packets[0..count])
                 } else {
                     Poll::Ready(Err(UdpRelayRemoteError::AddressNotSupported))
                 }
@@ -387,7 +449,7 @@ where
                 }
 
                 if let Some(inner) = &mut self.inner_v6 {
-                    Self::poll_send_packets(inner, self.bind_v6, cx, &packets[0..count])
+                    Self::poll_send_packets(inner, self.bind_v6, None, cx, &packets[0..count])
                 } else {
                     Poll::Ready(Err(UdpRelayRemoteError::AddressNotSupported))
                 }
@@ -311,6 +311,15 @@ impl DirectFloatEscaper {
         }
     }
 
+    /// map the IPv4 address into the NAT64 prefix if set
+    fn nat64_translate(&self, ip: IpAddr) -> Option<IpAddr> {
+        let prefix = self.config.nat64_prefix?;
+        match ip {
+            IpAddr::V4(ip4) => Some(IpAddr::V6(prefix.embed(ip4))),
+            IpAddr::V6(_) => None,
+        }
+    }
+
     fn get_resolve_strategy(&self, task_notes: &ServerTaskNotes) -> ResolveStrategy {
         if let Some(user_ctx) = task_notes.user_ctx() {
             if let Some(rs) = user_ctx.resolve_strategy() {
@@ -103,6 +103,22 @@ impl DirectFloatEscaper {
         Ok((sock, bind))
     }
 
+    fn translate_nat64_ip(
+        &self,
+        ip: IpAddr,
+        task_notes: &ServerTaskNotes,
+    ) -> Result<IpAddr, TcpConnectError> {
+        match self.nat64_translate(ip) {
+            Some(ip6) => {
+                // the original IPv4 address should also be checked
+                let (_, action) = self.egress_net_filter.check(ip);
+                self.handle_tcp_target_ip_acl_action(action, task_notes)?;
+                Ok(ip6)
+            }
+            None => Ok(ip),
+        }
+    }
+
     async fn fixed_try_connect(
         &self,
         peer_ip: IpAddr,
@@ -112,6 +128,7 @@ impl DirectFloatEscaper {
         tcp_notes: &mut TcpConnectTaskNotes,
         task_notes: &ServerTaskNotes,
     ) -> Result<(TcpStream, DirectFloatBindIp), TcpConnectError> {
+        let peer_ip = self.translate_nat64_ip(peer_ip, task_notes)?;
         let (sock, bind) = self.prepare_connect_socket(
             peer_ip,
             tcp_notes.bind,
@@ -181,12 +198,23 @@ impl DirectFloatEscaper {
         task_notes: &ServerTaskNotes,
     ) -> Result<(TcpStream, DirectFloatBindIp), TcpConnectError> {
         let max_tries_each_family = tcp_connect_config.max_tries();
-        let mut ips = resolver_job
+        let resolve_start = Instant::now();
+        let mut ips = match resolver_job
             .get_r1_or_first(
                 self.config.happy_eyeballs.resolution_delay(),
                 max_tries_each_family,
             )
-            .await?;
+            .await
+        {
+            Ok(ips) => {
+                task_notes.add_trace_phase("resolve", resolve_start, None);
+                ips
+            }
+            Err(e) => {
+                task_notes.add_trace_phase("resolve", resolve_start, Some(e.to_string()));
+                return Err(TcpConnectError::ResolveFailed(e));
+            }
+        };
         let port = tcp_notes.upstream.port();
 
         let mut c_set = JoinSet::new();
@@ -350,7 +378,8 @@ impl DirectFloatEscaper {
             (self.config.tcp_keepalive, self.config.tcp_misc_opts)
         };
 
-        match tcp_notes.upstream.host() {
+        let connect_start = Instant::now();
+        let r = match tcp_notes.upstream.host() {
             Host::Ip(ip) => {
                 self.fixed_try_connect(
                     *ip,
@@ -376,7 +405,10 @@ impl DirectFloatEscaper {
                 )
                 .await
             }
-        }
+        };
+        let error = r.as_ref().err().map(|e| e.to_string());
+        task_notes.add_trace_phase("connect", connect_start, error);
+        r
     }
 
     pub(super) async fn tcp_connect_to_again<'a>(
@@ -18,6 +18,7 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use tokio::net::tcp;
+use tokio::time::Instant;
 
 use g3_daemon::stat::remote::{
     ArcTcpConnectionTaskRemoteStats, TcpConnectionTaskRemoteStatsWrapper,
@@ -78,10 +79,15 @@ impl DirectFloatEscaper {
         )
         .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
 
+        let handshake_start = Instant::now();
         match tokio::time::timeout(tls_config.handshake_timeout, connector.connect()).await {
-            Ok(Ok(stream)) => Ok((stream, bind)),
+            Ok(Ok(stream)) => {
+                task_notes.add_trace_phase("tls_handshake", handshake_start, None);
+                Ok((stream, bind))
+            }
             Ok(Err(e)) => {
                 let e = anyhow::Error::new(e);
+                task_notes.add_trace_phase("tls_handshake", handshake_start, Some(e.to_string()));
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
@@ -94,6 +100,7 @@ impl DirectFloatEscaper {
             }
             Err(_) => {
                 let e = anyhow!("upstream tls handshake timed out");
+                task_notes.add_trace_phase("tls_handshake", handshake_start, Some(e.to_string()));
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
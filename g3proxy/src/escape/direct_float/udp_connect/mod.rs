@@ -71,7 +71,7 @@ impl DirectFloatEscaper {
             .upstream
             .as_ref()
             .ok_or(UdpConnectError::NoUpstreamSupplied)?;
-        let peer_addr = self
+        let mut peer_addr = self
             .select_upstream_addr(upstream, self.get_resolve_strategy(task_notes), task_notes)
             .await?;
         udp_notes.next = Some(peer_addr);
@@ -79,6 +79,11 @@ impl DirectFloatEscaper {
         let (_, action) = self.egress_net_filter.check(peer_addr.ip());
         self.handle_udp_target_ip_acl_action(action, task_notes)?;
 
+        if let Some(ip6) = self.nat64_translate(peer_addr.ip()) {
+            peer_addr.set_ip(ip6);
+            udp_notes.next = Some(peer_addr);
+        }
+
         let family = AddressFamily::from(&peer_addr);
         let bind = self
             .select_bind(family, task_notes)
@@ -50,6 +50,10 @@ impl DirectFloatEscaper {
             &self.resolver_handle,
             self.config.resolve_strategy,
         );
+        if let Some(prefix) = self.config.nat64_prefix {
+            recv.set_nat64_prefix(prefix);
+            send.set_nat64_prefix(prefix);
+        }
 
         if !self.config.no_ipv4 {
             let (bind, r, w) =
@@ -75,6 +75,7 @@ impl ProxyFloatHttpPeer {
         tcp_notes.duration = instant_now.elapsed();
         match ret {
             Ok(Ok(ups_stream)) => {
+                task_notes.add_trace_phase("connect", instant_now, None);
                 let local_addr = ups_stream
                     .local_addr()
                     .map_err(TcpConnectError::SetupSocketFailed)?;
@@ -82,6 +83,7 @@ impl ProxyFloatHttpPeer {
                 Ok(ups_stream)
             }
             Ok(Err(e)) => {
+                task_notes.add_trace_phase("connect", instant_now, Some(e.to_string()));
                 EscapeLogForTcpConnect {
                     tcp_notes,
                     task_id: &task_notes.id,
@@ -91,6 +93,7 @@ impl ProxyFloatHttpPeer {
             }
             Err(_) => {
                 let e = TcpConnectError::TimeoutByRule;
+                task_notes.add_trace_phase("connect", instant_now, Some(e.to_string()));
                 EscapeLogForTcpConnect {
                     tcp_notes,
                     task_id: &task_notes.id,
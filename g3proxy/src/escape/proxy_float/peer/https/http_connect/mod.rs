@@ -18,6 +18,7 @@ use std::sync::Arc;
 
 use anyhow::anyhow;
 use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::time::Instant;
 
 use g3_daemon::stat::remote::{
     ArcTcpConnectionTaskRemoteStats, TcpConnectionTaskRemoteStatsWrapper,
@@ -121,10 +122,15 @@ impl ProxyFloatHttpsPeer {
         )
         .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
 
+        let handshake_start = Instant::now();
         match tokio::time::timeout(tls_config.handshake_timeout, connector.connect()).await {
-            Ok(Ok(stream)) => Ok(stream),
+            Ok(Ok(stream)) => {
+                task_notes.add_trace_phase("tls_handshake", handshake_start, None);
+                Ok(stream)
+            }
             Ok(Err(e)) => {
                 let e = anyhow::Error::new(e);
+                task_notes.add_trace_phase("tls_handshake", handshake_start, Some(e.to_string()));
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
@@ -137,6 +143,7 @@ impl ProxyFloatHttpsPeer {
             }
             Err(_) => {
                 let e = anyhow!("upstream tls handshake timed out");
+                task_notes.add_trace_phase("tls_handshake", handshake_start, Some(e.to_string()));
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
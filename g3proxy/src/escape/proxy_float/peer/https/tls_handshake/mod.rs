@@ -16,6 +16,7 @@
 
 use anyhow::anyhow;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
 
 use g3_io_ext::AggregatedIo;
 use g3_openssl::SslConnector;
@@ -48,13 +49,20 @@ impl ProxyFloatHttpsPeer {
         )
         .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
 
+        let handshake_start = Instant::now();
         match tokio::time::timeout(self.tls_config.handshake_timeout, connector.connect()).await {
             Ok(Ok(stream)) => {
+                task_notes.add_trace_phase("peer_tls_handshake", handshake_start, None);
                 let (r, w) = tokio::io::split(stream);
                 Ok((r, w))
             }
             Ok(Err(e)) => {
                 let e = anyhow::Error::new(e);
+                task_notes.add_trace_phase(
+                    "peer_tls_handshake",
+                    handshake_start,
+                    Some(e.to_string()),
+                );
                 let tls_peer = UpstreamAddr::from_ip_and_port(self.addr.ip(), self.addr.port());
                 EscapeLogForTlsHandshake {
                     tcp_notes,
@@ -69,6 +77,11 @@ impl ProxyFloatHttpsPeer {
             Err(_) => {
                 let tls_peer = UpstreamAddr::from_ip_and_port(self.addr.ip(), self.addr.port());
                 let e = anyhow!("peer tls handshake timed out");
+                task_notes.add_trace_phase(
+                    "peer_tls_handshake",
+                    handshake_start,
+                    Some(e.to_string()),
+                );
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
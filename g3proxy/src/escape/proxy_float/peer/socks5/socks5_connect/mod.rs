@@ -22,6 +22,7 @@ use anyhow::anyhow;
 use tokio::io::AsyncReadExt;
 use tokio::net::{tcp, UdpSocket};
 use tokio::sync::oneshot;
+use tokio::time::Instant;
 
 use g3_daemon::stat::remote::{
     ArcTcpConnectionTaskRemoteStats, TcpConnectionTaskRemoteStatsWrapper,
@@ -236,10 +237,15 @@ impl ProxyFloatSocks5Peer {
         )
         .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
 
+        let handshake_start = Instant::now();
         match tokio::time::timeout(tls_config.handshake_timeout, connector.connect()).await {
-            Ok(Ok(stream)) => Ok(stream),
+            Ok(Ok(stream)) => {
+                task_notes.add_trace_phase("tls_handshake", handshake_start, None);
+                Ok(stream)
+            }
             Ok(Err(e)) => {
                 let e = anyhow::Error::new(e);
+                task_notes.add_trace_phase("tls_handshake", handshake_start, Some(e.to_string()));
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
@@ -252,6 +258,7 @@ impl ProxyFloatSocks5Peer {
             }
             Err(_) => {
                 let e = anyhow!("upstream tls handshake timed out");
+                task_notes.add_trace_phase("tls_handshake", handshake_start, Some(e.to_string()));
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
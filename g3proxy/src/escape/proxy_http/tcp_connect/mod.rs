@@ -129,12 +129,23 @@ impl ProxyHttpEscaper {
         task_notes: &ServerTaskNotes,
     ) -> Result<TcpStream, TcpConnectError> {
         let max_tries_each_family = self.config.general.tcp_connect.max_tries();
-        let mut ips = resolver_job
+        let resolve_start = Instant::now();
+        let mut ips = match resolver_job
             .get_r1_or_first(
                 self.config.happy_eyeballs.resolution_delay(),
                 max_tries_each_family,
             )
-            .await?;
+            .await
+        {
+            Ok(ips) => {
+                task_notes.add_trace_phase("resolve", resolve_start, None);
+                ips
+            }
+            Err(e) => {
+                task_notes.add_trace_phase("resolve", resolve_start, Some(e.to_string()));
+                return Err(TcpConnectError::ResolveFailed(e));
+            }
+        };
 
         let mut c_set = JoinSet::new();
 
@@ -270,7 +281,8 @@ impl ProxyHttpEscaper {
     ) -> Result<TcpStream, TcpConnectError> {
         let peer_proxy = self.get_next_proxy(task_notes, tcp_notes.upstream.host());
 
-        match peer_proxy.host() {
+        let connect_start = Instant::now();
+        let r = match peer_proxy.host() {
             Host::Ip(ip) => {
                 self.fixed_try_connect(
                     SocketAddr::new(*ip, peer_proxy.port()),
@@ -285,7 +297,10 @@ impl ProxyHttpEscaper {
                 self.happy_try_connect(resolver_job, peer_proxy.port(), tcp_notes, task_notes)
                     .await
             }
-        }
+        };
+        let error = r.as_ref().err().map(|e| e.to_string());
+        task_notes.add_trace_phase("connect", connect_start, error);
+        r
     }
 
     pub(super) async fn tcp_new_connection<'a>(
@@ -16,6 +16,7 @@
 
 use anyhow::anyhow;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
 
 use g3_io_ext::AggregatedIo;
 use g3_openssl::SslConnector;
@@ -47,13 +48,20 @@ impl ProxyHttpsEscaper {
         )
         .map_err(|e| TcpConnectError::InternalTlsClientError(anyhow::Error::new(e)))?;
 
+        let handshake_start = Instant::now();
         match tokio::time::timeout(self.tls_config.handshake_timeout, connector.connect()).await {
             Ok(Ok(stream)) => {
+                task_notes.add_trace_phase("peer_tls_handshake", handshake_start, None);
                 let (r, w) = tokio::io::split(stream);
                 Ok((r, w))
             }
             Ok(Err(e)) => {
                 let e = anyhow::Error::new(e);
+                task_notes.add_trace_phase(
+                    "peer_tls_handshake",
+                    handshake_start,
+                    Some(e.to_string()),
+                );
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
@@ -66,6 +74,11 @@ impl ProxyHttpsEscaper {
             }
             Err(_) => {
                 let e = anyhow!("peer tls handshake timed out");
+                task_notes.add_trace_phase(
+                    "peer_tls_handshake",
+                    handshake_start,
+                    Some(e.to_string()),
+                );
                 EscapeLogForTlsHandshake {
                     tcp_notes,
                     task_id: &task_notes.id,
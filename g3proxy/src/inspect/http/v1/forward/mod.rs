@@ -43,7 +43,7 @@ use super::{HttpRequest, HttpRequestIo, HttpResponseIo};
 use crate::config::server::ServerConfig;
 use crate::inspect::StreamInspectContext;
 use crate::module::http_forward::HttpProxyClientResponse;
-use crate::serve::{ServerIdleChecker, ServerTaskError, ServerTaskResult};
+use crate::serve::{ServerIdleChecker, ServerTaskError, ServerTaskResult, ServerTaskTrace};
 
 mod adaptation;
 
@@ -54,6 +54,9 @@ macro_rules! intercept_log {
             "task_id" => LtUuid($obj.ctx.server_task_id()),
             "depth" => $obj.ctx.inspection_depth,
             "request_id" => $obj.req_id,
+            "trace_id" => $obj.trace.map(|t| t.trace_id()),
+            "span_id" => $obj.trace.map(|t| t.span_id()),
+            "parent_span_id" => $obj.trace.and_then(|t| t.parent_span_id()),
             "received_at" => LtDateTime(&$obj.http_notes.receive_datetime),
             "method" => LtHttpMethod(&$obj.req.method),
             "uri" => LtHttpUri::new(&$obj.req.uri, $obj.ctx.log_uri_max_chars()),
@@ -125,6 +128,7 @@ pub(super) struct H1ForwardTask<'a, SC: ServerConfig> {
     ctx: StreamInspectContext<SC>,
     req: &'a HttpTransparentRequest,
     req_id: usize,
+    trace: Option<&'a ServerTaskTrace>,
     send_error_response: bool,
     should_close: bool,
     http_notes: HttpForwardTaskNotes,
@@ -142,6 +146,7 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
             ctx,
             req: &req.inner,
             req_id,
+            trace: req.trace.as_ref(),
             send_error_response: true,
             should_close,
             http_notes,
@@ -153,6 +158,24 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         self.should_close
     }
 
+    fn add_trace_phase(&self, name: &'static str, start: Instant, error: Option<String>) {
+        if let Some(trace) = self.trace {
+            trace.add_phase(name, start, error);
+        }
+    }
+
+    fn export_trace(&self, e: &ServerTaskError) {
+        if let Some(trace) = self.trace {
+            trace.export_with(
+                "HttpForward",
+                self.ctx.server_task_id(),
+                self.ctx.task_notes.client_addr,
+                self.ctx.raw_user_name(),
+                e,
+            );
+        }
+    }
+
     async fn reply_task_err<CW>(&mut self, e: &ServerTaskError, clt_w: &mut CW)
     where
         CW: AsyncWrite + Unpin,
@@ -190,8 +213,10 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
                 self.reply_task_err(&e, &mut rsp_io.clt_w).await;
             }
             intercept_log!(self, "{e}");
+            self.export_trace(&e);
         } else {
             intercept_log!(self, "ok");
+            self.export_trace(&ServerTaskError::Finished);
         }
     }
 
@@ -206,6 +231,7 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         UR: AsyncRead + Unpin,
         UW: AsyncWrite + Send + Unpin,
     {
+        let adaptation_start = Instant::now();
         let adapter = match reqmod_client
             .h1_adapter(
                 self.ctx.server_config.limited_copy_config(),
@@ -223,12 +249,14 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
                 adapter
             }
             Err(e) => {
+                self.add_trace_phase("icap_reqmod", adaptation_start, Some(e.to_string()));
                 if reqmod_client.bypass() {
                     self.forward_with_io(req_io, rsp_io).await;
                 } else {
                     let e = ServerTaskError::InternalAdapterError(e);
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                     intercept_log!(self, "{e:?}");
+                    self.export_trace(&e);
                 }
                 return;
             }
@@ -238,6 +266,11 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         let r = self
             .run_with_adaptation(req_io, rsp_io, adapter, &mut adaptation_state)
             .await;
+        self.add_trace_phase(
+            "icap_reqmod",
+            adaptation_start,
+            r.as_ref().err().map(|e| e.to_string()),
+        );
 
         if let Some(dur) = adaptation_state.dur_ups_send_header {
             self.http_notes.dur_req_send_hdr = dur;
@@ -252,12 +285,14 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         match r {
             Ok(_) => {
                 intercept_log!(self, "ok");
+                self.export_trace(&ServerTaskError::Finished);
             }
             Err(e) => {
                 if self.send_error_response {
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                 }
                 intercept_log!(self, "{e}");
+                self.export_trace(&e);
             }
         }
     }
@@ -280,12 +315,14 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         match r {
             Ok(_) => {
                 intercept_log!(self, "ok");
+                self.export_trace(&ServerTaskError::Finished);
             }
             Err(e) => {
                 if self.send_error_response {
                     self.reply_task_err(&e, &mut rsp_io.clt_w).await;
                 }
                 intercept_log!(self, "{e}");
+                self.export_trace(&e);
             }
         }
     }
@@ -642,6 +679,7 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
         self.http_notes.mark_rsp_recv_hdr();
 
         if let Some(respmod) = self.ctx.audit_handle.icap_respmod_client() {
+            let adaptation_start = Instant::now();
             match respmod
                 .h1_adapter(
                     self.ctx.server_config.limited_copy_config(),
@@ -663,6 +701,11 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
                     let r = self
                         .send_response_with_adaptation(rsp, rsp_io, adapter, &mut adaptation_state)
                         .await;
+                    self.add_trace_phase(
+                        "icap_respmod",
+                        adaptation_start,
+                        r.as_ref().err().map(|e| e.to_string()),
+                    );
                     if !adaptation_state.clt_write_finished || !adaptation_state.ups_read_finished {
                         self.should_close = true;
                     }
@@ -673,6 +716,7 @@ impl<'a, SC: ServerConfig> H1ForwardTask<'a, SC> {
                     return r;
                 }
                 Err(e) => {
+                    self.add_trace_phase("icap_respmod", adaptation_start, Some(e.to_string()));
                     if !respmod.bypass() {
                         return Err(ServerTaskError::InternalAdapterError(e));
                     }
@@ -17,6 +17,7 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::sync::mpsc;
@@ -28,12 +29,14 @@ use g3_io_ext::LimitedBufReadExt;
 use super::{H1InterceptionError, HttpRequestIo, PipelineStats};
 use crate::config::server::ServerConfig;
 use crate::inspect::StreamInspectContext;
+use crate::serve::ServerTaskTrace;
 
 pub(crate) struct HttpRequest {
     pub(crate) inner: HttpTransparentRequest,
     pub(crate) time_received: Instant,
     pub(crate) datetime_received: DateTime<Utc>,
     pub(crate) dur_req_send_hdr: Duration,
+    pub(crate) trace: Option<ServerTaskTrace>,
 }
 
 pub(crate) enum HttpRecvRequest<R: AsyncRead, W: AsyncWrite> {
@@ -160,10 +163,24 @@ where
                 )
                 .await
                 {
-                    Ok(Ok((mut req, head_bytes))) => {
+                    Ok(Ok((mut req, mut head_bytes))) => {
                         let datetime_received = Utc::now();
                         let time_received = Instant::now();
 
+                        let mut trace = None;
+                        if let Some(trace_config) = self.ctx.server_config.trace_context() {
+                            trace = ServerTaskTrace::from_request_headers(
+                                &trace_config,
+                                self.ctx.server_config.name(),
+                                &mut req.end_to_end_headers,
+                                time_received,
+                            );
+                            if trace.is_some() && trace_config.inject() {
+                                // the traceparent header has been changed
+                                head_bytes = Bytes::from(req.serialize_for_origin());
+                            }
+                        }
+
                         if self.ctx.server_offline() {
                             // According to https://datatracker.ietf.org/doc/html/rfc7230#section-6.3.2
                             // A client that pipelines requests SHOULD retry unanswered requests if
@@ -180,6 +197,7 @@ where
                                     time_received,
                                     datetime_received,
                                     dur_req_send_hdr: Duration::ZERO,
+                                    trace,
                                 },
                                 io,
                                 io_sender.clone(),
@@ -201,6 +219,7 @@ where
                                         time_received,
                                         datetime_received,
                                         dur_req_send_hdr,
+                                        trace,
                                     })
                                 } else {
                                     HttpRecvRequest::RequestWithIO(
@@ -209,6 +228,7 @@ where
                                             time_received,
                                             datetime_received,
                                             dur_req_send_hdr,
+                                            trace,
                                         },
                                         io,
                                         io_sender.clone(),
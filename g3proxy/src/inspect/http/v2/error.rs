@@ -123,6 +123,30 @@ pub(crate) enum H2StreamTransferError {
 }
 
 impl H2StreamTransferError {
+    pub(super) fn brief(&self) -> &'static str {
+        match self {
+            H2StreamTransferError::InternalServerError(_) => "InternalServerError",
+            H2StreamTransferError::InternalAdapterError(_) => "InternalAdapterError",
+            H2StreamTransferError::UpstreamStreamOpenFailed(_) => "UpstreamStreamOpenFailed",
+            H2StreamTransferError::UpstreamStreamOpenTimeout => "UpstreamStreamOpenTimeout",
+            H2StreamTransferError::RequestHeadSendFailed(_) => "RequestHeadSendFailed",
+            H2StreamTransferError::InvalidHostHeader => "InvalidHostHeader",
+            H2StreamTransferError::ResponseHeadRecvFailed(_) => "ResponseHeadRecvFailed",
+            H2StreamTransferError::ResponseHeadRecvTimeout => "ResponseHeadRecvTimeout",
+            H2StreamTransferError::ResponseHeadSendFailed(_) => "ResponseHeadSendFailed",
+            H2StreamTransferError::RequestBodyTransferFailed(_) => "RequestBodyTransferFailed",
+            H2StreamTransferError::ResponseBodyTransferFailed(_) => "ResponseBodyTransferFailed",
+            H2StreamTransferError::CanceledAsUserBlocked => "CanceledAsUserBlocked",
+            H2StreamTransferError::CanceledAsServerQuit => "CanceledAsServerQuit",
+            H2StreamTransferError::HttpClientReadIdle => "HttpClientReadIdle",
+            H2StreamTransferError::HttpClientWriteIdle => "HttpClientWriteIdle",
+            H2StreamTransferError::HttpUpstreamReadIdle => "HttpUpstreamReadIdle",
+            H2StreamTransferError::HttpUpstreamWriteIdle => "HttpUpstreamWriteIdle",
+            H2StreamTransferError::Idle(_, _) => "Idle",
+            H2StreamTransferError::PushWaitError(_) => "PushWaitError",
+        }
+    }
+
     pub(super) fn build_reply(&self) -> Option<Response<()>> {
         let status_code = match self {
             H2StreamTransferError::UpstreamStreamOpenFailed(_)
@@ -41,7 +41,7 @@ use g3_types::net::HttpHeaderMap;
 use super::{H2BodyTransfer, H2ConcurrencyStats, H2StreamTransferError};
 use crate::config::server::ServerConfig;
 use crate::inspect::StreamInspectContext;
-use crate::serve::ServerIdleChecker;
+use crate::serve::{ServerIdleChecker, ServerTaskTrace};
 
 macro_rules! intercept_log {
     ($obj:tt, $($args:tt)+) => {
@@ -51,6 +51,9 @@ macro_rules! intercept_log {
             "depth" => $obj.ctx.inspection_depth,
             "clt_stream" => LtH2StreamId(&$obj.clt_stream_id),
             "ups_stream" => $obj.ups_stream_id.as_ref().map(LtH2StreamId),
+            "trace_id" => $obj.trace.as_ref().map(|t| t.trace_id()),
+            "span_id" => $obj.trace.as_ref().map(|t| t.span_id()),
+            "parent_span_id" => $obj.trace.as_ref().and_then(|t| t.parent_span_id()),
             "started_at" => LtDateTime(&$obj.http_notes.started_datetime),
             "method" => LtHttpMethod(&$obj.http_notes.method),
             "uri" => LtHttpUri::new(&$obj.http_notes.uri, $obj.ctx.log_uri_max_chars()),
@@ -129,6 +132,7 @@ pub(crate) struct H2ForwardTask<SC: ServerConfig> {
     ctx: StreamInspectContext<SC>,
     clt_stream_id: StreamId,
     ups_stream_id: Option<StreamId>,
+    trace: Option<ServerTaskTrace>,
     send_error_response: bool,
     cstats: Arc<H2ConcurrencyStats>,
     http_notes: HttpForwardTaskNotes,
@@ -149,12 +153,36 @@ where
             ctx,
             clt_stream_id,
             ups_stream_id: None,
+            trace: None,
             send_error_response: false,
             cstats,
             http_notes,
         }
     }
 
+    fn add_trace_phase(&self, name: &'static str, start: Instant, error: Option<String>) {
+        if let Some(trace) = &self.trace {
+            trace.add_phase(name, start, error);
+        }
+    }
+
+    fn export_trace(&self, e: Option<&H2StreamTransferError>) {
+        if let Some(trace) = &self.trace {
+            let (reason, error) = match e {
+                Some(e) => (e.brief(), Some(e.to_string())),
+                None => ("Finished", None),
+            };
+            trace.export_result(
+                "H2StreamForward",
+                self.ctx.server_task_id(),
+                self.ctx.task_notes.client_addr,
+                self.ctx.raw_user_name(),
+                reason,
+                error,
+            );
+        }
+    }
+
     fn reply_task_err(&mut self, mut clt_send_rsp: SendResponse<Bytes>, e: &H2StreamTransferError) {
         if let Some(rsp) = e.build_reply() {
             let rsp_status = rsp.status().as_u16();
@@ -196,8 +224,10 @@ where
                 self.reply_task_err(clt_send_rsp, &e);
             }
             intercept_log!(self, "{e}");
+            self.export_trace(Some(&e));
         } else {
             intercept_log!(self, "finished");
+            self.export_trace(None);
         }
     }
 
@@ -208,6 +238,15 @@ where
         h2s: SendRequest<Bytes>,
     ) -> Result<(), H2StreamTransferError> {
         let (mut parts, clt_body) = clt_req.into_parts();
+        if let Some(trace_config) = self.ctx.server_config.trace_context() {
+            self.trace = ServerTaskTrace::from_h2_request_headers(
+                &trace_config,
+                self.ctx.server_config.name(),
+                &mut parts.headers,
+                self.http_notes.started_ins,
+            );
+        }
+
         if self.ctx.h2_interception().silent_drop_expect_header {
             // just drop the Expect header to avoid 100-continue response, which currently is not supported by h2
             parts.headers.remove(http::header::EXPECT);
@@ -239,6 +278,7 @@ where
         let ups_req = Request::from_parts(parts, ());
 
         if let Some(reqmod) = self.ctx.audit_handle.icap_reqmod_client() {
+            let adaptation_start = Instant::now();
             match reqmod
                 .h2_adapter(
                     self.ctx.server_config.limited_copy_config(),
@@ -267,6 +307,11 @@ where
                             &mut adaptation_state,
                         )
                         .await;
+                    self.add_trace_phase(
+                        "icap_reqmod",
+                        adaptation_start,
+                        r.as_ref().err().map(|e| e.to_string()),
+                    );
                     if let Some(dur) = adaptation_state.dur_ups_send_header {
                         self.http_notes.dur_req_send_hdr = dur;
                     }
@@ -279,6 +324,7 @@ where
                     return r;
                 }
                 Err(e) => {
+                    self.add_trace_phase("icap_reqmod", adaptation_start, Some(e.to_string()));
                     if !reqmod.bypass() {
                         return Err(H2StreamTransferError::InternalAdapterError(e));
                     }
@@ -578,6 +624,7 @@ where
         self.http_notes.origin_status = clt_rsp.status().as_u16();
 
         if let Some(respmod) = self.ctx.audit_handle.icap_respmod_client() {
+            let adaptation_start = Instant::now();
             match respmod
                 .h2_adapter(
                     self.ctx.server_config.limited_copy_config(),
@@ -607,6 +654,11 @@ where
                             &mut adaptation_state,
                         )
                         .await;
+                    self.add_trace_phase(
+                        "icap_respmod",
+                        adaptation_start,
+                        r.as_ref().err().map(|e| e.to_string()),
+                    );
                     if let Some(dur) = adaptation_state.dur_ups_recv_all {
                         self.http_notes.dur_rsp_recv_all = dur;
                     }
@@ -616,6 +668,7 @@ where
                     return r;
                 }
                 Err(e) => {
+                    self.add_trace_phase("icap_respmod", adaptation_start, Some(e.to_string()));
                     if !respmod.bypass() {
                         return Err(H2StreamTransferError::InternalAdapterError(e));
                     }
@@ -98,13 +98,20 @@ where
                 ))
             })?;
 
-        // fetch fake server cert early in the background
-        let tls_interception = self.tls_interception.clone();
         let cert_domain = sni_hostname
             .map(|v| v.to_string())
             .unwrap_or_else(|| self.upstream.host().to_string());
-        let clt_cert_handle =
-            tokio::spawn(async move { tls_interception.cert_agent.fetch(cert_domain).await });
+        let clt_cert_handle = if self.tls_interception.cert_agent.mimic_upstream_cert() {
+            // we need the upstream cert before we can fetch the fake server cert
+            None
+        } else {
+            // fetch fake server cert early in the background
+            let tls_interception = self.tls_interception.clone();
+            let cert_domain = cert_domain.clone();
+            Some(tokio::spawn(async move {
+                tls_interception.cert_agent.fetch(cert_domain).await
+            }))
+        };
 
         // handshake with upstream server
         let ups_tls_connector = SslConnector::new(ups_ssl, AggregatedIo::new(ups_r, ups_w))
@@ -126,18 +133,25 @@ where
         let selected_alpn_protocol = ups_ssl.selected_alpn_protocol();
 
         // fetch fake server cert
-        let (clt_cert, clt_key) = clt_cert_handle
-            .await
-            .map_err(|e| {
+        let clt_cert_key = if let Some(handle) = clt_cert_handle {
+            handle.await.map_err(|e| {
                 TlsInterceptionError::NoFakeCertGenerated(anyhow!(
                     "join client cert handle failed: {e}"
                 ))
             })?
-            .ok_or_else(|| {
-                TlsInterceptionError::NoFakeCertGenerated(anyhow!(
-                    "failed to get fake upstream certificate"
-                ))
-            })?;
+        } else if let Some(ups_cert) = ups_ssl.peer_certificate() {
+            self.tls_interception
+                .cert_agent
+                .fetch_mimic(cert_domain, &ups_cert)
+                .await
+        } else {
+            self.tls_interception.cert_agent.fetch(cert_domain).await
+        };
+        let (clt_cert, clt_key) = clt_cert_key.ok_or_else(|| {
+            TlsInterceptionError::NoFakeCertGenerated(anyhow!(
+                "failed to get fake upstream certificate"
+            ))
+        })?;
 
         // build to client ssl context based on server response, and handshake
         let mut clt_server_config = rustls::ServerConfig::builder()
@@ -40,6 +40,11 @@ pub(crate) struct TaskLogForFtpOverHttp<'a> {
 
 impl TaskLogForFtpOverHttp<'_> {
     pub(crate) fn log(&self, logger: &Logger, e: &ServerTaskError) {
+        let trace = self.task_notes.trace();
+        if let Some(trace) = trace {
+            trace.export("FtpOverHttp", self.task_notes, e);
+        }
+
         if let Some(user_ctx) = self.task_notes.user_ctx() {
             if user_ctx.skip_log() {
                 return;
@@ -49,6 +54,9 @@ impl TaskLogForFtpOverHttp<'_> {
         slog_info!(logger, "{}", e;
             "task_type" => "FtpOverHttp",
             "task_id" => LtUuid(&self.task_notes.id),
+            "trace_id" => trace.map(|t| t.trace_id()),
+            "span_id" => trace.map(|t| t.span_id()),
+            "parent_span_id" => trace.and_then(|t| t.parent_span_id()),
             "stage" => self.task_notes.stage.brief(),
             "start_at" => LtDateTime(&self.task_notes.start_at),
             "user" => self.task_notes.raw_user_name(),
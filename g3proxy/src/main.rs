@@ -65,6 +65,9 @@ fn main() -> anyhow::Result<()> {
 
     // enter daemon mode after config loaded
     g3_daemon::daemonize::check_enter(&proc_args.daemon_config)?;
+    g3_daemon::stat::prometheus::spawn_working_thread()
+        .context("failed to start prometheus exporter")?;
+    g3proxy::serve::spawn_trace_exporter();
 
     let stat_join = if let Some(stat_config) = g3_daemon::stat::config::get_global_stat_config() {
         Some(
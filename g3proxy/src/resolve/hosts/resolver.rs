@@ -0,0 +1,162 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use slog::Logger;
+
+use g3_resolver::driver::hosts::HostsDriverConfig;
+use g3_types::metrics::MetricsName;
+
+use crate::config::resolver::hosts::HostsResolverConfig;
+use crate::config::resolver::{AnyResolverConfig, ResolverConfig};
+use crate::resolve::{
+    ArcIntegratedResolverHandle, BoxResolver, Resolver, ResolverInternal, ResolverStats,
+};
+
+pub(crate) struct HostsResolver {
+    config: Arc<HostsResolverConfig>,
+    driver_config: HostsDriverConfig,
+    inner: g3_resolver::Resolver,
+    stats: Arc<ResolverStats>,
+    logger: Arc<Logger>,
+}
+
+impl HostsResolver {
+    pub(crate) fn new_obj(config: HostsResolverConfig) -> anyhow::Result<BoxResolver> {
+        let mut driver_config = HostsDriverConfig::default();
+
+        if let Some(next) = &config.next {
+            let next_handle =
+                crate::resolve::get_handle(next).context("failed to get next resolver handle")?;
+            driver_config.set_next_handle(next_handle.clone_inner());
+        }
+        driver_config.set_static_config(config.static_conf.clone());
+
+        let inner_config = g3_resolver::ResolverConfig {
+            name: config.name().to_string(),
+            runtime: config.runtime.clone(),
+            driver: g3_resolver::AnyResolveDriverConfig::Hosts(driver_config.clone()),
+        };
+        let mut builder = g3_resolver::ResolverBuilder::new(inner_config);
+        builder.thread_name(format!("res-{}", config.name()));
+        let resolver = builder.build()?;
+
+        let logger = crate::log::resolve::get_logger(config.resolver_type(), config.name());
+        let stats = ResolverStats::new(config.name(), resolver.get_stats());
+
+        Ok(Box::new(HostsResolver {
+            config: Arc::new(config),
+            driver_config,
+            inner: resolver,
+            stats: Arc::new(stats),
+            logger: Arc::new(logger),
+        }))
+    }
+}
+
+#[async_trait]
+impl ResolverInternal for HostsResolver {
+    fn _dependent_resolver(&self) -> Option<BTreeSet<MetricsName>> {
+        self.config.dependent_resolver()
+    }
+
+    fn _clone_config(&self) -> AnyResolverConfig {
+        AnyResolverConfig::Hosts(self.config.as_ref().clone())
+    }
+
+    fn _update_config(
+        &mut self,
+        config: AnyResolverConfig,
+        dep_table: BTreeMap<MetricsName, ArcIntegratedResolverHandle>,
+    ) -> anyhow::Result<()> {
+        if let AnyResolverConfig::Hosts(config) = config {
+            let mut driver_config = HostsDriverConfig::default();
+
+            if let Some(next) = &config.next {
+                let next_handle = dep_table.get(next).unwrap();
+                driver_config.set_next_handle(next_handle.clone_inner());
+            }
+            driver_config.set_static_config(config.static_conf.clone());
+
+            let inner_config = g3_resolver::ResolverConfig {
+                name: config.name().to_string(),
+                runtime: config.runtime.clone(),
+                driver: g3_resolver::AnyResolveDriverConfig::Hosts(driver_config.clone()),
+            };
+
+            self.inner
+                .update_config(inner_config)
+                .context("failed to update inner hosts resolver config")?;
+            self.driver_config = driver_config;
+            self.config = Arc::new(config);
+            Ok(())
+        } else {
+            Err(anyhow!("invalid config type for HostsResolver"))
+        }
+    }
+
+    fn _update_dependent_handle(
+        &mut self,
+        target: &MetricsName,
+        handle: ArcIntegratedResolverHandle,
+    ) -> anyhow::Result<()> {
+        if self.config.next.as_ref() != Some(target) {
+            return Err(anyhow!(
+                "resolver {} doesn't depend on resolver {}",
+                self.config.name(),
+                target
+            ));
+        }
+
+        let mut driver_config = self.driver_config.clone();
+        driver_config.set_next_handle(handle.clone_inner());
+
+        let inner_config = g3_resolver::ResolverConfig {
+            name: self.config.name().to_string(),
+            runtime: self.config.runtime.clone(),
+            driver: g3_resolver::AnyResolveDriverConfig::Hosts(driver_config.clone()),
+        };
+
+        self.inner
+            .update_config(inner_config)
+            .context("failed to update inner hosts resolver config")?;
+        self.driver_config = driver_config;
+        Ok(())
+    }
+
+    async fn _shutdown(&mut self) {
+        self.inner.shutdown().await;
+    }
+}
+
+impl Resolver for HostsResolver {
+    fn get_handle(&self) -> ArcIntegratedResolverHandle {
+        let inner_context = self.inner.get_handle();
+        Arc::new(super::HostsResolverHandle::new(
+            &self.config,
+            inner_context,
+            &self.logger,
+        ))
+    }
+
+    fn get_stats(&self) -> Arc<ResolverStats> {
+        Arc::clone(&self.stats)
+    }
+}
@@ -43,7 +43,9 @@ mod c_ares;
 mod hickory;
 
 mod deny_all;
+mod domain_route;
 mod fail_over;
+mod hosts;
 
 mod ops;
 pub(crate) use ops::reload;
@@ -32,7 +32,9 @@ use super::c_ares::CAresResolver;
 use super::hickory::HickoryResolver;
 
 use super::deny_all::DenyAllResolver;
+use super::domain_route::DomainRouteResolver;
 use super::fail_over::FailOverResolver;
+use super::hosts::HostsResolver;
 
 use super::registry;
 
@@ -179,7 +181,9 @@ async fn spawn_new_unlocked(config: AnyResolverConfig) -> anyhow::Result<()> {
         #[cfg(feature = "hickory")]
         AnyResolverConfig::Hickory(c) => HickoryResolver::new_obj(c)?,
         AnyResolverConfig::DenyAll(c) => DenyAllResolver::new_obj(c)?,
+        AnyResolverConfig::DomainRoute(c) => DomainRouteResolver::new_obj(c)?,
         AnyResolverConfig::FailOver(c) => FailOverResolver::new_obj(c)?,
+        AnyResolverConfig::Hosts(c) => HostsResolver::new_obj(c)?,
     };
     let old_resolver = registry::add(name.clone(), resolver);
     update_dependency_to_resolver_unlocked(&name, STATUS).await;
@@ -757,6 +757,7 @@ impl<'a> HttpProxyForwardTask<'a> {
                             if let Some(name) = self.task_notes.raw_user_name() {
                                 adapter.set_client_username(name);
                             }
+                            let adaptation_start = Instant::now();
                             let r = self
                                 .run_with_adaptation(
                                     clt_r,
@@ -766,6 +767,11 @@ impl<'a> HttpProxyForwardTask<'a> {
                                     &mut adaptation_state,
                                 )
                                 .await;
+                            self.task_notes.add_trace_phase(
+                                "icap_reqmod",
+                                adaptation_start,
+                                r.as_ref().err().map(|e| e.to_string()),
+                            );
                             if let Some(dur) = adaptation_state.dur_ups_send_header {
                                 self.http_notes.retry_new_connection = false;
                                 self.http_notes.dur_req_send_hdr = dur;
@@ -1272,6 +1278,7 @@ impl<'a> HttpProxyForwardTask<'a> {
                                 adapter.set_client_username(name);
                             }
                             adapter.set_respond_shared_headers(adaptation_respond_shared_headers);
+                            let adaptation_start = Instant::now();
                             let r = self
                                 .send_response_with_adaptation(
                                     clt_w,
@@ -1281,6 +1288,11 @@ impl<'a> HttpProxyForwardTask<'a> {
                                     &mut adaptation_state,
                                 )
                                 .await;
+                            self.task_notes.add_trace_phase(
+                                "icap_respmod",
+                                adaptation_start,
+                                r.as_ref().err().map(|e| e.to_string()),
+                            );
                             if !adaptation_state.clt_write_finished
                                 || !adaptation_state.ups_read_finished
                             {
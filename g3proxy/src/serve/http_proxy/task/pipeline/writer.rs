@@ -21,6 +21,7 @@ use std::time::Duration;
 use ahash::AHashMap;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 
 use g3_io_ext::{ArcLimitedWriterStats, LimitedWriter};
 use g3_types::auth::UserAuthError;
@@ -35,7 +36,7 @@ use super::{
 use crate::auth::{UserContext, UserGroup, UserRequestStats};
 use crate::config::server::ServerConfig;
 use crate::module::http_forward::{BoxHttpForwardContext, HttpProxyClientResponse};
-use crate::serve::{ServerStats, ServerTaskNotes};
+use crate::serve::{ServerStats, ServerTaskNotes, ServerTaskTrace};
 
 struct UserData {
     req_stats: Arc<UserRequestStats>,
@@ -200,10 +201,11 @@ where
         loop {
             let res = match self.task_queue.recv().await {
                 Some(Ok(req)) => {
+                    let auth_start = Instant::now();
                     let res = match self.do_auth(&req) {
                         Ok(user_ctx) => {
                             self.req_count.consequent_auth_failed = 0;
-                            self.run(req, user_ctx).await
+                            self.run(req, user_ctx, auth_start).await
                         }
                         Err(e) => {
                             self.req_count.consequent_auth_failed += 1;
@@ -258,15 +260,27 @@ where
         &mut self,
         mut req: HttpProxyRequest<CDR>,
         user_ctx: Option<UserContext>,
+        auth_start: Instant,
     ) -> LoopAction {
         let path_selection =
             self.get_egress_path_selection(&mut req.inner.end_to_end_headers, user_ctx.as_ref());
-        let task_notes = ServerTaskNotes::with_path_selection(
+        let mut task_notes = ServerTaskNotes::with_path_selection(
             self.ctx.cc_info.clone(),
             user_ctx,
             req.time_accepted.elapsed(),
             path_selection,
         );
+        if let Some(trace_config) = &self.ctx.server_config.trace_context {
+            if let Some(trace) = ServerTaskTrace::from_request_headers(
+                trace_config,
+                self.ctx.server_config.name(),
+                &mut req.inner.end_to_end_headers,
+                req.time_accepted,
+            ) {
+                trace.add_phase("auth", auth_start, None);
+                task_notes.set_trace(trace);
+            }
+        }
 
         let forward_capability = self
             .forward_context
@@ -20,6 +20,7 @@ use std::time::Duration;
 use ahash::AHashMap;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 
 use g3_io_ext::{ArcLimitedWriterStats, LimitedWriter};
 use g3_types::auth::UserAuthError;
@@ -35,7 +36,7 @@ use crate::auth::{UserContext, UserGroup, UserRequestStats};
 use crate::config::server::ServerConfig;
 use crate::module::http_forward::{BoxHttpForwardContext, HttpProxyClientResponse};
 use crate::serve::http_rproxy::host::HttpHost;
-use crate::serve::{ServerStats, ServerTaskNotes};
+use crate::serve::{ServerStats, ServerTaskNotes, ServerTaskTrace};
 
 struct UserData {
     req_stats: Arc<UserRequestStats>,
@@ -201,12 +202,13 @@ where
         loop {
             let res = match self.task_queue.recv().await {
                 Some(Ok(req)) => {
+                    let auth_start = Instant::now();
                     let res = match self.do_auth(&req) {
                         Ok(user_ctx) => {
                             self.req_count.consequent_auth_failed = 0;
 
                             match hosts.get(req.upstream.host()).cloned() {
-                                Some(host) => self.run(req, user_ctx, host).await,
+                                Some(host) => self.run(req, user_ctx, host, auth_start).await,
                                 None => {
                                     // close the connection if no host config found
                                     self.req_count.invalid += 1;
@@ -268,17 +270,29 @@ where
 
     async fn run(
         &mut self,
-        req: HttpRProxyRequest<CDR>,
+        mut req: HttpRProxyRequest<CDR>,
         user_ctx: Option<UserContext>,
         host: Arc<HttpHost>,
+        auth_start: Instant,
     ) -> LoopAction {
         let path_selection = self.get_egress_path_selection(user_ctx.as_ref());
-        let task_notes = ServerTaskNotes::with_path_selection(
+        let mut task_notes = ServerTaskNotes::with_path_selection(
             self.ctx.cc_info.clone(),
             user_ctx,
             req.time_accepted.elapsed(),
             path_selection,
         );
+        if let Some(trace_config) = &self.ctx.server_config.trace_context {
+            if let Some(trace) = ServerTaskTrace::from_request_headers(
+                trace_config,
+                self.ctx.server_config.name(),
+                &mut req.inner.end_to_end_headers,
+                req.time_accepted,
+            ) {
+                trace.add_phase("auth", auth_start, None);
+                task_notes.set_trace(trace);
+            }
+        }
 
         if let Some(mut stream_w) = self.stream_writer.take() {
             // check in final escaper so we can use route escapers
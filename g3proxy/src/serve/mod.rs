@@ -62,9 +62,12 @@ mod tls_stream;
 
 mod error;
 mod task;
+mod trace;
 
 pub(crate) use error::{ServerTaskError, ServerTaskForbiddenError, ServerTaskResult};
 pub(crate) use task::{ServerTaskNotes, ServerTaskStage};
+pub use trace::spawn_trace_exporter;
+pub(crate) use trace::ServerTaskTrace;
 
 mod ops;
 pub(crate) use ops::{
@@ -26,6 +26,7 @@ use g3_daemon::server::ClientConnectionInfo;
 use g3_types::limit::GaugeSemaphorePermit;
 use g3_types::route::EgressPathSelection;
 
+use super::ServerTaskTrace;
 use crate::auth::UserContext;
 
 static DEFAULT_PATH_SELECTION: OnceLock<Arc<EgressPathSelection>> = OnceLock::new();
@@ -70,6 +71,7 @@ pub(crate) struct ServerTaskNotes {
     pub(crate) wait_time: Duration,
     pub(crate) ready_time: Duration,
     pub(crate) egress_path_selection: Arc<EgressPathSelection>,
+    trace: Option<ServerTaskTrace>,
     /// the following fields should not be cloned
     pub(crate) user_req_alive_permit: Option<GaugeSemaphorePermit>,
 }
@@ -103,6 +105,7 @@ impl ServerTaskNotes {
             wait_time,
             ready_time: Duration::default(),
             egress_path_selection,
+            trace: None,
             user_req_alive_permit: None,
         }
     }
@@ -151,6 +154,27 @@ impl ServerTaskNotes {
         self.create_ins.elapsed()
     }
 
+    pub(crate) fn set_trace(&mut self, trace: ServerTaskTrace) {
+        self.trace = Some(trace);
+    }
+
+    #[inline]
+    pub(crate) fn trace(&self) -> Option<&ServerTaskTrace> {
+        self.trace.as_ref()
+    }
+
+    /// record a phase span for this task, which started at `start` and ends now
+    pub(crate) fn add_trace_phase(
+        &self,
+        name: &'static str,
+        start: Instant,
+        error: Option<String>,
+    ) {
+        if let Some(trace) = &self.trace {
+            trace.add_phase(name, start, error);
+        }
+    }
+
     pub(crate) fn mark_relaying(&mut self) {
         self.stage = ServerTaskStage::Relaying;
         self.ready_time = self.create_ins.elapsed();
@@ -0,0 +1,502 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use g3_otlp::{OtlpSpan, OtlpSpanAttributeValue, OtlpSpanKind};
+use g3_types::metrics::MetricsName;
+use g3_types::net::{HttpHeaderMap, HttpTraceContext, HttpTraceContextConfig};
+
+use super::{ServerTaskError, ServerTaskNotes};
+
+/// Start the span exporter if trace has been enabled in the main config
+pub fn spawn_trace_exporter() {
+    g3_daemon::trace::spawn_exporter(crate::build::PKG_NAME, crate::opts::daemon_group());
+}
+
+struct TaskPhaseSpan {
+    name: &'static str,
+    span_id: [u8; 8],
+    start: SystemTime,
+    end: SystemTime,
+    error: Option<String>,
+}
+
+/// The W3C trace context of a server task, and the phase spans recorded for it.
+///
+/// The span of the task itself is the one in the trace context, and the phase spans
+/// will be exported as its children when the task finished.
+pub(crate) struct ServerTaskTrace {
+    server_name: MetricsName,
+    context: HttpTraceContext,
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    start_time: SystemTime,
+    start_ins: Instant,
+    phases: Mutex<Vec<TaskPhaseSpan>>,
+}
+
+impl ServerTaskTrace {
+    fn new(server_name: &MetricsName, context: HttpTraceContext, start: Instant) -> Self {
+        let now = SystemTime::now();
+        let start_time = now.checked_sub(start.elapsed()).unwrap_or(now);
+        ServerTaskTrace {
+            server_name: server_name.clone(),
+            trace_id: context.trace_id_hex(),
+            span_id: context.span_id_hex(),
+            parent_span_id: context.parent_id_hex(),
+            context,
+            start_time,
+            start_ins: start,
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Extract the trace context from the request headers, or create a new one if allowed.
+    ///
+    /// The traceparent header will be replaced if inject is enabled,
+    /// so the upstream will see the span of this task as its parent.
+    /// The task span starts at `start`, which should be the time the request was accepted,
+    /// so phases like auth that happen before the task is created will be included.
+    pub(crate) fn from_request_headers(
+        config: &HttpTraceContextConfig,
+        server_name: &MetricsName,
+        headers: &mut HttpHeaderMap,
+        start: Instant,
+    ) -> Option<Self> {
+        let context = match HttpTraceContext::from_headers(headers) {
+            Some(context) => context,
+            None => {
+                if !config.create() {
+                    return None;
+                }
+                HttpTraceContext::new_root()
+            }
+        };
+        if config.inject() {
+            context.inject_headers(headers);
+        }
+        Some(ServerTaskTrace::new(server_name, context, start))
+    }
+
+    /// The same as `from_request_headers`, but for HTTP/2 requests.
+    pub(crate) fn from_h2_request_headers(
+        config: &HttpTraceContextConfig,
+        server_name: &MetricsName,
+        headers: &mut http::HeaderMap,
+        start: Instant,
+    ) -> Option<Self> {
+        let context = match HttpTraceContext::from_h2_headers(headers) {
+            Some(context) => context,
+            None => {
+                if !config.create() {
+                    return None;
+                }
+                HttpTraceContext::new_root()
+            }
+        };
+        if config.inject() {
+            context.inject_h2_headers(headers);
+        }
+        Some(ServerTaskTrace::new(server_name, context, start))
+    }
+
+    #[inline]
+    pub(crate) fn trace_id(&self) -> &str {
+        &self.trace_id
+    }
+
+    #[inline]
+    pub(crate) fn span_id(&self) -> &str {
+        &self.span_id
+    }
+
+    #[inline]
+    pub(crate) fn parent_span_id(&self) -> Option<&str> {
+        self.parent_span_id.as_deref()
+    }
+
+    fn system_time(&self, ins: Instant) -> SystemTime {
+        self.start_time + ins.saturating_duration_since(self.start_ins)
+    }
+
+    /// record a phase span which started at `start` and ends now
+    pub(crate) fn add_phase(&self, name: &'static str, start: Instant, error: Option<String>) {
+        let span = TaskPhaseSpan {
+            name,
+            span_id: HttpTraceContext::new_span_id(),
+            start: self.system_time(start),
+            end: SystemTime::now(),
+            error,
+        };
+        let mut phases = self.phases.lock().unwrap();
+        phases.push(span);
+    }
+
+    fn new_span(
+        &self,
+        span_id: [u8; 8],
+        name: String,
+        start: SystemTime,
+        end: SystemTime,
+    ) -> OtlpSpan {
+        let mut span = OtlpSpan::new(*self.context.trace_id(), span_id, name, start, end);
+        span.set_server_name(self.server_name.to_string());
+        if let Some(state) = self.context.trace_state() {
+            span.set_trace_state(state.to_string());
+        }
+        span
+    }
+
+    /// export the task span and all the phase spans, if the trace exporter is enabled
+    pub(crate) fn export(
+        &self,
+        task_type: &'static str,
+        task_notes: &ServerTaskNotes,
+        e: &ServerTaskError,
+    ) {
+        self.export_with(
+            task_type,
+            &task_notes.id,
+            task_notes.client_addr(),
+            task_notes.raw_user_name(),
+            e,
+        );
+    }
+
+    pub(crate) fn export_with(
+        &self,
+        task_type: &'static str,
+        task_id: &Uuid,
+        client_addr: SocketAddr,
+        user: Option<&str>,
+        e: &ServerTaskError,
+    ) {
+        let error = match e {
+            ServerTaskError::Finished | ServerTaskError::ClosedByClient => None,
+            _ => Some(e.to_string()),
+        };
+        self.export_result(task_type, task_id, client_addr, user, e.brief(), error);
+    }
+
+    /// export the spans with the task reason and error message already set by the caller
+    pub(crate) fn export_result(
+        &self,
+        task_type: &'static str,
+        task_id: &Uuid,
+        client_addr: SocketAddr,
+        user: Option<&str>,
+        reason: &'static str,
+        error: Option<String>,
+    ) {
+        let Some(exporter) = g3_daemon::trace::span_exporter() else {
+            return;
+        };
+        if !self.context.sampled() {
+            return;
+        }
+
+        let end = SystemTime::now();
+        let mut span = self.new_span(
+            *self.context.span_id(),
+            task_type.to_string(),
+            self.start_time,
+            end,
+        );
+        if let Some(parent_id) = self.context.parent_id() {
+            span.set_parent_span_id(*parent_id);
+        }
+        span.set_kind(OtlpSpanKind::Server);
+        span.add_attribute(
+            "g3.task_id",
+            OtlpSpanAttributeValue::String(task_id.to_string()),
+        );
+        span.add_attribute(
+            "g3.task_reason",
+            OtlpSpanAttributeValue::String(reason.to_string()),
+        );
+        span.add_attribute(
+            "client.address",
+            OtlpSpanAttributeValue::String(client_addr.ip().to_string()),
+        );
+        span.add_attribute(
+            "client.port",
+            OtlpSpanAttributeValue::Int(client_addr.port() as i64),
+        );
+        if let Some(user) = user {
+            span.add_attribute("g3.user", OtlpSpanAttributeValue::String(user.to_string()));
+        }
+        if let Some(e) = error {
+            span.set_error(e);
+        }
+        exporter.export(span);
+
+        let phases = std::mem::take(&mut *self.phases.lock().unwrap());
+        for phase in phases {
+            let mut span = self.new_span(
+                phase.span_id,
+                phase.name.to_string(),
+                phase.start,
+                phase.end,
+            );
+            span.set_parent_span_id(*self.context.span_id());
+            if let Some(e) = phase.error {
+                span.set_error(e);
+            }
+            exporter.export(span);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    use g3_types::net::{HttpHeaderValue, HTTP_HEADER_TRACEPARENT, HTTP_HEADER_TRACESTATE};
+
+    const TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+    fn trace_config(create: bool, inject: bool) -> HttpTraceContextConfig {
+        let mut config = HttpTraceContextConfig::default();
+        config.set_create(create);
+        config.set_inject(inject);
+        config
+    }
+
+    fn request_headers() -> HttpHeaderMap {
+        let mut headers = HttpHeaderMap::default();
+        headers.insert(
+            HTTP_HEADER_TRACEPARENT,
+            HttpHeaderValue::from_static(TRACEPARENT),
+        );
+        headers.insert(
+            HTTP_HEADER_TRACESTATE,
+            HttpHeaderValue::from_static("congo=t61rcWkgMzE"),
+        );
+        headers
+    }
+
+    #[test]
+    fn propagate_and_inject() {
+        let server_name = MetricsName::from_str("test").unwrap();
+        let mut headers = request_headers();
+        let trace = ServerTaskTrace::from_request_headers(
+            &trace_config(false, true),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+        assert_eq!(trace.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(trace.parent_span_id(), Some("00f067aa0ba902b7"));
+        assert_ne!(trace.span_id(), "00f067aa0ba902b7");
+
+        let traceparent = headers.get(HTTP_HEADER_TRACEPARENT).unwrap().to_str();
+        assert_eq!(
+            traceparent,
+            format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", trace.span_id())
+        );
+        assert_eq!(
+            headers.get(HTTP_HEADER_TRACESTATE).unwrap().to_str(),
+            "congo=t61rcWkgMzE"
+        );
+    }
+
+    #[test]
+    fn propagate_without_inject() {
+        let server_name = MetricsName::from_str("test").unwrap();
+        let mut headers = request_headers();
+        let trace = ServerTaskTrace::from_request_headers(
+            &trace_config(true, false),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+        assert_eq!(trace.parent_span_id(), Some("00f067aa0ba902b7"));
+        assert_eq!(
+            headers.get(HTTP_HEADER_TRACEPARENT).unwrap().to_str(),
+            TRACEPARENT
+        );
+    }
+
+    #[test]
+    fn create_root() {
+        let server_name = MetricsName::from_str("test").unwrap();
+
+        let mut headers = HttpHeaderMap::default();
+        assert!(ServerTaskTrace::from_request_headers(
+            &trace_config(false, true),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .is_none());
+        assert!(headers.is_empty());
+
+        let trace = ServerTaskTrace::from_request_headers(
+            &trace_config(true, true),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+        assert!(trace.parent_span_id().is_none());
+        let traceparent = headers.get(HTTP_HEADER_TRACEPARENT).unwrap().to_str();
+        let context = HttpTraceContext::parse(traceparent, None).unwrap();
+        assert_eq!(context.trace_id_hex(), trace.trace_id());
+        assert_eq!(context.parent_id_hex().as_deref(), Some(trace.span_id()));
+    }
+
+    #[test]
+    fn h2_propagate_and_inject() {
+        let server_name = MetricsName::from_str("test").unwrap();
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            HTTP_HEADER_TRACEPARENT,
+            http::HeaderValue::from_static(TRACEPARENT),
+        );
+        let trace = ServerTaskTrace::from_h2_request_headers(
+            &trace_config(false, true),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+        assert_eq!(trace.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(trace.parent_span_id(), Some("00f067aa0ba902b7"));
+        assert_eq!(
+            headers
+                .get(HTTP_HEADER_TRACEPARENT)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            format!("00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01", trace.span_id())
+        );
+
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            HTTP_HEADER_TRACEPARENT,
+            http::HeaderValue::from_static(TRACEPARENT),
+        );
+        let trace = ServerTaskTrace::from_h2_request_headers(
+            &trace_config(false, false),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+        assert_eq!(trace.parent_span_id(), Some("00f067aa0ba902b7"));
+        assert_eq!(
+            headers
+                .get(HTTP_HEADER_TRACEPARENT)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            TRACEPARENT
+        );
+    }
+
+    #[test]
+    fn h2_create_root() {
+        let server_name = MetricsName::from_str("test").unwrap();
+
+        let mut headers = http::HeaderMap::new();
+        assert!(ServerTaskTrace::from_h2_request_headers(
+            &trace_config(false, true),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .is_none());
+        assert!(headers.is_empty());
+
+        let trace = ServerTaskTrace::from_h2_request_headers(
+            &trace_config(true, true),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+        assert!(trace.parent_span_id().is_none());
+        let traceparent = headers
+            .get(HTTP_HEADER_TRACEPARENT)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        let context = HttpTraceContext::parse(traceparent, None).unwrap();
+        assert_eq!(context.trace_id_hex(), trace.trace_id());
+        assert_eq!(context.parent_id_hex().as_deref(), Some(trace.span_id()));
+    }
+
+    #[test]
+    fn icap_phases() {
+        let server_name = MetricsName::from_str("test").unwrap();
+        let mut headers = request_headers();
+        let trace = ServerTaskTrace::from_request_headers(
+            &trace_config(false, false),
+            &server_name,
+            &mut headers,
+            Instant::now(),
+        )
+        .unwrap();
+
+        let start = Instant::now();
+        trace.add_phase("icap_reqmod", start, None);
+        trace.add_phase(
+            "icap_respmod",
+            start,
+            Some("internal adapter error".to_string()),
+        );
+        let phases = trace.phases.lock().unwrap();
+        assert_eq!(phases.len(), 2);
+        assert_eq!(phases[0].name, "icap_reqmod");
+        assert!(phases[0].error.is_none());
+        assert_eq!(phases[1].name, "icap_respmod");
+        assert_eq!(phases[1].error.as_deref(), Some("internal adapter error"));
+        assert_ne!(phases[0].span_id, phases[1].span_id);
+        assert_ne!(&phases[0].span_id, trace.context.span_id());
+    }
+
+    #[test]
+    fn start_before_creation() {
+        let server_name = MetricsName::from_str("test").unwrap();
+        let accepted = Instant::now() - Duration::from_secs(2);
+        let mut headers = HttpHeaderMap::default();
+        let trace = ServerTaskTrace::from_request_headers(
+            &trace_config(true, false),
+            &server_name,
+            &mut headers,
+            accepted,
+        )
+        .unwrap();
+        let elapsed = SystemTime::now().duration_since(trace.start_time).unwrap();
+        assert!(elapsed >= Duration::from_secs(2));
+
+        trace.add_phase("auth", accepted, None);
+        let phases = trace.phases.lock().unwrap();
+        assert_eq!(phases[0].start, trace.start_time);
+        assert!(phases[0].end >= phases[0].start);
+    }
+}
@@ -36,6 +36,7 @@ fn go_offline(_: u32) -> SigResult {
 
 fn call_reload(_: u32) -> SigResult {
     info!("got reload signal");
+    g3_daemon::log::reopen_files();
     tokio::spawn(do_reload());
     SigResult::Continue
 }
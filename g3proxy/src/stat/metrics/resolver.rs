@@ -38,6 +38,8 @@ const METRIC_NAME_QUERY_DRIVER: &str = "resolver.query.driver.total";
 const METRIC_NAME_QUERY_DRIVER_TIMEOUT: &str = "resolver.query.driver.timeout";
 const METRIC_NAME_QUERY_DRIVER_REFUSED: &str = "resolver.query.driver.refused";
 const METRIC_NAME_QUERY_DRIVER_MALFORMED: &str = "resolver.query.driver.malformed";
+const METRIC_NAME_QUERY_DRIVER_DNSSEC_BOGUS: &str = "resolver.query.driver.dnssec_bogus";
+const METRIC_NAME_QUERY_DRIVER_DNSSEC_INSECURE: &str = "resolver.query.driver.dnssec_insecure";
 const METRIC_NAME_QUERY_SERVER_REFUSED: &str = "resolver.query.server.refused";
 const METRIC_NAME_QUERY_SERVER_MALFORMED: &str = "resolver.query.server.malformed";
 const METRIC_NAME_QUERY_SERVER_NOT_FOUND: &str = "resolver.query.server.not_found";
@@ -162,6 +164,11 @@ fn emit_query_stats_to_statsd(
     emit_query_stats_u64!(driver_timeout, METRIC_NAME_QUERY_DRIVER_TIMEOUT);
     emit_query_stats_u64!(driver_refused, METRIC_NAME_QUERY_DRIVER_REFUSED);
     emit_query_stats_u64!(driver_malformed, METRIC_NAME_QUERY_DRIVER_MALFORMED);
+    emit_query_stats_u64!(driver_dnssec_bogus, METRIC_NAME_QUERY_DRIVER_DNSSEC_BOGUS);
+    emit_query_stats_u64!(
+        driver_dnssec_insecure,
+        METRIC_NAME_QUERY_DRIVER_DNSSEC_INSECURE
+    );
     emit_query_stats_u64!(server_refused, METRIC_NAME_QUERY_SERVER_REFUSED);
     emit_query_stats_u64!(server_malformed, METRIC_NAME_QUERY_SERVER_MALFORMED);
     emit_query_stats_u64!(server_not_found, METRIC_NAME_QUERY_SERVER_NOT_FOUND);
@@ -21,11 +21,14 @@ use anyhow::{anyhow, Context};
 use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use futures_util::future::{AbortHandle, Abortable};
+use tokio::io::AsyncWriteExt;
 use tokio::time::Instant;
 
 use g3_types::collection::{SelectiveVec, SelectiveVecBuilder, WeightedValue};
 use g3_types::metrics::MetricsName;
-use g3_types::net::ConnectError;
+use g3_types::net::{
+    ConnectError, ProxyProtocolEncoder, ProxyProtocolV2Tlvs, ProxyProtocolVersion,
+};
 
 use super::{ArcBackend, Backend, BackendExt};
 use crate::config::backend::stream_tcp::StreamTcpBackendConfig;
@@ -199,7 +202,30 @@ impl Backend for StreamTcpBackend {
         self.stats.add_conn_established();
         self.duration_recorder.record_connect_time(connect_dur);
 
-        let (ups_r, ups_w) = stream.into_split();
+        let (ups_r, mut ups_w) = stream.into_split();
+
+        if let Some(version) = self.config.proxy_protocol {
+            let mut tlvs = ProxyProtocolV2Tlvs::default();
+            if version == ProxyProtocolVersion::V2 {
+                tlvs.push_unique_id(task_notes.id.as_bytes())
+                    .map_err(StreamConnectError::ProxyProtocolEncodeError)?;
+                if let Some(tls_info) = &task_notes.tls_info {
+                    tls_info
+                        .push_proxy_protocol_tlvs(&mut tlvs)
+                        .map_err(StreamConnectError::ProxyProtocolEncodeError)?;
+                }
+            }
+
+            let mut encoder = ProxyProtocolEncoder::new(version);
+            let bytes = encoder
+                .encode_tcp_with_tlvs(task_notes.client_addr(), task_notes.server_addr(), &tlvs)
+                .map_err(StreamConnectError::ProxyProtocolEncodeError)?;
+            ups_w
+                .write_all(bytes)
+                .await
+                .map_err(StreamConnectError::ProxyProtocolWriteFailed)?;
+        }
+
         Ok((Box::new(ups_r), Box::new(ups_w)))
     }
 }
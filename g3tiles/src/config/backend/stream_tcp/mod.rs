@@ -22,6 +22,7 @@ use yaml_rust::{yaml, Yaml};
 use g3_histogram::HistogramMetricsConfig;
 use g3_types::collection::SelectivePickPolicy;
 use g3_types::metrics::{MetricsName, StaticMetricsTags};
+use g3_types::net::ProxyProtocolVersion;
 use g3_yaml::YamlDocPosition;
 
 use super::{AnyBackendConfig, BackendConfig, BackendConfigDiffAction};
@@ -38,6 +39,7 @@ pub(crate) struct StreamTcpBackendConfig {
     pub(crate) peer_pick_policy: SelectivePickPolicy,
     pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
     pub(crate) duration_stats: HistogramMetricsConfig,
+    pub(crate) proxy_protocol: Option<ProxyProtocolVersion>,
 }
 
 impl StreamTcpBackendConfig {
@@ -50,6 +52,7 @@ impl StreamTcpBackendConfig {
             peer_pick_policy: SelectivePickPolicy::Random,
             extra_metrics_tags: None,
             duration_stats: HistogramMetricsConfig::default(),
+            proxy_protocol: None,
         }
     }
 
@@ -107,6 +110,12 @@ impl StreamTcpBackendConfig {
                 )?;
                 Ok(())
             }
+            "proxy_protocol" | "use_proxy_protocol" => {
+                let v = g3_yaml::value::as_proxy_protocol_version(v)
+                    .context(format!("invalid proxy protocol version value for key {k}"))?;
+                self.proxy_protocol = Some(v);
+                Ok(())
+            }
             _ => Err(anyhow!("invalid key {k}")),
         }
     }
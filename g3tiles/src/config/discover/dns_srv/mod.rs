@@ -0,0 +1,98 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use g3_resolver::driver::hickory::HickoryDriverConfig;
+use g3_types::metrics::MetricsName;
+use g3_yaml::YamlDocPosition;
+
+use super::{
+    AnyDiscoverConfig, DiscoverConfig, DiscoverConfigDiffAction, CONFIG_KEY_DISCOVER_NAME,
+    CONFIG_KEY_DISCOVER_TYPE,
+};
+
+mod yaml;
+
+const DISCOVER_CONFIG_TYPE: &str = "DnsSrv";
+
+pub(crate) struct DnsSrvDiscoverInput {
+    pub(crate) name: String,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct DnsSrvDiscoverConfig {
+    name: MetricsName,
+    position: Option<YamlDocPosition>,
+    pub(crate) driver: HickoryDriverConfig,
+    pub(crate) min_refresh_interval: Duration,
+}
+
+impl DnsSrvDiscoverConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        DnsSrvDiscoverConfig {
+            name: MetricsName::default(),
+            position,
+            driver: HickoryDriverConfig::default(),
+            min_refresh_interval: Duration::from_secs(1),
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.driver.is_unspecified() {
+            return Err(anyhow!("no dns server has been set"));
+        }
+        if self.min_refresh_interval.is_zero() {
+            return Err(anyhow!("min refresh interval should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+impl DiscoverConfig for DnsSrvDiscoverConfig {
+    #[inline]
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    #[inline]
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    #[inline]
+    fn discover_type(&self) -> &'static str {
+        DISCOVER_CONFIG_TYPE
+    }
+
+    fn diff_action(&self, new: &AnyDiscoverConfig) -> DiscoverConfigDiffAction {
+        let new = match new {
+            AnyDiscoverConfig::DnsSrv(config) => config,
+            _ => return DiscoverConfigDiffAction::SpawnNew,
+        };
+
+        if self.eq(new) {
+            DiscoverConfigDiffAction::NoAction
+        } else {
+            DiscoverConfigDiffAction::SpawnNew
+        }
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
+
+use g3_yaml::YamlDocPosition;
+
+use super::{DnsSrvDiscoverConfig, DnsSrvDiscoverInput};
+
+impl DnsSrvDiscoverConfig {
+    pub(crate) fn parse_yaml_conf(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut site = DnsSrvDiscoverConfig::new(position);
+        g3_yaml::foreach_kv(map, |k, v| site.set_yaml(k, v))?;
+        site.check()?;
+        Ok(site)
+    }
+
+    fn set_yaml(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_DISCOVER_TYPE => Ok(()),
+            super::CONFIG_KEY_DISCOVER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "server" => match v {
+                Yaml::String(addrs) => {
+                    for (i, addr) in addrs.split_whitespace().enumerate() {
+                        self.add_server(addr)
+                            .context(format!("#{i} is not a valid ip address"))?;
+                    }
+                    Ok(())
+                }
+                Yaml::Array(seq) => {
+                    for (i, addr) in seq.iter().enumerate() {
+                        if let Yaml::String(addr) = addr {
+                            self.add_server(addr)
+                                .context(format!("#{i} is not a valid ip address"))?;
+                        } else {
+                            return Err(anyhow!("#{i} should be a string value"));
+                        }
+                    }
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid yaml value type, expect string / array")),
+            },
+            "server_port" => {
+                let port = g3_yaml::value::as_u16(v)?;
+                self.driver.set_server_port(port);
+                Ok(())
+            }
+            "encryption" | "encrypt" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let config =
+                    g3_yaml::value::as_dns_encryption_protocol_builder(v, Some(lookup_dir))
+                        .context(format!("invalid dns encryption config value for key {k}"))?;
+                self.driver.set_encryption(config);
+                Ok(())
+            }
+            "each_timeout" => {
+                let timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                self.driver.set_each_timeout(timeout);
+                Ok(())
+            }
+            "retry_attempts" => {
+                let attempts = g3_yaml::value::as_usize(v)?;
+                self.driver.set_retry_attempts(attempts);
+                Ok(())
+            }
+            "bind_ip" => {
+                let ip = g3_yaml::value::as_ipaddr(v)?;
+                self.driver.set_bind_ip(ip);
+                Ok(())
+            }
+            "positive_min_ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.driver.set_positive_min_ttl(ttl);
+                Ok(())
+            }
+            "positive_max_ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.driver.set_positive_max_ttl(ttl);
+                Ok(())
+            }
+            "negative_min_ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.driver.set_negative_min_ttl(ttl);
+                Ok(())
+            }
+            "negative_max_ttl" => {
+                let ttl = g3_yaml::value::as_u32(v)?;
+                self.driver.set_negative_max_ttl(ttl);
+                Ok(())
+            }
+            "min_refresh_interval" => {
+                self.min_refresh_interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn add_server(&mut self, addr: &str) -> anyhow::Result<()> {
+        let ip = IpAddr::from_str(addr)?;
+        self.driver.add_server(ip);
+        Ok(())
+    }
+
+    pub(crate) fn parse_yaml_data(&self, input: &Yaml) -> anyhow::Result<DnsSrvDiscoverInput> {
+        let name = g3_yaml::value::as_string(input).context("invalid srv record name")?;
+        if name.is_empty() {
+            return Err(anyhow!("empty srv record name"));
+        }
+        Ok(DnsSrvDiscoverInput { name })
+    }
+}
@@ -0,0 +1,117 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use g3_types::fs::ConfigFileFormat;
+use g3_types::metrics::MetricsName;
+use g3_yaml::YamlDocPosition;
+
+use super::{
+    AnyDiscoverConfig, DiscoverConfig, DiscoverConfigDiffAction, CONFIG_KEY_DISCOVER_NAME,
+    CONFIG_KEY_DISCOVER_TYPE,
+};
+
+mod yaml;
+
+const DISCOVER_CONFIG_TYPE: &str = "File";
+
+pub(crate) struct FileDiscoverInput {
+    pub(crate) path: PathBuf,
+    pub(crate) format: ConfigFileFormat,
+    pub(crate) metadata: BTreeMap<String, String>,
+}
+
+impl FileDiscoverInput {
+    fn new(path: PathBuf) -> Self {
+        let mut format = ConfigFileFormat::Yaml;
+        if let Some(extension) = path.extension() {
+            if let Some(s) = extension.to_str() {
+                format = ConfigFileFormat::from_str(s).unwrap_or(format);
+            }
+        }
+
+        FileDiscoverInput {
+            path,
+            format,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct FileDiscoverConfig {
+    name: MetricsName,
+    position: Option<YamlDocPosition>,
+    pub(crate) watch: bool,
+    pub(crate) check_interval: Duration,
+}
+
+impl FileDiscoverConfig {
+    fn new(position: Option<YamlDocPosition>) -> Self {
+        FileDiscoverConfig {
+            name: MetricsName::default(),
+            position,
+            watch: true,
+            check_interval: Duration::from_secs(60),
+        }
+    }
+
+    fn check(&self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.check_interval.is_zero() {
+            return Err(anyhow!("check interval should not be zero"));
+        }
+        Ok(())
+    }
+}
+
+impl DiscoverConfig for FileDiscoverConfig {
+    #[inline]
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    #[inline]
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    #[inline]
+    fn discover_type(&self) -> &'static str {
+        DISCOVER_CONFIG_TYPE
+    }
+
+    fn diff_action(&self, new: &AnyDiscoverConfig) -> DiscoverConfigDiffAction {
+        let new = match new {
+            AnyDiscoverConfig::File(config) => config,
+            _ => return DiscoverConfigDiffAction::SpawnNew,
+        };
+
+        if self.eq(new) {
+            DiscoverConfigDiffAction::NoAction
+        } else {
+            DiscoverConfigDiffAction::SpawnNew
+        }
+    }
+}
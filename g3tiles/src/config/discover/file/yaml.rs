@@ -0,0 +1,131 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
+
+use g3_yaml::YamlDocPosition;
+
+use super::{FileDiscoverConfig, FileDiscoverInput};
+
+impl FileDiscoverConfig {
+    pub(crate) fn parse_yaml_conf(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut site = FileDiscoverConfig::new(position);
+        g3_yaml::foreach_kv(map, |k, v| site.set_yaml(k, v))?;
+        site.check()?;
+        Ok(site)
+    }
+
+    fn set_yaml(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_DISCOVER_TYPE => Ok(()),
+            super::CONFIG_KEY_DISCOVER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "watch" => {
+                self.watch = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "check_interval" => {
+                self.check_interval = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    pub(crate) fn parse_yaml_data(&self, input: &Yaml) -> anyhow::Result<FileDiscoverInput> {
+        let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+        match input {
+            Yaml::Hash(map) => {
+                let v = g3_yaml::hash_get_required(map, "path")?;
+                let path = g3_yaml::value::as_file_path(v, lookup_dir, false)
+                    .context("invalid file path value for key path")?;
+                let mut data = FileDiscoverInput::new(path);
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    "path" => Ok(()),
+                    "format" => {
+                        data.format = g3_yaml::value::as_config_file_format(v)
+                            .context(format!("invalid config file format value for key {k}"))?;
+                        Ok(())
+                    }
+                    "metadata" => {
+                        if let Yaml::Hash(map) = v {
+                            g3_yaml::foreach_kv(map, |mk, mv| {
+                                let value = g3_yaml::value::as_string(mv).context(format!(
+                                    "invalid string value for metadata key {mk}"
+                                ))?;
+                                data.metadata.insert(mk.to_string(), value);
+                                Ok(())
+                            })
+                        } else {
+                            Err(anyhow!("invalid map value for key {k}"))
+                        }
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+
+                Ok(data)
+            }
+            _ => {
+                let path = g3_yaml::value::as_file_path(input, lookup_dir, false)
+                    .context("invalid file path value")?;
+                Ok(FileDiscoverInput::new(path))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use yaml_rust::YamlLoader;
+
+    fn parse(s: &str) -> anyhow::Result<FileDiscoverConfig> {
+        let docs = YamlLoader::load_from_str(s).unwrap();
+        let Yaml::Hash(map) = &docs[0] else {
+            panic!("not a yaml map");
+        };
+        FileDiscoverConfig::parse_yaml_conf(map, None)
+    }
+
+    #[test]
+    fn normalize_key() {
+        let config = parse("name: test\nwatch: false\ncheck-interval: 10s\n").unwrap();
+        assert_eq!(config.name.as_str(), "test");
+        assert!(!config.watch);
+        assert_eq!(config.check_interval, Duration::from_secs(10));
+
+        let config = parse("name: test\ncheck_interval: 20s\n").unwrap();
+        assert!(config.watch);
+        assert_eq!(config.check_interval, Duration::from_secs(20));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse("name: test\ncheck_interval: 0\n").is_err());
+        assert!(parse("name: test\nunknown_key: 1\n").is_err());
+        assert!(parse("watch: true\n").is_err());
+    }
+}
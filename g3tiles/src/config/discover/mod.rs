@@ -25,6 +25,8 @@ use g3_yaml::{HybridParser, YamlDocPosition};
 mod registry;
 pub(crate) use registry::{clear, get_all};
 
+pub(crate) mod dns_srv;
+pub(crate) mod file;
 pub(crate) mod host_resolver;
 pub(crate) mod static_addr;
 
@@ -58,6 +60,8 @@ pub(crate) trait DiscoverConfig {
 pub(crate) enum AnyDiscoverConfig {
     StaticAddr(static_addr::StaticAddrDiscoverConfig),
     HostResolver(host_resolver::HostResolverDiscoverConfig),
+    File(file::FileDiscoverConfig),
+    DnsSrv(dns_srv::DnsSrvDiscoverConfig),
 }
 
 macro_rules! impl_transparent0 {
@@ -66,6 +70,8 @@ macro_rules! impl_transparent0 {
             match self {
                 AnyDiscoverConfig::StaticAddr(d) => d.$f(),
                 AnyDiscoverConfig::HostResolver(d) => d.$f(),
+                AnyDiscoverConfig::File(d) => d.$f(),
+                AnyDiscoverConfig::DnsSrv(d) => d.$f(),
             }
         }
     };
@@ -77,6 +83,8 @@ macro_rules! impl_transparent1 {
             match self {
                 AnyDiscoverConfig::StaticAddr(d) => d.$f(p),
                 AnyDiscoverConfig::HostResolver(d) => d.$f(p),
+                AnyDiscoverConfig::File(d) => d.$f(p),
+                AnyDiscoverConfig::DnsSrv(d) => d.$f(p),
             }
         }
     };
@@ -127,6 +135,16 @@ fn load_discover(
                     .context("failed to load this HostResolver discover")?;
             Ok(AnyDiscoverConfig::HostResolver(discover))
         }
+        "file" => {
+            let discover = file::FileDiscoverConfig::parse_yaml_conf(map, position)
+                .context("failed to load this File discover")?;
+            Ok(AnyDiscoverConfig::File(discover))
+        }
+        "dns_srv" | "dnssrv" => {
+            let discover = dns_srv::DnsSrvDiscoverConfig::parse_yaml_conf(map, position)
+                .context("failed to load this DnsSrv discover")?;
+            Ok(AnyDiscoverConfig::DnsSrv(discover))
+        }
         _ => Err(anyhow!("unsupported discover type {}", discover_type)),
     }
 }
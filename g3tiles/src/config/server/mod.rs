@@ -34,6 +34,9 @@ pub(crate) mod plain_tcp_port;
 pub(crate) mod openssl_proxy;
 pub(crate) mod rustls_proxy;
 
+#[cfg(feature = "quic")]
+pub(crate) mod quic_proxy;
+
 mod registry;
 
 pub(crate) use registry::clear;
@@ -81,6 +84,8 @@ pub(crate) enum AnyServerConfig {
     PlainTcpPort(plain_tcp_port::PlainTcpPortConfig),
     OpensslProxy(openssl_proxy::OpensslProxyServerConfig),
     RustlsProxy(rustls_proxy::RustlsProxyServerConfig),
+    #[cfg(feature = "quic")]
+    QuicProxy(quic_proxy::QuicProxyServerConfig),
 }
 
 macro_rules! impl_transparent0 {
@@ -91,6 +96,8 @@ macro_rules! impl_transparent0 {
                 AnyServerConfig::PlainTcpPort(s) => s.$f(),
                 AnyServerConfig::OpensslProxy(s) => s.$f(),
                 AnyServerConfig::RustlsProxy(s) => s.$f(),
+                #[cfg(feature = "quic")]
+                AnyServerConfig::QuicProxy(s) => s.$f(),
             }
         }
     };
@@ -104,6 +111,8 @@ macro_rules! impl_transparent1 {
                 AnyServerConfig::PlainTcpPort(s) => s.$f(p),
                 AnyServerConfig::OpensslProxy(s) => s.$f(p),
                 AnyServerConfig::RustlsProxy(s) => s.$f(p),
+                #[cfg(feature = "quic")]
+                AnyServerConfig::QuicProxy(s) => s.$f(p),
             }
         }
     };
@@ -166,6 +175,12 @@ fn load_server(
                 .context("failed to load this RustlsProxy server")?;
             Ok(AnyServerConfig::RustlsProxy(server))
         }
+        #[cfg(feature = "quic")]
+        "quic_proxy" | "quicproxy" => {
+            let server = quic_proxy::QuicProxyServerConfig::parse(map, position)
+                .context("failed to load this QuicProxy server")?;
+            Ok(AnyServerConfig::QuicProxy(server))
+        }
         _ => Err(anyhow!("unsupported server type {}", server_type)),
     }
 }
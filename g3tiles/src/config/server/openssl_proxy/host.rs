@@ -33,12 +33,17 @@ use g3_yaml::{YamlDocPosition, YamlMapCallback};
 #[cfg(feature = "vendored-tongsuo")]
 use g3_types::net::OpensslTlcpCertificatePair;
 
+#[cfg(feature = "openssl-async-job")]
+use super::OpensslKeylessCertPair;
+
 #[derive(Clone, Debug, Default, PartialEq)]
 pub(crate) struct OpensslHostConfig {
     name: String,
     cert_pairs: Vec<OpensslCertificatePair>,
     #[cfg(feature = "vendored-tongsuo")]
     tlcp_cert_pairs: Vec<OpensslTlcpCertificatePair>,
+    #[cfg(feature = "openssl-async-job")]
+    keyless_cert_pairs: Vec<OpensslKeylessCertPair>,
     client_auth: bool,
     client_auth_certs: Vec<Vec<u8>>,
     session_id_context: String,
@@ -123,8 +128,16 @@ impl OpensslHostConfig {
         Ok(())
     }
 
+    fn has_tls_cert_pairs(&self) -> bool {
+        #[cfg(feature = "openssl-async-job")]
+        if !self.keyless_cert_pairs.is_empty() {
+            return true;
+        }
+        !self.cert_pairs.is_empty()
+    }
+
     pub(crate) fn build_ssl_context(&self) -> anyhow::Result<Option<SslContext>> {
-        if self.cert_pairs.is_empty() {
+        if !self.has_tls_cert_pairs() {
             return Ok(None);
         }
 
@@ -155,6 +168,17 @@ impl OpensslHostConfig {
             pair.add_to_server_ssl_context(&mut ssl_builder, &mut id_ctx)
                 .context(format!("failed to add cert pair #{i} to ssl context"))?;
         }
+        #[cfg(feature = "openssl-async-job")]
+        if !self.keyless_cert_pairs.is_empty() {
+            for (i, pair) in self.keyless_cert_pairs.iter().enumerate() {
+                pair.add_to_server_ssl_context(&mut ssl_builder, &mut id_ctx)
+                    .context(format!(
+                        "failed to add keyless cert pair #{i} to ssl context"
+                    ))?;
+            }
+            // private key operations will be done in openssl async jobs
+            ssl_builder.set_mode(openssl::ssl::SslMode::ASYNC);
+        }
 
         id_ctx
             .build_set(&mut ssl_builder)
@@ -266,6 +290,17 @@ impl YamlMapCallback for OpensslHostConfig {
                 ))?;
                 Ok(())
             }
+            #[cfg(feature = "openssl-async-job")]
+            "keyless_cert_pairs" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(doc)?;
+                self.keyless_cert_pairs = g3_yaml::value::as_list(value, |v| {
+                    OpensslKeylessCertPair::parse(v, Some(lookup_dir))
+                })
+                .context(format!(
+                    "invalid keyless cert pair list value for key {key}"
+                ))?;
+                Ok(())
+            }
             "enable_client_auth" => {
                 self.client_auth = g3_yaml::value::as_bool(value)
                     .context(format!("invalid value for key {key}"))?;
@@ -319,11 +354,11 @@ impl YamlMapCallback for OpensslHostConfig {
             return Err(anyhow!("no name set"));
         }
         #[cfg(not(feature = "vendored-tongsuo"))]
-        if self.cert_pairs.is_empty() {
+        if !self.has_tls_cert_pairs() {
             return Err(anyhow!("no certificate set"));
         }
         #[cfg(feature = "vendored-tongsuo")]
-        if self.cert_pairs.is_empty() && self.tlcp_cert_pairs.is_empty() {
+        if !self.has_tls_cert_pairs() && self.tlcp_cert_pairs.is_empty() {
             return Err(anyhow!("neither tls nor tlcp certificate set"));
         }
         if self.backends.is_empty() {
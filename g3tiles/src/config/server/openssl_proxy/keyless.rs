@@ -0,0 +1,273 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use openssl::hash::MessageDigest;
+use openssl::ssl::SslContextBuilder;
+use openssl::x509::X509;
+use yaml_rust::Yaml;
+
+use g3_types::net::{Host, OpensslClientConfigBuilder, OpensslSessionIdContext};
+
+use crate::module::keyless::KeylessPool;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KeylessBackendConfig {
+    pub(crate) servers: Vec<SocketAddr>,
+    pub(crate) connections_per_server: usize,
+    pub(crate) connect_timeout: Duration,
+    pub(crate) request_timeout: Duration,
+    pub(crate) tls_client: Option<OpensslClientConfigBuilder>,
+    pub(crate) tls_name: Option<Host>,
+    no_tls: bool,
+}
+
+impl Default for KeylessBackendConfig {
+    fn default() -> Self {
+        KeylessBackendConfig {
+            servers: Vec::new(),
+            connections_per_server: 1,
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(5),
+            tls_client: None,
+            tls_name: None,
+            no_tls: false,
+        }
+    }
+}
+
+impl KeylessBackendConfig {
+    fn set(&mut self, k: &str, v: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            "server" | "servers" => {
+                self.servers = g3_yaml::value::as_list(v, g3_yaml::value::as_sockaddr)
+                    .context(format!("invalid socket address list value for key {k}"))?;
+                Ok(())
+            }
+            "connections_per_server" | "connection_count" => {
+                self.connections_per_server = g3_yaml::value::as_usize(v)
+                    .context(format!("invalid usize value for key {k}"))?;
+                Ok(())
+            }
+            "connect_timeout" => {
+                self.connect_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "request_timeout" => {
+                self.request_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "tls" | "tls_client" => {
+                let builder =
+                    g3_yaml::value::as_to_one_openssl_tls_client_config_builder(v, lookup_dir)
+                        .context(format!(
+                            "invalid openssl tls client config value for key {k}"
+                        ))?;
+                self.tls_client = Some(builder);
+                Ok(())
+            }
+            "tls_name" => {
+                let name = g3_yaml::value::as_host(v)
+                    .context(format!("invalid tls server name value for key {k}"))?;
+                self.tls_name = Some(name);
+                Ok(())
+            }
+            "no_tls" => {
+                self.no_tls = g3_yaml::value::as_bool(v)
+                    .context(format!("invalid bool value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.servers.is_empty() {
+            return Err(anyhow!("no keyless server set"));
+        }
+        if self.connections_per_server == 0 {
+            return Err(anyhow!("connections per server should not be 0"));
+        }
+        if self.no_tls {
+            if self.tls_client.is_some() {
+                return Err(anyhow!("tls client config is set while no_tls is enabled"));
+            }
+        } else if self.tls_client.is_none() {
+            // plaintext connections should be explicitly enabled by no_tls
+            self.tls_client = Some(OpensslClientConfigBuilder::with_cache_for_one_site());
+        }
+        Ok(())
+    }
+}
+
+/// certificate pair with the private key hold by remote keyless servers
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct OpensslKeylessCertPair {
+    leaf_cert: Vec<u8>,
+    chain_certs: Vec<Vec<u8>>,
+    backend: KeylessBackendConfig,
+}
+
+impl OpensslKeylessCertPair {
+    pub(crate) fn parse(value: &Yaml, lookup_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let Yaml::Hash(map) = value else {
+            return Err(anyhow!(
+                "yaml value type for keyless certificate pair should be 'map'"
+            ));
+        };
+
+        let mut pair = OpensslKeylessCertPair::default();
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "certificate" | "cert" => {
+                let certs = g3_yaml::value::as_openssl_certificates(v, lookup_dir)
+                    .context(format!("invalid certificates value for key {k}"))?;
+                pair.set_certificates(certs)
+            }
+            _ => pair.backend.set(k, v, lookup_dir),
+        })?;
+
+        pair.check()?;
+        Ok(pair)
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.leaf_cert.is_empty() {
+            return Err(anyhow!("no certificate set"));
+        }
+        self.backend.check()
+    }
+
+    fn set_certificates(&mut self, certs: Vec<X509>) -> anyhow::Result<()> {
+        let mut certs_iter = certs.into_iter();
+        let leaf_cert = certs_iter
+            .next()
+            .ok_or_else(|| anyhow!("no certificate found"))?;
+        self.leaf_cert = leaf_cert
+            .to_der()
+            .map_err(|e| anyhow!("failed to encode certificate: {e}"))?;
+
+        let mut chain_certs = Vec::new();
+        for (i, cert) in certs_iter.enumerate() {
+            let bytes = cert
+                .to_der()
+                .map_err(|e| anyhow!("failed to encode chain certificate #{i}: {e}"))?;
+            chain_certs.push(bytes);
+        }
+        self.chain_certs = chain_certs;
+        Ok(())
+    }
+
+    pub(crate) fn add_to_server_ssl_context(
+        &self,
+        ssl_builder: &mut SslContextBuilder,
+        id_ctx: &mut OpensslSessionIdContext,
+    ) -> anyhow::Result<()> {
+        let leaf_cert = X509::from_der(self.leaf_cert.as_slice()).unwrap();
+        ssl_builder
+            .set_certificate(&leaf_cert)
+            .map_err(|e| anyhow!("failed to set certificate: {e}"))?;
+        id_ctx
+            .add_cert(&leaf_cert)
+            .map_err(|e| anyhow!("failed to add cert to session id context: {e}"))?;
+
+        for (i, cert) in self.chain_certs.iter().enumerate() {
+            let chain_cert = X509::from_der(cert.as_slice()).unwrap();
+            ssl_builder
+                .add_extra_chain_cert(chain_cert)
+                .map_err(|e| anyhow!("failed to add chain certificate #{i}: {e}"))?;
+        }
+
+        let ski = leaf_cert
+            .pubkey_digest(MessageDigest::sha1())
+            .map_err(|e| anyhow!("failed to get SKI of the certificate: {e}"))?;
+        let public_key = leaf_cert
+            .public_key()
+            .map_err(|e| anyhow!("failed to get public key of the certificate: {e}"))?;
+        let tls_client = match &self.backend.tls_client {
+            Some(builder) => Some(
+                builder
+                    .build()
+                    .context("failed to build keyless tls client config")?,
+            ),
+            None => None,
+        };
+        let pool = KeylessPool::new(self.backend.clone(), tls_client, ski.to_vec());
+        let key = g3_openssl::async_job::new_async_private_key(&public_key, Arc::new(pool))
+            .context("failed to create keyless private key")?;
+        ssl_builder
+            .set_private_key(&key)
+            .map_err(|e| anyhow!("failed to set private key: {e}"))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn parse_backend(s: &str) -> anyhow::Result<KeylessBackendConfig> {
+        let docs = YamlLoader::load_from_str(s).unwrap();
+        let Yaml::Hash(map) = &docs[0] else {
+            unreachable!()
+        };
+        let mut backend = KeylessBackendConfig::default();
+        g3_yaml::foreach_kv(map, |k, v| backend.set(k, v, None))?;
+        backend.check()?;
+        Ok(backend)
+    }
+
+    #[test]
+    fn tls_by_default() {
+        let backend = parse_backend("servers: [\"127.0.0.1:1300\"]").unwrap();
+        assert!(backend.tls_client.is_some());
+        assert!(backend.tls_name.is_none());
+
+        let backend = parse_backend(
+            "servers: [\"127.0.0.1:1300\"]\ntls_name: keyless.example.net\ntls_client: {}",
+        )
+        .unwrap();
+        assert!(backend.tls_client.is_some());
+        assert_eq!(
+            backend.tls_name,
+            Some(Host::Domain("keyless.example.net".into()))
+        );
+    }
+
+    #[test]
+    fn plaintext_opt_in() {
+        let backend = parse_backend("servers: [\"127.0.0.1:1300\"]\nno_tls: true").unwrap();
+        assert!(backend.tls_client.is_none());
+
+        assert!(
+            parse_backend("servers: [\"127.0.0.1:1300\"]\nno_tls: true\ntls_client: {}").is_err()
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_backend("connection_count: 1").is_err());
+        assert!(parse_backend("servers: [\"127.0.0.1:1300\"]\nconnection_count: 0").is_err());
+        assert!(parse_backend("servers: [\"127.0.0.1:1300\"]\nunknown: 1").is_err());
+    }
+}
@@ -34,6 +34,13 @@ use crate::config::server::{AnyServerConfig, ServerConfigDiffAction};
 mod host;
 pub(crate) use host::OpensslHostConfig;
 
+#[cfg(feature = "openssl-async-job")]
+mod keyless;
+#[cfg(feature = "openssl-async-job")]
+pub(crate) use keyless::KeylessBackendConfig;
+#[cfg(feature = "openssl-async-job")]
+use keyless::OpensslKeylessCertPair;
+
 const SERVER_CONFIG_TYPE: &str = "OpensslProxy";
 
 #[derive(Clone, Debug, PartialEq)]
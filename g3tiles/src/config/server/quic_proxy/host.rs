@@ -0,0 +1,148 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use rustls::server::ResolvesServerCert;
+use yaml_rust::Yaml;
+
+use g3_types::collection::NamedValue;
+use g3_types::limit::RateLimitQuotaConfig;
+use g3_types::metrics::MetricsName;
+use g3_types::net::{MultipleCertResolver, RustlsCertificatePair, TcpSockSpeedLimitConfig};
+use g3_types::route::AlpnMatch;
+use g3_yaml::{YamlDocPosition, YamlMapCallback};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct QuicHostConfig {
+    name: String,
+    cert_pairs: Vec<RustlsCertificatePair>,
+    pub(crate) request_alive_max: Option<usize>,
+    pub(crate) request_rate_limit: Option<RateLimitQuotaConfig>,
+    pub(crate) stream_speed_limit: Option<TcpSockSpeedLimitConfig>,
+    pub(crate) task_idle_max_count: Option<i32>,
+    pub(crate) backends: AlpnMatch<MetricsName>,
+}
+
+impl Default for QuicHostConfig {
+    fn default() -> Self {
+        QuicHostConfig {
+            name: String::new(),
+            cert_pairs: Vec::with_capacity(1),
+            request_alive_max: None,
+            request_rate_limit: None,
+            stream_speed_limit: None,
+            task_idle_max_count: None,
+            backends: AlpnMatch::default(),
+        }
+    }
+}
+
+impl NamedValue for QuicHostConfig {
+    type Name = str;
+    type NameOwned = String;
+
+    fn name(&self) -> &Self::Name {
+        self.name.as_str()
+    }
+
+    fn name_owned(&self) -> Self::NameOwned {
+        self.name.clone()
+    }
+}
+
+impl QuicHostConfig {
+    pub(crate) fn build_cert_resolver(&self) -> anyhow::Result<Arc<dyn ResolvesServerCert>> {
+        let mut cert_resolver = MultipleCertResolver::with_capacity(self.cert_pairs.len());
+        for (i, pair) in self.cert_pairs.iter().enumerate() {
+            cert_resolver
+                .push_cert_pair(pair)
+                .context(format!("failed to add cert pair {i}"))?;
+        }
+        Ok(Arc::new(cert_resolver))
+    }
+}
+
+impl YamlMapCallback for QuicHostConfig {
+    fn type_name(&self) -> &'static str {
+        "QuicHostConfig"
+    }
+
+    fn parse_kv(
+        &mut self,
+        key: &str,
+        value: &Yaml,
+        doc: Option<&YamlDocPosition>,
+    ) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(key).as_str() {
+            "name" => {
+                self.name = g3_yaml::value::as_string(value)?;
+                Ok(())
+            }
+            "cert_pairs" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(doc)?;
+                self.cert_pairs = g3_yaml::value::as_list(value, |v| {
+                    g3_yaml::value::as_rustls_certificate_pair(v, Some(lookup_dir))
+                })
+                .context(format!("invalid rustls cert pair list value for key {key}"))?;
+                Ok(())
+            }
+            "request_rate_limit" | "request_limit_quota" => {
+                let quota = g3_yaml::value::as_rate_limit_quota(value)
+                    .context(format!("invalid request quota value for key {key}"))?;
+                self.request_rate_limit = Some(quota);
+                Ok(())
+            }
+            "request_max_alive" | "request_alive_max" => {
+                let alive_max = g3_yaml::value::as_usize(value)
+                    .context(format!("invalid usize value for key {key}"))?;
+                self.request_alive_max = Some(alive_max);
+                Ok(())
+            }
+            "stream_speed_limit" => {
+                let limit = g3_yaml::value::as_tcp_sock_speed_limit(value)
+                    .context(format!("invalid stream speed limit value for key {key}"))?;
+                self.stream_speed_limit = Some(limit);
+                Ok(())
+            }
+            "task_idle_max_count" => {
+                let max_count = g3_yaml::value::as_i32(value)
+                    .context(format!("invalid i32 value for key {key}"))?;
+                self.task_idle_max_count = Some(max_count);
+                Ok(())
+            }
+            "backends" => {
+                self.backends = g3_yaml::value::as_alpn_matched_backends(value)?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {key}")),
+        }
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("no name set"));
+        }
+        if self.cert_pairs.is_empty() {
+            return Err(anyhow!("no certificate set"));
+        }
+        if self.backends.is_empty() {
+            return Err(anyhow!("no backend service set"));
+        }
+        Ok(())
+    }
+}
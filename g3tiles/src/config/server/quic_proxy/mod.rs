@@ -0,0 +1,396 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use ascii::AsciiString;
+use rustls::server::{AllowAnyAuthenticatedClient, ResolvesServerCert};
+use rustls::{Certificate, RootCertStore, ServerConfig as RustlsServerConfig};
+use yaml_rust::{yaml, Yaml};
+
+use g3_io_ext::LimitedCopyConfig;
+use g3_types::acl::AclNetworkRuleBuilder;
+use g3_types::metrics::{MetricsName, StaticMetricsTags};
+use g3_types::net::{
+    RustlsServerSessionCache, RustlsSessionTicketer, TcpSockSpeedLimitConfig, UdpListenConfig,
+};
+use g3_types::route::HostMatch;
+use g3_yaml::YamlDocPosition;
+
+use super::{ServerConfig, IDLE_CHECK_DEFAULT_DURATION, IDLE_CHECK_MAXIMUM_DURATION};
+use crate::config::server::{AnyServerConfig, ServerConfigDiffAction};
+
+mod host;
+pub(crate) use host::QuicHostConfig;
+
+const SERVER_CONFIG_TYPE: &str = "QuicProxy";
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct QuicProxyServerConfig {
+    name: MetricsName,
+    position: Option<YamlDocPosition>,
+    pub(crate) shared_logger: Option<AsciiString>,
+    pub(crate) listen: UdpListenConfig,
+    pub(crate) listen_in_worker: bool,
+    pub(crate) offline_rebind_port: Option<u16>,
+    pub(crate) ingress_net_filter: Option<AclNetworkRuleBuilder>,
+    pub(crate) extra_metrics_tags: Option<Arc<StaticMetricsTags>>,
+    pub(crate) accept_timeout: Duration,
+    client_auth: bool,
+    client_auth_certs: Vec<Certificate>,
+    use_session_ticket: bool,
+    pub(crate) hosts: HostMatch<Arc<QuicHostConfig>>,
+    pub(crate) stream_speed_limit: TcpSockSpeedLimitConfig,
+    pub(crate) task_idle_check_duration: Duration,
+    pub(crate) task_idle_max_count: i32,
+    pub(crate) stream_copy: LimitedCopyConfig,
+    pub(crate) spawn_task_unconstrained: bool,
+}
+
+impl QuicProxyServerConfig {
+    pub(crate) fn new(position: Option<YamlDocPosition>) -> Self {
+        QuicProxyServerConfig {
+            name: MetricsName::default(),
+            position,
+            shared_logger: None,
+            listen: UdpListenConfig::default(),
+            listen_in_worker: false,
+            offline_rebind_port: None,
+            ingress_net_filter: None,
+            extra_metrics_tags: None,
+            accept_timeout: Duration::from_secs(60),
+            client_auth: false,
+            client_auth_certs: Vec::new(),
+            use_session_ticket: false,
+            hosts: HostMatch::default(),
+            stream_speed_limit: TcpSockSpeedLimitConfig::default(),
+            task_idle_check_duration: IDLE_CHECK_DEFAULT_DURATION,
+            task_idle_max_count: 1,
+            stream_copy: Default::default(),
+            spawn_task_unconstrained: false,
+        }
+    }
+
+    pub(super) fn parse(
+        map: &yaml::Hash,
+        position: Option<YamlDocPosition>,
+    ) -> anyhow::Result<Self> {
+        let mut server = QuicProxyServerConfig::new(position);
+
+        g3_yaml::foreach_kv(map, |k, v| server.set(k, v))?;
+
+        server.check()?;
+        Ok(server)
+    }
+
+    fn check(&mut self) -> anyhow::Result<()> {
+        if self.name.is_empty() {
+            return Err(anyhow!("name is not set"));
+        }
+        if self.hosts.is_empty() {
+            return Err(anyhow!("no host config set"));
+        }
+        self.check_alpn_protocols()?;
+        // make sure listen is always set
+        self.listen.check().context("invalid listen config")?;
+        if self.task_idle_check_duration > IDLE_CHECK_MAXIMUM_DURATION {
+            self.task_idle_check_duration = IDLE_CHECK_MAXIMUM_DURATION;
+        }
+        Ok(())
+    }
+
+    /// All hosts share one rustls config, so the alpn protocols offered can not
+    /// vary by SNI, and the hosts must agree on them.
+    fn check_alpn_protocols(&self) -> anyhow::Result<()> {
+        let hosts: BTreeMap<String, Arc<QuicHostConfig>> =
+            self.hosts.get_all_values().into_iter().collect();
+        let mut hosts_iter = hosts.iter();
+        let Some((first_name, first_host)) = hosts_iter.next() else {
+            return Ok(());
+        };
+        for (name, host) in hosts_iter {
+            if host.backends.protocols() != first_host.backends.protocols() {
+                return Err(anyhow!(
+                    "host {name} has different alpn protocols than host {first_name}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, k: &str, v: &Yaml) -> anyhow::Result<()> {
+        match g3_yaml::key::normalize(k).as_str() {
+            super::CONFIG_KEY_SERVER_TYPE => Ok(()),
+            super::CONFIG_KEY_SERVER_NAME => {
+                self.name = g3_yaml::value::as_metrics_name(v)?;
+                Ok(())
+            }
+            "shared_logger" => {
+                let name = g3_yaml::value::as_ascii(v)?;
+                self.shared_logger = Some(name);
+                Ok(())
+            }
+            "extra_metrics_tags" => {
+                let tags = g3_yaml::value::as_static_metrics_tags(v)
+                    .context(format!("invalid static metrics tags value for key {k}"))?;
+                self.extra_metrics_tags = Some(Arc::new(tags));
+                Ok(())
+            }
+            "listen" => {
+                self.listen = g3_yaml::value::as_udp_listen_config(v)
+                    .context(format!("invalid udp listen config value for key {k}"))?;
+                Ok(())
+            }
+            "listen_in_worker" => {
+                self.listen_in_worker = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            "offline_rebind_port" => {
+                let port = g3_yaml::value::as_u16(v)?;
+                self.offline_rebind_port = Some(port);
+                Ok(())
+            }
+            "ingress_network_filter" | "ingress_net_filter" => {
+                let filter = g3_yaml::value::acl::as_ingress_network_rule_builder(v).context(
+                    format!("invalid ingress network acl rule value for key {k}"),
+                )?;
+                self.ingress_net_filter = Some(filter);
+                Ok(())
+            }
+            "accept_timeout" | "handshake_timeout" | "negotiation_timeout" => {
+                self.accept_timeout = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "enable_client_auth" => {
+                self.client_auth =
+                    g3_yaml::value::as_bool(v).context(format!("invalid value for key {k}"))?;
+                Ok(())
+            }
+            "ca_certificate" | "ca_cert" | "client_auth_certificate" | "client_auth_cert" => {
+                let lookup_dir = g3_daemon::config::get_lookup_dir(self.position.as_ref())?;
+                let certs = g3_yaml::value::as_rustls_certificates(v, Some(lookup_dir))
+                    .context(format!("invalid certificate(s) value for key {k}"))?;
+                for cert in certs {
+                    self.client_auth_certs.push(cert);
+                }
+                Ok(())
+            }
+            "use_session_ticket" => {
+                self.use_session_ticket =
+                    g3_yaml::value::as_bool(v).context(format!("invalid value for key {k}"))?;
+                Ok(())
+            }
+            "hosts" => {
+                self.hosts = g3_yaml::value::as_host_matched_obj(v, self.position.as_ref())?;
+                Ok(())
+            }
+            "stream_speed_limit" => {
+                self.stream_speed_limit = g3_yaml::value::as_tcp_sock_speed_limit(v)
+                    .context(format!("invalid stream speed limit value for key {k}"))?;
+                Ok(())
+            }
+            "task_idle_check_duration" => {
+                self.task_idle_check_duration = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            "task_idle_max_count" => {
+                self.task_idle_max_count =
+                    g3_yaml::value::as_i32(v).context(format!("invalid i32 value for key {k}"))?;
+                Ok(())
+            }
+            "stream_copy_buffer_size" => {
+                let buffer_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.stream_copy.set_buffer_size(buffer_size);
+                Ok(())
+            }
+            "stream_copy_yield_size" => {
+                let yield_size = g3_yaml::humanize::as_usize(v)
+                    .context(format!("invalid humanize usize value for key {k}"))?;
+                self.stream_copy.set_yield_size(yield_size);
+                Ok(())
+            }
+            "spawn_task_unconstrained" | "task_unconstrained" => {
+                self.spawn_task_unconstrained = g3_yaml::value::as_bool(v)?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        }
+    }
+
+    pub(crate) fn build_tls_config(
+        &self,
+        cert_resolver: Arc<dyn ResolvesServerCert>,
+    ) -> anyhow::Result<Arc<RustlsServerConfig>> {
+        let config_builder = RustlsServerConfig::builder()
+            .with_safe_default_cipher_suites()
+            .with_safe_default_kx_groups()
+            .with_protocol_versions(&[&rustls::version::TLS13])
+            .map_err(|e| anyhow!("failed to set tls protocol versions: {e}"))?;
+        let config_builder = if self.client_auth {
+            let mut root_store = RootCertStore::empty();
+            if self.client_auth_certs.is_empty() {
+                let certs = g3_types::net::load_native_certs_for_rustls()?;
+                for (i, cert) in certs.iter().enumerate() {
+                    root_store.add(cert).map_err(|e| {
+                        anyhow!("failed to add openssl ca cert {i} as root certs for client auth: {e:?}",)
+                    })?;
+                }
+            } else {
+                for (i, cert) in self.client_auth_certs.iter().enumerate() {
+                    root_store.add(cert).map_err(|e| {
+                        anyhow!("failed to add cert {i} as root certs for client auth: {e:?}",)
+                    })?;
+                }
+            }
+            config_builder
+                .with_client_cert_verifier(Arc::new(AllowAnyAuthenticatedClient::new(root_store)))
+        } else {
+            config_builder.with_no_client_auth()
+        };
+
+        let mut config = config_builder.with_cert_resolver(cert_resolver);
+
+        config.session_storage = Arc::new(RustlsServerSessionCache::default());
+        if self.use_session_ticket {
+            let ticketer =
+                RustlsSessionTicketer::new().context("failed to create session ticketer")?;
+            config.ticketer = Arc::new(ticketer);
+        }
+
+        // all hosts have the same alpn protocols, which is ensured in check
+        let hosts: BTreeMap<String, Arc<QuicHostConfig>> =
+            self.hosts.get_all_values().into_iter().collect();
+        if let Some(host) = hosts.values().next() {
+            for protocol in host.backends.protocols() {
+                config.alpn_protocols.push(protocol.clone().into_bytes());
+            }
+        }
+
+        Ok(Arc::new(config))
+    }
+}
+
+impl ServerConfig for QuicProxyServerConfig {
+    fn name(&self) -> &MetricsName {
+        &self.name
+    }
+
+    fn position(&self) -> Option<YamlDocPosition> {
+        self.position.clone()
+    }
+
+    fn server_type(&self) -> &'static str {
+        SERVER_CONFIG_TYPE
+    }
+
+    fn diff_action(&self, new: &AnyServerConfig) -> ServerConfigDiffAction {
+        let new = match new {
+            AnyServerConfig::QuicProxy(config) => config,
+            _ => return ServerConfigDiffAction::SpawnNew,
+        };
+
+        if self.eq(new) {
+            return ServerConfigDiffAction::NoAction;
+        }
+
+        if self.listen_in_worker != new.listen_in_worker {
+            return ServerConfigDiffAction::ReloadAndRespawn;
+        }
+
+        // listen and tls config changes will be pushed to the running listener
+        ServerConfigDiffAction::ReloadOnlyConfig
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    use g3_types::collection::NamedValue;
+    use g3_types::net::Host;
+    use g3_yaml::YamlMapCallback;
+
+    fn new_host(name: &str, protocols: &[(&str, &str)]) -> Arc<QuicHostConfig> {
+        let mut host = QuicHostConfig::default();
+        host.parse_kv("name", &Yaml::String(name.to_string()), None)
+            .unwrap();
+        for (protocol, backend) in protocols {
+            host.backends.add_protocol(
+                protocol.to_string(),
+                MetricsName::from_str(backend).unwrap(),
+            );
+        }
+        Arc::new(host)
+    }
+
+    #[test]
+    fn alpn_same() {
+        let mut config = QuicProxyServerConfig::new(None);
+        config.hosts.add_exact_domain(
+            "a.example.net".to_string(),
+            new_host("a", &[("h3", "a-h3"), ("dns", "a-dns")]),
+        );
+        config
+            .hosts
+            .set_default(new_host("b", &[("dns", "b-dns"), ("h3", "b-h3")]));
+        assert!(config.check_alpn_protocols().is_ok());
+    }
+
+    #[test]
+    fn alpn_mismatch() {
+        let mut config = QuicProxyServerConfig::new(None);
+        config.hosts.add_exact_domain(
+            "a.example.net".to_string(),
+            new_host("a", &[("h3", "a-h3")]),
+        );
+        config
+            .hosts
+            .set_default(new_host("b", &[("h3", "b-h3"), ("dns", "b-dns")]));
+        assert!(config.check_alpn_protocols().is_err());
+    }
+
+    #[test]
+    fn select_host() {
+        let mut hosts = HostMatch::default();
+        hosts.add_exact_domain(
+            "a.example.net".to_string(),
+            new_host("a", &[("h3", "a-h3"), ("dns", "a-dns")]),
+        );
+        hosts.set_default(new_host("b", &[("h3", "b-h3"), ("dns", "b-dns")]));
+
+        let host = hosts
+            .get(&Host::from_str("a.example.net").unwrap())
+            .unwrap();
+        assert_eq!(host.name(), "a");
+        assert_eq!(host.backends.get("h3").unwrap().as_str(), "a-h3");
+        assert_eq!(host.backends.get("dns").unwrap().as_str(), "a-dns");
+        assert!(host.backends.get("h2").is_none());
+
+        // unmatched sni falls back to the default host
+        let host = hosts
+            .get(&Host::from_str("b.example.net").unwrap())
+            .unwrap();
+        assert_eq!(host.name(), "b");
+        assert_eq!(host.backends.get("h3").unwrap().as_str(), "b-h3");
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context};
+use hickory_resolver::proto::rr::rdata::SRV;
+use hickory_resolver::TokioAsyncResolver;
+use log::{debug, warn};
+use tokio::sync::watch;
+use yaml_rust::Yaml;
+
+use g3_types::collection::WeightedValue;
+
+use super::{ArcDiscover, Discover, DiscoverResult, DiscoveredData};
+use crate::config::discover::dns_srv::DnsSrvDiscoverConfig;
+use crate::config::discover::{AnyDiscoverConfig, DiscoverConfig};
+
+// RFC 2782: weight 0 should have a very small chance of being selected
+const SRV_ZERO_WEIGHT: f64 = 0.01;
+/// the minimal wait time before retry on query failure
+const MIN_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(crate) struct DnsSrvDiscover {
+    config: DnsSrvDiscoverConfig,
+    resolver: Arc<TokioAsyncResolver>,
+}
+
+impl DnsSrvDiscover {
+    pub(crate) fn new_obj(config: DnsSrvDiscoverConfig) -> anyhow::Result<ArcDiscover> {
+        let resolver = config.driver.build_async_resolver().context(format!(
+            "failed to build resolver for discover {}",
+            config.name()
+        ))?;
+        Ok(Arc::new(DnsSrvDiscover {
+            config,
+            resolver: Arc::new(resolver),
+        }))
+    }
+}
+
+impl Discover for DnsSrvDiscover {
+    fn _clone_config(&self) -> AnyDiscoverConfig {
+        AnyDiscoverConfig::DnsSrv(self.config.clone())
+    }
+
+    fn _update_config_in_place(&self, _config: AnyDiscoverConfig) -> anyhow::Result<()> {
+        Err(anyhow!(
+            "discover {} does not support in place update",
+            self.config.name()
+        ))
+    }
+
+    fn register_yaml(&self, data: &Yaml) -> anyhow::Result<watch::Receiver<DiscoverResult>> {
+        let input = self.config.parse_yaml_data(data).context(format!(
+            "invalid input data for discover {}",
+            self.config.name()
+        ))?;
+        let resolver = self.resolver.clone();
+        let min_refresh_interval = self.config.min_refresh_interval;
+        let retry_interval = Duration::from_secs(self.config.driver.get_negative_min_ttl() as u64)
+            .max(min_refresh_interval)
+            .max(MIN_RETRY_INTERVAL);
+
+        let (sender, receiver) = watch::channel(Ok(Vec::new()));
+        let discover = self.config.name().clone();
+        tokio::spawn(async move {
+            // add trailing '.' to avoid search
+            let query_name = if input.name.ends_with('.') {
+                input.name
+            } else {
+                format!("{}.", input.name)
+            };
+            loop {
+                let wait = match query_srv(&resolver, &query_name).await {
+                    Ok((data, valid_until)) => {
+                        sender.send_replace(Ok(data));
+                        valid_until
+                            .saturating_duration_since(Instant::now())
+                            .max(min_refresh_interval)
+                    }
+                    Err(e) => {
+                        warn!("discover {discover}: failed to query srv {query_name}: {e:?}");
+                        sender.send_replace(Err(e));
+                        retry_interval
+                    }
+                };
+                match tokio::time::timeout(wait, sender.closed()).await {
+                    Ok(_) => break,
+                    Err(_) => continue,
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+async fn query_srv(
+    resolver: &TokioAsyncResolver,
+    name: &str,
+) -> anyhow::Result<(DiscoveredData, Instant)> {
+    let lookup = resolver
+        .srv_lookup(name)
+        .await
+        .map_err(|e| anyhow!("srv lookup failed: {e}"))?;
+    let mut valid_until = lookup.valid_until();
+
+    let mut priority_groups = BTreeMap::<u16, Vec<&SRV>>::new();
+    for srv in lookup.iter() {
+        // a target of "." means the service is decidedly not available at this domain
+        if srv.target().is_root() {
+            continue;
+        }
+        priority_groups.entry(srv.priority()).or_default().push(srv);
+    }
+    if priority_groups.is_empty() {
+        return Err(anyhow!("no available target found in srv records"));
+    }
+
+    // only use the targets with the lowest priority value that can be resolved
+    for (priority, records) in priority_groups {
+        let resolve_jobs = records
+            .iter()
+            .map(|srv| resolver.lookup_ip(srv.target().to_string()));
+        let results = futures_util::future::join_all(resolve_jobs).await;
+
+        let mut data = DiscoveredData::new();
+        for (srv, r) in records.iter().zip(results) {
+            match r {
+                Ok(ips) => {
+                    valid_until = valid_until.min(ips.valid_until());
+                    let ips: Vec<IpAddr> = ips.iter().collect();
+                    if ips.is_empty() {
+                        continue;
+                    }
+                    let weight = if srv.weight() == 0 {
+                        SRV_ZERO_WEIGHT
+                    } else {
+                        srv.weight() as f64
+                    };
+                    let weight = weight / ips.len() as f64;
+                    for ip in ips {
+                        let addr = SocketAddr::new(ip, srv.port());
+                        data.push(WeightedValue::with_weight(addr, weight));
+                    }
+                }
+                Err(e) => {
+                    debug!("failed to resolve srv target {}: {e}", srv.target());
+                }
+            }
+        }
+        if !data.is_empty() {
+            return Ok((data, valid_until));
+        }
+        debug!("no srv target with priority {priority} can be resolved");
+    }
+
+    Err(anyhow!("no srv target can be resolved"))
+}
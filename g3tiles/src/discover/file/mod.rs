@@ -0,0 +1,121 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context};
+use log::warn;
+use tokio::sync::watch;
+use yaml_rust::Yaml;
+
+use g3_types::fs::ConfigFileFormat;
+
+use super::{ArcDiscover, Discover, DiscoverResult, DiscoveredData};
+use crate::config::discover::file::{FileDiscoverConfig, FileDiscoverInput};
+use crate::config::discover::{AnyDiscoverConfig, DiscoverConfig};
+
+mod peer;
+
+mod watch_file;
+use watch_file::FileWatcher;
+
+pub(crate) struct FileDiscover {
+    config: FileDiscoverConfig,
+}
+
+impl FileDiscover {
+    pub(crate) fn new_obj(config: FileDiscoverConfig) -> ArcDiscover {
+        Arc::new(FileDiscover { config })
+    }
+}
+
+impl Discover for FileDiscover {
+    fn _clone_config(&self) -> AnyDiscoverConfig {
+        AnyDiscoverConfig::File(self.config.clone())
+    }
+
+    fn _update_config_in_place(&self, _config: AnyDiscoverConfig) -> anyhow::Result<()> {
+        Err(anyhow!(
+            "discover {} does not support in place update",
+            self.config.name()
+        ))
+    }
+
+    fn register_yaml(&self, data: &Yaml) -> anyhow::Result<watch::Receiver<DiscoverResult>> {
+        let input = self.config.parse_yaml_data(data).context(format!(
+            "invalid input data for discover {}",
+            self.config.name()
+        ))?;
+        let mut watcher = FileWatcher::new(&input.path, self.config.watch).context(format!(
+            "failed to watch file {} for discover {}",
+            input.path.display(),
+            self.config.name()
+        ))?;
+        let check_interval = self.config.check_interval;
+
+        let (sender, receiver) = watch::channel(Ok(Vec::new()));
+        let discover = self.config.name().clone();
+        tokio::spawn(async move {
+            loop {
+                let r = load_peers(&input).await;
+                if let Err(e) = &r {
+                    warn!("discover {discover}: failed to load peers: {e:?}");
+                }
+                sender.send_if_modified(|old| {
+                    if let (Ok(old), Ok(new)) = (&*old, &r) {
+                        if old.eq(new) {
+                            return false;
+                        }
+                    }
+                    *old = r;
+                    true
+                });
+
+                tokio::select! {
+                    _ = sender.closed() => break,
+                    _ = watcher.changed() => {}
+                    _ = tokio::time::sleep(check_interval) => {}
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+async fn load_peers(input: &FileDiscoverInput) -> anyhow::Result<DiscoveredData> {
+    let contents = tokio::fs::read_to_string(&input.path)
+        .await
+        .map_err(|e| anyhow!("failed to read in file {}: {e}", input.path.display()))?;
+    if contents.is_empty() {
+        return Ok(Vec::new());
+    }
+    match input.format {
+        ConfigFileFormat::Yaml => {
+            let docs = yaml_rust::YamlLoader::load_from_str(&contents)
+                .map_err(|e| anyhow!("invalid yaml file {}: {e}", input.path.display()))?;
+            match docs.first() {
+                Some(doc) => peer::parse_yaml(doc, &input.metadata),
+                None => Ok(Vec::new()),
+            }
+        }
+        ConfigFileFormat::Json => {
+            let doc = serde_json::Value::from_str(&contents)
+                .map_err(|e| anyhow!("invalid json file {}: {e}", input.path.display()))?;
+            peer::parse_json(&doc, &input.metadata)
+        }
+    }
+}
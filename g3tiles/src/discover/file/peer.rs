@@ -0,0 +1,305 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use serde_json::Value;
+use yaml_rust::Yaml;
+
+use g3_types::collection::WeightedValue;
+
+use crate::discover::DiscoveredData;
+
+const KEY_ADDR: &str = "addr";
+const KEY_WEIGHT: &str = "weight";
+const KEY_METADATA: &str = "metadata";
+
+struct PeerRecord {
+    addr: SocketAddr,
+    weight: f64,
+    metadata: BTreeMap<String, String>,
+}
+
+impl PeerRecord {
+    fn new(addr: SocketAddr) -> Self {
+        PeerRecord {
+            addr,
+            weight: WeightedValue::<SocketAddr>::DEFAULT_WEIGHT,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    fn matches(&self, filter: &BTreeMap<String, String>) -> bool {
+        filter
+            .iter()
+            .all(|(k, v)| self.metadata.get(k).map(|mv| mv.eq(v)).unwrap_or(false))
+    }
+
+    fn parse_yaml(value: &Yaml) -> anyhow::Result<Self> {
+        match value {
+            Yaml::Hash(map) => {
+                let v = g3_yaml::hash_get_required(map, KEY_ADDR)?;
+                let addr = g3_yaml::value::as_sockaddr(v)
+                    .context(format!("invalid sockaddr string value for key {KEY_ADDR}"))?;
+                let mut peer = PeerRecord::new(addr);
+
+                g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                    KEY_ADDR => Ok(()),
+                    KEY_WEIGHT => {
+                        peer.weight = g3_yaml::value::as_f64(v)
+                            .context(format!("invalid f64 value for key {k}"))?;
+                        Ok(())
+                    }
+                    KEY_METADATA => {
+                        if let Yaml::Hash(map) = v {
+                            g3_yaml::foreach_kv(map, |mk, mv| {
+                                let value = g3_yaml::value::as_string(mv).context(format!(
+                                    "invalid string value for metadata key {mk}"
+                                ))?;
+                                peer.metadata.insert(mk.to_string(), value);
+                                Ok(())
+                            })
+                        } else {
+                            Err(anyhow!("invalid map value for key {k}"))
+                        }
+                    }
+                    _ => Err(anyhow!("invalid key {k}")),
+                })?;
+
+                Ok(peer)
+            }
+            _ => {
+                let addr =
+                    g3_yaml::value::as_sockaddr(value).context("invalid sockaddr string value")?;
+                Ok(PeerRecord::new(addr))
+            }
+        }
+    }
+
+    fn parse_json(value: &Value) -> anyhow::Result<Self> {
+        match value {
+            Value::Object(map) => {
+                let v = g3_json::map_get_required(map, KEY_ADDR)?;
+                let addr = json_as_sockaddr(v)
+                    .context(format!("invalid sockaddr string value for key {KEY_ADDR}"))?;
+                let mut peer = PeerRecord::new(addr);
+
+                for (k, v) in map {
+                    match g3_json::key::normalize(k).as_str() {
+                        KEY_ADDR => {}
+                        KEY_WEIGHT => {
+                            peer.weight = g3_json::value::as_f64(v)
+                                .context(format!("invalid f64 value for key {k}"))?;
+                        }
+                        KEY_METADATA => {
+                            let Value::Object(map) = v else {
+                                return Err(anyhow!("invalid map value for key {k}"));
+                            };
+                            for (mk, mv) in map {
+                                let value = g3_json::value::as_string(mv).context(format!(
+                                    "invalid string value for metadata key {mk}"
+                                ))?;
+                                peer.metadata.insert(mk.to_string(), value);
+                            }
+                        }
+                        _ => return Err(anyhow!("invalid key {k}")),
+                    }
+                }
+
+                Ok(peer)
+            }
+            _ => {
+                let addr = json_as_sockaddr(value).context("invalid sockaddr string value")?;
+                Ok(PeerRecord::new(addr))
+            }
+        }
+    }
+}
+
+fn json_as_sockaddr(value: &Value) -> anyhow::Result<SocketAddr> {
+    if let Value::String(s) = value {
+        SocketAddr::from_str(s).map_err(|e| anyhow!("invalid socket address: {e}"))
+    } else {
+        Err(anyhow!(
+            "json value type for 'SocketAddr' should be 'string'"
+        ))
+    }
+}
+
+pub(super) fn parse_yaml(
+    doc: &Yaml,
+    filter: &BTreeMap<String, String>,
+) -> anyhow::Result<DiscoveredData> {
+    let mut data = DiscoveredData::new();
+    match doc {
+        Yaml::Array(seq) => {
+            for (i, v) in seq.iter().enumerate() {
+                let peer = PeerRecord::parse_yaml(v).context(format!("invalid peer #{i}"))?;
+                if peer.matches(filter) {
+                    data.push(WeightedValue::with_weight(peer.addr, peer.weight));
+                }
+            }
+        }
+        Yaml::Null => {}
+        _ => return Err(anyhow!("the yaml doc should be an array of peers")),
+    }
+    Ok(data)
+}
+
+pub(super) fn parse_json(
+    doc: &Value,
+    filter: &BTreeMap<String, String>,
+) -> anyhow::Result<DiscoveredData> {
+    let mut data = DiscoveredData::new();
+    match doc {
+        Value::Array(seq) => {
+            for (i, v) in seq.iter().enumerate() {
+                let peer = PeerRecord::parse_json(v).context(format!("invalid peer #{i}"))?;
+                if peer.matches(filter) {
+                    data.push(WeightedValue::with_weight(peer.addr, peer.weight));
+                }
+            }
+        }
+        Value::Null => {}
+        _ => return Err(anyhow!("the json doc should be an array of peers")),
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use yaml_rust::YamlLoader;
+
+    fn load_yaml(s: &str) -> Yaml {
+        YamlLoader::load_from_str(s).unwrap().remove(0)
+    }
+
+    fn to_pairs(data: &DiscoveredData) -> Vec<(SocketAddr, f64)> {
+        data.iter().map(|v| (*v.inner(), v.weight())).collect()
+    }
+
+    #[test]
+    fn yaml_peers() {
+        let doc = load_yaml(
+            r#"
+            - 127.0.0.1:8080
+            - addr: 127.0.0.2:8080
+              weight: 2.5
+            - addr: "[::1]:8443"
+              metadata:
+                region: us
+            "#,
+        );
+
+        let data = parse_yaml(&doc, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            to_pairs(&data),
+            vec![
+                ("127.0.0.1:8080".parse().unwrap(), 1.0),
+                ("127.0.0.2:8080".parse().unwrap(), 2.5),
+                ("[::1]:8443".parse().unwrap(), 1.0),
+            ]
+        );
+
+        let mut filter = BTreeMap::new();
+        filter.insert("region".to_string(), "us".to_string());
+        let data = parse_yaml(&doc, &filter).unwrap();
+        assert_eq!(to_pairs(&data), vec![("[::1]:8443".parse().unwrap(), 1.0)]);
+
+        filter.insert("region".to_string(), "eu".to_string());
+        let data = parse_yaml(&doc, &filter).unwrap();
+        assert!(data.is_empty());
+
+        let data = parse_yaml(&Yaml::Null, &BTreeMap::new()).unwrap();
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn yaml_invalid() {
+        let filter = BTreeMap::new();
+
+        let doc = load_yaml("addr: 127.0.0.1:8080");
+        assert!(parse_yaml(&doc, &filter).is_err());
+
+        let doc = load_yaml("- 127.0.0.1");
+        assert!(parse_yaml(&doc, &filter).is_err());
+
+        let doc = load_yaml("- weight: 1");
+        assert!(parse_yaml(&doc, &filter).is_err());
+
+        let doc = load_yaml("- addr: 127.0.0.1:8080\n  port: 80");
+        assert!(parse_yaml(&doc, &filter).is_err());
+
+        let doc = load_yaml("- addr: 127.0.0.1:8080\n  metadata: us");
+        assert!(parse_yaml(&doc, &filter).is_err());
+    }
+
+    #[test]
+    fn json_peers() {
+        let doc: Value = serde_json::from_str(
+            r#"[
+                "127.0.0.1:8080",
+                {"addr": "127.0.0.2:8080", "weight": 2.5},
+                {"addr": "[::1]:8443", "weight": "3", "metadata": {"region": "us"}}
+            ]"#,
+        )
+        .unwrap();
+
+        let data = parse_json(&doc, &BTreeMap::new()).unwrap();
+        assert_eq!(
+            to_pairs(&data),
+            vec![
+                ("127.0.0.1:8080".parse().unwrap(), 1.0),
+                ("127.0.0.2:8080".parse().unwrap(), 2.5),
+                ("[::1]:8443".parse().unwrap(), 3.0),
+            ]
+        );
+
+        let mut filter = BTreeMap::new();
+        filter.insert("region".to_string(), "us".to_string());
+        let data = parse_json(&doc, &filter).unwrap();
+        assert_eq!(to_pairs(&data), vec![("[::1]:8443".parse().unwrap(), 3.0)]);
+
+        let data = parse_json(&Value::Null, &BTreeMap::new()).unwrap();
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn json_invalid() {
+        let filter = BTreeMap::new();
+
+        let doc: Value = serde_json::from_str(r#"{"addr": "127.0.0.1:8080"}"#).unwrap();
+        assert!(parse_json(&doc, &filter).is_err());
+
+        let doc: Value = serde_json::from_str(r#"[8080]"#).unwrap();
+        assert!(parse_json(&doc, &filter).is_err());
+
+        let doc: Value = serde_json::from_str(r#"[{"weight": 1}]"#).unwrap();
+        assert!(parse_json(&doc, &filter).is_err());
+
+        let doc: Value =
+            serde_json::from_str(r#"[{"addr": "127.0.0.1:8080", "port": 80}]"#).unwrap();
+        assert!(parse_json(&doc, &filter).is_err());
+
+        let doc: Value =
+            serde_json::from_str(r#"[{"addr": "127.0.0.1:8080", "metadata": "us"}]"#).unwrap();
+        assert!(parse_json(&doc, &filter).is_err());
+    }
+}
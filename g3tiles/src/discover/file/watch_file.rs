@@ -0,0 +1,111 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::Path;
+
+#[cfg(target_os = "linux")]
+pub(super) struct FileWatcher {
+    file_name: std::ffi::OsString,
+    events: Option<inotify::EventStream<[u8; 4096]>>,
+}
+
+#[cfg(target_os = "linux")]
+impl FileWatcher {
+    pub(super) fn new(path: &Path, enable: bool) -> anyhow::Result<Self> {
+        use anyhow::anyhow;
+        use inotify::{Inotify, WatchMask};
+
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("no file name found in path {}", path.display()))?
+            .to_os_string();
+        if !enable {
+            return Ok(FileWatcher {
+                file_name,
+                events: None,
+            });
+        }
+
+        let dir_path = path
+            .parent()
+            .ok_or_else(|| anyhow!("no parent dir found for path {}", path.display()))?;
+
+        let inotify =
+            Inotify::init().map_err(|e| anyhow!("failed to init inotify instance: {e}"))?;
+        // watch the parent dir, so we can detect atomic replacement of the file
+        inotify
+            .watches()
+            .add(dir_path, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO)
+            .map_err(|e| {
+                anyhow!(
+                    "failed to watch close_write and moved_to events of {}: {e}",
+                    dir_path.display()
+                )
+            })?;
+        let events = inotify.into_event_stream([0u8; 4096])?;
+
+        Ok(FileWatcher {
+            file_name,
+            events: Some(events),
+        })
+    }
+
+    pub(super) async fn changed(&mut self) {
+        use futures_util::StreamExt;
+        use log::warn;
+
+        if let Some(events) = &mut self.events {
+            loop {
+                match events.next().await {
+                    Some(Ok(v)) => {
+                        if v.name
+                            .as_ref()
+                            .map(|n| n.eq(&self.file_name))
+                            .unwrap_or(false)
+                        {
+                            return;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        warn!("inotify watch failed: {e}");
+                        break;
+                    }
+                    None => {
+                        warn!("inotify watch ended unexpected");
+                        break;
+                    }
+                }
+            }
+            self.events = None;
+        }
+
+        std::future::pending::<()>().await
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(super) struct FileWatcher {}
+
+#[cfg(not(target_os = "linux"))]
+impl FileWatcher {
+    pub(super) fn new(_path: &Path, _enable: bool) -> anyhow::Result<Self> {
+        Ok(FileWatcher {})
+    }
+
+    pub(super) async fn changed(&mut self) {
+        std::future::pending::<()>().await
+    }
+}
@@ -25,6 +25,8 @@ use g3_types::collection::WeightedValue;
 
 use crate::config::discover::{AnyDiscoverConfig, DiscoverRegisterData};
 
+mod dns_srv;
+mod file;
 mod host_resolver;
 mod static_addr;
 
@@ -26,6 +26,8 @@ use g3_yaml::YamlDocPosition;
 use super::{registry, ArcDiscover};
 use crate::config::discover::{AnyDiscoverConfig, DiscoverConfigDiffAction};
 
+use super::dns_srv::DnsSrvDiscover;
+use super::file::FileDiscover;
 use super::host_resolver::HostResolverDiscover;
 use super::static_addr::StaticAddrDiscover;
 
@@ -138,6 +140,8 @@ async fn spawn_new_unlocked(config: AnyDiscoverConfig) -> anyhow::Result<()> {
     let discover = match config {
         AnyDiscoverConfig::StaticAddr(c) => StaticAddrDiscover::new_obj(c),
         AnyDiscoverConfig::HostResolver(c) => HostResolverDiscover::new_obj(c),
+        AnyDiscoverConfig::File(c) => FileDiscover::new_obj(c),
+        AnyDiscoverConfig::DnsSrv(c) => DnsSrvDiscover::new_obj(c)?,
     };
     registry::add(name.clone(), discover);
     crate::backend::update_dependency_to_discover(&name, "spawned").await;
@@ -0,0 +1,349 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use g3_types::net::{T1L2BVParse, TlvParse};
+
+const MESSAGE_HEADER_LENGTH: usize = 8;
+const ITEM_HEADER_LENGTH: usize = 3;
+const MESSAGE_PADDED_LENGTH: usize = 1024;
+
+const TAG_SKI: u8 = 0x04;
+const TAG_OPCODE: u8 = 0x11;
+const TAG_PAYLOAD: u8 = 0x12;
+const TAG_PADDING: u8 = 0x20;
+
+const OPCODE_RESPONSE: u8 = 0xF0;
+const OPCODE_ERROR: u8 = 0xFF;
+
+#[repr(u8)]
+#[derive(Clone, Copy)]
+pub(super) enum KeylessOpCode {
+    // requests an RSA decrypted payload without padding
+    RsaDecryptRaw = 0x08,
+    // requests an ECDSA signature on an MD5SHA1 hash payload
+    EcdsaSignMd5Sha1 = 0x12,
+    // requests an ECDSA signature on an SHA1 hash payload
+    EcdsaSignSha1 = 0x13,
+    // requests an ECDSA signature on an SHA224 hash payload
+    EcdsaSignSha224 = 0x14,
+    // requests an ECDSA signature on an SHA256 hash payload
+    EcdsaSignSha256 = 0x15,
+    // requests an ECDSA signature on an SHA384 hash payload
+    EcdsaSignSha384 = 0x16,
+    // requests an ECDSA signature on an SHA512 hash payload
+    EcdsaSignSha512 = 0x17,
+}
+
+impl KeylessOpCode {
+    pub(super) fn ecdsa_sign_for_digest(digest: &[u8]) -> Option<Self> {
+        match digest.len() {
+            36 => Some(KeylessOpCode::EcdsaSignMd5Sha1),
+            20 => Some(KeylessOpCode::EcdsaSignSha1),
+            28 => Some(KeylessOpCode::EcdsaSignSha224),
+            32 => Some(KeylessOpCode::EcdsaSignSha256),
+            48 => Some(KeylessOpCode::EcdsaSignSha384),
+            64 => Some(KeylessOpCode::EcdsaSignSha512),
+            _ => None,
+        }
+    }
+}
+
+pub(super) struct KeylessRequest {
+    buf: Vec<u8>,
+}
+
+impl KeylessRequest {
+    pub(super) fn new(ski: &[u8], opcode: KeylessOpCode, payload: &[u8]) -> Option<Self> {
+        let mut buf = Vec::with_capacity(MESSAGE_PADDED_LENGTH);
+        // hdr and ID
+        buf.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        let ski_len = u16::try_from(ski.len()).ok()?;
+        buf.push(TAG_SKI);
+        buf.extend_from_slice(&ski_len.to_be_bytes());
+        buf.extend_from_slice(ski);
+
+        buf.extend_from_slice(&[TAG_OPCODE, 0x00, 0x01, opcode as u8]);
+
+        let payload_len = u16::try_from(payload.len()).ok()?;
+        buf.push(TAG_PAYLOAD);
+        buf.extend_from_slice(&payload_len.to_be_bytes());
+        buf.extend_from_slice(payload);
+
+        match MESSAGE_PADDED_LENGTH.checked_sub(buf.len()) {
+            Some(0) | None => {}
+            Some(1..=ITEM_HEADER_LENGTH) => buf.extend_from_slice(&[TAG_PADDING, 0x00, 0x00]),
+            Some(n) => {
+                let left = (n - ITEM_HEADER_LENGTH) as u16;
+                buf.push(TAG_PADDING);
+                buf.extend_from_slice(&left.to_be_bytes());
+                buf.resize(MESSAGE_PADDED_LENGTH, 0);
+            }
+        }
+
+        let len = u16::try_from(buf.len() - MESSAGE_HEADER_LENGTH).ok()?;
+        buf[2..4].copy_from_slice(&len.to_be_bytes());
+        Some(KeylessRequest { buf })
+    }
+
+    pub(super) fn set_id(&mut self, id: u32) {
+        self.buf[4..8].copy_from_slice(&id.to_be_bytes());
+    }
+
+    pub(super) fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+struct KeylessResponseTlvParser<'a> {
+    opcode: u8,
+    payload: &'a [u8],
+}
+
+impl<'a> T1L2BVParse<'a> for KeylessResponseTlvParser<'a> {
+    type Error = io::Error;
+
+    fn no_enough_data() -> Self::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not enough data for a valid item",
+        )
+    }
+
+    fn parse_value(&mut self, tag: u8, v: &'a [u8]) -> Result<(), Self::Error> {
+        match tag {
+            TAG_OPCODE => {
+                if v.len() != 1 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid opcode item length",
+                    ));
+                }
+                self.opcode = v[0];
+            }
+            TAG_PAYLOAD => self.payload = v,
+            TAG_PADDING => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid item tag {tag}"),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(super) struct KeylessResponse {
+    pub(super) id: u32,
+    /// the payload data, or None if it's an error response
+    pub(super) data: Option<Vec<u8>>,
+}
+
+impl KeylessResponse {
+    pub(super) async fn read<R>(reader: &mut R, buf: &mut Vec<u8>) -> io::Result<Self>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut hdr_buf = [0u8; MESSAGE_HEADER_LENGTH];
+        reader.read_exact(&mut hdr_buf).await?;
+
+        let major = hdr_buf[0];
+        let minor = hdr_buf[1];
+        if major != 1 || minor != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected version {major}.{minor}"),
+            ));
+        }
+
+        let len = u16::from_be_bytes([hdr_buf[2], hdr_buf[3]]) as usize;
+        buf.clear();
+        buf.resize(len, 0);
+        reader.read_exact(buf).await?;
+
+        let id = u32::from_be_bytes([hdr_buf[4], hdr_buf[5], hdr_buf[6], hdr_buf[7]]);
+        let mut parser = KeylessResponseTlvParser {
+            opcode: 0,
+            payload: &[],
+        };
+        parser.parse_tlv(buf.as_slice())?;
+        let data = match parser.opcode {
+            OPCODE_RESPONSE => Some(parser.payload.to_vec()),
+            OPCODE_ERROR => None,
+            n => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unexpected response opcode {n}"),
+                ))
+            }
+        };
+        Ok(KeylessResponse { id, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RequestParser<'a> {
+        ski: &'a [u8],
+        opcode: u8,
+        payload: &'a [u8],
+        padding: usize,
+    }
+
+    impl<'a> T1L2BVParse<'a> for RequestParser<'a> {
+        type Error = io::Error;
+
+        fn no_enough_data() -> Self::Error {
+            io::Error::new(io::ErrorKind::InvalidData, "no enough data")
+        }
+
+        fn parse_value(&mut self, tag: u8, v: &'a [u8]) -> Result<(), Self::Error> {
+            match tag {
+                TAG_SKI => self.ski = v,
+                TAG_OPCODE => self.opcode = v[0],
+                TAG_PAYLOAD => self.payload = v,
+                TAG_PADDING => self.padding = v.len(),
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid tag")),
+            }
+            Ok(())
+        }
+    }
+
+    fn build_response(id: u32, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut buf = vec![0x01, 0x00, 0x00, 0x00];
+        buf.extend_from_slice(&id.to_be_bytes());
+        buf.extend_from_slice(&[TAG_OPCODE, 0x00, 0x01, opcode]);
+        buf.push(TAG_PAYLOAD);
+        buf.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        buf.extend_from_slice(payload);
+        buf.extend_from_slice(&[TAG_PADDING, 0x00, 0x02, 0x00, 0x00]);
+        let len = (buf.len() - MESSAGE_HEADER_LENGTH) as u16;
+        buf[2..4].copy_from_slice(&len.to_be_bytes());
+        buf
+    }
+
+    #[test]
+    fn ecdsa_opcode() {
+        let cases = [
+            (36, KeylessOpCode::EcdsaSignMd5Sha1),
+            (20, KeylessOpCode::EcdsaSignSha1),
+            (28, KeylessOpCode::EcdsaSignSha224),
+            (32, KeylessOpCode::EcdsaSignSha256),
+            (48, KeylessOpCode::EcdsaSignSha384),
+            (64, KeylessOpCode::EcdsaSignSha512),
+        ];
+        for (len, opcode) in cases {
+            let digest = vec![0u8; len];
+            let found = KeylessOpCode::ecdsa_sign_for_digest(&digest).unwrap();
+            assert_eq!(found as u8, opcode as u8);
+        }
+        assert!(KeylessOpCode::ecdsa_sign_for_digest(&[0u8; 16]).is_none());
+    }
+
+    #[test]
+    fn request_encode() {
+        let ski = [0x5a; 20];
+        let payload = [0xa5; 32];
+        let mut req = KeylessRequest::new(&ski, KeylessOpCode::EcdsaSignSha256, &payload).unwrap();
+        req.set_id(0x01020304);
+
+        let buf = req.as_bytes();
+        assert_eq!(buf.len(), MESSAGE_PADDED_LENGTH);
+        assert_eq!(&buf[0..2], &[0x01, 0x00]);
+        let len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+        assert_eq!(len, MESSAGE_PADDED_LENGTH - MESSAGE_HEADER_LENGTH);
+        assert_eq!(&buf[4..8], &[0x01, 0x02, 0x03, 0x04]);
+
+        let mut parser = RequestParser::default();
+        parser.parse_tlv(&buf[MESSAGE_HEADER_LENGTH..]).unwrap();
+        assert_eq!(parser.ski, &ski);
+        assert_eq!(parser.opcode, KeylessOpCode::EcdsaSignSha256 as u8);
+        assert_eq!(parser.payload, &payload);
+        assert_eq!(
+            parser.padding,
+            MESSAGE_PADDED_LENGTH - MESSAGE_HEADER_LENGTH - 4 * ITEM_HEADER_LENGTH - 1 - 20 - 32
+        );
+    }
+
+    #[test]
+    fn request_without_padding() {
+        let ski = [0x5a; 20];
+        let payload = [0xa5; 1024];
+        let req = KeylessRequest::new(&ski, KeylessOpCode::RsaDecryptRaw, &payload).unwrap();
+
+        let buf = req.as_bytes();
+        assert!(buf.len() > MESSAGE_PADDED_LENGTH);
+        let len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+        assert_eq!(len, buf.len() - MESSAGE_HEADER_LENGTH);
+
+        let mut parser = RequestParser::default();
+        parser.parse_tlv(&buf[MESSAGE_HEADER_LENGTH..]).unwrap();
+        assert_eq!(parser.opcode, KeylessOpCode::RsaDecryptRaw as u8);
+        assert_eq!(parser.payload, &payload);
+        assert_eq!(parser.padding, 0);
+
+        assert!(KeylessRequest::new(&ski, KeylessOpCode::RsaDecryptRaw, &[0u8; 65536]).is_none());
+    }
+
+    #[tokio::test]
+    async fn response_read() {
+        let mut data = build_response(1, OPCODE_RESPONSE, b"signature");
+        data.extend_from_slice(&build_response(2, OPCODE_ERROR, &[0x05]));
+        let mut reader = data.as_slice();
+        let mut buf = Vec::new();
+
+        let rsp = KeylessResponse::read(&mut reader, &mut buf).await.unwrap();
+        assert_eq!(rsp.id, 1);
+        assert_eq!(rsp.data.as_deref(), Some(b"signature".as_slice()));
+
+        let rsp = KeylessResponse::read(&mut reader, &mut buf).await.unwrap();
+        assert_eq!(rsp.id, 2);
+        assert!(rsp.data.is_none());
+
+        assert!(reader.is_empty());
+    }
+
+    #[tokio::test]
+    async fn response_invalid() {
+        let mut buf = Vec::new();
+
+        let mut data = build_response(1, OPCODE_RESPONSE, b"signature");
+        data[0] = 0x02;
+        let mut reader = data.as_slice();
+        assert!(KeylessResponse::read(&mut reader, &mut buf).await.is_err());
+
+        let data = build_response(1, KeylessOpCode::RsaDecryptRaw as u8, b"data");
+        let mut reader = data.as_slice();
+        assert!(KeylessResponse::read(&mut reader, &mut buf).await.is_err());
+
+        let mut data = build_response(1, OPCODE_RESPONSE, b"signature");
+        data[MESSAGE_HEADER_LENGTH] = TAG_SKI;
+        let mut reader = data.as_slice();
+        assert!(KeylessResponse::read(&mut reader, &mut buf).await.is_err());
+
+        let data = build_response(1, OPCODE_RESPONSE, b"signature");
+        let mut reader = &data[..data.len() - 1];
+        assert!(KeylessResponse::read(&mut reader, &mut buf).await.is_err());
+    }
+}
@@ -0,0 +1,216 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use g3_openssl::async_job::{AsyncKeyDriver, AsyncKeyNotifier};
+use g3_openssl::SslConnector;
+use g3_types::net::{Host, OpensslClientConfig};
+
+use super::message::KeylessOpCode;
+use super::{KeylessRequest, KeylessResponse};
+use crate::config::server::openssl_proxy::KeylessBackendConfig;
+
+struct KeylessJob {
+    request: KeylessRequest,
+    notifier: AsyncKeyNotifier,
+}
+
+/// connection pool to keyless servers for a single certificate
+pub(crate) struct KeylessPool {
+    config: KeylessBackendConfig,
+    tls_client: Option<Arc<OpensslClientConfig>>,
+    ski: Vec<u8>,
+    connections: Vec<Mutex<Option<mpsc::UnboundedSender<KeylessJob>>>>,
+    next_index: AtomicUsize,
+}
+
+impl KeylessPool {
+    pub(crate) fn new(
+        config: KeylessBackendConfig,
+        tls_client: Option<OpensslClientConfig>,
+        ski: Vec<u8>,
+    ) -> Self {
+        let count = config.servers.len() * config.connections_per_server;
+        let mut connections = Vec::with_capacity(count);
+        connections.resize_with(count, || Mutex::new(None));
+        KeylessPool {
+            config,
+            tls_client: tls_client.map(Arc::new),
+            ski,
+            connections,
+            next_index: AtomicUsize::new(0),
+        }
+    }
+
+    fn submit(&self, opcode: KeylessOpCode, payload: &[u8], notifier: AsyncKeyNotifier) {
+        let Some(request) = KeylessRequest::new(&self.ski, opcode, payload) else {
+            notifier.notify(None);
+            return;
+        };
+        let mut job = KeylessJob { request, notifier };
+
+        let index = self.next_index.fetch_add(1, Ordering::Relaxed) % self.connections.len();
+        let mut sender_guard = self.connections[index].lock().unwrap();
+        if let Some(sender) = &*sender_guard {
+            match sender.send(job) {
+                Ok(_) => return,
+                Err(mpsc::error::SendError(j)) => job = j,
+            }
+        }
+
+        // the old connection has gone, spawn a new one
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            job.notifier.notify(None);
+            return;
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let _ = sender.send(job);
+        let server = self.config.servers[index % self.config.servers.len()];
+        let tls = self.tls_client.as_ref().map(|config| {
+            let name = self
+                .config
+                .tls_name
+                .clone()
+                .unwrap_or(Host::Ip(server.ip()));
+            (config.clone(), name)
+        });
+        let connection = KeylessConnection {
+            server,
+            tls,
+            connect_timeout: self.config.connect_timeout,
+            request_timeout: self.config.request_timeout,
+        };
+        handle.spawn(connection.run(receiver));
+        *sender_guard = Some(sender);
+    }
+}
+
+impl AsyncKeyDriver for KeylessPool {
+    fn rsa_private(&self, data: Vec<u8>, notifier: AsyncKeyNotifier) {
+        self.submit(KeylessOpCode::RsaDecryptRaw, &data, notifier);
+    }
+
+    fn ecdsa_sign(&self, digest: Vec<u8>, notifier: AsyncKeyNotifier) {
+        match KeylessOpCode::ecdsa_sign_for_digest(&digest) {
+            Some(opcode) => self.submit(opcode, &digest, notifier),
+            None => notifier.notify(None),
+        }
+    }
+}
+
+struct KeylessConnection {
+    server: SocketAddr,
+    tls: Option<(Arc<OpensslClientConfig>, Host)>,
+    connect_timeout: Duration,
+    request_timeout: Duration,
+}
+
+impl KeylessConnection {
+    async fn run(self, mut receiver: mpsc::UnboundedReceiver<KeylessJob>) {
+        let stream =
+            match tokio::time::timeout(self.connect_timeout, TcpStream::connect(self.server)).await
+            {
+                Ok(Ok(stream)) => stream,
+                _ => {
+                    // all queued jobs will be failed when dropped
+                    receiver.close();
+                    return;
+                }
+            };
+        match &self.tls {
+            Some((tls_client, tls_name)) => {
+                let Ok(ssl) = tls_client.build_ssl(tls_name, self.server.port()) else {
+                    receiver.close();
+                    return;
+                };
+                let Ok(connector) = SslConnector::new(ssl, stream) else {
+                    receiver.close();
+                    return;
+                };
+                match tokio::time::timeout(tls_client.handshake_timeout, connector.connect()).await
+                {
+                    Ok(Ok(ssl_stream)) => self.serve(ssl_stream, receiver).await,
+                    _ => receiver.close(),
+                }
+            }
+            None => self.serve(stream, receiver).await,
+        }
+    }
+
+    async fn serve<S>(&self, stream: S, mut receiver: mpsc::UnboundedReceiver<KeylessJob>)
+    where
+        S: AsyncRead + AsyncWrite,
+    {
+        let (r, mut w) = tokio::io::split(stream);
+        let mut r = BufReader::new(r);
+
+        let pending: Mutex<HashMap<u32, (AsyncKeyNotifier, Instant)>> = Mutex::new(HashMap::new());
+
+        let write_requests = async {
+            let mut next_id = 0u32;
+            while let Some(mut job) = receiver.recv().await {
+                let id = next_id;
+                next_id = next_id.wrapping_add(1);
+                job.request.set_id(id);
+                pending
+                    .lock()
+                    .unwrap()
+                    .insert(id, (job.notifier, Instant::now()));
+                if w.write_all(job.request.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        };
+
+        let read_responses = async {
+            let mut buf = Vec::with_capacity(1024);
+            while let Ok(rsp) = KeylessResponse::read(&mut r, &mut buf).await {
+                let notifier = pending.lock().unwrap().remove(&rsp.id);
+                if let Some((notifier, _)) = notifier {
+                    notifier.notify(rsp.data);
+                }
+            }
+        };
+
+        let check_timeout = async {
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                let mut pending = pending.lock().unwrap();
+                // the expired notifiers will report failure when dropped
+                pending.retain(|_, (_, created)| created.elapsed() < self.request_timeout);
+            }
+        };
+
+        tokio::select! {
+            _ = write_requests => {}
+            _ = read_responses => {}
+            _ = check_timeout => {}
+        }
+
+        receiver.close();
+    }
+}
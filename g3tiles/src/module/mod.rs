@@ -15,3 +15,6 @@
  */
 
 pub(crate) mod stream;
+
+#[cfg(feature = "openssl-async-job")]
+pub(crate) mod keyless;
@@ -31,6 +31,10 @@ pub(crate) enum ServerTaskError {
     ClientTcpReadFailed(io::Error),
     #[error("tcp write to client: {0:?}")]
     ClientTcpWriteFailed(io::Error),
+    #[error("quic read from client: {0:?}")]
+    ClientQuicReadFailed(io::Error),
+    #[error("quic write to client: {0:?}")]
+    ClientQuicWriteFailed(io::Error),
     #[error("upstream not resolved")]
     UpstreamNotResolved,
     #[error("upstream not connected: {0}")]
@@ -60,6 +64,8 @@ impl ServerTaskError {
             ServerTaskError::InternalServerError(_) => "InternalServerError",
             ServerTaskError::ClientTcpReadFailed(_) => "ClientTcpReadFailed",
             ServerTaskError::ClientTcpWriteFailed(_) => "ClientTcpWriteFailed",
+            ServerTaskError::ClientQuicReadFailed(_) => "ClientQuicReadFailed",
+            ServerTaskError::ClientQuicWriteFailed(_) => "ClientQuicWriteFailed",
             ServerTaskError::UpstreamNotResolved => "UpstreamNotResolved",
             ServerTaskError::UpstreamNotConnected(_) => "UpstreamNotConnected",
             ServerTaskError::UpstreamReadFailed(_) => "UpstreamReadFailed",
@@ -84,6 +90,12 @@ impl From<StreamConnectError> for ServerTaskError {
                 "failed to setup local socket for remote connection",
             ),
             StreamConnectError::ConnectFailed(e) => ServerTaskError::UpstreamNotConnected(e),
+            StreamConnectError::ProxyProtocolEncodeError(_) => {
+                ServerTaskError::InternalServerError("proxy protocol encode failed")
+            }
+            StreamConnectError::ProxyProtocolWriteFailed(e) => {
+                ServerTaskError::UpstreamWriteFailed(e)
+            }
         }
     }
 }
@@ -42,6 +42,9 @@ mod plain_tcp_port;
 mod openssl_proxy;
 mod rustls_proxy;
 
+#[cfg(feature = "quic")]
+mod quic_proxy;
+
 mod ops;
 pub(crate) use ops::{
     force_quit_offline_server, force_quit_offline_servers, get_server, reload, stop_all,
@@ -52,6 +55,9 @@ pub use ops::{spawn_all, spawn_offline_clean};
 mod task;
 pub(crate) use task::{ServerTaskNotes, ServerTaskStage};
 
+mod tls_info;
+pub(crate) use tls_info::TlsSessionInfo;
+
 mod stats;
 pub(crate) use stats::{ArcServerStats, ServerStats};
 
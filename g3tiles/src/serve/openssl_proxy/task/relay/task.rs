@@ -31,7 +31,9 @@ use crate::backend::ArcBackend;
 use crate::config::server::ServerConfig;
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::serve::openssl_proxy::OpensslHost;
-use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
+use crate::serve::{
+    ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage, TlsSessionInfo,
+};
 
 pub(crate) struct OpensslRelayTask {
     ctx: CommonTaskContext,
@@ -80,6 +82,7 @@ impl OpensslRelayTask {
         S: AsyncRead + AsyncWrite + Unpin,
     {
         self.pre_start();
+        self.task_notes.tls_info = Some(TlsSessionInfo::from_openssl(ssl_stream.ssl()));
         if let Err(e) = self.run(ssl_stream).await {
             self.get_log_context().log(&self.ctx.task_logger, &e)
         }
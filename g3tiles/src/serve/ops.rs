@@ -35,6 +35,9 @@ use super::plain_tcp_port::PlainTcpPort;
 use super::openssl_proxy::OpensslProxyServer;
 use super::rustls_proxy::RustlsProxyServer;
 
+#[cfg(feature = "quic")]
+use super::quic_proxy::QuicProxyServer;
+
 static SERVER_OPS_LOCK: Mutex<()> = Mutex::const_new(());
 
 pub fn spawn_offline_clean() {
@@ -222,6 +225,8 @@ fn spawn_new_unlocked(config: AnyServerConfig) -> anyhow::Result<()> {
         AnyServerConfig::PlainTcpPort(c) => PlainTcpPort::prepare_initial(c)?,
         AnyServerConfig::OpensslProxy(c) => OpensslProxyServer::prepare_initial(c)?,
         AnyServerConfig::RustlsProxy(c) => RustlsProxyServer::prepare_initial(c)?,
+        #[cfg(feature = "quic")]
+        AnyServerConfig::QuicProxy(c) => QuicProxyServer::prepare_initial(c)?,
     };
     registry::add(name.clone(), server)?;
     update_dependency_to_server_unlocked(&name, "spawned");
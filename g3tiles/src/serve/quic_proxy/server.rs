@@ -0,0 +1,325 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ahash::AHashMap;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use quinn::Connection;
+use slog::Logger;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, watch};
+
+use g3_daemon::listen::{
+    AcceptQuicServer, AcceptTcpServer, ListenQuicConf, ListenQuicRuntime, ListenStats,
+};
+use g3_daemon::server::{BaseServer, ClientConnectionInfo, ServerReloadCommand};
+use g3_types::acl::AclNetworkRule;
+use g3_types::metrics::MetricsName;
+use g3_types::net::UdpListenConfig;
+use g3_types::route::HostMatch;
+
+use super::{
+    CommonTaskContext, QuicAcceptTask, QuicHost, QuicProxyServerStats, QuicSniCertResolver,
+};
+use crate::config::server::quic_proxy::QuicProxyServerConfig;
+use crate::config::server::{AnyServerConfig, ServerConfig};
+use crate::serve::{
+    ArcServer, ArcServerStats, Server, ServerInternal, ServerQuitPolicy, ServerStats, WrapArcServer,
+};
+
+#[derive(Clone)]
+struct QuicProxyAuxConfig {
+    ingress_net_filter: Option<Arc<AclNetworkRule>>,
+    listen_config: Option<UdpListenConfig>,
+    quinn_config: Option<quinn::ServerConfig>,
+    accept_timeout: Duration,
+    offline_rebind_port: Option<u16>,
+}
+
+impl ListenQuicConf for QuicProxyAuxConfig {
+    #[inline]
+    fn take_udp_listen_config(&mut self) -> Option<UdpListenConfig> {
+        self.listen_config.take()
+    }
+
+    #[inline]
+    fn take_quinn_config(&mut self) -> Option<quinn::ServerConfig> {
+        self.quinn_config.take()
+    }
+
+    #[inline]
+    fn offline_rebind_port(&self) -> Option<u16> {
+        self.offline_rebind_port
+    }
+
+    #[inline]
+    fn ingress_network_acl(&self) -> Option<&AclNetworkRule> {
+        self.ingress_net_filter.as_ref().map(|v| v.as_ref())
+    }
+
+    #[inline]
+    fn accept_timeout(&self) -> Duration {
+        self.accept_timeout
+    }
+}
+
+pub(crate) struct QuicProxyServer {
+    config: Arc<QuicProxyServerConfig>,
+    server_stats: Arc<QuicProxyServerStats>,
+    listen_stats: Arc<ListenStats>,
+    quinn_config: quinn::ServerConfig,
+    reload_sender: broadcast::Sender<ServerReloadCommand>,
+    cfg_sender: Arc<watch::Sender<QuicProxyAuxConfig>>,
+    task_logger: Logger,
+    hosts: HostMatch<Arc<QuicHost>>,
+
+    quit_policy: Arc<ServerQuitPolicy>,
+    reload_version: usize,
+}
+
+impl QuicProxyServer {
+    fn new(
+        config: Arc<QuicProxyServerConfig>,
+        server_stats: Arc<QuicProxyServerStats>,
+        listen_stats: Arc<ListenStats>,
+        hosts: HostMatch<Arc<QuicHost>>,
+        version: usize,
+    ) -> anyhow::Result<Self> {
+        let reload_sender = crate::serve::new_reload_notify_channel();
+
+        let cert_resolver = QuicSniCertResolver::new(&hosts);
+        let tls_config = config.build_tls_config(Arc::new(cert_resolver))?;
+        let quinn_config = quinn::ServerConfig::with_crypto(tls_config);
+
+        let aux_config = QuicProxyAuxConfig {
+            ingress_net_filter: config
+                .ingress_net_filter
+                .as_ref()
+                .map(|builder| Arc::new(builder.build())),
+            listen_config: None,
+            quinn_config: None,
+            accept_timeout: config.accept_timeout,
+            offline_rebind_port: config.offline_rebind_port,
+        };
+        let (cfg_sender, _cfg_receiver) = watch::channel(aux_config);
+
+        let task_logger = config.get_task_logger();
+
+        // always update extra metrics tags
+        server_stats.set_extra_tags(config.extra_metrics_tags.clone());
+
+        Ok(QuicProxyServer {
+            config,
+            server_stats,
+            listen_stats,
+            quinn_config,
+            reload_sender,
+            cfg_sender: Arc::new(cfg_sender),
+            task_logger,
+            hosts,
+            quit_policy: Arc::new(ServerQuitPolicy::default()),
+            reload_version: version,
+        })
+    }
+
+    pub(crate) fn prepare_initial(config: QuicProxyServerConfig) -> anyhow::Result<ArcServer> {
+        let config = Arc::new(config);
+        let server_stats = Arc::new(QuicProxyServerStats::new(config.name()));
+        let listen_stats = Arc::new(ListenStats::new(config.name()));
+
+        let hosts = config.hosts.try_build_arc(QuicHost::try_build)?;
+
+        let server = QuicProxyServer::new(config, server_stats, listen_stats, hosts, 1)?;
+        Ok(Arc::new(server))
+    }
+
+    fn prepare_reload(&self, config: AnyServerConfig) -> anyhow::Result<QuicProxyServer> {
+        if let AnyServerConfig::QuicProxy(config) = config {
+            let config = Arc::new(config);
+            let server_stats = Arc::clone(&self.server_stats);
+            let listen_stats = Arc::clone(&self.listen_stats);
+
+            let old_hosts_map = self.hosts.get_all_values();
+            let new_conf_map = config.hosts.get_all_values();
+            let mut new_hosts_map = AHashMap::with_capacity(new_conf_map.len());
+            for (name, conf) in new_conf_map {
+                let host = if let Some(old_host) = old_hosts_map.get(&name) {
+                    old_host.new_for_reload(conf)?
+                } else {
+                    QuicHost::try_build(&conf)?
+                };
+                new_hosts_map.insert(name, Arc::new(host));
+            }
+            let hosts = config.hosts.build_from(new_hosts_map);
+
+            QuicProxyServer::new(
+                config,
+                server_stats,
+                listen_stats,
+                hosts,
+                self.reload_version + 1,
+            )
+        } else {
+            Err(anyhow!(
+                "config type mismatch: expect {}, actual {}",
+                self.config.server_type(),
+                config.server_type()
+            ))
+        }
+    }
+
+    fn push_aux_config(&self, server: &QuicProxyServer) {
+        let listen_config = if server.config.listen != self.config.listen {
+            Some(server.config.listen.clone())
+        } else {
+            None
+        };
+        let mut aux_config = server.cfg_sender.borrow().clone();
+        aux_config.listen_config = listen_config;
+        aux_config.quinn_config = Some(server.quinn_config.clone());
+        self.cfg_sender.send_replace(aux_config);
+    }
+}
+
+impl ServerInternal for QuicProxyServer {
+    fn _clone_config(&self) -> AnyServerConfig {
+        AnyServerConfig::QuicProxy(self.config.as_ref().clone())
+    }
+
+    fn _update_config_in_place(&self, _flags: u64, _config: AnyServerConfig) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn _depend_on_server(&self, _name: &MetricsName) -> bool {
+        false
+    }
+
+    fn _reload_config_notify_runtime(&self) {
+        let cmd = ServerReloadCommand::ReloadVersion(self.reload_version);
+        let _ = self.reload_sender.send(cmd);
+    }
+
+    fn _update_next_servers_in_place(&self) {}
+
+    fn _reload_with_old_notifier(&self, config: AnyServerConfig) -> anyhow::Result<ArcServer> {
+        let mut server = self.prepare_reload(config)?;
+        // the running listeners will pick up the new tls and listen config
+        self.push_aux_config(&server);
+        server.reload_sender = self.reload_sender.clone();
+        server.cfg_sender = Arc::clone(&self.cfg_sender);
+        Ok(Arc::new(server))
+    }
+
+    fn _reload_with_new_notifier(&self, config: AnyServerConfig) -> anyhow::Result<ArcServer> {
+        let server = self.prepare_reload(config)?;
+        Ok(Arc::new(server))
+    }
+
+    fn _start_runtime(&self, server: &ArcServer) -> anyhow::Result<()> {
+        let runtime = ListenQuicRuntime::new(
+            WrapArcServer(server.clone()),
+            server.get_listen_stats(),
+            self.config.listen.clone(),
+        );
+        runtime
+            .run_all_instances(
+                self.config.listen_in_worker,
+                &self.quinn_config,
+                &self.reload_sender,
+                &self.cfg_sender,
+            )
+            .map(|_| self.server_stats.set_online())
+    }
+
+    fn _abort_runtime(&self) {
+        let _ = self.reload_sender.send(ServerReloadCommand::QuitRuntime);
+        self.server_stats.set_offline();
+    }
+}
+
+impl BaseServer for QuicProxyServer {
+    #[inline]
+    fn name(&self) -> &MetricsName {
+        self.config.name()
+    }
+
+    #[inline]
+    fn server_type(&self) -> &'static str {
+        self.config.server_type()
+    }
+
+    #[inline]
+    fn version(&self) -> usize {
+        self.reload_version
+    }
+}
+
+#[async_trait]
+impl AcceptTcpServer for QuicProxyServer {
+    async fn run_tcp_task(&self, _stream: TcpStream, _cc_info: ClientConnectionInfo) {}
+}
+
+#[async_trait]
+impl AcceptQuicServer for QuicProxyServer {
+    async fn run_quic_task(&self, connection: Connection, cc_info: ClientConnectionInfo) {
+        // the ingress network filter has already been checked in the listen runtime
+        self.server_stats.add_conn(cc_info.client_addr());
+
+        let ctx = CommonTaskContext {
+            server_config: Arc::clone(&self.config),
+            server_stats: Arc::clone(&self.server_stats),
+            server_quit_policy: Arc::clone(&self.quit_policy),
+            cc_info,
+            task_logger: self.task_logger.clone(),
+        };
+
+        QuicAcceptTask::new(ctx)
+            .into_running(connection, &self.hosts)
+            .await
+    }
+}
+
+#[async_trait]
+impl Server for QuicProxyServer {
+    fn get_server_stats(&self) -> Option<ArcServerStats> {
+        Some(Arc::clone(&self.server_stats) as _)
+    }
+
+    fn get_listen_stats(&self) -> Arc<ListenStats> {
+        Arc::clone(&self.listen_stats)
+    }
+
+    fn alive_count(&self) -> i32 {
+        self.server_stats.get_alive_count()
+    }
+
+    #[inline]
+    fn quit_policy(&self) -> &Arc<ServerQuitPolicy> {
+        &self.quit_policy
+    }
+
+    fn update_backend(&self, name: &MetricsName) {
+        let host_map = self.hosts.get_all_values();
+        for host in host_map.values() {
+            if host.use_backend(name) {
+                host.update_backends();
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+
+use g3_types::net::Host;
+use g3_types::route::HostMatch;
+
+use super::QuicHost;
+
+pub(super) struct QuicSniCertResolver {
+    hosts: HostMatch<Arc<QuicHost>>,
+}
+
+impl QuicSniCertResolver {
+    pub(super) fn new(hosts: &HostMatch<Arc<QuicHost>>) -> Self {
+        QuicSniCertResolver {
+            hosts: hosts.clone(),
+        }
+    }
+}
+
+impl ResolvesServerCert for QuicSniCertResolver {
+    fn resolve(&self, client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        let host = match client_hello.server_name() {
+            Some(sni) => {
+                let name = Host::from_str(sni).ok()?;
+                self.hosts.get(&name).or_else(|| self.hosts.get_default())?
+            }
+            None => self.hosts.get_default()?,
+        };
+        host.cert_resolver.resolve(client_hello)
+    }
+}
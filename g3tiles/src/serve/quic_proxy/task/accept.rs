@@ -0,0 +1,147 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use log::debug;
+use quinn::{Connection, RecvStream, SendStream, VarInt};
+use tokio::time::Instant;
+
+use g3_types::net::Host;
+use g3_types::route::HostMatch;
+
+use super::{CommonTaskContext, QuicRelayTask};
+use crate::serve::quic_proxy::QuicHost;
+use crate::serve::TlsSessionInfo;
+
+const QUIC_NO_ERROR: VarInt = VarInt::from_u32(0);
+
+pub(crate) struct QuicAcceptTask {
+    ctx: CommonTaskContext,
+}
+
+impl QuicAcceptTask {
+    pub(crate) fn new(ctx: CommonTaskContext) -> Self {
+        QuicAcceptTask { ctx }
+    }
+
+    pub(crate) async fn into_running(
+        self,
+        connection: Connection,
+        hosts: &HostMatch<Arc<QuicHost>>,
+    ) {
+        let time_accepted = Instant::now();
+
+        let tls_info = TlsSessionInfo::from_quinn(&connection);
+        let Some(host) = self.get_host(tls_info.sni(), hosts) else {
+            connection.close(QUIC_NO_ERROR, b"no matched host");
+            return;
+        };
+
+        let backend = if let Some(alpn) = tls_info.alpn() {
+            let protocol = unsafe { std::str::from_utf8_unchecked(alpn) };
+            host.get_backend(protocol)
+        } else {
+            host.get_default_backend()
+        };
+        let Some(backend) = backend else {
+            connection.close(QUIC_NO_ERROR, b"no backend service");
+            return;
+        };
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration;
+        let mut quit_check_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        loop {
+            tokio::select! {
+                biased;
+
+                r = connection.accept_bi() => {
+                    match r {
+                        Ok((send_stream, recv_stream)) => {
+                            if host.check_rate_limit().is_err() {
+                                reject_stream(send_stream, recv_stream);
+                                continue;
+                            }
+                            let Ok(alive_permit) = host.acquire_request_semaphore() else {
+                                reject_stream(send_stream, recv_stream);
+                                continue;
+                            };
+
+                            let task = QuicRelayTask::new(
+                                self.ctx.clone(),
+                                host.clone(),
+                                backend.clone(),
+                                time_accepted.elapsed(),
+                                tls_info.clone(),
+                                alive_permit,
+                            );
+                            if self.ctx.server_config.spawn_task_unconstrained {
+                                tokio::spawn(tokio::task::unconstrained(
+                                    task.into_running(send_stream, recv_stream),
+                                ));
+                            } else {
+                                tokio::spawn(task.into_running(send_stream, recv_stream));
+                            }
+                        }
+                        Err(e) => {
+                            debug!(
+                                "{} - {} quic connection error: {e:?}",
+                                self.ctx.cc_info.sock_local_addr(),
+                                self.ctx.cc_info.sock_peer_addr()
+                            );
+                            break;
+                        }
+                    }
+                }
+                _ = quit_check_interval.tick() => {
+                    if self.ctx.server_quit_policy.force_quit() {
+                        connection.close(QUIC_NO_ERROR, b"server quit");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn get_host(
+        &self,
+        sni: Option<&str>,
+        hosts: &HostMatch<Arc<QuicHost>>,
+    ) -> Option<Arc<QuicHost>> {
+        if let Some(sni) = sni {
+            match Host::from_str(sni) {
+                Ok(name) => {
+                    if let Some(host) = hosts.get(&name) {
+                        return Some(host.clone());
+                    }
+                }
+                Err(e) => {
+                    debug!("invalid sni hostname: {e:?}");
+                    return None;
+                }
+            }
+        }
+
+        hosts.get_default().cloned()
+    }
+}
+
+fn reject_stream(mut send_stream: SendStream, mut recv_stream: RecvStream) {
+    let _ = send_stream.reset(QUIC_NO_ERROR);
+    let _ = recv_stream.stop(QUIC_NO_ERROR);
+}
@@ -0,0 +1,226 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::debug;
+use quinn::{RecvStream, SendStream};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::Instant;
+
+use g3_daemon::stat::task::TcpStreamTaskStats;
+use g3_io_ext::{LimitedCopy, LimitedCopyError, LimitedReader, LimitedWriter};
+use g3_types::limit::GaugeSemaphorePermit;
+
+use super::{CommonTaskContext, QuicRelayTaskCltWrapperStats};
+use crate::backend::ArcBackend;
+use crate::config::server::ServerConfig;
+use crate::log::task::tcp_connect::TaskLogForTcpConnect;
+use crate::serve::quic_proxy::QuicHost;
+use crate::serve::{
+    ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage, TlsSessionInfo,
+};
+
+pub(crate) struct QuicRelayTask {
+    ctx: CommonTaskContext,
+    host: Arc<QuicHost>,
+    backend: ArcBackend,
+    task_notes: ServerTaskNotes,
+    task_stats: Arc<TcpStreamTaskStats>,
+    alive_permit: Option<GaugeSemaphorePermit>,
+}
+
+impl QuicRelayTask {
+    pub(crate) fn new(
+        ctx: CommonTaskContext,
+        host: Arc<QuicHost>,
+        backend: ArcBackend,
+        wait_time: Duration,
+        tls_info: TlsSessionInfo,
+        alive_permit: Option<GaugeSemaphorePermit>,
+    ) -> Self {
+        let mut task_notes = ServerTaskNotes::new(ctx.cc_info.clone(), wait_time);
+        task_notes.tls_info = Some(tls_info);
+        QuicRelayTask {
+            ctx,
+            host,
+            backend,
+            task_notes,
+            task_stats: Arc::new(TcpStreamTaskStats::default()),
+            alive_permit,
+        }
+    }
+
+    fn get_log_context(&self) -> TaskLogForTcpConnect {
+        TaskLogForTcpConnect {
+            task_notes: &self.task_notes,
+            total_time: self.task_notes.time_elapsed(),
+            client_rd_bytes: self.task_stats.clt.read.get_bytes(),
+            client_wr_bytes: self.task_stats.clt.write.get_bytes(),
+            remote_rd_bytes: self.task_stats.ups.read.get_bytes(),
+            remote_wr_bytes: self.task_stats.ups.write.get_bytes(),
+        }
+    }
+
+    pub(crate) async fn into_running(mut self, send_stream: SendStream, recv_stream: RecvStream) {
+        self.pre_start();
+        if let Err(e) = self.run(send_stream, recv_stream).await {
+            self.get_log_context().log(&self.ctx.task_logger, &e)
+        }
+        self.pre_stop();
+    }
+
+    fn pre_start(&self) {
+        debug!(
+            "QuicProxy: new client stream from {} to {} server {}",
+            self.ctx.client_addr(),
+            self.ctx.server_config.server_type(),
+            self.ctx.server_config.name(),
+        );
+        self.ctx.server_stats.add_task();
+        self.ctx.server_stats.inc_alive_task();
+    }
+
+    fn pre_stop(&mut self) {
+        if let Some(permit) = self.alive_permit.take() {
+            drop(permit);
+        }
+        self.ctx.server_stats.dec_alive_task();
+    }
+
+    async fn run(
+        &mut self,
+        send_stream: SendStream,
+        recv_stream: RecvStream,
+    ) -> ServerTaskResult<()> {
+        self.task_notes.stage = ServerTaskStage::Connecting;
+
+        let (ups_r, ups_w) = self.backend.stream_connect(&self.task_notes).await?;
+
+        self.task_notes.stage = ServerTaskStage::Connected;
+
+        self.run_connected(send_stream, recv_stream, ups_r, ups_w)
+            .await
+    }
+
+    async fn run_connected<UR, UW>(
+        &mut self,
+        send_stream: SendStream,
+        recv_stream: RecvStream,
+        ups_r: UR,
+        ups_w: UW,
+    ) -> ServerTaskResult<()>
+    where
+        UR: AsyncRead + Unpin,
+        UW: AsyncWrite + Unpin,
+    {
+        self.task_notes.mark_relaying();
+        self.relay(send_stream, recv_stream, ups_r, ups_w).await
+    }
+
+    async fn relay<UR, UW>(
+        &mut self,
+        send_stream: SendStream,
+        recv_stream: RecvStream,
+        mut ups_r: UR,
+        mut ups_w: UW,
+    ) -> ServerTaskResult<()>
+    where
+        UR: AsyncRead + Unpin,
+        UW: AsyncWrite + Unpin,
+    {
+        let limit = match &self.host.config.stream_speed_limit {
+            Some(limit) => self
+                .ctx
+                .server_config
+                .stream_speed_limit
+                .shrink_as_smaller(limit),
+            None => self.ctx.server_config.stream_speed_limit,
+        };
+        // TODO add host level stats
+        let clt_wrapper_stats = Arc::new(QuicRelayTaskCltWrapperStats::new(
+            &self.ctx.server_stats,
+            &self.task_stats,
+        ));
+        let mut clt_r = LimitedReader::new(
+            recv_stream,
+            limit.shift_millis,
+            limit.max_north,
+            clt_wrapper_stats.clone(),
+        );
+        let mut clt_w = LimitedWriter::new(
+            send_stream,
+            limit.shift_millis,
+            limit.max_south,
+            clt_wrapper_stats,
+        );
+
+        let copy_config = &self.ctx.server_config.stream_copy;
+        let mut clt_to_ups = LimitedCopy::new(&mut clt_r, &mut ups_w, copy_config);
+        let mut ups_to_clt = LimitedCopy::new(&mut ups_r, &mut clt_w, copy_config);
+
+        let idle_duration = self.ctx.server_config.task_idle_check_duration;
+        let task_idle_max_count = self
+            .host
+            .config
+            .task_idle_max_count
+            .unwrap_or(self.ctx.server_config.task_idle_max_count);
+        let mut idle_interval =
+            tokio::time::interval_at(Instant::now() + idle_duration, idle_duration);
+        let mut idle_count = 0;
+        loop {
+            tokio::select! {
+                biased;
+
+                r = &mut clt_to_ups => {
+                    let _ = ups_to_clt.write_flush().await;
+                    return match r {
+                        Ok(_) => Err(ServerTaskError::ClosedByClient),
+                        Err(LimitedCopyError::ReadFailed(e)) => Err(ServerTaskError::ClientQuicReadFailed(e)),
+                        Err(LimitedCopyError::WriteFailed(e)) => Err(ServerTaskError::UpstreamWriteFailed(e)),
+                    };
+                }
+                r = &mut ups_to_clt => {
+                    let _ = clt_to_ups.write_flush().await;
+                    return match r {
+                        Ok(_) => Err(ServerTaskError::ClosedByUpstream),
+                        Err(LimitedCopyError::ReadFailed(e)) => Err(ServerTaskError::UpstreamReadFailed(e)),
+                        Err(LimitedCopyError::WriteFailed(e)) => Err(ServerTaskError::ClientQuicWriteFailed(e)),
+                    };
+                }
+                _ = idle_interval.tick() => {
+                    if clt_to_ups.is_idle() && ups_to_clt.is_idle() {
+                        idle_count += 1;
+
+                        if idle_count >= task_idle_max_count {
+                            return Err(ServerTaskError::Idle(idle_duration, idle_count));
+                        }
+                    } else {
+                        idle_count = 0;
+
+                        clt_to_ups.reset_active();
+                        ups_to_clt.reset_active();
+                    }
+
+                    if self.ctx.server_quit_policy.force_quit() {
+                        return Err(ServerTaskError::CanceledAsServerQuit)
+                    }
+                }
+            };
+        }
+    }
+}
@@ -31,7 +31,9 @@ use crate::backend::ArcBackend;
 use crate::config::server::ServerConfig;
 use crate::log::task::tcp_connect::TaskLogForTcpConnect;
 use crate::serve::rustls_proxy::RustlsHost;
-use crate::serve::{ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage};
+use crate::serve::{
+    ServerTaskError, ServerTaskNotes, ServerTaskResult, ServerTaskStage, TlsSessionInfo,
+};
 
 pub(crate) struct RustlsRelayTask {
     ctx: CommonTaskContext,
@@ -80,6 +82,9 @@ impl RustlsRelayTask {
         S: AsyncRead + AsyncWrite + Unpin,
     {
         self.pre_start();
+        if let TlsStream::Server(s) = &tls_stream {
+            self.task_notes.tls_info = Some(TlsSessionInfo::from_rustls(s.get_ref().1));
+        }
         if let Err(e) = self.run(tls_stream).await {
             self.get_log_context().log(&self.ctx.task_logger, &e)
         }
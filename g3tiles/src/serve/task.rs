@@ -23,6 +23,8 @@ use uuid::Uuid;
 
 use g3_daemon::server::ClientConnectionInfo;
 
+use super::TlsSessionInfo;
+
 #[derive(Clone)]
 pub(crate) enum ServerTaskStage {
     Created,
@@ -61,6 +63,7 @@ pub(crate) struct ServerTaskNotes {
     pub(crate) id: Uuid,
     pub(crate) wait_time: Duration,
     pub(crate) ready_time: Duration,
+    pub(crate) tls_info: Option<TlsSessionInfo>,
 }
 
 impl ServerTaskNotes {
@@ -75,6 +78,7 @@ impl ServerTaskNotes {
             id: uuid,
             wait_time,
             ready_time: Duration::default(),
+            tls_info: None,
         }
     }
 
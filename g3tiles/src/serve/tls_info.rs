@@ -0,0 +1,151 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use openssl::nid::Nid;
+use openssl::ssl::SslRef;
+use openssl::x509::{X509Ref, X509VerifyResult, X509};
+use rustls::server::ServerConnection;
+use rustls::ProtocolVersion;
+
+use g3_types::net::{ProxyProtocolEncodeError, ProxyProtocolV2SslTlv, ProxyProtocolV2Tlvs};
+
+#[derive(Clone, Default)]
+pub(crate) struct TlsSessionInfo {
+    sni: Option<String>,
+    alpn: Option<Vec<u8>>,
+    version: Option<&'static str>,
+    cipher: Option<&'static str>,
+    client_cert_presented: bool,
+    client_cert_verified: bool,
+    session_reused: bool,
+    client_cert_cn: Option<String>,
+}
+
+impl TlsSessionInfo {
+    pub(crate) fn from_openssl(ssl: &SslRef) -> Self {
+        let mut info = TlsSessionInfo {
+            sni: ssl
+                .servername(openssl::ssl::NameType::HOST_NAME)
+                .map(|s| s.to_string()),
+            alpn: ssl.selected_alpn_protocol().map(|v| v.to_vec()),
+            version: Some(ssl.version_str()),
+            cipher: ssl.current_cipher().map(|c| c.name()),
+            session_reused: ssl.session_reused(),
+            ..Default::default()
+        };
+        if let Some(cert) = ssl.peer_certificate() {
+            info.client_cert_presented = true;
+            info.client_cert_verified = ssl.verify_result() == X509VerifyResult::OK;
+            info.client_cert_cn = get_cert_cn(&cert);
+        }
+        info
+    }
+
+    pub(crate) fn from_rustls(conn: &ServerConnection) -> Self {
+        let mut info = TlsSessionInfo {
+            sni: conn.server_name().map(|s| s.to_string()),
+            alpn: conn.alpn_protocol().map(|v| v.to_vec()),
+            version: conn.protocol_version().and_then(|v| match v {
+                ProtocolVersion::TLSv1_3 => Some("TLSv1.3"),
+                ProtocolVersion::TLSv1_2 => Some("TLSv1.2"),
+                v => v.as_str(),
+            }),
+            cipher: conn
+                .negotiated_cipher_suite()
+                .and_then(|s| s.suite().as_str()),
+            ..Default::default()
+        };
+        if let Some(certs) = conn.peer_certificates() {
+            if let Some(cert) = certs.first() {
+                // rustls will fail the handshake if the client certificate is not verified
+                info.client_cert_presented = true;
+                info.client_cert_verified = true;
+                info.client_cert_cn = X509::from_der(&cert.0)
+                    .ok()
+                    .and_then(|cert| get_cert_cn(&cert));
+            }
+        }
+        info
+    }
+
+    #[cfg(feature = "quic")]
+    pub(crate) fn from_quinn(conn: &quinn::Connection) -> Self {
+        let mut info = TlsSessionInfo {
+            version: Some("TLSv1.3"),
+            ..Default::default()
+        };
+        if let Some(data) = conn
+            .handshake_data()
+            .and_then(|v| v.downcast::<quinn::crypto::rustls::HandshakeData>().ok())
+        {
+            info.sni = data.server_name;
+            info.alpn = data.protocol;
+        }
+        if let Some(certs) = conn
+            .peer_identity()
+            .and_then(|v| v.downcast::<Vec<rustls::Certificate>>().ok())
+        {
+            if let Some(cert) = certs.first() {
+                // rustls will fail the handshake if the client certificate is not verified
+                info.client_cert_presented = true;
+                info.client_cert_verified = true;
+                info.client_cert_cn = X509::from_der(&cert.0)
+                    .ok()
+                    .and_then(|cert| get_cert_cn(&cert));
+            }
+        }
+        info
+    }
+
+    #[cfg(feature = "quic")]
+    pub(crate) fn sni(&self) -> Option<&str> {
+        self.sni.as_deref()
+    }
+
+    #[cfg(feature = "quic")]
+    pub(crate) fn alpn(&self) -> Option<&[u8]> {
+        self.alpn.as_deref()
+    }
+
+    pub(crate) fn push_proxy_protocol_tlvs(
+        &self,
+        tlvs: &mut ProxyProtocolV2Tlvs,
+    ) -> Result<(), ProxyProtocolEncodeError> {
+        if let Some(alpn) = &self.alpn {
+            tlvs.push_alpn(alpn)?;
+        }
+        if let Some(sni) = &self.sni {
+            tlvs.push_authority(sni)?;
+        }
+        let ssl = ProxyProtocolV2SslTlv {
+            client_cert_conn: self.client_cert_presented && !self.session_reused,
+            client_cert_sess: self.client_cert_presented,
+            client_cert_verified: self.client_cert_verified,
+            version: self.version,
+            cipher: self.cipher,
+            client_cert_cn: self.client_cert_cn.as_deref(),
+        };
+        tlvs.push_ssl(&ssl)
+    }
+}
+
+fn get_cert_cn(cert: &X509Ref) -> Option<String> {
+    cert.subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|e| e.data().as_utf8().ok())
+        .map(|s| s.to_string())
+}
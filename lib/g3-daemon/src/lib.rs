@@ -24,6 +24,7 @@ pub mod opts;
 pub mod runtime;
 pub mod server;
 pub mod stat;
+pub mod trace;
 
 #[cfg(feature = "register")]
 pub mod register;
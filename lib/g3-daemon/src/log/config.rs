@@ -20,9 +20,11 @@ use std::sync::Arc;
 use anyhow::{anyhow, Context};
 use yaml_rust::Yaml;
 
+use g3_filelog::FileLogConfig;
 use g3_fluentd::FluentdClientConfig;
 #[cfg(target_os = "linux")]
 use g3_journal::JournalConfig;
+use g3_otlp::OtlpLogClientConfig;
 use g3_syslog::SyslogBuilder;
 
 const DEFAULT_CHANNEL_SIZE: usize = 4096;
@@ -35,7 +37,9 @@ pub enum LogConfigDriver {
     #[cfg(target_os = "linux")]
     Journal(JournalConfig),
     Syslog(SyslogBuilder),
+    File(Arc<FileLogConfig>),
     Fluentd(Arc<FluentdClientConfig>),
+    Otlp(Arc<OtlpLogClientConfig>),
 }
 
 #[derive(Clone)]
@@ -77,6 +81,13 @@ impl LogConfig {
         )
     }
 
+    pub fn default_file(program_name: &'static str) -> Self {
+        Self::with_driver(
+            LogConfigDriver::File(Arc::new(FileLogConfig::with_program_name(program_name))),
+            program_name,
+        )
+    }
+
     pub fn default_fluentd(program_name: &'static str) -> Self {
         Self::with_driver(
             LogConfigDriver::Fluentd(Arc::new(FluentdClientConfig::default())),
@@ -84,6 +95,13 @@ impl LogConfig {
         )
     }
 
+    pub fn default_otlp(program_name: &'static str) -> Self {
+        Self::with_driver(
+            LogConfigDriver::Otlp(Arc::new(OtlpLogClientConfig::default())),
+            program_name,
+        )
+    }
+
     pub fn parse(
         v: &Yaml,
         conf_dir: &Path,
@@ -95,7 +113,9 @@ impl LogConfig {
                 #[cfg(target_os = "linux")]
                 "journal" => Ok(LogConfig::default_journal(program_name)),
                 "syslog" => Ok(LogConfig::default_syslog(program_name)),
+                "file" => Ok(LogConfig::default_file(program_name)),
                 "fluentd" => Ok(LogConfig::default_fluentd(program_name)),
+                "otlp" => Ok(LogConfig::default_otlp(program_name)),
                 _ => Err(anyhow!("invalid log config")),
             },
             Yaml::Hash(map) => {
@@ -113,12 +133,24 @@ impl LogConfig {
                         config.driver = LogConfigDriver::Syslog(builder);
                         Ok(())
                     }
+                    "file" => {
+                        let file_config = g3_yaml::value::as_file_log_config(v, program_name)
+                            .context("invalid file log config")?;
+                        config.driver = LogConfigDriver::File(Arc::new(file_config));
+                        Ok(())
+                    }
                     "fluentd" => {
                         let client = g3_yaml::value::as_fluentd_client_config(v, Some(conf_dir))
                             .context("invalid fluentd config")?;
                         config.driver = LogConfigDriver::Fluentd(Arc::new(client));
                         Ok(())
                     }
+                    "otlp" => {
+                        let client = g3_yaml::value::as_otlp_log_client_config(v, Some(conf_dir))
+                            .context("invalid otlp config")?;
+                        config.driver = LogConfigDriver::Otlp(Arc::new(client));
+                        Ok(())
+                    }
                     "async_channel_size" | "channel_size" => {
                         let channel_size = g3_yaml::value::as_usize(v)
                             .context(format!("invalid usize value for key {k}"))?;
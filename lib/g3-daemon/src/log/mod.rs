@@ -24,7 +24,7 @@ pub(crate) use stats::LoggerStats;
 
 pub mod metrics;
 
-mod registry;
+pub(crate) mod registry;
 
 mod runtime;
 pub use runtime::{create_logger, create_shared_logger};
@@ -32,6 +32,11 @@ pub use runtime::{create_logger, create_shared_logger};
 mod config;
 pub use config::{LogConfig, LogConfigDriver};
 
+/// Reopen all log files used by the file log driver, so external tools can rotate them
+pub fn reopen_files() {
+    g3_filelog::reopen_all();
+}
+
 pub struct LogConfigContainer {
     inner: Option<LogConfig>,
 }
@@ -24,7 +24,7 @@ use super::LoggerStats;
 static RUNTIME_LOGGER_REGISTRY: Lazy<Mutex<HashMap<String, Arc<LoggerStats>>>> =
     Lazy::new(|| Mutex::new(HashMap::new()));
 
-pub(super) fn add(name: String, stats: Arc<LoggerStats>) {
+pub(crate) fn add(name: String, stats: Arc<LoggerStats>) {
     let mut ht = RUNTIME_LOGGER_REGISTRY.lock().unwrap();
     let _ = ht.insert(name, stats);
 }
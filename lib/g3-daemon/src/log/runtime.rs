@@ -75,6 +75,22 @@ where
             let drain = ReportLogIoError::new(drain, &logger_name, config.io_err_sampling_mask);
             Logger::root(drain, common_values)
         }
+        LogConfigDriver::File(file_conf) => {
+            let async_conf = AsyncLogConfig {
+                channel_capacity: config.async_channel_size,
+                thread_number: config.async_thread_number,
+                thread_name: logger_name.clone(),
+            };
+            let drain = g3_filelog::new_async_logger(
+                &async_conf,
+                &file_conf,
+                &format!("{logger_name}.log"),
+            );
+            let logger_stats = LoggerStats::new(&logger_name, drain.get_stats());
+            super::registry::add(logger_name.clone(), Arc::new(logger_stats));
+            let drain = ReportLogIoError::new(drain, &logger_name, config.io_err_sampling_mask);
+            Logger::root(drain, common_values)
+        }
         LogConfigDriver::Fluentd(fluentd_conf) => {
             let async_conf = AsyncLogConfig {
                 channel_capacity: config.async_channel_size,
@@ -91,5 +107,22 @@ where
             let drain = ReportLogIoError::new(drain, &logger_name, config.io_err_sampling_mask);
             Logger::root(drain, common_values)
         }
+        LogConfigDriver::Otlp(otlp_conf) => {
+            let async_conf = AsyncLogConfig {
+                channel_capacity: config.async_channel_size,
+                thread_number: config.async_thread_number,
+                thread_name: logger_name.clone(),
+            };
+            let drain = g3_otlp::new_async_logger(
+                &async_conf,
+                &otlp_conf,
+                config.program_name.to_string(),
+                log_type.to_string(),
+            );
+            let logger_stats = LoggerStats::new(&logger_name, drain.get_stats());
+            super::registry::add(logger_name.clone(), Arc::new(logger_stats));
+            let drain = ReportLogIoError::new(drain, &logger_name, config.io_err_sampling_mask);
+            Logger::root(drain, common_values)
+        }
     }
 }
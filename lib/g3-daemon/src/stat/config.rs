@@ -16,12 +16,14 @@
 
 use std::str::FromStr;
 
-use anyhow::anyhow;
-use yaml_rust::Yaml;
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
 
-use g3_statsd_client::StatsdClientConfig;
+use g3_statsd_client::{StatsdBackend, StatsdClientConfig};
 use g3_types::metrics::MetricsName;
 
+use super::prometheus::PrometheusExporterConfig;
+
 static mut GLOBAL_STAT_CONFIG: Option<StatsdClientConfig> = None;
 
 pub fn get_global_stat_config() -> Option<StatsdClientConfig> {
@@ -35,7 +37,42 @@ fn set_global_stat_config(config: StatsdClientConfig) {
 pub fn load(v: &Yaml, prefix: &'static str) -> anyhow::Result<()> {
     let prefix = MetricsName::from_str(prefix)
         .map_err(|e| anyhow!("invalid default metrics prefix: {e}"))?;
-    let config = g3_yaml::value::as_statsd_client_config(v, prefix)?;
+    let mut exporter_config: Option<PrometheusExporterConfig> = None;
+    let mut statsd_target_set = false;
+    let v = match v {
+        Yaml::Hash(map) => {
+            let mut statsd_map = yaml::Hash::new();
+            for (k, v) in map {
+                if let Yaml::String(key) = k {
+                    match g3_yaml::key::normalize(key).as_str() {
+                        "prometheus" => {
+                            let mut config = PrometheusExporterConfig::default();
+                            config.parse(v).context(format!(
+                                "invalid prometheus exporter config for key {key}"
+                            ))?;
+                            exporter_config = Some(config);
+                            continue;
+                        }
+                        "target_udp" | "backend_udp" | "target_unix" | "backend_unix"
+                        | "target" | "backend" => statsd_target_set = true,
+                        _ => {}
+                    }
+                }
+                statsd_map.insert(k.clone(), v.clone());
+            }
+            Yaml::Hash(statsd_map)
+        }
+        _ => v.clone(),
+    };
+
+    let mut config = g3_yaml::value::as_statsd_client_config(&v, prefix)?;
+    if let Some(exporter_config) = exporter_config {
+        if !statsd_target_set {
+            // only export to prometheus
+            config.set_backend(StatsdBackend::Null);
+        }
+        super::prometheus::enable(exporter_config, &mut config);
+    }
     set_global_stat_config(config);
     Ok(())
 }
@@ -20,3 +20,5 @@ pub mod remote;
 pub mod task;
 
 pub mod emit;
+
+pub mod prometheus;
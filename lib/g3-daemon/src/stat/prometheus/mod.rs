@@ -0,0 +1,114 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::{yaml, Yaml};
+
+use g3_statsd_client::StatsdClientConfig;
+
+mod registry;
+use registry::PrometheusRegistry;
+
+mod server;
+
+pub struct PrometheusExporterConfig {
+    listen: SocketAddr,
+    path: String,
+    expire: Duration,
+}
+
+impl Default for PrometheusExporterConfig {
+    fn default() -> Self {
+        PrometheusExporterConfig {
+            listen: SocketAddr::from(([127, 0, 0, 1], 9091)),
+            path: "/metrics".to_string(),
+            expire: Duration::from_secs(300),
+        }
+    }
+}
+
+impl PrometheusExporterConfig {
+    pub(crate) fn parse(&mut self, v: &Yaml) -> anyhow::Result<()> {
+        match v {
+            Yaml::Hash(map) => self.parse_map(map),
+            Yaml::String(_) => {
+                self.listen = g3_yaml::value::as_env_sockaddr(v)
+                    .context("invalid listen socket address value")?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid yaml value type")),
+        }
+    }
+
+    fn parse_map(&mut self, map: &yaml::Hash) -> anyhow::Result<()> {
+        g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+            "listen" | "listen_addr" => {
+                self.listen = g3_yaml::value::as_env_sockaddr(v)
+                    .context(format!("invalid socket address value for key {k}"))?;
+                Ok(())
+            }
+            "path" => {
+                let path = g3_yaml::value::as_string(v)?;
+                if !path.starts_with('/') {
+                    return Err(anyhow!("invalid http path value for key {k}"));
+                }
+                self.path = path;
+                Ok(())
+            }
+            "expire" | "expire_duration" => {
+                self.expire = g3_yaml::humanize::as_duration(v)
+                    .context(format!("invalid humanize duration value for key {k}"))?;
+                Ok(())
+            }
+            _ => Err(anyhow!("invalid key {k}")),
+        })
+    }
+}
+
+static mut PROMETHEUS_EXPORTER: Option<(Arc<PrometheusExporterConfig>, Arc<PrometheusRegistry>)> =
+    None;
+
+/// Enable the prometheus exporter by setting the registry as collector of the statsd client
+pub(super) fn enable(config: PrometheusExporterConfig, stat_config: &mut StatsdClientConfig) {
+    let registry = Arc::new(PrometheusRegistry::new(config.expire));
+    stat_config.set_collector(registry.clone());
+    unsafe { PROMETHEUS_EXPORTER = Some((Arc::new(config), registry)) }
+}
+
+/// Spawn the exporter http server thread if it has been configured.
+///
+/// This should be called after entering daemon mode.
+pub fn spawn_working_thread() -> anyhow::Result<()> {
+    let Some((config, registry)) = (unsafe { PROMETHEUS_EXPORTER.clone() }) else {
+        return Ok(());
+    };
+
+    let listener = std::net::TcpListener::bind(config.listen)
+        .map_err(|e| anyhow!("failed to bind to {}: {e}", config.listen))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| anyhow!("failed to set listen socket nonblocking: {e}"))?;
+
+    std::thread::Builder::new()
+        .name("stat-prometheus".to_string())
+        .spawn(move || server::run(listener, config, registry))
+        .map_err(|e| anyhow!("failed to spawn thread: {e:?}"))?;
+    Ok(())
+}
@@ -0,0 +1,345 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+use g3_statsd_client::StatsdMetricsCollector;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MetricValue {
+    Counter(u64),
+    Gauge(f64),
+}
+
+struct MetricSeries {
+    value: MetricValue,
+    updated: Instant,
+}
+
+struct MetricFamily {
+    kind: MetricKind,
+    /// series keyed by the rendered label set
+    series: BTreeMap<String, MetricSeries>,
+}
+
+/// Registry that converts the statsd metrics to prometheus metrics.
+///
+/// The statsd count values are accumulated to be prometheus counters, and the statsd gauge
+/// values are kept as the latest value. A series will be dropped if there is no update for it
+/// during the expire duration, which happens when the corresponding server / escaper etc. is
+/// removed or reloaded.
+pub(super) struct PrometheusRegistry {
+    expire: Duration,
+    families: Mutex<BTreeMap<String, MetricFamily>>,
+}
+
+impl std::fmt::Debug for PrometheusRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrometheusRegistry")
+            .field("expire", &self.expire)
+            .finish()
+    }
+}
+
+impl PrometheusRegistry {
+    pub(super) fn new(expire: Duration) -> Self {
+        PrometheusRegistry {
+            expire,
+            families: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Render all the metrics, in OpenMetrics text format or the classic prometheus text format
+    pub(super) fn render(&self, openmetrics: bool) -> String {
+        let mut families = self.families.lock().unwrap();
+
+        let now = Instant::now();
+        families.retain(|_, family| {
+            family
+                .series
+                .retain(|_, series| now.duration_since(series.updated) < self.expire);
+            !family.series.is_empty()
+        });
+
+        let mut buf = String::with_capacity(4096);
+        for (name, family) in families.iter() {
+            let sample_name = match family.kind {
+                MetricKind::Counter => {
+                    if openmetrics {
+                        let _ = writeln!(buf, "# TYPE {name} counter");
+                    } else {
+                        let _ = writeln!(buf, "# TYPE {name}_total counter");
+                    }
+                    format!("{name}_total")
+                }
+                MetricKind::Gauge => {
+                    let _ = writeln!(buf, "# TYPE {name} gauge");
+                    name.to_string()
+                }
+            };
+            for (labels, series) in family.series.iter() {
+                buf.push_str(&sample_name);
+                if !labels.is_empty() {
+                    buf.push('{');
+                    buf.push_str(labels);
+                    buf.push('}');
+                }
+                buf.push(' ');
+                match series.value {
+                    MetricValue::Counter(v) => {
+                        let mut b = itoa::Buffer::new();
+                        buf.push_str(b.format(v));
+                    }
+                    MetricValue::Gauge(v) => {
+                        if v.is_nan() {
+                            buf.push_str("NaN");
+                        } else if v.is_infinite() {
+                            buf.push_str(if v > 0.0 { "+Inf" } else { "-Inf" });
+                        } else {
+                            let _ = write!(buf, "{v}");
+                        }
+                    }
+                }
+                buf.push('\n');
+            }
+        }
+        if openmetrics {
+            buf.push_str("# EOF\n");
+        }
+        buf
+    }
+}
+
+impl StatsdMetricsCollector for PrometheusRegistry {
+    fn collect(&self, msg: &[u8]) {
+        let Ok(msg) = std::str::from_utf8(msg) else {
+            return;
+        };
+
+        let mut families = self.families.lock().unwrap();
+        for line in msg.split('\n') {
+            record_line(&mut families, line);
+        }
+    }
+}
+
+fn record_line(families: &mut BTreeMap<String, MetricFamily>, line: &str) {
+    // <NAME>:<VALUE>|<TYPE>[|#<TAGS>]
+    let mut parts = line.split('|');
+    let Some(name_value) = parts.next() else {
+        return;
+    };
+    let Some((name, value)) = name_value.rsplit_once(':') else {
+        return;
+    };
+    let (kind, value) = match parts.next() {
+        Some("c") => {
+            let Ok(v) = value.parse::<u64>() else {
+                return;
+            };
+            (MetricKind::Counter, MetricValue::Counter(v))
+        }
+        Some("g") => {
+            let Ok(v) = value.parse::<f64>() else {
+                return;
+            };
+            (MetricKind::Gauge, MetricValue::Gauge(v))
+        }
+        _ => return,
+    };
+    let labels = match parts.next().and_then(|s| s.strip_prefix('#')) {
+        Some(tags) => render_labels(tags),
+        None => String::new(),
+    };
+
+    let mut family_name = sanitize_name(name);
+    if kind == MetricKind::Counter {
+        // the "_total" suffix will be added back when rendering
+        if let Some(s) = family_name.strip_suffix("_total") {
+            family_name.truncate(s.len());
+        }
+    }
+
+    if let Some(family) = families.get(&family_name) {
+        if family.kind != kind {
+            // a counter and a gauge may have the same name after sanitized,
+            // so add the metric type as suffix to the later one
+            let new_name = format!("{family_name}_{}", kind.as_str());
+            if !families.contains_key(&new_name) {
+                warn!(
+                    "prometheus {} {name} conflicts with {} {family_name}, renamed to {new_name}",
+                    kind.as_str(),
+                    family.kind.as_str(),
+                );
+            }
+            family_name = new_name;
+        }
+    }
+
+    let family = families.entry(family_name).or_insert_with(|| MetricFamily {
+        kind,
+        series: BTreeMap::new(),
+    });
+    if family.kind != kind {
+        return;
+    }
+
+    let now = Instant::now();
+    match family.series.get_mut(&labels) {
+        Some(series) => {
+            series.value = match (series.value, value) {
+                (MetricValue::Counter(old), MetricValue::Counter(diff)) => {
+                    MetricValue::Counter(old.wrapping_add(diff))
+                }
+                (_, new) => new,
+            };
+            series.updated = now;
+        }
+        None => {
+            family.series.insert(
+                labels,
+                MetricSeries {
+                    value,
+                    updated: now,
+                },
+            );
+        }
+    }
+}
+
+fn sanitize_name(name: &str) -> String {
+    let mut s = String::with_capacity(name.len());
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_alphabetic() || c == '_' || (i > 0 && c.is_ascii_digit()) {
+            s.push(c);
+        } else {
+            s.push('_');
+        }
+    }
+    s
+}
+
+/// Convert the statsd tags to prometheus labels, tags without value will be skipped
+fn render_labels(tags: &str) -> String {
+    let mut labels: Vec<(String, &str)> = Vec::new();
+    for tag in tags.split(',') {
+        let Some((k, v)) = tag.split_once(':') else {
+            continue;
+        };
+        let k = sanitize_name(k);
+        if labels.iter().any(|(name, _)| *name == k) {
+            continue;
+        }
+        labels.push((k, v));
+    }
+    labels.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut s = String::new();
+    for (k, v) in labels {
+        if !s.is_empty() {
+            s.push(',');
+        }
+        s.push_str(&k);
+        s.push_str("=\"");
+        for c in v.chars() {
+            match c {
+                '\\' => s.push_str("\\\\"),
+                '"' => s.push_str("\\\""),
+                '\n' => s.push_str("\\n"),
+                _ => s.push(c),
+            }
+        }
+        s.push('"');
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_and_gauge() {
+        let registry = PrometheusRegistry::new(Duration::from_secs(60));
+        registry.collect(b"g3proxy.server.task.total:2|c|#server:s1,online:y");
+        registry.collect(
+            b"g3proxy.server.task.total:3|c|#online:y,server:s1\ng3proxy.server.task.alive:4|g",
+        );
+
+        let s = registry.render(true);
+        assert_eq!(
+            s,
+            "# TYPE g3proxy_server_task counter\n\
+             g3proxy_server_task_total{online=\"y\",server=\"s1\"} 5\n\
+             # TYPE g3proxy_server_task_alive gauge\n\
+             g3proxy_server_task_alive 4\n\
+             # EOF\n"
+        );
+    }
+
+    #[test]
+    fn kind_conflict() {
+        let registry = PrometheusRegistry::new(Duration::from_secs(60));
+        registry.collect(b"g3proxy.task.total:2|c\ng3proxy.task:3|g");
+        registry.collect(b"g3proxy.task.total:1|c\ng3proxy.task:4|g");
+
+        let s = registry.render(true);
+        assert_eq!(
+            s,
+            "# TYPE g3proxy_task counter\n\
+             g3proxy_task_total 3\n\
+             # TYPE g3proxy_task_gauge gauge\n\
+             g3proxy_task_gauge 4\n\
+             # EOF\n"
+        );
+
+        let registry = PrometheusRegistry::new(Duration::from_secs(60));
+        registry.collect(b"g3proxy.task:3|g\ng3proxy.task.total:2|c");
+        let s = registry.render(false);
+        assert_eq!(
+            s,
+            "# TYPE g3proxy_task gauge\n\
+             g3proxy_task 3\n\
+             # TYPE g3proxy_task_counter_total counter\n\
+             g3proxy_task_counter_total 2\n"
+        );
+    }
+
+    #[test]
+    fn label_escape() {
+        assert_eq!(render_labels("a-b:x\"y,c"), "a_b=\"x\\\"y\"");
+    }
+}
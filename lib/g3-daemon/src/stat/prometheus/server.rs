@@ -0,0 +1,151 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use log::{debug, warn};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::{PrometheusExporterConfig, PrometheusRegistry};
+
+const MAX_HEADER_SIZE: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CONTENT_TYPE_OPENMETRICS: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub(super) fn run(
+    listener: std::net::TcpListener,
+    config: Arc<PrometheusExporterConfig>,
+    registry: Arc<PrometheusRegistry>,
+) {
+    let rt = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
+        Err(e) => {
+            warn!("failed to create runtime for prometheus exporter: {e}");
+            return;
+        }
+    };
+
+    rt.block_on(async move {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(l) => l,
+            Err(e) => {
+                warn!("failed to setup prometheus exporter listener: {e}");
+                return;
+            }
+        };
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let config = config.clone();
+                    let registry = registry.clone();
+                    tokio::spawn(async move {
+                        match tokio::time::timeout(
+                            REQUEST_TIMEOUT,
+                            serve_connection(stream, &config, &registry),
+                        )
+                        .await
+                        {
+                            Ok(Ok(_)) => {}
+                            Ok(Err(e)) => debug!("prometheus exporter: error serving {peer}: {e}"),
+                            Err(_) => debug!("prometheus exporter: timeout serving {peer}"),
+                        }
+                    });
+                }
+                Err(e) => {
+                    warn!("prometheus exporter: failed to accept: {e}");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    config: &PrometheusExporterConfig,
+    registry: &PrometheusRegistry,
+) -> anyhow::Result<()> {
+    let (r, mut w) = stream.into_split();
+    let mut reader = BufReader::new(r);
+
+    let mut header_size = 0usize;
+    let mut request_line = String::new();
+    let mut accept_openmetrics = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let nr = reader.read_line(&mut line).await?;
+        if nr == 0 {
+            return Err(anyhow!("connection closed before end of request header"));
+        }
+        header_size += nr;
+        if header_size > MAX_HEADER_SIZE {
+            return Err(anyhow!("request header too large"));
+        }
+
+        let l = line.trim_end();
+        if l.is_empty() {
+            break;
+        }
+        if request_line.is_empty() {
+            request_line.push_str(l);
+        } else if let Some((name, value)) = l.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("accept")
+                && value.contains("application/openmetrics-text")
+            {
+                accept_openmetrics = true;
+            }
+        }
+    }
+
+    let mut parts = request_line.split_ascii_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let path = target.split_once('?').map(|(p, _)| p).unwrap_or(target);
+
+    let (status, content_type, body) = if method != "GET" && method != "HEAD" {
+        ("405 Method Not Allowed", CONTENT_TYPE_TEXT, String::new())
+    } else if path != config.path {
+        ("404 Not Found", CONTENT_TYPE_TEXT, String::new())
+    } else if accept_openmetrics {
+        ("200 OK", CONTENT_TYPE_OPENMETRICS, registry.render(true))
+    } else {
+        ("200 OK", CONTENT_TYPE_TEXT, registry.render(false))
+    };
+
+    let header = format!(
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: {content_type}\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    );
+    w.write_all(header.as_bytes()).await?;
+    if method != "HEAD" {
+        w.write_all(body.as_bytes()).await?;
+    }
+    w.shutdown().await?;
+    Ok(())
+}
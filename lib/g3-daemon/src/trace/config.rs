@@ -0,0 +1,84 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+use g3_otlp::OtlpLogClientConfig;
+
+const DEFAULT_CHANNEL_SIZE: usize = 4096;
+
+#[derive(Clone)]
+pub struct TraceConfig {
+    pub(crate) otlp: OtlpLogClientConfig,
+    pub(crate) async_channel_size: usize,
+    pub(crate) async_thread_number: usize,
+}
+
+impl Default for TraceConfig {
+    fn default() -> Self {
+        TraceConfig {
+            otlp: OtlpLogClientConfig::default(),
+            async_channel_size: DEFAULT_CHANNEL_SIZE,
+            async_thread_number: 1,
+        }
+    }
+}
+
+static mut GLOBAL_TRACE_CONFIG: Option<TraceConfig> = None;
+
+pub fn get_global_trace_config() -> Option<TraceConfig> {
+    unsafe { GLOBAL_TRACE_CONFIG.clone() }
+}
+
+fn set_global_trace_config(config: TraceConfig) {
+    unsafe { GLOBAL_TRACE_CONFIG = Some(config) }
+}
+
+pub fn load(v: &Yaml, conf_dir: &Path) -> anyhow::Result<()> {
+    let mut config = TraceConfig::default();
+    match v {
+        Yaml::String(s) => match s.as_str() {
+            "otlp" => {}
+            _ => return Err(anyhow!("invalid trace config")),
+        },
+        Yaml::Hash(map) => {
+            g3_yaml::foreach_kv(map, |k, v| match g3_yaml::key::normalize(k).as_str() {
+                "otlp" => {
+                    config.otlp = g3_yaml::value::as_otlp_log_client_config(v, Some(conf_dir))
+                        .context("invalid otlp config")?;
+                    Ok(())
+                }
+                "async_channel_size" | "channel_size" => {
+                    config.async_channel_size = g3_yaml::value::as_usize(v)
+                        .context(format!("invalid usize value for key {k}"))?;
+                    Ok(())
+                }
+                "async_thread_number" | "thread_number" => {
+                    config.async_thread_number = g3_yaml::value::as_usize(v)
+                        .context(format!("invalid usize value for key {k}"))?;
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+        }
+        _ => return Err(anyhow!("invalid value type")),
+    }
+    set_global_trace_config(config);
+    Ok(())
+}
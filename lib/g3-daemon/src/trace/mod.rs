@@ -0,0 +1,62 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Arc, OnceLock};
+
+use g3_otlp::OtlpSpanExporter;
+use g3_types::log::AsyncLogConfig;
+
+use crate::log::LoggerStats;
+
+pub mod config;
+
+const SPAN_EXPORTER_NAME: &str = "trace";
+
+static GLOBAL_SPAN_EXPORTER: OnceLock<OtlpSpanExporter> = OnceLock::new();
+
+/// Start the span exporter if trace has been configured.
+///
+/// This should be called after entering daemon mode, as threads will be spawned.
+pub fn spawn_exporter(program_name: &'static str, daemon_group: &'static str) {
+    let Some(config) = config::get_global_trace_config() else {
+        return;
+    };
+
+    let async_conf = AsyncLogConfig {
+        channel_capacity: config.async_channel_size,
+        thread_number: config.async_thread_number,
+        thread_name: SPAN_EXPORTER_NAME.to_string(),
+    };
+    let daemon_group = if daemon_group.is_empty() {
+        None
+    } else {
+        Some(daemon_group.to_string())
+    };
+    let exporter = g3_otlp::new_span_exporter(
+        &async_conf,
+        &config.otlp,
+        program_name.to_string(),
+        SPAN_EXPORTER_NAME.to_string(),
+        daemon_group,
+    );
+    let stats = LoggerStats::new(SPAN_EXPORTER_NAME, exporter.get_stats());
+    crate::log::registry::add(SPAN_EXPORTER_NAME.to_string(), Arc::new(stats));
+    let _ = GLOBAL_SPAN_EXPORTER.set(exporter);
+}
+
+pub fn span_exporter() -> Option<&'static OtlpSpanExporter> {
+    GLOBAL_SPAN_EXPORTER.get()
+}
@@ -0,0 +1,72 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 100 << 20;
+const DEFAULT_MAX_FILE_COUNT: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct FileLogConfig {
+    pub(crate) dir: PathBuf,
+    pub(crate) max_file_size: u64,
+    pub(crate) rotate_interval: Option<Duration>,
+    pub(crate) max_file_count: usize,
+    pub(crate) compress: bool,
+}
+
+impl FileLogConfig {
+    pub fn with_program_name(program_name: &str) -> Self {
+        FileLogConfig::new(Path::new("/var/log").join(program_name))
+    }
+
+    pub fn new(dir: PathBuf) -> Self {
+        FileLogConfig {
+            dir,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            rotate_interval: None,
+            max_file_count: DEFAULT_MAX_FILE_COUNT,
+            compress: false,
+        }
+    }
+
+    pub fn set_dir(&mut self, dir: PathBuf) {
+        self.dir = dir;
+    }
+
+    /// set to 0 to disable size based rotation
+    pub fn set_max_file_size(&mut self, size: u64) {
+        self.max_file_size = size;
+    }
+
+    pub fn set_rotate_interval(&mut self, interval: Duration) {
+        if interval.is_zero() {
+            self.rotate_interval = None;
+        } else {
+            self.rotate_interval = Some(interval);
+        }
+    }
+
+    /// the max number of rotated files to keep
+    pub fn set_max_file_count(&mut self, count: usize) {
+        self.max_file_count = count;
+    }
+
+    pub fn set_compress(&mut self, compress: bool) {
+        self.compress = compress;
+    }
+}
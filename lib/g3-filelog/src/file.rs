@@ -0,0 +1,211 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::FileLogConfig;
+
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
+
+pub(crate) struct LogFile {
+    writer: BufWriter<File>,
+    size: u64,
+    rotate_time: Option<SystemTime>,
+}
+
+impl LogFile {
+    pub(crate) fn open(path: &Path, config: &FileLogConfig) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        let rotate_time = config
+            .rotate_interval
+            .map(|interval| next_rotate_time(SystemTime::now(), interval));
+        Ok(LogFile {
+            writer: BufWriter::with_capacity(WRITE_BUFFER_SIZE, file),
+            size,
+            rotate_time,
+        })
+    }
+
+    pub(crate) fn need_rotate(&self, config: &FileLogConfig, next_size: usize) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        if config.max_file_size > 0 && self.size + next_size as u64 > config.max_file_size {
+            return true;
+        }
+        if let Some(time) = self.rotate_time {
+            return SystemTime::now() >= time;
+        }
+        false
+    }
+
+    pub(crate) fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// the rotate time is aligned to the multiples of the interval since the unix epoch
+fn next_rotate_time(now: SystemTime, interval: Duration) -> SystemTime {
+    let interval_secs = interval.as_secs().max(1);
+    let now_secs = now
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let next_secs = (now_secs / interval_secs + 1) * interval_secs;
+    UNIX_EPOCH + Duration::from_secs(next_secs)
+}
+
+fn rotated_path(path: &Path, index: usize, compressed: bool) -> PathBuf {
+    let mut s = path.as_os_str().to_os_string();
+    s.push(format!(".{index}"));
+    if compressed {
+        s.push(".gz");
+    }
+    PathBuf::from(s)
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn compress_file(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut src_file = File::open(src)?;
+    let dst_file = File::create(dst)?;
+    let mut encoder = GzEncoder::new(dst_file, Compression::default());
+    io::copy(&mut src_file, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::remove_file(src)
+}
+
+/// Rotate the log file at `path`, the current file should have been closed.
+///
+/// `path` will be renamed to `path.1`, and `path.N` will be renamed to `path.N+1`.
+/// The ones beyond the max file count will be deleted.
+pub(crate) fn rotate(path: &Path, config: &FileLogConfig) -> io::Result<()> {
+    let max = config.max_file_count;
+    if max == 0 {
+        return remove_if_exists(path);
+    }
+
+    // files may be left with or without compression if the config changed, so handle both
+    remove_if_exists(&rotated_path(path, max, false))?;
+    remove_if_exists(&rotated_path(path, max, true))?;
+    for i in (1..max).rev() {
+        rename_if_exists(
+            &rotated_path(path, i, false),
+            &rotated_path(path, i + 1, false),
+        )?;
+        rename_if_exists(
+            &rotated_path(path, i, true),
+            &rotated_path(path, i + 1, true),
+        )?;
+    }
+
+    let rotated = rotated_path(path, 1, false);
+    rename_if_exists(path, &rotated)?;
+    if config.compress {
+        compress_file(&rotated, &rotated_path(path, 1, true))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("g3-filelog-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn rotate_time() {
+        let now = UNIX_EPOCH + Duration::from_secs(3600 * 5 + 10);
+        let next = next_rotate_time(now, Duration::from_secs(3600));
+        assert_eq!(next, UNIX_EPOCH + Duration::from_secs(3600 * 6));
+    }
+
+    #[test]
+    fn rotate_count() {
+        let dir = test_dir("count");
+        let path = dir.join("test.log");
+        let mut config = FileLogConfig::new(dir.clone());
+        config.set_max_file_count(2);
+
+        for i in 0..3 {
+            fs::write(&path, format!("{i}")).unwrap();
+            rotate(&path, &config).unwrap();
+        }
+
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(dir.join("test.log.1")).unwrap(), "2");
+        assert_eq!(fs::read_to_string(dir.join("test.log.2")).unwrap(), "1");
+        assert!(!dir.join("test.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_compress() {
+        let dir = test_dir("compress");
+        let path = dir.join("test.log");
+        let mut config = FileLogConfig::new(dir.clone());
+        config.set_compress(true);
+
+        let mut log_file = LogFile::open(&path, &config).unwrap();
+        log_file.write_all(b"{\"msg\":\"test\"}\n").unwrap();
+        log_file.flush().unwrap();
+        assert!(log_file.need_rotate(&config, config.max_file_size as usize));
+        drop(log_file);
+
+        rotate(&path, &config).unwrap();
+        assert!(!path.exists());
+        assert!(!dir.join("test.log.1").exists());
+        assert!(dir.join("test.log.1.gz").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
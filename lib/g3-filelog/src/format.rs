@@ -0,0 +1,178 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+use std::fmt::{Arguments, Write};
+use std::io;
+use std::sync::Arc;
+
+use chrono::{SecondsFormat, Utc};
+use serde::ser::SerializeMap;
+use slog::{OwnedKVList, Record, Serializer, KV};
+
+use g3_types::log::{AsyncLogFormatter, LogStats};
+
+use super::FileLogRecord;
+
+thread_local! {
+    static TL_BUF: RefCell<String> = RefCell::new(String::with_capacity(128))
+}
+
+/// Format each record as a single line of json object
+pub struct FileLogFormatter {
+    stats: Arc<LogStats>,
+}
+
+impl FileLogFormatter {
+    pub(crate) fn new(stats: Arc<LogStats>) -> Self {
+        FileLogFormatter { stats }
+    }
+}
+
+impl AsyncLogFormatter<FileLogRecord> for FileLogFormatter {
+    fn format_slog(
+        &self,
+        record: &Record,
+        logger_values: &OwnedKVList,
+    ) -> Result<FileLogRecord, slog::Error> {
+        let datetime_now = Utc::now();
+        let mut buf = Vec::<u8>::with_capacity(1024);
+
+        let mut serde = serde_json::Serializer::new(&mut buf);
+        let mut kv_formatter = SerdeFormatterKV::start(&mut serde)?;
+        kv_formatter.emit_str(
+            "time",
+            &datetime_now.to_rfc3339_opts(SecondsFormat::Micros, true),
+        )?;
+        kv_formatter.emit_str("level", record.level().as_str())?;
+        logger_values.serialize(record, &mut kv_formatter)?;
+        record.kv().serialize(record, &mut kv_formatter)?;
+        kv_formatter.emit_arguments("msg", record.msg())?;
+        kv_formatter.end().map_err(io::Error::other)?;
+
+        buf.push(b'\n');
+        Ok(FileLogRecord {
+            data: buf,
+            stats: self.stats.clone(),
+        })
+    }
+}
+
+struct SerdeFormatterKV<S: serde::Serializer> {
+    ser_map: S::SerializeMap,
+}
+
+impl<S: serde::Serializer> SerdeFormatterKV<S> {
+    fn start(ser: S) -> Result<Self, slog::Error> {
+        let ser_map = ser
+            .serialize_map(None)
+            .map_err(|e| io::Error::other(format!("serde serialization error: {e}")))?;
+        Ok(SerdeFormatterKV { ser_map })
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.ser_map.end()
+    }
+}
+
+macro_rules! impl_m(
+    ($s:expr, $key:expr, $val:expr) => ({
+        let k_s:  &str = $key.as_ref();
+        $s.ser_map.serialize_entry(k_s, $val)
+             .map_err(|e| io::Error::other(format!("serde serialization error: {e}")))?;
+        Ok(())
+    });
+);
+
+impl<S: serde::Serializer> Serializer for SerdeFormatterKV<S> {
+    fn emit_bool(&mut self, key: slog::Key, value: bool) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+
+    fn emit_unit(&mut self, key: slog::Key) -> slog::Result {
+        impl_m!(self, key, &())
+    }
+
+    fn emit_char(&mut self, key: slog::Key, value: char) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+
+    fn emit_none(&mut self, _key: slog::Key) -> slog::Result {
+        Ok(())
+    }
+    fn emit_u8(&mut self, key: slog::Key, value: u8) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_i8(&mut self, key: slog::Key, value: i8) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_u16(&mut self, key: slog::Key, value: u16) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_i16(&mut self, key: slog::Key, value: i16) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_usize(&mut self, key: slog::Key, value: usize) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_isize(&mut self, key: slog::Key, value: isize) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_u32(&mut self, key: slog::Key, value: u32) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_i32(&mut self, key: slog::Key, value: i32) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_f32(&mut self, key: slog::Key, value: f32) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_u64(&mut self, key: slog::Key, value: u64) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_i64(&mut self, key: slog::Key, value: i64) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_f64(&mut self, key: slog::Key, value: f64) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+    fn emit_str(&mut self, key: slog::Key, value: &str) -> slog::Result {
+        impl_m!(self, key, &value)
+    }
+
+    fn emit_arguments(&mut self, key: slog::Key, value: &Arguments) -> slog::Result {
+        if let Some(s) = value.as_str() {
+            self.emit_str(key, s)
+        } else {
+            TL_BUF.with_borrow_mut(|buf| {
+                buf.clear();
+
+                buf.write_fmt(*value).unwrap();
+
+                self.emit_str(key, buf.as_str())
+            })
+        }
+    }
+
+    fn emit_serde(&mut self, key: slog::Key, value: &dyn slog::SerdeValue) -> slog::Result {
+        self.ser_map
+            .serialize_entry(key, value.as_serde())
+            .map_err(|e| {
+                io::Error::other(format!("serde serialization error for key {key}: {e}"))
+            })?;
+        Ok(())
+    }
+}
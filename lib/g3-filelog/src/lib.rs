@@ -0,0 +1,262 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use flume::{Receiver, Sender, WeakSender};
+use log::{debug, warn};
+
+use g3_types::log::{AsyncLogConfig, AsyncLogger, LogStats};
+
+mod config;
+pub use config::FileLogConfig;
+
+mod format;
+pub use format::FileLogFormatter;
+
+mod file;
+use file::LogFile;
+
+static REOPEN_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+/// The io thread senders of all opened log files, keyed by the file path
+static FILE_WRITERS: Mutex<Option<HashMap<PathBuf, WeakSender<FileLogRecord>>>> = Mutex::new(None);
+
+/// A formatted log record, with the stats of the logger that generated it
+pub struct FileLogRecord {
+    data: Vec<u8>,
+    stats: Arc<LogStats>,
+}
+
+/// Notify all file loggers to reopen their log files.
+///
+/// This should be called when the log files may have been moved away by external tools.
+pub fn reopen_all() {
+    REOPEN_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Create a new file logger.
+///
+/// Loggers that write to the same file path will share the same io thread, which is the one
+/// created by the first logger, so the config of the later ones will be ignored.
+pub fn new_async_logger(
+    async_conf: &AsyncLogConfig,
+    file_conf: &Arc<FileLogConfig>,
+    file_name: &str,
+) -> AsyncLogger<FileLogRecord, FileLogFormatter> {
+    let stats = Arc::new(LogStats::default());
+    let path = file_conf.dir.join(file_name);
+    let sender = get_file_writer(async_conf, file_conf, path);
+    AsyncLogger::new(sender, FileLogFormatter::new(stats.clone()), stats)
+}
+
+fn get_file_writer(
+    async_conf: &AsyncLogConfig,
+    file_conf: &Arc<FileLogConfig>,
+    path: PathBuf,
+) -> Sender<FileLogRecord> {
+    let mut writers = FILE_WRITERS.lock().unwrap();
+    let writers = writers.get_or_insert_with(HashMap::new);
+    if let Some(sender) = writers.get(&path).and_then(|s| s.upgrade()) {
+        debug!("reuse the io thread for log file {}", path.display());
+        return sender;
+    }
+    // the io thread exits after all senders are dropped
+    writers.retain(|_, s| s.upgrade().is_some());
+
+    let (sender, receiver) = flume::bounded::<FileLogRecord>(async_conf.channel_capacity);
+    writers.insert(path.clone(), sender.downgrade());
+
+    // only one io thread is allowed for each file, as the rotation should not be done concurrently
+    let io_thread = AsyncIoThread {
+        config: Arc::clone(file_conf),
+        path,
+        receiver,
+        file: None,
+        reopen_generation: REOPEN_GENERATION.load(Ordering::Relaxed),
+        open_failed_instant: None,
+    };
+
+    let _detached_thread = std::thread::Builder::new()
+        .name(format!("{}#0", async_conf.thread_name))
+        .spawn(move || {
+            io_thread.run_to_end();
+        });
+
+    sender
+}
+
+struct AsyncIoThread {
+    config: Arc<FileLogConfig>,
+    path: PathBuf,
+    receiver: Receiver<FileLogRecord>,
+    file: Option<LogFile>,
+    reopen_generation: usize,
+    open_failed_instant: Option<Instant>,
+}
+
+impl AsyncIoThread {
+    fn run_to_end(mut self) {
+        while let Ok(record) = self.receiver.recv() {
+            self.write_data(record);
+            if self.receiver.is_empty() {
+                self.flush();
+            }
+        }
+        self.flush();
+    }
+
+    fn write_data(&mut self, record: FileLogRecord) {
+        let FileLogRecord { data, stats } = record;
+
+        let reopen_generation = REOPEN_GENERATION.load(Ordering::Relaxed);
+        if reopen_generation != self.reopen_generation {
+            self.reopen_generation = reopen_generation;
+            self.close();
+        }
+
+        if let Some(file) = &self.file {
+            if file.need_rotate(&self.config, data.len()) {
+                self.close();
+                if let Err(e) = file::rotate(&self.path, &self.config) {
+                    warn!("failed to rotate log file {}: {e}", self.path.display());
+                }
+            }
+        }
+
+        let Some(file) = self.get_file() else {
+            stats.drop.add_peer_unreachable();
+            return;
+        };
+        match file.write_all(&data) {
+            Ok(_) => {
+                stats.io.add_passed();
+                stats.io.add_size(data.len());
+            }
+            Err(e) => {
+                warn!("failed to write to log file {}: {e}", self.path.display());
+                stats.drop.add_peer_unreachable();
+                self.file = None;
+            }
+        }
+    }
+
+    fn get_file(&mut self) -> Option<&mut LogFile> {
+        if self.file.is_none() {
+            if let Some(instant) = self.open_failed_instant {
+                if instant.elapsed() < Duration::from_secs(4) {
+                    // hard coded 4s for a minimal reopen interval
+                    return None;
+                }
+            }
+            match LogFile::open(&self.path, &self.config) {
+                Ok(file) => {
+                    self.open_failed_instant = None;
+                    self.file = Some(file);
+                }
+                Err(e) => {
+                    warn!("failed to open log file {}: {e}", self.path.display());
+                    self.open_failed_instant = Some(Instant::now());
+                    return None;
+                }
+            }
+        }
+        self.file.as_mut()
+    }
+
+    fn flush(&mut self) {
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.flush() {
+                warn!("failed to flush log file {}: {e}", self.path.display());
+                self.file = None;
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        self.flush();
+        self.file = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use slog::{info, slog_o, Logger};
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("g3-filelog-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn shared_writer() {
+        let dir = test_dir("shared-writer");
+        let file_conf = Arc::new(FileLogConfig::new(dir.clone()));
+        let async_conf = AsyncLogConfig::with_name("test-shared-writer");
+
+        let s1 = get_file_writer(&async_conf, &file_conf, dir.join("a.log"));
+        let s2 = get_file_writer(&async_conf, &file_conf, dir.join("a.log"));
+        let s3 = get_file_writer(&async_conf, &file_conf, dir.join("b.log"));
+        assert!(s1.same_channel(&s2));
+        assert!(!s1.same_channel(&s3));
+
+        drop(s1);
+        drop(s2);
+        let s4 = get_file_writer(&async_conf, &file_conf, dir.join("a.log"));
+        assert!(!s4.same_channel(&s3));
+    }
+
+    #[test]
+    fn shared_file() {
+        let dir = test_dir("shared-file");
+        let file_conf = Arc::new(FileLogConfig::new(dir.clone()));
+        let async_conf = AsyncLogConfig::with_name("test-shared-file");
+
+        let drain1 = new_async_logger(&async_conf, &file_conf, "test.log");
+        let stats1 = drain1.get_stats();
+        let drain2 = new_async_logger(&async_conf, &file_conf, "test.log");
+        let stats2 = drain2.get_stats();
+        let logger1 = Logger::root(slog::Fuse(drain1), slog_o!());
+        let logger2 = Logger::root(slog::Fuse(drain2), slog_o!());
+        info!(logger1, "from logger 1");
+        info!(logger2, "from logger 2");
+        drop(logger1);
+        drop(logger2);
+
+        let path = dir.join("test.log");
+        let mut lines = 0;
+        for _ in 0..100 {
+            std::thread::sleep(Duration::from_millis(10));
+            if let Ok(s) = std::fs::read_to_string(&path) {
+                lines = s.lines().count();
+                if lines == 2 {
+                    break;
+                }
+            }
+        }
+        assert_eq!(lines, 2);
+        assert_eq!(stats1.io.snapshot().passed, 1);
+        assert_eq!(stats2.io.snapshot().passed, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -47,7 +47,7 @@ impl AsyncRead for H2StreamReader {
     ) -> Poll<io::Result<()>> {
         loop {
             if let Some(mut b) = self.received_bytes.take() {
-                let to_write = buf.remaining().max(b.len());
+                let to_write = buf.remaining().min(b.len());
                 return match self.recv_flow_control.release_capacity(to_write) {
                     Ok(_) => {
                         let split = b.split_to(to_write);
@@ -87,3 +87,44 @@ impl AsyncRead for H2StreamReader {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Request, Response};
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn read_into_small_buf() {
+        let (client_io, server_io) = tokio::io::duplex(4096);
+
+        tokio::spawn(async move {
+            let mut conn = h2::server::handshake(server_io).await.unwrap();
+            let (_req, mut respond) = conn.accept().await.unwrap().unwrap();
+            let mut send_stream = respond.send_response(Response::new(()), false).unwrap();
+            send_stream
+                .send_data(Bytes::from(vec![b'x'; 100]), true)
+                .unwrap();
+            while conn.accept().await.is_some() {}
+        });
+
+        let (mut client, conn) = h2::client::handshake(client_io).await.unwrap();
+        tokio::spawn(conn);
+        let req = Request::get("http://example.net/").body(()).unwrap();
+        let (rsp_fut, _) = client.send_request(req, true).unwrap();
+        let rsp = rsp_fut.await.unwrap();
+
+        let mut reader = H2StreamReader::new(rsp.into_body());
+        let mut buf = [0u8; 16];
+        let mut received = Vec::new();
+        loop {
+            let len = reader.read(&mut buf).await.unwrap();
+            if len == 0 {
+                break;
+            }
+            assert!(len <= buf.len());
+            received.extend_from_slice(&buf[..len]);
+        }
+        assert_eq!(received, vec![b'x'; 100]);
+    }
+}
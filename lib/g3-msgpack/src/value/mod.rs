@@ -22,6 +22,9 @@ mod uuid;
 #[cfg(feature = "rustls")]
 mod rustls;
 
+#[cfg(feature = "openssl")]
+mod openssl;
+
 pub use self::uuid::as_uuid;
 pub use datetime::as_rfc3339_datetime;
 pub use metrics::{as_metrics_name, as_weighted_metrics_name};
@@ -29,3 +32,6 @@ pub use primary::{as_f64, as_string, as_u32, as_weighted_name_string};
 
 #[cfg(feature = "rustls")]
 pub use self::rustls::{as_certificates, as_private_key};
+
+#[cfg(feature = "openssl")]
+pub use self::openssl::as_openssl_certificate;
@@ -0,0 +1,32 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::anyhow;
+use openssl::x509::X509;
+use rmpv::ValueRef;
+
+pub fn as_openssl_certificate(value: &ValueRef) -> anyhow::Result<X509> {
+    match value {
+        ValueRef::String(s) => X509::from_pem(s.as_bytes())
+            .map_err(|e| anyhow!("invalid pem encoded certificate: {e}")),
+        ValueRef::Binary(b) => {
+            X509::from_der(b).map_err(|e| anyhow!("invalid der encoded certificate: {e}"))
+        }
+        _ => Err(anyhow!(
+            "msgpack value type for 'openssl certificate' should be 'string' or 'binary'"
+        )),
+    }
+}
@@ -0,0 +1,145 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::OnceLock;
+use std::{ptr, slice};
+
+use anyhow::anyhow;
+use libc::{c_int, c_long, c_uchar, c_uint};
+use openssl::ec::EcKey;
+use openssl::error::ErrorStack;
+use openssl::foreign_types::ForeignType;
+use openssl::pkey::{PKey, PKeyRef, Private, Public};
+use openssl_sys::{BIGNUM, ECDSA_SIG, EC_KEY};
+
+use super::{op, ArcAsyncKeyDriver};
+use crate::ffi;
+
+struct AsyncEcMethod {
+    meth: *mut ffi::EC_KEY_METHOD,
+    ex_index: c_int,
+}
+
+unsafe impl Send for AsyncEcMethod {}
+unsafe impl Sync for AsyncEcMethod {}
+
+static ASYNC_EC_METHOD: OnceLock<Option<AsyncEcMethod>> = OnceLock::new();
+
+fn async_ec_method() -> Option<&'static AsyncEcMethod> {
+    ASYNC_EC_METHOD
+        .get_or_init(|| {
+            let ex_index = super::new_driver_ex_index(ffi::CRYPTO_EX_INDEX_EC_KEY)?;
+            let meth = unsafe { ffi::EC_KEY_METHOD_new(ffi::EC_KEY_OpenSSL()) };
+            if meth.is_null() {
+                return None;
+            }
+            unsafe {
+                let mut sign_setup: ffi::EC_KEY_sign_setup_fn = None;
+                ffi::EC_KEY_METHOD_get_sign(
+                    meth,
+                    ptr::null_mut(),
+                    &mut sign_setup,
+                    ptr::null_mut(),
+                );
+                ffi::EC_KEY_METHOD_set_sign(meth, Some(ec_sign), sign_setup, Some(ec_sign_sig));
+            }
+            Some(AsyncEcMethod { meth, ex_index })
+        })
+        .as_ref()
+}
+
+pub(super) fn new_private_key(
+    public_key: &PKeyRef<Public>,
+    driver: ArcAsyncKeyDriver,
+) -> anyhow::Result<PKey<Private>> {
+    let method = async_ec_method().ok_or_else(|| anyhow!("failed to create async ec method"))?;
+
+    let ec_key = public_key
+        .ec_key()
+        .map_err(|e| anyhow!("failed to get ec public key: {e}"))?;
+    let ec_key = EcKey::from_public_key(ec_key.group(), ec_key.public_key())
+        .map_err(|e| anyhow!("failed to create ec key: {e}"))?;
+
+    unsafe {
+        if ffi::EC_KEY_set_method(ec_key.as_ptr(), method.meth) != 1 {
+            return Err(anyhow!(
+                "failed to set ec key method: {}",
+                ErrorStack::get()
+            ));
+        }
+        let data = super::driver_into_ex_data(driver);
+        if ffi::EC_KEY_set_ex_data(ec_key.as_ptr(), method.ex_index, data) != 1 {
+            super::drop_ex_data(data);
+            return Err(super::set_ex_data_error(ErrorStack::get()));
+        }
+
+        let ec_key = EcKey::<Private>::from_ptr(ec_key.into_ptr());
+        PKey::from_ec_key(ec_key).map_err(|e| anyhow!("failed to create private key: {e}"))
+    }
+}
+
+unsafe fn get_ec_driver<'a>(eckey: *mut EC_KEY) -> Option<&'a ArcAsyncKeyDriver> {
+    let method = ASYNC_EC_METHOD.get()?.as_ref()?;
+    let data = ffi::EC_KEY_get_ex_data(eckey, method.ex_index);
+    super::driver_from_ex_data(data)
+}
+
+unsafe fn offload_sign(dgst: *const c_uchar, dlen: c_int, eckey: *mut EC_KEY) -> Option<Vec<u8>> {
+    let driver = get_ec_driver(eckey)?;
+    if dgst.is_null() || dlen <= 0 {
+        return None;
+    }
+    let digest = slice::from_raw_parts(dgst, dlen as usize).to_vec();
+    op::run_in_async_job(|notifier| driver.ecdsa_sign(digest, notifier))
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn ec_sign(
+    _type: c_int,
+    dgst: *const c_uchar,
+    dlen: c_int,
+    sig: *mut c_uchar,
+    siglen: *mut c_uint,
+    _kinv: *const BIGNUM,
+    _r: *const BIGNUM,
+    eckey: *mut EC_KEY,
+) -> c_int {
+    *siglen = 0;
+    let Some(data) = offload_sign(dgst, dlen, eckey) else {
+        return 0;
+    };
+    let max_size = ffi::ECDSA_size(eckey);
+    if max_size <= 0 || data.len() > max_size as usize {
+        return 0;
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), sig, data.len());
+    *siglen = data.len() as c_uint;
+    1
+}
+
+unsafe extern "C" fn ec_sign_sig(
+    dgst: *const c_uchar,
+    dgst_len: c_int,
+    _in_kinv: *const BIGNUM,
+    _in_r: *const BIGNUM,
+    eckey: *mut EC_KEY,
+) -> *mut ECDSA_SIG {
+    let Some(data) = offload_sign(dgst, dgst_len, eckey) else {
+        return ptr::null_mut();
+    };
+    let mut p = data.as_ptr();
+    ffi::d2i_ECDSA_SIG(ptr::null_mut(), &mut p, data.len() as c_long)
+}
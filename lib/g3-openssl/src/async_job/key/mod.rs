@@ -0,0 +1,139 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#[cfg(ossl300)]
+use std::ptr;
+use std::sync::Arc;
+
+use anyhow::anyhow;
+#[cfg(ossl300)]
+use libc::{c_int, c_long, c_void};
+#[cfg(ossl300)]
+use openssl::error::ErrorStack;
+#[cfg(ossl300)]
+use openssl::pkey::Id;
+use openssl::pkey::{PKey, PKeyRef, Private, Public};
+
+#[cfg(ossl300)]
+use crate::ffi;
+
+mod op;
+pub use op::AsyncKeyNotifier;
+
+#[cfg(ossl300)]
+mod ec;
+#[cfg(ossl300)]
+mod rsa;
+
+/// The driver for private key operations that are offloaded out of the current process.
+///
+/// The methods will be called inside an openssl async job, and they should not block.
+/// The result should be sent back through the notifier.
+pub trait AsyncKeyDriver: Send + Sync {
+    /// do the raw RSA private key operation, the input data is of the key size
+    fn rsa_private(&self, data: Vec<u8>, notifier: AsyncKeyNotifier);
+    /// do ECDSA sign on the digest, the output should be DER encoded
+    fn ecdsa_sign(&self, digest: Vec<u8>, notifier: AsyncKeyNotifier);
+}
+
+pub type ArcAsyncKeyDriver = Arc<dyn AsyncKeyDriver>;
+
+/// Create a private key which has only the public part locally,
+/// and all private key operations will be done by the driver.
+///
+/// The returned key can only be used in openssl async jobs with wait ctx callback set,
+/// such as SSL objects with ASYNC mode enabled.
+#[cfg(ossl300)]
+pub fn new_async_private_key(
+    public_key: &PKeyRef<Public>,
+    driver: ArcAsyncKeyDriver,
+) -> anyhow::Result<PKey<Private>> {
+    match public_key.id() {
+        Id::RSA => rsa::new_private_key(public_key, driver),
+        Id::EC => ec::new_private_key(public_key, driver),
+        id => Err(anyhow!(
+            "unsupported public key type {} for async private key",
+            id.as_raw()
+        )),
+    }
+}
+
+#[cfg(not(ossl300))]
+pub fn new_async_private_key(
+    _public_key: &PKeyRef<Public>,
+    _driver: ArcAsyncKeyDriver,
+) -> anyhow::Result<PKey<Private>> {
+    Err(anyhow!("async private key requires OpenSSL 3.0 or later"))
+}
+
+#[cfg(ossl300)]
+unsafe extern "C" fn free_driver(
+    _parent: *mut c_void,
+    ptr: *mut c_void,
+    _ad: *mut ffi::CRYPTO_EX_DATA,
+    _idx: c_int,
+    _argl: c_long,
+    _argp: *mut c_void,
+) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(ptr as *mut ArcAsyncKeyDriver));
+    }
+}
+
+#[cfg(ossl300)]
+fn new_driver_ex_index(class_index: c_int) -> Option<c_int> {
+    let idx = unsafe {
+        ffi::CRYPTO_get_ex_new_index(
+            class_index,
+            0,
+            ptr::null_mut(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+            Some(free_driver),
+        )
+    };
+    if idx < 0 {
+        None
+    } else {
+        Some(idx)
+    }
+}
+
+#[cfg(ossl300)]
+fn driver_into_ex_data(driver: ArcAsyncKeyDriver) -> *mut c_void {
+    Box::into_raw(Box::new(driver)) as *mut c_void
+}
+
+#[cfg(ossl300)]
+/// # Safety
+///
+/// the data should be set by `driver_into_ex_data`
+unsafe fn driver_from_ex_data<'a>(data: *mut c_void) -> Option<&'a ArcAsyncKeyDriver> {
+    (data as *const ArcAsyncKeyDriver).as_ref()
+}
+
+#[cfg(ossl300)]
+/// # Safety
+///
+/// the data should be set by `driver_into_ex_data` and not yet attached
+unsafe fn drop_ex_data(data: *mut c_void) {
+    drop(Box::from_raw(data as *mut ArcAsyncKeyDriver));
+}
+
+#[cfg(ossl300)]
+fn set_ex_data_error(e: ErrorStack) -> anyhow::Error {
+    anyhow!("failed to attach key driver: {e}")
+}
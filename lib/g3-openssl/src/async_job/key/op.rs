@@ -0,0 +1,161 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#[cfg(ossl300)]
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+#[cfg(ossl300)]
+use libc::c_int;
+use libc::c_void;
+
+use crate::ffi;
+
+#[cfg_attr(not(ossl300), allow(dead_code))]
+struct OpState {
+    finished: bool,
+    result: Option<Option<Vec<u8>>>,
+    callback: ffi::ASYNC_callback_fn,
+    callback_arg: *mut c_void,
+}
+
+/// the raw callback arg will only be used while the wait ctx is still alive
+unsafe impl Send for OpState {}
+
+impl OpState {
+    fn finish(&mut self, result: Option<Vec<u8>>) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.result = Some(result);
+        if let Some(callback) = self.callback.take() {
+            unsafe { callback(self.callback_arg) };
+        }
+    }
+
+    #[cfg(ossl300)]
+    fn cancel(&mut self) {
+        self.callback = None;
+        self.callback_arg = ptr::null_mut();
+    }
+}
+
+/// Used by the key driver to return the result of an offloaded key operation.
+///
+/// The paused openssl async job will be resumed after `notify` is called,
+/// or after this notifier is dropped, which will be considered as a failure.
+pub struct AsyncKeyNotifier {
+    state: Arc<Mutex<OpState>>,
+}
+
+impl AsyncKeyNotifier {
+    pub fn notify(self, result: Option<Vec<u8>>) {
+        let mut state = self.state.lock().unwrap();
+        state.finish(result);
+    }
+}
+
+impl Drop for AsyncKeyNotifier {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.finish(None);
+    }
+}
+
+#[cfg(ossl300)]
+unsafe extern "C" fn cancel_op(
+    _ctx: *mut ffi::ASYNC_WAIT_CTX,
+    _key: *const c_void,
+    _fd: c_int,
+    custom_data: *mut c_void,
+) {
+    let state = Arc::from_raw(custom_data as *const Mutex<OpState>);
+    let mut guard = state.lock().unwrap();
+    guard.cancel();
+}
+
+/// Run the key operation inside the current openssl async job.
+///
+/// The job will be paused until the notifier is triggered. The wait ctx
+/// callback, which should be set by the caller of the async job, will be
+/// used to resume the job, so no wait fd is needed.
+#[cfg(ossl300)]
+pub(super) fn run_in_async_job<F>(submit: F) -> Option<Vec<u8>>
+where
+    F: FnOnce(AsyncKeyNotifier),
+{
+    let job = unsafe { ffi::ASYNC_get_current_job() };
+    if job.is_null() {
+        return None;
+    }
+    let ctx = unsafe { ffi::ASYNC_get_wait_ctx(job) };
+    if ctx.is_null() {
+        return None;
+    }
+
+    let mut callback: ffi::ASYNC_callback_fn = None;
+    let mut callback_arg: *mut c_void = ptr::null_mut();
+    let r = unsafe { ffi::ASYNC_WAIT_CTX_get_callback(ctx, &mut callback, &mut callback_arg) };
+    if r != 1 || callback.is_none() {
+        return None;
+    }
+
+    let state = Arc::new(Mutex::new(OpState {
+        finished: false,
+        result: None,
+        callback,
+        callback_arg,
+    }));
+
+    // register a cleanup hook so the callback won't be called after the wait ctx is freed
+    let cancel_data = Arc::into_raw(state.clone()) as *mut c_void;
+    let key = cancel_data as *const c_void;
+    let r = unsafe { ffi::ASYNC_WAIT_CTX_set_wait_fd(ctx, key, -1, cancel_data, Some(cancel_op)) };
+    if r != 1 {
+        drop(unsafe { Arc::from_raw(cancel_data as *const Mutex<OpState>) });
+        return None;
+    }
+    unsafe { ffi::ASYNC_WAIT_CTX_set_status(ctx, ffi::ASYNC_STATUS_OK) };
+
+    submit(AsyncKeyNotifier {
+        state: state.clone(),
+    });
+
+    let result = loop {
+        let mut guard = state.lock().unwrap();
+        if let Some(r) = guard.result.take() {
+            break r;
+        }
+        drop(guard);
+
+        if unsafe { ffi::ASYNC_pause_job() } != 1 {
+            break None;
+        }
+    };
+
+    unsafe { ffi::ASYNC_WAIT_CTX_set_status(ctx, ffi::ASYNC_STATUS_UNSUPPORTED) };
+    state.lock().unwrap().cancel();
+
+    let mut fd: c_int = 0;
+    let mut custom_data: *mut c_void = ptr::null_mut();
+    if unsafe { ffi::ASYNC_WAIT_CTX_get_fd(ctx, key, &mut fd, &mut custom_data) } == 1 {
+        unsafe { ffi::ASYNC_WAIT_CTX_clear_fd(ctx, key) };
+        drop(unsafe { Arc::from_raw(custom_data as *const Mutex<OpState>) });
+    }
+
+    result
+}
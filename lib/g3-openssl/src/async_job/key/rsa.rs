@@ -0,0 +1,199 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::ptr;
+use std::sync::OnceLock;
+
+use anyhow::anyhow;
+use libc::{c_char, c_int, c_uchar};
+use openssl::error::ErrorStack;
+use openssl::foreign_types::ForeignType;
+use openssl::pkey::{PKey, PKeyRef, Private, Public};
+use openssl::rsa::Rsa;
+use openssl_sys::RSA;
+
+use super::{op, ArcAsyncKeyDriver};
+use crate::ffi;
+
+struct AsyncRsaMethod {
+    meth: *mut ffi::RSA_METHOD,
+    ex_index: c_int,
+}
+
+unsafe impl Send for AsyncRsaMethod {}
+unsafe impl Sync for AsyncRsaMethod {}
+
+static ASYNC_RSA_METHOD: OnceLock<Option<AsyncRsaMethod>> = OnceLock::new();
+
+fn async_rsa_method() -> Option<&'static AsyncRsaMethod> {
+    ASYNC_RSA_METHOD
+        .get_or_init(|| {
+            let ex_index = super::new_driver_ex_index(ffi::CRYPTO_EX_INDEX_RSA)?;
+            let meth = unsafe { ffi::RSA_meth_dup(ffi::RSA_PKCS1_OpenSSL()) };
+            if meth.is_null() {
+                return None;
+            }
+            unsafe {
+                ffi::RSA_meth_set1_name(meth, b"g3 async rsa method\0".as_ptr() as *const c_char);
+                ffi::RSA_meth_set_priv_enc(meth, Some(rsa_priv_enc));
+                ffi::RSA_meth_set_priv_dec(meth, Some(rsa_priv_dec));
+            }
+            Some(AsyncRsaMethod { meth, ex_index })
+        })
+        .as_ref()
+}
+
+pub(super) fn new_private_key(
+    public_key: &PKeyRef<Public>,
+    driver: ArcAsyncKeyDriver,
+) -> anyhow::Result<PKey<Private>> {
+    let method = async_rsa_method().ok_or_else(|| anyhow!("failed to create async rsa method"))?;
+
+    let rsa = public_key
+        .rsa()
+        .map_err(|e| anyhow!("failed to get rsa public key: {e}"))?;
+    let n = rsa
+        .n()
+        .to_owned()
+        .map_err(|e| anyhow!("failed to copy rsa modulus: {e}"))?;
+    let e = rsa
+        .e()
+        .to_owned()
+        .map_err(|e| anyhow!("failed to copy rsa public exponent: {e}"))?;
+    let rsa =
+        Rsa::from_public_components(n, e).map_err(|e| anyhow!("failed to create rsa key: {e}"))?;
+
+    unsafe {
+        if ffi::RSA_set_method(rsa.as_ptr(), method.meth) != 1 {
+            return Err(anyhow!("failed to set rsa method: {}", ErrorStack::get()));
+        }
+        let data = super::driver_into_ex_data(driver);
+        if ffi::RSA_set_ex_data(rsa.as_ptr(), method.ex_index, data) != 1 {
+            super::drop_ex_data(data);
+            return Err(super::set_ex_data_error(ErrorStack::get()));
+        }
+
+        let rsa = Rsa::<Private>::from_ptr(rsa.into_ptr());
+        PKey::from_rsa(rsa).map_err(|e| anyhow!("failed to create private key: {e}"))
+    }
+}
+
+unsafe fn get_rsa_driver<'a>(rsa: *mut RSA) -> Option<&'a ArcAsyncKeyDriver> {
+    let method = ASYNC_RSA_METHOD.get()?.as_ref()?;
+    let data = ffi::RSA_get_ex_data(rsa, method.ex_index);
+    super::driver_from_ex_data(data)
+}
+
+unsafe extern "C" fn rsa_priv_enc(
+    flen: c_int,
+    from: *const c_uchar,
+    to: *mut c_uchar,
+    rsa: *mut RSA,
+    padding: c_int,
+) -> c_int {
+    let Some(driver) = get_rsa_driver(rsa) else {
+        return -1;
+    };
+    let rsa_size = ffi::RSA_size(rsa);
+    if rsa_size <= 0 || flen < 0 {
+        return -1;
+    }
+    let key_size = rsa_size as usize;
+
+    let mut buf = vec![0u8; key_size];
+    match padding {
+        openssl_sys::RSA_PKCS1_PADDING => {
+            if ffi::RSA_padding_add_PKCS1_type_1(buf.as_mut_ptr(), rsa_size, from, flen) != 1 {
+                return -1;
+            }
+        }
+        openssl_sys::RSA_NO_PADDING => {
+            if flen != rsa_size {
+                return -1;
+            }
+            ptr::copy_nonoverlapping(from, buf.as_mut_ptr(), key_size);
+        }
+        _ => return -1,
+    }
+
+    let Some(data) = op::run_in_async_job(|notifier| driver.rsa_private(buf, notifier)) else {
+        return -1;
+    };
+    let Some(em) = left_pad(data, key_size) else {
+        return -1;
+    };
+    ptr::copy_nonoverlapping(em.as_ptr(), to, key_size);
+    rsa_size
+}
+
+unsafe extern "C" fn rsa_priv_dec(
+    flen: c_int,
+    from: *const c_uchar,
+    to: *mut c_uchar,
+    rsa: *mut RSA,
+    padding: c_int,
+) -> c_int {
+    let Some(driver) = get_rsa_driver(rsa) else {
+        return -1;
+    };
+    let rsa_size = ffi::RSA_size(rsa);
+    if rsa_size <= 0 || flen < 0 || flen > rsa_size {
+        return -1;
+    }
+    let key_size = rsa_size as usize;
+
+    let mut buf = vec![0u8; key_size];
+    let offset = key_size - flen as usize;
+    ptr::copy_nonoverlapping(from, buf.as_mut_ptr().add(offset), flen as usize);
+
+    let Some(data) = op::run_in_async_job(|notifier| driver.rsa_private(buf, notifier)) else {
+        return -1;
+    };
+    let Some(em) = left_pad(data, key_size) else {
+        return -1;
+    };
+    match padding {
+        openssl_sys::RSA_NO_PADDING => {
+            ptr::copy_nonoverlapping(em.as_ptr(), to, key_size);
+            rsa_size
+        }
+        openssl_sys::RSA_PKCS1_PADDING => {
+            ffi::RSA_padding_check_PKCS1_type_2(to, rsa_size, em.as_ptr(), rsa_size, rsa_size)
+        }
+        openssl_sys::RSA_PKCS1_OAEP_PADDING => ffi::RSA_padding_check_PKCS1_OAEP(
+            to,
+            rsa_size,
+            em.as_ptr(),
+            rsa_size,
+            rsa_size,
+            ptr::null(),
+            0,
+        ),
+        _ => -1,
+    }
+}
+
+fn left_pad(data: Vec<u8>, size: usize) -> Option<Vec<u8>> {
+    match data.len().cmp(&size) {
+        std::cmp::Ordering::Equal => Some(data),
+        std::cmp::Ordering::Less => {
+            let mut buf = vec![0u8; size];
+            buf[size - data.len()..].copy_from_slice(&data);
+            Some(buf)
+        }
+        std::cmp::Ordering::Greater => None,
+    }
+}
@@ -27,6 +27,9 @@ pub use task::{AsyncOperation, OpensslAsyncTask, SyncOperation};
 mod tokio_op;
 pub use tokio_op::TokioAsyncOperation;
 
+mod key;
+pub use key::{new_async_private_key, ArcAsyncKeyDriver, AsyncKeyDriver, AsyncKeyNotifier};
+
 pub fn async_is_capable() -> bool {
     let capable = unsafe { ffi::ASYNC_is_capable() };
     capable == 1
@@ -38,7 +38,6 @@ pub enum ASYNC_JOB {}
 pub enum ASYNC_WAIT_CTX {}
 
 #[allow(non_camel_case_types)]
-#[cfg(ossl300)]
 pub type ASYNC_callback_fn = Option<unsafe extern "C" fn(arg: *mut c_void) -> c_int>;
 
 #[allow(non_camel_case_types)]
@@ -137,3 +136,149 @@ extern "C" {
     #[cfg(ossl300)]
     pub fn SSL_get_async_status(s: *mut SSL) -> c_int;
 }
+
+#[cfg(ossl300)]
+pub use self::key::*;
+
+#[cfg(ossl300)]
+mod key {
+    use libc::{c_char, c_int, c_long, c_uchar, c_uint, c_void};
+    use openssl_sys::{BIGNUM, ECDSA_SIG, EC_KEY, RSA};
+
+    pub const CRYPTO_EX_INDEX_EC_KEY: c_int = 8;
+    pub const CRYPTO_EX_INDEX_RSA: c_int = 9;
+
+    #[allow(non_camel_case_types)]
+    pub enum RSA_METHOD {}
+
+    #[allow(non_camel_case_types)]
+    pub enum EC_KEY_METHOD {}
+
+    #[allow(non_camel_case_types)]
+    pub enum CRYPTO_EX_DATA {}
+
+    #[allow(non_camel_case_types)]
+    pub type CRYPTO_EX_free = Option<
+        unsafe extern "C" fn(
+            parent: *mut c_void,
+            ptr: *mut c_void,
+            ad: *mut CRYPTO_EX_DATA,
+            idx: c_int,
+            argl: c_long,
+            argp: *mut c_void,
+        ),
+    >;
+
+    #[allow(non_camel_case_types)]
+    pub type RSA_priv_fn = Option<
+        unsafe extern "C" fn(
+            flen: c_int,
+            from: *const c_uchar,
+            to: *mut c_uchar,
+            rsa: *mut RSA,
+            padding: c_int,
+        ) -> c_int,
+    >;
+
+    #[allow(non_camel_case_types)]
+    pub type EC_KEY_sign_fn = Option<
+        unsafe extern "C" fn(
+            type_: c_int,
+            dgst: *const c_uchar,
+            dlen: c_int,
+            sig: *mut c_uchar,
+            siglen: *mut c_uint,
+            kinv: *const BIGNUM,
+            r: *const BIGNUM,
+            eckey: *mut EC_KEY,
+        ) -> c_int,
+    >;
+
+    #[allow(non_camel_case_types)]
+    pub type EC_KEY_sign_setup_fn = Option<
+        unsafe extern "C" fn(
+            eckey: *mut EC_KEY,
+            ctx_in: *mut c_void,
+            kinvp: *mut *mut BIGNUM,
+            rp: *mut *mut BIGNUM,
+        ) -> c_int,
+    >;
+
+    #[allow(non_camel_case_types)]
+    pub type EC_KEY_sign_sig_fn = Option<
+        unsafe extern "C" fn(
+            dgst: *const c_uchar,
+            dgst_len: c_int,
+            in_kinv: *const BIGNUM,
+            in_r: *const BIGNUM,
+            eckey: *mut EC_KEY,
+        ) -> *mut ECDSA_SIG,
+    >;
+
+    extern "C" {
+        pub fn CRYPTO_get_ex_new_index(
+            class_index: c_int,
+            argl: c_long,
+            argp: *mut c_void,
+            new_func: *mut c_void,
+            dup_func: *mut c_void,
+            free_func: CRYPTO_EX_free,
+        ) -> c_int;
+
+        pub fn RSA_PKCS1_OpenSSL() -> *const RSA_METHOD;
+        pub fn RSA_meth_dup(meth: *const RSA_METHOD) -> *mut RSA_METHOD;
+        pub fn RSA_meth_set1_name(meth: *mut RSA_METHOD, name: *const c_char) -> c_int;
+        pub fn RSA_meth_set_priv_enc(meth: *mut RSA_METHOD, priv_enc: RSA_priv_fn) -> c_int;
+        pub fn RSA_meth_set_priv_dec(meth: *mut RSA_METHOD, priv_dec: RSA_priv_fn) -> c_int;
+        pub fn RSA_set_method(rsa: *mut RSA, meth: *const RSA_METHOD) -> c_int;
+        pub fn RSA_set_ex_data(rsa: *mut RSA, idx: c_int, arg: *mut c_void) -> c_int;
+        pub fn RSA_get_ex_data(rsa: *const RSA, idx: c_int) -> *mut c_void;
+        pub fn RSA_size(rsa: *const RSA) -> c_int;
+        pub fn RSA_padding_add_PKCS1_type_1(
+            to: *mut c_uchar,
+            tlen: c_int,
+            f: *const c_uchar,
+            fl: c_int,
+        ) -> c_int;
+        pub fn RSA_padding_check_PKCS1_type_2(
+            to: *mut c_uchar,
+            tlen: c_int,
+            f: *const c_uchar,
+            fl: c_int,
+            rsa_len: c_int,
+        ) -> c_int;
+        pub fn RSA_padding_check_PKCS1_OAEP(
+            to: *mut c_uchar,
+            tlen: c_int,
+            f: *const c_uchar,
+            fl: c_int,
+            rsa_len: c_int,
+            p: *const c_uchar,
+            pl: c_int,
+        ) -> c_int;
+
+        pub fn EC_KEY_OpenSSL() -> *const EC_KEY_METHOD;
+        pub fn EC_KEY_METHOD_new(meth: *const EC_KEY_METHOD) -> *mut EC_KEY_METHOD;
+        pub fn EC_KEY_METHOD_get_sign(
+            meth: *const EC_KEY_METHOD,
+            psign: *mut EC_KEY_sign_fn,
+            psign_setup: *mut EC_KEY_sign_setup_fn,
+            psign_sig: *mut EC_KEY_sign_sig_fn,
+        );
+        pub fn EC_KEY_METHOD_set_sign(
+            meth: *mut EC_KEY_METHOD,
+            sign: EC_KEY_sign_fn,
+            sign_setup: EC_KEY_sign_setup_fn,
+            sign_sig: EC_KEY_sign_sig_fn,
+        );
+        pub fn EC_KEY_set_method(key: *mut EC_KEY, meth: *const EC_KEY_METHOD) -> c_int;
+        pub fn EC_KEY_set_ex_data(key: *mut EC_KEY, idx: c_int, arg: *mut c_void) -> c_int;
+        pub fn EC_KEY_get_ex_data(key: *const EC_KEY, idx: c_int) -> *mut c_void;
+        pub fn ECDSA_size(key: *const EC_KEY) -> c_int;
+        pub fn d2i_ECDSA_SIG(
+            sig: *mut *mut ECDSA_SIG,
+            pp: *mut *const c_uchar,
+            len: c_long,
+        ) -> *mut ECDSA_SIG;
+    }
+}
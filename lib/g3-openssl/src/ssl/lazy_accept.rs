@@ -19,6 +19,8 @@ use std::io;
 use std::task::{Context, Poll};
 
 use openssl::error::ErrorStack;
+#[cfg(feature = "async-job")]
+use openssl::foreign_types::ForeignTypeRef;
 use openssl::ssl::{self, ErrorCode, Ssl, SslContextRef, SslRef};
 use tokio::io::{AsyncRead, AsyncWrite};
 
@@ -60,6 +62,23 @@ impl<S: AsyncRead + AsyncWrite + Unpin> SslLazyAcceptor<S> {
         use crate::ssl::async_mode::AsyncEnginePoller;
 
         self.inner.ssl_mut().set_ssl_context(ssl_ctx)?;
+        // the SSL mode won't be changed along with the context, so copy the async mode here
+        unsafe {
+            let ctx_mode = openssl_sys::SSL_CTX_ctrl(
+                ssl_ctx.as_ptr(),
+                openssl_sys::SSL_CTRL_MODE,
+                0,
+                std::ptr::null_mut(),
+            );
+            if ctx_mode & openssl_sys::SSL_MODE_ASYNC != 0 {
+                openssl_sys::SSL_ctrl(
+                    self.inner.ssl().as_ptr(),
+                    openssl_sys::SSL_CTRL_MODE,
+                    openssl_sys::SSL_MODE_ASYNC,
+                    std::ptr::null_mut(),
+                );
+            }
+        }
         let async_engine = AsyncEnginePoller::new(self.inner.ssl())?;
         Ok(SslAcceptor {
             inner: self.inner,
@@ -0,0 +1,285 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use http::{HeaderName, HeaderValue};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::ServerName;
+use tokio_rustls::TlsConnector;
+
+use g3_types::net::{
+    AlpnProtocol, RustlsClientConfig, RustlsClientConfigBuilder, TcpKeepAliveConfig,
+};
+
+use super::export::{GrpcExporter, HttpExporter, OtlpConnection};
+
+const OTLP_GRPC_DEFAULT_PORT: u16 = 4317;
+const OTLP_HTTP_DEFAULT_PORT: u16 = 4318;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum OtlpSignal {
+    Logs,
+    Traces,
+}
+
+impl OtlpSignal {
+    fn default_http_path(&self) -> &'static str {
+        match self {
+            OtlpSignal::Logs => "/v1/logs",
+            OtlpSignal::Traces => "/v1/traces",
+        }
+    }
+
+    fn grpc_path(&self) -> &'static str {
+        match self {
+            OtlpSignal::Logs => "/opentelemetry.proto.collector.logs.v1.LogsService/Export",
+            OtlpSignal::Traces => "/opentelemetry.proto.collector.trace.v1.TraceService/Export",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtlpExportProtocol {
+    Grpc,
+    HttpProtobuf,
+}
+
+impl OtlpExportProtocol {
+    fn default_port(&self) -> u16 {
+        match self {
+            OtlpExportProtocol::Grpc => OTLP_GRPC_DEFAULT_PORT,
+            OtlpExportProtocol::HttpProtobuf => OTLP_HTTP_DEFAULT_PORT,
+        }
+    }
+}
+
+impl FromStr for OtlpExportProtocol {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "grpc" => Ok(OtlpExportProtocol::Grpc),
+            "http" | "http_protobuf" | "http/protobuf" => Ok(OtlpExportProtocol::HttpProtobuf),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OtlpLogClientConfig {
+    pub(super) protocol: OtlpExportProtocol,
+    server_addr: Option<SocketAddr>,
+    bind_ip: Option<IpAddr>,
+    tcp_keepalive: TcpKeepAliveConfig,
+    tls_client: Option<RustlsClientConfig>,
+    tls_name: Option<ServerName>,
+    pub(super) signal: OtlpSignal,
+    http_path: Option<String>,
+    pub(super) headers: Vec<(HeaderName, HeaderValue)>,
+    pub(super) hostname: String,
+    pub(super) resource_attributes: Vec<(String, String)>,
+    pub(super) connect_timeout: Duration,
+    pub(super) connect_delay: Duration,
+    pub(super) request_timeout: Duration,
+    pub(super) flush_interval: Duration,
+    pub(super) batch_size: usize,
+    pub(super) retry_queue_len: usize,
+}
+
+impl Default for OtlpLogClientConfig {
+    fn default() -> Self {
+        OtlpLogClientConfig::new(OtlpExportProtocol::Grpc)
+    }
+}
+
+impl OtlpLogClientConfig {
+    pub fn new(protocol: OtlpExportProtocol) -> Self {
+        let hostname = nix::unistd::gethostname()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        OtlpLogClientConfig {
+            protocol,
+            server_addr: None,
+            bind_ip: None,
+            tcp_keepalive: TcpKeepAliveConfig::default_enabled(),
+            tls_client: None,
+            tls_name: None,
+            signal: OtlpSignal::Logs,
+            http_path: None,
+            headers: Vec::new(),
+            hostname,
+            resource_attributes: Vec::new(),
+            connect_timeout: Duration::from_secs(10),
+            connect_delay: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(10),
+            flush_interval: Duration::from_secs(1),
+            batch_size: 512,
+            retry_queue_len: 10,
+        }
+    }
+
+    pub fn set_protocol(&mut self, protocol: OtlpExportProtocol) {
+        self.protocol = protocol;
+    }
+
+    pub fn set_server_addr(&mut self, addr: SocketAddr) {
+        self.server_addr = Some(addr);
+    }
+
+    pub fn set_bind_ip(&mut self, ip: IpAddr) {
+        self.bind_ip = Some(ip);
+    }
+
+    pub fn set_tcp_keepalive(&mut self, keepalive: TcpKeepAliveConfig) {
+        self.tcp_keepalive = keepalive;
+    }
+
+    /// should be called after the protocol has been set, as we need to set the ALPN value
+    pub fn set_tls_client(&mut self, tls_config: RustlsClientConfigBuilder) -> anyhow::Result<()> {
+        let alpn_protocol = match self.protocol {
+            OtlpExportProtocol::Grpc => AlpnProtocol::Http2,
+            OtlpExportProtocol::HttpProtobuf => AlpnProtocol::Http11,
+        };
+        let tls_client = tls_config
+            .build_with_alpn_protocols(Some(vec![alpn_protocol]))
+            .context("failed to build tls client config")?;
+        self.tls_client = Some(tls_client);
+        Ok(())
+    }
+
+    pub fn set_tls_name(&mut self, tls_name: ServerName) {
+        self.tls_name = Some(tls_name);
+    }
+
+    pub fn set_http_path(&mut self, path: String) {
+        self.http_path = Some(path);
+    }
+
+    pub fn add_header(&mut self, name: HeaderName, value: HeaderValue) {
+        self.headers.push((name, value));
+    }
+
+    pub fn set_hostname(&mut self, hostname: String) {
+        self.hostname = hostname;
+    }
+
+    pub fn add_resource_attribute(&mut self, key: String, value: String) {
+        self.resource_attributes.push((key, value));
+    }
+
+    pub fn set_connect_timeout(&mut self, timeout: Duration) {
+        self.connect_timeout = timeout;
+    }
+
+    pub fn set_connect_delay(&mut self, delay: Duration) {
+        self.connect_delay = delay;
+    }
+
+    pub fn set_request_timeout(&mut self, timeout: Duration) {
+        self.request_timeout = timeout;
+    }
+
+    pub fn set_flush_interval(&mut self, interval: Duration) {
+        self.flush_interval = interval;
+    }
+
+    pub fn set_batch_size(&mut self, size: usize) {
+        self.batch_size = size.max(1);
+    }
+
+    pub fn set_retry_queue_len(&mut self, len: usize) {
+        self.retry_queue_len = len;
+    }
+
+    pub(super) fn http_path(&self) -> &str {
+        self.http_path
+            .as_deref()
+            .unwrap_or_else(|| self.signal.default_http_path())
+    }
+
+    pub(super) fn grpc_path(&self) -> &'static str {
+        self.signal.grpc_path()
+    }
+
+    pub(super) fn server_addr(&self) -> SocketAddr {
+        self.server_addr.unwrap_or_else(|| {
+            SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                self.protocol.default_port(),
+            )
+        })
+    }
+
+    /// the authority value used in the Host header or the :authority pseudo header
+    pub(super) fn authority(&self) -> String {
+        let addr = self.server_addr();
+        match &self.tls_name {
+            Some(ServerName::DnsName(name)) => format!("{}:{}", name.as_ref(), addr.port()),
+            _ => addr.to_string(),
+        }
+    }
+
+    pub(super) async fn new_connection(&self) -> anyhow::Result<OtlpConnection> {
+        let server_addr = self.server_addr();
+        let socket = g3_socket::tcp::new_socket_to(
+            server_addr.ip(),
+            self.bind_ip,
+            &self.tcp_keepalive,
+            &Default::default(),
+            false,
+        )
+        .map_err(|e| anyhow!("failed to setup socket: {e:?}"))?;
+        let tcp_stream: TcpStream = socket
+            .connect(server_addr)
+            .await
+            .map_err(|e| anyhow!("failed to tcp connect to peer {server_addr}: {e:?}"))?;
+
+        if let Some(tls_client) = &self.tls_client {
+            let tls_name = self
+                .tls_name
+                .clone()
+                .unwrap_or(ServerName::IpAddress(server_addr.ip()));
+            let tls_connect =
+                TlsConnector::from(tls_client.driver.clone()).connect(tls_name, tcp_stream);
+
+            match tokio::time::timeout(tls_client.handshake_timeout, tls_connect).await {
+                Ok(Ok(stream)) => match self.protocol {
+                    OtlpExportProtocol::Grpc => GrpcExporter::handshake(self, stream, true)
+                        .await
+                        .map(OtlpConnection::Grpc),
+                    OtlpExportProtocol::HttpProtobuf => {
+                        Ok(OtlpConnection::HttpTls(HttpExporter::new(self, stream)))
+                    }
+                },
+                Ok(Err(e)) => Err(anyhow!("failed to tls connect to peer: {e}")),
+                Err(_) => Err(anyhow!("tls connect to peer timedout")),
+            }
+        } else {
+            match self.protocol {
+                OtlpExportProtocol::Grpc => GrpcExporter::handshake(self, tcp_stream, false)
+                    .await
+                    .map(OtlpConnection::Grpc),
+                OtlpExportProtocol::HttpProtobuf => {
+                    Ok(OtlpConnection::Http(HttpExporter::new(self, tcp_stream)))
+                }
+            }
+        }
+    }
+}
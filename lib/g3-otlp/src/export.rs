@@ -0,0 +1,517 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::Write;
+
+use anyhow::anyhow;
+use bytes::{BufMut, Bytes, BytesMut};
+use h2::client::SendRequest;
+use http::{Method, Request, StatusCode};
+use log::debug;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+
+use g3_http::client::HttpForwardRemoteResponse;
+use g3_http::HttpBodyReader;
+
+use super::OtlpLogClientConfig;
+
+const HTTP_RESPONSE_HEADER_MAX_SIZE: usize = 4096;
+
+pub(crate) enum ExportError {
+    /// the request may succeed later, the connection should be closed
+    Retryable(anyhow::Error),
+    /// the request is rejected by the server, and should be dropped
+    Rejected(anyhow::Error),
+}
+
+pub(crate) enum OtlpConnection {
+    Http(HttpExporter<TcpStream>),
+    HttpTls(HttpExporter<TlsStream<TcpStream>>),
+    Grpc(GrpcExporter),
+}
+
+impl OtlpConnection {
+    pub(crate) async fn export(
+        &mut self,
+        config: &OtlpLogClientConfig,
+        data: Bytes,
+    ) -> Result<(), ExportError> {
+        match self {
+            OtlpConnection::Http(exporter) => exporter.export(config, data).await,
+            OtlpConnection::HttpTls(exporter) => exporter.export(config, data).await,
+            OtlpConnection::Grpc(exporter) => exporter.export(config, data).await,
+        }
+    }
+
+    pub(crate) fn reusable(&self) -> bool {
+        match self {
+            OtlpConnection::Http(exporter) => exporter.keep_alive,
+            OtlpConnection::HttpTls(exporter) => exporter.keep_alive,
+            OtlpConnection::Grpc(_) => true,
+        }
+    }
+}
+
+fn check_http_status(code: u16) -> Result<(), ExportError> {
+    match code {
+        200..=299 => Ok(()),
+        // see https://opentelemetry.io/docs/specs/otlp/#failures-1
+        429 | 502 | 503 | 504 => Err(ExportError::Retryable(anyhow!(
+            "server responded with status code {code}"
+        ))),
+        _ => Err(ExportError::Rejected(anyhow!(
+            "server responded with status code {code}"
+        ))),
+    }
+}
+
+pub(crate) struct HttpExporter<S> {
+    stream: BufReader<S>,
+    authority: String,
+    keep_alive: bool,
+}
+
+impl<S> HttpExporter<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub(crate) fn new(config: &OtlpLogClientConfig, stream: S) -> Self {
+        HttpExporter {
+            stream: BufReader::new(stream),
+            authority: config.authority(),
+            keep_alive: true,
+        }
+    }
+
+    fn build_header(&self, config: &OtlpLogClientConfig, body_len: usize) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(256);
+        let _ = write!(
+            buf,
+            "POST {} HTTP/1.1\r\n\
+             Host: {}\r\n\
+             Content-Type: application/x-protobuf\r\n\
+             Content-Length: {body_len}\r\n",
+            config.http_path(),
+            self.authority
+        );
+        for (name, value) in &config.headers {
+            buf.extend_from_slice(name.as_str().as_bytes());
+            buf.extend_from_slice(b": ");
+            buf.extend_from_slice(value.as_bytes());
+            buf.extend_from_slice(b"\r\n");
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+
+    async fn export(
+        &mut self,
+        config: &OtlpLogClientConfig,
+        data: Bytes,
+    ) -> Result<(), ExportError> {
+        let header = self.build_header(config, data.len());
+        let writer = self.stream.get_mut();
+        writer
+            .write_all(&header)
+            .await
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to write header: {e:?}")))?;
+        writer
+            .write_all(&data)
+            .await
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to write body: {e:?}")))?;
+        writer
+            .flush()
+            .await
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to flush data: {e:?}")))?;
+
+        let rsp = HttpForwardRemoteResponse::parse(
+            &mut self.stream,
+            &Method::POST,
+            true,
+            HTTP_RESPONSE_HEADER_MAX_SIZE,
+        )
+        .await
+        .map_err(|e| ExportError::Retryable(anyhow!("failed to read response: {e}")))?;
+        self.keep_alive = rsp.keep_alive();
+
+        if let Some(body_type) = rsp.body_type(&Method::POST) {
+            // the body should contain an ExportLogsServiceResponse or a Status message,
+            // which is only useful for debugging, so just skip it
+            let mut body_reader = HttpBodyReader::new(&mut self.stream, body_type, 2048);
+            let mut sink = tokio::io::sink();
+            tokio::io::copy(&mut body_reader, &mut sink)
+                .await
+                .map_err(|e| {
+                    ExportError::Retryable(anyhow!("failed to read response body: {e:?}"))
+                })?;
+        }
+
+        check_http_status(rsp.code)
+    }
+}
+
+pub(crate) struct GrpcExporter {
+    send_request: SendRequest<Bytes>,
+    uri: String,
+}
+
+impl GrpcExporter {
+    pub(crate) async fn handshake<S>(
+        config: &OtlpLogClientConfig,
+        stream: S,
+        tls: bool,
+    ) -> anyhow::Result<Self>
+    where
+        S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+    {
+        let (send_request, connection) = h2::client::handshake(stream)
+            .await
+            .map_err(|e| anyhow!("h2 handshake failed: {e}"))?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("otlp grpc connection closed with error: {e}");
+            }
+        });
+
+        let scheme = if tls { "https" } else { "http" };
+        Ok(GrpcExporter {
+            send_request,
+            uri: format!("{scheme}://{}{}", config.authority(), config.grpc_path()),
+        })
+    }
+
+    async fn export(
+        &mut self,
+        config: &OtlpLogClientConfig,
+        data: Bytes,
+    ) -> Result<(), ExportError> {
+        let mut builder = Request::builder()
+            .method(Method::POST)
+            .uri(&self.uri)
+            .header(http::header::CONTENT_TYPE, "application/grpc")
+            .header(http::header::TE, "trailers");
+        for (name, value) in &config.headers {
+            builder = builder.header(name, value);
+        }
+        let req = builder
+            .body(())
+            .map_err(|e| ExportError::Rejected(anyhow!("failed to build request: {e}")))?;
+
+        // the length-prefixed message, see
+        // https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md
+        let mut body = BytesMut::with_capacity(data.len() + 5);
+        body.put_u8(0); // no compression
+        body.put_u32(data.len() as u32);
+        body.put_slice(&data);
+
+        let mut send_request = self
+            .send_request
+            .clone()
+            .ready()
+            .await
+            .map_err(|e| ExportError::Retryable(anyhow!("h2 connection is not ready: {e}")))?;
+        let (rsp_fut, mut send_stream) = send_request
+            .send_request(req, false)
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to send request: {e}")))?;
+        send_stream
+            .send_data(body.freeze(), true)
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to send data: {e}")))?;
+
+        let rsp = rsp_fut
+            .await
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to recv response: {e}")))?;
+        let status = rsp.status();
+        if status != StatusCode::OK {
+            check_http_status(status.as_u16())?;
+            return Err(ExportError::Rejected(anyhow!(
+                "unexpected status code {status} for grpc response"
+            )));
+        }
+
+        // trailers-only response
+        if let Some(v) = rsp.headers().get("grpc-status") {
+            return check_grpc_status(v.as_bytes());
+        }
+
+        let mut recv_stream = rsp.into_body();
+        while let Some(r) = recv_stream.data().await {
+            let data =
+                r.map_err(|e| ExportError::Retryable(anyhow!("failed to recv data: {e}")))?;
+            let _ = recv_stream.flow_control().release_capacity(data.len());
+        }
+        let trailers = recv_stream
+            .trailers()
+            .await
+            .map_err(|e| ExportError::Retryable(anyhow!("failed to recv trailers: {e}")))?
+            .ok_or_else(|| ExportError::Retryable(anyhow!("no trailers found in response")))?;
+        match trailers.get("grpc-status") {
+            Some(v) => check_grpc_status(v.as_bytes()),
+            None => Err(ExportError::Retryable(anyhow!(
+                "no grpc-status found in trailers"
+            ))),
+        }
+    }
+}
+
+fn check_grpc_status(v: &[u8]) -> Result<(), ExportError> {
+    let code = std::str::from_utf8(v)
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+        .ok_or_else(|| ExportError::Rejected(anyhow!("invalid grpc-status value")))?;
+    match code {
+        0 => Ok(()),
+        // see https://opentelemetry.io/docs/specs/otlp/#failures
+        1 | 4 | 8 | 10 | 11 | 14 | 15 => Err(ExportError::Retryable(anyhow!(
+            "server responded with grpc-status {code}"
+        ))),
+        _ => Err(ExportError::Rejected(anyhow!(
+            "server responded with grpc-status {code}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{HeaderMap, HeaderValue, Response};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, DuplexStream};
+
+    fn http_config() -> OtlpLogClientConfig {
+        let mut config = OtlpLogClientConfig::new(crate::OtlpExportProtocol::HttpProtobuf);
+        config.set_server_addr("127.0.0.1:4318".parse().unwrap());
+        config.add_header(
+            http::header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer t"),
+        );
+        config
+    }
+
+    /// read one request from the client, and return the header and the body
+    async fn recv_http_request(stream: &mut BufReader<DuplexStream>) -> (String, Vec<u8>) {
+        let mut header = String::new();
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            header.push_str(&line);
+        }
+        let body_len = header
+            .lines()
+            .find_map(|l| l.strip_prefix("Content-Length: "))
+            .map(|v| v.parse::<usize>().unwrap())
+            .unwrap();
+        let mut body = vec![0u8; body_len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    #[tokio::test]
+    async fn http_export() {
+        let config = http_config();
+        let (client, server) = tokio::io::duplex(4096);
+        let mut exporter = HttpExporter::new(&config, client);
+        let mut server = BufReader::new(server);
+
+        let server_task = tokio::spawn(async move {
+            let (header, body) = recv_http_request(&mut server).await;
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .await
+                .unwrap();
+
+            let (_, body2) = recv_http_request(&mut server).await;
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+            (header, body, body2)
+        });
+
+        assert!(exporter
+            .export(&config, Bytes::from_static(b"data1"))
+            .await
+            .is_ok());
+        assert!(exporter.keep_alive);
+        assert!(exporter
+            .export(&config, Bytes::from_static(b"data2"))
+            .await
+            .is_ok());
+        assert!(!exporter.keep_alive);
+
+        let (header, body, body2) = server_task.await.unwrap();
+        assert!(header.starts_with("POST /v1/logs HTTP/1.1\r\n"));
+        assert!(header.contains("Host: 127.0.0.1:4318\r\n"));
+        assert!(header.contains("Content-Type: application/x-protobuf\r\n"));
+        assert!(header.contains("authorization: Bearer t\r\n"));
+        assert_eq!(body, b"data1");
+        assert_eq!(body2, b"data2");
+    }
+
+    #[tokio::test]
+    async fn http_export_failure() {
+        let config = http_config();
+        let (client, server) = tokio::io::duplex(4096);
+        let mut exporter = HttpExporter::new(&config, client);
+        let mut server = BufReader::new(server);
+
+        let server_task = tokio::spawn(async move {
+            recv_http_request(&mut server).await;
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            recv_http_request(&mut server).await;
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 3\r\n\r\nbad")
+                .await
+                .unwrap();
+            recv_http_request(&mut server).await;
+            // close the connection without response
+        });
+
+        let r = exporter.export(&config, Bytes::from_static(b"data")).await;
+        assert!(matches!(r, Err(ExportError::Retryable(_))));
+        let r = exporter.export(&config, Bytes::from_static(b"data")).await;
+        assert!(matches!(r, Err(ExportError::Rejected(_))));
+        let r = exporter.export(&config, Bytes::from_static(b"data")).await;
+        assert!(matches!(r, Err(ExportError::Retryable(_))));
+
+        server_task.await.unwrap();
+    }
+
+    #[test]
+    fn http_status() {
+        assert!(check_http_status(200).is_ok());
+        assert!(check_http_status(204).is_ok());
+        for code in [429, 502, 503, 504] {
+            assert!(matches!(
+                check_http_status(code),
+                Err(ExportError::Retryable(_))
+            ));
+        }
+        for code in [400, 401, 404, 500] {
+            assert!(matches!(
+                check_http_status(code),
+                Err(ExportError::Rejected(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn grpc_status() {
+        assert!(check_grpc_status(b"0").is_ok());
+        for code in [b"1", b"4", b"8"] {
+            assert!(matches!(
+                check_grpc_status(code),
+                Err(ExportError::Retryable(_))
+            ));
+        }
+        assert!(matches!(
+            check_grpc_status(b"14"),
+            Err(ExportError::Retryable(_))
+        ));
+        assert!(matches!(
+            check_grpc_status(b"3"),
+            Err(ExportError::Rejected(_))
+        ));
+        assert!(matches!(
+            check_grpc_status(b"x"),
+            Err(ExportError::Rejected(_))
+        ));
+    }
+
+    /// serve grpc requests with the given grpc-status values, and return the received messages
+    async fn serve_grpc(stream: DuplexStream, status_list: Vec<&'static str>) -> Vec<Bytes> {
+        let mut connection = h2::server::handshake(stream).await.unwrap();
+        let mut messages = Vec::new();
+        for (i, status) in status_list.into_iter().enumerate() {
+            let (req, mut respond) = connection.accept().await.unwrap().unwrap();
+            assert_eq!(req.method(), Method::POST);
+            assert_eq!(
+                req.uri().path(),
+                "/opentelemetry.proto.collector.logs.v1.LogsService/Export"
+            );
+            assert_eq!(
+                req.headers().get(http::header::CONTENT_TYPE).unwrap(),
+                "application/grpc"
+            );
+
+            let mut recv_stream = req.into_body();
+            let mut data = BytesMut::new();
+            while let Some(r) = recv_stream.data().await {
+                data.extend_from_slice(&r.unwrap());
+            }
+            messages.push(data.freeze());
+
+            if i % 2 == 0 {
+                // send with trailers
+                let rsp = Response::builder().status(200).body(()).unwrap();
+                let mut send_stream = respond.send_response(rsp, false).unwrap();
+                let mut trailers = HeaderMap::new();
+                trailers.insert("grpc-status", HeaderValue::from_static(status));
+                send_stream.send_trailers(trailers).unwrap();
+            } else {
+                // trailers-only response
+                let rsp = Response::builder()
+                    .status(200)
+                    .header("grpc-status", status)
+                    .body(())
+                    .unwrap();
+                respond.send_response(rsp, true).unwrap();
+            }
+        }
+        // drive the connection until the client closes it
+        let _ = connection.accept().await;
+        messages
+    }
+
+    #[tokio::test]
+    async fn grpc_export() {
+        let config = OtlpLogClientConfig::new(crate::OtlpExportProtocol::Grpc);
+        let (client, server) = tokio::io::duplex(4096);
+        let server_task = tokio::spawn(serve_grpc(server, vec!["0", "0", "14", "3"]));
+
+        let mut exporter = GrpcExporter::handshake(&config, client, false)
+            .await
+            .unwrap();
+        assert!(exporter
+            .export(&config, Bytes::from_static(b"abc"))
+            .await
+            .is_ok());
+        assert!(exporter
+            .export(&config, Bytes::from_static(b"de"))
+            .await
+            .is_ok());
+        let r = exporter.export(&config, Bytes::from_static(b"f")).await;
+        assert!(matches!(r, Err(ExportError::Retryable(_))));
+        let r = exporter.export(&config, Bytes::from_static(b"g")).await;
+        assert!(matches!(r, Err(ExportError::Rejected(_))));
+        drop(exporter);
+
+        let messages = server_task.await.unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].as_ref(), b"\x00\x00\x00\x00\x03abc");
+        assert_eq!(messages[1].as_ref(), b"\x00\x00\x00\x00\x02de");
+    }
+}
@@ -0,0 +1,301 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::RefCell;
+use std::fmt::{Arguments, Write};
+
+use chrono::Utc;
+use slog::{Level, OwnedKVList, Record, Serializer, KV};
+
+use g3_types::log::AsyncLogFormatter;
+
+use super::proto::{self, AnyValue};
+
+thread_local! {
+    static TL_BUF: RefCell<String> = RefCell::new(String::with_capacity(128));
+    static TL_VBUF: RefCell<Vec<u8>> = RefCell::new(Vec::with_capacity(128));
+}
+
+const KEY_DAEMON_NAME: &str = "daemon_name";
+const KEY_SERVER_NAME: &str = "server_name";
+const KEY_TRACE_ID: &str = "trace_id";
+const KEY_SPAN_ID: &str = "span_id";
+
+/// A formatted log record, which should be grouped by resource before export
+pub struct OtlpLogRecord {
+    pub(crate) daemon_group: Option<String>,
+    pub(crate) server_name: Option<String>,
+    /// the encoded `LogRecord` message, without the field tag and length
+    pub(crate) data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct OtlpLogFormatter {}
+
+impl OtlpLogFormatter {
+    fn encode(
+        &self,
+        record: &Record,
+        logger_values: &OwnedKVList,
+    ) -> Result<OtlpLogRecord, slog::Error> {
+        let datetime_now = Utc::now();
+        let time_nanos = u64::try_from(datetime_now.timestamp()).unwrap_or_default()
+            * 1_000_000_000
+            + datetime_now.timestamp_subsec_nanos().min(999_999_999) as u64; // ignore leap second
+
+        let mut buf = Vec::<u8>::with_capacity(1024);
+        proto::encode_fixed64_field(&mut buf, proto::LOG_RECORD_TIME_UNIX_NANO, time_nanos);
+        proto::encode_fixed64_field(
+            &mut buf,
+            proto::LOG_RECORD_OBSERVED_TIME_UNIX_NANO,
+            time_nanos,
+        );
+        proto::encode_varint_field(
+            &mut buf,
+            proto::LOG_RECORD_SEVERITY_NUMBER,
+            severity_number(record.level()),
+        );
+        proto::encode_string_field(
+            &mut buf,
+            proto::LOG_RECORD_SEVERITY_TEXT,
+            record.level().as_str(),
+        );
+
+        TL_BUF.with_borrow_mut(|s| {
+            s.clear();
+
+            s.write_fmt(*record.msg()).unwrap();
+
+            proto::encode_any_value_field(&mut buf, proto::LOG_RECORD_BODY, AnyValue::String(s));
+        });
+
+        let mut kv_formatter = FormatterKv {
+            buf,
+            daemon_group: None,
+            server_name: None,
+            trace_id: None,
+            span_id: None,
+        };
+        logger_values.serialize(record, &mut kv_formatter)?;
+        record.kv().serialize(record, &mut kv_formatter)?;
+
+        let mut buf = kv_formatter.buf;
+        if let Some(trace_id) = kv_formatter.trace_id {
+            proto::encode_bytes_field(&mut buf, proto::LOG_RECORD_TRACE_ID, &trace_id);
+        }
+        if let Some(span_id) = kv_formatter.span_id {
+            proto::encode_bytes_field(&mut buf, proto::LOG_RECORD_SPAN_ID, &span_id);
+        }
+
+        Ok(OtlpLogRecord {
+            daemon_group: kv_formatter.daemon_group,
+            server_name: kv_formatter.server_name,
+            data: buf,
+        })
+    }
+}
+
+impl AsyncLogFormatter<OtlpLogRecord> for OtlpLogFormatter {
+    fn format_slog(
+        &self,
+        record: &Record,
+        logger_values: &OwnedKVList,
+    ) -> Result<OtlpLogRecord, slog::Error> {
+        self.encode(record, logger_values)
+    }
+}
+
+/// map to the SeverityNumber enum defined in the OpenTelemetry log data model
+fn severity_number(level: Level) -> u64 {
+    match level {
+        Level::Trace => 1,
+        Level::Debug => 5,
+        Level::Info => 9,
+        Level::Warning => 13,
+        Level::Error => 17,
+        Level::Critical => 21,
+    }
+}
+
+fn decode_hex_id<const N: usize>(s: &str) -> Option<[u8; N]> {
+    let s = s.as_bytes();
+    if s.len() != N * 2 {
+        return None;
+    }
+    let mut id = [0u8; N];
+    for (i, b) in id.iter_mut().enumerate() {
+        let hi = (s[i * 2] as char).to_digit(16)?;
+        let lo = (s[i * 2 + 1] as char).to_digit(16)?;
+        *b = ((hi << 4) | lo) as u8;
+    }
+    if id.iter().all(|b| *b == 0) {
+        // all zero ids are invalid
+        return None;
+    }
+    Some(id)
+}
+
+struct FormatterKv {
+    buf: Vec<u8>,
+    daemon_group: Option<String>,
+    server_name: Option<String>,
+    trace_id: Option<[u8; 16]>,
+    span_id: Option<[u8; 8]>,
+}
+
+impl FormatterKv {
+    fn emit_attribute(&mut self, key: slog::Key, value: AnyValue) -> slog::Result {
+        proto::encode_key_value_field(&mut self.buf, proto::LOG_RECORD_ATTRIBUTES, key, value);
+        Ok(())
+    }
+}
+
+impl Serializer for FormatterKv {
+    fn emit_usize(&mut self, key: slog::Key, value: usize) -> slog::Result {
+        self.emit_u64(key, value as u64)
+    }
+    fn emit_isize(&mut self, key: slog::Key, value: isize) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_u8(&mut self, key: slog::Key, value: u8) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_i8(&mut self, key: slog::Key, value: i8) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_u16(&mut self, key: slog::Key, value: u16) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_i16(&mut self, key: slog::Key, value: i16) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_u32(&mut self, key: slog::Key, value: u32) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_i32(&mut self, key: slog::Key, value: i32) -> slog::Result {
+        self.emit_i64(key, value as i64)
+    }
+    fn emit_u64(&mut self, key: slog::Key, value: u64) -> slog::Result {
+        match i64::try_from(value) {
+            Ok(v) => self.emit_i64(key, v),
+            // AnyValue has no unsigned int type
+            Err(_) => self.emit_f64(key, value as f64),
+        }
+    }
+    fn emit_i64(&mut self, key: slog::Key, value: i64) -> slog::Result {
+        self.emit_attribute(key, AnyValue::Int(value))
+    }
+
+    fn emit_f32(&mut self, key: slog::Key, value: f32) -> slog::Result {
+        self.emit_f64(key, value as f64)
+    }
+    fn emit_f64(&mut self, key: slog::Key, value: f64) -> slog::Result {
+        self.emit_attribute(key, AnyValue::Double(value))
+    }
+
+    fn emit_bool(&mut self, key: slog::Key, value: bool) -> slog::Result {
+        self.emit_attribute(key, AnyValue::Bool(value))
+    }
+
+    fn emit_char(&mut self, key: slog::Key, value: char) -> slog::Result {
+        self.emit_str(key, value.encode_utf8(&mut [0u8; 4]))
+    }
+
+    fn emit_none(&mut self, _key: slog::Key) -> slog::Result {
+        Ok(())
+    }
+
+    fn emit_str(&mut self, key: slog::Key, value: &str) -> slog::Result {
+        match key {
+            KEY_DAEMON_NAME => {
+                self.daemon_group = Some(value.to_string());
+                Ok(())
+            }
+            KEY_SERVER_NAME => {
+                self.server_name = Some(value.to_string());
+                Ok(())
+            }
+            KEY_TRACE_ID => {
+                if let Some(id) = decode_hex_id::<16>(value) {
+                    self.trace_id = Some(id);
+                    Ok(())
+                } else {
+                    self.emit_attribute(key, AnyValue::String(value))
+                }
+            }
+            KEY_SPAN_ID => {
+                if let Some(id) = decode_hex_id::<8>(value) {
+                    self.span_id = Some(id);
+                    Ok(())
+                } else {
+                    self.emit_attribute(key, AnyValue::String(value))
+                }
+            }
+            _ => self.emit_attribute(key, AnyValue::String(value)),
+        }
+    }
+
+    fn emit_arguments(&mut self, key: slog::Key, value: &Arguments) -> slog::Result {
+        if let Some(s) = value.as_str() {
+            self.emit_str(key, s)
+        } else {
+            TL_BUF.with_borrow_mut(|buf| {
+                buf.clear();
+
+                buf.write_fmt(*value).unwrap();
+
+                self.emit_str(key, buf.as_str())
+            })
+        }
+    }
+
+    fn emit_serde(&mut self, key: slog::Key, value: &dyn slog::SerdeValue) -> slog::Result {
+        use serde::ser::Serialize;
+        use std::io;
+
+        TL_VBUF.with_borrow_mut(|buf| {
+            buf.clear();
+
+            let mut serializer = serde_json::Serializer::new(&mut *buf);
+            value.as_serde().serialize(&mut serializer).map_err(|e| {
+                io::Error::other(format!("serde serialization error for key {key}: {e}"))
+            })?;
+
+            let v = std::str::from_utf8(buf)
+                .map_err(|e| io::Error::other(format!("invalid utf-8 value for key {key}: {e}")))?;
+            self.emit_attribute(key, AnyValue::String(v))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_id() {
+        let id = decode_hex_id::<16>("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+        assert_eq!(id[0], 0x4b);
+        assert_eq!(id[15], 0x36);
+
+        let id = decode_hex_id::<8>("00f067aa0ba902b7").unwrap();
+        assert_eq!(id, [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]);
+
+        assert!(decode_hex_id::<8>("00f067aa0ba902b").is_none());
+        assert!(decode_hex_id::<8>("00f067aa0ba902bx").is_none());
+        assert!(decode_hex_id::<8>("0000000000000000").is_none());
+    }
+}
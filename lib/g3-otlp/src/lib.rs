@@ -0,0 +1,568 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use flume::Receiver;
+use log::warn;
+use tokio::time::Instant;
+
+use g3_types::log::{AsyncLogConfig, AsyncLogger, LogStats};
+
+mod config;
+use config::OtlpSignal;
+pub use config::{OtlpExportProtocol, OtlpLogClientConfig};
+
+mod export;
+use export::{ExportError, OtlpConnection};
+
+mod format;
+pub use format::{OtlpLogFormatter, OtlpLogRecord};
+
+mod span;
+pub use span::{OtlpSpan, OtlpSpanAttributeValue, OtlpSpanExporter, OtlpSpanKind};
+
+mod proto;
+use proto::AnyValue;
+
+const RESOURCE_KEY_SERVICE_NAME: &str = "service.name";
+const RESOURCE_KEY_HOST_NAME: &str = "host.name";
+const RESOURCE_KEY_DAEMON_GROUP: &str = "g3.daemon_group";
+const RESOURCE_KEY_SERVER_NAME: &str = "g3.server_name";
+
+pub fn new_async_logger(
+    async_conf: &AsyncLogConfig,
+    otlp_conf: &Arc<OtlpLogClientConfig>,
+    service_name: String,
+    scope_name: String,
+) -> AsyncLogger<OtlpLogRecord, OtlpLogFormatter> {
+    let (sender, receiver) = flume::bounded::<OtlpLogRecord>(async_conf.channel_capacity);
+
+    let stats = Arc::new(LogStats::default());
+
+    spawn_io_threads(
+        async_conf,
+        Arc::clone(otlp_conf),
+        service_name,
+        scope_name,
+        receiver,
+        &stats,
+    );
+
+    AsyncLogger::new(sender, OtlpLogFormatter::default(), stats)
+}
+
+/// Create a span exporter which will send spans to the `/v1/traces` endpoint of the otlp server
+pub fn new_span_exporter(
+    async_conf: &AsyncLogConfig,
+    otlp_conf: &OtlpLogClientConfig,
+    service_name: String,
+    scope_name: String,
+    daemon_group: Option<String>,
+) -> OtlpSpanExporter {
+    let (sender, receiver) = flume::bounded::<OtlpLogRecord>(async_conf.channel_capacity);
+
+    let stats = Arc::new(LogStats::default());
+
+    let mut otlp_conf = otlp_conf.clone();
+    otlp_conf.signal = OtlpSignal::Traces;
+    spawn_io_threads(
+        async_conf,
+        Arc::new(otlp_conf),
+        service_name,
+        scope_name,
+        receiver,
+        &stats,
+    );
+
+    OtlpSpanExporter::new(daemon_group, sender, stats)
+}
+
+fn spawn_io_threads(
+    async_conf: &AsyncLogConfig,
+    otlp_conf: Arc<OtlpLogClientConfig>,
+    service_name: String,
+    scope_name: String,
+    receiver: Receiver<OtlpLogRecord>,
+    stats: &Arc<LogStats>,
+) {
+    for i in 0..async_conf.thread_number {
+        let io_thread = AsyncIoThread {
+            config: Arc::clone(&otlp_conf),
+            service_name: service_name.clone(),
+            scope_name: scope_name.clone(),
+            receiver: receiver.clone(),
+            stats: Arc::clone(stats),
+            batch: Vec::with_capacity(otlp_conf.batch_size),
+            retry_queue: VecDeque::with_capacity(otlp_conf.retry_queue_len),
+            connection: None,
+            next_connect_time: Instant::now(),
+        };
+
+        let _detached_thread = std::thread::Builder::new()
+            .name(format!("{}#{i}", async_conf.thread_name))
+            .spawn(move || {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                rt.block_on(io_thread.run_to_end());
+            });
+    }
+}
+
+struct ExportRequest {
+    record_count: usize,
+    data: Bytes,
+}
+
+struct AsyncIoThread {
+    config: Arc<OtlpLogClientConfig>,
+    service_name: String,
+    scope_name: String,
+    receiver: Receiver<OtlpLogRecord>,
+    stats: Arc<LogStats>,
+    batch: Vec<OtlpLogRecord>,
+    retry_queue: VecDeque<ExportRequest>,
+    connection: Option<OtlpConnection>,
+    next_connect_time: Instant,
+}
+
+impl AsyncIoThread {
+    async fn run_to_end(mut self) {
+        let mut flush_interval = tokio::time::interval(self.config.flush_interval);
+
+        loop {
+            tokio::select! {
+                r = self.receiver.recv_async() => {
+                    match r {
+                        Ok(record) => {
+                            self.batch.push(record);
+                            if self.batch.len() >= self.config.batch_size {
+                                self.flush().await;
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                }
+                _ = flush_interval.tick() => {
+                    self.flush().await;
+                }
+            }
+        }
+
+        // try our best to send out the remaining logs
+        self.next_connect_time = Instant::now();
+        self.flush().await;
+    }
+
+    async fn flush(&mut self) {
+        if !self.batch.is_empty() {
+            let req = self.encode_request();
+            self.push_to_retry(req);
+        }
+
+        while let Some(req) = self.retry_queue.front() {
+            let data = req.data.clone();
+
+            if self.connection.is_none() {
+                if Instant::now() < self.next_connect_time {
+                    return;
+                }
+                match tokio::time::timeout(
+                    self.config.connect_timeout,
+                    self.config.new_connection(),
+                )
+                .await
+                {
+                    Ok(Ok(c)) => self.connection = Some(c),
+                    Ok(Err(e)) => {
+                        warn!("failed to connect to otlp server: {e:?}");
+                        self.next_connect_time = Instant::now() + self.config.connect_delay;
+                        return;
+                    }
+                    Err(_) => {
+                        warn!("timed out to connect to otlp server");
+                        self.next_connect_time = Instant::now() + self.config.connect_delay;
+                        return;
+                    }
+                }
+            }
+            let Some(connection) = &mut self.connection else {
+                return;
+            };
+
+            match tokio::time::timeout(
+                self.config.request_timeout,
+                connection.export(&self.config, data),
+            )
+            .await
+            {
+                Ok(Ok(_)) => {
+                    if !connection.reusable() {
+                        self.connection = None;
+                    }
+                    if let Some(req) = self.retry_queue.pop_front() {
+                        for _ in 0..req.record_count {
+                            self.stats.io.add_passed();
+                        }
+                        self.stats.io.add_size(req.data.len());
+                    }
+                }
+                Ok(Err(ExportError::Rejected(e))) => {
+                    warn!("otlp export request rejected: {e:?}");
+                    if !connection.reusable() {
+                        self.connection = None;
+                    }
+                    if let Some(req) = self.retry_queue.pop_front() {
+                        self.drop_request(req);
+                    }
+                }
+                Ok(Err(ExportError::Retryable(e))) => {
+                    warn!("otlp export request failed, will retry later: {e:?}");
+                    self.connection = None;
+                    self.next_connect_time = Instant::now() + self.config.connect_delay;
+                    return;
+                }
+                Err(_) => {
+                    warn!("otlp export request timed out, will retry later");
+                    self.connection = None;
+                    self.next_connect_time = Instant::now() + self.config.connect_delay;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn push_to_retry(&mut self, req: ExportRequest) {
+        self.retry_queue.push_back(req);
+        if self.retry_queue.len() > self.config.retry_queue_len {
+            if let Some(req) = self.retry_queue.pop_front() {
+                self.drop_request(req);
+            }
+        }
+    }
+
+    fn drop_request(&self, req: ExportRequest) {
+        for _ in 0..req.record_count {
+            self.stats.drop.add_peer_unreachable();
+        }
+    }
+
+    /// encode all records in the current batch as an ExportLogsServiceRequest message,
+    /// or an ExportTraceServiceRequest message, which has the same structure
+    fn encode_request(&mut self) -> ExportRequest {
+        let mut resource_map: BTreeMap<(Option<String>, Option<String>), Vec<OtlpLogRecord>> =
+            BTreeMap::new();
+        let record_count = self.batch.len();
+        for record in self.batch.drain(..) {
+            resource_map
+                .entry((record.daemon_group.clone(), record.server_name.clone()))
+                .or_default()
+                .push(record);
+        }
+
+        let mut buf = Vec::with_capacity(4096);
+        for ((daemon_group, server_name), records) in resource_map {
+            proto::encode_message_field(&mut buf, proto::EXPORT_REQUEST_RESOURCE_LOGS, |buf| {
+                proto::encode_message_field(buf, proto::RESOURCE_LOGS_RESOURCE, |buf| {
+                    self.encode_resource_attributes(
+                        buf,
+                        daemon_group.as_deref(),
+                        server_name.as_deref(),
+                    );
+                });
+                proto::encode_message_field(buf, proto::RESOURCE_LOGS_SCOPE_LOGS, |buf| {
+                    proto::encode_message_field(buf, proto::SCOPE_LOGS_SCOPE, |buf| {
+                        proto::encode_string_field(buf, proto::SCOPE_NAME, &self.scope_name);
+                        proto::encode_string_field(
+                            buf,
+                            proto::SCOPE_VERSION,
+                            env!("CARGO_PKG_VERSION"),
+                        );
+                    });
+                    for record in records {
+                        proto::encode_bytes_field(buf, proto::SCOPE_LOGS_LOG_RECORDS, &record.data);
+                    }
+                });
+            });
+        }
+
+        ExportRequest {
+            record_count,
+            data: Bytes::from(buf),
+        }
+    }
+
+    fn encode_resource_attributes(
+        &self,
+        buf: &mut Vec<u8>,
+        daemon_group: Option<&str>,
+        server_name: Option<&str>,
+    ) {
+        proto::encode_key_value_field(
+            buf,
+            proto::RESOURCE_ATTRIBUTES,
+            RESOURCE_KEY_SERVICE_NAME,
+            AnyValue::String(&self.service_name),
+        );
+        if !self.config.hostname.is_empty() {
+            proto::encode_key_value_field(
+                buf,
+                proto::RESOURCE_ATTRIBUTES,
+                RESOURCE_KEY_HOST_NAME,
+                AnyValue::String(&self.config.hostname),
+            );
+        }
+        if let Some(daemon_group) = daemon_group {
+            proto::encode_key_value_field(
+                buf,
+                proto::RESOURCE_ATTRIBUTES,
+                RESOURCE_KEY_DAEMON_GROUP,
+                AnyValue::String(daemon_group),
+            );
+        }
+        if let Some(server_name) = server_name {
+            proto::encode_key_value_field(
+                buf,
+                proto::RESOURCE_ATTRIBUTES,
+                RESOURCE_KEY_SERVER_NAME,
+                AnyValue::String(server_name),
+            );
+        }
+        for (k, v) in &self.config.resource_attributes {
+            proto::encode_key_value_field(buf, proto::RESOURCE_ATTRIBUTES, k, AnyValue::String(v));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    fn new_io_thread(server_addr: SocketAddr) -> (AsyncIoThread, flume::Sender<OtlpLogRecord>) {
+        let mut config = OtlpLogClientConfig::new(OtlpExportProtocol::HttpProtobuf);
+        config.set_server_addr(server_addr);
+        config.set_connect_delay(Duration::ZERO);
+        let (sender, receiver) = flume::bounded(16);
+        let io_thread = AsyncIoThread {
+            config: Arc::new(config),
+            service_name: "test".to_string(),
+            scope_name: "test".to_string(),
+            receiver,
+            stats: Arc::new(LogStats::default()),
+            batch: Vec::new(),
+            retry_queue: VecDeque::new(),
+            connection: None,
+            next_connect_time: Instant::now(),
+        };
+        (io_thread, sender)
+    }
+
+    fn new_record(data: &[u8]) -> OtlpLogRecord {
+        OtlpLogRecord {
+            daemon_group: None,
+            server_name: None,
+            data: data.to_vec(),
+        }
+    }
+
+    fn contains(data: &[u8], s: &[u8]) -> bool {
+        data.windows(s.len()).any(|w| w == s)
+    }
+
+    /// read one request from the client, return None if the connection is closed
+    async fn recv_request(stream: &mut BufReader<TcpStream>) -> Option<Vec<u8>> {
+        let mut body_len = 0;
+        loop {
+            let mut line = String::new();
+            if stream.read_line(&mut line).await.unwrap() == 0 {
+                return None;
+            }
+            if line == "\r\n" {
+                break;
+            }
+            if let Some(v) = line.strip_prefix("Content-Length: ") {
+                body_len = v.trim_end().parse().unwrap();
+            }
+        }
+        let mut body = vec![0u8; body_len];
+        stream.read_exact(&mut body).await.unwrap();
+        Some(body)
+    }
+
+    /// a stand-in otlp http receiver, which responds with the given status codes in turn,
+    /// and returns the received request bodies
+    async fn serve_http(listener: TcpListener, status_list: Vec<u16>) -> Vec<Vec<u8>> {
+        let mut bodies = Vec::new();
+        let mut connection: Option<BufReader<TcpStream>> = None;
+        let mut status_iter = status_list.into_iter();
+        let mut status = status_iter.next();
+        while let Some(code) = status {
+            if connection.is_none() {
+                let (s, _) = listener.accept().await.unwrap();
+                connection = Some(BufReader::new(s));
+            }
+            let stream = connection.as_mut().unwrap();
+            let Some(body) = recv_request(stream).await else {
+                connection = None;
+                continue;
+            };
+            stream
+                .get_mut()
+                .write_all(format!("HTTP/1.1 {code} Test\r\nContent-Length: 0\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            bodies.push(body);
+            status = status_iter.next();
+        }
+        bodies
+    }
+
+    async fn local_listener() -> (TcpListener, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        (listener, addr)
+    }
+
+    #[tokio::test]
+    async fn flush_batch() {
+        let (listener, addr) = local_listener().await;
+        let server_task = tokio::spawn(serve_http(listener, vec![200]));
+
+        let (mut io_thread, _sender) = new_io_thread(addr);
+        io_thread.batch.push(new_record(b"record-1"));
+        io_thread.batch.push(new_record(b"record-2"));
+        io_thread.batch.push(new_record(b"record-3"));
+        io_thread.flush().await;
+        assert!(io_thread.batch.is_empty());
+        assert!(io_thread.retry_queue.is_empty());
+
+        let bodies = server_task.await.unwrap();
+        assert_eq!(bodies.len(), 1);
+        assert!(contains(&bodies[0], b"record-1"));
+        assert!(contains(&bodies[0], b"record-3"));
+        let snapshot = io_thread.stats.snapshot();
+        assert_eq!(snapshot.io.passed, 3);
+        assert_eq!(snapshot.io.size, bodies[0].len() as u64);
+    }
+
+    #[tokio::test]
+    async fn batch_by_size() {
+        let (listener, addr) = local_listener().await;
+        let server_task = tokio::spawn(serve_http(listener, vec![200, 200, 200]));
+
+        let (mut io_thread, sender) = new_io_thread(addr);
+        let mut config = OtlpLogClientConfig::clone(&io_thread.config);
+        config.set_batch_size(2);
+        config.set_flush_interval(Duration::from_secs(3600));
+        io_thread.config = Arc::new(config);
+        let stats = io_thread.stats.clone();
+
+        for i in 0..5 {
+            sender
+                .send(new_record(format!("record-{i}").as_bytes()))
+                .unwrap();
+        }
+        drop(sender);
+        // the remaining record will be sent out when the channel is closed
+        io_thread.run_to_end().await;
+
+        let bodies = server_task.await.unwrap();
+        assert_eq!(bodies.len(), 3);
+        assert!(contains(&bodies[0], b"record-1"));
+        assert!(contains(&bodies[1], b"record-3"));
+        assert!(contains(&bodies[2], b"record-4"));
+        assert_eq!(stats.snapshot().io.passed, 5);
+    }
+
+    #[tokio::test]
+    async fn retry_later() {
+        let (listener, addr) = local_listener().await;
+        let server_task = tokio::spawn(serve_http(listener, vec![503, 200]));
+
+        let (mut io_thread, _sender) = new_io_thread(addr);
+        io_thread.batch.push(new_record(b"record-1"));
+        io_thread.batch.push(new_record(b"record-2"));
+
+        io_thread.flush().await;
+        assert_eq!(io_thread.retry_queue.len(), 1);
+        assert!(io_thread.connection.is_none());
+        assert_eq!(io_thread.stats.snapshot().io.passed, 0);
+
+        io_thread.flush().await;
+        assert!(io_thread.retry_queue.is_empty());
+        let snapshot = io_thread.stats.snapshot();
+        assert_eq!(snapshot.io.passed, 2);
+        assert_eq!(snapshot.drop.peer_unreachable, 0);
+
+        let bodies = server_task.await.unwrap();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0], bodies[1]);
+    }
+
+    #[tokio::test]
+    async fn drop_rejected() {
+        let (listener, addr) = local_listener().await;
+        let server_task = tokio::spawn(serve_http(listener, vec![400]));
+
+        let (mut io_thread, _sender) = new_io_thread(addr);
+        io_thread.batch.push(new_record(b"record-1"));
+        io_thread.batch.push(new_record(b"record-2"));
+        io_thread.flush().await;
+        assert!(io_thread.retry_queue.is_empty());
+        let snapshot = io_thread.stats.snapshot();
+        assert_eq!(snapshot.io.passed, 0);
+        assert_eq!(snapshot.drop.peer_unreachable, 2);
+
+        assert_eq!(server_task.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bounded_retry_queue() {
+        let (listener, addr) = local_listener().await;
+        // close the listener to make the server unreachable
+        drop(listener);
+
+        let (mut io_thread, _sender) = new_io_thread(addr);
+        let mut config = OtlpLogClientConfig::clone(&io_thread.config);
+        config.set_retry_queue_len(2);
+        io_thread.config = Arc::new(config);
+
+        for n in 1..=4 {
+            for _ in 0..n {
+                io_thread.batch.push(new_record(b"record"));
+            }
+            io_thread.flush().await;
+        }
+
+        let queued: Vec<usize> = io_thread
+            .retry_queue
+            .iter()
+            .map(|req| req.record_count)
+            .collect();
+        assert_eq!(queued, [3, 4]);
+        let snapshot = io_thread.stats.snapshot();
+        assert_eq!(snapshot.io.passed, 0);
+        assert_eq!(snapshot.drop.peer_unreachable, 1 + 2);
+    }
+}
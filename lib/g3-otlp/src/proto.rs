@@ -0,0 +1,201 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Minimal protobuf encoder for the OTLP logs and traces data model.
+//!
+//! See opentelemetry/proto/logs/v1/logs.proto, opentelemetry/proto/trace/v1/trace.proto and
+//! the service definitions in opentelemetry/proto/collector/.
+//!
+//! The ResourceSpans / ScopeSpans messages share the same field numbers as the
+//! ResourceLogs / ScopeLogs messages, so the same constants are used for both.
+
+const WIRE_TYPE_VARINT: u32 = 0;
+const WIRE_TYPE_FIXED64: u32 = 1;
+const WIRE_TYPE_LEN: u32 = 2;
+
+// ExportLogsServiceRequest
+pub(crate) const EXPORT_REQUEST_RESOURCE_LOGS: u32 = 1;
+
+// ResourceLogs
+pub(crate) const RESOURCE_LOGS_RESOURCE: u32 = 1;
+pub(crate) const RESOURCE_LOGS_SCOPE_LOGS: u32 = 2;
+
+// Resource
+pub(crate) const RESOURCE_ATTRIBUTES: u32 = 1;
+
+// ScopeLogs
+pub(crate) const SCOPE_LOGS_SCOPE: u32 = 1;
+pub(crate) const SCOPE_LOGS_LOG_RECORDS: u32 = 2;
+
+// InstrumentationScope
+pub(crate) const SCOPE_NAME: u32 = 1;
+pub(crate) const SCOPE_VERSION: u32 = 2;
+
+// LogRecord
+pub(crate) const LOG_RECORD_TIME_UNIX_NANO: u32 = 1;
+pub(crate) const LOG_RECORD_SEVERITY_NUMBER: u32 = 2;
+pub(crate) const LOG_RECORD_SEVERITY_TEXT: u32 = 3;
+pub(crate) const LOG_RECORD_BODY: u32 = 5;
+pub(crate) const LOG_RECORD_ATTRIBUTES: u32 = 6;
+pub(crate) const LOG_RECORD_TRACE_ID: u32 = 9;
+pub(crate) const LOG_RECORD_SPAN_ID: u32 = 10;
+pub(crate) const LOG_RECORD_OBSERVED_TIME_UNIX_NANO: u32 = 11;
+
+// Span
+pub(crate) const SPAN_TRACE_ID: u32 = 1;
+pub(crate) const SPAN_SPAN_ID: u32 = 2;
+pub(crate) const SPAN_TRACE_STATE: u32 = 3;
+pub(crate) const SPAN_PARENT_SPAN_ID: u32 = 4;
+pub(crate) const SPAN_NAME: u32 = 5;
+pub(crate) const SPAN_KIND: u32 = 6;
+pub(crate) const SPAN_START_TIME_UNIX_NANO: u32 = 7;
+pub(crate) const SPAN_END_TIME_UNIX_NANO: u32 = 8;
+pub(crate) const SPAN_ATTRIBUTES: u32 = 9;
+pub(crate) const SPAN_STATUS: u32 = 15;
+
+// Status
+pub(crate) const STATUS_MESSAGE: u32 = 2;
+pub(crate) const STATUS_CODE: u32 = 3;
+
+// KeyValue
+const KEY_VALUE_KEY: u32 = 1;
+const KEY_VALUE_VALUE: u32 = 2;
+
+// AnyValue
+const ANY_VALUE_STRING: u32 = 1;
+const ANY_VALUE_BOOL: u32 = 2;
+const ANY_VALUE_INT: u32 = 3;
+const ANY_VALUE_DOUBLE: u32 = 4;
+const ANY_VALUE_BYTES: u32 = 7;
+
+pub(crate) enum AnyValue<'a> {
+    String(&'a str),
+    Bool(bool),
+    Int(i64),
+    Double(f64),
+    Bytes(&'a [u8]),
+}
+
+pub(crate) fn encode_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+fn encode_tag(buf: &mut Vec<u8>, field: u32, wire_type: u32) {
+    encode_varint(buf, ((field << 3) | wire_type) as u64);
+}
+
+pub(crate) fn encode_varint_field(buf: &mut Vec<u8>, field: u32, v: u64) {
+    encode_tag(buf, field, WIRE_TYPE_VARINT);
+    encode_varint(buf, v);
+}
+
+pub(crate) fn encode_fixed64_field(buf: &mut Vec<u8>, field: u32, v: u64) {
+    encode_tag(buf, field, WIRE_TYPE_FIXED64);
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub(crate) fn encode_bytes_field(buf: &mut Vec<u8>, field: u32, data: &[u8]) {
+    encode_tag(buf, field, WIRE_TYPE_LEN);
+    encode_varint(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+pub(crate) fn encode_string_field(buf: &mut Vec<u8>, field: u32, s: &str) {
+    encode_bytes_field(buf, field, s.as_bytes());
+}
+
+/// encode a nested message, the message body will be filled by `f`
+pub(crate) fn encode_message_field<F>(buf: &mut Vec<u8>, field: u32, f: F)
+where
+    F: FnOnce(&mut Vec<u8>),
+{
+    let mut msg = Vec::with_capacity(64);
+    f(&mut msg);
+    encode_bytes_field(buf, field, &msg);
+}
+
+pub(crate) fn encode_any_value_field(buf: &mut Vec<u8>, field: u32, value: AnyValue) {
+    encode_message_field(buf, field, |buf| match value {
+        AnyValue::String(s) => encode_string_field(buf, ANY_VALUE_STRING, s),
+        AnyValue::Bool(b) => encode_varint_field(buf, ANY_VALUE_BOOL, b as u64),
+        AnyValue::Int(i) => encode_varint_field(buf, ANY_VALUE_INT, i as u64),
+        AnyValue::Double(f) => {
+            encode_tag(buf, ANY_VALUE_DOUBLE, WIRE_TYPE_FIXED64);
+            buf.extend_from_slice(&f.to_bits().to_le_bytes());
+        }
+        AnyValue::Bytes(b) => encode_bytes_field(buf, ANY_VALUE_BYTES, b),
+    });
+}
+
+pub(crate) fn encode_key_value_field(buf: &mut Vec<u8>, field: u32, key: &str, value: AnyValue) {
+    encode_message_field(buf, field, |buf| {
+        encode_string_field(buf, KEY_VALUE_KEY, key);
+        encode_any_value_field(buf, KEY_VALUE_VALUE, value);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint() {
+        let mut buf = Vec::new();
+        encode_varint(&mut buf, 1);
+        assert_eq!(buf, [0x01]);
+
+        buf.clear();
+        encode_varint(&mut buf, 300);
+        assert_eq!(buf, [0xac, 0x02]);
+
+        buf.clear();
+        encode_varint(&mut buf, -1i64 as u64);
+        assert_eq!(buf.len(), 10);
+        assert_eq!(buf[9], 0x01);
+    }
+
+    #[test]
+    fn key_value() {
+        let mut buf = Vec::new();
+        encode_key_value_field(&mut buf, 1, "a", AnyValue::String("b"));
+        assert_eq!(
+            buf,
+            [0x0a, 0x08, 0x0a, 0x01, b'a', 0x12, 0x03, 0x0a, 0x01, b'b']
+        );
+
+        buf.clear();
+        encode_key_value_field(&mut buf, 6, "n", AnyValue::Int(150));
+        assert_eq!(
+            buf,
+            [0x32, 0x08, 0x0a, 0x01, b'n', 0x12, 0x03, 0x18, 0x96, 0x01]
+        );
+
+        buf.clear();
+        encode_key_value_field(&mut buf, 6, "t", AnyValue::Bool(false));
+        assert_eq!(buf, [0x32, 0x07, 0x0a, 0x01, b't', 0x12, 0x02, 0x10, 0x00]);
+    }
+
+    #[test]
+    fn fixed64() {
+        let mut buf = Vec::new();
+        encode_fixed64_field(&mut buf, 1, 1);
+        assert_eq!(buf, [0x09, 1, 0, 0, 0, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,245 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flume::{Sender, TrySendError};
+
+use g3_types::log::LogStats;
+
+use super::proto::{self, AnyValue};
+use super::OtlpLogRecord;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OtlpSpanKind {
+    Internal,
+    Server,
+    Client,
+}
+
+impl OtlpSpanKind {
+    fn code(&self) -> u64 {
+        match self {
+            OtlpSpanKind::Internal => 1,
+            OtlpSpanKind::Server => 2,
+            OtlpSpanKind::Client => 3,
+        }
+    }
+}
+
+pub enum OtlpSpanAttributeValue {
+    String(String),
+    Bool(bool),
+    Int(i64),
+}
+
+/// A finished span, which will be encoded as a `Span` message
+pub struct OtlpSpan {
+    server_name: Option<String>,
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    trace_state: Option<String>,
+    name: String,
+    kind: OtlpSpanKind,
+    start_time: SystemTime,
+    end_time: SystemTime,
+    attributes: Vec<(&'static str, OtlpSpanAttributeValue)>,
+    error: Option<String>,
+}
+
+impl OtlpSpan {
+    pub fn new(
+        trace_id: [u8; 16],
+        span_id: [u8; 8],
+        name: String,
+        start_time: SystemTime,
+        end_time: SystemTime,
+    ) -> Self {
+        OtlpSpan {
+            server_name: None,
+            trace_id,
+            span_id,
+            parent_span_id: None,
+            trace_state: None,
+            name,
+            kind: OtlpSpanKind::Internal,
+            start_time,
+            end_time,
+            attributes: Vec::new(),
+            error: None,
+        }
+    }
+
+    pub fn set_server_name(&mut self, name: String) {
+        self.server_name = Some(name);
+    }
+
+    pub fn set_parent_span_id(&mut self, id: [u8; 8]) {
+        self.parent_span_id = Some(id);
+    }
+
+    pub fn set_trace_state(&mut self, state: String) {
+        self.trace_state = Some(state);
+    }
+
+    pub fn set_kind(&mut self, kind: OtlpSpanKind) {
+        self.kind = kind;
+    }
+
+    pub fn add_attribute(&mut self, key: &'static str, value: OtlpSpanAttributeValue) {
+        self.attributes.push((key, value));
+    }
+
+    /// mark the span status as error
+    pub fn set_error(&mut self, msg: String) {
+        self.error = Some(msg);
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(256);
+        proto::encode_bytes_field(&mut buf, proto::SPAN_TRACE_ID, &self.trace_id);
+        proto::encode_bytes_field(&mut buf, proto::SPAN_SPAN_ID, &self.span_id);
+        if let Some(state) = &self.trace_state {
+            proto::encode_string_field(&mut buf, proto::SPAN_TRACE_STATE, state);
+        }
+        if let Some(id) = &self.parent_span_id {
+            proto::encode_bytes_field(&mut buf, proto::SPAN_PARENT_SPAN_ID, id);
+        }
+        proto::encode_string_field(&mut buf, proto::SPAN_NAME, &self.name);
+        proto::encode_varint_field(&mut buf, proto::SPAN_KIND, self.kind.code());
+        proto::encode_fixed64_field(
+            &mut buf,
+            proto::SPAN_START_TIME_UNIX_NANO,
+            unix_nanos(self.start_time),
+        );
+        proto::encode_fixed64_field(
+            &mut buf,
+            proto::SPAN_END_TIME_UNIX_NANO,
+            unix_nanos(self.end_time),
+        );
+        for (key, value) in &self.attributes {
+            let value = match value {
+                OtlpSpanAttributeValue::String(s) => AnyValue::String(s),
+                OtlpSpanAttributeValue::Bool(b) => AnyValue::Bool(*b),
+                OtlpSpanAttributeValue::Int(i) => AnyValue::Int(*i),
+            };
+            proto::encode_key_value_field(&mut buf, proto::SPAN_ATTRIBUTES, key, value);
+        }
+        if let Some(msg) = &self.error {
+            proto::encode_message_field(&mut buf, proto::SPAN_STATUS, |buf| {
+                proto::encode_string_field(buf, proto::STATUS_MESSAGE, msg);
+                // STATUS_CODE_ERROR
+                proto::encode_varint_field(buf, proto::STATUS_CODE, 2);
+            });
+        }
+        buf
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+pub struct OtlpSpanExporter {
+    daemon_group: Option<String>,
+    sender: Sender<OtlpLogRecord>,
+    stats: Arc<LogStats>,
+}
+
+impl OtlpSpanExporter {
+    pub(crate) fn new(
+        daemon_group: Option<String>,
+        sender: Sender<OtlpLogRecord>,
+        stats: Arc<LogStats>,
+    ) -> Self {
+        OtlpSpanExporter {
+            daemon_group,
+            sender,
+            stats,
+        }
+    }
+
+    pub fn get_stats(&self) -> Arc<LogStats> {
+        self.stats.clone()
+    }
+
+    pub fn export(&self, span: OtlpSpan) {
+        self.stats.io.add_total();
+
+        let record = OtlpLogRecord {
+            daemon_group: self.daemon_group.clone(),
+            data: span.encode(),
+            server_name: span.server_name,
+        };
+        match self.sender.try_send(record) {
+            Ok(_) => {}
+            Err(TrySendError::Full(_)) => self.stats.drop.add_channel_overflow(),
+            Err(TrySendError::Disconnected(_)) => self.stats.drop.add_channel_closed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn encode_span() {
+        let start = UNIX_EPOCH + Duration::from_secs(1);
+        let mut span = OtlpSpan::new(
+            [1; 16],
+            [2; 8],
+            "connect".to_string(),
+            start,
+            start + Duration::from_millis(1),
+        );
+        span.set_parent_span_id([3; 8]);
+        span.set_error("refused".to_string());
+
+        let data = span.encode();
+        // trace_id
+        assert_eq!(&data[0..2], &[0x0a, 16]);
+        assert_eq!(&data[2..18], &[1; 16]);
+        // span_id
+        assert_eq!(&data[18..20], &[0x12, 8]);
+        assert_eq!(&data[20..28], &[2; 8]);
+        // parent_span_id
+        assert_eq!(&data[28..30], &[0x22, 8]);
+        assert_eq!(&data[30..38], &[3; 8]);
+        // name
+        assert_eq!(&data[38..40], &[0x2a, 7]);
+        assert_eq!(&data[40..47], b"connect");
+        // kind
+        assert_eq!(&data[47..49], &[0x30, 1]);
+        // start time
+        assert_eq!(data[49], 0x39);
+        assert_eq!(&data[50..58], &1_000_000_000u64.to_le_bytes());
+        // end time
+        assert_eq!(data[58], 0x41);
+        assert_eq!(&data[59..67], &1_001_000_000u64.to_le_bytes());
+        // status
+        assert_eq!(&data[67..69], &[0x7a, 11]);
+        assert_eq!(&data[69..71], &[0x12, 7]);
+        assert_eq!(&data[71..78], b"refused");
+        assert_eq!(&data[78..80], &[0x18, 2]);
+        assert_eq!(data.len(), 80);
+    }
+}
@@ -14,8 +14,13 @@
  * limitations under the License.
  */
 
+use std::path::PathBuf;
 use std::time::Duration;
 
+use anyhow::anyhow;
+
+use g3_types::net::Nat64Prefix;
+
 use super::AnyResolveDriverConfig;
 
 pub(crate) const RESOLVER_MINIMUM_CACHE_TTL: u32 = 30;
@@ -26,6 +31,7 @@ const RESOLVER_CACHE_INITIAL_CAPACITY: usize = 10;
 const RESOLVER_BATCH_REQUEST_COUNT: usize = 10;
 const RESOLVER_PROTECTIVE_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
 const RESOLVER_GRACEFUL_STOP_WAIT: Duration = Duration::from_secs(30);
+const RESOLVER_CACHE_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(300);
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct ResolverRuntimeConfig {
@@ -33,6 +39,10 @@ pub struct ResolverRuntimeConfig {
     pub batch_request_count: usize,
     pub protective_query_timeout: Duration,
     pub graceful_stop_wait: Duration,
+    pub dns64_prefix: Option<Nat64Prefix>,
+    pub cache_snapshot_file: Option<PathBuf>,
+    pub cache_snapshot_interval: Duration,
+    pub serve_stale_period: Duration,
 }
 
 impl Default for ResolverRuntimeConfig {
@@ -42,7 +52,20 @@ impl Default for ResolverRuntimeConfig {
             batch_request_count: RESOLVER_BATCH_REQUEST_COUNT,
             protective_query_timeout: RESOLVER_PROTECTIVE_QUERY_TIMEOUT,
             graceful_stop_wait: RESOLVER_GRACEFUL_STOP_WAIT,
+            dns64_prefix: None,
+            cache_snapshot_file: None,
+            cache_snapshot_interval: RESOLVER_CACHE_SNAPSHOT_INTERVAL,
+            serve_stale_period: Duration::ZERO,
+        }
+    }
+}
+
+impl ResolverRuntimeConfig {
+    pub fn check(&self) -> anyhow::Result<()> {
+        if self.cache_snapshot_interval.is_zero() {
+            return Err(anyhow!("cache snapshot interval should not be zero"));
         }
+        Ok(())
     }
 }
 
@@ -0,0 +1,184 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+
+use ahash::AHashMap;
+
+use super::DomainRouteResolver;
+use crate::{BoxResolverDriver, ResolverHandle};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DomainRouteDriverStaticConfig {
+    exact_match: AHashMap<String, String>,
+    child_match: AHashMap<String, String>,
+    default_next: String,
+    pub(crate) negative_ttl: u32,
+}
+
+impl Default for DomainRouteDriverStaticConfig {
+    fn default() -> Self {
+        DomainRouteDriverStaticConfig {
+            exact_match: AHashMap::new(),
+            child_match: AHashMap::new(),
+            default_next: String::new(),
+            negative_ttl: crate::config::RESOLVER_MINIMUM_CACHE_TTL,
+        }
+    }
+}
+
+impl DomainRouteDriverStaticConfig {
+    pub fn add_exact_match(&mut self, domain: &str, next: String) -> Option<String> {
+        self.exact_match.insert(normalize_domain(domain), next)
+    }
+
+    /// the parent domain itself will also be matched.
+    /// The domain can be written as `*.example.net` or `.example.net`
+    pub fn add_child_match(&mut self, domain: &str, next: String) -> Option<String> {
+        let domain = domain
+            .strip_prefix("*.")
+            .or_else(|| domain.strip_prefix('.'))
+            .unwrap_or(domain);
+        self.child_match.insert(normalize_domain(domain), next)
+    }
+
+    pub fn set_default_next(&mut self, next: String) {
+        self.default_next = next;
+    }
+
+    pub fn set_negative_ttl(&mut self, ttl: u32) {
+        self.negative_ttl = ttl;
+    }
+
+    pub fn select_next(&self, domain: &str) -> &str {
+        let domain = normalize_domain(domain);
+
+        if let Some(next) = self.exact_match.get(&domain) {
+            return next;
+        }
+
+        if !self.child_match.is_empty() {
+            let mut parent = domain.as_str();
+            loop {
+                if let Some(next) = self.child_match.get(parent) {
+                    return next;
+                }
+                match parent.split_once('.') {
+                    Some((_, p)) => parent = p,
+                    None => break,
+                }
+            }
+        }
+
+        &self.default_next
+    }
+}
+
+fn normalize_domain(domain: &str) -> String {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    domain.to_ascii_lowercase()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DomainRouteDriverConfig {
+    next_handles: BTreeMap<String, Option<ResolverHandle>>,
+    static_config: DomainRouteDriverStaticConfig,
+}
+
+impl DomainRouteDriverConfig {
+    pub fn set_next_handle(&mut self, name: &str, handle: Option<ResolverHandle>) {
+        self.next_handles.insert(name.to_string(), handle);
+    }
+
+    pub fn set_static_config(&mut self, conf: DomainRouteDriverStaticConfig) {
+        self.static_config = conf;
+    }
+
+    pub(crate) fn spawn_resolver_driver(&self) -> BoxResolverDriver {
+        Box::new(DomainRouteResolver {
+            next_handles: self.next_handles.clone(),
+            conf: self.static_config.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn static_config() -> DomainRouteDriverStaticConfig {
+        let mut conf = DomainRouteDriverStaticConfig::default();
+        assert!(conf
+            .add_exact_match("www.corp.example", "exact".to_string())
+            .is_none());
+        assert!(conf
+            .add_child_match("corp.example", "corp".to_string())
+            .is_none());
+        assert!(conf
+            .add_child_match("*.dev.corp.example", "dev".to_string())
+            .is_none());
+        assert!(conf.add_child_match(".lan", "lan".to_string()).is_none());
+        conf.set_default_next("default".to_string());
+        conf
+    }
+
+    #[test]
+    fn select_exact() {
+        let conf = static_config();
+        assert_eq!(conf.select_next("www.corp.example"), "exact");
+        assert_eq!(conf.select_next("WWW.Corp.Example."), "exact");
+        // the child of an exact domain is not matched by the exact rule
+        assert_eq!(conf.select_next("a.www.corp.example"), "corp");
+    }
+
+    #[test]
+    fn select_child() {
+        let conf = static_config();
+        assert_eq!(conf.select_next("corp.example"), "corp");
+        assert_eq!(conf.select_next("mail.corp.example"), "corp");
+        // the longest parent domain wins
+        assert_eq!(conf.select_next("dev.corp.example"), "dev");
+        assert_eq!(conf.select_next("a.b.dev.corp.example"), "dev");
+        assert_eq!(conf.select_next("host.lan"), "lan");
+        assert_eq!(conf.select_next("lan"), "lan");
+    }
+
+    #[test]
+    fn select_default() {
+        let conf = static_config();
+        assert_eq!(conf.select_next("example"), "default");
+        assert_eq!(conf.select_next("corp.example.net"), "default");
+        assert_eq!(conf.select_next("xcorp.example"), "default");
+        assert_eq!(conf.select_next("plan"), "default");
+    }
+
+    #[test]
+    fn duplicate() {
+        let mut conf = static_config();
+        assert_eq!(
+            conf.add_exact_match("www.corp.example.", "other".to_string()),
+            Some("exact".to_string())
+        );
+        assert_eq!(
+            conf.add_child_match("*.Corp.Example", "other".to_string()),
+            Some("corp".to_string())
+        );
+        assert_eq!(
+            conf.add_child_match("dev.corp.example", "other".to_string()),
+            Some("dev".to_string())
+        );
+    }
+}
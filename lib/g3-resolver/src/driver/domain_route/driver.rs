@@ -0,0 +1,101 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use super::DomainRouteDriverStaticConfig;
+use crate::config::ResolverRuntimeConfig;
+use crate::message::ResolveDriverResponse;
+use crate::{ResolveDriver, ResolveJob, ResolveLocalError, ResolvedRecord, ResolverHandle};
+
+pub(super) struct DomainRouteResolver {
+    pub(super) next_handles: BTreeMap<String, Option<ResolverHandle>>,
+    pub(super) conf: DomainRouteDriverStaticConfig,
+}
+
+impl DomainRouteResolver {
+    fn select_handle(&self, domain: &str) -> Option<&ResolverHandle> {
+        let next = self.conf.select_next(domain);
+        self.next_handles.get(next).and_then(|h| h.as_ref())
+    }
+}
+
+struct DomainRouteResolverJob {
+    inner: Option<ResolveJob>,
+    job_timeout: Duration,
+    negative_ttl: u32,
+}
+
+impl DomainRouteResolverJob {
+    async fn resolve(mut self, domain: String) -> ResolvedRecord {
+        let Some(mut job) = self.inner.take() else {
+            return ResolvedRecord::failed(
+                domain,
+                self.negative_ttl,
+                ResolveLocalError::NoResolverRunning.into(),
+            );
+        };
+
+        match tokio::time::timeout(self.job_timeout, job.recv()).await {
+            Ok(Ok((r, _))) => r.as_ref().clone(),
+            Ok(Err(e)) => ResolvedRecord::failed(domain, self.negative_ttl, e.into()),
+            Err(_) => ResolvedRecord::timed_out(domain, self.negative_ttl),
+        }
+    }
+}
+
+impl ResolveDriver for DomainRouteResolver {
+    fn query_v4(
+        &self,
+        domain: String,
+        config: &ResolverRuntimeConfig,
+        sender: mpsc::UnboundedSender<ResolveDriverResponse>,
+    ) {
+        let job = DomainRouteResolverJob {
+            inner: self
+                .select_handle(&domain)
+                .and_then(|handle| handle.get_v4(domain.clone()).ok()),
+            job_timeout: config.protective_query_timeout,
+            negative_ttl: self.conf.negative_ttl,
+        };
+        tokio::spawn(async move {
+            let record = job.resolve(domain).await;
+            let _ = sender.send(ResolveDriverResponse::V4(record));
+        });
+    }
+
+    fn query_v6(
+        &self,
+        domain: String,
+        config: &ResolverRuntimeConfig,
+        sender: mpsc::UnboundedSender<ResolveDriverResponse>,
+    ) {
+        let job = DomainRouteResolverJob {
+            inner: self
+                .select_handle(&domain)
+                .and_then(|handle| handle.get_v6(domain.clone()).ok()),
+            job_timeout: config.protective_query_timeout,
+            negative_ttl: self.conf.negative_ttl,
+        };
+        tokio::spawn(async move {
+            let record = job.resolve(domain).await;
+            let _ = sender.send(ResolveDriverResponse::V6(record));
+        });
+    }
+}
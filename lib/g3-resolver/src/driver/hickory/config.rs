@@ -16,17 +16,20 @@
 
 use std::convert::TryFrom;
 use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::{anyhow, Context};
+use hickory_proto::rr::dnssec::TrustAnchor;
 use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
 use hickory_resolver::TokioAsyncResolver;
 use rustls::ServerName;
 
 use g3_types::net::{DnsEncryptionConfigBuilder, DnsEncryptionProtocol};
 
-use super::HickoryResolver;
+use super::dnssec::HickoryDnssecClient;
+use super::{DnssecInsecureStrategy, HickoryResolver};
 use crate::BoxResolverDriver;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -41,6 +44,9 @@ pub struct HickoryDriverConfig {
     server_port: Option<u16>,
     bind_ip: Option<IpAddr>,
     encryption: Option<DnsEncryptionConfigBuilder>,
+    dnssec: bool,
+    dnssec_trust_anchor: Option<PathBuf>,
+    dnssec_insecure_strategy: DnssecInsecureStrategy,
 }
 
 impl Default for HickoryDriverConfig {
@@ -56,6 +62,9 @@ impl Default for HickoryDriverConfig {
             server_port: None,
             bind_ip: None,
             encryption: None,
+            dnssec: false,
+            dnssec_trust_anchor: None,
+            dnssec_insecure_strategy: DnssecInsecureStrategy::default(),
         }
     }
 }
@@ -197,19 +206,68 @@ impl HickoryDriverConfig {
         self.negative_max_ttl = ttl;
     }
 
+    #[inline]
+    pub fn get_negative_min_ttl(&self) -> u32 {
+        self.negative_min_ttl
+    }
+
+    pub fn set_dnssec(&mut self, enable: bool) {
+        self.dnssec = enable;
+    }
+
+    pub fn set_dnssec_trust_anchor(&mut self, path: PathBuf) {
+        self.dnssec_trust_anchor = Some(path);
+    }
+
+    #[inline]
+    pub fn get_dnssec_trust_anchor(&self) -> Option<&Path> {
+        self.dnssec_trust_anchor.as_deref()
+    }
+
+    pub fn set_dnssec_insecure_strategy(&mut self, strategy: DnssecInsecureStrategy) {
+        self.dnssec_insecure_strategy = strategy;
+    }
+
     pub fn is_unspecified(&self) -> bool {
         self.servers.is_empty()
     }
 
-    pub(crate) fn spawn_resolver_driver(&self) -> anyhow::Result<BoxResolverDriver> {
+    pub fn build_async_resolver(&self) -> anyhow::Result<TokioAsyncResolver> {
         let name_servers = NameServerConfigGroup::try_from(self)?;
         let d_config = ResolverConfig::from_parts(None, vec![], name_servers);
         let d_opts = ResolverOpts::from(self);
 
-        let d_resolver = TokioAsyncResolver::tokio(d_config, d_opts);
+        Ok(TokioAsyncResolver::tokio(d_config, d_opts))
+    }
+
+    fn build_dnssec_client(&self) -> anyhow::Result<HickoryDnssecClient> {
+        let trust_anchor = match &self.dnssec_trust_anchor {
+            Some(path) => super::dnssec::load_trust_anchor(path)
+                .context("failed to load dnssec trust anchor")?,
+            None => TrustAnchor::default(),
+        };
+        let name_servers = NameServerConfigGroup::try_from(self)?;
+        let d_opts = ResolverOpts::from(self);
+
+        Ok(HickoryDnssecClient::new(
+            name_servers,
+            d_opts,
+            trust_anchor,
+            self.dnssec_insecure_strategy,
+        ))
+    }
+
+    pub(crate) fn spawn_resolver_driver(&self) -> anyhow::Result<BoxResolverDriver> {
+        let d_resolver = self.build_async_resolver()?;
+        let dnssec = if self.dnssec {
+            Some(Arc::new(self.build_dnssec_client()?))
+        } else {
+            None
+        };
 
         let resolver = HickoryResolver {
             inner: Arc::new(d_resolver),
+            dnssec,
             protective_cache_ttl: self.negative_min_ttl,
         };
         Ok(Box::new(resolver))
@@ -0,0 +1,490 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use base64::prelude::*;
+use hickory_proto::error::{ProtoError, ProtoErrorKind};
+use hickory_proto::op::{Query, ResponseCode};
+use hickory_proto::rr::dnssec::rdata::DNSSECRData;
+use hickory_proto::rr::dnssec::{Algorithm, PublicKeyEnum, TrustAnchor};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use hickory_proto::xfer::{
+    DnsHandle, DnsRequestOptions, DnssecDnsHandle, FirstAnswer, RetryDnsHandle,
+};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverOpts};
+use hickory_resolver::name_server::{NameServerPool, TokioConnectionProvider};
+
+use crate::error::{ResolveDriverError, ResolveError, ResolveServerError};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DnssecInsecureStrategy {
+    /// return the unsigned answer as is
+    #[default]
+    Allow,
+    /// treat the unsigned answer as an error
+    Deny,
+}
+
+impl FromStr for DnssecInsecureStrategy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "allow" | "accept" => Ok(DnssecInsecureStrategy::Allow),
+            "deny" | "reject" => Ok(DnssecInsecureStrategy::Deny),
+            _ => Err(()),
+        }
+    }
+}
+
+pub(super) enum DnssecLookupError {
+    /// the answer is unsigned, and the zone is proved to be unsigned
+    Insecure,
+    Failed(ResolveError),
+}
+
+type ValidatingHandle = DnssecDnsHandle<RetryDnsHandle<NameServerPool<TokioConnectionProvider>>>;
+
+pub(super) struct HickoryDnssecClient {
+    handle: ValidatingHandle,
+    positive_min_ttl: u32,
+    positive_max_ttl: u32,
+    negative_ttl: u32,
+    pub(super) insecure_strategy: DnssecInsecureStrategy,
+}
+
+impl HickoryDnssecClient {
+    pub(super) fn new(
+        name_servers: NameServerConfigGroup,
+        opts: ResolverOpts,
+        trust_anchor: TrustAnchor,
+        insecure_strategy: DnssecInsecureStrategy,
+    ) -> Self {
+        let positive_min_ttl = opts.positive_min_ttl.map(|d| d.as_secs()).unwrap_or(0);
+        let positive_max_ttl = opts
+            .positive_max_ttl
+            .map(|d| d.as_secs())
+            .unwrap_or(u32::MAX as u64);
+        let negative_ttl = opts.negative_min_ttl.map(|d| d.as_secs()).unwrap_or(0);
+        let attempts = opts.attempts;
+
+        let pool =
+            NameServerPool::from_config(name_servers, opts, TokioConnectionProvider::default());
+        let handle =
+            DnssecDnsHandle::with_trust_anchor(RetryDnsHandle::new(pool, attempts), trust_anchor);
+        HickoryDnssecClient {
+            handle,
+            positive_min_ttl: positive_min_ttl as u32,
+            positive_max_ttl: positive_max_ttl.min(u32::MAX as u64) as u32,
+            negative_ttl: negative_ttl as u32,
+            insecure_strategy,
+        }
+    }
+
+    /// Query with DNSSEC validation, and return the validated addresses with their ttl
+    pub(super) async fn lookup(
+        &self,
+        domain: &str,
+        record_type: RecordType,
+    ) -> Result<(Duration, Vec<IpAddr>), DnssecLookupError> {
+        let name = Name::from_ascii(format!("{domain}.")) // add trailing '.' to avoid search
+            .map_err(|_| DnssecLookupError::Failed(ResolveDriverError::BadName.into()))?;
+        let query = Query::query(name.clone(), record_type);
+
+        let response = match self
+            .handle
+            .lookup(query, DnsRequestOptions::default())
+            .first_answer()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                if matches!(e.kind(), ProtoErrorKind::RrsigsNotPresent { .. })
+                    && self.prove_insecure(&name).await
+                {
+                    return Err(DnssecLookupError::Insecure);
+                }
+                return Err(convert_proto_error(e));
+            }
+        };
+
+        match response.response_code() {
+            ResponseCode::NoError => {}
+            ResponseCode::FormErr => {
+                return Err(DnssecLookupError::Failed(
+                    ResolveServerError::FormErr.into(),
+                ))
+            }
+            ResponseCode::ServFail => {
+                return Err(DnssecLookupError::Failed(
+                    ResolveServerError::ServFail.into(),
+                ))
+            }
+            ResponseCode::NXDomain => {
+                return Err(DnssecLookupError::Failed(
+                    ResolveServerError::NotFound.into(),
+                ))
+            }
+            ResponseCode::NotImp => {
+                return Err(DnssecLookupError::Failed(ResolveServerError::NotImp.into()))
+            }
+            ResponseCode::Refused => {
+                return Err(DnssecLookupError::Failed(
+                    ResolveServerError::Refused.into(),
+                ))
+            }
+            _ => {
+                return Err(DnssecLookupError::Failed(
+                    ResolveDriverError::BadResp.into(),
+                ))
+            }
+        }
+
+        let mut min_ttl = u32::MAX;
+        let mut addrs = Vec::new();
+        for record in response.answers() {
+            let ip = match record.data() {
+                Some(RData::A(a)) if record_type == RecordType::A => IpAddr::V4(a.0),
+                Some(RData::AAAA(aaaa)) if record_type == RecordType::AAAA => IpAddr::V6(aaaa.0),
+                _ => continue,
+            };
+            min_ttl = min_ttl.min(record.ttl());
+            addrs.push(ip);
+        }
+
+        let ttl = if addrs.is_empty() {
+            self.negative_ttl
+        } else {
+            min_ttl.clamp(self.positive_min_ttl, self.positive_max_ttl)
+        };
+        Ok((Duration::from_secs(ttl as u64), addrs))
+    }
+}
+
+impl HickoryDnssecClient {
+    /// Check if there is a validated insecure delegation for the name or any of its ancestors.
+    ///
+    /// The missing of signatures is only acceptable if a signed parent zone proves that there is
+    /// no DS record for the delegation, otherwise the signatures may have been stripped.
+    async fn prove_insecure(&self, name: &Name) -> bool {
+        let mut zone = name.clone();
+        while !zone.is_root() {
+            let query = Query::query(zone.clone(), RecordType::DS);
+            match self
+                .handle
+                .lookup(query, DnsRequestOptions::default())
+                .first_answer()
+                .await
+            {
+                Ok(response) => {
+                    if response.response_code() != ResponseCode::NoError {
+                        return false;
+                    }
+                    if response
+                        .answers()
+                        .iter()
+                        .any(|r| r.record_type() == RecordType::DS)
+                    {
+                        // a signed delegation
+                        return false;
+                    }
+                    if is_insecure_delegation_proof(&zone, response.name_servers()) {
+                        return true;
+                    }
+                }
+                // the zone that contains the DS record may also be unsigned, check the parent
+                Err(e) if matches!(e.kind(), ProtoErrorKind::RrsigsNotPresent { .. }) => {}
+                Err(_) => return false,
+            }
+            zone = zone.base_name();
+        }
+        false
+    }
+}
+
+/// Check if the validated authority records contain a NSEC proof for an insecure delegation.
+///
+/// See RFC 4035 Section 5.2, the NSEC record at the delegation point should have the NS bit set,
+/// and the DS and SOA bits cleared.
+fn is_insecure_delegation_proof(zone: &Name, authorities: &[Record]) -> bool {
+    authorities.iter().any(|r| {
+        if !r.name().eq_case(zone) {
+            return false;
+        }
+        let Some(RData::DNSSEC(DNSSECRData::NSEC(nsec))) = r.data() else {
+            return false;
+        };
+        let types = nsec.type_bit_maps();
+        types.contains(&RecordType::NS)
+            && !types.contains(&RecordType::DS)
+            && !types.contains(&RecordType::SOA)
+    })
+}
+
+fn convert_proto_error(e: ProtoError) -> DnssecLookupError {
+    match e.kind() {
+        ProtoErrorKind::Timeout => DnssecLookupError::Failed(ResolveDriverError::Timeout.into()),
+        ProtoErrorKind::Io(_) | ProtoErrorKind::NoConnections | ProtoErrorKind::Busy => {
+            DnssecLookupError::Failed(ResolveDriverError::Internal(e.to_string()).into())
+        }
+        _ => DnssecLookupError::Failed(ResolveDriverError::DnssecBogus(e.to_string()).into()),
+    }
+}
+
+struct DnskeyRecord {
+    flags: u16,
+    protocol: u8,
+    algorithm: u8,
+    public_key: Vec<u8>,
+}
+
+impl DnskeyRecord {
+    const FLAG_ZONE_KEY: u16 = 0x0100;
+    const FLAG_REVOKE: u16 = 0x0080;
+
+    fn parse_tokens(tokens: &[&str]) -> anyhow::Result<Option<Self>> {
+        let Some(p) = tokens.iter().position(|t| t.eq_ignore_ascii_case("DNSKEY")) else {
+            return Ok(None);
+        };
+        let rdata = &tokens[p + 1..];
+        if rdata.len() < 4 {
+            return Err(anyhow!("incomplete DNSKEY rdata"));
+        }
+
+        let flags = u16::from_str(rdata[0]).map_err(|e| anyhow!("invalid flags: {e}"))?;
+        let protocol = u8::from_str(rdata[1]).map_err(|e| anyhow!("invalid protocol: {e}"))?;
+        let algorithm = u8::from_str(rdata[2]).map_err(|e| anyhow!("invalid algorithm: {e}"))?;
+        let encoded = rdata[3..].concat();
+        let public_key = BASE64_STANDARD
+            .decode(encoded)
+            .map_err(|e| anyhow!("invalid base64 encoded public key: {e}"))?;
+        Ok(Some(DnskeyRecord {
+            flags,
+            protocol,
+            algorithm,
+            public_key,
+        }))
+    }
+
+    fn usable(&self) -> bool {
+        self.protocol == 3
+            && (self.flags & Self::FLAG_ZONE_KEY) != 0
+            && (self.flags & Self::FLAG_REVOKE) == 0
+    }
+}
+
+/// Parse DNSKEY records in zone file presentation format, as output by `dig DNSKEY .`
+fn parse_dnskey_records(content: &str) -> anyhow::Result<Vec<DnskeyRecord>> {
+    let mut records = Vec::new();
+    let mut tokens = Vec::new();
+    let mut depth = 0usize;
+    let mut start_line = 0usize;
+
+    for (i, line) in content.lines().enumerate() {
+        let line = match line.split_once(';') {
+            Some((s, _)) => s,
+            None => line,
+        };
+        if depth == 0 {
+            start_line = i + 1;
+        }
+
+        for token in line.split_whitespace() {
+            let mut token = token;
+            while let Some(s) = token.strip_prefix('(') {
+                depth += 1;
+                token = s;
+            }
+            while let Some(s) = token.strip_suffix(')') {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| anyhow!("unbalanced ')' at line {}", i + 1))?;
+                token = s;
+            }
+            if !token.is_empty() {
+                tokens.push(token);
+            }
+        }
+
+        if depth == 0 && !tokens.is_empty() {
+            if let Some(record) = DnskeyRecord::parse_tokens(&tokens)
+                .context(format!("invalid DNSKEY record at line {start_line}"))?
+            {
+                records.push(record);
+            }
+            tokens.clear();
+        }
+    }
+    if depth != 0 {
+        return Err(anyhow!("unclosed '(' at line {start_line}"));
+    }
+
+    Ok(records)
+}
+
+pub(super) fn load_trust_anchor(path: &Path) -> anyhow::Result<TrustAnchor> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("failed to read file {}: {e}", path.display()))?;
+    let records = parse_dnskey_records(&content)?;
+
+    let mut trust_anchor = TrustAnchor::new();
+    let mut count = 0usize;
+    for r in records {
+        if !r.usable() {
+            continue;
+        }
+        let algorithm = Algorithm::from_u8(r.algorithm);
+        let key = PublicKeyEnum::from_public_bytes(&r.public_key, algorithm)
+            .map_err(|e| anyhow!("unsupported DNSKEY with algorithm {}: {e}", r.algorithm))?;
+        trust_anchor.insert_trust_anchor(&key);
+        count += 1;
+    }
+    if count == 0 {
+        return Err(anyhow!(
+            "no usable DNSKEY record found in file {}",
+            path.display()
+        ));
+    }
+
+    Ok(trust_anchor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hickory_proto::rr::dnssec::rdata::NSEC;
+
+    fn nsec_record(name: &str, types: Vec<RecordType>) -> Record {
+        let name = Name::from_ascii(name).unwrap();
+        let next = Name::from_ascii("zzz.example.net.").unwrap();
+        Record::from_rdata(
+            name,
+            300,
+            RData::DNSSEC(DNSSECRData::NSEC(NSEC::new(next, types))),
+        )
+    }
+
+    #[test]
+    fn insecure_delegation_proof() {
+        let zone = Name::from_ascii("sub.example.net.").unwrap();
+
+        let records = vec![nsec_record(
+            "sub.example.net.",
+            vec![RecordType::NS, RecordType::RRSIG, RecordType::NSEC],
+        )];
+        assert!(is_insecure_delegation_proof(&zone, &records));
+
+        // a signed delegation
+        let records = vec![nsec_record(
+            "sub.example.net.",
+            vec![
+                RecordType::NS,
+                RecordType::DS,
+                RecordType::RRSIG,
+                RecordType::NSEC,
+            ],
+        )];
+        assert!(!is_insecure_delegation_proof(&zone, &records));
+
+        // a zone apex
+        let records = vec![nsec_record(
+            "sub.example.net.",
+            vec![
+                RecordType::NS,
+                RecordType::SOA,
+                RecordType::RRSIG,
+                RecordType::NSEC,
+            ],
+        )];
+        assert!(!is_insecure_delegation_proof(&zone, &records));
+
+        // not a delegation, so the signatures should be present
+        let records = vec![nsec_record(
+            "sub.example.net.",
+            vec![RecordType::A, RecordType::RRSIG, RecordType::NSEC],
+        )];
+        assert!(!is_insecure_delegation_proof(&zone, &records));
+
+        // the proof for another name
+        let records = vec![nsec_record(
+            "other.example.net.",
+            vec![RecordType::NS, RecordType::RRSIG, RecordType::NSEC],
+        )];
+        assert!(!is_insecure_delegation_proof(&zone, &records));
+
+        assert!(!is_insecure_delegation_proof(&zone, &[]));
+    }
+
+    #[test]
+    fn error_mapping() {
+        // missing signatures are bogus unless the zone is proved to be unsigned
+        let e = ProtoError::from(ProtoErrorKind::RrsigsNotPresent {
+            name: Name::from_ascii("www.example.net.").unwrap(),
+            record_type: RecordType::A,
+        });
+        assert!(matches!(
+            convert_proto_error(e),
+            DnssecLookupError::Failed(ResolveError::FromDriver(ResolveDriverError::DnssecBogus(_)))
+        ));
+
+        let e = ProtoError::from(ProtoErrorKind::Message("signature verification failed"));
+        assert!(matches!(
+            convert_proto_error(e),
+            DnssecLookupError::Failed(ResolveError::FromDriver(ResolveDriverError::DnssecBogus(_)))
+        ));
+
+        let e = ProtoError::from(ProtoErrorKind::Timeout);
+        assert!(matches!(
+            convert_proto_error(e),
+            DnssecLookupError::Failed(ResolveError::FromDriver(ResolveDriverError::Timeout))
+        ));
+    }
+
+    #[test]
+    fn parse_dnskey() {
+        let content = r#"
+; root zone key
+.   172800  IN  DNSKEY  257 3 8 AwEA AQID ; comment
+.   172800  IN  DNSKEY  ( 256 3 8
+                          AwEAAQ== ) ; multi-line
+.   172800  IN  DNSKEY  385 3 8 AwEA
+"#;
+        let records = parse_dnskey_records(content).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].flags, 257);
+        assert_eq!(records[0].algorithm, 8);
+        assert_eq!(
+            records[0].public_key,
+            vec![0x03, 0x01, 0x00, 0x01, 0x02, 0x03]
+        );
+        assert!(records[0].usable());
+        assert_eq!(records[1].flags, 256);
+        assert_eq!(records[1].public_key, vec![0x03, 0x01, 0x00, 0x01]);
+        assert!(records[1].usable());
+        assert!(!records[2].usable());
+
+        assert!(parse_dnskey_records(". IN DNSKEY ( 257 3 8 AwEA").is_err());
+        assert!(parse_dnskey_records(". IN DNSKEY 257 3").is_err());
+        assert!(parse_dnskey_records(". IN DS 20326 8 2 E06D")
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -19,17 +19,23 @@ use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::RecordType;
+use hickory_resolver::error::ResolveErrorKind;
 use hickory_resolver::lookup::{Ipv4Lookup, Ipv6Lookup};
 use hickory_resolver::TokioAsyncResolver;
 use tokio::sync::mpsc;
 use tokio::time::Instant;
 
+use super::dnssec::{DnssecLookupError, HickoryDnssecClient};
+use super::DnssecInsecureStrategy;
 use crate::config::ResolverRuntimeConfig;
 use crate::message::ResolveDriverResponse;
-use crate::{ResolveDriver, ResolvedRecord};
+use crate::{ResolveDriver, ResolveDriverError, ResolvedRecord};
 
 pub(super) struct HickoryResolver {
     pub(super) inner: Arc<TokioAsyncResolver>,
+    pub(super) dnssec: Option<Arc<HickoryDnssecClient>>,
     pub(super) protective_cache_ttl: u32,
 }
 
@@ -92,7 +98,63 @@ where
                 result: Ok(addrs),
             }
         }
-        Ok(Err(e)) => ResolvedRecord::failed(domain, config.protective_cache_ttl, e.into()),
+        Ok(Err(e)) => match e.kind() {
+            // NODATA, the domain exists but has no record of the queried type,
+            // use an empty result so the resolver runtime can handle it, e.g. for DNS64
+            ResolveErrorKind::NoRecordsFound {
+                response_code: ResponseCode::NoError,
+                negative_ttl,
+                ..
+            } => {
+                let ttl = negative_ttl.unwrap_or(config.protective_cache_ttl);
+                let created = Instant::now();
+                ResolvedRecord {
+                    domain,
+                    created,
+                    expire: created.checked_add(Duration::from_secs(ttl as u64)),
+                    result: Ok(Vec::new()),
+                }
+            }
+            _ => ResolvedRecord::failed(domain, config.protective_cache_ttl, e.into()),
+        },
+        Err(_) => ResolvedRecord::timed_out(domain, config.protective_cache_ttl),
+    }
+}
+
+async fn resolve_secure<F, T>(
+    client: &HickoryDnssecClient,
+    record_type: RecordType,
+    insecure_query_future: F,
+    domain: String,
+    config: JobConfig,
+) -> ResolvedRecord
+where
+    F: Future<Output = Result<T, hickory_resolver::error::ResolveError>>,
+    T: ResultConverter,
+{
+    match tokio::time::timeout(config.timeout, client.lookup(&domain, record_type)).await {
+        Ok(Ok((ttl, addrs))) => {
+            let created = Instant::now();
+            ResolvedRecord {
+                domain,
+                created,
+                expire: created.checked_add(ttl),
+                result: Ok(addrs),
+            }
+        }
+        Ok(Err(DnssecLookupError::Insecure)) => match client.insecure_strategy {
+            DnssecInsecureStrategy::Allow => {
+                resolve_protective(insecure_query_future, domain, config).await
+            }
+            DnssecInsecureStrategy::Deny => ResolvedRecord::failed(
+                domain,
+                config.protective_cache_ttl,
+                ResolveDriverError::DnssecInsecure.into(),
+            ),
+        },
+        Ok(Err(DnssecLookupError::Failed(e))) => {
+            ResolvedRecord::failed(domain, config.protective_cache_ttl, e)
+        }
         Err(_) => ResolvedRecord::timed_out(domain, config.protective_cache_ttl),
     }
 }
@@ -105,10 +167,16 @@ impl ResolveDriver for HickoryResolver {
         sender: mpsc::UnboundedSender<ResolveDriverResponse>,
     ) {
         let resolver = Arc::clone(&self.inner);
+        let dnssec = self.dnssec.clone();
         let job_config = self.build_job_config(config);
         tokio::spawn(async move {
             let query = resolver.ipv4_lookup(format!("{domain}.")); // add trailing '.' to avoid search
-            let record = resolve_protective(query, domain, job_config).await;
+            let record = match dnssec {
+                Some(client) => {
+                    resolve_secure(&client, RecordType::A, query, domain, job_config).await
+                }
+                None => resolve_protective(query, domain, job_config).await,
+            };
 
             let _ = sender.send(ResolveDriverResponse::V4(record)); // TODO log error
         });
@@ -121,12 +189,18 @@ impl ResolveDriver for HickoryResolver {
         sender: mpsc::UnboundedSender<ResolveDriverResponse>,
     ) {
         let resolver = Arc::clone(&self.inner);
+        let dnssec = self.dnssec.clone();
         let job_config = self.build_job_config(config);
         tokio::spawn(async move {
             let query = resolver.ipv6_lookup(format!("{domain}.")); // add trailing '.' to avoid search
-            let record = resolve_protective(query, domain, job_config).await;
-
-            let _ = sender.send(ResolveDriverResponse::V4(record)); // TODO log error
+            let record = match dnssec {
+                Some(client) => {
+                    resolve_secure(&client, RecordType::AAAA, query, domain, job_config).await
+                }
+                None => resolve_protective(query, domain, job_config).await,
+            };
+
+            let _ = sender.send(ResolveDriverResponse::V6(record)); // TODO log error
         });
     }
 }
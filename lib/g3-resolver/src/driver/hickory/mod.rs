@@ -19,5 +19,8 @@ use driver::HickoryResolver;
 
 mod error;
 
+mod dnssec;
+pub use dnssec::DnssecInsecureStrategy;
+
 mod config;
 pub use config::HickoryDriverConfig;
@@ -0,0 +1,133 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+
+use super::{HostsResolver, HostsTable};
+use crate::{BoxResolverDriver, ResolverHandle};
+
+const DEFAULT_HOSTS_FILE_TTL: u32 = 60;
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct HostsStaticRecord {
+    pub(super) addrs: Vec<IpAddr>,
+    pub(super) ttl: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostsDriverStaticConfig {
+    hosts_file: Option<PathBuf>,
+    pub(super) check_interval: Duration,
+    pub(super) hosts_file_ttl: u32,
+    pub(super) negative_ttl: u32,
+    pub(super) static_records: BTreeMap<String, HostsStaticRecord>,
+}
+
+impl Default for HostsDriverStaticConfig {
+    fn default() -> Self {
+        HostsDriverStaticConfig {
+            hosts_file: None,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            hosts_file_ttl: DEFAULT_HOSTS_FILE_TTL,
+            negative_ttl: crate::config::RESOLVER_MINIMUM_CACHE_TTL,
+            static_records: BTreeMap::new(),
+        }
+    }
+}
+
+impl HostsDriverStaticConfig {
+    pub fn set_hosts_file(&mut self, path: PathBuf) {
+        self.hosts_file = Some(path);
+    }
+
+    #[inline]
+    pub fn hosts_file(&self) -> Option<&Path> {
+        self.hosts_file.as_deref()
+    }
+
+    pub fn set_check_interval(&mut self, interval: Duration) {
+        self.check_interval = interval;
+    }
+
+    pub fn set_hosts_file_ttl(&mut self, ttl: u32) {
+        self.hosts_file_ttl = ttl;
+    }
+
+    pub fn set_negative_ttl(&mut self, ttl: u32) {
+        self.negative_ttl = ttl;
+    }
+
+    /// add a static record which will override the one with the same domain in the hosts file
+    pub fn add_static_record(&mut self, domain: &str, addrs: Vec<IpAddr>, ttl: u32) -> bool {
+        let domain = super::table::normalize_domain(domain);
+        self.static_records
+            .insert(domain, HostsStaticRecord { addrs, ttl })
+            .is_none()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hosts_file.is_none() && self.static_records.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostsDriverConfig {
+    next_handle: Option<ResolverHandle>,
+    static_config: HostsDriverStaticConfig,
+}
+
+impl HostsDriverConfig {
+    pub fn set_next_handle(&mut self, handle: Option<ResolverHandle>) {
+        self.next_handle = handle;
+    }
+
+    pub fn set_static_config(&mut self, conf: HostsDriverStaticConfig) {
+        self.static_config = conf;
+    }
+
+    pub(crate) fn spawn_resolver_driver(&self) -> BoxResolverDriver {
+        let conf = &self.static_config;
+
+        let (table, modified) = match &conf.hosts_file {
+            Some(path) => HostsTable::load(conf, path),
+            None => (HostsTable::build(conf, None), None),
+        };
+        let table = Arc::new(ArcSwap::from_pointee(table));
+
+        let watcher = conf.hosts_file.as_ref().map(|path| {
+            tokio::spawn(super::driver::watch_hosts_file(
+                path.clone(),
+                conf.clone(),
+                Arc::clone(&table),
+                modified,
+            ))
+        });
+
+        Box::new(HostsResolver {
+            table,
+            next: self.next_handle.clone(),
+            negative_ttl: conf.negative_ttl,
+            watcher,
+        })
+    }
+}
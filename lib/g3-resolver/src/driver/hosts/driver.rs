@@ -0,0 +1,236 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use arc_swap::ArcSwap;
+use log::{debug, warn};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use super::{HostsDriverStaticConfig, HostsTable};
+use crate::config::ResolverRuntimeConfig;
+use crate::message::ResolveDriverResponse;
+use crate::{
+    ResolveDriver, ResolveJob, ResolveLocalError, ResolveServerError, ResolvedRecord,
+    ResolverHandle,
+};
+
+pub(super) struct HostsResolver {
+    pub(super) table: Arc<ArcSwap<HostsTable>>,
+    pub(super) next: Option<ResolverHandle>,
+    pub(super) negative_ttl: u32,
+    pub(super) watcher: Option<JoinHandle<()>>,
+}
+
+impl Drop for HostsResolver {
+    fn drop(&mut self) {
+        if let Some(handle) = self.watcher.take() {
+            handle.abort();
+        }
+    }
+}
+
+/// tokio interval will panic if the period is zero
+const MINIMUM_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+fn reload_if_changed(
+    path: &Path,
+    conf: &HostsDriverStaticConfig,
+    last_modified: Option<SystemTime>,
+) -> io::Result<Option<(HostsTable, Option<SystemTime>)>> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified())?;
+    if last_modified == Some(modified) {
+        return Ok(None);
+    }
+
+    debug!("hosts file {} changed, will reload it", path.display());
+    Ok(Some(HostsTable::load(conf, path)))
+}
+
+pub(super) async fn watch_hosts_file(
+    path: PathBuf,
+    conf: HostsDriverStaticConfig,
+    table: Arc<ArcSwap<HostsTable>>,
+    mut last_modified: Option<SystemTime>,
+) {
+    let mut interval = tokio::time::interval(conf.check_interval.max(MINIMUM_CHECK_INTERVAL));
+    let conf = Arc::new(conf);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+
+        // the file system operations may block, so run them in the blocking thread pool
+        let task_path = path.clone();
+        let task_conf = Arc::clone(&conf);
+        let task_modified = last_modified;
+        match tokio::task::spawn_blocking(move || {
+            reload_if_changed(&task_path, &task_conf, task_modified)
+        })
+        .await
+        {
+            Ok(Ok(Some((new_table, modified)))) => {
+                table.store(Arc::new(new_table));
+                last_modified = modified;
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => {
+                if last_modified.take().is_some() {
+                    warn!("failed to get status of hosts file {}: {e}", path.display());
+                }
+            }
+            Err(e) => warn!("failed to join hosts file check task: {e}"),
+        }
+    }
+}
+
+struct HostsResolverJob {
+    next: Option<ResolveJob>,
+    job_timeout: Duration,
+    negative_ttl: u32,
+}
+
+impl HostsResolverJob {
+    async fn resolve(mut self, domain: String) -> ResolvedRecord {
+        let Some(mut job) = self.next.take() else {
+            return ResolvedRecord::failed(
+                domain,
+                self.negative_ttl,
+                ResolveLocalError::NoResolverRunning.into(),
+            );
+        };
+
+        match tokio::time::timeout(self.job_timeout, job.recv()).await {
+            Ok(Ok((r, _))) => r.as_ref().clone(),
+            Ok(Err(e)) => ResolvedRecord::failed(domain, self.negative_ttl, e.into()),
+            Err(_) => ResolvedRecord::timed_out(domain, self.negative_ttl),
+        }
+    }
+}
+
+impl HostsResolver {
+    fn not_found(&self, domain: String) -> ResolvedRecord {
+        ResolvedRecord::failed(
+            domain,
+            self.negative_ttl,
+            ResolveServerError::NotFound.into(),
+        )
+    }
+}
+
+impl ResolveDriver for HostsResolver {
+    fn query_v4(
+        &self,
+        domain: String,
+        config: &ResolverRuntimeConfig,
+        sender: mpsc::UnboundedSender<ResolveDriverResponse>,
+    ) {
+        if let Some(record) = self.table.load().query_v4(&domain) {
+            let _ = sender.send(ResolveDriverResponse::V4(record));
+            return;
+        }
+
+        let Some(next) = &self.next else {
+            let _ = sender.send(ResolveDriverResponse::V4(self.not_found(domain)));
+            return;
+        };
+        let job = HostsResolverJob {
+            next: next.get_v4(domain.clone()).ok(),
+            job_timeout: config.protective_query_timeout,
+            negative_ttl: self.negative_ttl,
+        };
+        tokio::spawn(async move {
+            let record = job.resolve(domain).await;
+            let _ = sender.send(ResolveDriverResponse::V4(record));
+        });
+    }
+
+    fn query_v6(
+        &self,
+        domain: String,
+        config: &ResolverRuntimeConfig,
+        sender: mpsc::UnboundedSender<ResolveDriverResponse>,
+    ) {
+        if let Some(record) = self.table.load().query_v6(&domain) {
+            let _ = sender.send(ResolveDriverResponse::V6(record));
+            return;
+        }
+
+        let Some(next) = &self.next else {
+            let _ = sender.send(ResolveDriverResponse::V6(self.not_found(domain)));
+            return;
+        };
+        let job = HostsResolverJob {
+            next: next.get_v6(domain.clone()).ok(),
+            job_timeout: config.protective_query_timeout,
+            negative_ttl: self.negative_ttl,
+        };
+        tokio::spawn(async move {
+            let record = job.resolve(domain).await;
+            let _ = sender.send(ResolveDriverResponse::V6(record));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::driver::hosts::HostsDriverConfig;
+    use crate::ResolveError;
+
+    fn query_v6(driver: &dyn ResolveDriver, domain: &str) -> ResolvedRecord {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        driver.query_v6(
+            domain.to_string(),
+            &ResolverRuntimeConfig::default(),
+            sender,
+        );
+        match receiver.try_recv().unwrap() {
+            ResolveDriverResponse::V6(r) => r,
+            ResolveDriverResponse::V4(_) => panic!("unexpected v4 response"),
+        }
+    }
+
+    #[tokio::test]
+    async fn pinned_v4_only() {
+        let mut static_conf = HostsDriverStaticConfig::default();
+        static_conf.add_static_record(
+            "pinned.example.net",
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+            60,
+        );
+        let mut conf = HostsDriverConfig::default();
+        conf.set_static_config(static_conf);
+        let driver = conf.spawn_resolver_driver();
+
+        // a pinned domain should never fall through to the next resolver,
+        // so no AAAA record will be leaked for it
+        let r = query_v6(driver.as_ref(), "pinned.example.net");
+        assert!(r.result.unwrap().is_empty());
+
+        // without next resolver, the unknown domains are not found
+        let r = query_v6(driver.as_ref(), "other.example.net");
+        assert!(matches!(
+            r.result,
+            Err(ResolveError::FromServer(ResolveServerError::NotFound))
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use ahash::AHashMap;
+use log::warn;
+use tokio::time::Instant;
+
+use super::HostsDriverStaticConfig;
+use crate::ResolvedRecord;
+
+pub(super) fn normalize_domain(domain: &str) -> String {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    domain.to_ascii_lowercase()
+}
+
+struct HostsEntry {
+    v4: Vec<IpAddr>,
+    v6: Vec<IpAddr>,
+    ttl: u32,
+}
+
+impl HostsEntry {
+    fn new(ttl: u32) -> Self {
+        HostsEntry {
+            v4: Vec::new(),
+            v6: Vec::new(),
+            ttl,
+        }
+    }
+
+    fn add(&mut self, ip: IpAddr) {
+        let list = match ip {
+            IpAddr::V4(_) => &mut self.v4,
+            IpAddr::V6(ip6) => match ip6.to_ipv4_mapped() {
+                Some(ip4) => return self.add(IpAddr::V4(ip4)),
+                None => &mut self.v6,
+            },
+        };
+        if !list.contains(&ip) {
+            list.push(ip);
+        }
+    }
+
+    fn record(&self, domain: String, addrs: &[IpAddr]) -> ResolvedRecord {
+        let created = Instant::now();
+        let expire = created.checked_add(Duration::from_secs(self.ttl as u64));
+        ResolvedRecord {
+            domain,
+            created,
+            expire,
+            result: Ok(addrs.to_vec()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct HostsTable {
+    entries: AHashMap<String, HostsEntry>,
+}
+
+impl HostsTable {
+    /// load the hosts file, the returned modified time should be used to detect later changes
+    pub(super) fn load(
+        conf: &HostsDriverStaticConfig,
+        path: &Path,
+    ) -> (HostsTable, Option<SystemTime>) {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        match std::fs::read_to_string(path) {
+            Ok(content) => (HostsTable::build(conf, Some(&content)), modified),
+            Err(e) => {
+                warn!("failed to read hosts file {}: {e}", path.display());
+                (HostsTable::build(conf, None), modified)
+            }
+        }
+    }
+
+    pub(super) fn build(conf: &HostsDriverStaticConfig, hosts_file: Option<&str>) -> HostsTable {
+        let mut entries = AHashMap::<String, HostsEntry>::new();
+
+        if let Some(content) = hosts_file {
+            for line in content.lines() {
+                let line = match line.split_once('#') {
+                    Some((s, _)) => s,
+                    None => line,
+                };
+                let mut fields = line.split_whitespace();
+                let Some(ip) = fields.next() else {
+                    continue;
+                };
+                // the ones with zone index will be skipped
+                let Ok(ip) = IpAddr::from_str(ip) else {
+                    continue;
+                };
+                for name in fields {
+                    entries
+                        .entry(normalize_domain(name))
+                        .or_insert_with(|| HostsEntry::new(conf.hosts_file_ttl))
+                        .add(ip);
+                }
+            }
+        }
+
+        for (domain, r) in &conf.static_records {
+            let mut entry = HostsEntry::new(r.ttl);
+            r.addrs.iter().for_each(|ip| entry.add(*ip));
+            entries.insert(domain.to_string(), entry);
+        }
+
+        HostsTable { entries }
+    }
+
+    pub(super) fn query_v4(&self, domain: &str) -> Option<ResolvedRecord> {
+        self.entries
+            .get(&normalize_domain(domain))
+            .map(|entry| entry.record(domain.to_string(), &entry.v4))
+    }
+
+    pub(super) fn query_v6(&self, domain: &str) -> Option<ResolvedRecord> {
+        self.entries
+            .get(&normalize_domain(domain))
+            .map(|entry| entry.record(domain.to_string(), &entry.v6))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn parse_hosts_file() {
+        let content = "\
+# comment line
+127.0.0.1   localhost
+::1         localhost ip6-localhost # trailing comment
+192.0.2.1   www.example.com  WWW.Example.NET.
+192.0.2.2   www.example.com
+fe80::1%lo0 link-local
+bad-ip      bad.example.com
+";
+        let mut conf = HostsDriverStaticConfig::default();
+        conf.add_static_record(
+            "www.example.net",
+            vec![IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))],
+            300,
+        );
+        let table = HostsTable::build(&conf, Some(content));
+
+        let r = table.query_v4("localhost").unwrap();
+        assert_eq!(r.result.unwrap(), vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+        let r = table.query_v6("ip6-localhost").unwrap();
+        assert_eq!(r.result.unwrap(), vec![IpAddr::V6(Ipv6Addr::LOCALHOST)]);
+        let r = table.query_v6("www.example.com").unwrap();
+        assert!(r.result.unwrap().is_empty());
+
+        let r = table.query_v4("www.example.com.").unwrap();
+        assert_eq!(r.domain, "www.example.com.");
+        assert_eq!(r.result.unwrap().len(), 2);
+
+        let r = table.query_v4("www.example.net").unwrap();
+        assert_eq!(
+            r.result.unwrap(),
+            vec![IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1))]
+        );
+        let ttl = r.expire.unwrap().duration_since(r.created);
+        assert_eq!(ttl, Duration::from_secs(300));
+
+        assert!(table.query_v4("link-local").is_none());
+        assert!(table.query_v4("bad.example.com").is_none());
+    }
+}
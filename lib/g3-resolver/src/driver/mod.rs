@@ -19,7 +19,9 @@ use tokio::sync::mpsc;
 use crate::config::ResolverRuntimeConfig;
 use crate::message::ResolveDriverResponse;
 
+pub mod domain_route;
 pub mod fail_over;
+pub mod hosts;
 
 #[cfg(feature = "c-ares")]
 pub mod c_ares;
@@ -29,7 +31,9 @@ pub mod hickory;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum AnyResolveDriverConfig {
+    DomainRoute(domain_route::DomainRouteDriverConfig),
     FailOver(fail_over::FailOverDriverConfig),
+    Hosts(hosts::HostsDriverConfig),
     #[cfg(feature = "c-ares")]
     CAres(c_ares::CAresDriverConfig),
     #[cfg(feature = "hickory")]
@@ -39,7 +43,9 @@ pub enum AnyResolveDriverConfig {
 impl AnyResolveDriverConfig {
     pub(crate) fn spawn_resolver_driver(&self) -> anyhow::Result<Box<dyn ResolveDriver>> {
         match self {
+            AnyResolveDriverConfig::DomainRoute(c) => Ok(c.spawn_resolver_driver()),
             AnyResolveDriverConfig::FailOver(c) => Ok(c.spawn_resolver_driver()),
+            AnyResolveDriverConfig::Hosts(c) => Ok(c.spawn_resolver_driver()),
             #[cfg(feature = "c-ares")]
             AnyResolveDriverConfig::CAres(c) => c.spawn_resolver_driver(),
             #[cfg(feature = "hickory")]
@@ -56,6 +56,10 @@ pub enum ResolveDriverError {
     ConnRefused,
     #[error("timeout while contacting server")]
     Timeout,
+    #[error("dnssec validation failed: {0}")]
+    DnssecBogus(String),
+    #[error("dnssec insecure answer not allowed")]
+    DnssecInsecure,
     #[error("internal error: {0}")]
     Internal(String),
 }
@@ -69,6 +73,8 @@ impl ResolveDriverError {
             ResolveDriverError::BadResp => "BadResp",
             ResolveDriverError::ConnRefused => "ConnRefused",
             ResolveDriverError::Timeout => "Timeout",
+            ResolveDriverError::DnssecBogus(_) => "DnssecBogus",
+            ResolveDriverError::DnssecInsecure => "DnssecInsecure",
             ResolveDriverError::Internal(_) => "InternalError",
         }
     }
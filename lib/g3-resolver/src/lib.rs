@@ -27,6 +27,7 @@ mod query;
 mod record;
 mod resolver;
 mod runtime;
+mod snapshot;
 mod stats;
 
 pub use config::{ResolverConfig, ResolverRuntimeConfig};
@@ -27,6 +27,7 @@ use crate::ResolveLocalError;
 #[derive(Clone, Copy, Debug)]
 pub enum ResolvedRecordSource {
     Cache,
+    Stale,
     Query,
 }
 
@@ -34,6 +35,7 @@ impl ResolvedRecordSource {
     pub const fn as_str(&self) -> &'static str {
         match self {
             ResolvedRecordSource::Cache => "cache",
+            ResolvedRecordSource::Stale => "stale",
             ResolvedRecordSource::Query => "query",
         }
     }
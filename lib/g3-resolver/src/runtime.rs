@@ -16,24 +16,37 @@
 
 use std::collections::hash_map;
 use std::future::Future;
+use std::net::IpAddr;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime};
 
 use ahash::AHashMap;
 use log::{trace, warn};
 use tokio::sync::{mpsc, oneshot};
-use tokio::time::Instant;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
 use tokio_util::time::{delay_queue, DelayQueue};
 
+use g3_types::net::Nat64Prefix;
+
+use super::snapshot::{CacheSnapshot, CacheSnapshotEntry};
 use super::stats::{ResolverMemoryStats, ResolverStats};
-use super::{ArcResolvedRecord, BoxResolverDriver, ResolvedRecordSource, ResolverConfig};
+use super::{
+    ArcResolvedRecord, BoxResolverDriver, ResolveError, ResolveServerError, ResolvedRecord,
+    ResolvedRecordSource, ResolverConfig,
+};
 use crate::message::{ResolveDriverRequest, ResolveDriverResponse, ResolverCommand};
 
+const MINIMUM_SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
 struct CachedRecord {
     inner: ArcResolvedRecord,
     expire_at: Instant,
     expire_key: Option<delay_queue::Key>,
+    /// the record has expired and is kept only for serve-stale
+    stale: bool,
 }
 
 pub(crate) struct ResolverRuntime {
@@ -49,7 +62,11 @@ pub(crate) struct ResolverRuntime {
     cache_v6: AHashMap<String, CachedRecord>,
     doing_v4: AHashMap<String, Vec<oneshot::Sender<(ArcResolvedRecord, ResolvedRecordSource)>>>,
     doing_v6: AHashMap<String, Vec<oneshot::Sender<(ArcResolvedRecord, ResolvedRecordSource)>>>,
+    doing_dns64: AHashMap<String, ResolvedRecord>,
     driver: Option<BoxResolverDriver>,
+    snapshot_interval: Option<Interval>,
+    snapshot_loading: Option<JoinHandle<anyhow::Result<CacheSnapshot>>>,
+    snapshot_saving: Option<JoinHandle<()>>,
 }
 
 impl Drop for ResolverRuntime {
@@ -81,7 +98,11 @@ impl ResolverRuntime {
             cache_v6: AHashMap::with_capacity(initial_cache_capacity),
             doing_v4: AHashMap::with_capacity(initial_cache_capacity),
             doing_v6: AHashMap::with_capacity(initial_cache_capacity),
+            doing_dns64: AHashMap::new(),
             driver: None,
+            snapshot_interval: None,
+            snapshot_loading: None,
+            snapshot_saving: None,
         }
     }
 
@@ -91,6 +112,7 @@ impl ResolverRuntime {
                 Ok(driver) => {
                     self.driver = Some(driver);
                     self.config = *config;
+                    self.snapshot_interval = None;
                 }
                 Err(e) => {
                     warn!("invalid resolver config {config:?} : {e}");
@@ -119,6 +141,7 @@ impl ResolverRuntime {
                 v.inner = record;
                 v.expire_at = expire_at;
                 v.expire_key = Some(expire_key);
+                v.stale = false;
             }
             hash_map::Entry::Vacant(v) => {
                 let expire_key = expire_queue.insert_at(record.domain.to_owned(), expire_at);
@@ -126,6 +149,7 @@ impl ResolverRuntime {
                     inner: record,
                     expire_at,
                     expire_key: Some(expire_key),
+                    stale: false,
                 });
             }
         }
@@ -145,46 +169,152 @@ impl ResolverRuntime {
                         }
                     }
                 }
+                if let Some(v6_record) = self.doing_dns64.remove(&record.domain) {
+                    self.finish_dns64(v6_record, &record);
+                }
+                if keep_stale(&self.cache_v4, &record) {
+                    return;
+                }
                 if let Some(expire_at) = record.expire {
                     Self::update_cache(&mut self.cache_v4, &mut self.expired_v4, record, expire_at);
                 }
             }
             ResolveDriverResponse::V6(record) => {
                 self.stats.query_aaaa.add_record(&record);
-                let record = Arc::new(record);
-                if let Some(mut vec) = self.doing_v6.remove(&record.domain) {
-                    if let Some(sender) = vec.pop() {
-                        let _ = sender.send((Arc::clone(&record), ResolvedRecordSource::Query));
-                        self.stats.query_aaaa.add_query_cached_n(vec.len());
-                        for sender in vec.into_iter() {
-                            let _ = sender.send((Arc::clone(&record), ResolvedRecordSource::Cache));
-                        }
-                    }
+                let need_dns64 = self.config.runtime.dns64_prefix.is_some()
+                    && record
+                        .result
+                        .as_ref()
+                        .map(|v| v.is_empty())
+                        .unwrap_or(false);
+                if need_dns64 {
+                    self.start_dns64(record);
+                } else {
+                    self.finish_v6(record);
                 }
-                if let Some(expire_at) = record.expire {
-                    Self::update_cache(&mut self.cache_v6, &mut self.expired_v6, record, expire_at);
+            }
+        }
+    }
+
+    fn finish_v6(&mut self, record: ResolvedRecord) {
+        let record = Arc::new(record);
+        if let Some(mut vec) = self.doing_v6.remove(&record.domain) {
+            if let Some(sender) = vec.pop() {
+                let _ = sender.send((Arc::clone(&record), ResolvedRecordSource::Query));
+                self.stats.query_aaaa.add_query_cached_n(vec.len());
+                for sender in vec.into_iter() {
+                    let _ = sender.send((Arc::clone(&record), ResolvedRecordSource::Cache));
                 }
             }
         }
+        if keep_stale(&self.cache_v6, &record) {
+            return;
+        }
+        if let Some(expire_at) = record.expire {
+            Self::update_cache(&mut self.cache_v6, &mut self.expired_v6, record, expire_at);
+        }
+    }
+
+    /// there is no AAAA record, so we need to synthesize it from the A record
+    fn start_dns64(&mut self, v6_record: ResolvedRecord) {
+        if let Some(r) = self.cache_v4.get(&v6_record.domain) {
+            let v4_record = Arc::clone(&r.inner);
+            self.finish_dns64(v6_record, &v4_record);
+            return;
+        }
+
+        let domain = v6_record.domain.to_owned();
+        self.doing_dns64.insert(domain.to_owned(), v6_record);
+        self.query_v4_in_background(domain);
+    }
+
+    /// send the A query if there is no one running, with no client waiting for it
+    fn query_v4_in_background(&mut self, domain: String) {
+        if let hash_map::Entry::Vacant(v) = self.doing_v4.entry(domain.to_owned()) {
+            v.insert(Vec::new());
+            if let Some(driver) = &self.driver {
+                self.stats.query_a.add_query_driver();
+                driver.query_v4(domain, &self.config.runtime, self.rsp_sender.clone());
+            }
+        }
+    }
+
+    /// send the AAAA query if there is no one running, with no client waiting for it
+    fn query_v6_in_background(&mut self, domain: String) {
+        if let hash_map::Entry::Vacant(v) = self.doing_v6.entry(domain.to_owned()) {
+            v.insert(Vec::new());
+            if let Some(driver) = &self.driver {
+                self.stats.query_aaaa.add_query_driver();
+                driver.query_v6(domain, &self.config.runtime, self.rsp_sender.clone());
+            }
+        }
+    }
+
+    fn finish_dns64(&mut self, v6_record: ResolvedRecord, v4_record: &ResolvedRecord) {
+        match self.config.runtime.dns64_prefix {
+            Some(prefix) => {
+                let record = synthesize_dns64_record(&prefix, v6_record, v4_record);
+                self.finish_v6(record);
+            }
+            None => self.finish_v6(v6_record),
+        }
+    }
+
+    fn handle_expired(
+        cache: &mut AHashMap<String, CachedRecord>,
+        expire_queue: &mut DelayQueue<String>,
+        domain: String,
+        serve_stale_period: Duration,
+    ) {
+        match cache.get_mut(&domain) {
+            Some(v) if !v.stale && !serve_stale_period.is_zero() && v.inner.is_usable() => {
+                // keep it for serve-stale
+                let stale_until = v.expire_at + serve_stale_period;
+                v.expire_key = Some(expire_queue.insert_at(domain, stale_until));
+                v.stale = true;
+            }
+            _ => {
+                cache.remove(&domain);
+            }
+        }
     }
 
-    fn handle_expired_v4(&mut self, domain: &str) {
+    fn handle_expired_v4(&mut self, domain: String) {
         trace!("clean expired v4 for domain {domain}");
-        self.cache_v4.remove(domain);
+        Self::handle_expired(
+            &mut self.cache_v4,
+            &mut self.expired_v4,
+            domain,
+            self.config.runtime.serve_stale_period,
+        );
     }
-    fn handle_expired_v6(&mut self, domain: &str) {
+    fn handle_expired_v6(&mut self, domain: String) {
         trace!("clean expired v6 for domain {domain}");
-        self.cache_v6.remove(domain);
+        Self::handle_expired(
+            &mut self.cache_v6,
+            &mut self.expired_v6,
+            domain,
+            self.config.runtime.serve_stale_period,
+        );
     }
 
     fn handle_req(&mut self, req: ResolveDriverRequest) {
         match req {
             ResolveDriverRequest::GetV4(domain, sender) => {
                 self.stats.query_a.add_query_total();
-                match self.cache_v4.get(&domain) {
-                    Some(r) => {
+                match self
+                    .cache_v4
+                    .get(&domain)
+                    .map(|r| (Arc::clone(&r.inner), r.stale))
+                {
+                    Some((record, false)) => {
+                        self.stats.query_a.add_query_cached();
+                        let _ = sender.send((record, ResolvedRecordSource::Cache));
+                    }
+                    Some((record, true)) => {
                         self.stats.query_a.add_query_cached();
-                        let _ = sender.send((Arc::clone(&r.inner), ResolvedRecordSource::Cache));
+                        let _ = sender.send((record, ResolvedRecordSource::Stale));
+                        self.query_v4_in_background(domain);
                     }
                     None => match self.doing_v4.entry(domain.to_owned()) {
                         hash_map::Entry::Occupied(mut o) => {
@@ -209,10 +339,19 @@ impl ResolverRuntime {
             }
             ResolveDriverRequest::GetV6(domain, sender) => {
                 self.stats.query_aaaa.add_query_total();
-                match self.cache_v6.get(&domain) {
-                    Some(r) => {
+                match self
+                    .cache_v6
+                    .get(&domain)
+                    .map(|r| (Arc::clone(&r.inner), r.stale))
+                {
+                    Some((record, false)) => {
+                        self.stats.query_aaaa.add_query_cached();
+                        let _ = sender.send((record, ResolvedRecordSource::Cache));
+                    }
+                    Some((record, true)) => {
                         self.stats.query_aaaa.add_query_cached();
-                        let _ = sender.send((Arc::clone(&r.inner), ResolvedRecordSource::Cache));
+                        let _ = sender.send((record, ResolvedRecordSource::Stale));
+                        self.query_v6_in_background(domain);
                     }
                     None => match self.doing_v6.entry(domain.to_owned()) {
                         hash_map::Entry::Occupied(mut o) => {
@@ -254,9 +393,145 @@ impl ResolverRuntime {
         update(&self.stats.memory_aaaa, &self.cache_v6, &self.doing_v6);
     }
 
+    fn build_cache_snapshot(&self) -> CacheSnapshot {
+        fn add_entries(
+            entries: &mut Vec<CacheSnapshotEntry>,
+            cache_ht: &AHashMap<String, CachedRecord>,
+        ) {
+            let now = Instant::now();
+            let sys_now = SystemTime::now();
+            for (domain, r) in cache_ht {
+                let Ok(addrs) = &r.inner.result else {
+                    continue;
+                };
+                if addrs.is_empty() {
+                    continue;
+                }
+                let expire = if r.expire_at > now {
+                    sys_now + r.expire_at.duration_since(now)
+                } else {
+                    sys_now - now.duration_since(r.expire_at)
+                };
+                entries.push(CacheSnapshotEntry {
+                    domain: domain.to_string(),
+                    expire,
+                    addrs: addrs.clone(),
+                });
+            }
+        }
+
+        let mut snapshot = CacheSnapshot::default();
+        add_entries(&mut snapshot.v4, &self.cache_v4);
+        add_entries(&mut snapshot.v6, &self.cache_v6);
+        snapshot
+    }
+
+    fn load_cache_snapshot_in_background(&mut self) {
+        if let Some(path) = &self.config.runtime.cache_snapshot_file {
+            let path = path.clone();
+            self.snapshot_loading = Some(tokio::task::spawn_blocking(move || {
+                CacheSnapshot::load(&path)
+            }));
+        }
+    }
+
+    fn poll_snapshot_loading(&mut self, cx: &mut Context<'_>) {
+        let Some(handle) = &mut self.snapshot_loading else {
+            return;
+        };
+        let r = match Pin::new(handle).poll(cx) {
+            Poll::Pending => return,
+            Poll::Ready(r) => r,
+        };
+        self.snapshot_loading = None;
+
+        let snapshot = match r {
+            Ok(Ok(snapshot)) => snapshot,
+            Ok(Err(e)) => {
+                warn!(
+                    "resolver {} failed to load cache snapshot: {e:?}",
+                    self.config.name
+                );
+                return;
+            }
+            Err(e) => {
+                warn!(
+                    "resolver {} failed to join cache snapshot load task: {e}",
+                    self.config.name
+                );
+                return;
+            }
+        };
+
+        // records already resolved while loading will be kept
+        let serve_stale_period = self.config.runtime.serve_stale_period;
+        load_snapshot_entries(
+            snapshot.v4,
+            &mut self.cache_v4,
+            &mut self.expired_v4,
+            serve_stale_period,
+        );
+        load_snapshot_entries(
+            snapshot.v6,
+            &mut self.cache_v6,
+            &mut self.expired_v6,
+            serve_stale_period,
+        );
+        self.update_mem_stats();
+    }
+
+    fn save_cache_snapshot_in_background(&self) -> Option<JoinHandle<()>> {
+        if self.snapshot_loading.is_some() {
+            // don't overwrite the snapshot file before it's loaded
+            return None;
+        }
+        let path = self.config.runtime.cache_snapshot_file.clone()?;
+        let snapshot = self.build_cache_snapshot();
+        let resolver_name = self.config.name.to_owned();
+        let handle = tokio::task::spawn_blocking(move || {
+            if let Err(e) = snapshot.save(&path) {
+                warn!("resolver {resolver_name} failed to save cache snapshot: {e:?}");
+            }
+        });
+        Some(handle)
+    }
+
+    fn poll_snapshot_saving(&mut self, cx: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+        match &mut self.snapshot_saving {
+            Some(handle) => Pin::new(handle).poll(cx).map(|_| Ok(())),
+            None => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_snapshot_interval(&mut self, cx: &mut Context<'_>) {
+        if self.config.runtime.cache_snapshot_file.is_none() {
+            return;
+        }
+
+        let interval = self.snapshot_interval.get_or_insert_with(|| {
+            // tokio interval will panic if the period is zero
+            let period = self
+                .config
+                .runtime
+                .cache_snapshot_interval
+                .max(MINIMUM_SNAPSHOT_INTERVAL);
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        if interval.poll_tick(cx).is_ready() {
+            let _ = self.save_cache_snapshot_in_background();
+        }
+    }
+
     fn poll_loop(&mut self, cx: &mut Context<'_>) -> Poll<anyhow::Result<()>> {
+        if self.snapshot_saving.is_some() {
+            // wait for the final snapshot to be saved before quit
+            return self.poll_snapshot_saving(cx);
+        }
         if self.driver.is_none() {
             self.driver = Some(self.config.driver.spawn_resolver_driver()?);
+            self.load_cache_snapshot_in_background();
         }
 
         'outer: loop {
@@ -294,7 +569,7 @@ impl ResolverRuntime {
                     Poll::Ready(None) => break, // all items fetched
                     Poll::Ready(Some(t)) => {
                         update_mem_stats = true;
-                        self.handle_expired_v4(t.get_ref());
+                        self.handle_expired_v4(t.into_inner());
                     }
                 }
             }
@@ -304,7 +579,7 @@ impl ResolverRuntime {
                     Poll::Ready(None) => break, // all items fetched
                     Poll::Ready(Some(t)) => {
                         update_mem_stats = true;
-                        self.handle_expired_v6(t.get_ref());
+                        self.handle_expired_v6(t.into_inner());
                     }
                 }
             }
@@ -313,6 +588,9 @@ impl ResolverRuntime {
                 self.update_mem_stats();
             }
 
+            self.poll_snapshot_loading(cx);
+            self.poll_snapshot_interval(cx);
+
             // handle request
             for _ in 1..self.config.runtime.batch_request_count {
                 let req = match self.req_receiver.poll_recv(cx) {
@@ -327,7 +605,96 @@ impl ResolverRuntime {
             }
         }
 
-        Poll::Ready(Ok(()))
+        self.snapshot_saving = self.save_cache_snapshot_in_background();
+        self.poll_snapshot_saving(cx)
+    }
+}
+
+/// load the snapshot entries that are not expired, or still in the serve-stale period
+fn load_snapshot_entries(
+    entries: Vec<CacheSnapshotEntry>,
+    cache_ht: &mut AHashMap<String, CachedRecord>,
+    expire_queue: &mut DelayQueue<String>,
+    serve_stale_period: Duration,
+) {
+    let now = Instant::now();
+    let sys_now = SystemTime::now();
+    for entry in entries {
+        if entry.addrs.is_empty() || cache_ht.contains_key(&entry.domain) {
+            continue;
+        }
+        // use the remaining TTL of the record
+        let (expire_at, remove_at, stale) = match entry.expire.duration_since(sys_now) {
+            Ok(left) => (now + left, now + left, false),
+            Err(e) => {
+                let passed = e.duration();
+                if passed >= serve_stale_period {
+                    continue;
+                }
+                let expire_at = now.checked_sub(passed).unwrap_or(now);
+                (expire_at, now + (serve_stale_period - passed), true)
+            }
+        };
+        let expire_key = expire_queue.insert_at(entry.domain.to_owned(), remove_at);
+        let record = ResolvedRecord {
+            domain: entry.domain.to_owned(),
+            created: now,
+            expire: Some(expire_at),
+            result: Ok(entry.addrs),
+        };
+        cache_ht.insert(
+            entry.domain,
+            CachedRecord {
+                inner: Arc::new(record),
+                expire_at,
+                expire_key: Some(expire_key),
+                stale,
+            },
+        );
+    }
+}
+
+/// keep the stale record if we failed to refresh it, but not for authoritative negative answer
+fn keep_stale(cache: &AHashMap<String, CachedRecord>, record: &ResolvedRecord) -> bool {
+    let Err(e) = &record.result else {
+        return false;
+    };
+    if matches!(e, ResolveError::FromServer(ResolveServerError::NotFound)) {
+        return false;
+    }
+    cache.get(&record.domain).map(|r| r.stale).unwrap_or(false)
+}
+
+/// the empty AAAA record will be returned if there is no usable A record
+fn synthesize_dns64_record(
+    prefix: &Nat64Prefix,
+    v6_record: ResolvedRecord,
+    v4_record: &ResolvedRecord,
+) -> ResolvedRecord {
+    let Ok(addrs) = &v4_record.result else {
+        return v6_record;
+    };
+    if addrs.is_empty() {
+        return v6_record;
+    }
+
+    let addrs = addrs
+        .iter()
+        .filter_map(|ip| match ip {
+            IpAddr::V4(ip4) => Some(IpAddr::V6(prefix.embed(*ip4))),
+            IpAddr::V6(_) => None,
+        })
+        .collect();
+    // use the smaller TTL of the negative AAAA and the A record
+    let expire = match (v6_record.expire, v4_record.expire) {
+        (Some(e6), Some(e4)) => Some(e6.min(e4)),
+        (e6, e4) => e6.or(e4),
+    };
+    ResolvedRecord {
+        domain: v6_record.domain,
+        created: v6_record.created,
+        expire,
+        result: Ok(addrs),
     }
 }
 
@@ -338,3 +705,391 @@ impl Future for ResolverRuntime {
         (*self).poll_loop(cx)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use crate::driver::hosts::{HostsDriverConfig, HostsDriverStaticConfig};
+    use crate::{AnyResolveDriverConfig, ResolverRuntimeConfig};
+
+    const TEST_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+    fn new_record(domain: &str, result: Result<Vec<IpAddr>, ResolveError>) -> ResolvedRecord {
+        let created = Instant::now();
+        ResolvedRecord {
+            domain: domain.to_string(),
+            created,
+            expire: Some(created + Duration::from_secs(10)),
+            result,
+        }
+    }
+
+    fn snapshot_entry(domain: &str, expire: SystemTime) -> CacheSnapshotEntry {
+        CacheSnapshotEntry {
+            domain: domain.to_string(),
+            expire,
+            addrs: vec![TEST_IP],
+        }
+    }
+
+    fn cache_record(
+        cache: &mut AHashMap<String, CachedRecord>,
+        expire_queue: &mut DelayQueue<String>,
+        record: ResolvedRecord,
+    ) {
+        let expire_at = record.expire.unwrap();
+        ResolverRuntime::update_cache(cache, expire_queue, Arc::new(record), expire_at);
+    }
+
+    #[tokio::test]
+    async fn load_snapshot() {
+        let mut cache = AHashMap::new();
+        let mut expire_queue = DelayQueue::new();
+        let now = SystemTime::now();
+
+        let entries = vec![
+            snapshot_entry("fresh.example.net", now + Duration::from_secs(60)),
+            snapshot_entry("stale.example.net", now - Duration::from_secs(10)),
+            snapshot_entry("expired.example.net", now - Duration::from_secs(120)),
+            CacheSnapshotEntry {
+                domain: "empty.example.net".to_string(),
+                expire: now + Duration::from_secs(60),
+                addrs: Vec::new(),
+            },
+        ];
+        load_snapshot_entries(
+            entries,
+            &mut cache,
+            &mut expire_queue,
+            Duration::from_secs(60),
+        );
+        assert_eq!(cache.len(), 2);
+        assert_eq!(expire_queue.len(), 2);
+        let fresh = cache.get("fresh.example.net").unwrap();
+        assert!(!fresh.stale);
+        assert!(fresh.expire_at > Instant::now());
+        assert_eq!(fresh.inner.result.as_ref().unwrap(), &vec![TEST_IP]);
+        let stale = cache.get("stale.example.net").unwrap();
+        assert!(stale.stale);
+        assert!(stale.expire_at <= Instant::now());
+
+        // existing records should not be overwritten
+        let entries = vec![snapshot_entry(
+            "fresh.example.net",
+            now + Duration::from_secs(600),
+        )];
+        load_snapshot_entries(
+            entries,
+            &mut cache,
+            &mut expire_queue,
+            Duration::from_secs(60),
+        );
+        let fresh = cache.get("fresh.example.net").unwrap();
+        assert!(fresh.expire_at < Instant::now() + Duration::from_secs(61));
+    }
+
+    #[tokio::test]
+    async fn load_snapshot_no_serve_stale() {
+        let mut cache = AHashMap::new();
+        let mut expire_queue = DelayQueue::new();
+        let now = SystemTime::now();
+
+        let entries = vec![
+            snapshot_entry("fresh.example.net", now + Duration::from_secs(60)),
+            snapshot_entry("stale.example.net", now - Duration::from_secs(10)),
+        ];
+        load_snapshot_entries(entries, &mut cache, &mut expire_queue, Duration::ZERO);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains_key("fresh.example.net"));
+    }
+
+    #[tokio::test]
+    async fn expire_serve_stale() {
+        let mut cache = AHashMap::new();
+        let mut expire_queue = DelayQueue::new();
+        let serve_stale_period = Duration::from_secs(60);
+
+        cache_record(
+            &mut cache,
+            &mut expire_queue,
+            new_record("a.example.net", Ok(vec![TEST_IP])),
+        );
+        ResolverRuntime::handle_expired(
+            &mut cache,
+            &mut expire_queue,
+            "a.example.net".to_string(),
+            serve_stale_period,
+        );
+        assert!(cache.get("a.example.net").unwrap().stale);
+
+        // removed at the end of the serve-stale period
+        ResolverRuntime::handle_expired(
+            &mut cache,
+            &mut expire_queue,
+            "a.example.net".to_string(),
+            serve_stale_period,
+        );
+        assert!(cache.is_empty());
+
+        // a fresh answer makes the record usable again
+        cache_record(
+            &mut cache,
+            &mut expire_queue,
+            new_record("b.example.net", Ok(vec![TEST_IP])),
+        );
+        ResolverRuntime::handle_expired(
+            &mut cache,
+            &mut expire_queue,
+            "b.example.net".to_string(),
+            serve_stale_period,
+        );
+        cache_record(
+            &mut cache,
+            &mut expire_queue,
+            new_record("b.example.net", Ok(vec![TEST_IP])),
+        );
+        assert!(!cache.get("b.example.net").unwrap().stale);
+    }
+
+    #[tokio::test]
+    async fn expire_no_serve_stale() {
+        let mut cache = AHashMap::new();
+        let mut expire_queue = DelayQueue::new();
+
+        cache_record(
+            &mut cache,
+            &mut expire_queue,
+            new_record("a.example.net", Ok(vec![TEST_IP])),
+        );
+        ResolverRuntime::handle_expired(
+            &mut cache,
+            &mut expire_queue,
+            "a.example.net".to_string(),
+            Duration::ZERO,
+        );
+        assert!(cache.is_empty());
+
+        // unusable records are never kept
+        cache_record(
+            &mut cache,
+            &mut expire_queue,
+            new_record("b.example.net", Ok(Vec::new())),
+        );
+        cache_record(
+            &mut cache,
+            &mut expire_queue,
+            ResolvedRecord::timed_out("c.example.net".to_string(), 10),
+        );
+        for domain in ["b.example.net", "c.example.net"] {
+            ResolverRuntime::handle_expired(
+                &mut cache,
+                &mut expire_queue,
+                domain.to_string(),
+                Duration::from_secs(60),
+            );
+        }
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keep_stale_record() {
+        let mut cache = AHashMap::new();
+        let mut expire_queue = DelayQueue::new();
+
+        let timed_out = ResolvedRecord::timed_out("a.example.net".to_string(), 10);
+        let not_found = new_record("a.example.net", Err(ResolveServerError::NotFound.into()));
+        let ok = new_record("a.example.net", Ok(vec![TEST_IP]));
+
+        // not cached
+        assert!(!keep_stale(&cache, &timed_out));
+
+        cache_record(&mut cache, &mut expire_queue, ok.clone());
+        // not stale
+        assert!(!keep_stale(&cache, &timed_out));
+
+        ResolverRuntime::handle_expired(
+            &mut cache,
+            &mut expire_queue,
+            "a.example.net".to_string(),
+            Duration::from_secs(60),
+        );
+        assert!(keep_stale(&cache, &timed_out));
+        assert!(!keep_stale(&cache, &not_found));
+        assert!(!keep_stale(&cache, &ok));
+    }
+
+    fn dns64_runtime() -> ResolverRuntime {
+        let mut static_conf = HostsDriverStaticConfig::default();
+        static_conf.add_static_record("v4only.example.net", vec![TEST_IP], 60);
+        let mut driver_conf = HostsDriverConfig::default();
+        driver_conf.set_static_config(static_conf);
+        let mut runtime_conf = ResolverRuntimeConfig::default();
+        runtime_conf.dns64_prefix = Some(Nat64Prefix::WELL_KNOWN);
+        let config = ResolverConfig {
+            name: "test".to_string(),
+            driver: AnyResolveDriverConfig::Hosts(driver_conf),
+            runtime: runtime_conf,
+        };
+
+        let (_req_sender, req_receiver) = mpsc::unbounded_channel();
+        let (_ctl_sender, ctl_receiver) = mpsc::unbounded_channel();
+        let mut runtime = ResolverRuntime::new(
+            config,
+            req_receiver,
+            ctl_receiver,
+            Arc::new(ResolverStats::default()),
+        );
+        runtime.driver = Some(runtime.config.driver.spawn_resolver_driver().unwrap());
+        runtime
+    }
+
+    #[tokio::test]
+    async fn snapshot_in_background() {
+        let path =
+            std::env::temp_dir().join(format!("g3-resolver-snapshot-{}.txt", std::process::id()));
+        let snapshot = CacheSnapshot {
+            v4: vec![snapshot_entry(
+                "a.example.net",
+                SystemTime::now() + Duration::from_secs(60),
+            )],
+            v6: Vec::new(),
+        };
+        snapshot.save(&path).unwrap();
+
+        let mut runtime = dns64_runtime();
+        runtime.config.runtime.cache_snapshot_file = Some(path.clone());
+        runtime.load_cache_snapshot_in_background();
+        // the periodic save is skipped until the snapshot is loaded
+        assert!(runtime.save_cache_snapshot_in_background().is_none());
+        std::future::poll_fn(|cx| {
+            runtime.poll_snapshot_loading(cx);
+            if runtime.snapshot_loading.is_some() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+        assert!(runtime.cache_v4.contains_key("a.example.net"));
+
+        std::fs::remove_file(&path).unwrap();
+        runtime.snapshot_saving = runtime.save_cache_snapshot_in_background();
+        assert!(runtime.snapshot_saving.is_some());
+        std::future::poll_fn(|cx| runtime.poll_snapshot_saving(cx))
+            .await
+            .unwrap();
+        let snapshot = CacheSnapshot::load(&path).unwrap();
+        assert_eq!(snapshot.v4.len(), 1);
+        assert_eq!(snapshot.v4[0].domain, "a.example.net");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn wait_v6(
+        runtime: &mut ResolverRuntime,
+        domain: &str,
+    ) -> oneshot::Receiver<(ArcResolvedRecord, ResolvedRecordSource)> {
+        let (sender, receiver) = oneshot::channel();
+        runtime.doing_v6.insert(domain.to_string(), vec![sender]);
+        receiver
+    }
+
+    fn synthesized_ip() -> IpAddr {
+        IpAddr::V6(Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0xc000, 0x0201))
+    }
+
+    #[test]
+    fn synthesize_dns64() {
+        let prefix = Nat64Prefix::WELL_KNOWN;
+        let created = Instant::now();
+        let v6_record = ResolvedRecord {
+            domain: "a.example.net".to_string(),
+            created,
+            expire: Some(created + Duration::from_secs(30)),
+            result: Ok(Vec::new()),
+        };
+        let v4_record = ResolvedRecord {
+            domain: "a.example.net".to_string(),
+            created,
+            expire: Some(created + Duration::from_secs(300)),
+            result: Ok(vec![TEST_IP, IpAddr::V6(Ipv6Addr::LOCALHOST)]),
+        };
+        let r = synthesize_dns64_record(&prefix, v6_record.clone(), &v4_record);
+        assert_eq!(r.result.unwrap(), vec![synthesized_ip()]);
+        assert_eq!(r.expire, v6_record.expire);
+
+        // keep the negative AAAA record if there is no usable A record
+        let v4_failed = ResolvedRecord::timed_out("a.example.net".to_string(), 10);
+        let r = synthesize_dns64_record(&prefix, v6_record.clone(), &v4_failed);
+        assert!(r.result.unwrap().is_empty());
+        let v4_empty = new_record("a.example.net", Ok(Vec::new()));
+        let r = synthesize_dns64_record(&prefix, v6_record, &v4_empty);
+        assert!(r.result.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dns64_with_cached_v4() {
+        let mut runtime = dns64_runtime();
+        let domain = "v4only.example.net";
+        cache_record(
+            &mut runtime.cache_v4,
+            &mut runtime.expired_v4,
+            new_record(domain, Ok(vec![TEST_IP])),
+        );
+
+        let mut receiver = wait_v6(&mut runtime, domain);
+        runtime.handle_rsp(ResolveDriverResponse::V6(new_record(
+            domain,
+            Ok(Vec::new()),
+        )));
+        let (r, _) = receiver.try_recv().unwrap();
+        assert_eq!(r.result.as_ref().unwrap(), &vec![synthesized_ip()]);
+        assert!(runtime.doing_dns64.is_empty());
+        assert!(runtime.cache_v6.contains_key(domain));
+    }
+
+    #[tokio::test]
+    async fn dns64_query_v4() {
+        let mut runtime = dns64_runtime();
+        let domain = "v4only.example.net";
+
+        let mut receiver = wait_v6(&mut runtime, domain);
+        runtime.handle_rsp(ResolveDriverResponse::V6(new_record(
+            domain,
+            Ok(Vec::new()),
+        )));
+        assert!(receiver.try_recv().is_err());
+        assert!(runtime.doing_dns64.contains_key(domain));
+        assert!(runtime.doing_v4.contains_key(domain));
+
+        // the hosts driver will send the A record response at once
+        let rsp = runtime.rsp_receiver.try_recv().unwrap();
+        runtime.handle_rsp(rsp);
+        let (r, _) = receiver.try_recv().unwrap();
+        assert_eq!(r.result.as_ref().unwrap(), &vec![synthesized_ip()]);
+        assert!(runtime.doing_dns64.is_empty());
+        assert!(runtime.cache_v4.contains_key(domain));
+    }
+
+    #[tokio::test]
+    async fn dns64_not_needed() {
+        let mut runtime = dns64_runtime();
+        let domain = "v4only.example.net";
+
+        let mut receiver = wait_v6(&mut runtime, domain);
+        let record = ResolvedRecord::timed_out(domain.to_string(), 10);
+        runtime.handle_rsp(ResolveDriverResponse::V6(record));
+        let (r, _) = receiver.try_recv().unwrap();
+        assert!(r.is_err());
+        assert!(runtime.doing_dns64.is_empty());
+        assert!(runtime.doing_v4.is_empty());
+
+        let ip6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        let mut receiver = wait_v6(&mut runtime, domain);
+        runtime.handle_rsp(ResolveDriverResponse::V6(new_record(domain, Ok(vec![ip6]))));
+        let (r, _) = receiver.try_recv().unwrap();
+        assert_eq!(r.result.as_ref().unwrap(), &vec![ip6]);
+        assert!(runtime.doing_v4.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+
+pub(crate) struct CacheSnapshotEntry {
+    pub(crate) domain: String,
+    pub(crate) expire: SystemTime,
+    pub(crate) addrs: Vec<IpAddr>,
+}
+
+#[derive(Default)]
+pub(crate) struct CacheSnapshot {
+    pub(crate) v4: Vec<CacheSnapshotEntry>,
+    pub(crate) v6: Vec<CacheSnapshotEntry>,
+}
+
+impl CacheSnapshot {
+    /// Each line is in format `<A|AAAA> <domain> <expire unix timestamp> <ip>...`
+    fn encode(&self) -> String {
+        let mut s = String::new();
+        let mut add_entries = |rr_type: &str, entries: &[CacheSnapshotEntry]| {
+            for entry in entries {
+                let expire = entry
+                    .expire
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let _ = write!(s, "{rr_type} {} {expire}", entry.domain);
+                for ip in &entry.addrs {
+                    let _ = write!(s, " {ip}");
+                }
+                s.push('\n');
+            }
+        };
+        add_entries("A", &self.v4);
+        add_entries("AAAA", &self.v6);
+        s
+    }
+
+    fn decode(content: &str) -> anyhow::Result<Self> {
+        let mut snapshot = CacheSnapshot::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            let (Some(rr_type), Some(domain), Some(expire)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                return Err(anyhow!("incomplete record at line {}", i + 1));
+            };
+            let expire = u64::from_str(expire)
+                .map_err(|e| anyhow!("invalid expire timestamp at line {}: {e}", i + 1))?;
+            let mut addrs = Vec::new();
+            for ip in parts {
+                let ip = IpAddr::from_str(ip)
+                    .map_err(|e| anyhow!("invalid ip address at line {}: {e}", i + 1))?;
+                addrs.push(ip);
+            }
+            let entry = CacheSnapshotEntry {
+                domain: domain.to_string(),
+                expire: UNIX_EPOCH + Duration::from_secs(expire),
+                addrs,
+            };
+            match rr_type {
+                "A" => snapshot.v4.push(entry),
+                "AAAA" => snapshot.v6.push(entry),
+                _ => return Err(anyhow!("invalid record type at line {}", i + 1)),
+            }
+        }
+        Ok(snapshot)
+    }
+
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read file {}: {e}", path.display()))?;
+        CacheSnapshot::decode(&content)
+            .context(format!("invalid cache snapshot file {}", path.display()))
+    }
+
+    /// write to a temp file first and then rename it, so the file is always complete
+    pub(crate) fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, self.encode())
+            .map_err(|e| anyhow!("failed to write to temp file {tmp_path:?}: {e}"))?;
+        std::fs::rename(&tmp_path, path)
+            .map_err(|e| anyhow!("failed to rename {tmp_path:?} to {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn encode_decode() {
+        let expire = UNIX_EPOCH + Duration::from_secs(1700000000);
+        let snapshot = CacheSnapshot {
+            v4: vec![CacheSnapshotEntry {
+                domain: "www.example.net".to_string(),
+                expire,
+                addrs: vec![
+                    IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+                    IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+                ],
+            }],
+            v6: vec![CacheSnapshotEntry {
+                domain: "www.example.net".to_string(),
+                expire,
+                addrs: vec![IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))],
+            }],
+        };
+
+        let content = snapshot.encode();
+        assert_eq!(
+            content,
+            "A www.example.net 1700000000 192.0.2.1 192.0.2.2\n\
+             AAAA www.example.net 1700000000 2001:db8::1\n"
+        );
+
+        let decoded = CacheSnapshot::decode(&content).unwrap();
+        assert_eq!(decoded.v4.len(), 1);
+        assert_eq!(decoded.v4[0].domain, "www.example.net");
+        assert_eq!(decoded.v4[0].expire, expire);
+        assert_eq!(decoded.v4[0].addrs, snapshot.v4[0].addrs);
+        assert_eq!(decoded.v6.len(), 1);
+        assert_eq!(decoded.v6[0].addrs, snapshot.v6[0].addrs);
+
+        assert!(CacheSnapshot::decode("# comment\n\n")
+            .unwrap()
+            .v4
+            .is_empty());
+        assert!(CacheSnapshot::decode("A www.example.net").is_err());
+        assert!(CacheSnapshot::decode("MX www.example.net 1700000000").is_err());
+        assert!(CacheSnapshot::decode("A www.example.net 1700000000 ::x").is_err());
+    }
+}
@@ -28,6 +28,8 @@ pub struct ResolverQueryStats {
     driver_timeout: AtomicU64,
     driver_refused: AtomicU64,
     driver_malformed: AtomicU64,
+    driver_dnssec_bogus: AtomicU64,
+    driver_dnssec_insecure: AtomicU64,
     server_refused: AtomicU64,
     server_malformed: AtomicU64,
     server_not_found: AtomicU64,
@@ -42,6 +44,8 @@ pub struct ResolverQuerySnapshot {
     pub driver_timeout: u64,
     pub driver_refused: u64,
     pub driver_malformed: u64,
+    pub driver_dnssec_bogus: u64,
+    pub driver_dnssec_insecure: u64,
     pub server_refused: u64,
     pub server_malformed: u64,
     pub server_not_found: u64,
@@ -57,6 +61,8 @@ impl ResolverQueryStats {
             driver_timeout: self.driver_timeout.load(Ordering::Relaxed),
             driver_refused: self.driver_refused.load(Ordering::Relaxed),
             driver_malformed: self.driver_malformed.load(Ordering::Relaxed),
+            driver_dnssec_bogus: self.driver_dnssec_bogus.load(Ordering::Relaxed),
+            driver_dnssec_insecure: self.driver_dnssec_insecure.load(Ordering::Relaxed),
             server_refused: self.server_refused.load(Ordering::Relaxed),
             server_malformed: self.server_malformed.load(Ordering::Relaxed),
             server_not_found: self.server_not_found.load(Ordering::Relaxed),
@@ -97,6 +103,16 @@ impl ResolverQueryStats {
         self.driver_malformed.fetch_add(1, Ordering::Relaxed);
     }
 
+    #[inline]
+    fn add_driver_dnssec_bogus(&self) {
+        self.driver_dnssec_bogus.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn add_driver_dnssec_insecure(&self) {
+        self.driver_dnssec_insecure.fetch_add(1, Ordering::Relaxed);
+    }
+
     #[inline]
     fn add_server_refused(&self) {
         self.server_refused.fetch_add(1, Ordering::Relaxed);
@@ -140,6 +156,8 @@ impl ResolverQueryStats {
             ResolveDriverError::BadName
             | ResolveDriverError::BadQuery
             | ResolveDriverError::BadResp => self.add_driver_malformed(),
+            ResolveDriverError::DnssecBogus(_) => self.add_driver_dnssec_bogus(),
+            ResolveDriverError::DnssecInsecure => self.add_driver_dnssec_insecure(),
             _ => {}
         }
     }
@@ -0,0 +1,23 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+
+/// A collector that will receive a copy of all the emitted metrics, in statsd text format.
+pub trait StatsdMetricsCollector: fmt::Debug + Send + Sync {
+    /// The message may contain multiple metrics, separated by '\n'
+    fn collect(&self, msg: &[u8]);
+}
@@ -18,11 +18,12 @@ use std::io;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
 use std::os::unix::net::UnixDatagram;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use g3_types::metrics::MetricsName;
 
-use crate::{StatsdClient, StatsdMetricsSink};
+use crate::{StatsdClient, StatsdMetricsCollector, StatsdMetricsSink};
 
 const UDP_DEFAULT_PORT: u16 = 8125;
 
@@ -30,6 +31,8 @@ const UDP_DEFAULT_PORT: u16 = 8125;
 pub enum StatsdBackend {
     Udp(SocketAddr, Option<IpAddr>),
     Unix(PathBuf),
+    /// send nothing out, only useful if a collector is set
+    Null,
 }
 
 impl Default for StatsdBackend {
@@ -43,6 +46,7 @@ impl Default for StatsdBackend {
 pub struct StatsdClientConfig {
     backend: StatsdBackend,
     prefix: MetricsName,
+    collector: Option<Arc<dyn StatsdMetricsCollector>>,
     pub emit_duration: Duration,
 }
 
@@ -57,6 +61,7 @@ impl StatsdClientConfig {
         StatsdClientConfig {
             backend: StatsdBackend::default(),
             prefix,
+            collector: None,
             emit_duration: Duration::from_millis(200),
         }
     }
@@ -69,8 +74,13 @@ impl StatsdClientConfig {
         self.prefix = prefix;
     }
 
+    /// Set a collector which will receive a copy of all the metrics sent by the built clients
+    pub fn set_collector(&mut self, collector: Arc<dyn StatsdMetricsCollector>) {
+        self.collector = Some(collector);
+    }
+
     pub fn build(&self) -> io::Result<StatsdClient> {
-        let sink = match &self.backend {
+        let mut sink = match &self.backend {
             StatsdBackend::Udp(addr, bind) => {
                 let bind_ip = bind.unwrap_or_else(|| match addr {
                     SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
@@ -83,7 +93,11 @@ impl StatsdClientConfig {
                 let socket = UnixDatagram::unbound()?;
                 StatsdMetricsSink::unix_with_capacity(path.clone(), socket, 4096)
             }
+            StatsdBackend::Null => StatsdMetricsSink::null_with_capacity(4096),
         };
+        if let Some(collector) = &self.collector {
+            sink.set_collector(collector.clone());
+        }
 
         Ok(StatsdClient::new(self.prefix.clone(), sink))
     }
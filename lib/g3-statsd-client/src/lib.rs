@@ -23,5 +23,8 @@ pub use client::StatsdClient;
 mod tag;
 pub use tag::StatsdTagGroup;
 
+mod collector;
+pub use collector::StatsdMetricsCollector;
+
 mod config;
 pub use config::{StatsdBackend, StatsdClientConfig};
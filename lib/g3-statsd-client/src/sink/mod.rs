@@ -20,6 +20,7 @@ use std::os::unix::net::UnixDatagram;
 use std::path::PathBuf;
 #[cfg(test)]
 use std::rc::Rc;
+use std::sync::Arc;
 #[cfg(test)]
 use std::sync::Mutex;
 
@@ -28,6 +29,8 @@ mod buf;
 #[cfg(test)]
 use buf::BufMetricsSink;
 
+use crate::StatsdMetricsCollector;
+
 mod udp;
 use udp::UdpMetricsSink;
 
@@ -39,6 +42,7 @@ enum MetricsSinkIo {
     Buf(BufMetricsSink),
     Udp(UdpMetricsSink),
     Unix(UnixMetricsSink),
+    Null,
 }
 
 impl MetricsSinkIo {
@@ -48,6 +52,7 @@ impl MetricsSinkIo {
             MetricsSinkIo::Buf(b) => b.send_msg(buf),
             MetricsSinkIo::Udp(s) => s.send_msg(buf),
             MetricsSinkIo::Unix(s) => s.send_msg(buf),
+            MetricsSinkIo::Null => Ok(buf.len()),
         }
     }
 }
@@ -56,6 +61,7 @@ pub(crate) struct StatsdMetricsSink {
     cache_size: usize,
     buf: Vec<u8>,
     io: MetricsSinkIo,
+    collector: Option<Arc<dyn StatsdMetricsCollector>>,
 }
 
 impl StatsdMetricsSink {
@@ -65,6 +71,7 @@ impl StatsdMetricsSink {
             cache_size,
             buf: Vec::with_capacity(cache_size),
             io: MetricsSinkIo::Buf(BufMetricsSink::new(buf)),
+            collector: None,
         }
     }
 
@@ -77,6 +84,7 @@ impl StatsdMetricsSink {
             cache_size,
             buf: Vec::with_capacity(cache_size),
             io: MetricsSinkIo::Udp(UdpMetricsSink::new(addr, socket)),
+            collector: None,
         }
     }
 
@@ -89,9 +97,23 @@ impl StatsdMetricsSink {
             cache_size,
             buf: Vec::with_capacity(cache_size),
             io: MetricsSinkIo::Unix(UnixMetricsSink::new(path, socket)),
+            collector: None,
+        }
+    }
+
+    pub(crate) fn null_with_capacity(cache_size: usize) -> Self {
+        StatsdMetricsSink {
+            cache_size,
+            buf: Vec::with_capacity(cache_size),
+            io: MetricsSinkIo::Null,
+            collector: None,
         }
     }
 
+    pub(crate) fn set_collector(&mut self, collector: Arc<dyn StatsdMetricsCollector>) {
+        self.collector = Some(collector);
+    }
+
     pub(super) fn emit<F>(&mut self, msg_len: usize, format: F) -> io::Result<()>
     where
         F: Fn(&mut Vec<u8>),
@@ -116,8 +138,92 @@ impl StatsdMetricsSink {
     }
 
     fn flush_buf(&mut self) -> io::Result<()> {
+        if let Some(collector) = &self.collector {
+            collector.collect(&self.buf);
+            // drop the collected metrics even if the send failed,
+            // or they will be collected again at the next flush
+            let r = self.io.send_msg(&self.buf);
+            self.buf.clear();
+            return r.map(|_| ());
+        }
         self.io.send_msg(&self.buf)?;
         self.buf.clear();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct CountCollector {
+        lines: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl StatsdMetricsCollector for CountCollector {
+        fn collect(&self, msg: &[u8]) {
+            let mut lines = self.lines.lock().unwrap();
+            for line in msg.split(|c| *c == b'\n') {
+                lines.push(line.to_vec());
+            }
+        }
+    }
+
+    #[test]
+    fn collect_once_on_send_failure() {
+        let socket = UnixDatagram::unbound().unwrap();
+        let path = PathBuf::from("/nonexistent/g3-statsd-client-test.sock");
+        let mut sink = StatsdMetricsSink::unix_with_capacity(path, socket, 16);
+        let collector = Arc::new(CountCollector::default());
+        sink.set_collector(collector.clone());
+
+        sink.emit(8, |b| b.extend_from_slice(b"a:1|c")).unwrap();
+        sink.emit(8, |b| b.extend_from_slice(b"b:1|c")).unwrap();
+        // the buffer is full, the flush will fail
+        assert!(sink.emit(8, |b| b.extend_from_slice(b"c:1|c")).is_err());
+        assert!(sink.buf.is_empty());
+
+        sink.emit(8, |b| b.extend_from_slice(b"d:1|c")).unwrap();
+        assert!(sink.flush().is_err());
+        assert!(sink.buf.is_empty());
+        assert!(sink.flush().is_ok());
+
+        let lines = collector.lines.lock().unwrap();
+        assert_eq!(
+            lines.as_slice(),
+            &[b"a:1|c".to_vec(), b"b:1|c".to_vec(), b"d:1|c".to_vec()]
+        );
+    }
+
+    #[test]
+    fn keep_buf_on_send_failure() {
+        let socket = UnixDatagram::unbound().unwrap();
+        let path = PathBuf::from("/nonexistent/g3-statsd-client-test.sock");
+        let mut sink = StatsdMetricsSink::unix_with_capacity(path, socket, 16);
+
+        sink.emit(8, |b| b.extend_from_slice(b"a:1|c")).unwrap();
+        assert!(sink.flush().is_err());
+        // the metrics will be sent again at the next flush if there is no collector
+        assert_eq!(sink.buf.as_slice(), b"a:1|c");
+    }
+
+    #[test]
+    fn null_with_collector() {
+        let mut sink = StatsdMetricsSink::null_with_capacity(16);
+        let collector = Arc::new(CountCollector::default());
+        sink.set_collector(collector.clone());
+
+        sink.emit(8, |b| b.extend_from_slice(b"a:1|c")).unwrap();
+        sink.emit(8, |b| b.extend_from_slice(b"b:1|c")).unwrap();
+        sink.emit(8, |b| b.extend_from_slice(b"c:1|c")).unwrap();
+        sink.flush().unwrap();
+        assert!(sink.buf.is_empty());
+
+        let lines = collector.lines.lock().unwrap();
+        assert_eq!(
+            lines.as_slice(),
+            &[b"a:1|c".to_vec(), b"b:1|c".to_vec(), b"c:1|c".to_vec()]
+        );
+    }
+}
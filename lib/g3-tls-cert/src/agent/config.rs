@@ -16,13 +16,17 @@
 
 use anyhow::anyhow;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
 
 use g3_types::net::SocketBufferConfig;
 
-use super::{CertAgentHandle, QueryRuntime};
+use super::{CertAgentHandle, QueryRuntime, QueryTransport, StreamPeer, StreamQueryTask};
+use crate::cache::CertDiskCache;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CertAgentConfig {
@@ -30,10 +34,15 @@ pub struct CertAgentConfig {
     pub(crate) cache_request_timeout: Duration,
     pub(crate) cache_vanish_wait: Duration,
     pub(crate) query_peer_addr: SocketAddr,
+    pub(crate) query_stream_peer: Option<StreamPeer>,
     pub(crate) query_socket_buffer: SocketBufferConfig,
     pub(crate) query_wait_timeout: Duration,
     pub(crate) protective_cache_ttl: u32,
     pub(crate) maximum_cache_ttl: u32,
+    pub(crate) persist_cache_dir: Option<PathBuf>,
+    pub(crate) persist_cache_max_entries: usize,
+    pub(crate) persist_cache_ttl: u32,
+    pub(crate) mimic_upstream_cert: bool,
 }
 
 impl Default for CertAgentConfig {
@@ -43,10 +52,15 @@ impl Default for CertAgentConfig {
             cache_request_timeout: Duration::from_millis(800),
             cache_vanish_wait: Duration::from_secs(300),
             query_peer_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 2999),
+            query_stream_peer: None,
             query_socket_buffer: SocketBufferConfig::default(),
             query_wait_timeout: Duration::from_millis(400),
             protective_cache_ttl: 10,
             maximum_cache_ttl: 300,
+            persist_cache_dir: None,
+            persist_cache_max_entries: 65536,
+            persist_cache_ttl: 86400,
+            mimic_upstream_cert: false,
         }
     }
 }
@@ -68,6 +82,17 @@ impl CertAgentConfig {
         self.query_peer_addr = addr;
     }
 
+    /// Use a length prefixed tcp stream instead of udp to query the peer
+    pub fn set_query_tcp_peer_addr(&mut self, addr: SocketAddr) {
+        self.query_stream_peer = Some(StreamPeer::Tcp(addr));
+    }
+
+    /// Use a length prefixed unix stream instead of udp to query the peer
+    #[cfg(unix)]
+    pub fn set_query_unix_peer_path(&mut self, path: PathBuf) {
+        self.query_stream_peer = Some(StreamPeer::Unix(path));
+    }
+
     pub fn set_query_socket_buffer(&mut self, config: SocketBufferConfig) {
         self.query_socket_buffer = config;
     }
@@ -84,7 +109,23 @@ impl CertAgentConfig {
         self.maximum_cache_ttl = ttl;
     }
 
-    pub fn spawn_cert_agent(&self) -> anyhow::Result<CertAgentHandle> {
+    pub fn set_persist_cache_dir(&mut self, dir: PathBuf) {
+        self.persist_cache_dir = Some(dir);
+    }
+
+    pub fn set_persist_cache_max_entries(&mut self, count: usize) {
+        self.persist_cache_max_entries = count;
+    }
+
+    pub fn set_persist_cache_ttl(&mut self, ttl: u32) {
+        self.persist_cache_ttl = ttl;
+    }
+
+    pub fn set_mimic_upstream_cert(&mut self, enable: bool) {
+        self.mimic_upstream_cert = enable;
+    }
+
+    fn build_udp_transport(&self) -> anyhow::Result<QueryTransport> {
         use anyhow::Context;
 
         let (socket, _addr) = g3_socket::udp::new_std_bind_connect(
@@ -100,10 +141,36 @@ impl CertAgentConfig {
             )
         })?;
         let socket = UdpSocket::from_std(socket).context("failed to setup udp socket")?;
+        Ok(QueryTransport::Udp(socket))
+    }
+
+    pub fn spawn_cert_agent(&self) -> anyhow::Result<CertAgentHandle> {
+        use anyhow::Context;
+
+        let persist_cache = match &self.persist_cache_dir {
+            Some(dir) => {
+                let cache = CertDiskCache::open(dir, self.persist_cache_max_entries).context(
+                    format!("failed to open persist cache dir {}", dir.display()),
+                )?;
+                Some(Arc::new(cache))
+            }
+            None => None,
+        };
+
+        let transport = match &self.query_stream_peer {
+            Some(peer) => {
+                let (req_sender, req_receiver) = mpsc::unbounded_channel();
+                let (rsp_sender, rsp_receiver) = mpsc::unbounded_channel();
+                let task = StreamQueryTask::new(peer.clone(), req_receiver, rsp_sender);
+                tokio::spawn(task.into_running());
+                QueryTransport::Stream(req_sender, rsp_receiver)
+            }
+            None => self.build_udp_transport()?,
+        };
 
         let (cache_runtime, cache_handle, query_handle) =
             g3_io_ext::spawn_effective_cache(self.cache_request_batch_count);
-        let query_runtime = QueryRuntime::new(self, socket, query_handle);
+        let query_runtime = QueryRuntime::new(self, transport, query_handle, persist_cache);
 
         tokio::spawn(query_runtime);
         tokio::spawn(cache_runtime);
@@ -111,6 +178,7 @@ impl CertAgentConfig {
         Ok(CertAgentHandle::new(
             cache_handle,
             self.cache_request_timeout,
+            self.mimic_upstream_cert,
         ))
     }
 }
@@ -17,6 +17,8 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use openssl::hash::MessageDigest;
+use openssl::x509::X509Ref;
 use rustls::{Certificate, PrivateKey};
 
 use g3_io_ext::EffectiveCacheHandle;
@@ -27,22 +29,52 @@ use super::CacheQueryKey;
 pub struct CertAgentHandle {
     inner: EffectiveCacheHandle<CacheQueryKey, (Vec<Certificate>, PrivateKey)>,
     request_timeout: Duration,
+    mimic_upstream_cert: bool,
 }
 
 impl CertAgentHandle {
     pub(crate) fn new(
         inner: EffectiveCacheHandle<CacheQueryKey, (Vec<Certificate>, PrivateKey)>,
         request_timeout: Duration,
+        mimic_upstream_cert: bool,
     ) -> Self {
         CertAgentHandle {
             inner,
             request_timeout,
+            mimic_upstream_cert,
         }
     }
 
+    /// Whether we should fetch certs with the upstream cert
+    #[inline]
+    pub fn mimic_upstream_cert(&self) -> bool {
+        self.mimic_upstream_cert
+    }
+
     pub async fn fetch(&self, host: String) -> Option<(Vec<Certificate>, PrivateKey)> {
-        let query_key = CacheQueryKey { host };
+        self.fetch_by_key(CacheQueryKey::new(host)).await
+    }
+
+    /// Fetch a cert that mimic the fields of the upstream cert
+    pub async fn fetch_mimic(
+        &self,
+        host: String,
+        upstream_cert: &X509Ref,
+    ) -> Option<(Vec<Certificate>, PrivateKey)> {
+        let (Ok(der), Ok(digest)) = (
+            upstream_cert.to_der(),
+            upstream_cert.digest(MessageDigest::sha256()),
+        ) else {
+            return self.fetch(host).await;
+        };
+        self.fetch_by_key(CacheQueryKey::new_mimic(host, der, digest.to_vec()))
+            .await
+    }
 
+    async fn fetch_by_key(
+        &self,
+        query_key: CacheQueryKey,
+    ) -> Option<(Vec<Certificate>, PrivateKey)> {
         self.inner
             .fetch(Arc::new(query_key), self.request_timeout)
             .await
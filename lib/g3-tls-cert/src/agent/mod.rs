@@ -14,16 +14,66 @@
  * limitations under the License.
  */
 
+use std::hash::{Hash, Hasher};
+
 mod query;
-use query::QueryRuntime;
+use query::{QueryRuntime, QueryTransport};
+
+mod stream;
+use stream::{StreamPeer, StreamQueryTask};
 
 mod config;
 pub use config::CertAgentConfig;
 
+mod persist;
+
 mod handle;
 pub use handle::CertAgentHandle;
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+#[derive(Clone, Debug)]
 pub(crate) struct CacheQueryKey {
     pub(crate) host: String,
+    /// the DER encoded upstream cert, only used for sending the query
+    pub(crate) mimic_cert: Option<Vec<u8>>,
+    pub(crate) mimic_cert_sha256: Option<Vec<u8>>,
+}
+
+impl CacheQueryKey {
+    fn new(host: String) -> Self {
+        CacheQueryKey {
+            host,
+            mimic_cert: None,
+            mimic_cert_sha256: None,
+        }
+    }
+
+    fn new_mimic(host: String, mimic_cert: Vec<u8>, mimic_cert_sha256: Vec<u8>) -> Self {
+        CacheQueryKey {
+            host,
+            mimic_cert: Some(mimic_cert),
+            mimic_cert_sha256: Some(mimic_cert_sha256),
+        }
+    }
+
+    fn persist_name(&self) -> String {
+        match &self.mimic_cert_sha256 {
+            Some(digest) => crate::cache::mimic_cache_name(&self.host, digest),
+            None => self.host.clone(),
+        }
+    }
+}
+
+impl Hash for CacheQueryKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.host.hash(state);
+        self.mimic_cert_sha256.hash(state);
+    }
 }
+
+impl PartialEq for CacheQueryKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host && self.mimic_cert_sha256 == other.mimic_cert_sha256
+    }
+}
+
+impl Eq for CacheQueryKey {}
@@ -0,0 +1,148 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use log::{debug, warn};
+use openssl::asn1::Asn1Time;
+use openssl::pkey::PKey;
+use openssl::x509::X509;
+use rustls::{Certificate, PrivateKey};
+
+use crate::cache::{CertCacheEntry, CertDiskCache};
+
+pub(super) struct WarmCacheEntry {
+    pub(super) cert: Vec<Certificate>,
+    pub(super) key: PrivateKey,
+    expire_at: Instant,
+}
+
+impl WarmCacheEntry {
+    pub(super) fn ttl(&self) -> u32 {
+        let left = self.expire_at.saturating_duration_since(Instant::now());
+        u32::try_from(left.as_secs()).unwrap_or(u32::MAX)
+    }
+}
+
+fn parse_entry(entry: CertCacheEntry) -> anyhow::Result<WarmCacheEntry> {
+    let certs =
+        X509::stack_from_pem(&entry.cert_pem).map_err(|e| anyhow!("invalid cert pem: {e}"))?;
+    let mut cert = Vec::with_capacity(certs.len());
+    for c in certs {
+        let der = c
+            .to_der()
+            .map_err(|e| anyhow!("failed to encode cert to der: {e}"))?;
+        cert.push(Certificate(der));
+    }
+    if cert.is_empty() {
+        return Err(anyhow!("no cert found"));
+    }
+    let pkey =
+        PKey::private_key_from_pem(&entry.key_pem).map_err(|e| anyhow!("invalid key pem: {e}"))?;
+    let key = pkey
+        .private_key_to_pkcs8()
+        .map_err(|e| anyhow!("failed to encode key to pkcs8 der: {e}"))?;
+
+    Ok(WarmCacheEntry {
+        cert,
+        key: PrivateKey(key),
+        expire_at: Instant::now() + Duration::from_secs(entry.ttl() as u64),
+    })
+}
+
+/// Load all persisted certs, which should be used to answer the first query of each host.
+/// The persisted certs are keyed by the persist name of the query key.
+pub(super) fn load_warm_cache(cache: &CertDiskCache) -> HashMap<String, WarmCacheEntry> {
+    let mut map = HashMap::new();
+    for entry in cache.load_all() {
+        let host = entry.host.clone();
+        match parse_entry(entry) {
+            Ok(v) => {
+                // there may be certs with different key types for the same host
+                if map
+                    .get(&host)
+                    .map(|old: &WarmCacheEntry| old.expire_at < v.expire_at)
+                    .unwrap_or(true)
+                {
+                    map.insert(host, v);
+                }
+            }
+            Err(e) => warn!("invalid persisted cert for host {host}: {e:?}"),
+        }
+    }
+    debug!(
+        "loaded {} persisted certs from {}",
+        map.len(),
+        cache.dir().display()
+    );
+    map
+}
+
+fn save(
+    cache: &CertDiskCache,
+    name: String,
+    cert: &[Certificate],
+    key: &PrivateKey,
+    max_ttl: u32,
+) -> anyhow::Result<()> {
+    let mut cert_pem = Vec::new();
+    let mut ttl = max_ttl;
+    let now = Asn1Time::days_from_now(0).map_err(|e| anyhow!("failed to get time now: {e}"))?;
+    for (i, c) in cert.iter().enumerate() {
+        let x509 = X509::from_der(&c.0).map_err(|e| anyhow!("invalid cert {i}: {e}"))?;
+        if i == 0 {
+            // never persist longer than the leaf cert is valid
+            let diff = now
+                .diff(x509.not_after())
+                .map_err(|e| anyhow!("failed to get time diff: {e}"))?;
+            let left = (diff.days as i64) * 86400 + diff.secs as i64;
+            if left <= 0 {
+                return Ok(());
+            }
+            ttl = ttl.min(u32::try_from(left).unwrap_or(u32::MAX));
+        }
+        let pem = x509
+            .to_pem()
+            .map_err(|e| anyhow!("failed to encode cert {i} to pem: {e}"))?;
+        cert_pem.extend(pem);
+    }
+    let pkey = PKey::private_key_from_der(&key.0).map_err(|e| anyhow!("invalid key: {e}"))?;
+    let key_pem = pkey
+        .private_key_to_pem_pkcs8()
+        .map_err(|e| anyhow!("failed to encode key to pem: {e}"))?;
+    let key_type = crate::cache::key_type_name(&pkey);
+
+    let entry = CertCacheEntry::new(name, key_type, ttl, cert_pem, key_pem);
+    cache.put(&entry)
+}
+
+pub(super) fn spawn_save(
+    cache: &Arc<CertDiskCache>,
+    name: String,
+    cert: Vec<Certificate>,
+    key: PrivateKey,
+    max_ttl: u32,
+) {
+    let cache = Arc::clone(cache);
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = save(&cache, name.clone(), &cert, &key, max_ttl) {
+            warn!("failed to persist cert for {name}: {e:?}");
+        }
+    });
+}
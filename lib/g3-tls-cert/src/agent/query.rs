@@ -14,7 +14,7 @@
  * limitations under the License.
  */
 
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::future::Future;
 use std::io;
 use std::pin::Pin;
@@ -23,17 +23,36 @@ use std::task::{Context, Poll};
 use std::time::Duration;
 
 use anyhow::anyhow;
-use log::warn;
+use log::{debug, warn};
 use rustls::{Certificate, PrivateKey};
 use tokio::io::ReadBuf;
 use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
 
 use g3_io_ext::{EffectiveCacheData, EffectiveQueryHandle};
 
+use super::persist::WarmCacheEntry;
 use super::{CacheQueryKey, CertAgentConfig};
+use crate::cache::CertDiskCache;
+
+pub(super) enum QueryTransport {
+    Udp(UdpSocket),
+    /// the request and response frames of the stream query task
+    Stream(
+        mpsc::UnboundedSender<Vec<u8>>,
+        mpsc::UnboundedReceiver<Vec<u8>>,
+    ),
+}
+
+type QueryResponse = (Arc<CacheQueryKey>, Vec<Certificate>, PrivateKey, u32);
+
+enum RecvData {
+    Udp(usize),
+    Stream(Vec<u8>),
+}
 
 pub(super) struct QueryRuntime {
-    socket: UdpSocket,
+    transport: QueryTransport,
     query_handle: EffectiveQueryHandle<CacheQueryKey, (Vec<Certificate>, PrivateKey)>,
     read_buffer: Box<[u8]>,
     write_queue: VecDeque<(Arc<CacheQueryKey>, Vec<u8>)>,
@@ -41,16 +60,26 @@ pub(super) struct QueryRuntime {
     maximum_ttl: u32,
     vanish_wait: Duration,
     query_wait: Duration,
+    persist_cache: Option<Arc<CertDiskCache>>,
+    persist_ttl: u32,
+    warm_cache: HashMap<String, WarmCacheEntry>,
+    /// the sent queries that are waiting for responses, grouped by host
+    pending: HashMap<String, Vec<Arc<CacheQueryKey>>>,
 }
 
 impl QueryRuntime {
     pub(super) fn new(
         config: &CertAgentConfig,
-        socket: UdpSocket,
+        transport: QueryTransport,
         query_handle: EffectiveQueryHandle<CacheQueryKey, (Vec<Certificate>, PrivateKey)>,
+        persist_cache: Option<Arc<CertDiskCache>>,
     ) -> Self {
+        let warm_cache = persist_cache
+            .as_ref()
+            .map(|c| super::persist::load_warm_cache(c))
+            .unwrap_or_default();
         QueryRuntime {
-            socket,
+            transport,
             query_handle,
             read_buffer: vec![0u8; 16384].into_boxed_slice(),
             write_queue: VecDeque::new(),
@@ -58,10 +87,50 @@ impl QueryRuntime {
             maximum_ttl: config.maximum_cache_ttl,
             vanish_wait: config.cache_vanish_wait,
             query_wait: config.query_wait_timeout,
+            persist_cache,
+            persist_ttl: config.persist_cache_ttl,
+            warm_cache,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn add_pending(&mut self, req: Arc<CacheQueryKey>) {
+        let keys = self.pending.entry(req.host.clone()).or_default();
+        if !keys.contains(&req) {
+            keys.push(req);
+        }
+    }
+
+    fn remove_pending(&mut self, req: &CacheQueryKey) -> bool {
+        let Some(keys) = self.pending.get_mut(&req.host) else {
+            return false;
+        };
+        let Some(i) = keys.iter().position(|k| k.as_ref() == req) else {
+            return false;
+        };
+        keys.swap_remove(i);
+        if keys.is_empty() {
+            self.pending.remove(&req.host);
         }
+        true
+    }
+
+    /// Take all pending mimic queries for this host if there is no pending non-mimic one.
+    ///
+    /// This is used for peers that do not echo back the mimic_cert_sha256 key, the mimic cert
+    /// will be ignored and the response will be used for all mimic queries of the same host.
+    fn take_pending_mimic(&mut self, host: &str) -> Vec<Arc<CacheQueryKey>> {
+        let Some(keys) = self.pending.get(host) else {
+            return Vec::new();
+        };
+        if keys.iter().any(|k| k.mimic_cert_sha256.is_none()) {
+            return Vec::new();
+        }
+        self.pending.remove(host).unwrap_or_default()
     }
 
     fn send_empty_result(&mut self, req: Arc<CacheQueryKey>, expired: bool) {
+        self.remove_pending(&req);
         let result = EffectiveCacheData::empty(self.protective_ttl, self.vanish_wait);
         self.query_handle.send_rsp_data(req, result, expired);
     }
@@ -73,29 +142,48 @@ impl QueryRuntime {
             .query_handle
             .should_send_raw_query(req.clone(), self.query_wait)
         {
-            let map = vec![(
+            // answer the first query with the persisted one, later updates will go to the peer
+            if !self.warm_cache.is_empty() {
+                if let Some(v) = self.warm_cache.remove(&req.persist_name()) {
+                    let ttl = v.ttl().min(self.maximum_ttl);
+                    if ttl > 0 {
+                        let result =
+                            EffectiveCacheData::new((v.cert, v.key), ttl, self.vanish_wait);
+                        self.query_handle.send_rsp_data(req, result, false);
+                        return;
+                    }
+                }
+            }
+
+            let mut map = vec![(
                 ValueRef::String("host".into()),
                 ValueRef::String(req.host.as_str().into()),
             )];
+            if let Some(mimic_cert) = &req.mimic_cert {
+                map.push((
+                    ValueRef::String("mimic_cert".into()),
+                    ValueRef::Binary(mimic_cert.as_slice()),
+                ));
+            }
             let mut buf = Vec::with_capacity(32);
             let v = ValueRef::Map(map);
             if rmpv::encode::write_value_ref(&mut buf, &v).is_err() {
                 self.send_empty_result(req, false);
                 return;
             }
+            self.add_pending(req.clone());
             self.write_queue.push_back((req, buf));
         }
     }
 
-    fn parse_rsp(
-        map: Vec<(rmpv::ValueRef, rmpv::ValueRef)>,
-    ) -> anyhow::Result<(Arc<CacheQueryKey>, Vec<Certificate>, PrivateKey, u32)> {
+    fn parse_rsp(map: Vec<(rmpv::ValueRef, rmpv::ValueRef)>) -> anyhow::Result<QueryResponse> {
         use anyhow::Context;
 
         let mut host = String::new();
         let mut cert = Vec::new();
         let mut pkey = PrivateKey(Vec::new());
         let mut ttl: u32 = 0;
+        let mut mimic_cert_sha256: Option<Vec<u8>> = None;
 
         for (k, v) in map {
             let key = g3_msgpack::value::as_string(&k)?;
@@ -116,6 +204,13 @@ impl QueryRuntime {
                     ttl = g3_msgpack::value::as_u32(&v)
                         .context(format!("invalid u32 value for key {key}"))?;
                 }
+                "mimic_cert_sha256" => {
+                    if let rmpv::ValueRef::Binary(b) = v {
+                        mimic_cert_sha256 = Some(b.to_vec());
+                    } else {
+                        return Err(anyhow!("invalid binary value for key {key}"));
+                    }
+                }
                 _ => return Err(anyhow!("invalid key {key}")),
             }
         }
@@ -130,53 +225,132 @@ impl QueryRuntime {
             return Err(anyhow!("no required pkey key found"));
         }
 
-        Ok((Arc::new(CacheQueryKey { host }), cert, pkey, ttl))
+        let req_key = CacheQueryKey {
+            host,
+            mimic_cert: None,
+            mimic_cert_sha256,
+        };
+        Ok((Arc::new(req_key), cert, pkey, ttl))
     }
 
-    fn handle_rsp(&mut self, len: usize) {
+    fn decode_rsp(mut buf: &[u8]) -> Option<anyhow::Result<QueryResponse>> {
         use rmpv::ValueRef;
 
-        let mut buf = &self.read_buffer[..len];
         if let Ok(ValueRef::Map(map)) = rmpv::decode::read_value_ref(&mut buf) {
-            match Self::parse_rsp(map) {
-                Ok((req_key, cert, key, mut ttl)) => {
-                    if ttl == 0 {
-                        ttl = self.protective_ttl;
-                    } else if ttl > self.maximum_ttl {
-                        ttl = self.maximum_ttl;
-                    }
+            Some(Self::parse_rsp(map))
+        } else {
+            None
+        }
+    }
 
-                    let result = EffectiveCacheData::new((cert, key), ttl, self.vanish_wait);
-                    self.query_handle.send_rsp_data(req_key, result, false);
+    fn handle_rsp(&mut self, rsp: anyhow::Result<QueryResponse>) {
+        match rsp {
+            Ok((req_key, cert, key, mut ttl)) => {
+                if ttl == 0 {
+                    ttl = self.protective_ttl;
+                } else if ttl > self.maximum_ttl {
+                    ttl = self.maximum_ttl;
                 }
-                Err(e) => {
-                    warn!("parse cert generator rsp error: {e:?}");
+
+                if let Some(cache) = &self.persist_cache {
+                    super::persist::spawn_save(
+                        cache,
+                        req_key.persist_name(),
+                        cert.clone(),
+                        key.clone(),
+                        self.persist_ttl,
+                    );
                 }
+
+                let matched = self.remove_pending(&req_key);
+                if !matched && req_key.mimic_cert_sha256.is_none() {
+                    let mimic_keys = self.take_pending_mimic(&req_key.host);
+                    if !mimic_keys.is_empty() {
+                        debug!(
+                            "no mimic_cert_sha256 in cert generator rsp for host {}, \
+                             will use it for all mimic queries of this host",
+                            req_key.host
+                        );
+                    }
+                    for mimic_key in mimic_keys {
+                        let result = EffectiveCacheData::new(
+                            (cert.clone(), key.clone()),
+                            ttl,
+                            self.vanish_wait,
+                        );
+                        self.query_handle.send_rsp_data(mimic_key, result, false);
+                    }
+                }
+
+                let result = EffectiveCacheData::new((cert, key), ttl, self.vanish_wait);
+                self.query_handle.send_rsp_data(req_key, result, false);
+            }
+            Err(e) => {
+                warn!("parse cert generator rsp error: {e:?}");
             }
         }
     }
 
+    fn poll_recv_rsp(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<RecvData>> {
+        match &mut self.transport {
+            QueryTransport::Udp(socket) => {
+                let mut buf = ReadBuf::new(&mut self.read_buffer);
+                match socket.poll_recv(cx, &mut buf) {
+                    Poll::Pending => Poll::Pending,
+                    Poll::Ready(Ok(_)) => Poll::Ready(Ok(RecvData::Udp(buf.filled().len()))),
+                    Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+                }
+            }
+            QueryTransport::Stream(_, receiver) => match receiver.poll_recv(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Some(data)) => Poll::Ready(Ok(RecvData::Stream(data))),
+                Poll::Ready(None) => {
+                    Poll::Ready(Err(io::Error::other("the stream query task has exited")))
+                }
+            },
+        }
+    }
+
+    fn poll_send_req(&mut self, cx: &mut Context<'_>, data: &[u8]) -> Poll<io::Result<()>> {
+        match &mut self.transport {
+            QueryTransport::Udp(socket) => match socket.poll_send(cx, data) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Ok(_)) => Poll::Ready(Ok(())),
+                Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            },
+            QueryTransport::Stream(sender, _) => match sender.send(data.to_vec()) {
+                Ok(_) => Poll::Ready(Ok(())),
+                Err(_) => Poll::Ready(Err(io::Error::other("the stream query task has exited"))),
+            },
+        }
+    }
+
     fn poll_loop(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         loop {
             // handle rsp
-            let mut buf = ReadBuf::new(&mut self.read_buffer);
-            match self.socket.poll_recv(cx, &mut buf) {
+            match self.poll_recv_rsp(cx) {
                 Poll::Pending => {}
                 Poll::Ready(Err(e)) => {
                     warn!("socket recv error: {e:?}");
                     return Poll::Ready(Err(e));
                 }
-                Poll::Ready(Ok(_)) => {
-                    let len = buf.filled().len();
+                Poll::Ready(Ok(RecvData::Udp(len))) => {
                     if len > 0 {
-                        self.handle_rsp(len);
+                        if let Some(rsp) = Self::decode_rsp(&self.read_buffer[..len]) {
+                            self.handle_rsp(rsp);
+                        }
+                    }
+                }
+                Poll::Ready(Ok(RecvData::Stream(data))) => {
+                    if let Some(rsp) = Self::decode_rsp(&data) {
+                        self.handle_rsp(rsp);
                     }
                 }
             }
 
             // send req from write queue
             while let Some((req_key, v)) = self.write_queue.pop_front() {
-                match self.socket.poll_send(cx, v.as_slice()) {
+                match self.poll_send_req(cx, v.as_slice()) {
                     Poll::Pending => {
                         self.write_queue.push_front((req_key, v));
                         break;
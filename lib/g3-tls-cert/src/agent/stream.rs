@@ -0,0 +1,165 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// The max size of a single response frame
+const MAX_FRAME_SIZE: usize = 1 << 20;
+const RECONNECT_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum StreamPeer {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+async fn read_frame<R>(reader: &mut R) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    let len = reader.read_u32().await? as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::other(format!("too large frame size {len}")));
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+async fn write_frame<W>(writer: &mut W, data: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let len = u32::try_from(data.len())
+        .map_err(|_| io::Error::other(format!("too large frame size {}", data.len())))?;
+    let mut buf = Vec::with_capacity(data.len() + 4);
+    buf.extend_from_slice(&len.to_be_bytes());
+    buf.extend_from_slice(data);
+    writer.write_all(&buf).await?;
+    writer.flush().await
+}
+
+pub(super) struct StreamQueryTask {
+    peer: StreamPeer,
+    req_receiver: mpsc::UnboundedReceiver<Vec<u8>>,
+    rsp_sender: mpsc::UnboundedSender<Vec<u8>>,
+}
+
+impl StreamQueryTask {
+    pub(super) fn new(
+        peer: StreamPeer,
+        req_receiver: mpsc::UnboundedReceiver<Vec<u8>>,
+        rsp_sender: mpsc::UnboundedSender<Vec<u8>>,
+    ) -> Self {
+        StreamQueryTask {
+            peer,
+            req_receiver,
+            rsp_sender,
+        }
+    }
+
+    pub(super) async fn into_running(mut self) {
+        loop {
+            let r = match &self.peer {
+                StreamPeer::Tcp(addr) => match tokio::net::TcpStream::connect(addr).await {
+                    Ok(stream) => {
+                        let _ = stream.set_nodelay(true);
+                        let (r, w) = stream.into_split();
+                        self.run_connection(r, w).await
+                    }
+                    Err(e) => Err(io::Error::other(format!(
+                        "failed to connect to {addr}: {e}"
+                    ))),
+                },
+                #[cfg(unix)]
+                StreamPeer::Unix(path) => match tokio::net::UnixStream::connect(path).await {
+                    Ok(stream) => {
+                        let (r, w) = stream.into_split();
+                        self.run_connection(r, w).await
+                    }
+                    Err(e) => Err(io::Error::other(format!(
+                        "failed to connect to {}: {e}",
+                        path.display()
+                    ))),
+                },
+            };
+            match r {
+                Ok(true) => {
+                    debug!("cert agent query runtime closed");
+                    return;
+                }
+                Ok(false) => debug!("cert generator peer {:?} closed the connection", self.peer),
+                Err(e) => warn!("cert generator peer {:?} error: {e}", self.peer),
+            }
+            if self.rsp_sender.is_closed() {
+                return;
+            }
+            tokio::time::sleep(RECONNECT_WAIT).await;
+        }
+    }
+
+    /// Return true if the query runtime has been closed
+    async fn run_connection<R, W>(&mut self, mut reader: R, mut writer: W) -> io::Result<bool>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Unpin,
+    {
+        let rsp_sender = self.rsp_sender.clone();
+        let mut read_task = tokio::spawn(async move {
+            loop {
+                let frame = read_frame(&mut reader).await?;
+                if rsp_sender.send(frame).is_err() {
+                    return Ok::<(), io::Error>(());
+                }
+            }
+        });
+
+        // requests can be pipelined, the responses are matched by the query key
+        loop {
+            tokio::select! {
+                biased;
+
+                r = &mut read_task => {
+                    return match r {
+                        Ok(Ok(_)) => Ok(true),
+                        Ok(Err(e)) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+                        Ok(Err(e)) => Err(e),
+                        Err(e) => Err(io::Error::other(format!("read task join error: {e}"))),
+                    };
+                }
+                r = self.req_receiver.recv() => {
+                    let Some(req) = r else {
+                        read_task.abort();
+                        return Ok(true);
+                    };
+                    if let Err(e) = write_frame(&mut writer, &req).await {
+                        read_task.abort();
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -26,6 +26,7 @@ use time::asn1_time_from_chrono;
 mod server;
 pub use server::{
     ServerCertBuilder, TlcpServerEncCertBuilder, TlcpServerSignCertBuilder, TlsServerCertBuilder,
+    TlsServerCertKeyType,
 };
 
 mod client;
@@ -16,19 +16,20 @@
 
 use anyhow::{anyhow, Context};
 use chrono::{Days, Utc};
-use openssl::asn1::{Asn1Integer, Asn1Time};
+use openssl::asn1::{Asn1Integer, Asn1Time, Asn1TimeRef};
 use openssl::hash::MessageDigest;
-use openssl::pkey::{PKey, Private};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private};
 use openssl::x509::extension::{
     AuthorityKeyIdentifier, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
     SubjectKeyIdentifier,
 };
-use openssl::x509::{X509Builder, X509Extension, X509Name, X509Ref, X509};
+use openssl::x509::{GeneralNameRef, X509Builder, X509Extension, X509Name, X509Ref, X509};
 
 use g3_types::net::Host;
 
 use super::{asn1_time_from_chrono, SubjectNameBuilder};
-use crate::ext::X509BuilderExt;
+use crate::ext::{X509BuilderExt, X509Ext};
 
 pub struct ServerCertBuilder {
     pkey: PKey<Private>,
@@ -128,6 +129,55 @@ impl TlsServerCertBuilder {
     }
 }
 
+/// The key type to use when mimic the upstream certificate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TlsServerCertKeyType {
+    Ec256,
+    Ec384,
+    Ec521,
+    Ed25519,
+    Ed448,
+    Rsa(u32),
+}
+
+impl TlsServerCertKeyType {
+    /// Get the key type of the cert, unsupported ones will fallback to EC P-256
+    pub fn from_cert(cert: &X509Ref) -> Self {
+        let Ok(pkey) = cert.public_key() else {
+            return TlsServerCertKeyType::Ec256;
+        };
+        match pkey.id() {
+            Id::RSA => match pkey.bits() {
+                0..=2048 => TlsServerCertKeyType::Rsa(2048),
+                2049..=3072 => TlsServerCertKeyType::Rsa(3072),
+                _ => TlsServerCertKeyType::Rsa(4096),
+            },
+            Id::EC => {
+                let curve = pkey.ec_key().ok().and_then(|k| k.group().curve_name());
+                match curve {
+                    Some(Nid::SECP384R1) => TlsServerCertKeyType::Ec384,
+                    Some(Nid::SECP521R1) => TlsServerCertKeyType::Ec521,
+                    _ => TlsServerCertKeyType::Ec256,
+                }
+            }
+            Id::ED25519 => TlsServerCertKeyType::Ed25519,
+            Id::ED448 => TlsServerCertKeyType::Ed448,
+            _ => TlsServerCertKeyType::Ec256,
+        }
+    }
+
+    pub fn new_builder(&self) -> anyhow::Result<ServerCertBuilder> {
+        match self {
+            TlsServerCertKeyType::Ec256 => TlsServerCertBuilder::new_ec256(),
+            TlsServerCertKeyType::Ec384 => TlsServerCertBuilder::new_ec384(),
+            TlsServerCertKeyType::Ec521 => TlsServerCertBuilder::new_ec521(),
+            TlsServerCertKeyType::Ed25519 => TlsServerCertBuilder::new_ed25519(),
+            TlsServerCertKeyType::Ed448 => TlsServerCertBuilder::new_ed448(),
+            TlsServerCertKeyType::Rsa(bits) => TlsServerCertBuilder::new_rsa(*bits),
+        }
+    }
+}
+
 pub struct TlcpServerSignCertBuilder {}
 
 impl TlcpServerSignCertBuilder {
@@ -309,6 +359,72 @@ impl ServerCertBuilder {
         self.build_with_subject(&subject_name, san, ca_cert, ca_key, sign_digest)
     }
 
+    /// Build a fake cert that mimic the fields of the upstream leaf cert.
+    ///
+    /// The subject, all subject alternative names, validity window and key usage extensions
+    /// will be copied. The NotBefore time will be clamped to that of the CA, and the NotAfter
+    /// time will be clamped to that of the builder and the CA.
+    /// The key type is not changed here, and the caller should use a builder with the same key
+    /// type as the upstream cert.
+    pub fn build_mimic(
+        &self,
+        host: &Host,
+        mimic_cert: &X509Ref,
+        ca_cert: &X509Ref,
+        ca_key: &PKey<Private>,
+        sign_digest: Option<MessageDigest>,
+    ) -> anyhow::Result<X509> {
+        let subject_name = mimic_cert.subject_name().to_owned().map_err(|e| {
+            anyhow!("failed to copy subject name from the upstream certificate: {e}")
+        })?;
+
+        let mut san = SubjectAlternativeName::new();
+        let mut san_count = 0usize;
+        if let Some(names) = mimic_cert.subject_alt_names() {
+            for name in names.iter() {
+                if copy_general_name(&mut san, name) {
+                    san_count += 1;
+                }
+            }
+        }
+        if san_count == 0 {
+            match host {
+                Host::Domain(domain) => san.dns(domain),
+                Host::Ip(ip) => san.ip(&ip.to_string()),
+            };
+        }
+
+        let not_before = if mimic_cert.not_before() < ca_cert.not_before() {
+            ca_cert.not_before()
+        } else {
+            mimic_cert.not_before()
+        };
+        let not_after = if mimic_cert.not_after() < self.not_after {
+            mimic_cert.not_after()
+        } else {
+            &self.not_after
+        };
+
+        let key_usage = mimic_cert
+            .key_usage()
+            .and_then(|ku| ku.to_leaf_extension().ok());
+        let ext_key_usage = mimic_cert
+            .ext_key_usage()
+            .and_then(|eku| eku.to_extension().ok());
+
+        self.build_inner(
+            &subject_name,
+            san,
+            not_before,
+            not_after,
+            key_usage.as_ref().unwrap_or(&self.key_usage),
+            ext_key_usage.as_ref().unwrap_or(&self.ext_key_usage),
+            ca_cert,
+            ca_key,
+            sign_digest,
+        )
+    }
+
     pub fn build_with_subject(
         &self,
         subject_name: &X509Name,
@@ -316,6 +432,32 @@ impl ServerCertBuilder {
         ca_cert: &X509Ref,
         ca_key: &PKey<Private>,
         sign_digest: Option<MessageDigest>,
+    ) -> anyhow::Result<X509> {
+        self.build_inner(
+            subject_name,
+            subject_alt_name,
+            &self.not_before,
+            &self.not_after,
+            &self.key_usage,
+            &self.ext_key_usage,
+            ca_cert,
+            ca_key,
+            sign_digest,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build_inner(
+        &self,
+        subject_name: &X509Name,
+        subject_alt_name: SubjectAlternativeName,
+        not_before: &Asn1TimeRef,
+        not_after: &Asn1TimeRef,
+        key_usage: &X509Extension,
+        ext_key_usage: &X509Extension,
+        ca_cert: &X509Ref,
+        ca_key: &PKey<Private>,
+        sign_digest: Option<MessageDigest>,
     ) -> anyhow::Result<X509> {
         let mut builder =
             X509Builder::new().map_err(|e| anyhow!("failed to create x509 builder {e}"))?;
@@ -326,18 +468,18 @@ impl ServerCertBuilder {
             .set_serial_number(&self.serial)
             .map_err(|e| anyhow!("failed to set serial number: {e}"))?;
 
-        let not_before = if ca_cert.not_before() > self.not_after {
+        let not_before = if ca_cert.not_before() > not_after {
             ca_cert.not_before()
         } else {
-            &self.not_before
+            not_before
         };
         builder
             .set_not_before(not_before)
             .map_err(|e| anyhow!("failed to set NotBefore: {e}"))?;
-        let not_after = if ca_cert.not_after() < self.not_after {
+        let not_after = if ca_cert.not_after() < not_after {
             ca_cert.not_after()
         } else {
-            &self.not_after
+            not_after
         };
         builder
             .set_not_after(not_after)
@@ -347,10 +489,10 @@ impl ServerCertBuilder {
             .set_version(2)
             .map_err(|e| anyhow!("failed to set x509 version 3: {e}"))?;
         builder
-            .append_extension2(&self.key_usage)
+            .append_extension2(key_usage)
             .map_err(|e| anyhow!("failed to append KeyUsage extension: {e}"))?;
         builder
-            .append_extension2(&self.ext_key_usage)
+            .append_extension2(ext_key_usage)
             .map_err(|e| anyhow!("failed to append ExtendedKeyUsage extension: {e}"))?;
 
         builder
@@ -390,3 +532,190 @@ impl ServerCertBuilder {
         Ok(builder.build())
     }
 }
+
+fn copy_general_name(san: &mut SubjectAlternativeName, name: &GeneralNameRef) -> bool {
+    if let Some(dns) = name.dnsname() {
+        san.dns(dns);
+    } else if let Some(ip) = name.ipaddress() {
+        let ip = match ip.len() {
+            4 => {
+                let mut buf = [0u8; 4];
+                buf.copy_from_slice(ip);
+                std::net::IpAddr::from(buf)
+            }
+            16 => {
+                let mut buf = [0u8; 16];
+                buf.copy_from_slice(ip);
+                std::net::IpAddr::from(buf)
+            }
+            _ => return false,
+        };
+        san.ip(&ip.to_string());
+    } else if let Some(email) = name.email() {
+        san.email(email);
+    } else if let Some(uri) = name.uri() {
+        san.uri(uri);
+    } else {
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::RootCertBuilder;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn build_ca() -> (X509, PKey<Private>) {
+        let mut builder = RootCertBuilder::new_ec256().unwrap();
+        builder
+            .subject_builder_mut()
+            .set_common_name("test ca".to_string());
+        let cert = builder.build(None).unwrap();
+        (cert, builder.pkey().clone())
+    }
+
+    fn build_upstream(ca_cert: &X509Ref, ca_key: &PKey<Private>) -> X509 {
+        let pkey = super::super::pkey::new_ec384().unwrap();
+        let key_usage = KeyUsage::new()
+            .critical()
+            .digital_signature()
+            .build()
+            .unwrap();
+        let mut builder = ServerCertBuilder::new(pkey, key_usage).unwrap();
+        builder
+            .subject_builder_mut()
+            .set_common_name("upstream.example.net".to_string());
+        let subject = builder.subject_builder().build().unwrap();
+        let mut san = SubjectAlternativeName::new();
+        san.dns("upstream.example.net");
+        san.dns("*.upstream.example.net");
+        san.ip("192.0.2.1");
+        builder
+            .build_with_subject(&subject, san, ca_cert, ca_key, None)
+            .unwrap()
+    }
+
+    fn san_dns_names(cert: &X509Ref) -> Vec<String> {
+        cert.subject_alt_names()
+            .unwrap()
+            .iter()
+            .filter_map(|n| n.dnsname().map(|s| s.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn key_type_from_cert() {
+        let (ca_cert, ca_key) = build_ca();
+        let host = Host::Domain("example.net".into());
+
+        let builder = TlsServerCertBuilder::new_ec256().unwrap();
+        let cert = builder.build_fake(&host, &ca_cert, &ca_key, None).unwrap();
+        assert_eq!(
+            TlsServerCertKeyType::from_cert(&cert),
+            TlsServerCertKeyType::Ec256
+        );
+
+        let builder = TlsServerCertBuilder::new_ec384().unwrap();
+        let cert = builder.build_fake(&host, &ca_cert, &ca_key, None).unwrap();
+        assert_eq!(
+            TlsServerCertKeyType::from_cert(&cert),
+            TlsServerCertKeyType::Ec384
+        );
+
+        let builder = TlsServerCertBuilder::new_ed25519().unwrap();
+        let cert = builder.build_fake(&host, &ca_cert, &ca_key, None).unwrap();
+        assert_eq!(
+            TlsServerCertKeyType::from_cert(&cert),
+            TlsServerCertKeyType::Ed25519
+        );
+
+        let builder = TlsServerCertBuilder::new_rsa(1024).unwrap();
+        let cert = builder.build_fake(&host, &ca_cert, &ca_key, None).unwrap();
+        assert_eq!(
+            TlsServerCertKeyType::from_cert(&cert),
+            TlsServerCertKeyType::Rsa(2048)
+        );
+    }
+
+    #[test]
+    fn build_mimic() {
+        let (ca_cert, ca_key) = build_ca();
+        let upstream = build_upstream(&ca_cert, &ca_key);
+
+        let key_type = TlsServerCertKeyType::from_cert(&upstream);
+        assert_eq!(key_type, TlsServerCertKeyType::Ec384);
+        let builder = key_type.new_builder().unwrap();
+
+        let host = Host::Domain("other.example.net".into());
+        let cert = builder
+            .build_mimic(&host, &upstream, &ca_cert, &ca_key, None)
+            .unwrap();
+
+        assert_eq!(
+            cert.subject_name().to_der().unwrap(),
+            upstream.subject_name().to_der().unwrap()
+        );
+        assert_eq!(
+            cert.issuer_name().to_der().unwrap(),
+            ca_cert.subject_name().to_der().unwrap()
+        );
+        assert!(cert.verify(&ca_key).unwrap());
+        assert!(cert.public_key().unwrap().public_eq(builder.pkey()));
+
+        assert_eq!(
+            san_dns_names(&cert),
+            vec!["upstream.example.net", "*.upstream.example.net"]
+        );
+        let ips: Vec<IpAddr> = cert
+            .subject_alt_names()
+            .unwrap()
+            .iter()
+            .filter_map(|n| n.ipaddress())
+            .map(|ip| {
+                let mut buf = [0u8; 4];
+                buf.copy_from_slice(ip);
+                IpAddr::from(buf)
+            })
+            .collect();
+        assert_eq!(ips, vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))]);
+
+        assert_eq!(cert.key_usage(), upstream.key_usage());
+        assert_eq!(cert.ext_key_usage(), upstream.ext_key_usage());
+        assert!(cert.not_after() <= upstream.not_after());
+    }
+
+    #[test]
+    fn build_mimic_without_san() {
+        let (ca_cert, ca_key) = build_ca();
+
+        // a self-signed root cert has no SAN and a longer validity than the builder
+        let mut upstream_builder = RootCertBuilder::new_ec256().unwrap();
+        upstream_builder
+            .subject_builder_mut()
+            .set_common_name("upstream root".to_string());
+        let upstream = upstream_builder.build(None).unwrap();
+        assert!(upstream.subject_alt_names().is_none());
+
+        let builder = TlsServerCertBuilder::new_ec256().unwrap();
+        let host = Host::Domain("www.example.net".into());
+        let cert = builder
+            .build_mimic(&host, &upstream, &ca_cert, &ca_key, None)
+            .unwrap();
+        assert_eq!(san_dns_names(&cert), vec!["www.example.net"]);
+        assert!(cert.not_after() == &*builder.not_after);
+        assert!(cert.verify(&ca_key).unwrap());
+
+        let ip = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1));
+        let cert = builder
+            .build_mimic(&Host::Ip(ip), &upstream, &ca_cert, &ca_key, None)
+            .unwrap();
+        let names = cert.subject_alt_names().unwrap();
+        assert_eq!(names.len(), 1);
+        assert_eq!(
+            names.get(0).unwrap().ipaddress(),
+            Some(&[198, 51, 100, 1][..])
+        );
+    }
+}
@@ -0,0 +1,624 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context};
+use log::{debug, warn};
+use openssl::pkey::{Id, PKey, Private};
+
+const META_FILE_EXT: &str = "meta";
+const CERT_FILE_EXT: &str = "crt";
+const KEY_FILE_EXT: &str = "key";
+const TMP_FILE_EXT: &str = "tmp";
+
+const DIR_MODE: u32 = 0o700;
+const KEY_FILE_MODE: u32 = 0o600;
+const PUBLIC_FILE_MODE: u32 = 0o644;
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Get the key type name that should be used as part of the cache key
+pub fn key_type_name(pkey: &PKey<Private>) -> String {
+    match pkey.id() {
+        Id::RSA => format!("rsa{}", pkey.bits()),
+        Id::EC => match pkey.ec_key().ok().and_then(|k| k.group().curve_name()) {
+            Some(nid) => match nid.short_name() {
+                Ok(name) => format!("ec-{}", name.to_lowercase()),
+                Err(_) => format!("ec{}", pkey.bits()),
+            },
+            None => format!("ec{}", pkey.bits()),
+        },
+        Id::ED25519 => "ed25519".to_string(),
+        Id::ED448 => "ed448".to_string(),
+        Id::X25519 => "x25519".to_string(),
+        Id::X448 => "x448".to_string(),
+        id => format!("id{}", id.as_raw()),
+    }
+}
+
+/// Get the cache name for certs that mimic the upstream cert
+pub fn mimic_cache_name(host: &str, mimic_cert_sha256: &[u8]) -> String {
+    let mut name = String::with_capacity(host.len() + 33);
+    name.push_str(host);
+    name.push('@');
+    for b in mimic_cert_sha256.iter().take(16) {
+        name.push_str(&format!("{b:02x}"));
+    }
+    name
+}
+
+pub struct CertCacheEntry {
+    pub host: String,
+    pub key_type: String,
+    /// expire time as unix timestamp in seconds
+    pub expire: u64,
+    pub cert_pem: Vec<u8>,
+    pub key_pem: Vec<u8>,
+}
+
+impl CertCacheEntry {
+    pub fn new(
+        host: String,
+        key_type: String,
+        ttl: u32,
+        cert_pem: Vec<u8>,
+        key_pem: Vec<u8>,
+    ) -> Self {
+        CertCacheEntry {
+            host,
+            key_type,
+            expire: unix_now() + ttl as u64,
+            cert_pem,
+            key_pem,
+        }
+    }
+
+    /// Get the left ttl in seconds, 0 means the entry has already expired
+    pub fn ttl(&self) -> u32 {
+        self.expire
+            .saturating_sub(unix_now())
+            .try_into()
+            .unwrap_or(u32::MAX)
+    }
+
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expire <= unix_now()
+    }
+
+    fn encode_meta(&self) -> String {
+        format!(
+            "host={}\nkey_type={}\nexpire={}\n",
+            self.host, self.key_type, self.expire
+        )
+    }
+}
+
+struct CacheMeta {
+    host: String,
+    key_type: String,
+    expire: u64,
+}
+
+impl CacheMeta {
+    fn parse(content: &str) -> anyhow::Result<Self> {
+        let mut host = String::new();
+        let mut key_type = String::new();
+        let mut expire = 0u64;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((k, v)) = line.split_once('=') else {
+                return Err(anyhow!("invalid line {line}"));
+            };
+            match k {
+                "host" => host = v.to_string(),
+                "key_type" => key_type = v.to_string(),
+                "expire" => {
+                    expire = v
+                        .parse::<u64>()
+                        .map_err(|e| anyhow!("invalid expire value {v}: {e}"))?
+                }
+                _ => return Err(anyhow!("invalid key {k}")),
+            }
+        }
+
+        if host.is_empty() {
+            return Err(anyhow!("no host set"));
+        }
+        if key_type.is_empty() {
+            return Err(anyhow!("no key type set"));
+        }
+        Ok(CacheMeta {
+            host,
+            key_type,
+            expire,
+        })
+    }
+}
+
+fn encode_file_stem(host: &str) -> String {
+    let mut s = String::with_capacity(host.len());
+    for (i, c) in host.chars().enumerate() {
+        // a leading '.' is always escaped, so we won't get '.', '..' or hidden files
+        if c.is_ascii_alphanumeric() || (c == '.' && i > 0) || c == '-' || c == '_' {
+            s.push(c);
+        } else {
+            let mut buf = [0u8; 4];
+            for b in c.encode_utf8(&mut buf).as_bytes() {
+                s.push_str(&format!("%{b:02x}"));
+            }
+        }
+    }
+    s
+}
+
+fn create_dir(path: &Path) -> io::Result<()> {
+    DirBuilder::new()
+        .recursive(true)
+        .mode(DIR_MODE)
+        .create(path)
+}
+
+fn write_file_atomic(path: &Path, content: &[u8], mode: u32) -> io::Result<()> {
+    // append the tmp ext, as the file stem may contain '.'
+    let mut tmp_path = path.as_os_str().to_os_string();
+    tmp_path.push(".");
+    tmp_path.push(TMP_FILE_EXT);
+    let tmp_path = PathBuf::from(tmp_path);
+    // remove any stale tmp file, as the mode is only applied when creating new files
+    if let Err(e) = fs::remove_file(&tmp_path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(&tmp_path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    fs::rename(&tmp_path, path)
+}
+
+/// An on-disk certificate cache, keyed by host and key type.
+///
+/// Each entry is stored as `<dir>/<key_type>/<host>.{crt,key,meta}`, the meta file is written
+/// at last, so an entry without meta file will be treated as incomplete and be ignored.
+/// The directories are created with mode 0700 and the key files with mode 0600.
+pub struct CertDiskCache {
+    dir: PathBuf,
+    max_entries: usize,
+    index: Mutex<HashMap<(String, String), u64>>,
+}
+
+impl CertDiskCache {
+    pub fn open(dir: &Path, max_entries: usize) -> anyhow::Result<Self> {
+        create_dir(dir)
+            .map_err(|e| anyhow!("failed to create cache dir {}: {e}", dir.display()))?;
+        let cache = CertDiskCache {
+            dir: dir.to_path_buf(),
+            max_entries,
+            index: Mutex::new(HashMap::new()),
+        };
+        cache.scan().context("failed to scan cache dir")?;
+        Ok(cache)
+    }
+
+    #[inline]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn entry_path(&self, host: &str, key_type: &str, ext: &str) -> PathBuf {
+        // never use set_extension here, as the file stem may contain '.'
+        let stem = encode_file_stem(host);
+        self.dir
+            .join(encode_file_stem(key_type))
+            .join(format!("{stem}.{ext}"))
+    }
+
+    fn remove_files(&self, host: &str, key_type: &str) {
+        for ext in [META_FILE_EXT, CERT_FILE_EXT, KEY_FILE_EXT] {
+            let path = self.entry_path(host, key_type, ext);
+            if let Err(e) = fs::remove_file(&path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("failed to remove cache file {}: {e}", path.display());
+                }
+            }
+        }
+    }
+
+    fn scan(&self) -> io::Result<()> {
+        let now = unix_now();
+        let mut index = HashMap::new();
+        for type_dir in fs::read_dir(&self.dir)? {
+            let type_dir = type_dir?;
+            if !type_dir.file_type()?.is_dir() {
+                continue;
+            }
+            for file in fs::read_dir(type_dir.path())? {
+                let path = file?.path();
+                if path.extension().and_then(|s| s.to_str()) != Some(META_FILE_EXT) {
+                    continue;
+                }
+                let meta = match fs::read_to_string(&path)
+                    .map_err(anyhow::Error::new)
+                    .and_then(|s| CacheMeta::parse(&s))
+                {
+                    Ok(meta) => meta,
+                    Err(e) => {
+                        warn!("invalid cache meta file {}: {e:?}", path.display());
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                };
+                if meta.expire <= now {
+                    self.remove_files(&meta.host, &meta.key_type);
+                    continue;
+                }
+                index.insert((meta.host, meta.key_type), meta.expire);
+            }
+        }
+        debug!(
+            "found {} cached certs in dir {}",
+            index.len(),
+            self.dir.display()
+        );
+        *self.index.lock().unwrap() = index;
+        self.evict(None);
+        Ok(())
+    }
+
+    fn read_entry(&self, host: &str, key_type: &str, expire: u64) -> io::Result<CertCacheEntry> {
+        let cert_pem = fs::read(self.entry_path(host, key_type, CERT_FILE_EXT))?;
+        let key_pem = fs::read(self.entry_path(host, key_type, KEY_FILE_EXT))?;
+        Ok(CertCacheEntry {
+            host: host.to_string(),
+            key_type: key_type.to_string(),
+            expire,
+            cert_pem,
+            key_pem,
+        })
+    }
+
+    /// Get a not expired entry
+    pub fn get(&self, host: &str, key_type: &str) -> Option<CertCacheEntry> {
+        let expire = {
+            let mut index = self.index.lock().unwrap();
+            let key = (host.to_string(), key_type.to_string());
+            let expire = *index.get(&key)?;
+            if expire <= unix_now() {
+                index.remove(&key);
+                drop(index);
+                self.remove_files(host, key_type);
+                return None;
+            }
+            expire
+        };
+
+        match self.read_entry(host, key_type, expire) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                warn!("failed to read cached cert for {host}/{key_type}: {e}");
+                self.index
+                    .lock()
+                    .unwrap()
+                    .remove(&(host.to_string(), key_type.to_string()));
+                self.remove_files(host, key_type);
+                None
+            }
+        }
+    }
+
+    /// Load all not expired entries
+    pub fn load_all(&self) -> Vec<CertCacheEntry> {
+        let now = unix_now();
+        let keys: Vec<(String, String, u64)> = self
+            .index
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, expire)| **expire > now)
+            .map(|((host, key_type), expire)| (host.clone(), key_type.clone(), *expire))
+            .collect();
+
+        let mut entries = Vec::with_capacity(keys.len());
+        for (host, key_type, expire) in keys {
+            match self.read_entry(&host, &key_type, expire) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => warn!("failed to read cached cert for {host}/{key_type}: {e}"),
+            }
+        }
+        entries
+    }
+
+    pub fn put(&self, entry: &CertCacheEntry) -> anyhow::Result<()> {
+        if entry.is_expired() {
+            return Ok(());
+        }
+
+        let type_dir = self.dir.join(encode_file_stem(&entry.key_type));
+        create_dir(&type_dir)
+            .map_err(|e| anyhow!("failed to create dir {}: {e}", type_dir.display()))?;
+
+        let meta_path = self.entry_path(&entry.host, &entry.key_type, META_FILE_EXT);
+        // remove the meta file first, so the entry won't be used if we failed in the middle
+        if let Err(e) = fs::remove_file(&meta_path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                return Err(anyhow!(
+                    "failed to remove old meta file {}: {e}",
+                    meta_path.display()
+                ));
+            }
+        }
+        let cert_path = self.entry_path(&entry.host, &entry.key_type, CERT_FILE_EXT);
+        write_file_atomic(&cert_path, &entry.cert_pem, PUBLIC_FILE_MODE)
+            .map_err(|e| anyhow!("failed to write cert file {}: {e}", cert_path.display()))?;
+        let key_path = self.entry_path(&entry.host, &entry.key_type, KEY_FILE_EXT);
+        write_file_atomic(&key_path, &entry.key_pem, KEY_FILE_MODE)
+            .map_err(|e| anyhow!("failed to write key file {}: {e}", key_path.display()))?;
+        write_file_atomic(&meta_path, entry.encode_meta().as_bytes(), PUBLIC_FILE_MODE)
+            .map_err(|e| anyhow!("failed to write meta file {}: {e}", meta_path.display()))?;
+
+        let key = (entry.host.clone(), entry.key_type.clone());
+        self.index.lock().unwrap().insert(key.clone(), entry.expire);
+        self.evict(Some(&key));
+        Ok(())
+    }
+
+    fn evict(&self, keep: Option<&(String, String)>) {
+        let mut removed = Vec::new();
+        {
+            let mut index = self.index.lock().unwrap();
+            if index.len() <= self.max_entries {
+                return;
+            }
+
+            let now = unix_now();
+            index.retain(|k, expire| {
+                if *expire <= now {
+                    removed.push(k.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+
+            if index.len() > self.max_entries {
+                // remove the ones that will expire first, and leave some room for new entries
+                let target = self.max_entries - self.max_entries / 10;
+                let mut all: Vec<((String, String), u64)> = index
+                    .iter()
+                    .filter(|(k, _)| Some(*k) != keep)
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect();
+                all.sort_by_key(|(_, expire)| *expire);
+                let count = index.len().saturating_sub(target).min(all.len());
+                for (k, _) in all.into_iter().take(count) {
+                    index.remove(&k);
+                    removed.push(k);
+                }
+            }
+        }
+
+        for (host, key_type) in removed {
+            self.remove_files(&host, &key_type);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("g3-tls-cert-cache-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn new_entry(host: &str, ttl: u32) -> CertCacheEntry {
+        CertCacheEntry::new(
+            host.to_string(),
+            "ec-prime256v1".to_string(),
+            ttl,
+            format!("cert of {host}").into_bytes(),
+            format!("key of {host}").into_bytes(),
+        )
+    }
+
+    #[test]
+    fn parse_meta() {
+        let meta =
+            CacheMeta::parse("host=www.example.net\nkey_type=rsa2048\nexpire=100\n").unwrap();
+        assert_eq!(meta.host, "www.example.net");
+        assert_eq!(meta.key_type, "rsa2048");
+        assert_eq!(meta.expire, 100);
+
+        let entry = new_entry("www.example.net", 60);
+        let meta = CacheMeta::parse(&entry.encode_meta()).unwrap();
+        assert_eq!(meta.host, entry.host);
+        assert_eq!(meta.key_type, entry.key_type);
+        assert_eq!(meta.expire, entry.expire);
+
+        assert!(CacheMeta::parse("key_type=rsa2048\nexpire=100").is_err());
+        assert!(CacheMeta::parse("host=a.net\nexpire=100").is_err());
+        assert!(CacheMeta::parse("host=a.net\nkey_type=rsa2048\nexpire=x").is_err());
+        assert!(CacheMeta::parse("host=a.net\nkey_type=rsa2048\nfoo=1").is_err());
+        assert!(CacheMeta::parse("host a.net").is_err());
+    }
+
+    #[test]
+    fn file_stem() {
+        assert_eq!(encode_file_stem("www.example.net"), "www.example.net");
+        assert_eq!(encode_file_stem("a_b-c"), "a_b-c");
+        assert_eq!(encode_file_stem("a/b"), "a%2fb");
+        assert_eq!(encode_file_stem("[::1]"), "%5b%3a%3a1%5d");
+        assert_eq!(encode_file_stem("例"), "%e4%be%8b");
+        assert_eq!(encode_file_stem("."), "%2e");
+        assert_eq!(encode_file_stem(".."), "%2e.");
+        assert_eq!(encode_file_stem(".hidden"), "%2ehidden");
+    }
+
+    #[test]
+    fn put_get() {
+        let dir = test_dir("put-get");
+        let cache = CertDiskCache::open(&dir, 16).unwrap();
+        assert!(cache.is_empty());
+        assert!(cache.get("www.example.net", "ec-prime256v1").is_none());
+
+        let entry = new_entry("www.example.net", 60);
+        cache.put(&entry).unwrap();
+        let cached = cache.get("www.example.net", "ec-prime256v1").unwrap();
+        assert_eq!(cached.cert_pem, entry.cert_pem);
+        assert_eq!(cached.key_pem, entry.key_pem);
+        assert_eq!(cached.expire, entry.expire);
+        assert!(cache.get("www.example.net", "rsa2048").is_none());
+
+        // expired entries are never stored
+        cache.put(&new_entry("expired.example.net", 0)).unwrap();
+        assert!(cache.get("expired.example.net", "ec-prime256v1").is_none());
+        assert_eq!(cache.len(), 1);
+
+        // reopen and load from disk
+        drop(cache);
+        let cache = CertDiskCache::open(&dir, 16).unwrap();
+        assert_eq!(cache.len(), 1);
+        let all = cache.load_all();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].host, "www.example.net");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn similar_hosts() {
+        let dir = test_dir("similar-hosts");
+        let cache = CertDiskCache::open(&dir, 16).unwrap();
+
+        let mimic_host = mimic_cache_name("a.example.com", &[0x01; 32]);
+        let other_mimic_host = mimic_cache_name("a.example.com", &[0x02; 32]);
+        let hosts = [
+            "a.example.com",
+            "a.example.net",
+            mimic_host.as_str(),
+            other_mimic_host.as_str(),
+        ];
+        for host in hosts {
+            cache.put(&new_entry(host, 60)).unwrap();
+        }
+        assert_eq!(cache.len(), 4);
+
+        let path = cache.entry_path("a.example.com", "ec-prime256v1", CERT_FILE_EXT);
+        assert_eq!(path.file_name().unwrap(), "a.example.com.crt");
+
+        for host in hosts {
+            let cached = cache.get(host, "ec-prime256v1").unwrap();
+            assert_eq!(cached.cert_pem, format!("cert of {host}").into_bytes());
+            assert_eq!(cached.key_pem, format!("key of {host}").into_bytes());
+        }
+
+        // removing one entry should not affect the others
+        cache.remove_files("a.example.net", "ec-prime256v1");
+        for host in [
+            "a.example.com",
+            mimic_host.as_str(),
+            other_mimic_host.as_str(),
+        ] {
+            for ext in [META_FILE_EXT, CERT_FILE_EXT, KEY_FILE_EXT] {
+                assert!(cache.entry_path(host, "ec-prime256v1", ext).exists());
+            }
+        }
+
+        // reload from disk
+        drop(cache);
+        let cache = CertDiskCache::open(&dir, 16).unwrap();
+        assert_eq!(cache.len(), 3);
+        let cached = cache.get(&mimic_host, "ec-prime256v1").unwrap();
+        assert_eq!(
+            cached.cert_pem,
+            format!("cert of {mimic_host}").into_bytes()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_mode() {
+        let dir = test_dir("mode");
+        let cache = CertDiskCache::open(&dir, 16).unwrap();
+        cache.put(&new_entry("www.example.net", 60)).unwrap();
+
+        let mode = |p: &Path| fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), DIR_MODE);
+        assert_eq!(mode(&dir.join("ec-prime256v1")), DIR_MODE);
+        let key_path = cache.entry_path("www.example.net", "ec-prime256v1", KEY_FILE_EXT);
+        assert_eq!(mode(&key_path), KEY_FILE_MODE);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn evict() {
+        let dir = test_dir("evict");
+        let cache = CertDiskCache::open(&dir, 10).unwrap();
+        for i in 0..10 {
+            cache
+                .put(&new_entry(&format!("h{i}.example.net"), 100 + i))
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 10);
+
+        // the ones that will expire first should be removed
+        cache.put(&new_entry("new.example.net", 10)).unwrap();
+        assert_eq!(cache.len(), 9);
+        assert!(cache.get("new.example.net", "ec-prime256v1").is_some());
+        assert!(cache.get("h0.example.net", "ec-prime256v1").is_none());
+        assert!(cache.get("h1.example.net", "ec-prime256v1").is_none());
+        assert!(cache.get("h9.example.net", "ec-prime256v1").is_some());
+        let key_path = cache.entry_path("h0.example.net", "ec-prime256v1", KEY_FILE_EXT);
+        assert!(!key_path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
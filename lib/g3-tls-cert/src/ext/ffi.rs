@@ -15,7 +15,7 @@
  */
 
 use libc::{c_int, c_uchar, c_uint};
-use openssl_sys::RSA;
+use openssl_sys::{RSA, X509};
 
 extern "C" {
 
@@ -27,4 +27,8 @@ extern "C" {
         siglen: *mut c_uint,
         rsa: *mut RSA,
     ) -> c_int;
+
+    pub fn X509_get_key_usage(x: *mut X509) -> u32;
+
+    pub fn X509_get_extended_key_usage(x: *mut X509) -> u32;
 }
@@ -19,6 +19,9 @@ mod ffi;
 mod x509_builder;
 pub use x509_builder::X509BuilderExt;
 
+mod x509;
+pub use x509::{X509Ext, X509ExtKeyUsage, X509KeyUsage};
+
 mod rsa;
 pub use rsa::RsaExt;
 
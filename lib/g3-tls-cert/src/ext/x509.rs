@@ -0,0 +1,254 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::anyhow;
+use openssl::foreign_types::ForeignTypeRef;
+use openssl::x509::extension::{ExtendedKeyUsage, KeyUsage};
+use openssl::x509::{X509Extension, X509Ref};
+
+use super::ffi;
+
+/// The KeyUsage bits, see KU_* in openssl/x509v3.h
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct X509KeyUsage(u32);
+
+impl X509KeyUsage {
+    const DIGITAL_SIGNATURE: u32 = 0x0080;
+    const NON_REPUDIATION: u32 = 0x0040;
+    const KEY_ENCIPHERMENT: u32 = 0x0020;
+    const DATA_ENCIPHERMENT: u32 = 0x0010;
+    const KEY_AGREEMENT: u32 = 0x0008;
+    const ENCIPHER_ONLY: u32 = 0x0001;
+    const DECIPHER_ONLY: u32 = 0x8000;
+
+    /// Build the extension for a leaf certificate, CA only bits will be dropped
+    pub fn to_leaf_extension(&self) -> anyhow::Result<X509Extension> {
+        let mut builder = KeyUsage::new();
+        builder.critical();
+        if self.0 & Self::DIGITAL_SIGNATURE != 0 {
+            builder.digital_signature();
+        }
+        if self.0 & Self::NON_REPUDIATION != 0 {
+            builder.non_repudiation();
+        }
+        if self.0 & Self::KEY_ENCIPHERMENT != 0 {
+            builder.key_encipherment();
+        }
+        if self.0 & Self::DATA_ENCIPHERMENT != 0 {
+            builder.data_encipherment();
+        }
+        if self.0 & Self::KEY_AGREEMENT != 0 {
+            builder.key_agreement();
+        }
+        if self.0 & Self::ENCIPHER_ONLY != 0 {
+            builder.encipher_only();
+        }
+        if self.0 & Self::DECIPHER_ONLY != 0 {
+            builder.decipher_only();
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow!("failed to build KeyUsage extension: {e}"))
+    }
+}
+
+/// The ExtendedKeyUsage bits, see XKU_* in openssl/x509v3.h
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct X509ExtKeyUsage(u32);
+
+impl X509ExtKeyUsage {
+    const SSL_SERVER: u32 = 0x1;
+    const SSL_CLIENT: u32 = 0x2;
+    const SMIME: u32 = 0x4;
+    const CODE_SIGN: u32 = 0x8;
+    const TIMESTAMP: u32 = 0x40;
+
+    pub fn to_extension(&self) -> anyhow::Result<X509Extension> {
+        let mut builder = ExtendedKeyUsage::new();
+        if self.0 & Self::SSL_SERVER != 0 {
+            builder.server_auth();
+        }
+        if self.0 & Self::SSL_CLIENT != 0 {
+            builder.client_auth();
+        }
+        if self.0 & Self::SMIME != 0 {
+            builder.email_protection();
+        }
+        if self.0 & Self::CODE_SIGN != 0 {
+            builder.code_signing();
+        }
+        if self.0 & Self::TIMESTAMP != 0 {
+            builder.time_stamping();
+        }
+        builder
+            .build()
+            .map_err(|e| anyhow!("failed to build ExtendedKeyUsage extension: {e}"))
+    }
+}
+
+pub trait X509Ext {
+    fn key_usage(&self) -> Option<X509KeyUsage>;
+    fn ext_key_usage(&self) -> Option<X509ExtKeyUsage>;
+}
+
+impl X509Ext for X509Ref {
+    fn key_usage(&self) -> Option<X509KeyUsage> {
+        // UINT32_MAX will be returned if there is no such extension
+        let v = unsafe { ffi::X509_get_key_usage(self.as_ptr()) };
+        if v == u32::MAX {
+            None
+        } else {
+            Some(X509KeyUsage(v))
+        }
+    }
+
+    fn ext_key_usage(&self) -> Option<X509ExtKeyUsage> {
+        // UINT32_MAX will be returned if there is no such extension
+        let v = unsafe { ffi::X509_get_extended_key_usage(self.as_ptr()) };
+        if v == u32::MAX {
+            None
+        } else {
+            Some(X509ExtKeyUsage(v))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::x509::X509;
+
+    const KU_KEY_CERT_SIGN: u32 = 0x0004;
+    const KU_CRL_SIGN: u32 = 0x0002;
+
+    fn build_cert(extensions: Vec<X509Extension>) -> X509 {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let pkey = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        for ext in extensions {
+            builder.append_extension(ext).unwrap();
+        }
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn no_extensions() {
+        let cert = build_cert(Vec::new());
+        assert!(cert.key_usage().is_none());
+        assert!(cert.ext_key_usage().is_none());
+    }
+
+    #[test]
+    fn key_usage_bits() {
+        let ku = KeyUsage::new()
+            .critical()
+            .digital_signature()
+            .non_repudiation()
+            .key_encipherment()
+            .data_encipherment()
+            .key_agreement()
+            .encipher_only()
+            .build()
+            .unwrap();
+        let cert = build_cert(vec![ku]);
+        let ku = cert.key_usage().unwrap();
+        assert_eq!(
+            ku.0,
+            X509KeyUsage::DIGITAL_SIGNATURE
+                | X509KeyUsage::NON_REPUDIATION
+                | X509KeyUsage::KEY_ENCIPHERMENT
+                | X509KeyUsage::DATA_ENCIPHERMENT
+                | X509KeyUsage::KEY_AGREEMENT
+                | X509KeyUsage::ENCIPHER_ONLY
+        );
+
+        let copied = build_cert(vec![ku.to_leaf_extension().unwrap()]);
+        assert_eq!(copied.key_usage().unwrap().0, ku.0);
+
+        let ku = KeyUsage::new()
+            .key_agreement()
+            .decipher_only()
+            .build()
+            .unwrap();
+        let cert = build_cert(vec![ku]);
+        let ku = cert.key_usage().unwrap();
+        assert_eq!(
+            ku.0,
+            X509KeyUsage::KEY_AGREEMENT | X509KeyUsage::DECIPHER_ONLY
+        );
+        let copied = build_cert(vec![ku.to_leaf_extension().unwrap()]);
+        assert_eq!(copied.key_usage().unwrap().0, ku.0);
+    }
+
+    #[test]
+    fn key_usage_drop_ca_bits() {
+        let ku = KeyUsage::new()
+            .critical()
+            .digital_signature()
+            .key_cert_sign()
+            .crl_sign()
+            .build()
+            .unwrap();
+        let cert = build_cert(vec![ku]);
+        let ku = cert.key_usage().unwrap();
+        assert_eq!(
+            ku.0,
+            X509KeyUsage::DIGITAL_SIGNATURE | KU_KEY_CERT_SIGN | KU_CRL_SIGN
+        );
+
+        let copied = build_cert(vec![ku.to_leaf_extension().unwrap()]);
+        assert_eq!(
+            copied.key_usage().unwrap().0,
+            X509KeyUsage::DIGITAL_SIGNATURE
+        );
+    }
+
+    #[test]
+    fn ext_key_usage_bits() {
+        let eku = ExtendedKeyUsage::new()
+            .server_auth()
+            .client_auth()
+            .email_protection()
+            .code_signing()
+            .time_stamping()
+            .build()
+            .unwrap();
+        let cert = build_cert(vec![eku]);
+        let eku = cert.ext_key_usage().unwrap();
+        assert_eq!(
+            eku.0,
+            X509ExtKeyUsage::SSL_SERVER
+                | X509ExtKeyUsage::SSL_CLIENT
+                | X509ExtKeyUsage::SMIME
+                | X509ExtKeyUsage::CODE_SIGN
+                | X509ExtKeyUsage::TIMESTAMP
+        );
+
+        let copied = build_cert(vec![eku.to_extension().unwrap()]);
+        assert_eq!(copied.ext_key_usage().unwrap().0, eku.0);
+
+        let eku = ExtendedKeyUsage::new().server_auth().build().unwrap();
+        let cert = build_cert(vec![eku]);
+        assert_eq!(cert.ext_key_usage().unwrap().0, X509ExtKeyUsage::SSL_SERVER);
+    }
+}
@@ -18,4 +18,6 @@ pub mod agent;
 
 pub mod builder;
 
+pub mod cache;
+
 pub mod ext;
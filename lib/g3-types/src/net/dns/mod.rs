@@ -15,7 +15,9 @@
  */
 
 mod encryption;
+mod nat64;
 
 #[cfg(feature = "rustls")]
 pub use encryption::DnsEncryptionConfigBuilder;
 pub use encryption::DnsEncryptionProtocol;
+pub use nat64::Nat64Prefix;
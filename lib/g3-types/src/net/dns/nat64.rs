@@ -0,0 +1,161 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+
+/// NAT64 prefix, with IPv4 embedding as described in rfc6052
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct Nat64Prefix {
+    prefix: Ipv6Addr,
+    prefix_len: u8,
+}
+
+impl Default for Nat64Prefix {
+    fn default() -> Self {
+        Nat64Prefix::WELL_KNOWN
+    }
+}
+
+impl Nat64Prefix {
+    /// the well-known prefix 64:ff9b::/96
+    pub const WELL_KNOWN: Nat64Prefix = Nat64Prefix {
+        prefix: Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0, 0),
+        prefix_len: 96,
+    };
+
+    pub fn new(prefix: Ipv6Addr, prefix_len: u8) -> anyhow::Result<Self> {
+        if !matches!(prefix_len, 32 | 40 | 48 | 56 | 64 | 96) {
+            return Err(anyhow!("unsupported NAT64 prefix length {prefix_len}"));
+        }
+
+        // clear all bits after the prefix
+        let mut octets = prefix.octets();
+        octets[(prefix_len / 8) as usize..].fill(0);
+        Ok(Nat64Prefix {
+            prefix: Ipv6Addr::from(octets),
+            prefix_len,
+        })
+    }
+
+    #[inline]
+    pub fn prefix(&self) -> Ipv6Addr {
+        self.prefix
+    }
+
+    #[inline]
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// embed the IPv4 address into the IPv6 prefix, bits 64 to 71 will be skipped
+    pub fn embed(&self, ip4: Ipv4Addr) -> Ipv6Addr {
+        let mut octets = self.prefix.octets();
+        let mut pos = (self.prefix_len / 8) as usize;
+        for b in ip4.octets() {
+            if pos == 8 {
+                pos += 1;
+            }
+            octets[pos] = b;
+            pos += 1;
+        }
+        Ipv6Addr::from(octets)
+    }
+
+    /// extract the embedded IPv4 address if the IPv6 address is within this prefix
+    pub fn extract(&self, ip6: Ipv6Addr) -> Option<Ipv4Addr> {
+        let octets = ip6.octets();
+        let mut pos = (self.prefix_len / 8) as usize;
+        if octets[..pos] != self.prefix.octets()[..pos] {
+            return None;
+        }
+        let mut ip4 = [0u8; 4];
+        for b in ip4.iter_mut() {
+            if pos == 8 {
+                pos += 1;
+            }
+            *b = octets[pos];
+            pos += 1;
+        }
+        Some(Ipv4Addr::from(ip4))
+    }
+}
+
+impl FromStr for Nat64Prefix {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, len) = s.split_once('/').unwrap_or((s, "96"));
+        let prefix = Ipv6Addr::from_str(prefix)
+            .map_err(|e| anyhow!("invalid IPv6 prefix address {prefix}: {e}"))?;
+        let len =
+            u8::from_str(len).map_err(|e| anyhow!("invalid IPv6 prefix length {len}: {e}"))?;
+        Nat64Prefix::new(prefix, len)
+    }
+}
+
+impl fmt::Display for Nat64Prefix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.prefix, self.prefix_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embed_rfc6052() {
+        let ip4 = Ipv4Addr::new(192, 0, 2, 33);
+
+        let cases = [
+            ("2001:db8::/32", "2001:db8:c000:221::"),
+            ("2001:db8:100::/40", "2001:db8:1c0:2:21::"),
+            ("2001:db8:122::/48", "2001:db8:122:c000:2:2100::"),
+            ("2001:db8:122:300::/56", "2001:db8:122:3c0:0:221::"),
+            ("2001:db8:122:344::/64", "2001:db8:122:344:c0:2:2100:0"),
+            ("2001:db8:122:344::/96", "2001:db8:122:344::192.0.2.33"),
+            ("64:ff9b::/96", "64:ff9b::192.0.2.33"),
+        ];
+        for (prefix, expected) in cases {
+            let prefix = Nat64Prefix::from_str(prefix).unwrap();
+            let ip6 = Ipv6Addr::from_str(expected).unwrap();
+            assert_eq!(prefix.embed(ip4), ip6);
+            assert_eq!(prefix.extract(ip6), Some(ip4));
+        }
+    }
+
+    #[test]
+    fn extract_outside() {
+        let prefix = Nat64Prefix::WELL_KNOWN;
+        assert!(prefix
+            .extract(Ipv6Addr::from_str("2001:db8::1").unwrap())
+            .is_none());
+        assert!(prefix.extract(Ipv6Addr::LOCALHOST).is_none());
+    }
+
+    #[test]
+    fn parse() {
+        let prefix = Nat64Prefix::from_str("64:ff9b::").unwrap();
+        assert_eq!(prefix, Nat64Prefix::WELL_KNOWN);
+        assert_eq!(prefix.to_string(), "64:ff9b::/96");
+
+        assert!(Nat64Prefix::from_str("64:ff9b::/80").is_err());
+        assert!(Nat64Prefix::from_str("192.0.2.0/96").is_err());
+    }
+}
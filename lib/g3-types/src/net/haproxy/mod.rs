@@ -25,6 +25,7 @@ mod v2;
 
 use v1::ProxyProtocolV1Encoder;
 use v2::ProxyProtocolV2Encoder;
+pub use v2::{ProxyProtocolV2SslTlv, ProxyProtocolV2Tlvs};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ProxyProtocolVersion {
@@ -48,6 +49,8 @@ impl FromStr for ProxyProtocolVersion {
 pub enum ProxyProtocolEncodeError {
     #[error("address family not match")]
     AddressFamilyNotMatch,
+    #[error("too long tlv value")]
+    TooLongTlvValue,
 }
 
 pub enum ProxyProtocolEncoder {
@@ -73,4 +76,17 @@ impl ProxyProtocolEncoder {
             ProxyProtocolEncoder::V2(v2) => v2.encode_tcp(client_addr, server_addr),
         }
     }
+
+    /// the tlvs will be ignored for PROXY protocol v1
+    pub fn encode_tcp_with_tlvs(
+        &mut self,
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        tlvs: &ProxyProtocolV2Tlvs,
+    ) -> Result<&[u8], ProxyProtocolEncodeError> {
+        match self {
+            ProxyProtocolEncoder::V1(v1) => v1.encode_tcp(client_addr, server_addr),
+            ProxyProtocolEncoder::V2(v2) => v2.encode_tcp_with_tlvs(client_addr, server_addr, tlvs),
+        }
+    }
 }
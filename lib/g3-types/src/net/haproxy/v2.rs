@@ -41,15 +41,29 @@ const _PROTO_DGRAM: u8 = 0x02;
 const BYTE14_TCP4: u8 = AF_INET | PROTO_STREAM;
 const BYTE14_TCP6: u8 = AF_INET6 | PROTO_STREAM;
 
+const PP2_TYPE_ALPN: u8 = 0x01;
+const PP2_TYPE_AUTHORITY: u8 = 0x02;
+const PP2_TYPE_UNIQUE_ID: u8 = 0x05;
+const PP2_TYPE_SSL: u8 = 0x20;
+const PP2_SUBTYPE_SSL_VERSION: u8 = 0x21;
+const PP2_SUBTYPE_SSL_CN: u8 = 0x22;
+const PP2_SUBTYPE_SSL_CIPHER: u8 = 0x23;
+
+const PP2_CLIENT_SSL: u8 = 0x01;
+const PP2_CLIENT_CERT_CONN: u8 = 0x02;
+const PP2_CLIENT_CERT_SESS: u8 = 0x04;
+
+const PP2_UNIQUE_ID_MAX_LEN: usize = 128;
+
 // TODO use concat_bytes to generate header after it's stabilization
 // const V2_HEADER_TCP4: &[u8] = concat_bytes!(V2_MAGIC_HEADER, &[BYTE_13_PROXY, BYTE14_TCP4, 0x00, 12]);
 // const V2_HEADER_TCP6: &[u8] = concat_bytes!(V2_MAGIC_HEADER, &[BYTE_13_PROXY, BYTE14_TCP6, 0x00, 36]);
 
-pub struct ProxyProtocolV2Encoder([u8; V2_BUF_CAP]);
+pub struct ProxyProtocolV2Encoder(Vec<u8>);
 
 impl ProxyProtocolV2Encoder {
     pub fn new() -> Self {
-        ProxyProtocolV2Encoder([0u8; V2_BUF_CAP])
+        ProxyProtocolV2Encoder(Vec::with_capacity(V2_BUF_CAP))
     }
 
     pub fn encode_tcp(
@@ -57,27 +71,122 @@ impl ProxyProtocolV2Encoder {
         client_addr: SocketAddr,
         server_addr: SocketAddr,
     ) -> Result<&[u8], ProxyProtocolEncodeError> {
-        self.0[..12].copy_from_slice(V2_MAGIC_HEADER);
+        self.encode_tcp_with_tlvs(client_addr, server_addr, &ProxyProtocolV2Tlvs::default())
+    }
+
+    pub fn encode_tcp_with_tlvs(
+        &mut self,
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        tlvs: &ProxyProtocolV2Tlvs,
+    ) -> Result<&[u8], ProxyProtocolEncodeError> {
+        self.0.clear();
+        self.0.extend_from_slice(V2_MAGIC_HEADER);
 
         match (client_addr, server_addr) {
             (SocketAddr::V4(c4), SocketAddr::V4(s4)) => {
-                self.0[12..16].copy_from_slice(&[BYTE_13_PROXY, BYTE14_TCP4, 0x00, 12]);
-                self.0[16..20].copy_from_slice(&c4.ip().octets());
-                self.0[20..24].copy_from_slice(&s4.ip().octets());
-                self.0[24..26].copy_from_slice(&c4.port().to_be_bytes());
-                self.0[26..28].copy_from_slice(&s4.port().to_be_bytes());
-                Ok(&self.0[..28])
+                let len = u16::try_from(12 + tlvs.0.len())
+                    .map_err(|_| ProxyProtocolEncodeError::TooLongTlvValue)?;
+                self.0.extend_from_slice(&[BYTE_13_PROXY, BYTE14_TCP4]);
+                self.0.extend_from_slice(&len.to_be_bytes());
+                self.0.extend_from_slice(&c4.ip().octets());
+                self.0.extend_from_slice(&s4.ip().octets());
+                self.0.extend_from_slice(&c4.port().to_be_bytes());
+                self.0.extend_from_slice(&s4.port().to_be_bytes());
             }
             (SocketAddr::V6(c6), SocketAddr::V6(s6)) => {
-                self.0[12..16].copy_from_slice(&[BYTE_13_PROXY, BYTE14_TCP6, 0x00, 36]);
-                self.0[16..32].copy_from_slice(&c6.ip().octets());
-                self.0[32..48].copy_from_slice(&s6.ip().octets());
-                self.0[48..50].copy_from_slice(&c6.port().to_be_bytes());
-                self.0[50..52].copy_from_slice(&s6.port().to_be_bytes());
-                Ok(&self.0[..52])
+                let len = u16::try_from(36 + tlvs.0.len())
+                    .map_err(|_| ProxyProtocolEncodeError::TooLongTlvValue)?;
+                self.0.extend_from_slice(&[BYTE_13_PROXY, BYTE14_TCP6]);
+                self.0.extend_from_slice(&len.to_be_bytes());
+                self.0.extend_from_slice(&c6.ip().octets());
+                self.0.extend_from_slice(&s6.ip().octets());
+                self.0.extend_from_slice(&c6.port().to_be_bytes());
+                self.0.extend_from_slice(&s6.port().to_be_bytes());
             }
-            _ => Err(ProxyProtocolEncodeError::AddressFamilyNotMatch),
+            _ => return Err(ProxyProtocolEncodeError::AddressFamilyNotMatch),
+        }
+
+        self.0.extend_from_slice(&tlvs.0);
+        Ok(self.0.as_slice())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ProxyProtocolV2SslTlv<'a> {
+    pub client_cert_conn: bool,
+    pub client_cert_sess: bool,
+    pub client_cert_verified: bool,
+    pub version: Option<&'a str>,
+    pub cipher: Option<&'a str>,
+    pub client_cert_cn: Option<&'a str>,
+}
+
+#[derive(Debug, Default)]
+pub struct ProxyProtocolV2Tlvs(Vec<u8>);
+
+impl ProxyProtocolV2Tlvs {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn push(&mut self, tlv_type: u8, value: &[u8]) -> Result<(), ProxyProtocolEncodeError> {
+        let len =
+            u16::try_from(value.len()).map_err(|_| ProxyProtocolEncodeError::TooLongTlvValue)?;
+        self.0.push(tlv_type);
+        self.0.extend_from_slice(&len.to_be_bytes());
+        self.0.extend_from_slice(value);
+        Ok(())
+    }
+
+    pub fn push_alpn(&mut self, alpn: &[u8]) -> Result<(), ProxyProtocolEncodeError> {
+        self.push(PP2_TYPE_ALPN, alpn)
+    }
+
+    pub fn push_authority(&mut self, authority: &str) -> Result<(), ProxyProtocolEncodeError> {
+        self.push(PP2_TYPE_AUTHORITY, authority.as_bytes())
+    }
+
+    pub fn push_unique_id(&mut self, id: &[u8]) -> Result<(), ProxyProtocolEncodeError> {
+        if id.len() > PP2_UNIQUE_ID_MAX_LEN {
+            return Err(ProxyProtocolEncodeError::TooLongTlvValue);
+        }
+        self.push(PP2_TYPE_UNIQUE_ID, id)
+    }
+
+    pub fn push_ssl(
+        &mut self,
+        ssl: &ProxyProtocolV2SslTlv,
+    ) -> Result<(), ProxyProtocolEncodeError> {
+        let mut client = PP2_CLIENT_SSL;
+        if ssl.client_cert_conn {
+            client |= PP2_CLIENT_CERT_CONN;
+        }
+        if ssl.client_cert_sess {
+            client |= PP2_CLIENT_CERT_SESS;
         }
+        // zero only if the client presented a certificate and it was successfully verified
+        let verify: u32 =
+            if (ssl.client_cert_conn || ssl.client_cert_sess) && ssl.client_cert_verified {
+                0
+            } else {
+                1
+            };
+
+        let mut sub_tlvs = ProxyProtocolV2Tlvs::default();
+        sub_tlvs.0.push(client);
+        sub_tlvs.0.extend_from_slice(&verify.to_be_bytes());
+        if let Some(version) = ssl.version {
+            sub_tlvs.push(PP2_SUBTYPE_SSL_VERSION, version.as_bytes())?;
+        }
+        if let Some(cn) = ssl.client_cert_cn {
+            sub_tlvs.push(PP2_SUBTYPE_SSL_CN, cn.as_bytes())?;
+        }
+        if let Some(cipher) = ssl.cipher {
+            sub_tlvs.push(PP2_SUBTYPE_SSL_CIPHER, cipher.as_bytes())?;
+        }
+        self.push(PP2_TYPE_SSL, &sub_tlvs.0)
     }
 }
 
@@ -125,4 +234,39 @@ mod tests {
               \xDC\x04\x01\xBB"
         );
     }
+
+    #[test]
+    fn t_tcp4_tlv() {
+        let client = SocketAddr::from_str("192.168.0.1:56324").unwrap();
+        let server = SocketAddr::from_str("192.168.0.11:443").unwrap();
+
+        let mut tlvs = ProxyProtocolV2Tlvs::default();
+        tlvs.push_alpn(b"h2").unwrap();
+        tlvs.push_authority("a.cn").unwrap();
+        tlvs.push_ssl(&ProxyProtocolV2SslTlv {
+            client_cert_conn: true,
+            client_cert_sess: false,
+            client_cert_verified: true,
+            version: Some("TLSv1.3"),
+            cipher: None,
+            client_cert_cn: Some("c"),
+        })
+        .unwrap();
+
+        let mut encoder = ProxyProtocolV2Encoder::new();
+        let encoded = encoder.encode_tcp_with_tlvs(client, server, &tlvs).unwrap();
+        assert_eq!(
+            encoded,
+            b"\x0d\x0a\x0d\x0a\x00\x0d\x0a\x51\x55\x49\x54\x0a\
+              \x21\x11\x00\x2E\
+              \xC0\xA8\x00\x01\
+              \xC0\xA8\x00\x0B\
+              \xDC\x04\x01\xBB\
+              \x01\x00\x02h2\
+              \x02\x00\x04a.cn\
+              \x20\x00\x13\x03\x00\x00\x00\x00\
+              \x21\x00\x07TLSv1.3\
+              \x22\x00\x01c"
+        );
+    }
 }
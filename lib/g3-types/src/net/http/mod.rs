@@ -18,10 +18,14 @@ mod auth;
 mod capability;
 mod header;
 mod keepalive;
+mod trace_context;
 mod upgrade;
 
 pub use auth::{HttpAuth, HttpBasicAuth};
 pub use capability::*;
 pub use header::*;
 pub use keepalive::HttpKeepAliveConfig;
+pub use trace_context::{
+    HttpTraceContext, HttpTraceContextConfig, HTTP_HEADER_TRACEPARENT, HTTP_HEADER_TRACESTATE,
+};
 pub use upgrade::{HttpUpgradeToken, HttpUpgradeTokenParseError};
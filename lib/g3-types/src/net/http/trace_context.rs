@@ -0,0 +1,422 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use http::{HeaderMap, HeaderName, HeaderValue};
+use rand::Rng;
+
+use super::{HttpHeaderMap, HttpHeaderValue};
+
+pub const HTTP_HEADER_TRACEPARENT: HeaderName = HeaderName::from_static("traceparent");
+pub const HTTP_HEADER_TRACESTATE: HeaderName = HeaderName::from_static("tracestate");
+
+const TRACE_FLAG_SAMPLED: u8 = 0x01;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HttpTraceContextConfig {
+    create: bool,
+    inject: bool,
+}
+
+impl HttpTraceContextConfig {
+    /// create a new trace if no valid traceparent header found in the request
+    pub fn set_create(&mut self, create: bool) {
+        self.create = create;
+    }
+
+    #[inline]
+    pub fn create(&self) -> bool {
+        self.create
+    }
+
+    /// send the span id of the current task as the parent id to upstream
+    pub fn set_inject(&mut self, inject: bool) {
+        self.inject = inject;
+    }
+
+    #[inline]
+    pub fn inject(&self) -> bool {
+        self.inject
+    }
+}
+
+/// The W3C trace context for a single http request.
+///
+/// See <https://www.w3.org/TR/trace-context/>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpTraceContext {
+    trace_id: [u8; 16],
+    parent_id: Option<[u8; 8]>,
+    span_id: [u8; 8],
+    flags: u8,
+    trace_state: Option<String>,
+}
+
+impl HttpTraceContext {
+    /// start a new trace, with the current task as the root span
+    pub fn new_root() -> Self {
+        let mut trace_id = [0u8; 16];
+        fill_random_id(&mut trace_id);
+        HttpTraceContext {
+            trace_id,
+            parent_id: None,
+            span_id: Self::new_span_id(),
+            flags: TRACE_FLAG_SAMPLED,
+            trace_state: None,
+        }
+    }
+
+    pub fn new_span_id() -> [u8; 8] {
+        let mut span_id = [0u8; 8];
+        fill_random_id(&mut span_id);
+        span_id
+    }
+
+    /// parse the traceparent and tracestate header values,
+    /// the returned context will have a new span id as a child of the one in traceparent
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let traceparent = traceparent.trim();
+        let b = traceparent.as_bytes();
+        if b.len() < 55 {
+            return None;
+        }
+        let version = decode_hex_byte(&b[0..2])?;
+        match version {
+            0xff => return None,
+            0x00 => {
+                if b.len() != 55 {
+                    return None;
+                }
+            }
+            _ => {
+                // future versions may append more fields
+                if b.len() > 55 && b[55] != b'-' {
+                    return None;
+                }
+            }
+        }
+        if b[2] != b'-' || b[35] != b'-' || b[52] != b'-' {
+            return None;
+        }
+
+        let mut trace_id = [0u8; 16];
+        decode_hex_id(&b[3..35], &mut trace_id)?;
+        let mut parent_id = [0u8; 8];
+        decode_hex_id(&b[36..52], &mut parent_id)?;
+        let flags = decode_hex_byte(&b[53..55])?;
+
+        let trace_state = tracestate
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_string());
+        Some(HttpTraceContext {
+            trace_id,
+            parent_id: Some(parent_id),
+            span_id: Self::new_span_id(),
+            flags,
+            trace_state,
+        })
+    }
+
+    pub fn from_headers(headers: &HttpHeaderMap) -> Option<Self> {
+        let mut traceparent_iter = headers.get_all(HTTP_HEADER_TRACEPARENT).iter();
+        let traceparent = traceparent_iter.next()?;
+        if traceparent_iter.next().is_some() {
+            // multiple traceparent headers is not allowed
+            return None;
+        }
+
+        let mut tracestate = String::new();
+        for v in headers.get_all(HTTP_HEADER_TRACESTATE) {
+            // multiple tracestate headers should be combined
+            if !tracestate.is_empty() {
+                tracestate.push(',');
+            }
+            tracestate.push_str(v.to_str());
+        }
+
+        HttpTraceContext::parse(traceparent.to_str(), Some(&tracestate))
+    }
+
+    /// the same as `from_headers`, but for the headers of HTTP/2 requests
+    pub fn from_h2_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut traceparent_iter = headers.get_all(HTTP_HEADER_TRACEPARENT).iter();
+        let traceparent = traceparent_iter.next()?;
+        if traceparent_iter.next().is_some() {
+            return None;
+        }
+        let traceparent = traceparent.to_str().ok()?;
+
+        let mut tracestate = String::new();
+        for v in headers.get_all(HTTP_HEADER_TRACESTATE) {
+            let Ok(v) = v.to_str() else {
+                continue;
+            };
+            if !tracestate.is_empty() {
+                tracestate.push(',');
+            }
+            tracestate.push_str(v);
+        }
+
+        HttpTraceContext::parse(traceparent, Some(&tracestate))
+    }
+
+    #[inline]
+    pub fn trace_id(&self) -> &[u8; 16] {
+        &self.trace_id
+    }
+
+    #[inline]
+    pub fn parent_id(&self) -> Option<&[u8; 8]> {
+        self.parent_id.as_ref()
+    }
+
+    #[inline]
+    pub fn span_id(&self) -> &[u8; 8] {
+        &self.span_id
+    }
+
+    #[inline]
+    pub fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    #[inline]
+    pub fn sampled(&self) -> bool {
+        self.flags & TRACE_FLAG_SAMPLED != 0
+    }
+
+    #[inline]
+    pub fn trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    pub fn trace_id_hex(&self) -> String {
+        encode_hex(&self.trace_id)
+    }
+
+    pub fn parent_id_hex(&self) -> Option<String> {
+        self.parent_id.as_ref().map(|id| encode_hex(id))
+    }
+
+    pub fn span_id_hex(&self) -> String {
+        encode_hex(&self.span_id)
+    }
+
+    /// the traceparent value with the current span as the parent
+    pub fn traceparent(&self) -> String {
+        let mut s = String::with_capacity(55);
+        s.push_str("00-");
+        s.push_str(&self.trace_id_hex());
+        s.push('-');
+        s.push_str(&self.span_id_hex());
+        let _ = write!(s, "-{:02x}", self.flags);
+        s
+    }
+
+    /// replace the trace context headers, so the upstream will see the current span as the parent
+    pub fn inject_headers(&self, headers: &mut HttpHeaderMap) {
+        let traceparent = unsafe { HttpHeaderValue::from_string_unchecked(self.traceparent()) };
+        headers.remove(HTTP_HEADER_TRACEPARENT);
+        headers.insert(HTTP_HEADER_TRACEPARENT, traceparent);
+        headers.remove(HTTP_HEADER_TRACESTATE);
+        if let Some(state) = &self.trace_state {
+            if let Ok(value) = HttpHeaderValue::from_str(state) {
+                headers.insert(HTTP_HEADER_TRACESTATE, value);
+            }
+        }
+    }
+
+    /// the same as `inject_headers`, but for the headers of HTTP/2 requests
+    pub fn inject_h2_headers(&self, headers: &mut HeaderMap) {
+        headers.remove(HTTP_HEADER_TRACEPARENT);
+        if let Ok(value) = HeaderValue::from_str(&self.traceparent()) {
+            headers.insert(HTTP_HEADER_TRACEPARENT, value);
+        }
+        headers.remove(HTTP_HEADER_TRACESTATE);
+        if let Some(state) = &self.trace_state {
+            if let Ok(value) = HeaderValue::from_str(state) {
+                headers.insert(HTTP_HEADER_TRACESTATE, value);
+            }
+        }
+    }
+}
+
+fn fill_random_id(id: &mut [u8]) {
+    let mut rng = rand::thread_rng();
+    loop {
+        rng.fill(id);
+        // all zero value is invalid
+        if id.iter().any(|b| *b != 0) {
+            break;
+        }
+    }
+}
+
+fn decode_hex_char(c: u8) -> Option<u8> {
+    // only lowercase hex chars are allowed
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        _ => None,
+    }
+}
+
+fn decode_hex_byte(b: &[u8]) -> Option<u8> {
+    let hi = decode_hex_char(b[0])?;
+    let lo = decode_hex_char(b[1])?;
+    Some((hi << 4) | lo)
+}
+
+fn decode_hex_id(b: &[u8], id: &mut [u8]) -> Option<()> {
+    for (i, v) in id.iter_mut().enumerate() {
+        *v = decode_hex_byte(&b[i * 2..i * 2 + 2])?;
+    }
+    if id.iter().all(|v| *v == 0) {
+        return None;
+    }
+    Some(())
+}
+
+fn encode_hex(id: &[u8]) -> String {
+    let mut s = String::with_capacity(id.len() * 2);
+    for b in id {
+        let _ = write!(s, "{b:02x}");
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_valid() {
+        let ctx = HttpTraceContext::parse(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            Some("congo=t61rcWkgMzE"),
+        )
+        .unwrap();
+        assert_eq!(ctx.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id_hex().unwrap(), "00f067aa0ba902b7");
+        assert_ne!(ctx.span_id(), ctx.parent_id().unwrap());
+        assert!(ctx.sampled());
+        assert_eq!(ctx.trace_state(), Some("congo=t61rcWkgMzE"));
+
+        let traceparent = ctx.traceparent();
+        assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(traceparent.ends_with("-01"));
+        assert_eq!(traceparent.len(), 55);
+    }
+
+    #[test]
+    fn parse_future_version() {
+        let ctx = HttpTraceContext::parse(
+            "cc-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-what-the-future",
+            None,
+        )
+        .unwrap();
+        assert!(!ctx.sampled());
+    }
+
+    #[test]
+    fn parse_invalid() {
+        // invalid version
+        assert!(HttpTraceContext::parse(
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        // all zero trace id
+        assert!(HttpTraceContext::parse(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        // all zero parent id
+        assert!(HttpTraceContext::parse(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            None
+        )
+        .is_none());
+        // uppercase
+        assert!(HttpTraceContext::parse(
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        // trailing data for version 00
+        assert!(HttpTraceContext::parse(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-00",
+            None
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn new_root() {
+        let ctx = HttpTraceContext::new_root();
+        assert!(ctx.parent_id().is_none());
+        assert!(ctx.sampled());
+        let parsed = HttpTraceContext::parse(&ctx.traceparent(), None).unwrap();
+        assert_eq!(parsed.trace_id(), ctx.trace_id());
+        assert_eq!(parsed.parent_id(), Some(ctx.span_id()));
+    }
+
+    #[test]
+    fn h2_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(HttpTraceContext::from_h2_headers(&headers).is_none());
+
+        headers.insert(
+            HTTP_HEADER_TRACEPARENT,
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+        headers.append(HTTP_HEADER_TRACESTATE, HeaderValue::from_static("a=1"));
+        headers.append(HTTP_HEADER_TRACESTATE, HeaderValue::from_static("b=2"));
+        let ctx = HttpTraceContext::from_h2_headers(&headers).unwrap();
+        assert_eq!(ctx.trace_id_hex(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(ctx.parent_id_hex().unwrap(), "00f067aa0ba902b7");
+        assert_eq!(ctx.trace_state(), Some("a=1,b=2"));
+
+        ctx.inject_h2_headers(&mut headers);
+        assert_eq!(
+            headers
+                .get(HTTP_HEADER_TRACEPARENT)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            ctx.traceparent()
+        );
+        assert_eq!(headers.get_all(HTTP_HEADER_TRACESTATE).iter().count(), 1);
+        assert_eq!(
+            headers
+                .get(HTTP_HEADER_TRACESTATE)
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "a=1,b=2"
+        );
+
+        headers.append(
+            HTTP_HEADER_TRACEPARENT,
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+        assert!(HttpTraceContext::from_h2_headers(&headers).is_none());
+    }
+}
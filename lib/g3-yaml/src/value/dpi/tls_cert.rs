@@ -14,6 +14,8 @@
  * limitations under the License.
  */
 
+use std::path::Path;
+
 use anyhow::{anyhow, Context};
 use yaml_rust::Yaml;
 
@@ -25,7 +27,10 @@ fn set_query_peer_addr(config: &mut CertAgentConfig, value: &Yaml) -> anyhow::Re
     Ok(())
 }
 
-pub fn as_tls_cert_agent_config(value: &Yaml) -> anyhow::Result<CertAgentConfig> {
+pub fn as_tls_cert_agent_config(
+    value: &Yaml,
+    lookup_dir: Option<&Path>,
+) -> anyhow::Result<CertAgentConfig> {
     match value {
         Yaml::Hash(map) => {
             let mut config = CertAgentConfig::default();
@@ -53,6 +58,19 @@ pub fn as_tls_cert_agent_config(value: &Yaml) -> anyhow::Result<CertAgentConfig>
                         .context(format!("invalid sockaddr str value for key {k}"))?;
                     Ok(())
                 }
+                "query_tcp_peer_addr" => {
+                    let addr = crate::value::as_env_sockaddr(v)
+                        .context(format!("invalid sockaddr str value for key {k}"))?;
+                    config.set_query_tcp_peer_addr(addr);
+                    Ok(())
+                }
+                #[cfg(unix)]
+                "query_unix_peer_path" => {
+                    let path = crate::value::as_absolute_path(v)
+                        .context(format!("invalid absolute path value for key {k}"))?;
+                    config.set_query_unix_peer_path(path);
+                    Ok(())
+                }
                 "query_socket_buffer" => {
                     let buf_config = crate::value::as_socket_buffer_config(v)
                         .context(format!("invalid socket buffer config value for key {k}"))?;
@@ -75,6 +93,32 @@ pub fn as_tls_cert_agent_config(value: &Yaml) -> anyhow::Result<CertAgentConfig>
                     config.set_maximum_cache_ttl(ttl);
                     Ok(())
                 }
+                "mimic_upstream_cert" => {
+                    let enable = crate::value::as_bool(v)?;
+                    config.set_mimic_upstream_cert(enable);
+                    Ok(())
+                }
+                "persist_cache_dir" | "persist_cache_directory" => {
+                    let dir = if let Some(lookup_dir) = lookup_dir {
+                        crate::value::as_dir_path(v, lookup_dir, true)
+                    } else {
+                        crate::value::as_absolute_path(v)
+                    }
+                    .context(format!("invalid directory path value for key {k}"))?;
+                    config.set_persist_cache_dir(dir);
+                    Ok(())
+                }
+                "persist_cache_max_entries" => {
+                    let count = crate::value::as_usize(v)?;
+                    config.set_persist_cache_max_entries(count);
+                    Ok(())
+                }
+                "persist_cache_ttl" => {
+                    let ttl = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_persist_cache_ttl(u32::try_from(ttl.as_secs()).unwrap_or(u32::MAX));
+                    Ok(())
+                }
                 _ => Err(anyhow!("invalid key {k}")),
             })?;
 
@@ -0,0 +1,72 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::{anyhow, Context};
+use yaml_rust::Yaml;
+
+use g3_filelog::FileLogConfig;
+
+pub fn as_file_log_config(value: &Yaml, program_name: &str) -> anyhow::Result<FileLogConfig> {
+    match value {
+        Yaml::Hash(map) => {
+            let mut config = FileLogConfig::with_program_name(program_name);
+
+            crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+                "dir" | "directory" => {
+                    let dir = crate::value::as_absolute_path(v)
+                        .context(format!("invalid absolute path value for key {k}"))?;
+                    config.set_dir(dir);
+                    Ok(())
+                }
+                "max_file_size" | "rotate_size" => {
+                    let size = crate::humanize::as_usize(v)
+                        .context(format!("invalid humanize usize value for key {k}"))?;
+                    config.set_max_file_size(size as u64);
+                    Ok(())
+                }
+                "rotate_interval" => {
+                    let interval = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_rotate_interval(interval);
+                    Ok(())
+                }
+                "max_file_count" | "max_files" => {
+                    let count = crate::value::as_usize(v)
+                        .context(format!("invalid usize value for key {k}"))?;
+                    config.set_max_file_count(count);
+                    Ok(())
+                }
+                "compress" | "gzip" => {
+                    let enable = crate::value::as_bool(v)
+                        .context(format!("invalid bool value for key {k}"))?;
+                    config.set_compress(enable);
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+
+            Ok(config)
+        }
+        Yaml::String(_) => {
+            let dir = crate::value::as_absolute_path(value)?;
+            Ok(FileLogConfig::new(dir))
+        }
+        Yaml::Null => Ok(FileLogConfig::with_program_name(program_name)),
+        _ => Err(anyhow!(
+            "yaml value type for 'FileLogConfig' should be 'map' or 'string'"
+        )),
+    }
+}
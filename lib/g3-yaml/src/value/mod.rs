@@ -60,6 +60,16 @@ mod fluentd;
 #[cfg(feature = "fluentd")]
 pub use fluentd::as_fluentd_client_config;
 
+#[cfg(feature = "filelog")]
+mod filelog;
+#[cfg(feature = "filelog")]
+pub use filelog::as_file_log_config;
+
+#[cfg(feature = "otlp")]
+mod otlp;
+#[cfg(feature = "otlp")]
+pub use otlp::as_otlp_log_client_config;
+
 #[cfg(feature = "statsd")]
 mod statsd;
 #[cfg(feature = "statsd")]
@@ -23,6 +23,7 @@ use yaml_rust::Yaml;
 
 use g3_types::net::{
     HttpForwardCapability, HttpForwardedHeaderType, HttpKeepAliveConfig, HttpServerId,
+    HttpTraceContextConfig,
 };
 
 pub fn as_http_keepalive_config(v: &Yaml) -> anyhow::Result<HttpKeepAliveConfig> {
@@ -58,6 +59,36 @@ pub fn as_http_keepalive_config(v: &Yaml) -> anyhow::Result<HttpKeepAliveConfig>
     Ok(config)
 }
 
+pub fn as_http_trace_context_config(v: &Yaml) -> anyhow::Result<HttpTraceContextConfig> {
+    let mut config = HttpTraceContextConfig::default();
+
+    match v {
+        Yaml::Hash(map) => {
+            crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+                "create" => {
+                    let create = crate::value::as_bool(v)?;
+                    config.set_create(create);
+                    Ok(())
+                }
+                "inject" => {
+                    let inject = crate::value::as_bool(v)?;
+                    config.set_inject(inject);
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+        }
+        _ => {
+            let enable =
+                crate::value::as_bool(v).context("invalid http trace context config value")?;
+            config.set_create(enable);
+            config.set_inject(enable);
+        }
+    }
+
+    Ok(config)
+}
+
 pub fn as_http_forwarded_header_type(value: &Yaml) -> anyhow::Result<HttpForwardedHeaderType> {
     match crate::value::as_bool(value) {
         Ok(true) => Ok(HttpForwardedHeaderType::default()),
@@ -32,8 +32,8 @@ mod ftp;
 mod dns;
 
 pub use base::{
-    as_domain, as_env_sockaddr, as_host, as_ipaddr, as_ipv4addr, as_ipv6addr, as_sockaddr,
-    as_upstream_addr, as_url, as_weighted_sockaddr, as_weighted_upstream_addr,
+    as_domain, as_env_sockaddr, as_host, as_ipaddr, as_ipv4addr, as_ipv6addr, as_nat64_prefix,
+    as_sockaddr, as_upstream_addr, as_url, as_weighted_sockaddr, as_weighted_upstream_addr,
 };
 pub use buf::as_socket_buffer_config;
 pub use haproxy::as_proxy_protocol_version;
@@ -52,6 +52,7 @@ pub use base::as_ip_network;
 pub use self::http::{
     as_http_forward_capability, as_http_forwarded_header_type, as_http_header_name,
     as_http_keepalive_config, as_http_path_and_query, as_http_server_id,
+    as_http_trace_context_config,
 };
 
 #[cfg(feature = "ftp-client")]
@@ -0,0 +1,184 @@
+/*
+ * Copyright 2024 ByteDance and/or its affiliates.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Context};
+use http::{HeaderName, HeaderValue};
+use yaml_rust::Yaml;
+
+use g3_otlp::{OtlpExportProtocol, OtlpLogClientConfig};
+
+fn as_otlp_export_protocol(value: &Yaml) -> anyhow::Result<OtlpExportProtocol> {
+    if let Yaml::String(s) = value {
+        OtlpExportProtocol::from_str(s).map_err(|_| anyhow!("invalid otlp export protocol {s}"))
+    } else {
+        Err(anyhow!(
+            "yaml value type for 'OtlpExportProtocol' should be 'string'"
+        ))
+    }
+}
+
+pub fn as_otlp_log_client_config(
+    value: &Yaml,
+    lookup_dir: Option<&Path>,
+) -> anyhow::Result<OtlpLogClientConfig> {
+    match value {
+        Yaml::Hash(map) => {
+            let mut config = OtlpLogClientConfig::default();
+            let mut tls_config = None;
+
+            crate::foreach_kv(map, |k, v| match crate::key::normalize(k).as_str() {
+                "protocol" => {
+                    let protocol = as_otlp_export_protocol(v)
+                        .context(format!("invalid otlp export protocol value for key {k}"))?;
+                    config.set_protocol(protocol);
+                    Ok(())
+                }
+                "address" | "addr" => {
+                    let addr = crate::value::as_env_sockaddr(v)?;
+                    config.set_server_addr(addr);
+                    Ok(())
+                }
+                "bind_ip" | "bind" => {
+                    let ip = crate::value::as_ipaddr(v)?;
+                    config.set_bind_ip(ip);
+                    Ok(())
+                }
+                "tcp_keepalive" => {
+                    let keepalive = crate::value::as_tcp_keepalive_config(v)
+                        .context(format!("invalid tcp keepalive config value for key {k}"))?;
+                    config.set_tcp_keepalive(keepalive);
+                    Ok(())
+                }
+                "tls_client" => {
+                    let builder = crate::value::as_rustls_client_config_builder(v, lookup_dir)
+                        .context(format!(
+                            "invalid rustls tls client config value for key {k}"
+                        ))?;
+                    tls_config = Some(builder);
+                    Ok(())
+                }
+                "tls_name" => {
+                    let tls_name = crate::value::as_rustls_server_name(v)
+                        .context(format!("invalid rustls server name value for key {k}"))?;
+                    config.set_tls_name(tls_name);
+                    Ok(())
+                }
+                "path" | "http_path" => {
+                    let path = crate::value::as_string(v)?;
+                    if !path.starts_with('/') {
+                        return Err(anyhow!("the http path should start with '/'"));
+                    }
+                    config.set_http_path(path);
+                    Ok(())
+                }
+                "headers" => {
+                    if let Yaml::Hash(map) = v {
+                        crate::foreach_kv(map, |k, v| {
+                            let name = HeaderName::from_str(k)
+                                .map_err(|e| anyhow!("invalid http header name {k}: {e}"))?;
+                            let value = crate::value::as_string(v)?;
+                            let value = HeaderValue::from_str(&value)
+                                .map_err(|e| anyhow!("invalid value for header {k}: {e}"))?;
+                            config.add_header(name, value);
+                            Ok(())
+                        })
+                    } else {
+                        Err(anyhow!("invalid map value for key {k}"))
+                    }
+                }
+                "hostname" => {
+                    let hostname = crate::value::as_string(v)?;
+                    config.set_hostname(hostname);
+                    Ok(())
+                }
+                "resource_attributes" => {
+                    if let Yaml::Hash(map) = v {
+                        crate::foreach_kv(map, |k, v| {
+                            let value = crate::value::as_string(v)
+                                .context(format!("invalid string value for key {k}"))?;
+                            config.add_resource_attribute(k.to_string(), value);
+                            Ok(())
+                        })
+                    } else {
+                        Err(anyhow!("invalid map value for key {k}"))
+                    }
+                }
+                "connect_timeout" => {
+                    let timeout = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_connect_timeout(timeout);
+                    Ok(())
+                }
+                "connect_delay" => {
+                    let delay = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_connect_delay(delay);
+                    Ok(())
+                }
+                "request_timeout" => {
+                    let timeout = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_request_timeout(timeout);
+                    Ok(())
+                }
+                "flush_interval" => {
+                    let interval = crate::humanize::as_duration(v)
+                        .context(format!("invalid humanize duration value for key {k}"))?;
+                    config.set_flush_interval(interval);
+                    Ok(())
+                }
+                "batch_size" => {
+                    let size = crate::value::as_usize(v)
+                        .context(format!("invalid usize value for key {k}"))?;
+                    config.set_batch_size(size);
+                    Ok(())
+                }
+                "retry_queue_len" => {
+                    let len = crate::value::as_usize(v)
+                        .context(format!("invalid usize value for key {k}"))?;
+                    config.set_retry_queue_len(len);
+                    Ok(())
+                }
+                _ => Err(anyhow!("invalid key {k}")),
+            })?;
+
+            // set tls at last as the alpn value depends on the protocol
+            if let Some(builder) = tls_config {
+                config
+                    .set_tls_client(builder)
+                    .context("failed to set tls client config")?;
+            }
+
+            Ok(config)
+        }
+        Yaml::String(_) => {
+            let addr = crate::value::as_env_sockaddr(value)?;
+            let mut config = OtlpLogClientConfig::default();
+            config.set_server_addr(addr);
+            Ok(config)
+        }
+        Yaml::Null => {
+            let config = OtlpLogClientConfig::default();
+            Ok(config)
+        }
+        _ => Err(anyhow!(
+            "yaml value type for 'OtlpLogClientConfig' should be 'map'"
+        )),
+    }
+}